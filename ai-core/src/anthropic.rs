@@ -0,0 +1,164 @@
+//! src/anthropic.rs
+//!
+//! The provider for Anthropic's Messages API. Chat completion requests are
+//! translated into Messages requests, and their responses back, so tools
+//! built on `ChatRequest` work with Claude models unchanged. Anthropic has
+//! no embeddings API.
+
+use crate::chat::{ChatRequest, ChatResponse, Choice, Message, ResponseFormat, Usage};
+use crate::error::{Error, Result};
+use crate::middleware::Middleware;
+use crate::provider::ChatProvider;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+/// The endpoint of the Messages API.
+const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
+
+/// The version of the Messages API requests are sent with.
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// The most tokens a reply may have. The Messages API requires a limit,
+/// where the Chat Completions API defaults to the model's.
+const MAX_TOKENS: u32 = 8192;
+
+/// A client for Anthropic's Messages API.
+#[derive(Clone)]
+pub struct Anthropic {
+    middleware: Middleware,
+    api_key: String,
+}
+
+impl Anthropic {
+    /// Creates a client authenticated with `api_key`.
+    pub fn new(api_key: String) -> Self {
+        Self {
+            middleware: Middleware::default(),
+            api_key,
+        }
+    }
+
+    /// Sends requests through `middleware`, with its retries and rate
+    /// limits, instead of the default one.
+    pub fn with_middleware(mut self, middleware: Middleware) -> Self {
+        self.middleware = middleware;
+        self
+    }
+}
+
+#[async_trait]
+impl ChatProvider for Anthropic {
+    /// Sends the request to the Messages API.
+    ///
+    /// System messages are joined into the system prompt. JSON mode has no
+    /// equivalent, so the reply is prefilled with the `{` that opens the
+    /// object, which the model then continues. A JSON schema is added to
+    /// the system prompt, as the API can't enforce it.
+    #[instrument(skip_all, fields(model = %request.model))]
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let json_mode = matches!(
+            request.response_format,
+            Some(ResponseFormat::JsonObject | ResponseFormat::JsonSchema { .. })
+        );
+        let mut system_parts: Vec<String> = request
+            .messages
+            .iter()
+            .filter(|message| message.role == "system")
+            .map(|message| message.content.clone())
+            .collect();
+        if let Some(ResponseFormat::JsonSchema { json_schema }) = &request.response_format {
+            system_parts.push(format!(
+                "Reply with a single JSON object that matches this JSON schema:\n{}",
+                json_schema.schema
+            ));
+        }
+        let system = system_parts.join("\n\n");
+        let mut messages: Vec<&Message> = request
+            .messages
+            .iter()
+            .filter(|message| message.role != "system")
+            .collect();
+        let prefill = Message::assistant("{");
+        if json_mode {
+            messages.push(&prefill);
+        }
+
+        let body = MessagesRequest {
+            model: &request.model,
+            max_tokens: MAX_TOKENS,
+            system: (!system.is_empty()).then_some(system),
+            messages,
+        };
+        let http_request = self
+            .middleware
+            .post(ANTHROPIC_API_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body);
+        let response: MessagesResponse = self.middleware.send(http_request).await?.json().await?;
+
+        let mut content: String = response
+            .content
+            .into_iter()
+            .filter_map(|block| block.text)
+            .collect();
+        if content.is_empty() && response.stop_reason.as_deref() != Some("max_tokens") {
+            return Err(Error::Api(
+                "API response did not contain any text.".to_string(),
+            ));
+        }
+        if json_mode {
+            content.insert(0, '{');
+        }
+        // `max_tokens` means the reply was cut off, as `length` does.
+        let finish_reason = response.stop_reason.map(|reason| match reason.as_str() {
+            "max_tokens" => "length".to_string(),
+            _ => "stop".to_string(),
+        });
+        let usage = Usage {
+            prompt_tokens: response.usage.input_tokens,
+            completion_tokens: response.usage.output_tokens,
+        };
+        self.middleware.record_usage(&request.model, Some(&usage));
+        Ok(ChatResponse {
+            choices: vec![Choice {
+                message: Message::assistant(content),
+                finish_reason,
+            }],
+            usage: Some(usage),
+        })
+    }
+}
+
+//========= API Data Structures =========//
+
+#[derive(Serialize, Debug)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<&'a Message>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    usage: MessagesUsage,
+}
+
+/// A block of the reply. Only text blocks have `text`.
+#[derive(Deserialize, Debug)]
+struct ContentBlock {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessagesUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
@@ -0,0 +1,121 @@
+//! src/azure.rs
+//!
+//! The provider for Azure OpenAI, which serves OpenAI models from a resource
+//! of its own. Requests and responses are those of the OpenAI API, but they
+//! are sent to a deployment of the resource rather than naming a model, and
+//! are authenticated with an `api-key` header.
+
+use crate::chat::{ChatRequest, ChatResponse};
+use crate::embeddings::{EmbeddingRequest, EmbeddingResponse};
+use crate::error::Result;
+use crate::middleware::Middleware;
+use crate::provider::{ChatProvider, EmbeddingProvider};
+use crate::stream::{self, TokenStream};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::instrument;
+
+/// The version of the Azure OpenAI API requests are sent with, unless
+/// another is configured.
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// A client for an Azure OpenAI resource. The model of each request is the
+/// name of the deployment it is sent to.
+#[derive(Clone)]
+pub struct AzureOpenAI {
+    middleware: Middleware,
+    /// The resource's endpoint, e.g. `https://my-resource.openai.azure.com`.
+    endpoint: String,
+    api_key: String,
+    api_version: String,
+}
+
+impl AzureOpenAI {
+    /// Creates a client for the resource at `endpoint`, authenticated with
+    /// `api_key`.
+    pub fn new(endpoint: &str, api_key: String) -> Self {
+        Self {
+            middleware: Middleware::default(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            api_key,
+            api_version: DEFAULT_AZURE_API_VERSION.to_string(),
+        }
+    }
+
+    /// Sends requests with `api_version` of the API instead of the default.
+    pub fn with_api_version(mut self, api_version: String) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    /// Sends requests through `middleware`, with its retries and rate
+    /// limits, instead of the default one.
+    pub fn with_middleware(mut self, middleware: Middleware) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Starts a POST request to `operation`, e.g. `chat/completions`, of the
+    /// deployment named `deployment`.
+    fn post(&self, deployment: &str, operation: &str) -> reqwest::RequestBuilder {
+        let url = format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            self.endpoint, deployment, operation, self.api_version
+        );
+        self.middleware.post(&url).header("api-key", &self.api_key)
+    }
+
+    /// Sends `body` as JSON to `operation` of the deployment and parses the
+    /// JSON response.
+    async fn post_json<B, R>(&self, deployment: &str, operation: &str, body: &B) -> Result<R>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let request = self.post(deployment, operation).json(body);
+        Ok(self.middleware.send(request).await?.json().await?)
+    }
+}
+
+#[async_trait]
+impl ChatProvider for AzureOpenAI {
+    #[instrument(skip_all, fields(deployment = %request.model))]
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let response: ChatResponse = self
+            .post_json(&request.model, "chat/completions", request)
+            .await?;
+        self.middleware
+            .record_usage(&request.model, response.usage.as_ref());
+        Ok(response)
+    }
+
+    #[instrument(skip_all, fields(deployment = %request.model))]
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream> {
+        let request = request.clone().streamed();
+        let response = self
+            .middleware
+            .send(self.post(&request.model, "chat/completions").json(&request))
+            .await?;
+        Ok(stream::read_chat_stream(
+            response,
+            self.middleware.clone(),
+            request.model,
+        ))
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for AzureOpenAI {
+    #[instrument(skip(self, texts), fields(count = texts.len()))]
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let body = EmbeddingRequest {
+            input: texts,
+            model,
+        };
+        let response: EmbeddingResponse = self.post_json(model, "embeddings", &body).await?;
+        self.middleware
+            .record_usage(model, response.usage().as_ref());
+        response.into_vectors(texts.len())
+    }
+}
@@ -0,0 +1,102 @@
+//! src/bin/ai-usage.rs
+//!
+//! The usage report: sums up the usage ledger that every tool records its
+//! responses in, per day, tool, and model, with the totals per tool.
+//!
+//! Usage: `ai-usage [--days <N>]`, where `--days` limits the report to the
+//! last N days (30 by default) and `--days 0` reports everything.
+
+use ai_core::usage::UsageSummary;
+use ai_core::UsageLedger;
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+/// The days reported without `--days`.
+const DEFAULT_DAYS: u32 = 30;
+
+fn main() -> ExitCode {
+    let days = match parse_days(std::env::args().skip(1)) {
+        Ok(days) => days,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            eprintln!("Usage: ai-usage [--days <N>]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let Some(path) = UsageLedger::default_path() else {
+        eprintln!("Error: neither XDG_DATA_HOME nor HOME is set.");
+        return ExitCode::FAILURE;
+    };
+    if !path.exists() {
+        println!("No usage recorded yet ({} does not exist).", path.display());
+        return ExitCode::SUCCESS;
+    }
+    match UsageLedger::open(&path).and_then(|ledger| ledger.report(days)) {
+        Ok(rows) => {
+            print_report(&rows, days);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Reads `--days <N>`; `None` for `--days 0`, which reports every day.
+fn parse_days(mut args: impl Iterator<Item = String>) -> Result<Option<u32>, String> {
+    let mut days = DEFAULT_DAYS;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--days" => {
+                let value = args.next().ok_or("--days needs a number of days")?;
+                days = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of days '{}'", value))?;
+            }
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    Ok((days > 0).then_some(days))
+}
+
+fn print_report(rows: &[UsageSummary], days: Option<u32>) {
+    match days {
+        Some(days) => println!("Usage over the last {} days", days),
+        None => println!("Usage over all time"),
+    }
+    if rows.is_empty() {
+        println!("\nNo requests recorded.");
+        return;
+    }
+
+    println!(
+        "\n{:<10}  {:<24}  {:<28}  {:>8}  {:>12}  {:>12}  {:>10}",
+        "Day", "Tool", "Model", "Requests", "Prompt", "Completion", "Cost"
+    );
+    let mut per_tool: BTreeMap<&str, (u64, f64)> = BTreeMap::new();
+    for row in rows {
+        println!(
+            "{:<10}  {:<24}  {:<28}  {:>8}  {:>12}  {:>12}  {:>10}",
+            row.day,
+            row.tool,
+            row.model,
+            row.requests,
+            row.prompt_tokens,
+            row.completion_tokens,
+            format!("${:.4}", row.cost_usd)
+        );
+        let total = per_tool.entry(row.tool.as_str()).or_default();
+        total.0 += row.requests;
+        total.1 += row.cost_usd;
+    }
+
+    println!("\nPer tool:");
+    for (tool, (requests, cost)) in &per_tool {
+        println!("  {:<24}  {:>8} requests  ${:.4}", tool, requests, cost);
+    }
+    let total: f64 = per_tool.values().map(|(_, cost)| cost).sum();
+    println!("\nTotal: ${:.4}", total);
+    println!("Models without a known price, such as local ones, are counted at $0.");
+}
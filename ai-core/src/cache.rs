@@ -0,0 +1,204 @@
+//! src/cache.rs
+//!
+//! An opt-in cache of chat completions on disk, shared by every tool in
+//! `~/.cache/ai-rs/responses`. Replies are keyed by a SHA-256 hash of the
+//! provider and the whole request (the model, the messages, and parameters
+//! such as the response format), so rerunning a tool on the same input
+//! reuses its earlier replies instead of calling the API again. Entries
+//! expire after a time to live.
+
+use crate::chat::{ChatRequest, ChatResponse, Choice, Message};
+use crate::error::Result;
+use crate::provider::ChatProvider;
+use crate::stream::{self, TokenStream};
+use crate::usage::unix_time;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How long cached replies are reused by default: a week.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The directory under the user's cache directory that holds the replies.
+const CACHE_DIR: &str = "ai-rs/responses";
+
+/// A cached reply, with when it was received.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// Seconds since the Unix epoch.
+    created_at: u64,
+    choices: Vec<Choice>,
+}
+
+/// Stores one JSON file per cached reply.
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    /// How long replies are reused; `None` keeps them forever.
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    /// A cache in `dir`, whose replies never expire. The directory is
+    /// created on the first write.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            ttl: None,
+        }
+    }
+
+    /// Reuses replies for `ttl` after they were received, or forever if
+    /// `None`.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The cache shared by every tool: `ai-rs/responses` under
+    /// `$XDG_CACHE_HOME`, or else `~/.cache`. `None` if neither is set.
+    pub fn default_dir() -> Option<PathBuf> {
+        let cache_dir = env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(cache_dir.join(CACHE_DIR))
+    }
+
+    /// The key of `request` sent to `provider`. Whether the reply is
+    /// streamed doesn't change it.
+    pub fn key(provider: &str, request: &ChatRequest) -> String {
+        let mut request = request.clone();
+        request.stream = false;
+        request.stream_options = None;
+        let mut hasher = Sha256::new();
+        hasher.update(provider.as_bytes());
+        hasher.update([0]);
+        hasher.update(serde_json::to_vec(&request).unwrap_or_default());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// The cached choices for `key`, unless there are none or they have
+    /// expired.
+    fn get(&self, key: &str) -> Option<Vec<Choice>> {
+        let data = fs::read(self.entry_path(key)).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&data).ok()?;
+        let age = Duration::from_secs(unix_time().saturating_sub(entry.created_at));
+        if self.ttl.is_some_and(|ttl| age > ttl) {
+            return None;
+        }
+        Some(entry.choices)
+    }
+
+    /// Stores `choices` under `key`.
+    fn put(&self, key: &str, choices: Vec<Choice>) -> io::Result<()> {
+        let entry = CacheEntry {
+            created_at: unix_time(),
+            choices,
+        };
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry_path(key), serde_json::to_vec(&entry)?)
+    }
+
+    /// Stores `choices`, logging instead of failing if the cache is not
+    /// writable, as the reply has been received either way.
+    fn put_or_warn(&self, key: &str, choices: Vec<Choice>) {
+        if let Err(e) = self.put(key, choices) {
+            warn!(
+                "Failed to write to the response cache at '{}': {}",
+                self.dir.display(),
+                e
+            );
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// A `ChatProvider` that answers from a `ResponseCache` when it can, and
+/// asks `inner` and caches its reply when it can't. Cached replies cost
+/// nothing, so they have no usage.
+pub struct CachedProvider {
+    inner: Arc<dyn ChatProvider>,
+    cache: ResponseCache,
+    /// The name of the provider, part of every key.
+    provider: String,
+}
+
+impl CachedProvider {
+    /// Caches the replies of `inner`, the provider named `provider`, in
+    /// `cache`.
+    pub fn new(inner: Arc<dyn ChatProvider>, cache: ResponseCache, provider: &str) -> Self {
+        Self {
+            inner,
+            cache,
+            provider: provider.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ChatProvider for CachedProvider {
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let key = ResponseCache::key(&self.provider, request);
+        if let Some(choices) = self.cache.get(&key) {
+            debug!("Reusing the cached reply of {}.", request.model);
+            return Ok(ChatResponse {
+                choices,
+                usage: None,
+            });
+        }
+        let response = self.inner.chat(request).await?;
+        self.cache.put_or_warn(&key, response.choices.clone());
+        Ok(response)
+    }
+
+    /// Yields a cached reply at once. Otherwise, streams the reply of the
+    /// inner provider and caches it once it has been read to the end.
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream> {
+        let key = ResponseCache::key(&self.provider, request);
+        if let Some(choice) = self
+            .cache
+            .get(&key)
+            .and_then(|choices| choices.into_iter().next())
+        {
+            debug!("Reusing the cached reply of {}.", request.model);
+            return Ok(stream::complete_stream(choice.message.content));
+        }
+        let tokens = self.inner.chat_stream(request).await?;
+        let cache = Some((self.cache.clone(), key));
+        Ok(Box::pin(futures::stream::unfold(
+            (tokens, String::new(), cache),
+            |(mut tokens, mut reply, mut cache)| async move {
+                match tokens.next().await {
+                    Some(Ok(token)) => {
+                        reply.push_str(&token);
+                        Some((Ok(token), (tokens, reply, cache)))
+                    }
+                    // A reply cut short by an error is not cached.
+                    Some(Err(e)) => Some((Err(e), (tokens, reply, None))),
+                    None => {
+                        if let Some((cache, key)) = cache.take() {
+                            let choice = Choice {
+                                message: Message::assistant(reply),
+                                finish_reason: None,
+                            };
+                            cache.put_or_warn(&key, vec![choice]);
+                        }
+                        None
+                    }
+                }
+            },
+        )))
+    }
+}
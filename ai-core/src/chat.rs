@@ -0,0 +1,184 @@
+//! src/chat.rs
+//!
+//! The data structures of the Chat Completions API. `ChatRequest` is built
+//! by the caller and sent with `Client::chat`, which returns the
+//! `ChatResponse`.
+
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+/// A single message of a conversation, from the `system`, the `user`, or the
+/// `assistant`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+impl Message {
+    /// A system message, which sets the model's task and persona.
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    /// A user message, such as a question or the input to process.
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    /// An assistant message, such as an earlier reply of the model.
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+/// The format the model must reply in.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free text, the default.
+    Text,
+    /// A single JSON object. The prompt must still describe its structure.
+    JsonObject,
+    /// A JSON object that matches a JSON schema.
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+/// The JSON schema of a `ResponseFormat::JsonSchema` reply.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JsonSchemaFormat {
+    /// The name of the schema, of letters, digits, `_`, and `-`.
+    pub name: String,
+    pub schema: serde_json::Value,
+    /// Whether the reply is held to the schema exactly. Strict mode only
+    /// supports a subset of JSON Schema, which derived schemas don't keep
+    /// to, such as optional fields.
+    #[serde(default)]
+    pub strict: bool,
+}
+
+impl JsonSchemaFormat {
+    /// The schema of `T`, derived with `schemars`, named after the type.
+    pub fn of<T: JsonSchema>() -> Self {
+        let name = T::schema_name()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        Self {
+            name,
+            schema: serde_json::to_value(schema_for!(T)).unwrap_or_default(),
+            strict: false,
+        }
+    }
+}
+
+/// A request to the Chat Completions API. It can be deserialized too, for
+/// the fixtures of `MockClient`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Whether the reply is sent as server-sent events as it is written.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    /// Options for streamed replies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+/// Options for streamed replies.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// Whether the last event holds the usage of the whole reply.
+    pub include_usage: bool,
+}
+
+impl ChatRequest {
+    /// A request for `model` to reply to `messages`.
+    pub fn new(model: impl Into<String>, messages: Vec<Message>) -> Self {
+        Self {
+            model: model.into(),
+            messages,
+            response_format: None,
+            stream: false,
+            stream_options: None,
+        }
+    }
+
+    /// A request for `model` to reply to a system and a user prompt, the
+    /// shape of most requests.
+    pub fn from_prompts(model: impl Into<String>, system_prompt: &str, user_prompt: &str) -> Self {
+        Self::new(
+            model,
+            vec![Message::system(system_prompt), Message::user(user_prompt)],
+        )
+    }
+
+    /// Enables JSON mode, so the reply is guaranteed to be a single JSON
+    /// object.
+    pub fn json(mut self) -> Self {
+        self.response_format = Some(ResponseFormat::JsonObject);
+        self
+    }
+
+    /// Asks for a reply that matches the JSON schema of `T`. The reply can
+    /// be read with `parse_structured`, or the request sent with
+    /// `complete_structured`, which does both.
+    pub fn json_schema<T: JsonSchema>(mut self) -> Self {
+        self.response_format = Some(ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat::of::<T>(),
+        });
+        self
+    }
+
+    /// Asks for the reply to be streamed, with its usage at the end.
+    pub(crate) fn streamed(mut self) -> Self {
+        self.stream = true;
+        self.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+        self
+    }
+}
+
+/// The response of the Chat Completions API.
+#[derive(Deserialize, Debug)]
+pub struct ChatResponse {
+    pub choices: Vec<Choice>,
+    /// The tokens the request used, which providers may leave out.
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+/// A single completion. Only one is requested, so only the first is used.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Choice {
+    pub message: Message,
+    /// `stop` for a complete reply, `length` if it hit the output token limit.
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+/// The number of tokens a request used.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
@@ -0,0 +1,155 @@
+//! src/client.rs
+//!
+//! The HTTP client for the OpenAI API, and for servers that implement the
+//! same API, such as Ollama. It sends the requests and turns error statuses
+//! and empty responses into errors; choosing models and building prompts is
+//! left to the callers.
+
+use crate::chat::{ChatRequest, ChatResponse};
+use crate::embeddings::{EmbeddingRequest, EmbeddingResponse};
+use crate::error::Result;
+use crate::middleware::Middleware;
+use crate::provider::{ChatProvider, EmbeddingProvider};
+use crate::stream::{self, TokenStream};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+/// The base URL of the OpenAI API, which every endpoint path is relative to.
+pub const OPENAI_API_URL: &str = "https://api.openai.com/v1";
+
+/// The path of Ollama's OpenAI-compatible API on an Ollama server.
+const OLLAMA_API_PATH: &str = "/v1";
+
+/// A client for the OpenAI API. Clones share the same connection pool and
+/// rate limits.
+#[derive(Clone)]
+pub struct Client {
+    middleware: Middleware,
+    /// Sent as a bearer token, unless empty, as for local servers.
+    api_key: String,
+    base_url: String,
+}
+
+impl Client {
+    /// Creates a client for the OpenAI API, authenticated with `api_key`.
+    pub fn new(api_key: String) -> Self {
+        Self {
+            middleware: Middleware::default(),
+            api_key,
+            base_url: OPENAI_API_URL.to_string(),
+        }
+    }
+
+    /// Creates a client for the OpenAI-compatible API at `base_url`, e.g.
+    /// `http://localhost:1234/v1`, sent without credentials.
+    pub fn local(base_url: &str) -> Self {
+        Self::new(String::new()).with_base_url(base_url)
+    }
+
+    /// Creates a client for the Ollama server at `host`, e.g.
+    /// `http://localhost:11434`, through its OpenAI-compatible API.
+    ///
+    /// No API key is sent, and nothing leaves the machine running Ollama.
+    pub fn ollama(host: &str) -> Self {
+        Self::local(&format!(
+            "{}{}",
+            host.trim_end_matches('/'),
+            OLLAMA_API_PATH
+        ))
+    }
+
+    /// Sends requests to the API at `base_url` instead of OpenAI's.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sends requests through `middleware`, with its retries and rate
+    /// limits, instead of the default one.
+    pub fn with_middleware(mut self, middleware: Middleware) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Starts a POST request to the endpoint at `path`, relative to the base
+    /// URL, with the API key set. For requests the other methods don't
+    /// cover; send it with `send`.
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        let request = self.middleware.post(&url);
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&self.api_key)
+        }
+    }
+
+    /// Sends a request started with `post`, with retries and rate limits,
+    /// and returns the response once it has a success status.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.middleware.send(request).await
+    }
+
+    /// Sends `body` as JSON to the endpoint at `path` and parses the JSON
+    /// response. Error statuses are returned as errors.
+    pub async fn post_json<B, R>(&self, path: &str, body: &B) -> Result<R>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        debug!("Sending request to '{}'.", path);
+        Ok(self.send(self.post(path).json(body)).await?.json().await?)
+    }
+
+    /// Sends `body` as JSON to the endpoint at `path` and returns the raw
+    /// response, such as audio data. Error statuses are returned as errors.
+    pub async fn post_bytes<B>(&self, path: &str, body: &B) -> Result<Vec<u8>>
+    where
+        B: Serialize + ?Sized,
+    {
+        debug!("Sending request to '{}'.", path);
+        let bytes = self.send(self.post(path).json(body)).await?.bytes().await?;
+        Ok(bytes.to_vec())
+    }
+}
+
+#[async_trait]
+impl ChatProvider for Client {
+    #[instrument(skip_all, fields(model = %request.model))]
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let response: ChatResponse = self.post_json("chat/completions", request).await?;
+        self.middleware
+            .record_usage(&request.model, response.usage.as_ref());
+        Ok(response)
+    }
+
+    #[instrument(skip_all, fields(model = %request.model))]
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream> {
+        let request = request.clone().streamed();
+        let response = self
+            .send(self.post("chat/completions").json(&request))
+            .await?;
+        Ok(stream::read_chat_stream(
+            response,
+            self.middleware.clone(),
+            request.model,
+        ))
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for Client {
+    #[instrument(skip(self, texts), fields(count = texts.len()))]
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let body = EmbeddingRequest {
+            input: texts,
+            model,
+        };
+        let response: EmbeddingResponse = self.post_json("embeddings", &body).await?;
+        self.middleware
+            .record_usage(model, response.usage().as_ref());
+        response.into_vectors(texts.len())
+    }
+}
@@ -0,0 +1,459 @@
+//! src/config.rs
+//!
+//! Chooses the model provider, and the models, from the environment, so
+//! every tool can switch providers without code changes:
+//!
+//! - `AI_PROVIDER`: `openai` (the default), `azure`, `anthropic`, or `ollama`.
+//! - `AI_MODEL`: the chat model, or the deployment on Azure OpenAI. Without
+//!   it, each tool uses its own model on OpenAI and Azure OpenAI, and the
+//!   provider's default model otherwise.
+//! - `AI_EMBEDDING_PROVIDER` and `AI_EMBEDDING_MODEL`: the same for
+//!   embeddings. The provider defaults to `AI_PROVIDER`.
+//! - The provider's settings: `OPENAI_API_KEY`; `AZURE_OPENAI_ENDPOINT`,
+//!   `AZURE_OPENAI_API_KEY`, and `AZURE_OPENAI_API_VERSION`;
+//!   `ANTHROPIC_API_KEY`; or `OLLAMA_HOST`.
+//! - `AI_MAX_RETRIES`, `AI_REQUESTS_PER_MINUTE`, and `AI_TOKENS_PER_MINUTE`:
+//!   how often failed requests are retried, and the budgets requests are
+//!   kept within.
+//! - `AI_MAX_COST`: the most a run may spend, in US dollars, for tools that
+//!   track their usage.
+//! - `AI_CACHE`: `1` to answer chat completions sent before from the
+//!   shared response cache. Caching is off by default.
+//! - `AI_CACHE_TTL`: how many seconds cached replies are reused for (a week
+//!   by default; `0` keeps them forever).
+
+use crate::anthropic::Anthropic;
+use crate::azure::AzureOpenAI;
+use crate::cache::{CachedProvider, ResponseCache, DEFAULT_CACHE_TTL};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::middleware::{Middleware, RateLimits, RetryPolicy};
+#[cfg(any(test, feature = "test-util"))]
+use crate::mock::MockClient;
+use crate::provider::{ChatProvider, EmbeddingProvider};
+use crate::usage::{UsageLedger, UsageTracker};
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// The Ollama server used when `OLLAMA_HOST` is not set.
+pub const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+
+/// The chat model used with Anthropic when `AI_MODEL` is not set.
+const ANTHROPIC_CHAT_MODEL: &str = "claude-3-5-sonnet-latest";
+/// The chat model used with Ollama when `AI_MODEL` is not set.
+const OLLAMA_CHAT_MODEL: &str = "llama3.1";
+/// The embedding model used with Ollama when `AI_EMBEDDING_MODEL` is not set.
+const OLLAMA_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
+/// A service that serves models.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProviderKind {
+    /// The OpenAI API.
+    OpenAI,
+    /// An Azure OpenAI resource.
+    Azure,
+    /// Anthropic's Messages API, for chat only.
+    Anthropic,
+    /// A local Ollama server.
+    Ollama,
+}
+
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAI),
+            "azure" => Ok(Self::Azure),
+            "anthropic" => Ok(Self::Anthropic),
+            "ollama" => Ok(Self::Ollama),
+            _ => Err(format!(
+                "unknown provider '{}'; expected openai, azure, anthropic, or ollama",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::OpenAI => "openai",
+            Self::Azure => "azure",
+            Self::Anthropic => "anthropic",
+            Self::Ollama => "ollama",
+        })
+    }
+}
+
+/// The providers and models to use, and the settings to reach them.
+#[derive(Clone, Debug)]
+pub struct ProviderConfig {
+    pub provider: ProviderKind,
+    /// The chat model, overriding the tool's and the provider's defaults.
+    pub model: Option<String>,
+    pub embedding_provider: ProviderKind,
+    /// The embedding model, overriding the tool's and the provider's defaults.
+    pub embedding_model: Option<String>,
+    pub openai_api_key: Option<String>,
+    pub azure_endpoint: Option<String>,
+    pub azure_api_key: Option<String>,
+    pub azure_api_version: Option<String>,
+    pub anthropic_api_key: Option<String>,
+    pub ollama_host: String,
+    pub retry: RetryPolicy,
+    pub rate_limits: RateLimits,
+    /// The most a run may spend, in US dollars, once usage is tracked.
+    pub max_cost: Option<f64>,
+    /// Records the usage of every provider's responses, if tracked.
+    pub usage: Option<Arc<UsageTracker>>,
+    /// How long cached replies are reused; `None` keeps them forever.
+    pub cache_ttl: Option<Duration>,
+    /// Answers chat completions sent before, if enabled.
+    pub cache: Option<ResponseCache>,
+    /// The provider of chat completions and embeddings in tests.
+    #[cfg(any(test, feature = "test-util"))]
+    pub mock: Option<Arc<MockClient>>,
+}
+
+impl ProviderConfig {
+    /// Reads the configuration from the environment variables. Variables
+    /// that are set but empty count as unset. Settings are only required
+    /// when a provider is created, so a tool that only uses Ollama never
+    /// needs an API key.
+    pub fn from_env() -> Result<Self> {
+        let provider = match var("AI_PROVIDER") {
+            Some(value) => parse_provider("AI_PROVIDER", &value)?,
+            None => ProviderKind::OpenAI,
+        };
+        let embedding_provider = match var("AI_EMBEDDING_PROVIDER") {
+            Some(value) => parse_provider("AI_EMBEDDING_PROVIDER", &value)?,
+            None => provider,
+        };
+        let retry = match parse_number("AI_MAX_RETRIES")? {
+            Some(max_retries) => RetryPolicy::default().with_max_retries(max_retries),
+            None => RetryPolicy::default(),
+        };
+        let rate_limits = RateLimits {
+            requests_per_minute: parse_number("AI_REQUESTS_PER_MINUTE")?,
+            tokens_per_minute: parse_number("AI_TOKENS_PER_MINUTE")?,
+        };
+        let cache_ttl = match parse_number("AI_CACHE_TTL")? {
+            Some(0) => None,
+            Some(seconds) => Some(Duration::from_secs(seconds.into())),
+            None => Some(DEFAULT_CACHE_TTL),
+        };
+        let cache = if parse_flag("AI_CACHE")? {
+            default_cache(cache_ttl)
+        } else {
+            None
+        };
+        Ok(Self {
+            provider,
+            model: var("AI_MODEL"),
+            embedding_provider,
+            embedding_model: var("AI_EMBEDDING_MODEL"),
+            openai_api_key: var("OPENAI_API_KEY"),
+            azure_endpoint: var("AZURE_OPENAI_ENDPOINT"),
+            azure_api_key: var("AZURE_OPENAI_API_KEY"),
+            azure_api_version: var("AZURE_OPENAI_API_VERSION"),
+            anthropic_api_key: var("ANTHROPIC_API_KEY"),
+            ollama_host: match var("OLLAMA_HOST") {
+                // Ollama itself accepts a host without a scheme, e.g. `127.0.0.1:11434`.
+                Some(host) if !host.contains("://") => format!("http://{}", host),
+                Some(host) => host,
+                None => DEFAULT_OLLAMA_HOST.to_string(),
+            },
+            retry,
+            rate_limits,
+            max_cost: parse_cost("AI_MAX_COST")?,
+            usage: None,
+            cache_ttl,
+            cache,
+            #[cfg(any(test, feature = "test-util"))]
+            mock: None,
+        })
+    }
+
+    /// A configuration for tests, whose chat and embedding provider is
+    /// `mock`. Nothing is read from the environment, and the tools' own
+    /// default models are used.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn mock(mock: Arc<MockClient>) -> Self {
+        Self {
+            provider: ProviderKind::OpenAI,
+            model: None,
+            embedding_provider: ProviderKind::OpenAI,
+            embedding_model: None,
+            openai_api_key: None,
+            azure_endpoint: None,
+            azure_api_key: None,
+            azure_api_version: None,
+            anthropic_api_key: None,
+            ollama_host: DEFAULT_OLLAMA_HOST.to_string(),
+            retry: RetryPolicy::none(),
+            rate_limits: RateLimits::default(),
+            max_cost: None,
+            usage: None,
+            cache_ttl: None,
+            cache: None,
+            mock: Some(mock),
+        }
+    }
+
+    /// Uses `provider` for chat completions instead of the configured one,
+    /// such as one chosen on the command line.
+    pub fn with_provider(mut self, provider: ProviderKind) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    /// Uses `model` for chat completions instead of the configured one.
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Retries failed requests with `retry` instead of the configured policy.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Keeps requests within `rate_limits` instead of the configured ones.
+    pub fn with_rate_limits(mut self, rate_limits: RateLimits) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
+    /// Records the usage of every response in the shared usage ledger, as
+    /// the usage of `tool`, and fails requests once the run has cost
+    /// `max_cost`, or else `AI_MAX_COST`, US dollars.
+    ///
+    /// If the ledger can't be opened, usage is still tracked for the budget
+    /// but not recorded.
+    pub fn track_usage(mut self, tool: &str, max_cost: Option<f64>) -> Self {
+        let ledger = UsageLedger::open_default()
+            .map_err(|e| warn!("Usage will not be recorded: {}", e))
+            .ok();
+        let mut tracker = UsageTracker::new(tool, ledger);
+        if let Some(max_cost) = max_cost.or(self.max_cost) {
+            tracker = tracker.with_max_cost(max_cost);
+        }
+        self.max_cost = tracker.max_cost();
+        self.usage = Some(Arc::new(tracker));
+        self
+    }
+
+    /// Answers chat completions from the shared response cache when the
+    /// same request was sent before, if `enabled` (such as with a tool's
+    /// `--cache` option) or `AI_CACHE` is set, so that rerunning a tool on
+    /// the same input doesn't call the API again. Cached replies are reused
+    /// for `AI_CACHE_TTL`.
+    pub fn cache_responses(mut self, enabled: bool) -> Self {
+        if enabled && self.cache.is_none() {
+            self.cache = default_cache(self.cache_ttl);
+        }
+        self
+    }
+
+    /// Always calls the API, even if `AI_CACHE` is set.
+    pub fn without_cache(mut self) -> Self {
+        self.cache = None;
+        self
+    }
+
+    /// The chat model to use: the configured one, or else `openai_model` on
+    /// OpenAI and Azure OpenAI, and the provider's default elsewhere.
+    pub fn chat_model(&self, openai_model: &str) -> String {
+        if let Some(model) = &self.model {
+            return model.clone();
+        }
+        match self.provider {
+            ProviderKind::OpenAI | ProviderKind::Azure => openai_model,
+            ProviderKind::Anthropic => ANTHROPIC_CHAT_MODEL,
+            ProviderKind::Ollama => OLLAMA_CHAT_MODEL,
+        }
+        .to_string()
+    }
+
+    /// The embedding model to use: the configured one, or else
+    /// `openai_model` on OpenAI and Azure OpenAI, and the provider's default
+    /// elsewhere.
+    pub fn embedding_model(&self, openai_model: &str) -> String {
+        if let Some(model) = &self.embedding_model {
+            return model.clone();
+        }
+        match self.embedding_provider {
+            ProviderKind::Ollama => OLLAMA_EMBEDDING_MODEL,
+            _ => openai_model,
+        }
+        .to_string()
+    }
+
+    /// Creates the provider for chat completions, answering from the
+    /// response cache if it is enabled.
+    pub fn chat_provider(&self) -> Result<Arc<dyn ChatProvider>> {
+        #[cfg(any(test, feature = "test-util"))]
+        if let Some(mock) = &self.mock {
+            return Ok(Arc::clone(mock) as Arc<dyn ChatProvider>);
+        }
+        let provider: Arc<dyn ChatProvider> = match self.provider {
+            ProviderKind::OpenAI => Arc::new(self.openai()?),
+            ProviderKind::Azure => Arc::new(self.azure()?),
+            ProviderKind::Anthropic => Arc::new(
+                Anthropic::new(required(
+                    &self.anthropic_api_key,
+                    "ANTHROPIC_API_KEY",
+                    ProviderKind::Anthropic,
+                )?)
+                .with_middleware(self.middleware()),
+            ),
+            ProviderKind::Ollama => Arc::new(self.ollama()),
+        };
+        Ok(match &self.cache {
+            Some(cache) => Arc::new(CachedProvider::new(
+                provider,
+                cache.clone(),
+                &self.provider.to_string(),
+            )),
+            None => provider,
+        })
+    }
+
+    /// Creates the provider for embeddings.
+    pub fn embedding_provider(&self) -> Result<Arc<dyn EmbeddingProvider>> {
+        #[cfg(any(test, feature = "test-util"))]
+        if let Some(mock) = &self.mock {
+            return Ok(Arc::clone(mock) as Arc<dyn EmbeddingProvider>);
+        }
+        Ok(match self.embedding_provider {
+            ProviderKind::OpenAI => Arc::new(self.openai()?),
+            ProviderKind::Azure => Arc::new(self.azure()?),
+            ProviderKind::Anthropic => {
+                return Err(Error::Config(
+                    "Anthropic does not offer embeddings; set AI_EMBEDDING_PROVIDER to openai, azure, or ollama."
+                        .to_string(),
+                ))
+            }
+            ProviderKind::Ollama => Arc::new(self.ollama()),
+        })
+    }
+
+    /// Creates a client for the OpenAI API itself, for the endpoints only
+    /// OpenAI offers, such as image generation.
+    pub fn openai(&self) -> Result<Client> {
+        let api_key = required(&self.openai_api_key, "OPENAI_API_KEY", ProviderKind::OpenAI)?;
+        Ok(Client::new(api_key).with_middleware(self.middleware()))
+    }
+
+    /// A new middleware with the configured retries and rate limits. Each
+    /// provider gets its own budgets, as each provider limits its own, but
+    /// they share the usage tracker and its maximum cost.
+    fn middleware(&self) -> Middleware {
+        let middleware = Middleware::new(self.retry, self.rate_limits);
+        match &self.usage {
+            Some(usage) => middleware.with_usage(Arc::clone(usage)),
+            None => middleware,
+        }
+    }
+
+    fn ollama(&self) -> Client {
+        Client::ollama(&self.ollama_host).with_middleware(self.middleware())
+    }
+
+    fn azure(&self) -> Result<AzureOpenAI> {
+        let endpoint = required(
+            &self.azure_endpoint,
+            "AZURE_OPENAI_ENDPOINT",
+            ProviderKind::Azure,
+        )?;
+        let api_key = required(
+            &self.azure_api_key,
+            "AZURE_OPENAI_API_KEY",
+            ProviderKind::Azure,
+        )?;
+        let client = AzureOpenAI::new(&endpoint, api_key).with_middleware(self.middleware());
+        Ok(match &self.azure_api_version {
+            Some(api_version) => client.with_api_version(api_version.clone()),
+            None => client,
+        })
+    }
+}
+
+/// The value of the environment variable `name`, if it is set and not empty.
+fn var(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The shared response cache, reusing replies for `ttl`, or `None` if there
+/// is no cache directory.
+fn default_cache(ttl: Option<Duration>) -> Option<ResponseCache> {
+    match ResponseCache::default_dir() {
+        Some(dir) => Some(ResponseCache::new(&dir).with_ttl(ttl)),
+        None => {
+            warn!("Replies will not be cached: neither XDG_CACHE_HOME nor HOME is set.");
+            None
+        }
+    }
+}
+
+/// Whether the environment variable `name` is set to `1` or `true`.
+fn parse_flag(name: &str) -> Result<bool> {
+    match var(name).as_deref().map(str::to_ascii_lowercase).as_deref() {
+        None | Some("0" | "false") => Ok(false),
+        Some("1" | "true") => Ok(true),
+        Some(value) => Err(Error::Config(format!(
+            "{} is invalid: expected 1 or 0, got '{}'",
+            name, value
+        ))),
+    }
+}
+
+/// The number in the environment variable `name`, if it is set.
+fn parse_number(name: &str) -> Result<Option<u32>> {
+    var(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|e| Error::Config(format!("{} is invalid: {}", name, e)))
+        })
+        .transpose()
+}
+
+/// The amount of US dollars in the environment variable `name`, if it is
+/// set.
+fn parse_cost(name: &str) -> Result<Option<f64>> {
+    var(name)
+        .map(|value| match value.parse::<f64>() {
+            Ok(cost) if cost.is_finite() && cost >= 0.0 => Ok(cost),
+            _ => Err(Error::Config(format!(
+                "{} is invalid: expected an amount in US dollars, got '{}'",
+                name, value
+            ))),
+        })
+        .transpose()
+}
+
+fn parse_provider(name: &str, value: &str) -> Result<ProviderKind> {
+    value
+        .parse()
+        .map_err(|e| Error::Config(format!("{} is invalid: {}", name, e)))
+}
+
+/// The setting `name` that `provider` requires, or an error if it is missing.
+fn required(value: &Option<String>, name: &str, provider: ProviderKind) -> Result<String> {
+    value.clone().ok_or_else(|| {
+        Error::Config(format!(
+            "{} must be set to use the {} provider.",
+            name, provider
+        ))
+    })
+}
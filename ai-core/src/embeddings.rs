@@ -0,0 +1,65 @@
+//! src/embeddings.rs
+//!
+//! The data structures of the Embeddings API, used by the `EmbeddingProvider`
+//! implementations of `Client` and `AzureOpenAI`.
+
+use crate::chat::Usage;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// A request to embed one or more texts with `model`.
+#[derive(Serialize, Debug)]
+pub(crate) struct EmbeddingRequest<'a> {
+    pub input: &'a [String],
+    pub model: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct EmbeddingResponse {
+    pub data: Vec<EmbeddingData>,
+    /// The tokens the texts took, which providers may leave out.
+    #[serde(default)]
+    pub usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct EmbeddingUsage {
+    pub prompt_tokens: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct EmbeddingData {
+    /// The position of the embedded text in the request's input.
+    #[serde(default)]
+    pub index: usize,
+    pub embedding: Vec<f32>,
+}
+
+impl EmbeddingResponse {
+    /// The tokens the request used, as the usage of a chat completion
+    /// without a reply.
+    pub(crate) fn usage(&self) -> Option<Usage> {
+        self.usage.as_ref().map(|usage| Usage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: 0,
+        })
+    }
+
+    /// The embeddings of the response, in the order of the `count` texts of
+    /// the request.
+    pub(crate) fn into_vectors(self, count: usize) -> Result<Vec<Vec<f32>>> {
+        if self.data.len() != count {
+            return Err(Error::Api(format!(
+                "API response contained {} embeddings for {} texts.",
+                self.data.len(),
+                count
+            )));
+        }
+        let mut data = self.data;
+        data.sort_by_key(|embedding_data| embedding_data.index);
+        Ok(data
+            .into_iter()
+            .map(|embedding_data| embedding_data.embedding)
+            .collect())
+    }
+}
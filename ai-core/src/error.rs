@@ -0,0 +1,66 @@
+//! src/error.rs
+//!
+//! Defines the error type for requests made through the shared client.
+//! Projects wrap it in a variant of their own error enum.
+
+use crate::middleware::is_transient_status;
+use thiserror::Error;
+
+/// The errors a request to the API can fail with.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// For providers that are unknown or missing their settings, such as an
+    /// API key.
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    /// Wraps errors from the `reqwest` HTTP client, such as timeouts and
+    /// failed connections.
+    #[error("HTTP request error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+
+    /// For error statuses returned by the API, with the message of its error
+    /// response.
+    #[error("API returned {status}: {message}")]
+    Status {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+
+    /// For responses that succeeded but don't hold what was requested.
+    #[error("API error: {0}")]
+    Api(String),
+
+    /// For requests not sent because the run has reached its maximum cost.
+    #[error("Cost limit reached: this run has spent ${spent:.4} of its ${max_cost:.2} budget")]
+    BudgetExceeded { spent: f64, max_cost: f64 },
+
+    /// Wraps errors of the SQLite database the usage ledger is kept in.
+    #[error("Usage ledger error: {0}")]
+    Ledger(#[from] rusqlite::Error),
+
+    /// For `MockClient` fixtures that can't be read or written, and requests
+    /// they have no reply to.
+    #[cfg(any(test, feature = "test-util"))]
+    #[error("Fixture error: {0}")]
+    Fixture(String),
+
+    /// For errors during JSON serialization or deserialization.
+    #[error("JSON processing error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// Whether the error may go away if the request is repeated: a timeout,
+    /// a dropped connection, a rate limit, or a server error.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Reqwest(e) => e.is_timeout() || e.is_connect(),
+            Error::Status { status, .. } => is_transient_status(*status),
+            _ => false,
+        }
+    }
+}
+
+/// A convenient type alias for `Result<T, E>` using the client's `Error` type.
+pub type Result<T> = std::result::Result<T, Error>;
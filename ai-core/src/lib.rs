@@ -0,0 +1,53 @@
+//! src/lib.rs
+//!
+//! The client library shared by every project in the repository. It holds
+//! what used to be copied into each of them: the HTTP client for the OpenAI
+//! API, the chat and embedding message types, and the error type for failed
+//! requests. Requests go through the `ChatProvider` and `EmbeddingProvider`
+//! traits, implemented for OpenAI, Azure OpenAI, Anthropic, and Ollama, and
+//! `ProviderConfig` picks the provider from the environment. Every request
+//! goes through the `Middleware`, which retries transient failures and keeps
+//! to the configured rate limits, and can record each response's cost in the
+//! `UsageLedger` shared by every tool. Tools can opt into the
+//! `ResponseCache`, which reuses the replies to requests sent before.
+//! `count_tokens` sizes prompts with the models' own tokenizers, and
+//! `complete_structured` reads replies into types whose JSON schema it sends
+//! with the request. Projects wrap the provider in their own client module,
+//! which chooses the models and builds the prompts, and test it against the
+//! `MockClient` of the `test-util` feature.
+
+pub mod anthropic;
+pub mod azure;
+pub mod cache;
+pub mod chat;
+pub mod client;
+pub mod config;
+mod embeddings;
+pub mod error;
+pub mod middleware;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+pub mod provider;
+mod stream;
+pub mod structured;
+pub mod tokens;
+pub mod usage;
+
+pub use anthropic::Anthropic;
+pub use azure::AzureOpenAI;
+pub use cache::ResponseCache;
+pub use chat::{
+    ChatRequest, ChatResponse, Choice, JsonSchemaFormat, Message, ResponseFormat, StreamOptions,
+    Usage,
+};
+pub use client::{Client, OPENAI_API_URL};
+pub use config::{ProviderConfig, ProviderKind};
+pub use error::{Error, Result};
+pub use middleware::{Middleware, RateLimits, RetryPolicy};
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::MockClient;
+pub use provider::{ChatProvider, EmbeddingProvider};
+pub use stream::TokenStream;
+pub use structured::parse_structured;
+pub use tokens::{count_tokens, truncate_to_tokens};
+pub use usage::{UsageLedger, UsageTracker};
@@ -0,0 +1,320 @@
+//! src/middleware.rs
+//!
+//! The layer every request to a provider goes through. It keeps requests
+//! within per-minute request and token budgets, retries rate limits (`429`)
+//! and server errors (`5xx`) with jittered exponential backoff, waiting as
+//! long as `Retry-After` asks when the API says, and turns the remaining
+//! error statuses into `Error::Status`, with the API's error message. With a
+//! `UsageTracker`, it also records the usage of each response and stops
+//! sending requests once the run's budget is spent.
+
+use crate::chat::Usage;
+use crate::error::{Error, Result};
+use crate::tokens::count_tokens;
+use crate::usage::UsageTracker;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
+use tracing::warn;
+
+/// How many times a failed request is retried by default.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// The delay before the first retry; each further retry waits about twice
+/// as long.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// The longest a single retry waits, including for `Retry-After`.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The window the request and token budgets apply to.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// The most characters of an error response kept in `Error::Status`.
+const MAX_ERROR_MESSAGE_CHARS: usize = 500;
+
+/// How requests that failed transiently are retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times a request is repeated after the first attempt.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: INITIAL_BACKOFF,
+            max_backoff: MAX_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that sends every request once.
+    pub fn none() -> Self {
+        Self::default().with_max_retries(0)
+    }
+
+    /// Retries failed requests up to `max_retries` times.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The delay before retry number `retry`, counting from 1: exponential,
+    /// capped at `max_backoff`, and scaled by a random factor between 0.5
+    /// and 1 so that concurrent requests don't retry in lockstep.
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+        exponential.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// The most requests and tokens sent per minute. Requests wait for room in
+/// the budgets instead of running into the API's rate limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub requests_per_minute: Option<u32>,
+    /// Counted with the tokenizer of each request's model over its whole
+    /// body, which slightly overestimates its prompt tokens.
+    pub tokens_per_minute: Option<u32>,
+}
+
+impl RateLimits {
+    fn is_unlimited(&self) -> bool {
+        self.requests_per_minute.is_none() && self.tokens_per_minute.is_none()
+    }
+
+    fn counts_tokens(&self) -> bool {
+        self.tokens_per_minute.is_some()
+    }
+}
+
+/// Sends requests with retries and rate limits. Clones share the same
+/// connection pool and budgets.
+#[derive(Clone, Debug, Default)]
+pub struct Middleware {
+    http_client: reqwest::Client,
+    retry: RetryPolicy,
+    budget: Option<Arc<Budget>>,
+    usage: Option<Arc<UsageTracker>>,
+}
+
+impl Middleware {
+    /// Creates a middleware that retries with `retry` and stays within
+    /// `limits`.
+    pub fn new(retry: RetryPolicy, limits: RateLimits) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            retry,
+            budget: (!limits.is_unlimited()).then(|| Arc::new(Budget::new(limits))),
+            usage: None,
+        }
+    }
+
+    /// Records the usage of every response with `usage`, and stops sending
+    /// requests once its budget is spent.
+    pub fn with_usage(mut self, usage: Arc<UsageTracker>) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Records the tokens a response of `model` used, if the provider sent
+    /// them.
+    pub(crate) fn record_usage(&self, model: &str, usage: Option<&Usage>) {
+        if let (Some(tracker), Some(usage)) = (&self.usage, usage) {
+            tracker.record(model, usage);
+        }
+    }
+
+    /// Starts a POST request to `url`.
+    pub(crate) fn post(&self, url: &str) -> RequestBuilder {
+        self.http_client.post(url)
+    }
+
+    /// Sends `request`, retrying it while it fails transiently, and returns
+    /// the response once it has a success status.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Status` for an error status, with the message of the
+    /// API's error response, or `Error::Reqwest` if the request could not be
+    /// sent, once the retries are used up. Returns `Error::BudgetExceeded`
+    /// without sending anything if the run's budget is spent.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        if let Some(tracker) = &self.usage {
+            tracker.check_budget()?;
+        }
+        let request = request.build()?;
+        // Counting tokens takes a while for large requests, so it is skipped
+        // without a token budget.
+        let tokens = match &self.budget {
+            Some(budget) if budget.limits.counts_tokens() => request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map_or(0, request_tokens),
+            _ => 0,
+        };
+
+        let mut retry = 0;
+        loop {
+            if let Some(budget) = &self.budget {
+                budget.acquire(tokens).await;
+            }
+            let Some(attempt) = request.try_clone() else {
+                // Only bodies held in memory, such as JSON, can be sent
+                // again; a streamed body is sent once.
+                let response = self.http_client.execute(request).await?;
+                if response.status().is_success() {
+                    return Ok(response);
+                }
+                return Err(status_error(response).await);
+            };
+
+            let (error, retry_after) = match self.http_client.execute(attempt).await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let retry_after = parse_retry_after(response.headers());
+                    (status_error(response).await, retry_after)
+                }
+                Err(e) => (Error::Reqwest(e), None),
+            };
+            if retry >= self.retry.max_retries || !error.is_transient() {
+                return Err(error);
+            }
+
+            retry += 1;
+            let delay = retry_after.map_or_else(
+                || self.retry.backoff(retry),
+                |delay| delay.min(self.retry.max_backoff),
+            );
+            warn!(
+                "Request failed ({}); retrying in {:.1}s ({}/{}).",
+                error,
+                delay.as_secs_f64(),
+                retry,
+                self.retry.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Turns a response with an error status into `Error::Status`, with the
+/// message of the API's JSON error, or else the start of the body.
+async fn status_error(response: Response) -> Error {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| {
+            value
+                .pointer("/error/message")
+                .and_then(|message| message.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.trim().chars().take(MAX_ERROR_MESSAGE_CHARS).collect());
+    Error::Status { status, message }
+}
+
+/// How long the API asks to wait before retrying: OpenAI's `retry-after-ms`,
+/// or the standard `Retry-After`, in seconds or as a date.
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let millis = header_str(headers, "retry-after-ms").and_then(|value| value.parse::<f64>().ok());
+    if let Some(millis) = millis.filter(|millis| millis.is_finite() && *millis >= 0.0) {
+        return Some(Duration::from_secs_f64(millis / 1000.0));
+    }
+    let value = header_str(headers, RETRY_AFTER.as_str())?;
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    // A date in the past means the request can be retried right away.
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// The tokens in a JSON request body, counted for the model it names.
+fn request_tokens(body: &[u8]) -> u32 {
+    let model = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value.get("model")?.as_str().map(str::to_string))
+        .unwrap_or_default();
+    count_tokens(&model, &String::from_utf8_lossy(body)) as u32
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok().map(str::trim)
+}
+
+/// Whether a status is worth retrying: a rate limit or a server error.
+pub(crate) fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The requests started within the last minute, with their estimated
+/// tokens, shared by the clones of a middleware.
+#[derive(Debug)]
+struct Budget {
+    limits: RateLimits,
+    started: Mutex<VecDeque<(Instant, u32)>>,
+}
+
+impl Budget {
+    fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            started: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Waits until a request of `tokens` tokens fits in the budgets, then
+    /// records it. A request larger than the whole token budget waits for an
+    /// empty window instead of forever.
+    async fn acquire(&self, tokens: u32) {
+        loop {
+            let wait = {
+                let mut started = self.started.lock().unwrap_or_else(PoisonError::into_inner);
+                let now = Instant::now();
+
+                // Forget requests that have left the window.
+                while started
+                    .front()
+                    .is_some_and(|(oldest, _)| now.duration_since(*oldest) >= WINDOW)
+                {
+                    started.pop_front();
+                }
+
+                let requests = started.len() as u64;
+                let used: u64 = started.iter().map(|&(_, tokens)| tokens as u64).sum();
+                let over_requests = self
+                    .limits
+                    .requests_per_minute
+                    .is_some_and(|max| requests >= max as u64);
+                let over_tokens = self
+                    .limits
+                    .tokens_per_minute
+                    .is_some_and(|max| used + tokens as u64 > max as u64);
+
+                match started.front() {
+                    Some((oldest, _)) if over_requests || over_tokens => {
+                        WINDOW - now.duration_since(*oldest)
+                    }
+                    _ => {
+                        started.push_back((now, tokens));
+                        return;
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
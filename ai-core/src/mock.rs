@@ -0,0 +1,340 @@
+//! src/mock.rs
+//!
+//! A provider for tests, which answers without network access. `MockClient`
+//! either hands out scripted replies in order, or replays a fixture: a JSON
+//! file of recorded exchanges, each a request and the reply it got. A request
+//! that no exchange matches fails the test, so a change to a prompt is
+//! caught as well as a change to how its reply is parsed.
+//!
+//! With `AI_RECORD_FIXTURES` set, fixtures are recorded instead: every
+//! request is sent to the provider configured in the environment, and the
+//! fixture is rewritten with the exchanges of the run.
+
+use crate::chat::{ChatRequest, ChatResponse, Choice, Message, ResponseFormat};
+use crate::config::ProviderConfig;
+use crate::error::{Error, Result};
+use crate::provider::{ChatProvider, EmbeddingProvider};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The environment variable that switches fixtures to recording.
+pub const RECORD_FIXTURES_VAR: &str = "AI_RECORD_FIXTURES";
+
+/// The dimensions of the embeddings of a `MockClient`.
+const EMBEDDING_DIMENSIONS: usize = 8;
+
+/// One request of a fixture, with the reply it got.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Exchange {
+    request: ChatRequest,
+    reply: String,
+}
+
+impl Exchange {
+    /// Whether `request` is the recorded request. The texts of the messages
+    /// are compared with runs of whitespace collapsed, so that reindenting
+    /// a prompt in the source doesn't invalidate the fixtures.
+    fn matches(&self, request: &ChatRequest) -> bool {
+        let recorded = &self.request;
+        recorded.model == request.model
+            && same_format(
+                recorded.response_format.as_ref(),
+                request.response_format.as_ref(),
+            )
+            && recorded.messages.len() == request.messages.len()
+            && recorded
+                .messages
+                .iter()
+                .zip(&request.messages)
+                .all(|(recorded, message)| {
+                    recorded.role == message.role
+                        && normalize_whitespace(&recorded.content)
+                            == normalize_whitespace(&message.content)
+                })
+    }
+}
+
+/// Where the replies of a `MockClient` come from.
+enum Replies {
+    /// Handed out in order, whatever the request.
+    Scripted(Mutex<VecDeque<String>>),
+    /// Looked up by request in the exchanges of a fixture.
+    Replay {
+        path: PathBuf,
+        exchanges: Vec<Exchange>,
+    },
+    /// Asked of a real provider, and recorded in a fixture.
+    Record {
+        path: PathBuf,
+        provider: Arc<dyn ChatProvider>,
+        exchanges: Mutex<Vec<Exchange>>,
+    },
+}
+
+/// A `ChatProvider` and `EmbeddingProvider` for tests. It keeps the
+/// requests it receives, so tests can check the prompts too. Embeddings are
+/// derived from a hash of each text: equal texts get equal embeddings, but
+/// similar texts don't get similar ones.
+pub struct MockClient {
+    replies: Replies,
+    requests: Mutex<Vec<ChatRequest>>,
+}
+
+impl MockClient {
+    /// A client that answers its requests with `replies`, in order, and
+    /// fails once they run out.
+    pub fn new<I>(replies: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::with_replies(Replies::Scripted(Mutex::new(
+            replies.into_iter().map(Into::into).collect(),
+        )))
+    }
+
+    /// A client that answers from the fixture at `path`, or records it if
+    /// `AI_RECORD_FIXTURES` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fixture can't be read, or, when recording,
+    /// if the configured provider can't be created.
+    pub fn fixture(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if env::var_os(RECORD_FIXTURES_VAR).is_some_and(|value| !value.is_empty()) {
+            let provider = ProviderConfig::from_env()?.chat_provider()?;
+            return Ok(Self::with_replies(Replies::Record {
+                path,
+                provider,
+                exchanges: Mutex::new(Vec::new()),
+            }));
+        }
+        let data = fs::read(&path).map_err(|e| {
+            Error::Fixture(format!(
+                "Failed to read '{}': {}; record it with {}=1.",
+                path.display(),
+                e,
+                RECORD_FIXTURES_VAR
+            ))
+        })?;
+        let exchanges = serde_json::from_slice(&data)?;
+        Ok(Self::with_replies(Replies::Replay { path, exchanges }))
+    }
+
+    fn with_replies(replies: Replies) -> Self {
+        Self {
+            replies,
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The requests received so far, in order.
+    pub fn requests(&self) -> Vec<ChatRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The reply to `request`.
+    async fn reply(&self, request: &ChatRequest) -> Result<String> {
+        match &self.replies {
+            Replies::Scripted(replies) => replies
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .pop_front()
+                .ok_or_else(|| {
+                    Error::Fixture(format!(
+                        "No reply left for the request to {}.",
+                        request.model
+                    ))
+                }),
+            Replies::Replay { path, exchanges } => exchanges
+                .iter()
+                .find(|exchange| exchange.matches(request))
+                .map(|exchange| exchange.reply.clone())
+                .ok_or_else(|| {
+                    Error::Fixture(format!(
+                        "No exchange in '{}' matches the request to {}. If its prompt changed \
+                         on purpose, record the fixture again with {}=1.",
+                        path.display(),
+                        request.model,
+                        RECORD_FIXTURES_VAR
+                    ))
+                }),
+            Replies::Record {
+                path,
+                provider,
+                exchanges,
+            } => {
+                let reply = provider.complete(request).await?;
+                let mut exchanges = exchanges.lock().unwrap_or_else(PoisonError::into_inner);
+                exchanges.push(Exchange {
+                    request: request.clone(),
+                    reply: reply.clone(),
+                });
+                save(path, &exchanges)?;
+                Ok(reply)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for MockClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockClient").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl ChatProvider for MockClient {
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let mut request = request.clone();
+        request.stream = false;
+        request.stream_options = None;
+        let reply = self.reply(&request).await;
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(request);
+        Ok(ChatResponse {
+            choices: vec![Choice {
+                message: Message::assistant(reply?),
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: None,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for MockClient {
+    async fn embed(&self, _model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts
+            .iter()
+            .map(|text| {
+                Sha256::digest(text.as_bytes())
+                    .iter()
+                    .take(EMBEDDING_DIMENSIONS)
+                    .map(|&byte| byte as f32 / 255.0 - 0.5)
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// Writes `exchanges` to the fixture at `path`, creating its directory.
+fn save(path: &Path, exchanges: &[Exchange]) -> Result<()> {
+    let write = |path: &Path| -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(exchanges)? + "\n")
+    };
+    write(path).map_err(|e| Error::Fixture(format!("Failed to write '{}': {}", path.display(), e)))
+}
+
+/// Whether two requests ask for the same response format. JSON schemas are
+/// compared by name only: they are derived from the type the reply is read
+/// into, and a test that reads the reply catches changes to that type.
+fn same_format(recorded: Option<&ResponseFormat>, format: Option<&ResponseFormat>) -> bool {
+    match (recorded, format) {
+        (
+            Some(ResponseFormat::JsonSchema {
+                json_schema: recorded,
+            }),
+            Some(ResponseFormat::JsonSchema { json_schema }),
+        ) => recorded.name == json_schema.name,
+        _ => recorded == format,
+    }
+}
+
+/// `text` with each run of whitespace replaced by a single space.
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(exchanges: Vec<Exchange>) -> MockClient {
+        MockClient::with_replies(Replies::Replay {
+            path: PathBuf::from("fixture.json"),
+            exchanges,
+        })
+    }
+
+    #[tokio::test]
+    async fn scripted_replies_are_handed_out_in_order() {
+        let mock = MockClient::new(["first", "second"]);
+        let request = ChatRequest::from_prompts("gpt-4o", "system", "user");
+
+        assert_eq!(mock.complete(&request).await.unwrap(), "first");
+        assert_eq!(mock.complete(&request).await.unwrap(), "second");
+        assert!(matches!(
+            mock.complete(&request).await,
+            Err(Error::Fixture(_))
+        ));
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn replay_ignores_whitespace_in_prompts() {
+        let recorded = ChatRequest::from_prompts("gpt-4o", "Be brief.", "Summarize:\n    the text");
+        let mock = replay(vec![Exchange {
+            request: recorded,
+            reply: "A summary.".to_string(),
+        }]);
+        let request = ChatRequest::from_prompts("gpt-4o", "Be  brief.", "Summarize: the text\n");
+
+        assert_eq!(mock.complete(&request).await.unwrap(), "A summary.");
+    }
+
+    #[tokio::test]
+    async fn replay_rejects_changed_requests() {
+        let recorded = ChatRequest::from_prompts("gpt-4o", "Be brief.", "Summarize the text");
+        let mock = replay(vec![Exchange {
+            request: recorded.clone(),
+            reply: "A summary.".to_string(),
+        }]);
+
+        let reworded = ChatRequest::from_prompts("gpt-4o", "Be brief.", "Summarize this text");
+        assert!(matches!(
+            mock.complete(&reworded).await,
+            Err(Error::Fixture(_))
+        ));
+        let other_model =
+            ChatRequest::from_prompts("gpt-4o-mini", "Be brief.", "Summarize the text");
+        assert!(matches!(
+            mock.complete(&other_model).await,
+            Err(Error::Fixture(_))
+        ));
+        assert!(matches!(
+            mock.complete(&recorded.json()).await,
+            Err(Error::Fixture(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fixtures_round_trip_through_json() {
+        let exchange = Exchange {
+            request: ChatRequest::from_prompts("gpt-4o", "system", "user").json(),
+            reply: "{}".to_string(),
+        };
+        let json = serde_json::to_string(&[exchange]).unwrap();
+        let exchanges: Vec<Exchange> = serde_json::from_str(&json).unwrap();
+        let mock = replay(exchanges);
+
+        let request = ChatRequest::from_prompts("gpt-4o", "system", "user").json();
+        assert_eq!(mock.complete(&request).await.unwrap(), "{}");
+    }
+}
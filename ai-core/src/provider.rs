@@ -0,0 +1,60 @@
+//! src/provider.rs
+//!
+//! The traits every model provider implements, so that tools can send
+//! requests without knowing which provider answers them. `ProviderConfig`
+//! picks the implementation from the environment.
+
+use crate::chat::{ChatRequest, ChatResponse, Choice};
+use crate::error::{Error, Result};
+use crate::stream::{self, TokenStream};
+use async_trait::async_trait;
+
+/// A provider of chat completions: OpenAI (`Client`), Azure OpenAI
+/// (`AzureOpenAI`), Anthropic (`Anthropic`), or Ollama (`Client::ollama`).
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    /// Sends a chat completion request. `request.model` names the model, or
+    /// the deployment on Azure OpenAI.
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse>;
+
+    /// Sends a chat completion request and streams the reply, yielding each
+    /// piece of it as soon as it arrives. Errors that occur before the reply
+    /// starts, such as error statuses, are returned here; later ones end the
+    /// stream.
+    ///
+    /// Providers that can't stream replies yield the whole reply at once.
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream> {
+        let content = self.complete(request).await?;
+        Ok(stream::complete_stream(content))
+    }
+
+    /// Sends a chat completion request and returns its first choice, with
+    /// the reason it finished.
+    async fn chat_choice(&self, request: &ChatRequest) -> Result<Choice> {
+        self.chat(request)
+            .await?
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Api("API response did not contain any choices.".to_string()))
+    }
+
+    /// Sends a chat completion request and returns the text of its first
+    /// choice.
+    async fn complete(&self, request: &ChatRequest) -> Result<String> {
+        Ok(self.chat_choice(request).await?.message.content)
+    }
+}
+
+/// A provider of text embeddings: OpenAI (`Client`), Azure OpenAI
+/// (`AzureOpenAI`), or Ollama (`Client::ollama`). Anthropic has none.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embeds each of `texts` with `model`, or the deployment on Azure
+    /// OpenAI, in a single request.
+    ///
+    /// # Returns
+    ///
+    /// The embeddings, in the order of `texts`.
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
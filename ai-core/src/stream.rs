@@ -0,0 +1,142 @@
+//! src/stream.rs
+//!
+//! Reads chat completions streamed as server-sent events, as the OpenAI API
+//! and the APIs compatible with it send them with `stream: true`, and hands
+//! them out as a `TokenStream`.
+
+use crate::chat::Usage;
+use crate::error::{Error, Result};
+use crate::middleware::Middleware;
+use futures::stream::{self, Stream};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// A reply streamed as it is written: each item is the next piece of its
+/// text. The stream ends with the reply, or with the first error.
+pub type TokenStream = Pin<Box<dyn Stream<Item = Result<String>> + Send>>;
+
+/// Streams the completion of `model` in `response`, yielding each piece as
+/// soon as it arrives. The usage sent at the end is recorded by `middleware`.
+pub(crate) fn read_chat_stream(
+    response: reqwest::Response,
+    middleware: Middleware,
+    model: String,
+) -> TokenStream {
+    let reader = EventReader {
+        response,
+        middleware,
+        model,
+        buffer: Vec::new(),
+        pending: VecDeque::new(),
+        finished: false,
+    };
+    Box::pin(stream::try_unfold(reader, |mut reader| async move {
+        Ok(reader.next_token().await?.map(|token| (token, reader)))
+    }))
+}
+
+/// A `TokenStream` that yields a whole reply at once, for providers that
+/// can't stream.
+pub(crate) fn complete_stream(content: String) -> TokenStream {
+    Box::pin(stream::once(async move { Ok(content) }))
+}
+
+/// Reads the events of a streamed completion.
+struct EventReader {
+    response: reqwest::Response,
+    middleware: Middleware,
+    model: String,
+    /// The bytes of a line that has not been completed yet. Network chunks
+    /// may end mid-line, or even mid-character.
+    buffer: Vec<u8>,
+    /// Pieces of the completion that were read but not handed out yet.
+    pending: VecDeque<String>,
+    /// Whether the last event has been read.
+    finished: bool,
+}
+
+impl EventReader {
+    /// The next piece of the completion, or `None` once it has ended.
+    async fn next_token(&mut self) -> Result<Option<String>> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Ok(Some(token));
+            }
+            if self.finished {
+                return Ok(None);
+            }
+
+            match self.response.chunk().await? {
+                Some(bytes) => self.buffer.extend_from_slice(&bytes),
+                None => {
+                    // The last line may not end with a newline.
+                    self.finished = true;
+                    self.buffer.push(b'\n');
+                }
+            }
+            while let Some(newline) = self.buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+                self.read_line(&String::from_utf8_lossy(&line))?;
+            }
+        }
+    }
+
+    /// Reads one line of the stream. The completion arrives as one `data:`
+    /// line per piece, ending with `data: [DONE]`.
+    fn read_line(&mut self, line: &str) -> Result<()> {
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            return Ok(());
+        };
+        let data = data.trim();
+        if data == "[DONE]" {
+            self.finished = true;
+            return Ok(());
+        }
+
+        let event: ChatCompletionChunk = serde_json::from_str(data)?;
+        if let Some(error) = event.error {
+            return Err(Error::Api(error.message));
+        }
+        self.middleware
+            .record_usage(&self.model, event.usage.as_ref());
+        if let Some(content) = event
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.delta.content)
+            .filter(|content| !content.is_empty())
+        {
+            self.pending.push_back(content);
+        }
+        Ok(())
+    }
+}
+
+//========= API Data Structures =========//
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    /// The usage of the whole reply, sent in the last event, which has no
+    /// choices.
+    usage: Option<Usage>,
+    /// An error that occurred after the stream started.
+    error: Option<StreamError>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChunkChoice {
+    delta: Delta,
+}
+
+#[derive(Deserialize, Debug)]
+struct Delta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamError {
+    message: String,
+}
@@ -0,0 +1,109 @@
+//! src/structured.rs
+//!
+//! Structured output: requests that ask for a reply matching the JSON schema
+//! of a Rust type, derived with `schemars`, and read the reply into that
+//! type with serde. OpenAI, Azure OpenAI, and Ollama constrain the reply to
+//! the schema; Anthropic is given the schema in its system prompt.
+
+use crate::chat::ChatRequest;
+use crate::error::{Error, Result};
+use crate::provider::ChatProvider;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+impl dyn ChatProvider {
+    /// Sends `request`, asking for a reply that matches the JSON schema of
+    /// `T`, and reads the reply into a `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Api` if the reply can't be read into a `T`, besides
+    /// the errors of `complete`.
+    pub async fn complete_structured<T: DeserializeOwned + JsonSchema>(
+        &self,
+        request: &ChatRequest,
+    ) -> Result<T> {
+        let request = request.clone().json_schema::<T>();
+        let content = self.complete(&request).await?;
+        parse_structured(&content)
+    }
+}
+
+/// Reads a reply requested with `ChatRequest::json_schema` into a `T`.
+/// Providers that don't support JSON schemas sometimes wrap the JSON in a
+/// Markdown code block, which is removed.
+///
+/// # Errors
+///
+/// Returns `Error::Api` if the reply is not JSON of the shape of `T`.
+pub fn parse_structured<T: DeserializeOwned + JsonSchema>(content: &str) -> Result<T> {
+    serde_json::from_str(strip_code_fence(content)).map_err(|e| {
+        Error::Api(format!(
+            "The reply does not match the schema of {}: {}. Reply: '{}'",
+            T::schema_name(),
+            e,
+            content
+        ))
+    })
+}
+
+/// `reply` without the Markdown code block around it, if there is one.
+fn strip_code_fence(reply: &str) -> &str {
+    let reply = reply.trim();
+    let Some(fenced) = reply.strip_prefix("```") else {
+        return reply;
+    };
+    let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().trim_end_matches("```").trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::ResponseFormat;
+    use crate::mock::MockClient;
+    use serde::Deserialize;
+    use std::sync::Arc;
+
+    /// A city and its population.
+    #[derive(Deserialize, JsonSchema, Debug, PartialEq)]
+    struct City {
+        name: String,
+        population: u64,
+    }
+
+    #[tokio::test]
+    async fn sends_the_schema_and_reads_the_reply() {
+        let mock = Arc::new(MockClient::new([
+            "```json\n{\"name\": \"Lyon\", \"population\": 522250}\n```",
+        ]));
+        let provider: Arc<dyn ChatProvider> = mock.clone();
+        let request =
+            ChatRequest::from_prompts("gpt-4o", "system", "The largest city on the Rhône?");
+        let city: City = provider.complete_structured(&request).await.unwrap();
+
+        assert_eq!(
+            city,
+            City {
+                name: "Lyon".to_string(),
+                population: 522250,
+            }
+        );
+        let requests = mock.requests();
+        let Some(ResponseFormat::JsonSchema { json_schema }) = &requests[0].response_format else {
+            panic!("the request has no JSON schema");
+        };
+        assert_eq!(json_schema.name, "City");
+        assert_eq!(
+            json_schema.schema["required"],
+            serde_json::json!(["name", "population"])
+        );
+    }
+
+    #[test]
+    fn rejects_replies_of_another_shape() {
+        let result = parse_structured::<City>(r#"{"name": "Lyon"}"#);
+
+        assert!(matches!(result, Err(Error::Api(_))));
+    }
+}
@@ -0,0 +1,75 @@
+//! src/tokens.rs
+//!
+//! Counts tokens with the tiktoken encodings of OpenAI's models, for sizing
+//! prompts and chunks and for estimating costs before anything is sent.
+
+use crate::error::{Error, Result};
+use std::fmt;
+use std::sync::OnceLock;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+
+pub use tiktoken_rs::CoreBPE;
+
+/// The approximate number of bytes per token, the fallback if an encoding
+/// can't be loaded.
+const BYTES_PER_TOKEN: usize = 4;
+
+/// Returns the encoding `model` splits text into tokens with: `cl100k_base`
+/// for GPT-4, GPT-3.5, and the `text-embedding-3` models, and `o200k_base`
+/// for GPT-4o and newer models. Models of other providers, whose encodings
+/// aren't published, are counted with `o200k_base` as an approximation.
+///
+/// Each encoding is loaded once and shared.
+pub fn tokenizer(model: &str) -> Result<&'static CoreBPE> {
+    static CL100K_BASE: OnceLock<CoreBPE> = OnceLock::new();
+    static O200K_BASE: OnceLock<CoreBPE> = OnceLock::new();
+
+    match get_tokenizer(model) {
+        Some(Tokenizer::Cl100kBase) => load(&CL100K_BASE, tiktoken_rs::cl100k_base),
+        _ => load(&O200K_BASE, tiktoken_rs::o200k_base),
+    }
+}
+
+/// Loads an encoding into `encoding` the first time it is used.
+fn load<E: fmt::Display>(
+    encoding: &'static OnceLock<CoreBPE>,
+    init: impl FnOnce() -> std::result::Result<CoreBPE, E>,
+) -> Result<&'static CoreBPE> {
+    if let Some(bpe) = encoding.get() {
+        return Ok(bpe);
+    }
+    let bpe = init().map_err(|e| Error::Config(format!("Failed to load tokenizer: {}", e)))?;
+    Ok(encoding.get_or_init(|| bpe))
+}
+
+/// Returns the number of tokens in `text` for `model`.
+///
+/// The encodings are bundled with the crate, so loading one only fails if
+/// it is corrupt; the count is then estimated from the length of `text`.
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    match tokenizer(model) {
+        Ok(bpe) => bpe.encode_with_special_tokens(text).len(),
+        Err(_) => text.len().div_ceil(BYTES_PER_TOKEN),
+    }
+}
+
+/// Returns the start of `text` that fits in `max_tokens` tokens for `model`.
+pub fn truncate_to_tokens(model: &str, text: &str, max_tokens: usize) -> String {
+    let Ok(bpe) = tokenizer(model) else {
+        let end = text
+            .char_indices()
+            .nth(max_tokens * BYTES_PER_TOKEN)
+            .map_or(text.len(), |(end, _)| end);
+        return text[..end].to_string();
+    };
+    let tokens = bpe.encode_with_special_tokens(text);
+    if tokens.len() <= max_tokens {
+        return text.to_string();
+    }
+    // Cutting between tokens may split a character; the decoder can't
+    // decode half of one, so tokens are dropped until it can.
+    (0..=max_tokens)
+        .rev()
+        .find_map(|end| bpe.decode(tokens[..end].to_vec()).ok())
+        .unwrap_or_default()
+}
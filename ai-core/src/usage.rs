@@ -0,0 +1,267 @@
+//! src/usage.rs
+//!
+//! Keeps track of what the tools spend. The tokens of every response are
+//! priced and recorded in a usage ledger, an SQLite database shared by every
+//! tool in `~/.local/share/ai-rs/usage.db`, and a run can be given a maximum
+//! cost, after which its requests fail instead of being sent.
+
+use crate::chat::Usage;
+use crate::error::{Error, Result};
+use rusqlite::{params, Connection};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// The directory under the user's data directory that holds the ledger.
+const LEDGER_DIR: &str = "ai-rs";
+/// The file name of the ledger.
+const LEDGER_FILE: &str = "usage.db";
+
+/// A model's list price in US dollars per million tokens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// The list prices of known models, matched by the start of the model's
+/// name. More specific names come before the names they start with.
+const MODEL_PRICES: [(&str, ModelPrice); 14] = [
+    ("gpt-4o-mini", price(0.15, 0.60)),
+    ("gpt-4o", price(2.50, 10.00)),
+    ("gpt-4.1-nano", price(0.10, 0.40)),
+    ("gpt-4.1-mini", price(0.40, 1.60)),
+    ("gpt-4.1", price(2.00, 8.00)),
+    ("gpt-4-turbo", price(10.00, 30.00)),
+    ("gpt-4", price(30.00, 60.00)),
+    ("gpt-3.5-turbo", price(0.50, 1.50)),
+    ("text-embedding-3-small", price(0.02, 0.0)),
+    ("text-embedding-3-large", price(0.13, 0.0)),
+    ("claude-3-5-haiku", price(0.80, 4.00)),
+    ("claude-3-5-sonnet", price(3.00, 15.00)),
+    ("claude-3-7-sonnet", price(3.00, 15.00)),
+    ("claude-3-opus", price(15.00, 75.00)),
+];
+
+const fn price(input_per_million: f64, output_per_million: f64) -> ModelPrice {
+    ModelPrice {
+        input_per_million,
+        output_per_million,
+    }
+}
+
+/// The list price of `model`, or `None` for models without one, such as
+/// local models served by Ollama.
+pub fn model_price(model: &str) -> Option<ModelPrice> {
+    MODEL_PRICES
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, price)| *price)
+}
+
+/// The cost in US dollars of the tokens in `usage` with `model`. Models
+/// without a known price cost nothing.
+pub fn cost_usd(model: &str, usage: &Usage) -> f64 {
+    model_price(model).map_or(0.0, |price| {
+        (usage.prompt_tokens as f64 * price.input_per_million
+            + usage.completion_tokens as f64 * price.output_per_million)
+            / 1_000_000.0
+    })
+}
+
+/// The spend of one tool on one model on one day, as reported by
+/// `UsageLedger::report`.
+#[derive(Clone, Debug)]
+pub struct UsageSummary {
+    /// The day, as `YYYY-MM-DD` in UTC.
+    pub day: String,
+    pub tool: String,
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// The usage ledger: one row per response, with its tokens and cost.
+#[derive(Debug)]
+pub struct UsageLedger {
+    connection: Mutex<Connection>,
+}
+
+impl UsageLedger {
+    /// The ledger shared by every tool: `ai-rs/usage.db` under
+    /// `$XDG_DATA_HOME`, or else `~/.local/share`. `None` if neither is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let data_dir = env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+        Some(data_dir.join(LEDGER_DIR).join(LEDGER_FILE))
+    }
+
+    /// Opens the shared ledger at `default_path`, creating it if needed.
+    pub fn open_default() -> Result<Self> {
+        let path = Self::default_path().ok_or_else(|| {
+            Error::Config("Neither XDG_DATA_HOME nor HOME is set for the usage ledger.".to_string())
+        })?;
+        Self::open(&path)
+    }
+
+    /// Opens the ledger at `path`, creating it and its directory if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                Error::Config(format!("Failed to create '{}': {}", dir.display(), e))
+            })?;
+        }
+        let connection = Connection::open(path)?;
+        // Several tools may write at once; wait for each other's writes.
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS usage (
+                id INTEGER PRIMARY KEY,
+                created_at INTEGER NOT NULL,
+                tool TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                cost_usd REAL NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS usage_created_at ON usage (created_at);",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Records a response of `model` to `tool`, and what it cost.
+    pub fn record(&self, tool: &str, model: &str, usage: &Usage, cost_usd: f64) -> Result<()> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        connection.execute(
+            "INSERT INTO usage (created_at, tool, model, prompt_tokens, completion_tokens, cost_usd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                unix_time() as i64,
+                tool,
+                model,
+                usage.prompt_tokens as i64,
+                usage.completion_tokens as i64,
+                cost_usd
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Sums up the spend per day, tool, and model, over the last `days`
+    /// days, or all of it. The latest days come first.
+    pub fn report(&self, days: Option<u32>) -> Result<Vec<UsageSummary>> {
+        let since = days.map_or(0, |days| unix_time().saturating_sub(days as u64 * 86_400));
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut statement = connection.prepare(
+            "SELECT date(created_at, 'unixepoch') AS day, tool, model, COUNT(*),
+                    SUM(prompt_tokens), SUM(completion_tokens), SUM(cost_usd)
+             FROM usage
+             WHERE created_at >= ?1
+             GROUP BY day, tool, model
+             ORDER BY day DESC, tool, model",
+        )?;
+        let rows = statement.query_map(params![since as i64], |row| {
+            Ok(UsageSummary {
+                day: row.get(0)?,
+                tool: row.get(1)?,
+                model: row.get(2)?,
+                requests: row.get::<_, i64>(3)? as u64,
+                prompt_tokens: row.get::<_, i64>(4)? as u64,
+                completion_tokens: row.get::<_, i64>(5)? as u64,
+                cost_usd: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// Tracks the spend of one run of a tool: every response is recorded in
+/// the ledger, and requests fail with `Error::BudgetExceeded` once the run
+/// has cost `max_cost`. Shared by the middlewares of all of the run's
+/// providers.
+#[derive(Debug)]
+pub struct UsageTracker {
+    tool: String,
+    ledger: Option<UsageLedger>,
+    max_cost: Option<f64>,
+    spent: Mutex<f64>,
+}
+
+impl UsageTracker {
+    /// Tracks the usage of `tool`, recorded in `ledger` if given.
+    pub fn new(tool: &str, ledger: Option<UsageLedger>) -> Self {
+        Self {
+            tool: tool.to_string(),
+            ledger,
+            max_cost: None,
+            spent: Mutex::new(0.0),
+        }
+    }
+
+    /// Stops the run once it has cost `max_cost` US dollars.
+    pub fn with_max_cost(mut self, max_cost: f64) -> Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    /// The most the run may cost, in US dollars, if limited.
+    pub fn max_cost(&self) -> Option<f64> {
+        self.max_cost
+    }
+
+    /// What the run has cost so far, in US dollars.
+    pub fn spent_usd(&self) -> f64 {
+        *self.spent.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Fails with `Error::BudgetExceeded` if the run has used up its budget.
+    pub(crate) fn check_budget(&self) -> Result<()> {
+        let spent = self.spent_usd();
+        match self.max_cost {
+            Some(max_cost) if spent >= max_cost => Err(Error::BudgetExceeded { spent, max_cost }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds a response of `model` to the run's spend and to the ledger,
+    /// priced by its tokens. Responses to the chat and embedding providers
+    /// are recorded by their middleware; this is for requests sent some
+    /// other way.
+    pub fn record(&self, model: &str, usage: &Usage) {
+        self.record_cost(model, usage, cost_usd(model, usage));
+    }
+
+    /// Adds a response of `model` that cost `cost` US dollars, for requests
+    /// that are not priced by their tokens, such as generated images. A
+    /// ledger that can't be written is only warned about, as the response
+    /// has been paid for either way.
+    pub fn record_cost(&self, model: &str, usage: &Usage, cost: f64) {
+        *self.spent.lock().unwrap_or_else(PoisonError::into_inner) += cost;
+        if let Some(ledger) = &self.ledger {
+            if let Err(e) = ledger.record(&self.tool, model, usage, cost) {
+                warn!("Failed to record usage in the ledger: {}", e);
+            }
+        }
+    }
+}
+
+/// The current time in seconds since the Unix epoch.
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
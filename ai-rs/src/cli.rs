@@ -11,6 +11,7 @@ use crate::error::Result;
 // Corrected line: removed the unused `self` import.
 use crate::openai::{Client, Message};
 use colored::Colorize;
+use futures::TryStreamExt;
 use std::io::{self, Write};
 
 /// The main entry point for the command-line interface.
@@ -63,14 +64,18 @@ pub async fn run(client: Client) -> Result<()> {
         });
 
         // Send the entire conversation history to the OpenAI API.
-        let ai_response_content = client.chat_completion(&messages).await?;
+        let mut tokens = client.chat_completion(&messages).await?;
 
-        // Print the assistant's response.
-        println!(
-            "{}{}",
-            "Assistant: ".yellow().bold(),
-            ai_response_content.yellow()
-        );
+        // Print the assistant's response as it arrives, keeping the whole of
+        // it for the history.
+        print!("{}", "Assistant: ".yellow().bold());
+        let mut ai_response_content = String::new();
+        while let Some(token) = tokens.try_next().await? {
+            print!("{}", token.yellow());
+            io::stdout().flush()?;
+            ai_response_content.push_str(&token);
+        }
+        println!();
 
         // Add the assistant's response to the history for the next turn.
         messages.push(Message {
@@ -2,36 +2,33 @@
 //!
 //! This module is responsible for managing the application's configuration.
 //! Its primary purpose is to load secrets and settings from the environment,
-//! most notably the OpenAI API key from a `.env` file.
+//! most notably the model provider and its API key from a `.env` file.
 
-use crate::error::{Error, Result};
+use crate::error::Result;
+use ai_core::ProviderConfig;
 use dotenvy::dotenv;
-use std::env;
 
-const OPENAI_API_KEY: &str = "OPENAI_API_KEY";
-
-/// Retrieves the OpenAI API key from the environment.
+/// Retrieves the model provider settings from the environment.
 ///
 /// This function first loads the `.env` file from the current directory,
-/// then attempts to read the `OPENAI_API_KEY` environment variable.
+/// then reads `AI_PROVIDER`, `AI_MODEL`, and the provider's settings, such
+/// as the `OPENAI_API_KEY` environment variable. The usage of the session is
+/// recorded in the shared usage ledger, and `AI_MAX_COST` caps its cost.
 ///
 /// # Returns
 ///
-/// A `Result` containing the API key as a `String` on success.
+/// A `Result` containing the `ProviderConfig` on success.
 ///
 /// # Errors
 ///
-/// Returns `Error::Config` if the `OPENAI_API_KEY` environment variable is not set.
-/// This error is specifically crafted in our `error.rs` module.
-pub fn api_key() -> Result<String> {
+/// Returns an error if `AI_PROVIDER` names an unknown provider. Missing
+/// settings, such as an unset `OPENAI_API_KEY`, are reported when the
+/// client is created.
+pub fn provider_config() -> Result<ProviderConfig> {
     // Load environment variables from the .env file in the project root.
     // This will do nothing if the file doesn't exist, which is fine.
     dotenv().ok();
 
-    // Attempt to read the OPENAI_API_KEY from the environment.
-    // `env::var` returns a `Result`, which we can elegantly handle with `map_err`.
-    env::var(OPENAI_API_KEY).map_err(|_| {
-        // If the variable is not found, we create a specific, user-friendly error.
-        Error::Config(format!("{} is not set in the .env file", OPENAI_API_KEY))
-    })
+    // Read the provider and its settings from the environment.
+    Ok(ProviderConfig::from_env()?.track_usage(env!("CARGO_PKG_NAME"), None))
 }
@@ -12,12 +12,6 @@ use thiserror::Error;
 // traits for this to be a proper error type.
 #[derive(Error, Debug)]
 pub enum Error {
-    /// Represents errors originating from the configuration process.
-    /// Typically, this means a required environment variable is missing.
-    /// The `#[error(...)]` attribute provides the display message for this error.
-    #[error("Configuration error: {0}")]
-    Config(String),
-
     /// A wrapper for errors that occur during I/O operations.
     /// The `#[from]` attribute allows for automatic conversion from `std::io::Error`
     /// into `Error::Io`, simplifying error handling at call sites.
@@ -25,21 +19,13 @@ pub enum Error {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
-    /// A wrapper for errors originating from the `reqwest` HTTP client.
-    /// This includes network issues, DNS failures, or timeouts.
-    /// The `#[from]` attribute enables seamless conversion from `reqwest::Error`.
-    #[error("HTTP request error")]
-    Reqwest(#[from] reqwest::Error),
-
-    /// Represents errors specifically from the OpenAI API.
-    /// This could be an invalid API key, a malformed request, or rate limiting.
-    #[error("OpenAI API error: {0}")]
-    OpenAI(String),
-
-    /// A catch-all for errors related to JSON serialization or deserialization.
-    /// The `#[from]` attribute handles conversion from `serde_json::Error`.
-    #[error("JSON serialization/deserialization error")]
-    SerdeJson(#[from] serde_json::Error),
+    /// A wrapper for errors from the shared `ai-core` client. This includes
+    /// configuration errors, such as a missing API key, network issues,
+    /// timeouts, an invalid API key, rate limiting, or a malformed response.
+    /// The `#[error(transparent)]` attribute shows the client's own message.
+    /// The `#[from]` attribute enables seamless conversion from `ai_core::Error`.
+    #[error(transparent)]
+    Client(#[from] ai_core::Error),
 }
 
 // We define a custom Result type alias.
@@ -0,0 +1,11 @@
+//! src/lib.rs
+//!
+//! The library behind the `ai-rs` chatbot. The binary in `main.rs` loads the
+//! configuration and starts the session; the modules here hold the
+//! interactive loop and the client that streams the model's replies, and the
+//! tests in `tests/` run the client against the mock provider.
+
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod openai;
@@ -0,0 +1,66 @@
+//! tests/chat.rs
+//!
+//! Streams replies through the mock provider: the recorded answer to a first
+//! question, and a scripted reply to check that the whole conversation is
+//! sent with every turn.
+
+use ai_core::{MockClient, ProviderConfig};
+use ai_rs::openai::{Client, Message};
+use futures::TryStreamExt;
+use std::sync::Arc;
+
+const SYSTEM_PROMPT: &str = "You are a helpful assistant.";
+
+#[tokio::test]
+async fn streams_the_recorded_answer() {
+    let mock = MockClient::fixture(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/chat_borrow_checker.json"
+    ))
+    .unwrap();
+    let client = Client::new(&ProviderConfig::mock(Arc::new(mock))).unwrap();
+    let messages = [
+        Message::system(SYSTEM_PROMPT),
+        Message::user("What does the borrow checker do?"),
+    ];
+
+    let tokens: Vec<String> = client
+        .chat_completion(&messages)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    let reply = tokens.concat();
+    assert!(reply.starts_with("The borrow checker enforces Rust's ownership rules"));
+    assert!(reply.contains("without a garbage collector"));
+}
+
+#[tokio::test]
+async fn sends_the_whole_conversation() {
+    let mock = Arc::new(MockClient::new(["Rust 1.0 came out in May 2015."]));
+    let client = Client::new(&ProviderConfig::mock(mock.clone())).unwrap();
+    let messages = [
+        Message::system(SYSTEM_PROMPT),
+        Message::user("What is Rust?"),
+        Message::assistant("A systems programming language."),
+        Message::user("When was 1.0 released?"),
+    ];
+
+    let tokens: Vec<String> = client
+        .chat_completion(&messages)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(tokens.concat(), "Rust 1.0 came out in May 2015.");
+
+    let requests = mock.requests();
+    let [request] = requests.as_slice() else {
+        panic!("expected a single request, got {}", requests.len());
+    };
+    assert_eq!(request.model, "gpt-3.5-turbo");
+    assert_eq!(request.messages, messages);
+}
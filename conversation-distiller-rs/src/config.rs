@@ -3,8 +3,9 @@
 //! This module handles loading and accessing the tone profile configurations
 //! from the external JSON file.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 
 const TONE_PROFILES_PATH: &str = "config/tone_profiles.json";
@@ -15,6 +16,52 @@ pub struct ToneProfile {
     pub name: String,
     pub description: String,
     pub system_prompt: String,
+    /// Default values for `{{placeholder}}` variables used in the system prompt.
+    /// Values supplied on the command line take precedence over these.
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+impl ToneProfile {
+    /// Renders the system prompt, substituting every `{{name}}` placeholder.
+    ///
+    /// Values are looked up first in `overrides` (typically the CLI `--var`
+    /// flags) and then in the profile's own `variables` defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Config` if a placeholder has no value or is malformed.
+    pub fn render_system_prompt(&self, overrides: &HashMap<String, String>) -> Result<String> {
+        let mut rendered = String::with_capacity(self.system_prompt.len());
+        let mut rest = self.system_prompt.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            rendered.push_str(&rest[..start]);
+            let after_open = &rest[start + 2..];
+            let end = after_open.find("}}").ok_or_else(|| {
+                Error::Config(format!(
+                    "Profile '{}' contains an unterminated '{{{{' placeholder.",
+                    self.name
+                ))
+            })?;
+
+            let key = after_open[..end].trim();
+            let value = overrides
+                .get(key)
+                .or_else(|| self.variables.get(key))
+                .ok_or_else(|| {
+                    Error::Config(format!(
+                        "Profile '{}' requires a value for '{}'. Provide it with --var {}=<value>.",
+                        self.name, key, key
+                    ))
+                })?;
+            rendered.push_str(value);
+            rest = &after_open[end + 2..];
+        }
+        rendered.push_str(rest);
+
+        Ok(rendered)
+    }
 }
 
 /// Represents the top-level structure of the tone profiles configuration file.
@@ -37,3 +84,17 @@ pub fn load_tone_profiles() -> Result<ToneProfileConfig> {
     let config: ToneProfileConfig = serde_json::from_str(&file_content)?;
     Ok(config)
 }
+
+/// Parses a single `key=value` command-line variable.
+///
+/// Used as a `clap` value parser for the repeatable `--var` flag.
+pub fn parse_template_var(raw: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("invalid variable '{}': expected KEY=VALUE", raw))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("invalid variable '{}': key must not be empty", raw));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
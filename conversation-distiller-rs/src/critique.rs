@@ -0,0 +1,103 @@
+//! src/critique.rs
+//!
+//! This module defines the self-critique quality pass. After a draft summary
+//! is produced, a second model call grades it against the original transcript
+//! for faithfulness and coverage and flags any claims it cannot support.
+
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+/// Controls whether, and how, the self-critique pass is applied.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CritiqueMode {
+    /// Skip the quality pass entirely.
+    #[default]
+    Off,
+    /// Grade the draft and print the critique alongside the summary.
+    Attach,
+    /// Grade the draft and, if problems were found, ask the model to revise it.
+    Revise,
+}
+
+/// The structured critique returned by the grading pass.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Critique {
+    /// How well every statement in the summary is supported by the transcript (1-10).
+    pub faithfulness_score: u8,
+    /// How completely the summary captures the important points (1-10).
+    pub coverage_score: u8,
+    /// Claims in the summary that the transcript does not support.
+    #[serde(default)]
+    pub hallucinated_claims: Vec<String>,
+    /// Important points from the transcript that the summary omits.
+    #[serde(default)]
+    pub missing_points: Vec<String>,
+    /// A short, overall assessment of the draft.
+    pub assessment: String,
+}
+
+impl Critique {
+    /// Returns `true` if the critique found anything worth revising.
+    pub fn needs_revision(&self) -> bool {
+        !self.hallucinated_claims.is_empty() || !self.missing_points.is_empty()
+    }
+
+    /// Prints the critique to the console in a formatted block.
+    pub fn print(&self) {
+        println!("\n{}", "--- Quality Review ---".bold().yellow());
+        println!(
+            "Faithfulness: {}/10 | Coverage: {}/10",
+            self.faithfulness_score, self.coverage_score
+        );
+        println!("{}", self.assessment);
+        if !self.hallucinated_claims.is_empty() {
+            println!("{}", "Unsupported claims:".red());
+            for claim in &self.hallucinated_claims {
+                println!("  - {}", claim);
+            }
+        }
+        if !self.missing_points.is_empty() {
+            println!("{}", "Missing points:".yellow());
+            for point in &self.missing_points {
+                println!("  - {}", point);
+            }
+        }
+        println!("{}", "--- End of Review ---".bold().yellow());
+    }
+}
+
+/// Builds the system prompt that instructs the model to grade a draft summary.
+pub fn build_critique_prompt() -> String {
+    let output_schema = serde_json::json!({
+        "faithfulnessScore": "A number from 1 (many unsupported claims) to 10 (fully supported).",
+        "coverageScore": "A number from 1 (misses most key points) to 10 (complete).",
+        "hallucinatedClaims": ["Each statement in the summary not supported by the transcript."],
+        "missingPoints": ["Each important point from the transcript the summary omits."],
+        "assessment": "One or two sentences summarizing the quality of the draft."
+    });
+
+    format!(
+        "You are a meticulous editor reviewing a summary of a conversation. Compare the draft \
+        summary against the transcript. Check every claim in the summary against the transcript \
+        and flag any that are not directly supported. Then identify important points from the \
+        transcript that the summary fails to mention. Do not judge tone or style.
+
+        Your final output must be a single, valid JSON object that strictly adheres to the following schema:
+        ```json
+        {}
+        ```",
+        serde_json::to_string_pretty(&output_schema).unwrap()
+    )
+}
+
+/// Builds the system prompt for the revision round, layered on the original profile prompt.
+pub fn build_revision_prompt(profile_prompt: &str) -> String {
+    format!(
+        "{}\n\nYou previously drafted a summary of this conversation and an editor reviewed it. \
+        Rewrite the summary so that it removes every unsupported claim and covers the missing \
+        points, while keeping the original format and tone. Output only the revised summary.",
+        profile_prompt
+    )
+}
@@ -6,8 +6,11 @@
 
 use crate::config::ToneProfile;
 use crate::conversation_parser::Conversation;
-use crate::error::Result;
+use crate::critique::{self, Critique};
+use crate::error::{Error, Result};
 use crate::openai_client::OpenAIClient;
+use ai_core::TokenStream;
+use std::collections::HashMap;
 use tracing::{info, instrument};
 
 /// The main engine responsible for distilling conversations.
@@ -27,25 +30,114 @@ impl DistillerEngine {
     ///
     /// * `conversation` - The conversation transcript to be distilled.
     /// * `profile` - The tone profile that will guide the AI's response.
+    /// * `variables` - Values for the `{{placeholder}}` variables in the profile's prompt.
     ///
     /// # Returns
     ///
     /// A `Result` containing the final summary as a `String`.
-    #[instrument(skip(self, conversation, profile))]
+    #[instrument(skip(self, conversation, profile, variables))]
     pub async fn distill(
         &self,
         conversation: &Conversation,
         profile: &ToneProfile,
+        variables: &HashMap<String, String>,
     ) -> Result<String> {
         info!(profile_name = %profile.name, "Distilling conversation.");
 
         // Construct the two parts of the prompt.
-        let system_prompt = &profile.system_prompt;
+        let system_prompt = profile.render_system_prompt(variables)?;
         let user_prompt = self.format_conversation_for_prompt(conversation);
 
         // Use the client to get the distilled summary from the AI.
         self.client
-            .send_request(system_prompt, &user_prompt)
+            .send_request(&system_prompt, &user_prompt)
+            .await
+    }
+
+    /// Distills a conversation like `distill`, but streams the summary as the
+    /// AI writes it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a stream of the pieces of the summary.
+    #[instrument(skip(self, conversation, profile, variables))]
+    pub async fn distill_stream(
+        &self,
+        conversation: &Conversation,
+        profile: &ToneProfile,
+        variables: &HashMap<String, String>,
+    ) -> Result<TokenStream> {
+        info!(profile_name = %profile.name, "Distilling conversation.");
+
+        let system_prompt = profile.render_system_prompt(variables)?;
+        let user_prompt = self.format_conversation_for_prompt(conversation);
+
+        self.client
+            .stream_request(&system_prompt, &user_prompt)
+            .await
+    }
+
+    /// Grades a draft summary against the transcript for faithfulness and coverage.
+    ///
+    /// # Arguments
+    ///
+    /// * `conversation` - The conversation transcript the draft was based on.
+    /// * `draft` - The summary produced by `distill`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the structured `Critique`.
+    #[instrument(skip(self, conversation, draft))]
+    pub async fn critique(&self, conversation: &Conversation, draft: &str) -> Result<Critique> {
+        info!("Running self-critique quality pass.");
+
+        let system_prompt = critique::build_critique_prompt();
+        let user_prompt = format!(
+            "{}\n\nDRAFT SUMMARY:\n---\n{}\n---",
+            self.format_conversation_for_prompt(conversation),
+            draft
+        );
+
+        let response_text = self
+            .client
+            .send_json_request(&system_prompt, &user_prompt)
+            .await?;
+
+        serde_json::from_str(&response_text).map_err(|e| {
+            Error::OpenAI(format!(
+                "Failed to parse critique response: {}. Response text: '{}'",
+                e, response_text
+            ))
+        })
+    }
+
+    /// Rewrites a draft summary so that it addresses the issues raised in a critique.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the revised summary as a `String`.
+    #[instrument(skip(self, conversation, profile, variables, draft, review))]
+    pub async fn revise(
+        &self,
+        conversation: &Conversation,
+        profile: &ToneProfile,
+        variables: &HashMap<String, String>,
+        draft: &str,
+        review: &Critique,
+    ) -> Result<String> {
+        info!(profile_name = %profile.name, "Revising summary based on critique.");
+
+        let system_prompt =
+            critique::build_revision_prompt(&profile.render_system_prompt(variables)?);
+        let user_prompt = format!(
+            "{}\n\nDRAFT SUMMARY:\n---\n{}\n---\n\nEDITOR REVIEW:\n{}",
+            self.format_conversation_for_prompt(conversation),
+            draft,
+            serde_json::to_string_pretty(review)?
+        );
+
+        self.client
+            .send_request(&system_prompt, &user_prompt)
             .await
     }
 
@@ -17,9 +17,10 @@ pub enum Error {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
-    /// Wraps errors from the `reqwest` HTTP client.
-    #[error("HTTP request error")]
-    Reqwest(#[from] reqwest::Error),
+    /// Wraps errors from requests to the OpenAI API, made with the shared
+    /// `ai-core` client.
+    #[error(transparent)]
+    Client(#[from] ai_core::Error),
 
     /// For errors returned specifically by the OpenAI API.
     #[error("OpenAI API error: {0}")]
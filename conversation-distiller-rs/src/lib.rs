@@ -0,0 +1,13 @@
+//! src/lib.rs
+//!
+//! The library behind the Intelligent Conversation Distillation Engine: tone
+//! profiles, transcript parsing, and the engine that distills, critiques,
+//! and revises summaries. The command-line interface in `main.rs` and the
+//! tests in `tests/` are built on it.
+
+pub mod config;
+pub mod conversation_parser;
+pub mod critique;
+pub mod distiller_engine;
+pub mod error;
+pub mod openai_client;
@@ -4,17 +4,18 @@
 //! This module ties all other components together to form a cohesive
 //! command-line application.
 
-// Declare the module hierarchy for the compiler.
-mod config;
-mod conversation_parser;
-mod distiller_engine;
-mod error;
+// The logger belongs to the binary; the engine itself is in the library.
 mod logger;
-mod openai_client;
 
-use crate::error::Result;
+use ai_core::ProviderConfig;
 use clap::Parser;
 use colored::Colorize;
+use conversation_distiller_rs::critique::CritiqueMode;
+use conversation_distiller_rs::error::{self, Result};
+use conversation_distiller_rs::{config, conversation_parser, distiller_engine, openai_client};
+use futures::TryStreamExt;
+use std::collections::HashMap;
+use std::io::{self, Write};
 use std::path::PathBuf;
 use tracing::{error, info};
 
@@ -33,6 +34,25 @@ struct Args {
     /// The name of the tone profile to use for the summary (e.g., 'executive_briefing').
     #[arg(short, long)]
     profile_name: String,
+
+    /// A template variable for the profile's prompt, as KEY=VALUE (e.g., 'company=Acme').
+    /// Can be repeated; fills `{{KEY}}` placeholders in the system prompt.
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = config::parse_template_var)]
+    vars: Vec<(String, String)>,
+
+    /// Run a second model pass that grades the summary for faithfulness and coverage.
+    /// 'attach' prints the critique with the summary; 'revise' also rewrites the summary.
+    #[arg(long, value_enum, default_value_t = CritiqueMode::Off)]
+    critique: CritiqueMode,
+
+    /// Abort once the run has cost this many US dollars (default: AI_MAX_COST).
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Reuse the replies cached by an earlier run on the same transcript,
+    /// instead of calling the API. `AI_CACHE=1` does the same.
+    #[arg(long)]
+    cache: bool,
 }
 
 /// The main asynchronous function that orchestrates the application.
@@ -89,43 +109,105 @@ async fn run() -> Result<()> {
 
     info!("Using selected profile: '{}'", selected_profile.name);
 
+    // Collect the template variables supplied on the command line.
+    let variables: HashMap<String, String> = args.vars.into_iter().collect();
+
     // Load the conversation transcript from the specified input file.
     let conversation = conversation_parser::load_conversation(&args.input_file)?;
     info!("Successfully loaded conversation with {} turns.", conversation.conversation.len());
 
-    // Load the OpenAI API key and create the client.
-    let api_key = load_api_key()?;
-    let openai_client = openai_client::OpenAIClient::new(api_key);
+    // Load the model provider settings and create the client.
+    let provider_config = load_provider_config()?
+        .track_usage(env!("CARGO_PKG_NAME"), args.max_cost)
+        .cache_responses(args.cache);
+    let openai_client = openai_client::OpenAIClient::new(&provider_config)?;
 
     // Create the distiller engine instance.
     let engine = distiller_engine::DistillerEngine::new(openai_client);
 
     // --- Distillation ---
     // Perform the distillation using the selected conversation and profile.
-    let summary = engine.distill(&conversation, &selected_profile).await?;
+    // Unless the draft may still be revised, it is printed as it is written.
+    let streamed = args.critique != CritiqueMode::Revise;
+    let mut summary = if streamed {
+        let mut tokens = engine
+            .distill_stream(&conversation, &selected_profile, &variables)
+            .await?;
+        print_summary_header(&selected_profile.name);
+        let mut summary = String::new();
+        while let Some(token) = tokens.try_next().await? {
+            print!("{}", token);
+            io::stdout().flush()?;
+            summary.push_str(&token);
+        }
+        println!();
+        print_summary_footer();
+        summary
+    } else {
+        engine
+            .distill(&conversation, &selected_profile, &variables)
+            .await?
+    };
+
+    // --- Quality Review ---
+    // Optionally grade the draft and, if requested, revise it once.
+    let review = match args.critique {
+        CritiqueMode::Off => None,
+        CritiqueMode::Attach | CritiqueMode::Revise => {
+            Some(engine.critique(&conversation, &summary).await?)
+        }
+    };
+
+    if let Some(review) = &review {
+        info!(
+            faithfulness = review.faithfulness_score,
+            coverage = review.coverage_score,
+            "Quality review complete."
+        );
+        if args.critique == CritiqueMode::Revise && review.needs_revision() {
+            summary = engine
+                .revise(&conversation, &selected_profile, &variables, &summary, review)
+                .await?;
+        }
+    }
 
     // --- Display Results ---
-    print_summary(&selected_profile.name, &summary);
+    if !streamed {
+        print_summary(&selected_profile.name, &summary);
+    }
+    if let (CritiqueMode::Attach, Some(review)) = (args.critique, &review) {
+        review.print();
+    }
 
     Ok(())
 }
 
-/// Loads the OpenAI API key from the environment variables.
-fn load_api_key() -> Result<String> {
+/// Loads the model provider and its settings, such as the OpenAI API key,
+/// from the environment variables.
+fn load_provider_config() -> Result<ProviderConfig> {
     dotenvy::dotenv().ok();
-    std::env::var("OPENAI_API_KEY")
-        .map_err(|_| error::Error::Config("OPENAI_API_KEY not found in environment.".to_string()))
+    Ok(ProviderConfig::from_env()?)
 }
 
 /// Prints the final summary to the console in a formatted block.
 fn print_summary(profile_name: &str, summary: &str) {
+    print_summary_header(profile_name);
+    println!("{}", summary);
+    print_summary_footer();
+}
+
+/// Prints the line that opens the summary block.
+fn print_summary_header(profile_name: &str) {
     println!(
         "\n{}",
         format!("--- Distilled Summary: {} ---", profile_name)
             .bold()
             .cyan()
     );
-    println!("{}", summary);
+}
+
+/// Prints the line that closes the summary block.
+fn print_summary_footer() {
     println!(
         "{}",
         "--- End of Summary ---".bold().cyan()
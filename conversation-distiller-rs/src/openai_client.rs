@@ -2,29 +2,34 @@
 //!
 //! This module provides a dedicated client for interacting with the OpenAI API.
 //! It encapsulates all the logic for creating and sending requests, as well as
-//! handling the responses in a structured way.
+//! handling the responses in a structured way. The requests are sent to the
+//! provider chosen by the shared `ai-core` crate's `ProviderConfig`, OpenAI
+//! by default.
 
-use crate::error::{Error, Result};
-use serde::{Deserialize, Serialize};
+use crate::error::Result;
+use ai_core::{ChatProvider, ChatRequest, ProviderConfig, TokenStream};
+use std::sync::Arc;
 use tracing::{info, instrument};
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
+/// The model used on OpenAI, unless `AI_MODEL` names another.
 const AI_MODEL_NAME: &str = "gpt-4o";
 
-/// A client for making requests to the OpenAI Chat Completions API.
+/// A client for making requests to the OpenAI Chat Completions API, or to
+/// the configured provider.
 #[derive(Clone)]
 pub struct OpenAIClient {
-    http_client: reqwest::Client,
-    api_key: String,
+    provider: Arc<dyn ChatProvider>,
+    model: String,
 }
 
 impl OpenAIClient {
-    /// Creates a new instance of the `OpenAIClient`.
-    pub fn new(api_key: String) -> Self {
-        Self {
-            http_client: reqwest::Client::new(),
-            api_key,
-        }
+    /// Creates a new instance of the `OpenAIClient` for the configured
+    /// provider and model.
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            provider: config.chat_provider()?,
+            model: config.chat_model(AI_MODEL_NAME),
+        })
     }
 
     /// Sends a request to the OpenAI API with a system and user prompt.
@@ -39,68 +44,46 @@ impl OpenAIClient {
     /// A `Result` containing the content of the AI's response as a `String`.
     #[instrument(skip(self, system_prompt, user_prompt))]
     pub async fn send_request(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
-        info!("Sending request to OpenAI API.");
-
-        let messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: user_prompt.to_string(),
-            },
-        ];
-
-        let body = ChatCompletionRequest {
-            model: AI_MODEL_NAME.to_string(),
-            messages,
-        };
-
-        // Send the request and handle potential errors robustly.
-        let response: ChatCompletionResponse = self
-            .http_client
-            .post(OPENAI_API_URL)
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()? // This is crucial for catching non-2xx responses.
-            .json()
-            .await?;
-
-        // Extract the message content from the first choice in the response.
-        if let Some(choice) = response.choices.into_iter().next() {
-            info!("Successfully received response from OpenAI API.");
-            Ok(choice.message.content)
-        } else {
-            Err(Error::OpenAI(
-                "API response did not contain any choices.".to_string(),
-            ))
-        }
+        let request = ChatRequest::from_prompts(&self.model, system_prompt, user_prompt);
+        self.send_chat(&request).await
     }
-}
 
-//========= API Data Structures =========//
-
-#[derive(Serialize, Debug)]
-struct ChatCompletionRequest {
-    model: String,
-    messages: Vec<Message>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Message {
-    role: String,
-    content: String,
-}
+    /// Sends a request like `send_request`, but streams the response.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a stream of the pieces of the AI's response,
+    /// each yielded as soon as it arrives.
+    #[instrument(skip(self, system_prompt, user_prompt))]
+    pub async fn stream_request(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<TokenStream> {
+        let request = ChatRequest::from_prompts(&self.model, system_prompt, user_prompt);
+        info!("Sending streamed request to {}.", self.model);
+        Ok(self.provider.chat_stream(&request).await?)
+    }
 
-#[derive(Deserialize, Debug)]
-struct ChatCompletionResponse {
-    choices: Vec<Choice>,
-}
+    /// Sends a request that instructs the API to return a single JSON object.
+    ///
+    /// The system prompt must still describe the expected JSON structure; this
+    /// only enables the API's JSON mode so the content is guaranteed to parse.
+    #[instrument(skip(self, system_prompt, user_prompt))]
+    pub async fn send_json_request(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<String> {
+        let request = ChatRequest::from_prompts(&self.model, system_prompt, user_prompt).json();
+        self.send_chat(&request).await
+    }
 
-#[derive(Deserialize, Debug)]
-struct Choice {
-    message: Message,
+    /// Sends a chat completion request, returning the first choice.
+    async fn send_chat(&self, request: &ChatRequest) -> Result<String> {
+        info!("Sending request to {}.", self.model);
+        let content = self.provider.complete(request).await?;
+        info!("Successfully received response from {}.", self.model);
+        Ok(content)
+    }
 }
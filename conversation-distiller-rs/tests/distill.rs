@@ -0,0 +1,154 @@
+//! tests/distill.rs
+//!
+//! Distills the sample support conversation through the mock provider: the
+//! recorded briefing and its critique, and the prompts of the template
+//! variables, the critique, and the revision round.
+
+use ai_core::{MockClient, ProviderConfig, ResponseFormat};
+use conversation_distiller_rs::config::{self, ToneProfile};
+use conversation_distiller_rs::conversation_parser::{self, Conversation};
+use conversation_distiller_rs::critique::Critique;
+use conversation_distiller_rs::distiller_engine::DistillerEngine;
+use conversation_distiller_rs::error::Error;
+use conversation_distiller_rs::openai_client::OpenAIClient;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+fn sample_conversation() -> Conversation {
+    conversation_parser::load_conversation(Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/input/sample_conversation.json"
+    )))
+    .unwrap()
+}
+
+fn tone_profile(name: &str) -> ToneProfile {
+    config::load_tone_profiles()
+        .unwrap()
+        .profiles
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn briefs_and_reviews_the_sample_conversation_as_recorded() {
+    let mock = MockClient::fixture(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/distill_executive_briefing.json"
+    ))
+    .unwrap();
+    let engine =
+        DistillerEngine::new(OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap());
+    let conversation = sample_conversation();
+
+    let draft = engine
+        .distill(
+            &conversation,
+            &tone_profile("executive_briefing"),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+    assert!(draft.contains("'Authentication Error'"));
+
+    let review = engine.critique(&conversation, &draft).await.unwrap();
+    assert_eq!((review.faithfulness_score, review.coverage_score), (9, 8));
+    assert!(review.hallucinated_claims.is_empty());
+    assert_eq!(review.missing_points.len(), 1);
+    assert!(review.needs_revision());
+}
+
+#[tokio::test]
+async fn fills_the_template_variables_and_sends_the_transcript_as_a_script() {
+    let mock = Arc::new(MockClient::new(["An update."]));
+    let engine =
+        DistillerEngine::new(OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap());
+    let variables = HashMap::from([("company".to_string(), "Acme".to_string())]);
+
+    engine
+        .distill(
+            &sample_conversation(),
+            &tone_profile("team_update"),
+            &variables,
+        )
+        .await
+        .unwrap();
+
+    let request = &mock.requests()[0];
+    let system_prompt = &request.messages[0].content;
+    // The override replaces one variable; the profile's defaults fill the rest.
+    assert!(system_prompt.contains("specialist at Acme."));
+    assert!(system_prompt.contains("as an update for the support team."));
+    assert!(system_prompt.contains("under 150 words."));
+    assert!(!system_prompt.contains("{{"));
+
+    let transcript = &request.messages[1].content;
+    assert!(transcript.starts_with("CONVERSATION TRANSCRIPT:\n---\nUser: Hi, I'm having trouble"));
+    assert!(transcript.contains("\nBot: Hello! I'm sorry to hear"));
+    assert!(transcript.ends_with("---\nEND OF TRANSCRIPT"));
+    assert_eq!(request.response_format, None);
+}
+
+#[tokio::test]
+async fn asks_for_a_json_critique_of_the_draft_and_rejects_prose() {
+    let mock = Arc::new(MockClient::new(["The summary looks good."]));
+    let engine =
+        DistillerEngine::new(OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap());
+
+    let result = engine
+        .critique(&sample_conversation(), "The user could not log in.")
+        .await;
+    assert!(matches!(result, Err(Error::OpenAI(_))));
+
+    let request = &mock.requests()[0];
+    assert_eq!(request.response_format, Some(ResponseFormat::JsonObject));
+    assert!(request.messages[0]
+        .content
+        .contains("\"hallucinatedClaims\""));
+    assert!(request.messages[1]
+        .content
+        .ends_with("DRAFT SUMMARY:\n---\nThe user could not log in.\n---"));
+}
+
+#[tokio::test]
+async fn revises_with_the_profile_prompt_and_the_editor_review() {
+    let mock = Arc::new(MockClient::new(["A revised briefing."]));
+    let engine =
+        DistillerEngine::new(OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap());
+    let profile = tone_profile("developer_debrief");
+    let review = Critique {
+        faithfulness_score: 6,
+        coverage_score: 7,
+        hallucinated_claims: vec!["The user reset their password.".to_string()],
+        missing_points: Vec::new(),
+        assessment: "One unsupported claim.".to_string(),
+    };
+
+    let revised = engine
+        .revise(
+            &sample_conversation(),
+            &profile,
+            &HashMap::new(),
+            "A draft.",
+            &review,
+        )
+        .await
+        .unwrap();
+    assert_eq!(revised, "A revised briefing.");
+
+    let request = &mock.requests()[0];
+    assert!(request.messages[0]
+        .content
+        .starts_with(&profile.system_prompt));
+    assert!(request.messages[0]
+        .content
+        .contains("removes every unsupported claim"));
+    assert!(request.messages[1]
+        .content
+        .contains("EDITOR REVIEW:\n{\n  \"faithfulnessScore\": 6,"));
+    assert!(request.messages[1]
+        .content
+        .contains("\"The user reset their password.\""));
+}
@@ -1,28 +1,26 @@
 //! src/config.rs
 //!
-//! Manages the application's configuration, primarily loading the
-//! OpenAI API key from the environment.
+//! Manages the application's configuration, primarily loading the model
+//! provider and its settings, such as the OpenAI API key, from the
+//! environment.
 
-use crate::error::{Error, Result};
+use crate::error::Result;
+use ai_core::ProviderConfig;
 use dotenvy::dotenv;
-use std::env;
 
-/// Loads the OpenAI API key from the environment.
+/// Loads the model provider settings from the environment.
 ///
 /// # Returns
 ///
-/// A `Result` containing the API key as a `String` on success.
+/// A `Result` containing the `ProviderConfig` on success. Its settings, such
+/// as `OPENAI_API_KEY`, are checked when the client is created.
 ///
 /// # Errors
 ///
-/// Returns `Error::Config` if the `OPENAI_API_KEY` environment variable is not set.
-pub fn get_api_key() -> Result<String> {
+/// Returns an error if `AI_PROVIDER` names an unknown provider.
+pub fn get_provider_config() -> Result<ProviderConfig> {
     // Load environment variables from a .env file if it exists.
     dotenv().ok();
 
-    env::var("OPENAI_API_KEY").map_err(|_| {
-        Error::Config(
-            "The OPENAI_API_KEY environment variable must be set.".to_string(),
-        )
-    })
+    Ok(ProviderConfig::from_env()?)
 }
@@ -0,0 +1,13 @@
+//! src/lib.rs
+//!
+//! The library behind the Explainable Credit Risk Assessment Engine: the
+//! applicant and assessment models, profile validation, and the analyzer
+//! that asks the model for an assessment. `main.rs` wraps it in a
+//! command-line interface, and the tests in `tests/` drive it directly.
+
+pub mod config;
+pub mod error;
+pub mod models;
+pub mod openai_client;
+pub mod risk_analyzer;
+pub mod validator;
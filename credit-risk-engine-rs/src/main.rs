@@ -4,19 +4,14 @@
 //! This module orchestrates the entire workflow, from parsing command-line
 //! arguments to printing the final, structured risk assessment.
 
-// Declare the module hierarchy for the compiler.
-mod config;
-mod error;
+// Only the logger is private to the binary; the engine is in the library.
 mod logger;
-mod models;
-mod openai_client;
-mod risk_analyzer;
-mod validator;
 
-use crate::error::Result;
 use clap::Parser;
 use colored::Colorize;
-use risk_analyzer::RiskAnalyzer;
+use credit_risk_engine_rs::error::Result;
+use credit_risk_engine_rs::risk_analyzer::RiskAnalyzer;
+use credit_risk_engine_rs::{config, openai_client, validator};
 use std::path::PathBuf;
 use tracing::{error, info};
 
@@ -31,6 +26,15 @@ struct Args {
     /// The path to the applicant's profile JSON file.
     #[arg(required = true)]
     input_file: PathBuf,
+
+    /// Stop once the assessment has cost this many US dollars (default: AI_MAX_COST).
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Reuse the cached reply if the profile was assessed before, instead of
+    /// assessing it again. `AI_CACHE=1` does the same.
+    #[arg(long)]
+    cache: bool,
 }
 
 /// The main asynchronous function that orchestrates the application.
@@ -56,8 +60,10 @@ async fn run() -> Result<()> {
     let profile = validator::load_and_validate_profile(&args.input_file)?;
     info!(applicant_id = %profile.applicant_id, "Applicant profile successfully validated.");
 
-    let api_key = config::get_api_key()?;
-    let openai_client = openai_client::OpenAIClient::new(api_key);
+    let provider_config = config::get_provider_config()?
+        .track_usage(env!("CARGO_PKG_NAME"), args.max_cost)
+        .cache_responses(args.cache);
+    let openai_client = openai_client::OpenAIClient::new(&provider_config)?;
     let analyzer = RiskAnalyzer::new(openai_client);
 
     // --- Assessment ---
@@ -5,6 +5,7 @@
 //! structured output from the risk assessment engine, ensuring type safety
 //! and data integrity throughout the system.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
@@ -62,8 +63,8 @@ pub struct ApplicantProfile {
 /// Represents the structured risk assessment generated by the AI.
 ///
 /// This is the schema that the AI will be instructed to return, providing a
-/// detailed and auditable analysis.
-#[derive(Serialize, Deserialize, Debug)]
+/// detailed and auditable analysis. Its JSON schema is sent with the request.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct RiskAssessment {
     #[serde(rename = "riskScore")]
     pub risk_score: u32, // A score from 1 (lowest risk) to 10 (highest risk).
@@ -81,7 +82,7 @@ pub struct RiskAssessment {
 }
 
 /// Defines the possible recommendations from the assessment.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Recommendation {
     Approve,
@@ -2,110 +2,59 @@
 //!
 //! This module provides a dedicated client for interacting with the OpenAI API.
 //! It encapsulates all the logic for creating and sending requests, and for
-//! handling the responses in a structured way.
-
-use crate::error::{Error, Result};
-use serde::{Deserialize, Serialize};
+//! handling the responses in a structured way. The requests are sent to the
+//! provider chosen by the shared `ai-core` crate's `ProviderConfig`, OpenAI
+//! by default.
+
+use crate::error::Result;
+use ai_core::{ChatProvider, ChatRequest, ProviderConfig};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
 use tracing::{info, instrument};
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
+/// The model used on OpenAI, unless `AI_MODEL` names another.
 const AI_MODEL_NAME: &str = "gpt-4o";
 
-/// A client for making requests to the OpenAI Chat Completions API.
+/// A client for making requests to the OpenAI Chat Completions API, or to
+/// the configured provider.
 #[derive(Clone)]
 pub struct OpenAIClient {
-    http_client: reqwest::Client,
-    api_key: String,
+    provider: Arc<dyn ChatProvider>,
+    model: String,
 }
 
 impl OpenAIClient {
-    /// Creates a new instance of the `OpenAIClient`.
-    pub fn new(api_key: String) -> Self {
-        Self {
-            http_client: reqwest::Client::new(),
-            api_key,
-        }
+    /// Creates a new instance of the `OpenAIClient` for the configured
+    /// provider and model.
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            provider: config.chat_provider()?,
+            model: config.chat_model(AI_MODEL_NAME),
+        })
     }
 
-    /// Sends a request to the OpenAI API with a system and user prompt.
+    /// Sends a request to the OpenAI API with a system and user prompt, asking
+    /// for a reply that matches the JSON schema of `T`.
     ///
     /// # Returns
     ///
-    /// A `Result` containing the content of the AI's response as a `String`.
+    /// A `Result` containing the AI's response, read into a `T`.
     #[instrument(skip(self, system_prompt, user_prompt))]
-    pub async fn send_request(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
-        info!("Sending request to OpenAI API.");
-
-        let messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: user_prompt.to_string(),
-            },
-        ];
-
-        let body = ChatCompletionRequest {
-            model: AI_MODEL_NAME.to_string(),
-            messages,
-            response_format: Some(ResponseFormat {
-                format_type: "json_object".to_string(),
-            }),
-        };
-
-        // Send the request and handle potential errors robustly.
-        let response: ChatCompletionResponse = self
-            .http_client
-            .post(OPENAI_API_URL)
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()? // This is crucial for catching non-2xx responses.
-            .json()
-            .await?;
-
-        // Extract the message content from the first choice in the response.
-        if let Some(choice) = response.choices.into_iter().next() {
-            info!("Successfully received structured response from OpenAI API.");
-            Ok(choice.message.content)
-        } else {
-            Err(Error::OpenAI(
-                "API response did not contain any choices.".to_string(),
-            ))
-        }
+    pub async fn send_request<T: DeserializeOwned + JsonSchema>(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<T> {
+        info!("Sending request to {}.", self.model);
+
+        let request = ChatRequest::from_prompts(&self.model, system_prompt, user_prompt);
+        let content = self.provider.complete_structured(&request).await?;
+
+        info!(
+            "Successfully received structured response from {}.",
+            self.model
+        );
+        Ok(content)
     }
 }
-
-//========= API Data Structures =========//
-
-#[derive(Serialize, Debug)]
-struct ResponseFormat {
-    #[serde(rename = "type")]
-    format_type: String,
-}
-
-#[derive(Serialize, Debug)]
-struct ChatCompletionRequest {
-    model: String,
-    messages: Vec<Message>,
-    response_format: Option<ResponseFormat>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Message {
-    role: String,
-    content: String,
-}
-
-#[derive(Deserialize, Debug)]
-struct ChatCompletionResponse {
-    choices: Vec<Choice>,
-}
-
-#[derive(Deserialize, Debug)]
-struct Choice {
-    message: Message,
-}
@@ -2,7 +2,8 @@
 //!
 //! This module contains the core logic for the risk assessment engine.
 //! It is responsible for constructing the detailed prompt for the OpenAI API,
-//! sending the request, and parsing the structured JSON response.
+//! sending the request with the JSON schema of the assessment, and reading
+//! the structured JSON response.
 
 use crate::error::Result;
 use crate::models::{ApplicantProfile, RiskAssessment};
@@ -25,8 +26,9 @@ impl RiskAnalyzer {
     /// This function orchestrates the entire analysis process:
     /// 1. Serializes the applicant's profile into a JSON string.
     /// 2. Constructs a sophisticated, multi-part system prompt.
-    /// 3. Sends the request to the OpenAI API.
-    /// 4. Parses the returned JSON string into a `RiskAssessment` struct.
+    /// 3. Sends the request to the OpenAI API with the JSON schema of a
+    ///    `RiskAssessment`.
+    /// 4. Reads the returned JSON into a `RiskAssessment` struct.
     ///
     /// # Arguments
     ///
@@ -42,10 +44,11 @@ impl RiskAnalyzer {
         let profile_json = serde_json::to_string_pretty(profile)?;
         let system_prompt = self.build_system_prompt();
         
-        let response_text = self.client.send_request(&system_prompt, &profile_json).await?;
-
-        // Parse the JSON string response from the AI into our target struct.
-        let assessment: RiskAssessment = serde_json::from_str(&response_text)?;
+        // The client reads the AI's response into our target struct.
+        let assessment: RiskAssessment = self
+            .client
+            .send_request(&system_prompt, &profile_json)
+            .await?;
         info!(applicant_id = %profile.applicant_id, "Successfully completed and parsed risk assessment.");
 
         Ok(assessment)
@@ -0,0 +1,93 @@
+//! tests/assess.rs
+//!
+//! Assesses the sample applicant with the recorded reply, and checks with
+//! scripted replies that the profile and the assessment schema are what the
+//! model is sent, and that only the three known recommendations are read.
+
+use ai_core::{MockClient, ProviderConfig, ResponseFormat};
+use credit_risk_engine_rs::error::Error;
+use credit_risk_engine_rs::models::{ApplicantProfile, Recommendation};
+use credit_risk_engine_rs::openai_client::OpenAIClient;
+use credit_risk_engine_rs::risk_analyzer::RiskAnalyzer;
+use credit_risk_engine_rs::validator;
+use std::path::Path;
+use std::sync::Arc;
+
+fn sample_applicant() -> ApplicantProfile {
+    validator::load_and_validate_profile(Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/input_profiles/sample_applicant.json"
+    )))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn recommends_a_manual_review_of_the_sample_applicant() {
+    let mock = MockClient::fixture(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/assess_sample_applicant.json"
+    ))
+    .unwrap();
+    let analyzer =
+        RiskAnalyzer::new(OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap());
+
+    let assessment = analyzer.assess(&sample_applicant()).await.unwrap();
+
+    assert_eq!(assessment.risk_score, 4);
+    assert_eq!(assessment.recommendation, Recommendation::ManualReview);
+    assert_eq!(assessment.positive_factors.len(), 3);
+    assert!(assessment.negative_factors[0].contains("680"));
+}
+
+#[tokio::test]
+async fn sends_the_profile_as_json_with_the_assessment_schema() {
+    let mock = Arc::new(MockClient::new([r#"{
+        "riskScore": 2,
+        "recommendation": "APPROVE",
+        "positiveFactors": ["Stable income."],
+        "negativeFactors": [],
+        "detailedRationale": "Low risk."
+    }"#]));
+    let analyzer =
+        RiskAnalyzer::new(OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap());
+    let applicant = sample_applicant();
+    analyzer.assess(&applicant).await.unwrap();
+
+    let requests = mock.requests();
+    let [request] = requests.as_slice() else {
+        panic!("expected a single request, got {}", requests.len());
+    };
+    let (system_prompt, user_prompt) = (&request.messages[0].content, &request.messages[1].content);
+    assert!(system_prompt.contains("income-to-debt ratio, credit score, employment stability"));
+    assert!(system_prompt.contains("one of: 'APPROVE', 'DENY', 'MANUAL_REVIEW'"));
+    assert_eq!(
+        user_prompt,
+        &serde_json::to_string_pretty(&applicant).unwrap()
+    );
+
+    let Some(ResponseFormat::JsonSchema { json_schema }) = &request.response_format else {
+        panic!("the assessment is not requested with a JSON schema");
+    };
+    assert_eq!(json_schema.name, "RiskAssessment");
+    assert_eq!(
+        json_schema.schema["definitions"]["Recommendation"]["enum"],
+        serde_json::json!(["APPROVE", "DENY", "MANUAL_REVIEW"])
+    );
+}
+
+#[tokio::test]
+async fn rejects_recommendations_outside_the_three_known_ones() {
+    let mock = MockClient::new([r#"{
+        "riskScore": 5,
+        "recommendation": "MAYBE",
+        "positiveFactors": [],
+        "negativeFactors": [],
+        "detailedRationale": "Unsure."
+    }"#]);
+    let analyzer =
+        RiskAnalyzer::new(OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap());
+
+    let result = analyzer.assess(&sample_applicant()).await;
+
+    assert!(matches!(result, Err(Error::Client(ai_core::Error::Api(_)))));
+}
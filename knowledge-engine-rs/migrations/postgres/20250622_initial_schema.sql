@@ -0,0 +1,34 @@
+-- migrations/postgres/20250622_initial_schema.sql
+--
+-- This migration script sets up the initial database schema for the
+-- knowledge engine on PostgreSQL. It mirrors the SQLite schema, but stores
+-- the embeddings with the pgvector extension so that similarity search can
+-- run inside the database.
+
+CREATE EXTENSION IF NOT EXISTS vector;
+
+-- The `documents` table stores metadata about the original source files
+-- that have been ingested into the knowledge base.
+CREATE TABLE IF NOT EXISTS documents (
+    id BIGSERIAL PRIMARY KEY,
+    file_path TEXT NOT NULL UNIQUE,
+    content_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- The `chunks` table stores the individual text chunks derived from the
+-- source documents, along with their vector embeddings.
+CREATE TABLE IF NOT EXISTS chunks (
+    id BIGSERIAL PRIMARY KEY,
+    document_id BIGINT NOT NULL REFERENCES documents (id) ON DELETE CASCADE,
+    chunk_text TEXT NOT NULL,
+    -- The dimensions match the `text-embedding-3-small` model. An HNSW index
+    -- needs a fixed dimension count.
+    embedding vector(1536) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS chunks_document_id_idx ON chunks (document_id);
+
+-- An approximate nearest-neighbor index for cosine distance (`<=>`).
+CREATE INDEX IF NOT EXISTS chunks_embedding_idx ON chunks USING hnsw (embedding vector_cosine_ops);
@@ -0,0 +1,11 @@
+-- migrations/postgres/20261018_chunk_keywords.sql
+--
+-- Adds a full-text keyword index of the chunk texts, so that exact
+-- identifiers, error codes, and names can be found alongside the vector
+-- similarity search. The `simple` configuration is used so that words are
+-- matched as written, without stemming or stop words.
+
+ALTER TABLE chunks ADD COLUMN IF NOT EXISTS search_vector tsvector
+    GENERATED ALWAYS AS (to_tsvector('simple', chunk_text)) STORED;
+
+CREATE INDEX IF NOT EXISTS chunks_search_vector_idx ON chunks USING gin (search_vector);
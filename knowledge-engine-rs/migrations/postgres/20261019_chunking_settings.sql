@@ -0,0 +1,7 @@
+-- migrations/postgres/20261019_chunking_settings.sql
+--
+-- Records the chunking settings each chunk was made with (strategy, size,
+-- and overlap), so that chunks split under different configurations can be
+-- told apart. Chunks indexed before this migration have none.
+
+ALTER TABLE chunks ADD COLUMN IF NOT EXISTS chunking TEXT;
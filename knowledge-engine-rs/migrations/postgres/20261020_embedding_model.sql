@@ -0,0 +1,11 @@
+-- migrations/postgres/20261020_embedding_model.sql
+--
+-- Records the model each chunk was embedded with, so that chunks embedded
+-- with an outdated model can be found and re-embedded. Embeddings of
+-- different models can't be compared, so mixing them corrupts similarity
+-- scores. Every chunk stored before this migration was embedded with
+-- `text-embedding-3-small`, the only model the engine has used so far.
+
+ALTER TABLE chunks ADD COLUMN IF NOT EXISTS embedding_model TEXT;
+
+UPDATE chunks SET embedding_model = 'text-embedding-3-small' WHERE embedding_model IS NULL;
@@ -0,0 +1,8 @@
+-- migrations/sqlite/20261016_chunk_offsets.sql
+--
+-- Records where each chunk lies in the text of its source document, as
+-- character offsets, so that query answers can cite their sources precisely.
+-- Chunks indexed before this migration have no offsets.
+
+ALTER TABLE chunks ADD COLUMN start_offset INTEGER;
+ALTER TABLE chunks ADD COLUMN end_offset INTEGER;
@@ -0,0 +1,15 @@
+-- migrations/sqlite/20261017_document_tags.sql
+--
+-- Stores the key-value tags attached to documents at ingest time. Chunks
+-- carry the tags of their document, so queries can be restricted to the
+-- chunks of documents with given tags.
+
+CREATE TABLE IF NOT EXISTS document_tags (
+    document_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (document_id, key),
+    FOREIGN KEY (document_id) REFERENCES documents (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS document_tags_key_value_idx ON document_tags (key, value);
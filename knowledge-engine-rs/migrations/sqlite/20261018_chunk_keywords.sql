@@ -0,0 +1,27 @@
+-- migrations/sqlite/20261018_chunk_keywords.sql
+--
+-- Adds an FTS5 keyword index of the chunk texts, ranked with BM25, so that
+-- exact identifiers, error codes, and names can be found alongside the
+-- vector similarity search. The index is kept in sync with the `chunks`
+-- table by triggers, and filled with the chunks that already exist.
+
+CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
+    chunk_text,
+    content = 'chunks',
+    content_rowid = 'id'
+);
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+    INSERT INTO chunks_fts (rowid, chunk_text) VALUES (new.id, new.chunk_text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+    INSERT INTO chunks_fts (chunks_fts, rowid, chunk_text) VALUES ('delete', old.id, old.chunk_text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_update AFTER UPDATE OF chunk_text ON chunks BEGIN
+    INSERT INTO chunks_fts (chunks_fts, rowid, chunk_text) VALUES ('delete', old.id, old.chunk_text);
+    INSERT INTO chunks_fts (rowid, chunk_text) VALUES (new.id, new.chunk_text);
+END;
+
+INSERT INTO chunks_fts (chunks_fts) VALUES ('rebuild');
@@ -0,0 +1,309 @@
+//! src/archive.rs
+//!
+//! This module implements the `export` and `import` commands, which move a
+//! whole knowledge base between databases as a portable archive, so that it
+//! can be backed up, shared between machines, or seeded in CI without
+//! re-embedding every document.
+//!
+//! An archive is a zstd-compressed tar file. It starts with `manifest.json`,
+//! which records the format version and the embedding model, followed by one
+//! `documents/<n>.json` entry per document holding its tags and its chunks
+//! with their embeddings. Embeddings are stored as JSON numbers, so archives
+//! can be read regardless of the backend or the machine's byte order.
+
+use crate::database::{NewChunk, Storage, Tag};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use tracing::{info, instrument, warn};
+
+/// The version of the archive format written by `export`. `import` rejects
+/// archives with a newer version.
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_PATH: &str = "manifest.json";
+const DOCUMENTS_DIR: &str = "documents";
+
+/// The zstd compression level; the default trades speed and size evenly.
+const COMPRESSION_LEVEL: i32 = 0;
+
+/// Describes the contents of an archive.
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    format_version: u32,
+    /// The model that made the embeddings. Embeddings from different models
+    /// can't be searched together.
+    embedding_model: String,
+    document_count: usize,
+    chunk_count: usize,
+}
+
+/// A document with its tags and chunks, as stored in an archive.
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchivedDocument {
+    file_path: String,
+    content_hash: String,
+    tags: Vec<Tag>,
+    chunks: Vec<ArchivedChunk>,
+}
+
+/// A chunk and its embedding, as stored in an archive.
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchivedChunk {
+    text: String,
+    start_offset: Option<i64>,
+    end_offset: Option<i64>,
+    chunking: Option<String>,
+    embedding: Vec<f32>,
+    /// The model that made the embedding. Archives written before it was
+    /// recorded only name the model in their manifest.
+    #[serde(default)]
+    embedding_model: Option<String>,
+}
+
+/// What an export or import went through.
+#[derive(Debug, Default)]
+pub struct ArchiveStats {
+    /// The documents written to, or stored from, the archive.
+    pub documents: usize,
+    pub chunks: usize,
+    /// The documents an import left alone because the knowledge base
+    /// already holds the same content.
+    pub unchanged: usize,
+}
+
+/// Writes every document of the knowledge base, with its tags, chunks, and
+/// embeddings, to a new archive at `archive_path`, whose manifest names
+/// `embedding_model`, the model in use.
+#[instrument(skip(storage))]
+pub async fn export(
+    storage: &dyn Storage,
+    archive_path: &Path,
+    embedding_model: &str,
+) -> Result<ArchiveStats> {
+    let documents = storage.list_documents().await?;
+    info!(
+        "Exporting {} documents to '{}'...",
+        documents.len(),
+        archive_path.display()
+    );
+
+    let file = File::create(archive_path)?;
+    let mut builder = tar::Builder::new(zstd::Encoder::new(file, COMPRESSION_LEVEL)?);
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        embedding_model: embedding_model.to_string(),
+        document_count: documents.len(),
+        chunk_count: documents
+            .iter()
+            .map(|document| document.chunk_count as usize)
+            .sum(),
+    };
+    append_json(&mut builder, MANIFEST_PATH, &manifest)?;
+
+    let mut stats = ArchiveStats::default();
+    for (index, document) in documents.iter().enumerate() {
+        let tags = storage.document_tags(document.id).await?;
+        let chunks = storage.document_chunks(document.id).await?;
+        let mut embeddings: HashMap<i64, Vec<f32>> = storage
+            .document_embeddings(document.id)
+            .await?
+            .into_iter()
+            .map(|chunk| (chunk.id, chunk.embedding))
+            .collect();
+
+        let chunks = chunks
+            .into_iter()
+            .map(|chunk| {
+                let embedding = embeddings.remove(&chunk.id).ok_or_else(|| {
+                    Error::Archive(format!(
+                        "Chunk {} of '{}' has no embedding.",
+                        chunk.id, document.file_path
+                    ))
+                })?;
+                Ok(ArchivedChunk {
+                    text: chunk.chunk_text,
+                    start_offset: chunk.start_offset,
+                    end_offset: chunk.end_offset,
+                    chunking: chunk.chunking,
+                    embedding,
+                    embedding_model: chunk.embedding_model,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        stats.documents += 1;
+        stats.chunks += chunks.len();
+
+        let archived = ArchivedDocument {
+            file_path: document.file_path.clone(),
+            content_hash: document.content_hash.clone(),
+            tags,
+            chunks,
+        };
+        let entry_path = format!("{}/{:06}.json", DOCUMENTS_DIR, index + 1);
+        append_json(&mut builder, &entry_path, &archived)?;
+    }
+
+    builder.into_inner()?.finish()?.flush()?;
+    info!(
+        "Exported {} documents and {} chunks.",
+        stats.documents, stats.chunks
+    );
+    Ok(stats)
+}
+
+/// Stores the documents of the archive at `archive_path` in the knowledge
+/// base, with their tags, chunks, and embeddings.
+///
+/// Documents that the knowledge base already holds with the same content
+/// only get the archive's tags. Documents whose content differs are
+/// replaced by the archive's version. If the archive's embeddings were not
+/// made with `current_model`, a warning suggests running `reembed`.
+#[instrument(skip(storage))]
+pub async fn import(
+    storage: &dyn Storage,
+    archive_path: &Path,
+    current_model: &str,
+) -> Result<ArchiveStats> {
+    info!("Importing the archive '{}'...", archive_path.display());
+    let file = File::open(archive_path)?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+
+    let mut manifest: Option<Manifest> = None;
+    let mut stats = ArchiveStats::default();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().into_owned();
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+
+        if entry_path == MANIFEST_PATH {
+            manifest = Some(read_manifest(&content, current_model)?);
+            continue;
+        }
+        if !entry_path.starts_with(DOCUMENTS_DIR) {
+            warn!("Skipping the unknown archive entry '{}'.", entry_path);
+            continue;
+        }
+        if manifest.is_none() {
+            return Err(Error::Archive(format!(
+                "'{}' has no manifest before its documents.",
+                archive_path.display()
+            )));
+        }
+        let document: ArchivedDocument = serde_json::from_str(&content).map_err(|e| {
+            Error::Archive(format!("Invalid archive entry '{}': {}", entry_path, e))
+        })?;
+        let embedding_model = manifest
+            .as_ref()
+            .map_or(current_model, |manifest| &manifest.embedding_model);
+        if import_document(storage, &document, embedding_model).await? {
+            stats.documents += 1;
+            stats.chunks += document.chunks.len();
+        } else {
+            stats.unchanged += 1;
+        }
+    }
+
+    if manifest.is_none() {
+        return Err(Error::Archive(format!(
+            "'{}' is not a knowledge base archive: it has no manifest.",
+            archive_path.display()
+        )));
+    }
+    info!(
+        "Imported {} documents and {} chunks; {} documents were unchanged.",
+        stats.documents, stats.chunks, stats.unchanged
+    );
+    Ok(stats)
+}
+
+/// Reads an archive's manifest and checks that its documents can be used.
+fn read_manifest(content: &str, current_model: &str) -> Result<Manifest> {
+    let manifest: Manifest = serde_json::from_str(content)
+        .map_err(|e| Error::Archive(format!("Invalid archive manifest: {}", e)))?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(Error::Archive(format!(
+            "The archive has format version {}, but only versions up to {} can be imported.",
+            manifest.format_version, FORMAT_VERSION
+        )));
+    }
+    if manifest.embedding_model != current_model {
+        warn!(
+            "The archive's embeddings were made with '{}', not '{}'; run `reembed` after the import so that questions find its chunks reliably.",
+            manifest.embedding_model, current_model
+        );
+    }
+    info!(
+        "The archive holds {} documents and {} chunks.",
+        manifest.document_count, manifest.chunk_count
+    );
+    Ok(manifest)
+}
+
+/// Stores one archived document, unless the knowledge base already holds
+/// the same content, in which case only its tags are applied. Chunks that
+/// don't name their embedding model are recorded with `embedding_model`,
+/// the archive's.
+///
+/// # Returns
+///
+/// Whether the document's chunks were stored.
+async fn import_document(
+    storage: &dyn Storage,
+    document: &ArchivedDocument,
+    embedding_model: &str,
+) -> Result<bool> {
+    let id = match storage.find_document(&document.file_path).await? {
+        Some(existing) if existing.content_hash == document.content_hash => {
+            storage.tag_document(existing.id, &document.tags).await?;
+            return Ok(false);
+        }
+        Some(existing) => {
+            storage
+                .reset_document(existing.id, &document.content_hash)
+                .await?;
+            existing.id
+        }
+        None => {
+            storage
+                .insert_document(&document.file_path, &document.content_hash)
+                .await?
+        }
+    };
+    storage.tag_document(id, &document.tags).await?;
+
+    let chunks: Vec<NewChunk> = document
+        .chunks
+        .iter()
+        .map(|chunk| NewChunk {
+            document_id: id,
+            chunk_text: &chunk.text,
+            start_offset: chunk.start_offset,
+            end_offset: chunk.end_offset,
+            chunking: chunk.chunking.as_deref(),
+            embedding: chunk.embedding.clone(),
+            embedding_model: chunk.embedding_model.as_deref().unwrap_or(embedding_model),
+        })
+        .collect();
+    storage.insert_chunks(&chunks).await?;
+    Ok(true)
+}
+
+/// Appends `value` as a JSON file at `path` in the archive.
+fn append_json<W: Write, T: Serialize>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    value: &T,
+) -> Result<()> {
+    let data = serde_json::to_vec(value)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, path, data.as_slice())?;
+    Ok(())
+}
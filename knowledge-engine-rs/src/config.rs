@@ -1,34 +1,169 @@
 //! src/config.rs
 //!
 //! Manages the application's configuration, such as the database URL
-//! and the OpenAI API key.
+//! and the model providers.
 
 use crate::error::{Error, Result};
+use crate::pipeline::chunking::ChunkingConfig;
+use crate::query_engine::DEFAULT_CONTEXT_TOKEN_BUDGET;
+use crate::vector_index::DEFAULT_INDEX_PATH;
+use ai_core::{ProviderConfig, ProviderKind};
 use dotenvy::dotenv;
 use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
 
 const DATABASE_URL_KEY: &str = "DATABASE_URL";
-const OPENAI_API_KEY: &str = "OPENAI_API_KEY";
+const INDEX_PATH_KEY: &str = "INDEX_PATH";
+const SQLITE_VEC_PATH_KEY: &str = "SQLITE_VEC_PATH";
+const RERANK_MODEL_KEY: &str = "RERANK_MODEL";
+const DEFAULT_RERANK_MODEL: &str = "gpt-4o-mini";
+const QUERY_TRANSFORM_MODEL_KEY: &str = "QUERY_TRANSFORM_MODEL";
+const DEFAULT_QUERY_TRANSFORM_MODEL: &str = "gpt-4o-mini";
+const MIN_CONFIDENCE_KEY: &str = "MIN_CONFIDENCE";
+const CONTEXT_TOKEN_BUDGET_KEY: &str = "CONTEXT_TOKEN_BUDGET";
+const INGEST_CONCURRENCY_KEY: &str = "INGEST_CONCURRENCY";
+const DEFAULT_INGEST_CONCURRENCY: usize = 8;
+const SERVER_API_KEY: &str = "SERVER_API_KEY";
+const CHUNK_STRATEGY_KEY: &str = "CHUNK_STRATEGY";
+const CHUNK_UNIT_KEY: &str = "CHUNK_UNIT";
+const CHUNK_SIZE_KEY: &str = "CHUNK_SIZE";
+const CHUNK_OVERLAP_KEY: &str = "CHUNK_OVERLAP";
 
 /// A struct to hold all application configuration.
 pub struct Config {
     pub database_url: String,
-    pub openai_api_key: String,
+    /// The providers and models for completions and embeddings.
+    pub provider: ProviderConfig,
+    /// Where the approximate nearest-neighbor index is saved.
+    pub index_path: PathBuf,
+    /// The `sqlite-vec` extension to load, if similarity search should run
+    /// inside SQLite.
+    pub sqlite_vec_path: Option<String>,
+    /// The model that scores retrieved chunks when reranking is enabled.
+    pub rerank_model: String,
+    /// The model that rewrites questions, writes hypothetical answers, or
+    /// paraphrases questions, when a query transformation or multi-query
+    /// expansion is enabled.
+    pub query_transform_model: String,
+    /// The confidence, from 0 to 100, below which queries decline to
+    /// answer, if abstention is enabled by default.
+    pub min_confidence: Option<u8>,
+    /// The most tokens of retrieved chunks packed into the answer prompt.
+    pub context_token_budget: usize,
+    /// How documents are split into chunks.
+    pub chunking: ChunkingConfig,
+    /// How many documents are chunked and embedded at a time.
+    pub ingest_concurrency: usize,
+    /// The bearer token the REST API requires, if any.
+    pub server_api_key: Option<String>,
 }
 
-/// Loads the application configuration from environment variables.
-pub fn load() -> Result<Config> {
+/// Loads the application configuration from environment variables. The
+/// usage of the providers is recorded in the shared usage ledger, and the
+/// run stops once it has cost `max_cost` (or `AI_MAX_COST`) US dollars.
+/// With `cache_responses`, or `AI_CACHE`, completions are answered from the
+/// shared response cache when the same request was sent before.
+pub fn load(max_cost: Option<f64>, cache_responses: bool) -> Result<Config> {
     // Load .env file if it exists.
     dotenv().ok();
 
     let database_url = env::var(DATABASE_URL_KEY)
         .map_err(|_| Error::Config(format!("{} must be set", DATABASE_URL_KEY)))?;
 
-    let openai_api_key = env::var(OPENAI_API_KEY)
-        .map_err(|_| Error::Config(format!("{} must be set", OPENAI_API_KEY)))?;
+    let provider = ProviderConfig::from_env()?
+        .track_usage(env!("CARGO_PKG_NAME"), max_cost)
+        .cache_responses(cache_responses);
+
+    let index_path = env::var(INDEX_PATH_KEY)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_INDEX_PATH));
+
+    let sqlite_vec_path = env::var(SQLITE_VEC_PATH_KEY).ok();
+
+    let rerank_model = env::var(RERANK_MODEL_KEY)
+        .unwrap_or_else(|_| auxiliary_model(&provider, DEFAULT_RERANK_MODEL));
+
+    let query_transform_model = env::var(QUERY_TRANSFORM_MODEL_KEY)
+        .unwrap_or_else(|_| auxiliary_model(&provider, DEFAULT_QUERY_TRANSFORM_MODEL));
+
+    let min_confidence: Option<u8> = parse_var(MIN_CONFIDENCE_KEY)?;
+    if min_confidence.is_some_and(|min_confidence| min_confidence > 100) {
+        return Err(Error::Config(format!(
+            "{} must be between 0 and 100",
+            MIN_CONFIDENCE_KEY
+        )));
+    }
+
+    let context_token_budget =
+        parse_var(CONTEXT_TOKEN_BUDGET_KEY)?.unwrap_or(DEFAULT_CONTEXT_TOKEN_BUDGET);
+    if context_token_budget == 0 {
+        return Err(Error::Config(format!(
+            "{} must be positive",
+            CONTEXT_TOKEN_BUDGET_KEY
+        )));
+    }
+
+    let ingest_concurrency =
+        parse_var(INGEST_CONCURRENCY_KEY)?.unwrap_or(DEFAULT_INGEST_CONCURRENCY);
+    if ingest_concurrency == 0 {
+        return Err(Error::Config(format!(
+            "{} must be positive",
+            INGEST_CONCURRENCY_KEY
+        )));
+    }
+
+    let server_api_key = env::var(SERVER_API_KEY).ok().filter(|key| !key.is_empty());
+
+    let defaults = ChunkingConfig::default();
+    let chunking = ChunkingConfig {
+        strategy: parse_var(CHUNK_STRATEGY_KEY)?.unwrap_or(defaults.strategy),
+        unit: parse_var(CHUNK_UNIT_KEY)?.unwrap_or(defaults.unit),
+        size: parse_var(CHUNK_SIZE_KEY)?.unwrap_or(defaults.size),
+        overlap: parse_var(CHUNK_OVERLAP_KEY)?.unwrap_or(defaults.overlap),
+    };
+    if chunking.size == 0 || chunking.overlap >= chunking.size {
+        return Err(Error::Config(format!(
+            "{} must be positive and larger than {}",
+            CHUNK_SIZE_KEY, CHUNK_OVERLAP_KEY
+        )));
+    }
 
     Ok(Config {
         database_url,
-        openai_api_key,
+        provider,
+        index_path,
+        sqlite_vec_path,
+        rerank_model,
+        query_transform_model,
+        min_confidence,
+        context_token_budget,
+        chunking,
+        ingest_concurrency,
+        server_api_key,
     })
 }
+
+/// The model for an auxiliary task: the cheaper `openai_model` on OpenAI,
+/// and the chat model elsewhere, where OpenAI's model names don't exist.
+fn auxiliary_model(provider: &ProviderConfig, openai_model: &str) -> String {
+    match provider.provider {
+        ProviderKind::OpenAI => openai_model.to_string(),
+        _ => provider.chat_model(openai_model),
+    }
+}
+
+/// Reads and parses an optional environment variable.
+fn parse_var<T>(key: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    match env::var(key) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|e| Error::Config(format!("Invalid {}: {}", key, e))),
+        Err(_) => Ok(None),
+    }
+}
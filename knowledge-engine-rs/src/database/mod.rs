@@ -0,0 +1,303 @@
+//! src/database/mod.rs
+//!
+//! This module is responsible for all database interactions. It defines the
+//! `Storage` trait, which covers every query the rest of the application
+//! makes, and selects its implementation from the `DATABASE_URL`: a local
+//! SQLite file, or a PostgreSQL server with the pgvector extension, which
+//! lets several users share one knowledge base.
+
+mod postgres;
+mod sqlite;
+mod sqlite_vec;
+
+use crate::error::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use tracing::info;
+
+/// How many more candidates a similarity search fetches when its results are
+/// filtered by tags afterwards, so that enough of them are left.
+pub const FILTER_OVERSAMPLING: usize = 10;
+
+/// A document's ID and content hash, as stored in the `documents` table.
+pub struct StoredDocument {
+    pub id: i64,
+    pub content_hash: String,
+}
+
+/// A key-value tag attached to a document, written as `key=value`.
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct Tag {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for Tag {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(Tag {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err(format!("expected a tag as KEY=VALUE, got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+/// A document with a summary of its chunks, as listed by the `docs` commands.
+#[derive(FromRow, Serialize)]
+pub struct DocumentSummary {
+    pub id: i64,
+    pub file_path: String,
+    pub content_hash: String,
+    /// When the document was first ingested, as formatted by the database.
+    pub created_at: String,
+    pub chunk_count: i64,
+}
+
+/// A chunk of a document, without its embedding.
+#[derive(FromRow)]
+pub struct DocumentChunk {
+    pub id: i64,
+    pub chunk_text: String,
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+    /// The chunking settings, or `None` for chunks made before they were
+    /// recorded.
+    pub chunking: Option<String>,
+    /// The model the chunk was embedded with, if known.
+    pub embedding_model: Option<String>,
+}
+
+/// A text chunk and its embedding, ready to be stored.
+pub struct NewChunk<'a> {
+    pub document_id: i64,
+    pub chunk_text: &'a str,
+    /// The character offsets of the chunk in its document, or `None` for
+    /// chunks imported from a knowledge base that didn't record them.
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+    /// The chunking settings the chunk was made with, if known.
+    pub chunking: Option<&'a str>,
+    pub embedding: Vec<f32>,
+    /// The model that made the embedding.
+    pub embedding_model: &'a str,
+}
+
+/// A chunk's embedding, as stored in the `chunks` table.
+pub struct ChunkEmbedding {
+    pub id: i64,
+    pub embedding: Vec<f32>,
+}
+
+/// A chunk's text, to be embedded again.
+#[derive(FromRow)]
+pub struct ChunkText {
+    pub id: i64,
+    pub chunk_text: String,
+}
+
+/// The number of chunks embedded with a model.
+#[derive(FromRow, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct EmbeddingModelCount {
+    /// The model, or `None` for chunks whose model is unknown.
+    pub embedding_model: Option<String>,
+    pub chunk_count: i64,
+}
+
+/// A chunk's text and where it comes from.
+pub struct ChunkSource {
+    pub chunk_text: String,
+    pub document_path: String,
+    /// The character offsets of the chunk in its document, or `None` for
+    /// chunks indexed before offsets were recorded.
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+}
+
+/// The chunks in the database, summarized to tell whether an index built
+/// from them is stale.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct ChunkCoverage {
+    pub chunk_count: i64,
+    pub max_chunk_id: Option<i64>,
+    /// The chunks embedded with each model, which change when embeddings are
+    /// replaced in place even though the chunks stay the same.
+    pub embedding_models: Vec<EmbeddingModelCount>,
+}
+
+/// The storage backend of the knowledge base.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Looks up a document by its file path.
+    async fn find_document(&self, file_path: &str) -> Result<Option<StoredDocument>>;
+
+    /// Adds a document and returns its ID.
+    async fn insert_document(&self, file_path: &str, content_hash: &str) -> Result<i64>;
+
+    /// Records a document's new content hash and deletes its chunks, which
+    /// are about to be rebuilt from the new content.
+    async fn reset_document(&self, id: i64, content_hash: &str) -> Result<()>;
+
+    /// Attaches tags to a document, replacing the values of keys it already has.
+    async fn tag_document(&self, id: i64, tags: &[Tag]) -> Result<()>;
+
+    /// Loads the tags of a document, ordered by key.
+    async fn document_tags(&self, id: i64) -> Result<Vec<Tag>>;
+
+    /// Lists every document, ordered by ID.
+    async fn list_documents(&self) -> Result<Vec<DocumentSummary>>;
+
+    /// Looks up a document by its ID.
+    async fn get_document(&self, id: i64) -> Result<Option<DocumentSummary>>;
+
+    /// Loads the chunks of a document, in the order they appear in it.
+    async fn document_chunks(&self, document_id: i64) -> Result<Vec<DocumentChunk>>;
+
+    /// Loads the embeddings of a document's chunks, in the order of
+    /// `document_chunks`.
+    async fn document_embeddings(&self, document_id: i64) -> Result<Vec<ChunkEmbedding>>;
+
+    /// Deletes a document, its tags, and its chunks.
+    async fn delete_document(&self, id: i64) -> Result<()>;
+
+    /// Stores chunks and their embeddings in a single transaction.
+    async fn insert_chunks(&self, chunks: &[NewChunk<'_>]) -> Result<()>;
+
+    /// Summarizes the chunks currently stored.
+    async fn chunk_coverage(&self) -> Result<ChunkCoverage>;
+
+    /// Counts the chunks embedded with each model, most common first, and
+    /// by model among equally common ones.
+    async fn embedding_models(&self) -> Result<Vec<EmbeddingModelCount>>;
+
+    /// Loads up to `limit` chunks that were not embedded with `model`, or
+    /// whose model is unknown, in the order of their IDs.
+    async fn outdated_chunks(&self, model: &str, limit: usize) -> Result<Vec<ChunkText>>;
+
+    /// Replaces the embeddings of existing chunks with ones made by `model`,
+    /// in a single transaction.
+    async fn update_embeddings(&self, embeddings: &[ChunkEmbedding], model: &str) -> Result<()>;
+
+    /// Loads the embedding of every chunk whose document has all the tags in
+    /// `filters`.
+    async fn load_embeddings(&self, filters: &[Tag]) -> Result<Vec<ChunkEmbedding>>;
+
+    /// Returns the IDs among `ids` of the chunks whose document has all the
+    /// tags in `filters`.
+    async fn filter_chunks(&self, ids: &[i64], filters: &[Tag]) -> Result<HashSet<i64>>;
+
+    /// Looks up the text and source of the chunks with the given IDs. Chunks
+    /// that no longer exist are left out.
+    async fn chunk_sources(&self, ids: &[i64]) -> Result<HashMap<i64, ChunkSource>>;
+
+    /// Whether `search_similar` is available, i.e. whether similarity search
+    /// can run inside the database.
+    fn has_native_search(&self) -> bool;
+
+    /// Brings the database's own vector search structures in line with the
+    /// stored chunks, if it keeps any apart from the `chunks` table.
+    async fn sync_vectors(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Discards the database's own vector search structures, so that the
+    /// next `sync_vectors` rebuilds them from every chunk, as is needed
+    /// after embeddings were replaced.
+    async fn reset_vectors(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The number of dimensions the schema fixes for every embedding, or
+    /// `None` if embeddings of any size can be stored.
+    async fn embedding_dimensions(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Finds the chunks most similar to `embedding` inside the database,
+    /// among the chunks whose document has all the tags in `filters`.
+    ///
+    /// # Returns
+    ///
+    /// Up to `limit` pairs of chunk ID and cosine similarity, most similar first.
+    async fn search_similar(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        filters: &[Tag],
+    ) -> Result<Vec<(i64, f32)>>;
+
+    /// Finds the chunks that contain the words of `text` with the keyword
+    /// index, among the chunks whose document has all the tags in `filters`.
+    /// A chunk matches if it contains any of the words.
+    ///
+    /// # Returns
+    ///
+    /// Up to `limit` pairs of chunk ID and keyword relevance, most relevant
+    /// first. Relevance scores are only comparable within one search.
+    async fn search_keywords(
+        &self,
+        text: &str,
+        limit: usize,
+        filters: &[Tag],
+    ) -> Result<Vec<(i64, f32)>>;
+}
+
+/// Splits text into the words looked up in the keyword index: runs of
+/// letters, digits, and underscores, lowercased and without duplicates.
+///
+/// Only these characters are passed on, so the words can't be mistaken for
+/// the query syntax of either full-text engine.
+fn keyword_terms(text: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in text.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        let word = word.to_lowercase();
+        if !word.is_empty() && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// Connects to the database named by `database_url` and runs its migrations.
+///
+/// URLs starting with `postgres://` or `postgresql://` select the PostgreSQL
+/// backend; anything else is opened as a SQLite database, which is created
+/// if it doesn't exist.
+///
+/// # Arguments
+///
+/// * `database_url` - The connection string for the database.
+/// * `sqlite_vec_path` - The `sqlite-vec` extension to load into every
+///   SQLite connection, if any.
+///
+/// # Returns
+///
+/// A `Result` containing the storage backend on success.
+pub async fn init_db(
+    database_url: &str,
+    sqlite_vec_path: Option<&str>,
+) -> Result<Box<dyn Storage>> {
+    info!("Initializing database connection...");
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        Ok(Box::new(
+            postgres::PostgresStorage::connect(database_url).await?,
+        ))
+    } else {
+        Ok(Box::new(
+            sqlite::SqliteStorage::connect(database_url, sqlite_vec_path).await?,
+        ))
+    }
+}
@@ -0,0 +1,418 @@
+//! src/database/postgres.rs
+//!
+//! The PostgreSQL storage backend, for running the knowledge engine as a
+//! shared service. Embeddings are stored in a pgvector `vector` column with
+//! an HNSW index, so similarity search always runs inside the database.
+
+use super::{
+    keyword_terms, ChunkCoverage, ChunkEmbedding, ChunkSource, ChunkText, DocumentChunk,
+    DocumentSummary, EmbeddingModelCount, NewChunk, Storage, StoredDocument, Tag,
+};
+use crate::error::Result;
+use async_trait::async_trait;
+use pgvector::Vector;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::FromRow;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+#[derive(FromRow)]
+struct DocIdAndHash {
+    id: i64,
+    content_hash: String,
+}
+
+/// Represents a chunk's embedding from the `chunks` table.
+#[derive(FromRow)]
+struct EmbeddingRecord {
+    id: i64,
+    embedding: Vector,
+}
+
+/// Represents a chunk joined with its document, as looked up by ID.
+#[derive(FromRow)]
+struct SourceRecord {
+    id: i64,
+    chunk_text: String,
+    file_path: String,
+    start_offset: Option<i64>,
+    end_offset: Option<i64>,
+}
+
+/// A knowledge base stored in a PostgreSQL database with pgvector.
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    /// Establishes a connection to the PostgreSQL server and applies any
+    /// pending schema migrations from the `migrations/postgres` directory.
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(10)
+            .connect(database_url)
+            .await?;
+
+        info!("Database connection established. Running migrations...");
+
+        sqlx::migrate!("./migrations/postgres").run(&pool).await?;
+
+        info!("Database migrations completed successfully.");
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn find_document(&self, file_path: &str) -> Result<Option<StoredDocument>> {
+        let document: Option<DocIdAndHash> =
+            sqlx::query_as("SELECT id, content_hash FROM documents WHERE file_path = $1")
+                .bind(file_path)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(document.map(|document| StoredDocument {
+            id: document.id,
+            content_hash: document.content_hash,
+        }))
+    }
+
+    async fn insert_document(&self, file_path: &str, content_hash: &str) -> Result<i64> {
+        Ok(sqlx::query_scalar(
+            "INSERT INTO documents (file_path, content_hash) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(file_path)
+        .bind(content_hash)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn reset_document(&self, id: i64, content_hash: &str) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("UPDATE documents SET content_hash = $1 WHERE id = $2")
+            .bind(content_hash)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM chunks WHERE document_id = $1")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn tag_document(&self, id: i64, tags: &[Tag]) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for tag in tags {
+            sqlx::query(
+                "INSERT INTO document_tags (document_id, key, value) VALUES ($1, $2, $3)
+                 ON CONFLICT (document_id, key) DO UPDATE SET value = EXCLUDED.value",
+            )
+            .bind(id)
+            .bind(&tag.key)
+            .bind(&tag.value)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn document_tags(&self, id: i64) -> Result<Vec<Tag>> {
+        Ok(sqlx::query_as(
+            "SELECT key, value FROM document_tags WHERE document_id = $1 ORDER BY key",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn list_documents(&self) -> Result<Vec<DocumentSummary>> {
+        Ok(sqlx::query_as(
+            "SELECT d.id, d.file_path, d.content_hash, CAST(d.created_at AS TEXT) AS created_at,
+                    (SELECT COUNT(*) FROM chunks c WHERE c.document_id = d.id) AS chunk_count
+             FROM documents d
+             ORDER BY d.id",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_document(&self, id: i64) -> Result<Option<DocumentSummary>> {
+        Ok(sqlx::query_as(
+            "SELECT d.id, d.file_path, d.content_hash, CAST(d.created_at AS TEXT) AS created_at,
+                    (SELECT COUNT(*) FROM chunks c WHERE c.document_id = d.id) AS chunk_count
+             FROM documents d
+             WHERE d.id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn document_chunks(&self, document_id: i64) -> Result<Vec<DocumentChunk>> {
+        Ok(sqlx::query_as(
+            "SELECT id, chunk_text, start_offset, end_offset, chunking, embedding_model FROM chunks WHERE document_id = $1 ORDER BY id",
+        )
+        .bind(document_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn document_embeddings(&self, document_id: i64) -> Result<Vec<ChunkEmbedding>> {
+        let records: Vec<EmbeddingRecord> =
+            sqlx::query_as("SELECT id, embedding FROM chunks WHERE document_id = $1 ORDER BY id")
+                .bind(document_id)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(records
+            .into_iter()
+            .map(|record| ChunkEmbedding {
+                id: record.id,
+                embedding: record.embedding.to_vec(),
+            })
+            .collect())
+    }
+
+    async fn delete_document(&self, id: i64) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("DELETE FROM chunks WHERE document_id = $1")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM document_tags WHERE document_id = $1")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM documents WHERE id = $1")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_chunks(&self, chunks: &[NewChunk<'_>]) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for chunk in chunks {
+            sqlx::query(
+                "INSERT INTO chunks (document_id, chunk_text, start_offset, end_offset, chunking, embedding, embedding_model) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            )
+            .bind(chunk.document_id)
+            .bind(chunk.chunk_text)
+            .bind(chunk.start_offset)
+            .bind(chunk.end_offset)
+            .bind(chunk.chunking)
+            .bind(Vector::from(chunk.embedding.clone()))
+            .bind(chunk.embedding_model)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn chunk_coverage(&self) -> Result<ChunkCoverage> {
+        let (chunk_count, max_chunk_id): (i64, Option<i64>) =
+            sqlx::query_as("SELECT COUNT(*), MAX(id) FROM chunks")
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(ChunkCoverage {
+            chunk_count,
+            max_chunk_id,
+            embedding_models: self.embedding_models().await?,
+        })
+    }
+
+    async fn embedding_models(&self) -> Result<Vec<EmbeddingModelCount>> {
+        Ok(sqlx::query_as(
+            "SELECT embedding_model, COUNT(*) AS chunk_count FROM chunks GROUP BY embedding_model ORDER BY chunk_count DESC, embedding_model",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn outdated_chunks(&self, model: &str, limit: usize) -> Result<Vec<ChunkText>> {
+        Ok(sqlx::query_as("SELECT id, chunk_text FROM chunks WHERE embedding_model IS DISTINCT FROM $1 ORDER BY id LIMIT $2")
+            .bind(model)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn update_embeddings(&self, embeddings: &[ChunkEmbedding], model: &str) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for embedding in embeddings {
+            sqlx::query("UPDATE chunks SET embedding = $1, embedding_model = $2 WHERE id = $3")
+                .bind(Vector::from(embedding.embedding.clone()))
+                .bind(model)
+                .bind(embedding.id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn load_embeddings(&self, filters: &[Tag]) -> Result<Vec<ChunkEmbedding>> {
+        let mut sql = "SELECT c.id, c.embedding FROM chunks c".to_string();
+        if !filters.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&tag_conditions(filters, 1));
+        }
+        let mut query = sqlx::query_as::<_, EmbeddingRecord>(&sql);
+        for tag in filters {
+            query = query.bind(&tag.key).bind(&tag.value);
+        }
+        let records = query.fetch_all(&self.pool).await?;
+        Ok(records
+            .into_iter()
+            .map(|record| ChunkEmbedding {
+                id: record.id,
+                embedding: record.embedding.to_vec(),
+            })
+            .collect())
+    }
+
+    async fn filter_chunks(&self, ids: &[i64], filters: &[Tag]) -> Result<HashSet<i64>> {
+        if ids.is_empty() || filters.is_empty() {
+            return Ok(ids.iter().copied().collect());
+        }
+        let sql = format!(
+            "SELECT c.id FROM chunks c WHERE c.id = ANY($1) AND {}",
+            tag_conditions(filters, 2)
+        );
+        let mut query = sqlx::query_scalar::<_, i64>(&sql).bind(ids);
+        for tag in filters {
+            query = query.bind(&tag.key).bind(&tag.value);
+        }
+        Ok(query.fetch_all(&self.pool).await?.into_iter().collect())
+    }
+
+    async fn chunk_sources(&self, ids: &[i64]) -> Result<HashMap<i64, ChunkSource>> {
+        let records: Vec<SourceRecord> = sqlx::query_as(
+            "SELECT c.id, c.chunk_text, d.file_path, c.start_offset, c.end_offset
+             FROM chunks c
+             JOIN documents d ON d.id = c.document_id
+             WHERE c.id = ANY($1)",
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    record.id,
+                    ChunkSource {
+                        chunk_text: record.chunk_text,
+                        document_path: record.file_path,
+                        start_offset: record.start_offset,
+                        end_offset: record.end_offset,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    fn has_native_search(&self) -> bool {
+        true
+    }
+
+    async fn embedding_dimensions(&self) -> Result<Option<usize>> {
+        // A `vector(n)` column records its dimensions as the type modifier,
+        // which is -1 when they aren't fixed.
+        let dimensions: i32 = sqlx::query_scalar(
+            "SELECT atttypmod FROM pg_attribute WHERE attrelid = 'chunks'::regclass AND attname = 'embedding'",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(usize::try_from(dimensions).ok().filter(|&n| n > 0))
+    }
+
+    async fn search_similar(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        filters: &[Tag],
+    ) -> Result<Vec<(i64, f32)>> {
+        let filter = if filters.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", tag_conditions(filters, 3))
+        };
+        // `<=>` is pgvector's cosine distance, which the HNSW index on
+        // `chunks.embedding` serves.
+        let sql = format!(
+            "SELECT c.id, 1 - (c.embedding <=> $1) AS similarity
+             FROM chunks c
+             {}
+             ORDER BY c.embedding <=> $1
+             LIMIT $2",
+            filter
+        );
+        let mut query = sqlx::query_as::<_, (i64, f64)>(&sql)
+            .bind(Vector::from(embedding.to_vec()))
+            .bind(limit as i64);
+        for tag in filters {
+            query = query.bind(&tag.key).bind(&tag.value);
+        }
+        let matches = query.fetch_all(&self.pool).await?;
+        Ok(matches
+            .into_iter()
+            .map(|(id, similarity)| (id, similarity as f32))
+            .collect())
+    }
+
+    async fn search_keywords(
+        &self,
+        text: &str,
+        limit: usize,
+        filters: &[Tag],
+    ) -> Result<Vec<(i64, f32)>> {
+        let terms = keyword_terms(text);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let filter = if filters.is_empty() {
+            String::new()
+        } else {
+            format!("AND {}", tag_conditions(filters, 3))
+        };
+        // A chunk matches if it contains any of the words. `ts_rank_cd`
+        // also rewards chunks where they appear close together.
+        let sql = format!(
+            "SELECT c.id, ts_rank_cd(c.search_vector, query) AS rank
+             FROM chunks c, to_tsquery('simple', $1) query
+             WHERE c.search_vector @@ query
+             {}
+             ORDER BY rank DESC
+             LIMIT $2",
+            filter
+        );
+        let mut query = sqlx::query_as::<_, (i64, f32)>(&sql)
+            .bind(terms.join(" | "))
+            .bind(limit as i64);
+        for tag in filters {
+            query = query.bind(&tag.key).bind(&tag.value);
+        }
+        Ok(query.fetch_all(&self.pool).await?)
+    }
+}
+
+/// Builds the conditions restricting chunks, aliased as `c`, to the
+/// documents with all of `filters`. Each tag binds its key and value, in
+/// order, starting at parameter `$first_param`.
+fn tag_conditions(filters: &[Tag], first_param: usize) -> String {
+    (0..filters.len())
+        .map(|index| {
+            let key_param = first_param + 2 * index;
+            format!(
+                "c.document_id IN (SELECT document_id FROM document_tags WHERE key = ${} AND value = ${})",
+                key_param,
+                key_param + 1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
@@ -0,0 +1,484 @@
+//! src/database/sqlite.rs
+//!
+//! The SQLite storage backend, which keeps the knowledge base in a single
+//! local file. Embeddings are stored as BLOBs of native-endian `f32` bytes.
+//! Similarity search inside the database is available when the `sqlite-vec`
+//! extension is loaded.
+
+use super::{
+    keyword_terms, sqlite_vec, ChunkCoverage, ChunkEmbedding, ChunkSource, ChunkText,
+    DocumentChunk, DocumentSummary, EmbeddingModelCount, NewChunk, Storage, StoredDocument, Tag,
+    FILTER_OVERSAMPLING,
+};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::FromRow;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use tracing::info;
+
+#[derive(FromRow)]
+struct DocIdAndHash {
+    id: i64,
+    content_hash: String,
+}
+
+/// Represents a chunk's embedding from the `chunks` table.
+#[derive(FromRow)]
+struct EmbeddingRecord {
+    id: i64,
+    embedding: Vec<u8>,
+}
+
+/// Represents a chunk joined with its document, as looked up by ID.
+#[derive(FromRow)]
+struct SourceRecord {
+    id: i64,
+    chunk_text: String,
+    file_path: String,
+    start_offset: Option<i64>,
+    end_offset: Option<i64>,
+}
+
+/// A knowledge base stored in a SQLite database.
+pub struct SqliteStorage {
+    pool: SqlitePool,
+    /// Whether the `sqlite-vec` extension is loaded.
+    sqlite_vec: bool,
+}
+
+impl SqliteStorage {
+    /// Establishes a connection to the SQLite database and runs migrations.
+    ///
+    /// This function creates the database file if it doesn't exist, sets up a
+    /// connection pool for efficient access, and applies any pending database
+    /// schema migrations from the `migrations/sqlite` directory.
+    pub async fn connect(database_url: &str, sqlite_vec_path: Option<&str>) -> Result<Self> {
+        // Create the database file if it does not exist.
+        let mut options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+        if let Some(extension_path) = sqlite_vec_path {
+            info!(
+                "Loading the sqlite-vec extension from '{}'.",
+                extension_path
+            );
+            options = options.extension(extension_path.to_string());
+        }
+
+        // Create a connection pool.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+
+        info!("Database connection established. Running migrations...");
+
+        // Run migrations to apply the schema.
+        sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+
+        info!("Database migrations completed successfully.");
+        Ok(Self {
+            pool,
+            sqlite_vec: sqlite_vec_path.is_some(),
+        })
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn find_document(&self, file_path: &str) -> Result<Option<StoredDocument>> {
+        let document: Option<DocIdAndHash> =
+            sqlx::query_as("SELECT id, content_hash FROM documents WHERE file_path = ?")
+                .bind(file_path)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(document.map(|document| StoredDocument {
+            id: document.id,
+            content_hash: document.content_hash,
+        }))
+    }
+
+    async fn insert_document(&self, file_path: &str, content_hash: &str) -> Result<i64> {
+        let result = sqlx::query("INSERT INTO documents (file_path, content_hash) VALUES (?, ?)")
+            .bind(file_path)
+            .bind(content_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn reset_document(&self, id: i64, content_hash: &str) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("UPDATE documents SET content_hash = ? WHERE id = ?")
+            .bind(content_hash)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM chunks WHERE document_id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn tag_document(&self, id: i64, tags: &[Tag]) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for tag in tags {
+            sqlx::query(
+                "INSERT INTO document_tags (document_id, key, value) VALUES (?, ?, ?)
+                 ON CONFLICT (document_id, key) DO UPDATE SET value = excluded.value",
+            )
+            .bind(id)
+            .bind(&tag.key)
+            .bind(&tag.value)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn document_tags(&self, id: i64) -> Result<Vec<Tag>> {
+        Ok(sqlx::query_as(
+            "SELECT key, value FROM document_tags WHERE document_id = ? ORDER BY key",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn list_documents(&self) -> Result<Vec<DocumentSummary>> {
+        Ok(sqlx::query_as(
+            "SELECT d.id, d.file_path, d.content_hash, CAST(d.created_at AS TEXT) AS created_at,
+                    (SELECT COUNT(*) FROM chunks c WHERE c.document_id = d.id) AS chunk_count
+             FROM documents d
+             ORDER BY d.id",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_document(&self, id: i64) -> Result<Option<DocumentSummary>> {
+        Ok(sqlx::query_as(
+            "SELECT d.id, d.file_path, d.content_hash, CAST(d.created_at AS TEXT) AS created_at,
+                    (SELECT COUNT(*) FROM chunks c WHERE c.document_id = d.id) AS chunk_count
+             FROM documents d
+             WHERE d.id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn document_chunks(&self, document_id: i64) -> Result<Vec<DocumentChunk>> {
+        Ok(sqlx::query_as(
+            "SELECT id, chunk_text, start_offset, end_offset, chunking, embedding_model FROM chunks WHERE document_id = ? ORDER BY id",
+        )
+        .bind(document_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn document_embeddings(&self, document_id: i64) -> Result<Vec<ChunkEmbedding>> {
+        let records: Vec<EmbeddingRecord> =
+            sqlx::query_as("SELECT id, embedding FROM chunks WHERE document_id = ? ORDER BY id")
+                .bind(document_id)
+                .fetch_all(&self.pool)
+                .await?;
+        records
+            .into_iter()
+            .map(|record| {
+                Ok(ChunkEmbedding {
+                    id: record.id,
+                    embedding: deserialize_embedding(&record.embedding)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn delete_document(&self, id: i64) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("DELETE FROM chunks WHERE document_id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM document_tags WHERE document_id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM documents WHERE id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_chunks(&self, chunks: &[NewChunk<'_>]) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for chunk in chunks {
+            let embedding_bytes: Vec<u8> = chunk
+                .embedding
+                .iter()
+                .flat_map(|&f| f.to_ne_bytes())
+                .collect();
+
+            // Use a runtime-checked query to avoid compile-time database access.
+            sqlx::query(
+                "INSERT INTO chunks (document_id, chunk_text, start_offset, end_offset, chunking, embedding, embedding_model) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(chunk.document_id)
+            .bind(chunk.chunk_text)
+            .bind(chunk.start_offset)
+            .bind(chunk.end_offset)
+            .bind(chunk.chunking)
+            .bind(&embedding_bytes)
+            .bind(chunk.embedding_model)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn chunk_coverage(&self) -> Result<ChunkCoverage> {
+        let (chunk_count, max_chunk_id): (i64, Option<i64>) =
+            sqlx::query_as("SELECT COUNT(*), MAX(id) FROM chunks")
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(ChunkCoverage {
+            chunk_count,
+            max_chunk_id,
+            embedding_models: self.embedding_models().await?,
+        })
+    }
+
+    async fn embedding_models(&self) -> Result<Vec<EmbeddingModelCount>> {
+        Ok(sqlx::query_as(
+            "SELECT embedding_model, COUNT(*) AS chunk_count FROM chunks GROUP BY embedding_model ORDER BY chunk_count DESC, embedding_model",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn outdated_chunks(&self, model: &str, limit: usize) -> Result<Vec<ChunkText>> {
+        Ok(sqlx::query_as("SELECT id, chunk_text FROM chunks WHERE embedding_model IS NULL OR embedding_model != ? ORDER BY id LIMIT ?")
+            .bind(model)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn update_embeddings(&self, embeddings: &[ChunkEmbedding], model: &str) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for embedding in embeddings {
+            let embedding_bytes: Vec<u8> = embedding
+                .embedding
+                .iter()
+                .flat_map(|&f| f.to_ne_bytes())
+                .collect();
+            sqlx::query("UPDATE chunks SET embedding = ?, embedding_model = ? WHERE id = ?")
+                .bind(&embedding_bytes)
+                .bind(model)
+                .bind(embedding.id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn load_embeddings(&self, filters: &[Tag]) -> Result<Vec<ChunkEmbedding>> {
+        let mut sql = "SELECT c.id, c.embedding FROM chunks c".to_string();
+        if !filters.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&tag_conditions(filters));
+        }
+        let mut query = sqlx::query_as::<_, EmbeddingRecord>(&sql);
+        for tag in filters {
+            query = query.bind(&tag.key).bind(&tag.value);
+        }
+        let records = query.fetch_all(&self.pool).await?;
+        records
+            .into_iter()
+            .map(|record| {
+                Ok(ChunkEmbedding {
+                    id: record.id,
+                    embedding: deserialize_embedding(&record.embedding)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn filter_chunks(&self, ids: &[i64], filters: &[Tag]) -> Result<HashSet<i64>> {
+        if ids.is_empty() || filters.is_empty() {
+            return Ok(ids.iter().copied().collect());
+        }
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!(
+            "SELECT c.id FROM chunks c WHERE c.id IN ({}) AND {}",
+            placeholders,
+            tag_conditions(filters)
+        );
+        let mut query = sqlx::query_scalar::<_, i64>(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        for tag in filters {
+            query = query.bind(&tag.key).bind(&tag.value);
+        }
+        Ok(query.fetch_all(&self.pool).await?.into_iter().collect())
+    }
+
+    async fn chunk_sources(&self, ids: &[i64]) -> Result<HashMap<i64, ChunkSource>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!(
+            "SELECT c.id, c.chunk_text, d.file_path, c.start_offset, c.end_offset
+             FROM chunks c
+             JOIN documents d ON d.id = c.document_id
+             WHERE c.id IN ({})",
+            placeholders
+        );
+        let mut query = sqlx::query_as::<_, SourceRecord>(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        Ok(query
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|record| {
+                (
+                    record.id,
+                    ChunkSource {
+                        chunk_text: record.chunk_text,
+                        document_path: record.file_path,
+                        start_offset: record.start_offset,
+                        end_offset: record.end_offset,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    fn has_native_search(&self) -> bool {
+        self.sqlite_vec
+    }
+
+    async fn sync_vectors(&self) -> Result<()> {
+        if self.sqlite_vec {
+            sqlite_vec::sync_vectors(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    async fn reset_vectors(&self) -> Result<()> {
+        if self.sqlite_vec {
+            sqlite_vec::drop_vectors(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    async fn search_similar(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        filters: &[Tag],
+    ) -> Result<Vec<(i64, f32)>> {
+        if !self.sqlite_vec {
+            return Err(Error::Config(
+                "Similarity search inside SQLite needs the sqlite-vec extension; set SQLITE_VEC_PATH."
+                    .to_string(),
+            ));
+        }
+        if filters.is_empty() {
+            return Ok(sqlite_vec::search(&self.pool, embedding, limit)
+                .await?
+                .into_iter()
+                .map(|vec_match| (vec_match.id, vec_match.similarity as f32))
+                .collect());
+        }
+
+        // A `vec0` k-nearest-neighbor query can't join the tags, so more
+        // candidates are fetched and filtered afterwards.
+        let candidates =
+            sqlite_vec::search(&self.pool, embedding, limit * FILTER_OVERSAMPLING).await?;
+        let candidate_ids: Vec<i64> = candidates.iter().map(|vec_match| vec_match.id).collect();
+        let allowed = self.filter_chunks(&candidate_ids, filters).await?;
+        Ok(candidates
+            .into_iter()
+            .filter(|vec_match| allowed.contains(&vec_match.id))
+            .take(limit)
+            .map(|vec_match| (vec_match.id, vec_match.similarity as f32))
+            .collect())
+    }
+
+    async fn search_keywords(
+        &self,
+        text: &str,
+        limit: usize,
+        filters: &[Tag],
+    ) -> Result<Vec<(i64, f32)>> {
+        let terms = keyword_terms(text);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Each word is quoted, so FTS5 reads it as a string rather than as
+        // an operator, and a chunk matches if it contains any of them.
+        let match_query = terms
+            .iter()
+            .map(|term| format!("\"{}\"", term))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let mut sql = "SELECT c.id, bm25(chunks_fts) AS rank
+             FROM chunks_fts
+             JOIN chunks c ON c.id = chunks_fts.rowid
+             WHERE chunks_fts MATCH ?"
+            .to_string();
+        if !filters.is_empty() {
+            sql.push_str(" AND ");
+            sql.push_str(&tag_conditions(filters));
+        }
+        sql.push_str(" ORDER BY rank LIMIT ?");
+
+        let mut query = sqlx::query_as::<_, (i64, f64)>(&sql).bind(match_query);
+        for tag in filters {
+            query = query.bind(&tag.key).bind(&tag.value);
+        }
+        // BM25 scores are negative in FTS5, lower meaning more relevant.
+        Ok(query
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|(id, rank)| (id, -rank as f32))
+            .collect())
+    }
+}
+
+/// Builds the conditions restricting chunks, aliased as `c`, to the
+/// documents with all of `filters`. Each tag binds its key and value, in order.
+fn tag_conditions(filters: &[Tag]) -> String {
+    vec![
+        "c.document_id IN (SELECT document_id FROM document_tags WHERE key = ? AND value = ?)";
+        filters.len()
+    ]
+    .join(" AND ")
+}
+
+/// Decodes an embedding stored as native-endian `f32` bytes.
+fn deserialize_embedding(bytes: &[u8]) -> Result<Vec<f32>> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(Error::Processing(
+            "Invalid embedding data in database: not a multiple of 4 bytes.".to_string(),
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_ne_bytes(c.try_into().unwrap()))
+        .collect())
+}
@@ -0,0 +1,122 @@
+//! src/database/sqlite_vec.rs
+//!
+//! Vector search inside SQLite with the `sqlite-vec` extension. When
+//! `SQLITE_VEC_PATH` points to the extension, every connection loads it, the
+//! chunk embeddings are mirrored into a `vec0` virtual table, and similarity
+//! search runs as a single SQL query instead of deserializing and scoring
+//! every embedding in Rust.
+//!
+//! The virtual table is created at runtime rather than by a migration,
+//! because it can only exist in databases opened with the extension loaded.
+
+use crate::error::{Error, Result};
+use sqlx::{FromRow, SqlitePool};
+use tracing::{info, instrument};
+
+/// A chunk found by a similarity search, with its cosine similarity to the query.
+#[derive(FromRow)]
+pub struct VecMatch {
+    pub id: i64,
+    pub similarity: f64,
+}
+
+/// Creates the `chunk_vectors` virtual table if needed and brings it in line
+/// with the `chunks` table, adding new embeddings and removing the ones of
+/// deleted chunks.
+#[instrument(skip_all)]
+pub async fn sync_vectors(pool: &SqlitePool) -> Result<()> {
+    let table_exists: Option<i64> =
+        sqlx::query_scalar("SELECT 1 FROM sqlite_master WHERE name = 'chunk_vectors'")
+            .fetch_optional(pool)
+            .await?;
+    if table_exists.is_none() {
+        // The dimensions of the table are fixed when it is created, so they
+        // are taken from the embeddings already stored.
+        let dimensions: Option<i64> =
+            sqlx::query_scalar("SELECT length(embedding) / 4 FROM chunks LIMIT 1")
+                .fetch_optional(pool)
+                .await?;
+        let Some(dimensions) = dimensions else {
+            return Ok(());
+        };
+        info!(
+            "Creating the sqlite-vec table for {}-dimensional embeddings...",
+            dimensions
+        );
+        sqlx::query(&format!(
+            "CREATE VIRTUAL TABLE chunk_vectors USING vec0(embedding float[{}] distance_metric=cosine)",
+            dimensions
+        ))
+        .execute(pool)
+        .await
+        .map_err(extension_error)?;
+    }
+
+    let mut transaction = pool.begin().await?;
+    let removed =
+        sqlx::query("DELETE FROM chunk_vectors WHERE rowid NOT IN (SELECT id FROM chunks)")
+            .execute(&mut *transaction)
+            .await
+            .map_err(extension_error)?
+            .rows_affected();
+    // Embeddings are stored as native-endian `f32` bytes, which is the
+    // little-endian layout sqlite-vec expects on every common platform.
+    let added = sqlx::query(
+        "INSERT INTO chunk_vectors (rowid, embedding) SELECT id, embedding FROM chunks WHERE id NOT IN (SELECT rowid FROM chunk_vectors)",
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(extension_error)?
+    .rows_affected();
+    transaction.commit().await?;
+
+    if added > 0 || removed > 0 {
+        info!(
+            "Synchronized the sqlite-vec table: {} embeddings added, {} removed.",
+            added, removed
+        );
+    }
+    Ok(())
+}
+
+/// Drops the `chunk_vectors` virtual table, so that the next `sync_vectors`
+/// creates it again from every chunk, with the dimensions of the embeddings
+/// stored then.
+#[instrument(skip_all)]
+pub async fn drop_vectors(pool: &SqlitePool) -> Result<()> {
+    sqlx::query("DROP TABLE IF EXISTS chunk_vectors")
+        .execute(pool)
+        .await
+        .map_err(extension_error)?;
+    Ok(())
+}
+
+/// Finds the chunks most similar to `embedding` with a `vec0` k-nearest-neighbor query.
+///
+/// # Returns
+///
+/// Up to `limit` chunks, most similar first.
+#[instrument(skip_all)]
+pub async fn search(pool: &SqlitePool, embedding: &[f32], limit: usize) -> Result<Vec<VecMatch>> {
+    let embedding_bytes: Vec<u8> = embedding.iter().flat_map(|&f| f.to_le_bytes()).collect();
+    sqlx::query_as(
+        "SELECT rowid AS id, 1.0 - distance AS similarity
+         FROM chunk_vectors
+         WHERE embedding MATCH ? AND k = ?
+         ORDER BY distance",
+    )
+    .bind(embedding_bytes)
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(extension_error)
+}
+
+/// Explains failing `vec0` statements, which usually mean that the extension
+/// did not load.
+fn extension_error(e: sqlx::Error) -> Error {
+    Error::Config(format!(
+        "sqlite-vec query failed; check that SQLITE_VEC_PATH points to the extension: {}",
+        e
+    ))
+}
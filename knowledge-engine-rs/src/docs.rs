@@ -0,0 +1,124 @@
+//! src/docs.rs
+//!
+//! This module implements the `docs` subcommands, which inspect and remove
+//! the documents in the knowledge base without writing SQL by hand.
+
+use crate::database::{Storage, Tag};
+use crate::error::{Error, Result};
+use colored::Colorize;
+use tracing::info;
+
+/// The number of characters of each chunk shown by `docs show` unless the
+/// full text is requested.
+const PREVIEW_CHARS: usize = 80;
+
+/// Prints every document in the knowledge base with its chunk count.
+pub async fn list(storage: &dyn Storage) -> Result<()> {
+    let documents = storage.list_documents().await?;
+    if documents.is_empty() {
+        println!("No documents have been ingested yet.");
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("{:>6}  {:>6}  {:<19}  PATH", "ID", "CHUNKS", "INGESTED").bold()
+    );
+    for document in &documents {
+        println!(
+            "{:>6}  {:>6}  {:<19}  {}",
+            document.id, document.chunk_count, document.created_at, document.file_path
+        );
+    }
+    Ok(())
+}
+
+/// Prints a document's details and its chunks, either as one-line previews
+/// or, with `full`, in full.
+pub async fn show(storage: &dyn Storage, id: i64, full: bool) -> Result<()> {
+    let document = storage
+        .get_document(id)
+        .await?
+        .ok_or_else(|| Error::Document(format!("No document with ID {}.", id)))?;
+    let tags = storage.document_tags(id).await?;
+    let chunks = storage.document_chunks(id).await?;
+
+    println!("{} {}", "Document".bold().cyan(), document.id);
+    println!("Path:     {}", document.file_path);
+    println!("Hash:     {}", document.content_hash);
+    println!("Ingested: {}", document.created_at);
+    println!("Chunks:   {}", document.chunk_count);
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags.iter().map(Tag::to_string).collect();
+        println!("Tags:     {}", tags.join(", "));
+    }
+
+    for chunk in &chunks {
+        let position = match (chunk.start_offset, chunk.end_offset) {
+            (Some(start), Some(end)) => format!("characters {}-{}", start, end),
+            _ => "no offsets".to_string(),
+        };
+        let position = match &chunk.chunking {
+            Some(chunking) => format!("{}; {}", position, chunking),
+            None => position,
+        };
+        let position = match &chunk.embedding_model {
+            Some(model) => format!("{}; embedded with {}", position, model),
+            None => position,
+        };
+        println!("\n{}", format!("Chunk {} ({})", chunk.id, position).bold());
+        if full {
+            println!("{}", chunk.chunk_text);
+        } else {
+            println!("{}", preview(&chunk.chunk_text));
+        }
+    }
+    Ok(())
+}
+
+/// Deletes a document and its chunks. The target is a document ID or the
+/// file path it was ingested from.
+///
+/// # Returns
+///
+/// The path of the deleted document.
+pub async fn delete(storage: &dyn Storage, target: &str) -> Result<String> {
+    let id = resolve_document(storage, target).await?;
+    let document = storage
+        .get_document(id)
+        .await?
+        .ok_or_else(|| Error::Document(format!("No document with ID {}.", id)))?;
+
+    storage.delete_document(id).await?;
+    info!(
+        "Deleted document {} ('{}') and its {} chunks.",
+        document.id, document.file_path, document.chunk_count
+    );
+    Ok(document.file_path)
+}
+
+/// Finds the ID of the document named by `target`, which is either an ID or
+/// a file path as it was ingested.
+pub async fn resolve_document(storage: &dyn Storage, target: &str) -> Result<i64> {
+    if let Ok(id) = target.parse::<i64>() {
+        return Ok(id);
+    }
+    storage
+        .find_document(target)
+        .await?
+        .map(|document| document.id)
+        .ok_or_else(|| Error::Document(format!("No document ingested from '{}'.", target)))
+}
+
+/// Shortens a chunk to its first line, cut to `PREVIEW_CHARS` characters.
+fn preview(text: &str) -> String {
+    let line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    let mut preview: String = line.trim().chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < text.trim().len() {
+        preview.push_str("...");
+    }
+    preview
+}
@@ -25,6 +25,11 @@ pub enum Error {
     #[error("Database migration error: {0}")]
     Migration(#[from] sqlx::migrate::MigrateError),
 
+    /// Wraps errors from requests to the OpenAI API, made with the shared
+    /// `ai-core` client.
+    #[error(transparent)]
+    Client(#[from] ai_core::Error),
+
     /// Wraps errors from the `reqwest` HTTP client.
     #[error("HTTP request error")]
     Reqwest(#[from] reqwest::Error),
@@ -37,6 +42,30 @@ pub enum Error {
     #[error("JSON processing error: {0}")]
     SerdeJson(#[from] serde_json::Error),
 
+    /// For errors building, saving, or loading the vector index.
+    #[error("Vector index error: {0}")]
+    Index(String),
+
+    /// For errors crawling web pages, such as invalid URLs.
+    #[error("Web ingestion error: {0}")]
+    Web(String),
+
+    /// For evaluation datasets that can't be read.
+    #[error("Evaluation error: {0}")]
+    Eval(String),
+
+    /// For errors reading or writing knowledge base archives.
+    #[error("Archive error: {0}")]
+    Archive(String),
+
+    /// For errors watching the documents directory for changes.
+    #[error("Watch error: {0}")]
+    Watch(String),
+
+    /// For documents that can't be found or managed.
+    #[error("Document error: {0}")]
+    Document(String),
+
     /// For general processing errors, like parsing files.
     #[error("Processing error: {0}")]
     Processing(String),
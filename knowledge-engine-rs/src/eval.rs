@@ -0,0 +1,287 @@
+//! src/eval.rs
+//!
+//! This module implements the `eval` command, which measures the quality of
+//! the engine's answers on a dataset of questions with expected answers. For
+//! each question it reports whether the expected sources were retrieved
+//! (recall), how well the answer is supported by the retrieved context and
+//! matches the expected answer (both judged by a model), and how long the
+//! answer took, so that changes to chunking and retrieval can be compared.
+
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use crate::query_engine::{Answer, QueryEngine};
+use colored::Colorize;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// One question of an evaluation dataset, read from a line of JSON such as
+/// `{"question": "...", "expected_answer": "...", "sources": ["a.md"]}`.
+#[derive(Deserialize, Debug)]
+struct EvalCase {
+    question: String,
+    expected_answer: String,
+    /// The documents the answer should be drawn from, as ingested or as a
+    /// trailing part of the ingested path. Without sources, the question
+    /// doesn't count towards recall.
+    #[serde(default)]
+    sources: Vec<String>,
+}
+
+/// The measurements for one question.
+struct CaseResult {
+    /// The fraction of the expected sources among the retrieved chunks.
+    recall: Option<f32>,
+    /// How well the answer is supported by the retrieved context, from 0 to 10.
+    faithfulness: Option<f32>,
+    /// How well the answer agrees with the expected answer, from 0 to 10.
+    correctness: Option<f32>,
+    latency: Duration,
+}
+
+/// Runs every question of the dataset at `dataset_path` through the query
+/// engine and prints the measurements for each, followed by a summary.
+/// Questions that fail are reported and left out of the summary.
+pub async fn run(
+    query_engine: &QueryEngine,
+    client: &OpenAIClient,
+    judge_model: &str,
+    dataset_path: &Path,
+) -> Result<()> {
+    let cases = load_dataset(dataset_path)?;
+    info!(
+        "Evaluating {} questions from '{}'...",
+        cases.len(),
+        dataset_path.display()
+    );
+
+    let mut results = Vec::new();
+    let mut failures = 0;
+    for (index, case) in cases.iter().enumerate() {
+        let label = format!("[{}/{}]", index + 1, cases.len());
+        match evaluate_case(query_engine, client, judge_model, case).await {
+            Ok(result) => {
+                println!(
+                    "{} recall {}  faithfulness {}  correctness {}  {:.2}s  {}",
+                    label.bold(),
+                    format_score(result.recall, 2),
+                    format_score(result.faithfulness, 0),
+                    format_score(result.correctness, 0),
+                    result.latency.as_secs_f32(),
+                    case.question
+                );
+                results.push(result);
+            }
+            Err(e) => {
+                warn!("Failed to evaluate '{}': {}", case.question, e);
+                println!("{} {}  {}", label.bold(), "failed".red(), case.question);
+                failures += 1;
+            }
+        }
+    }
+
+    print_summary(&results, failures);
+    Ok(())
+}
+
+/// Reads a dataset of one JSON question per line, skipping blank lines.
+fn load_dataset(path: &Path) -> Result<Vec<EvalCase>> {
+    let content = std::fs::read_to_string(path)?;
+    let cases = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                Error::Eval(format!(
+                    "Invalid question on line {} of '{}': {}",
+                    index + 1,
+                    path.display(),
+                    e
+                ))
+            })
+        })
+        .collect::<Result<Vec<EvalCase>>>()?;
+    if cases.is_empty() {
+        return Err(Error::Eval(format!(
+            "No questions found in '{}'.",
+            path.display()
+        )));
+    }
+    Ok(cases)
+}
+
+/// Answers a question and measures the answer.
+async fn evaluate_case(
+    query_engine: &QueryEngine,
+    client: &OpenAIClient,
+    judge_model: &str,
+    case: &EvalCase,
+) -> Result<CaseResult> {
+    let started = Instant::now();
+    let answer = query_engine.answer_question(&case.question).await?;
+    let latency = started.elapsed();
+
+    let recall = retrieval_recall(&answer, &case.sources);
+    let (faithfulness, correctness) = judge(client, judge_model, case, &answer).await?;
+    Ok(CaseResult {
+        recall,
+        faithfulness,
+        correctness,
+        latency,
+    })
+}
+
+/// The fraction of `sources` that at least one retrieved chunk comes from,
+/// or `None` if no sources are expected.
+fn retrieval_recall(answer: &Answer, sources: &[String]) -> Option<f32> {
+    if sources.is_empty() {
+        return None;
+    }
+    let found = sources
+        .iter()
+        .filter(|source| {
+            answer
+                .context
+                .iter()
+                .any(|chunk| source_matches(&chunk.document_path, source))
+        })
+        .count();
+    Some(found as f32 / sources.len() as f32)
+}
+
+/// Whether a document path names the expected source: either exactly, or
+/// with the source as its trailing components, so that `guide/install.md`
+/// matches `./documents/guide/install.md`.
+fn source_matches(document_path: &str, source: &str) -> bool {
+    document_path == source || Path::new(document_path).ends_with(source)
+}
+
+/// Has `model` score the answer's faithfulness to the retrieved context and
+/// its correctness against the expected answer, in a single request.
+///
+/// # Returns
+///
+/// The two scores, from 0 to 10, or `None` for scores the reply doesn't
+/// contain.
+async fn judge(
+    client: &OpenAIClient,
+    model: &str,
+    case: &EvalCase,
+    answer: &Answer,
+) -> Result<(Option<f32>, Option<f32>)> {
+    let system_prompt = "You evaluate answers written by a question-answering system from retrieved context. Score two things from 0 to 10. Faithfulness: whether every claim in the answer is supported by the context (10 means fully supported, 0 means mostly unsupported or contradicted; an answer stating that the context does not contain the information is faithful if that is true). Correctness: whether the answer agrees with the expected answer (10 means it conveys the same information, 0 means it is wrong or missing). Reply with exactly two lines, `faithfulness: <score>` and `correctness: <score>`, and nothing else.";
+    let context = if answer.context.is_empty() {
+        "(no context was retrieved)".to_string()
+    } else {
+        answer
+            .context
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| format!("[{}]\n{}", index + 1, chunk.text))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+    let user_prompt = format!(
+        "CONTEXT:\n{}\n\nQUESTION: {}\n\nEXPECTED ANSWER: {}\n\nANSWER: {}",
+        context, case.question, case.expected_answer, answer.text
+    );
+
+    let reply = client
+        .get_completion_with_model(model, system_prompt, &user_prompt)
+        .await?;
+    let faithfulness = parse_judge_score(&reply, "faithfulness");
+    let correctness = parse_judge_score(&reply, "correctness");
+    if faithfulness.is_none() && correctness.is_none() {
+        warn!(
+            "Could not read the judge's scores from its reply: {}",
+            reply
+        );
+    }
+    Ok((faithfulness, correctness))
+}
+
+/// Reads the score given on the `name: score` line of the judge's reply.
+fn parse_judge_score(reply: &str, name: &str) -> Option<f32> {
+    reply.lines().find_map(|line| {
+        let (key, score) = line.split_once(':')?;
+        if !key.trim().trim_matches('*').eq_ignore_ascii_case(name) {
+            return None;
+        }
+        let score = score
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .find(|part| !part.is_empty())?
+            .trim_matches('*')
+            .parse::<f32>()
+            .ok()?;
+        (score.is_finite() && (0.0..=10.0).contains(&score)).then_some(score)
+    })
+}
+
+/// Prints the averages of the measurements over all questions.
+fn print_summary(results: &[CaseResult], failures: usize) {
+    println!("\n{}", "Summary:".bold().cyan());
+    println!(
+        "Questions:     {} evaluated, {} failed",
+        results.len(),
+        failures
+    );
+    if results.is_empty() {
+        return;
+    }
+
+    let recalls: Vec<f32> = results.iter().filter_map(|result| result.recall).collect();
+    match mean(&recalls) {
+        Some(recall) => println!(
+            "Recall:        {:.2} (over {} questions with sources)",
+            recall,
+            recalls.len()
+        ),
+        None => println!("Recall:        - (no questions list sources)"),
+    }
+
+    let faithfulness: Vec<f32> = results.iter().filter_map(|r| r.faithfulness).collect();
+    let correctness: Vec<f32> = results.iter().filter_map(|r| r.correctness).collect();
+    println!(
+        "Faithfulness:  {} / 10",
+        format_score(mean(&faithfulness), 1)
+    );
+    println!(
+        "Correctness:   {} / 10",
+        format_score(mean(&correctness), 1)
+    );
+
+    let mut latencies: Vec<f32> = results
+        .iter()
+        .map(|result| result.latency.as_secs_f32())
+        .collect();
+    latencies.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    println!(
+        "Latency:       mean {:.2}s, p50 {:.2}s, p95 {:.2}s",
+        mean(&latencies).unwrap_or_default(),
+        percentile(&latencies, 0.50),
+        percentile(&latencies, 0.95)
+    );
+}
+
+fn format_score(score: Option<f32>, decimals: usize) -> String {
+    match score {
+        Some(score) => format!("{:.*}", decimals, score),
+        None => "-".to_string(),
+    }
+}
+
+fn mean(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f32>() / values.len() as f32)
+}
+
+/// The value below which `fraction` of the `sorted`, non-empty values fall,
+/// by the nearest-rank method.
+fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+    let rank = (fraction * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,22 @@
+//! src/lib.rs
+//!
+//! The library behind the AI-Powered Knowledge Synthesis and Query Engine: the
+//! ingestion pipeline, the storage backends and vector index, and the query
+//! engine that answers questions from the stored chunks. `main.rs` parses the
+//! command line and drives them, and the tests in `tests/` run the query engine
+//! against the mock provider.
+
+pub mod archive;
+pub mod config;
+pub mod database;
+pub mod docs;
+pub mod error;
+pub mod eval;
+pub mod mcp;
+pub mod openai_client;
+pub mod pipeline;
+pub mod query_engine;
+pub mod reembed;
+pub mod server;
+pub mod summarize;
+pub mod vector_index;
@@ -4,18 +4,21 @@
 //! This module orchestrates the two primary workflows: ingesting documents into
 //! the knowledge base and querying that knowledge base to answer questions.
 
-// Declare the module hierarchy for the compiler.
-mod config;
-mod database;
-mod error;
-mod openai_client;
-mod pipeline;
-mod query_engine;
-
-use crate::error::Result;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
+use knowledge_engine_rs::database::{self, Storage, Tag};
+use knowledge_engine_rs::error::Result;
+use knowledge_engine_rs::openai_client::OpenAIClient;
+use knowledge_engine_rs::pipeline::web::CrawlOptions;
+use knowledge_engine_rs::query_engine::{
+    Answer, Citation, QueryEngine, QueryTransform, RelevantChunk, Retrieval,
+};
+use knowledge_engine_rs::{archive, config, docs, eval, mcp, pipeline, reembed, server, summarize};
+use std::io::{self, Write};
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
 use tracing::{error, info};
 use tracing_subscriber::{fmt, EnvFilter};
 
@@ -29,9 +32,21 @@ use tracing_subscriber::{fmt, EnvFilter};
 struct Args {
     #[command(subcommand)]
     command: Command,
+
+    /// Stop sending requests once they have cost this many US dollars, over
+    /// the process's lifetime for `serve` (default: AI_MAX_COST).
+    #[arg(long, global = true, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Reuse the cached replies to requests sent before, such as the same
+    /// question over the same chunks, instead of asking the model again.
+    /// `AI_CACHE=1` does the same.
+    #[arg(long, global = true)]
+    cache: bool,
 }
 
-/// Defines the available subcommands: `ingest` and `query`.
+/// Defines the available subcommands: `ingest`, `ingest-url`, `query`, `eval`,
+/// `summarize`, `docs`, `export`, `import`, and `serve`.
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Ingests documents from a specified path into the knowledge base.
@@ -39,15 +54,216 @@ enum Command {
         /// The path to the directory containing documents to ingest.
         #[arg(default_value = "./documents")]
         path: PathBuf,
+        /// A tag to attach to every ingested document, as KEY=VALUE. Can be repeated.
+        #[arg(long = "tag", value_name = "KEY=VALUE")]
+        tags: Vec<Tag>,
+        /// Keep running after the ingestion, and ingest or delete documents
+        /// as files under the path are created, modified, or removed.
+        #[arg(long)]
+        watch: bool,
+        /// Delete the documents whose files under the path no longer exist.
+        /// Without it, such documents are only reported.
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Crawls web pages from a URL, or the pages listed in a sitemap, into the
+    /// knowledge base. Each page is stored with its URL as the document path.
+    IngestUrl {
+        /// The URL of the first page, or of a sitemap.
+        url: String,
+        /// How many links away from the first page to follow, within its host.
+        #[arg(long, default_value_t = 0)]
+        depth: usize,
+        /// The maximum number of pages to fetch.
+        #[arg(long, default_value_t = 100)]
+        max_pages: usize,
+        /// A tag to attach to every ingested page, as KEY=VALUE. Can be repeated.
+        #[arg(long = "tag", value_name = "KEY=VALUE")]
+        tags: Vec<Tag>,
     },
     /// Asks a question against the knowledge base.
     Query {
         /// The question to ask.
         #[arg(required = true)]
         question: String,
+        /// Score every chunk instead of searching the vector index or sqlite-vec.
+        #[arg(long)]
+        exact: bool,
+        /// Only retrieve chunks from documents with this tag, as KEY=VALUE.
+        /// Can be repeated; every tag must match.
+        #[arg(long = "filter", value_name = "KEY=VALUE")]
+        filters: Vec<Tag>,
+        /// Retrieve more chunks and have a cheaper model rerank them by
+        /// relevance before the answer is written.
+        #[arg(long)]
+        rerank: bool,
+        /// Transform the question before searching: `rewrite` it into an
+        /// explicit search query, or search with a hypothetical answer (`hyde`).
+        #[arg(long, value_name = "rewrite|hyde")]
+        transform: Option<QueryTransform>,
+        /// Also search with N paraphrases of the question, written by a
+        /// cheaper model, and fuse the results. Each one adds a search and an
+        /// embedding request.
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        expansion: usize,
+        /// Decline to answer, and list the closest chunks instead, if the
+        /// model's confidence in its answer is below this percentage.
+        /// Overrides MIN_CONFIDENCE.
+        #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_confidence: Option<u8>,
+        /// Print the answer once it is complete instead of as it is written.
+        #[arg(long)]
+        no_stream: bool,
+        /// Print each retrieved chunk, with its scores and source, before
+        /// the answer.
+        #[arg(long, conflicts_with = "format")]
+        show_context: bool,
+        /// Print the answer as `text`, or as a JSON object with the answer,
+        /// the passages it quotes, and the model's confidence (`json`).
+        #[arg(long, value_name = "text|json", default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Measures retrieval recall, answer quality, and latency on a dataset of
+    /// questions with expected answers.
+    Eval {
+        /// A JSON Lines file with one question per line, as
+        /// {"question": ..., "expected_answer": ..., "sources": [...]}.
+        #[arg(long)]
+        dataset: PathBuf,
+        /// Score every chunk instead of searching the vector index or sqlite-vec.
+        #[arg(long)]
+        exact: bool,
+        /// Only retrieve chunks from documents with this tag, as KEY=VALUE.
+        /// Can be repeated; every tag must match.
+        #[arg(long = "filter", value_name = "KEY=VALUE")]
+        filters: Vec<Tag>,
+        /// Rerank the retrieved chunks before each answer is written.
+        #[arg(long)]
+        rerank: bool,
+        /// Transform each question before searching, as with `query`.
+        #[arg(long, value_name = "rewrite|hyde")]
+        transform: Option<QueryTransform>,
+        /// Also search with N paraphrases of each question, as with `query`.
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        expansion: usize,
+        /// Decline to answer below this confidence, as with `query`.
+        #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_confidence: Option<u8>,
+        /// The model that judges the faithfulness and correctness of answers
+        /// (default: the model that writes them, `gpt-4o` on OpenAI).
+        #[arg(long)]
+        judge_model: Option<String>,
+    },
+    /// Summarizes an ingested document from its stored chunks.
+    Summarize {
+        /// The ID of the document, or its path as shown by `docs list`.
+        document: String,
+        /// The approximate maximum length of the summary, in words.
+        #[arg(long, default_value_t = 200, value_parser = clap::value_parser!(u32).range(1..))]
+        words: u32,
+    },
+    /// Embeds the chunks that were embedded with an outdated model again, in
+    /// batches, and rebuilds the similarity search structures.
+    Reembed {
+        /// The number of chunks embedded per request.
+        #[arg(long, default_value_t = reembed::DEFAULT_BATCH_SIZE, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=2048))]
+        batch_size: usize,
+        /// Only show how many chunks were embedded with each model.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Inspects and manages the ingested documents.
+    Docs {
+        #[command(subcommand)]
+        command: DocsCommand,
+    },
+    /// Writes the documents, chunks, and embeddings of the knowledge base to a
+    /// compressed archive.
+    Export {
+        /// The archive to create, such as `knowledge-base.tar.zst`.
+        archive: PathBuf,
+    },
+    /// Adds the documents of an archive made by `export` to the knowledge base.
+    Import {
+        /// The archive to import.
+        archive: PathBuf,
+    },
+    /// Serves the knowledge engine as a REST API.
+    Serve {
+        /// The address to listen on.
+        #[arg(long, default_value = server::DEFAULT_ADDR)]
+        addr: SocketAddr,
+        /// The directory that `POST /ingest` paths are resolved against and
+        /// must stay within.
+        #[arg(long, default_value = "./documents")]
+        documents: PathBuf,
+    },
+    /// Runs a Model Context Protocol server over stdio, exposing querying and
+    /// ingestion as tools to MCP clients such as Claude Desktop.
+    Mcp {
+        /// The directory that `ingest_document` paths are resolved against
+        /// and must stay within.
+        #[arg(long, default_value = "./documents")]
+        documents: PathBuf,
     },
 }
 
+/// Defines the `docs` subcommands.
+#[derive(Subcommand, Debug)]
+enum DocsCommand {
+    /// Lists the ingested documents.
+    List,
+    /// Shows a document and its chunks.
+    Show {
+        /// The ID of the document, as shown by `docs list`.
+        id: i64,
+        /// Print the full text of every chunk instead of a preview.
+        #[arg(long)]
+        full: bool,
+    },
+    /// Deletes a document and its chunks from the knowledge base.
+    Delete {
+        /// The ID of the document, or its path as shown by `docs list`.
+        document: String,
+    },
+    /// Re-chunks and re-embeds a document, or every document in a directory,
+    /// even if it hasn't changed.
+    Reindex {
+        /// The path of the document or directory to reindex.
+        path: PathBuf,
+    },
+}
+
+/// How `query` prints its answer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Prose, with references and the model's confidence after it.
+    Text,
+    /// A JSON object, for services that consume the answer.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format '{}'; expected text or json", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        })
+    }
+}
+
 /// The main asynchronous function that orchestrates the application.
 #[tokio::main]
 async fn main() {
@@ -62,42 +278,359 @@ async fn main() {
 /// The primary logic runner for the application.
 async fn run() -> Result<()> {
     let args = Args::parse();
-    let config = config::load()?;
+    let config = config::load(args.max_cost, args.cache)?;
 
-    let db_pool = database::init_db(&config.database_url).await?;
-    let client = openai_client::OpenAIClient::new(config.openai_api_key);
+    let storage: Arc<dyn Storage> =
+        database::init_db(&config.database_url, config.sqlite_vec_path.as_deref())
+            .await?
+            .into();
+    let client = OpenAIClient::new(&config.provider)?;
 
     match args.command {
-        Command::Ingest { path } => {
+        Command::Ingest {
+            path,
+            tags,
+            watch,
+            prune,
+        } => {
             info!("Starting 'ingest' command for path: '{}'", path.display());
-            let source_docs = pipeline::ingestion::ingest_documents(&db_pool, &path).await?;
-            if source_docs.is_empty() {
-                info!("{}", "No new or updated documents to process.".green());
-                return Ok(());
+            let source_docs =
+                pipeline::ingestion::ingest_documents(storage.as_ref(), &path, false, prune, &tags)
+                    .await?;
+            pipeline::process_documents(storage.as_ref(), &client, &config, &source_docs).await?;
+            if watch {
+                pipeline::watch::watch(storage.as_ref(), &client, &config, &path, &tags).await?;
             }
-            let chunks = pipeline::chunking::chunk_documents(&source_docs);
-            pipeline::indexing::index_chunks(&db_pool, &client, &chunks).await?;
-            info!("{}", "Ingestion process completed successfully.".green().bold());
         }
-        Command::Query { question } => {
+        Command::IngestUrl {
+            url,
+            depth,
+            max_pages,
+            tags,
+        } => {
+            info!("Starting 'ingest-url' command for URL: '{}'", url);
+            let options = CrawlOptions { depth, max_pages };
+            let source_docs =
+                pipeline::web::ingest_url(storage.as_ref(), &url, &options, &tags).await?;
+            pipeline::process_documents(storage.as_ref(), &client, &config, &source_docs).await?;
+        }
+        Command::Query {
+            question,
+            exact,
+            filters,
+            rerank,
+            transform,
+            expansion,
+            min_confidence,
+            no_stream,
+            show_context,
+            format,
+        } => {
             info!("Starting 'query' command with question: '{}'", question);
-            let query_engine = query_engine::QueryEngine::new(db_pool, client);
-            let answer = query_engine.answer_question(&question).await?;
+            let retrieval = if exact {
+                Retrieval::Exact
+            } else {
+                Retrieval::select(
+                    storage.as_ref(),
+                    &config.index_path,
+                    client.embedding_model(),
+                )
+                .await?
+            };
+            let mut query_engine = QueryEngine::new(storage, client, retrieval, filters)
+                .with_context_budget(config.context_token_budget);
+            if rerank {
+                query_engine = query_engine.with_reranking(config.rerank_model.clone());
+            }
+            if let Some(transform) = transform {
+                query_engine = query_engine
+                    .with_query_transform(transform, config.query_transform_model.clone());
+            }
+            if expansion > 0 {
+                query_engine =
+                    query_engine.with_expansion(expansion, config.query_transform_model.clone());
+            }
+            if let Some(min_confidence) = min_confidence.or(config.min_confidence) {
+                query_engine = query_engine.with_abstention(min_confidence);
+            }
+            if format == OutputFormat::Json {
+                let answer = query_engine.answer_question_structured(&question).await?;
+                println!("{}", serde_json::to_string_pretty(&answer)?);
+                return Ok(());
+            }
+            let answer = print_answer(&query_engine, &question, !no_stream, show_context).await?;
+            if answer.abstained {
+                print_closest_chunks(&answer.closest_chunks);
+            } else {
+                print_references(&answer.citations);
+            }
+            print_confidence(&answer);
+        }
+        Command::Eval {
+            dataset,
+            exact,
+            filters,
+            rerank,
+            transform,
+            expansion,
+            min_confidence,
+            judge_model,
+        } => {
+            info!(
+                "Starting 'eval' command with dataset: '{}'",
+                dataset.display()
+            );
+            let retrieval = if exact {
+                Retrieval::Exact
+            } else {
+                Retrieval::select(
+                    storage.as_ref(),
+                    &config.index_path,
+                    client.embedding_model(),
+                )
+                .await?
+            };
+            let mut query_engine = QueryEngine::new(storage, client.clone(), retrieval, filters)
+                .with_context_budget(config.context_token_budget);
+            if rerank {
+                query_engine = query_engine.with_reranking(config.rerank_model.clone());
+            }
+            if let Some(transform) = transform {
+                query_engine = query_engine
+                    .with_query_transform(transform, config.query_transform_model.clone());
+            }
+            if expansion > 0 {
+                query_engine =
+                    query_engine.with_expansion(expansion, config.query_transform_model.clone());
+            }
+            if let Some(min_confidence) = min_confidence.or(config.min_confidence) {
+                query_engine = query_engine.with_abstention(min_confidence);
+            }
+            let judge_model = judge_model.unwrap_or_else(|| client.generative_model().to_string());
+            eval::run(&query_engine, &client, &judge_model, &dataset).await?;
+        }
+        Command::Summarize { document, words } => {
+            info!("Starting 'summarize' command for document: '{}'", document);
+            let (path, summary) =
+                summarize::summarize(storage.as_ref(), &client, &document, words).await?;
+            println!("\n{}", format!("Summary of '{}':", path).bold().cyan());
+            println!("{}", summary);
+        }
+        Command::Reembed {
+            batch_size,
+            dry_run,
+        } => {
+            if dry_run {
+                reembed::status(storage.as_ref(), client.embedding_model()).await?;
+            } else {
+                info!("Starting 'reembed' command");
+                let stats =
+                    reembed::reembed(storage.as_ref(), &client, &config, batch_size).await?;
+                println!(
+                    "Re-embedded {} chunks in {} batches.",
+                    stats.chunks, stats.batches
+                );
+            }
+        }
+        Command::Docs { command } => match command {
+            DocsCommand::List => docs::list(storage.as_ref()).await?,
+            DocsCommand::Show { id, full } => docs::show(storage.as_ref(), id, full).await?,
+            DocsCommand::Delete { document } => {
+                let path = docs::delete(storage.as_ref(), &document).await?;
+                pipeline::update_search_index(storage.as_ref(), &config).await?;
+                println!("Deleted '{}'.", path);
+            }
+            DocsCommand::Reindex { path } => {
+                info!(
+                    "Starting 'docs reindex' command for path: '{}'",
+                    path.display()
+                );
+                let source_docs = pipeline::ingestion::ingest_documents(
+                    storage.as_ref(),
+                    &path,
+                    true,
+                    false,
+                    &[],
+                )
+                .await?;
+                pipeline::process_documents(storage.as_ref(), &client, &config, &source_docs)
+                    .await?;
+            }
+        },
+        Command::Export { archive } => {
+            info!("Starting 'export' command to '{}'", archive.display());
+            let stats =
+                archive::export(storage.as_ref(), &archive, client.embedding_model()).await?;
+            println!(
+                "Exported {} documents and {} chunks to '{}'.",
+                stats.documents,
+                stats.chunks,
+                archive.display()
+            );
+        }
+        Command::Import { archive } => {
+            info!("Starting 'import' command from '{}'", archive.display());
+            let stats =
+                archive::import(storage.as_ref(), &archive, client.embedding_model()).await?;
+            pipeline::update_search_index(storage.as_ref(), &config).await?;
+            println!(
+                "Imported {} documents and {} chunks from '{}'; {} documents were already up to date.",
+                stats.documents,
+                stats.chunks,
+                archive.display(),
+                stats.unchanged
+            );
+        }
+        Command::Serve { addr, documents } => {
+            info!("Starting 'serve' command on {}", addr);
+            server::serve(storage, client, config, &documents, addr).await?;
+        }
+        Command::Mcp { documents } => {
+            info!("Starting 'mcp' command");
+            mcp::serve(storage, client, config, &documents).await?;
+        }
+    }
+
+    Ok(())
+}
 
+/// Answers a question and prints the answer, as the model writes it if
+/// `stream` is set, or once it is complete otherwise. With `show_context`,
+/// the retrieved chunks are printed first.
+async fn print_answer(
+    query_engine: &QueryEngine,
+    question: &str,
+    stream: bool,
+    show_context: bool,
+) -> Result<Answer> {
+    let context = query_engine.find_relevant_chunks(question).await?;
+    if show_context {
+        print_context(&context);
+    }
+
+    if !stream {
+        let answer = query_engine
+            .answer_from_chunks(question, context, None)
+            .await?;
+        println!("\n{}", "Answer:".bold().cyan());
+        println!("{}", answer.text);
+        return Ok(answer);
+    }
+
+    // The heading is printed with the first token, so that it follows the
+    // log output of the retrieval.
+    let mut started = false;
+    let mut on_token = |token: &str| {
+        if !started {
             println!("\n{}", "Answer:".bold().cyan());
-            println!("{}", answer);
+            started = true;
         }
+        print!("{}", token);
+        let _ = io::stdout().flush();
+    };
+    let answer = query_engine
+        .answer_from_chunks(question, context, Some(&mut on_token))
+        .await?;
+    if started {
+        println!();
+    } else {
+        println!("\n{}", "Answer:".bold().cyan());
+        println!("{}", answer.text);
     }
+    Ok(answer)
+}
 
-    Ok(())
+/// Prints the chunks an answer is written from, numbered as the answer
+/// cites them, with their scores and the position in their document.
+fn print_context(chunks: &[RelevantChunk]) {
+    println!("\n{}", "Retrieved context:".bold().cyan());
+    if chunks.is_empty() {
+        println!("(no relevant chunks were found)");
+        return;
+    }
+    for (index, chunk) in chunks.iter().enumerate() {
+        let position = match (chunk.start_offset, chunk.end_offset) {
+            (Some(start), Some(end)) => format!("characters {}-{}", start, end),
+            _ => format!("chunk {}", chunk.id),
+        };
+        let similarity = match chunk.similarity {
+            Some(similarity) => format!("{:.4}", similarity),
+            None => "- (keyword match only)".to_string(),
+        };
+        println!(
+            "\n{} {} ({})",
+            format!("[{}]", index + 1).bold(),
+            chunk.document_path,
+            position
+        );
+        println!(
+            "{}",
+            format!("similarity {}, fused score {:.4}", similarity, chunk.score).dimmed()
+        );
+        println!("{}", chunk.text.trim());
+    }
+}
+
+/// Prints the references section mapping an answer's citations back to the
+/// source documents.
+fn print_references(citations: &[Citation]) {
+    if citations.is_empty() {
+        return;
+    }
+    println!("\n{}", "References:".bold().cyan());
+    for citation in citations {
+        let position = match (citation.start_offset, citation.end_offset) {
+            (Some(start), Some(end)) => format!("characters {}-{}", start, end),
+            _ => format!("chunk {}", citation.chunk_id),
+        };
+        println!(
+            "[{}] {} ({})",
+            citation.number, citation.document_path, position
+        );
+    }
+}
+
+/// Prints the model's confidence in an answer, if it rated it.
+fn print_confidence(answer: &Answer) {
+    let Some(confidence) = answer.confidence else {
+        return;
+    };
+    let evidence = match answer.evidence {
+        Some(evidence) => format!(" (evidence: {})", evidence),
+        None => String::new(),
+    };
+    println!(
+        "\n{} {}%{}",
+        "Confidence:".bold().cyan(),
+        confidence,
+        evidence
+    );
+}
+
+/// Prints the chunks closest to a question that the engine declined to answer.
+fn print_closest_chunks(chunks: &[Citation]) {
+    if chunks.is_empty() {
+        return;
+    }
+    println!("\n{}", "Closest chunks:".bold().cyan());
+    for chunk in chunks {
+        let position = match (chunk.start_offset, chunk.end_offset) {
+            (Some(start), Some(end)) => format!("characters {}-{}", start, end),
+            _ => format!("chunk {}", chunk.chunk_id),
+        };
+        println!("[{}] {} ({})", chunk.number, chunk.document_path, position);
+    }
 }
 
-/// Initializes the logging system.
+/// Initializes the logging system. Logs are written to stderr, so that
+/// stdout only holds the command's output, such as an answer or the MCP
+/// server's messages.
 fn init_logger() {
     let filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
     fmt::Subscriber::builder()
         .with_env_filter(filter)
         .with_target(true)
+        .with_writer(io::stderr)
         .init();
 }
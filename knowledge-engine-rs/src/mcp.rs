@@ -0,0 +1,409 @@
+//! src/mcp.rs
+//!
+//! This module implements the `mcp` command: a Model Context Protocol server
+//! over stdio, so that Claude Desktop and other MCP clients can use the local
+//! knowledge base as tools. Messages are JSON-RPC 2.0, one per line on stdin
+//! and stdout; logs go to stderr, so they never mix with the messages.
+//!
+//! The server exposes two tools:
+//! - `query_knowledge_base` answers a question from the knowledge base, with
+//!   the sources the answer cites.
+//! - `ingest_document` ingests a file or directory within the documents
+//!   directory.
+
+use crate::config::Config;
+use crate::database::Storage;
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use crate::pipeline;
+use crate::query_engine::{Citation, QueryEngine, Retrieval};
+use crate::server;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tracing::{info, warn};
+
+/// The protocol versions the server can speak, latest first. The tools only
+/// use features that all of them share.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+// The JSON-RPC error codes the server replies with.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC message from the client: a request, or a notification if it
+/// has no `id`. Messages without a `method` are responses, which the server
+/// never asks for.
+#[derive(Deserialize, Debug)]
+struct RpcMessage {
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A JSON-RPC error to reply with.
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// The parameters of a `tools/call` request.
+#[derive(Deserialize, Debug)]
+struct ToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+/// The arguments of the `query_knowledge_base` tool.
+#[derive(Deserialize, Debug)]
+struct QueryArguments {
+    question: String,
+    /// Only retrieve chunks from documents with these tags, as `KEY=VALUE`.
+    #[serde(default)]
+    filters: Vec<String>,
+    /// Rerank the retrieved chunks with the reranking model.
+    #[serde(default)]
+    rerank: bool,
+}
+
+/// The arguments of the `ingest_document` tool.
+#[derive(Deserialize, Debug)]
+struct IngestArguments {
+    /// A file or directory to ingest, relative to the documents directory.
+    path: PathBuf,
+    /// Tags to attach to the ingested documents, as `KEY=VALUE`.
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// The state shared by all requests.
+struct McpState {
+    storage: Arc<dyn Storage>,
+    client: OpenAIClient,
+    config: Config,
+    /// The directory that ingested paths are resolved against, as given.
+    documents_dir: PathBuf,
+    /// The canonical form of `documents_dir`, which ingested paths must stay
+    /// within.
+    documents_root: PathBuf,
+    /// The retrieval method for queries, updated after each ingestion.
+    retrieval: RwLock<Retrieval>,
+    /// Serializes ingestions, which would otherwise race on the same
+    /// documents and on rebuilding the index.
+    ingest_lock: Mutex<()>,
+}
+
+/// Serves the knowledge engine as MCP tools over stdio until the client
+/// closes stdin. Requests are handled concurrently, so a long query doesn't
+/// hold up a `ping`; replies are written as they complete.
+pub async fn serve(
+    storage: Arc<dyn Storage>,
+    client: OpenAIClient,
+    config: Config,
+    documents_dir: &Path,
+) -> Result<()> {
+    let retrieval = Retrieval::select(
+        storage.as_ref(),
+        &config.index_path,
+        client.embedding_model(),
+    )
+    .await?;
+    let state = Arc::new(McpState {
+        storage,
+        client,
+        config,
+        documents_dir: documents_dir.to_path_buf(),
+        documents_root: documents_dir.canonicalize()?,
+        retrieval: RwLock::new(retrieval),
+        ingest_lock: Mutex::new(()),
+    });
+
+    // A single task writes every reply, so that replies are never interleaved.
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = receiver.recv().await {
+            stdout
+                .write_all(format!("{}\n", message).as_bytes())
+                .await?;
+            stdout.flush().await?;
+        }
+        Ok::<(), std::io::Error>(())
+    });
+
+    info!("Serving the knowledge base as MCP tools over stdio.");
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: RpcMessage = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = RpcError {
+                    code: PARSE_ERROR,
+                    message: format!("Invalid JSON-RPC message: {}", e),
+                };
+                let _ = sender.send(error_reply(Value::Null, error));
+                continue;
+            }
+        };
+        let state = state.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            if let Some(reply) = handle_message(&state, message).await {
+                let _ = sender.send(reply);
+            }
+        });
+    }
+
+    // The requests still being handled hold senders, so the writer stops
+    // once they have replied.
+    info!("The client closed stdin; stopping the MCP server.");
+    drop(sender);
+    writer
+        .await
+        .map_err(|e| Error::Processing(format!("The MCP reply writer failed: {}", e)))??;
+    Ok(())
+}
+
+/// Handles one message from the client.
+///
+/// # Returns
+///
+/// The reply to send, or `None` for notifications and responses.
+async fn handle_message(state: &McpState, message: RpcMessage) -> Option<Value> {
+    let method = message.method?;
+    let result = match method.as_str() {
+        "initialize" => Ok(initialize(&message.params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(state, message.params).await,
+        _ if method.starts_with("notifications/") => return None,
+        _ => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Unknown method '{}'.", method),
+        }),
+    };
+    let id = message.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_reply(id, error),
+    })
+}
+
+/// Replies to `initialize` with the protocol version to use: the client's,
+/// if the server speaks it, or the latest one otherwise.
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = PROTOCOL_VERSIONS
+        .into_iter()
+        .find(|version| Some(*version) == requested)
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+/// Describes the tools and their arguments as JSON Schemas.
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "query_knowledge_base",
+            "description": "Answers a question from the local knowledge base of ingested documents. The answer cites its sources inline as [1], [2], followed by the document each source comes from. If the documents don't hold enough evidence, it says so instead of answering.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "question": {
+                        "type": "string",
+                        "description": "The question to answer."
+                    },
+                    "filters": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only use documents with all of these tags, as KEY=VALUE."
+                    },
+                    "rerank": {
+                        "type": "boolean",
+                        "description": "Have a cheaper model rerank the retrieved passages first, which helps with ambiguous questions."
+                    }
+                },
+                "required": ["question"]
+            }
+        },
+        {
+            "name": "ingest_document",
+            "description": "Ingests a file or directory from the documents directory into the knowledge base, so that questions can be answered from it. Only new or changed documents are processed. Supports .txt, .md, and .pdf files.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "The file or directory, relative to the documents directory."
+                    },
+                    "tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tags to attach to the ingested documents, as KEY=VALUE."
+                    }
+                },
+                "required": ["path"]
+            }
+        }
+    ])
+}
+
+/// Runs a tool. Failures of the tool itself, such as an unreachable OpenAI
+/// API, are returned as a result marked as an error, so that the model can
+/// see them and react.
+async fn call_tool(state: &McpState, params: Value) -> std::result::Result<Value, RpcError> {
+    let call: ToolCall = serde_json::from_value(params).map_err(invalid_params)?;
+    let outcome = match call.name.as_str() {
+        "query_knowledge_base" => {
+            let arguments = serde_json::from_value(call.arguments).map_err(invalid_params)?;
+            query(state, arguments).await
+        }
+        "ingest_document" => {
+            let arguments = serde_json::from_value(call.arguments).map_err(invalid_params)?;
+            ingest(state, arguments).await
+        }
+        name => {
+            return Err(RpcError {
+                code: INVALID_PARAMS,
+                message: format!("Unknown tool '{}'.", name),
+            })
+        }
+    };
+
+    Ok(match outcome {
+        Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+        Err(e) => {
+            warn!("The '{}' tool failed: {}", call.name, e);
+            json!({ "content": [{ "type": "text", "text": e.to_string() }], "isError": true })
+        }
+    })
+}
+
+/// Runs the `query_knowledge_base` tool.
+///
+/// # Returns
+///
+/// The answer, followed by the sources it cites, or by the closest chunks
+/// if the engine declined to answer.
+async fn query(state: &McpState, arguments: QueryArguments) -> Result<String> {
+    let filters = server::parse_tags(&arguments.filters).map_err(Error::Config)?;
+    let retrieval = state.retrieval.read().await.clone();
+    let mut query_engine = QueryEngine::new(
+        state.storage.clone(),
+        state.client.clone(),
+        retrieval,
+        filters,
+    )
+    .with_context_budget(state.config.context_token_budget);
+    if arguments.rerank {
+        query_engine = query_engine.with_reranking(state.config.rerank_model.clone());
+    }
+    if let Some(min_confidence) = state.config.min_confidence {
+        query_engine = query_engine.with_abstention(min_confidence);
+    }
+
+    let answer = query_engine.answer_question(&arguments.question).await?;
+    let mut text = answer.text;
+    if answer.abstained {
+        append_sources(&mut text, "Closest chunks:", &answer.closest_chunks);
+    } else {
+        append_sources(&mut text, "Sources:", &answer.citations);
+    }
+    if let Some(confidence) = answer.confidence {
+        text.push_str(&format!("\n\nConfidence: {}%", confidence));
+    }
+    Ok(text)
+}
+
+/// Lists sources under a heading after the text, if there are any.
+fn append_sources(text: &mut String, heading: &str, sources: &[Citation]) {
+    if sources.is_empty() {
+        return;
+    }
+    text.push_str("\n\n");
+    text.push_str(heading);
+    for source in sources {
+        let position = match (source.start_offset, source.end_offset) {
+            (Some(start), Some(end)) => format!("characters {}-{}", start, end),
+            _ => format!("chunk {}", source.chunk_id),
+        };
+        text.push_str(&format!(
+            "\n[{}] {} ({})",
+            source.number, source.document_path, position
+        ));
+    }
+}
+
+/// Runs the `ingest_document` tool.
+///
+/// # Returns
+///
+/// The paths of the documents that were new or changed and got indexed.
+async fn ingest(state: &McpState, arguments: IngestArguments) -> Result<String> {
+    let tags = server::parse_tags(&arguments.tags).map_err(Error::Config)?;
+    let path = server::resolve_path(&state.documents_dir, &state.documents_root, &arguments.path)
+        .ok_or_else(|| {
+        Error::Document(format!(
+            "'{}' is not a file or directory within the documents directory.",
+            arguments.path.display()
+        ))
+    })?;
+
+    let _guard = state.ingest_lock.lock().await;
+    let storage = state.storage.as_ref();
+    let source_docs =
+        pipeline::ingestion::ingest_documents(storage, &path, false, false, &tags).await?;
+    pipeline::process_documents(storage, &state.client, &state.config, &source_docs).await?;
+    let retrieval = Retrieval::select(
+        storage,
+        &state.config.index_path,
+        state.client.embedding_model(),
+    )
+    .await?;
+    *state.retrieval.write().await = retrieval;
+
+    if source_docs.is_empty() {
+        return Ok(format!(
+            "No new or changed documents were found in '{}'.",
+            path.display()
+        ));
+    }
+    let mut text = format!("Ingested {} documents:", source_docs.len());
+    for document in &source_docs {
+        text.push_str(&format!("\n- {}", document.path));
+    }
+    Ok(text)
+}
+
+fn invalid_params(e: serde_json::Error) -> RpcError {
+    RpcError {
+        code: INVALID_PARAMS,
+        message: format!("Invalid parameters: {}", e),
+    }
+}
+
+fn error_reply(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
@@ -1,57 +1,67 @@
 //! src/openai_client.rs
 //!
-//! This module provides a dedicated client for interacting with the OpenAI API.
-//! It is designed to handle different types of requests, including text
-//! embeddings and generative completions.
+//! This module provides a dedicated client for interacting with the model
+//! provider. It is designed to handle different types of requests, including
+//! text embeddings and generative completions. The requests are sent to the
+//! providers of the shared `ai-core` crate chosen with `AI_PROVIDER` and
+//! `AI_EMBEDDING_PROVIDER`: OpenAI by default, or Azure OpenAI, Anthropic
+//! (for completions only), or a local Ollama server.
 
 use crate::error::{Error, Result};
-use serde::{Deserialize, Serialize};
+use ai_core::{ChatProvider, ChatRequest, EmbeddingProvider, ProviderConfig, TokenStream};
+use std::sync::Arc;
 use tracing::{info, instrument};
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1";
+/// The embedding model used on OpenAI, unless `AI_EMBEDDING_MODEL` names another.
 const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+/// The generative model used on OpenAI, unless `AI_MODEL` names another.
 const GENERATIVE_MODEL: &str = "gpt-4o";
 
-/// A client for making requests to the OpenAI API.
+/// A client for making requests to the configured providers, with the
+/// shared `ai-core` crate.
 #[derive(Clone)]
 pub struct OpenAIClient {
-    http_client: reqwest::Client,
-    api_key: String,
+    chat: Arc<dyn ChatProvider>,
+    embeddings: Arc<dyn EmbeddingProvider>,
+    generative_model: String,
+    embedding_model: String,
 }
 
 impl OpenAIClient {
-    /// Creates a new instance of the `OpenAIClient`.
-    pub fn new(api_key: String) -> Self {
-        Self {
-            http_client: reqwest::Client::new(),
-            api_key,
-        }
+    /// Creates a new instance of the `OpenAIClient` for the configured
+    /// providers and models.
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            chat: config.chat_provider()?,
+            embeddings: config.embedding_provider()?,
+            generative_model: config.chat_model(GENERATIVE_MODEL),
+            embedding_model: config.embedding_model(EMBEDDING_MODEL),
+        })
+    }
+
+    /// The model that answers are written with.
+    pub fn generative_model(&self) -> &str {
+        &self.generative_model
+    }
+
+    /// The model embeddings are made with. Chunks record it, since
+    /// embeddings of different models can't be compared.
+    pub fn embedding_model(&self) -> &str {
+        &self.embedding_model
     }
 
     /// Generates a vector embedding for a given piece of text.
     #[instrument(skip(self, text))]
     pub async fn get_embedding(&self, text: &str) -> Result<Vec<f32>> {
-        info!("Requesting embedding from OpenAI API.");
-
-        let body = EmbeddingRequest {
-            input: text.to_string(),
-            model: EMBEDDING_MODEL.to_string(),
-        };
-
-        let response: EmbeddingResponse = self
-            .http_client
-            .post(format!("{}/embeddings", OPENAI_API_URL))
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
+        info!("Requesting embedding from '{}'.", self.embedding_model);
 
-        if let Some(embedding_data) = response.data.into_iter().next() {
+        let embeddings = self
+            .embeddings
+            .embed(&self.embedding_model, &[text.to_string()])
+            .await?;
+        if let Some(embedding) = embeddings.into_iter().next() {
             info!("Successfully generated text embedding.");
-            Ok(embedding_data.embedding)
+            Ok(embedding)
         } else {
             Err(Error::OpenAI(
                 "API response did not contain any embedding data.".to_string(),
@@ -59,85 +69,68 @@ impl OpenAIClient {
         }
     }
 
+    /// Generates vector embeddings for several pieces of text in a single
+    /// request.
+    ///
+    /// # Returns
+    ///
+    /// The embeddings, in the order of `texts`.
+    #[instrument(skip_all, fields(count = texts.len()))]
+    pub async fn get_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        info!(
+            "Requesting {} embeddings from '{}'.",
+            texts.len(),
+            self.embedding_model
+        );
+        Ok(self.embeddings.embed(&self.embedding_model, texts).await?)
+    }
+
     /// Generates a conversational completion based on a system and user prompt.
-    #[instrument(skip(self, system_prompt, user_prompt))]
     pub async fn get_completion(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
-        info!("Requesting completion from OpenAI API.");
-
-        let messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: user_prompt.to_string(),
-            },
-        ];
-
-        let body = ChatCompletionRequest {
-            model: GENERATIVE_MODEL.to_string(),
-            messages,
-        };
-
-        let response: ChatCompletionResponse = self
-            .http_client
-            .post(format!("{}/chat/completions", OPENAI_API_URL))
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-
-        if let Some(choice) = response.choices.into_iter().next() {
-            info!("Successfully received completion from API.");
-            Ok(choice.message.content)
-        } else {
-            Err(Error::OpenAI(
-                "API response did not contain any choices.".to_string(),
-            ))
-        }
+        self.get_completion_with_model(&self.generative_model, system_prompt, user_prompt)
+            .await
     }
-}
 
-//========= API Data Structures =========//
-
-#[derive(Serialize)]
-struct EmbeddingRequest {
-    input: String,
-    model: String,
-}
-
-#[derive(Deserialize)]
-struct EmbeddingResponse {
-    data: Vec<EmbeddingData>,
-}
-
-#[derive(Deserialize)]
-struct EmbeddingData {
-    embedding: Vec<f32>,
-}
-
-#[derive(Serialize, Debug)]
-struct ChatCompletionRequest {
-    model: String,
-    messages: Vec<Message>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Message {
-    role: String,
-    content: String,
-}
-
-#[derive(Deserialize, Debug)]
-struct ChatCompletionResponse {
-    choices: Vec<Choice>,
-}
+    /// Generates a conversational completion with the given model, such as a
+    /// cheaper one for auxiliary tasks.
+    #[instrument(skip(self, system_prompt, user_prompt))]
+    pub async fn get_completion_with_model(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<String> {
+        info!("Requesting completion from '{}'.", model);
+
+        let request = ChatRequest::from_prompts(model, system_prompt, user_prompt);
+        let content = self.chat.complete(&request).await?;
+
+        info!("Successfully received completion from API.");
+        Ok(content)
+    }
 
-#[derive(Deserialize, Debug)]
-struct Choice {
-    message: Message,
+    /// Generates a conversational completion like `get_completion`, but
+    /// streams it.
+    ///
+    /// # Returns
+    ///
+    /// A stream of the pieces of the completion, each yielded as soon as it
+    /// arrives.
+    #[instrument(skip(self, system_prompt, user_prompt))]
+    pub async fn stream_completion(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<TokenStream> {
+        info!(
+            "Requesting streamed completion from '{}'.",
+            self.generative_model
+        );
+
+        let request = ChatRequest::from_prompts(&self.generative_model, system_prompt, user_prompt);
+        let tokens = self.chat.chat_stream(&request).await?;
+
+        info!("Started receiving streamed completion from API.");
+        Ok(tokens)
+    }
 }
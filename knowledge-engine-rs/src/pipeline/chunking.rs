@@ -4,52 +4,257 @@
 //! source document and splits it into smaller, manageable chunks suitable for
 //! embedding.
 
+use crate::error::{Error, Result};
 use crate::pipeline::ingestion::SourceDocument;
-use text_splitter::TextSplitter;
+use ai_core::tokens::{self, CoreBPE};
+use std::fmt;
+use std::str::FromStr;
+use text_splitter::{Characters, ChunkConfig, ChunkSizer, MarkdownSplitter, TextSplitter};
 use tracing::info;
 
-const MAX_CHUNK_SIZE: usize = 1000; // The target size for each text chunk in characters.
+/// How documents are split into chunks. Both strategies split at the largest
+/// semantic boundary that keeps a chunk within the size limit: paragraphs,
+/// then sentences, then words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkStrategy {
+    /// `Markdown` for Markdown files, `Text` for everything else.
+    Auto,
+    /// Splits at paragraph, sentence, and word boundaries.
+    Text,
+    /// Also splits at Markdown headings, sections first, and keeps code
+    /// blocks and lists together where possible.
+    Markdown,
+}
+
+/// The unit chunk sizes are measured in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkUnit {
+    Characters,
+    /// Tokens of the embedding model's encoding.
+    Tokens,
+}
+
+/// How documents are split into chunks, read from the environment.
+#[derive(Clone, Debug)]
+pub struct ChunkingConfig {
+    pub strategy: ChunkStrategy,
+    pub unit: ChunkUnit,
+    /// The maximum size of a chunk.
+    pub size: usize,
+    /// How much of the end of each chunk is repeated at the start of the next
+    /// one, so that passages cut at a boundary keep some context.
+    pub overlap: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkStrategy::Auto,
+            unit: ChunkUnit::Characters,
+            size: 1000,
+            overlap: 0,
+        }
+    }
+}
+
+impl FromStr for ChunkStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "text" => Ok(Self::Text),
+            "markdown" => Ok(Self::Markdown),
+            _ => Err(format!(
+                "unknown chunking strategy '{}'; expected auto, text, or markdown",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ChunkStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Auto => "auto",
+            Self::Text => "text",
+            Self::Markdown => "markdown",
+        })
+    }
+}
+
+impl FromStr for ChunkUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "characters" | "chars" => Ok(Self::Characters),
+            "tokens" => Ok(Self::Tokens),
+            _ => Err(format!(
+                "unknown chunk size unit '{}'; expected characters or tokens",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ChunkUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Characters => "characters",
+            Self::Tokens => "tokens",
+        })
+    }
+}
 
 /// Represents a single piece of text derived from a source document.
 pub struct TextChunk {
     pub document_id: i64,
     pub chunk_text: String,
+    /// The character offset in the document where the chunk starts.
+    pub start_offset: usize,
+    /// The character offset in the document just past the chunk's end.
+    pub end_offset: usize,
+    /// The chunking settings the chunk was made with, such as
+    /// `markdown, 512 tokens, 64 overlap`, recorded for provenance.
+    pub chunking: String,
 }
 
-/// Splits a collection of source documents into text chunks.
-///
-/// This function iterates through each source document and uses the `text-splitter`
-/// crate to break its content down into chunks of a predefined maximum size.
+/// Splits source documents into text chunks with the `text-splitter` crate,
+/// as configured by a `ChunkingConfig`.
 ///
-/// # Arguments
-///
-/// * `documents` - A slice of `SourceDocument`s to be processed.
-///
-/// # Returns
-///
-/// A `Vec` of `TextChunk` structs ready for the next pipeline stage (indexing).
-pub fn chunk_documents(documents: &[SourceDocument]) -> Vec<TextChunk> {
-    info!("Starting document chunking process...");
-    let splitter = TextSplitter::default().with_trim_chunks(true);
-    let mut all_chunks = Vec::new();
-
-    for doc in documents {
-        let chunks: Vec<_> = splitter
-            .chunks(&doc.content, MAX_CHUNK_SIZE)
-            .map(|text| TextChunk {
-                document_id: doc.id,
-                chunk_text: text.to_string(),
+/// The splitters, and the tokenizer for token-sized chunks, are built once
+/// and shared by every document. A `Chunker` can be shared between threads,
+/// so several documents can be chunked at once.
+pub struct Chunker {
+    config: ChunkingConfig,
+    splitters: Splitters,
+}
+
+/// The text and Markdown splitters, for chunk sizes in each unit.
+enum Splitters {
+    Characters(TextSplitter<Characters>, MarkdownSplitter<Characters>),
+    Tokens(TextSplitter<&'static CoreBPE>, MarkdownSplitter<&'static CoreBPE>),
+}
+
+impl Chunker {
+    /// Builds the splitters for `config`. Token sizes are counted with the
+    /// tokenizer of `embedding_model`.
+    pub fn new(config: &ChunkingConfig, embedding_model: &str) -> Result<Self> {
+        info!(
+            "Preparing document chunking ({} strategy, {} {}, {} overlap)...",
+            config.strategy, config.size, config.unit, config.overlap
+        );
+        let splitters = match config.unit {
+            ChunkUnit::Characters => Splitters::Characters(
+                TextSplitter::new(chunk_config(config, Characters)?),
+                MarkdownSplitter::new(chunk_config(config, Characters)?),
+            ),
+            ChunkUnit::Tokens => {
+                let tokenizer = tokens::tokenizer(embedding_model)?;
+                Splitters::Tokens(
+                    TextSplitter::new(chunk_config(config, tokenizer)?),
+                    MarkdownSplitter::new(chunk_config(config, tokenizer)?),
+                )
+            }
+        };
+        Ok(Self {
+            config: config.clone(),
+            splitters,
+        })
+    }
+
+    /// Splits a document into chunks, ready for the next pipeline stage
+    /// (indexing).
+    pub fn chunk_document(&self, doc: &SourceDocument) -> Vec<TextChunk> {
+        let config = &self.config;
+        let strategy = match config.strategy {
+            ChunkStrategy::Auto if is_markdown(&doc.path) => ChunkStrategy::Markdown,
+            ChunkStrategy::Auto => ChunkStrategy::Text,
+            strategy => strategy,
+        };
+        let chunking = format!(
+            "{}, {} {}, {} overlap",
+            strategy, config.size, config.unit, config.overlap
+        );
+        let markdown = strategy == ChunkStrategy::Markdown;
+        let pieces: Vec<(usize, &str)> = match &self.splitters {
+            Splitters::Characters(_, splitter) if markdown => {
+                splitter.chunk_indices(&doc.content).collect()
+            }
+            Splitters::Characters(splitter, _) => splitter.chunk_indices(&doc.content).collect(),
+            Splitters::Tokens(_, splitter) if markdown => {
+                splitter.chunk_indices(&doc.content).collect()
+            }
+            Splitters::Tokens(splitter, _) => splitter.chunk_indices(&doc.content).collect(),
+        };
+
+        let mut offsets = CharOffsets::new(&doc.content);
+        let chunks: Vec<_> = pieces
+            .into_iter()
+            .map(|(byte_offset, text)| {
+                let start_offset = offsets.at(byte_offset);
+                TextChunk {
+                    document_id: doc.id,
+                    chunk_text: text.to_string(),
+                    start_offset,
+                    end_offset: start_offset + text.chars().count(),
+                    chunking: chunking.clone(),
+                }
             })
             .collect();
         info!(
             "Split document '{}' (ID: {}) into {} chunks.",
-            doc.path.display(),
+            doc.path,
             doc.id,
             chunks.len()
         );
-        all_chunks.extend(chunks);
+        chunks
     }
+}
+
+/// The splitter settings for chunk sizes measured by `sizer`.
+fn chunk_config<S: ChunkSizer>(config: &ChunkingConfig, sizer: S) -> Result<ChunkConfig<S>> {
+    ChunkConfig::new(config.size)
+        .with_sizer(sizer)
+        .with_trim(true)
+        .with_overlap(config.overlap)
+        .map_err(|e| Error::Config(format!("Invalid chunk overlap: {}", e)))
+}
+
+/// Whether a document's path or URL names a Markdown file.
+fn is_markdown(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
 
-    info!("Document chunking complete. Generated {} total chunks.", all_chunks.len());
-    all_chunks
+/// Converts byte offsets into a text into character offsets. Offsets passed
+/// in increasing order are counted from the previous one rather than from
+/// the start of the text.
+struct CharOffsets<'a> {
+    text: &'a str,
+    byte_offset: usize,
+    char_offset: usize,
+}
+
+impl<'a> CharOffsets<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            byte_offset: 0,
+            char_offset: 0,
+        }
+    }
+
+    /// The character offset of `byte_offset`.
+    fn at(&mut self, byte_offset: usize) -> usize {
+        if byte_offset < self.byte_offset {
+            // Out of order; count from the start instead.
+            self.byte_offset = 0;
+            self.char_offset = 0;
+        }
+        self.char_offset += self.text[self.byte_offset..byte_offset].chars().count();
+        self.byte_offset = byte_offset;
+        self.char_offset
+    }
 }
@@ -1,45 +1,41 @@
 //! src/pipeline/indexing.rs
 //!
 //! The final stage of the data processing pipeline. This module is responsible
-//! for taking text chunks, generating vector embeddings for them using the
-//! OpenAI API, and storing them in the database for later retrieval.
+//! for taking text chunks, generating vector embeddings for them with the
+//! configured embedding model, and storing them in the database for later retrieval.
 
+use crate::database::{NewChunk, Storage};
 use crate::error::Result;
 use crate::openai_client::OpenAIClient;
 use crate::pipeline::chunking::TextChunk;
-use sqlx::SqlitePool;
 use tracing::{info, instrument};
 
 /// Indexes a collection of text chunks by generating and storing their embeddings.
 #[instrument(skip_all)]
 pub async fn index_chunks(
-    pool: &SqlitePool,
+    storage: &dyn Storage,
     client: &OpenAIClient,
     chunks: &[TextChunk],
 ) -> Result<()> {
     info!("Starting chunk indexing process for {} chunks...", chunks.len());
-    let mut transaction = pool.begin().await?;
+    let mut new_chunks = Vec::with_capacity(chunks.len());
 
     for chunk in chunks {
-        let embedding_vec = client.get_embedding(&chunk.chunk_text).await?;
-
-        let embedding_bytes: Vec<u8> = embedding_vec
-            .iter()
-            .flat_map(|&f| f.to_ne_bytes())
-            .collect();
-
-        // Use a runtime-checked query to avoid compile-time database access.
-        sqlx::query(
-            "INSERT INTO chunks (document_id, chunk_text, embedding) VALUES (?, ?, ?)",
-        )
-        .bind(chunk.document_id)
-        .bind(&chunk.chunk_text)
-        .bind(&embedding_bytes)
-        .execute(&mut *transaction)
-        .await?;
+        let embedding = client.get_embedding(&chunk.chunk_text).await?;
+        new_chunks.push(NewChunk {
+            document_id: chunk.document_id,
+            chunk_text: &chunk.chunk_text,
+            start_offset: Some(chunk.start_offset as i64),
+            end_offset: Some(chunk.end_offset as i64),
+            chunking: Some(&chunk.chunking),
+            embedding,
+            embedding_model: client.embedding_model(),
+        });
     }
 
-    transaction.commit().await?;
+    // All chunks are stored in one transaction, so an interrupted run leaves
+    // no partially indexed documents behind.
+    storage.insert_chunks(&new_chunks).await?;
 
     info!("Successfully indexed {} chunks into the database.", chunks.len());
     Ok(())
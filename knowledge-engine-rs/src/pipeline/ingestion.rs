@@ -4,100 +4,201 @@
 //! is responsible for discovering documents, tracking their state via content
 //! hashing, and storing their metadata in the database.
 
+use crate::database::{DocumentSummary, Storage, Tag};
 use crate::error::{Error, Result};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use sha2::{Digest, Sha256};
-use sqlx::SqlitePool;
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::thread;
 use tracing::{info, warn};
 use walkdir::WalkDir;
 
 /// Represents a source document to be processed by the pipeline.
+#[derive(Clone)]
 pub struct SourceDocument {
     pub id: i64,
-    pub path: PathBuf,
+    /// The document's file path or URL, as stored in the database.
+    pub path: String,
     pub content: String,
 }
 
-#[derive(sqlx::FromRow)]
-struct DocIdAndHash {
-    id: i64,
-    content_hash: String,
-}
-
 /// Scans a directory for documents and ingests new or updated ones.
+///
+/// Files are read, and their text extracted and hashed, on blocking threads,
+/// as many at a time as there are CPU cores. They are recorded in the
+/// database in the order they were found.
+///
+/// With `force`, documents that haven't changed are ingested again too, so
+/// their chunks and embeddings are rebuilt from scratch. The `tags` are
+/// attached to every document found, whether it changed or not.
+///
+/// Documents previously ingested from within `documents_path` whose files
+/// were not found are stale. With `prune`, they are deleted along with their
+/// chunks; otherwise they are only reported.
 pub async fn ingest_documents(
-    pool: &SqlitePool,
+    storage: &dyn Storage,
     documents_path: &Path,
+    force: bool,
+    prune: bool,
+    tags: &[Tag],
 ) -> Result<Vec<SourceDocument>> {
     info!("Starting document ingestion from '{}'...", documents_path.display());
     let mut documents_to_process = Vec::new();
 
-    for entry in WalkDir::new(documents_path)
+    let paths: Vec<PathBuf> = WalkDir::new(documents_path)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-        
-        let content = match path.extension().and_then(|s| s.to_str()) {
-            Some("pdf") => extract_pdf_text(path)?,
-            Some("txt") | Some("md") => fs::read_to_string(path)?,
-            _ => {
+        .map(|e| e.into_path())
+        .filter(|path| {
+            let supported = matches!(
+                path.extension().and_then(|s| s.to_str()),
+                Some("pdf" | "txt" | "md")
+            );
+            if !supported {
                 warn!("Unsupported file type, skipping: {}", path.display());
-                continue;
             }
-        };
+            supported
+        })
+        .collect();
+    let seen: HashSet<String> = paths
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
 
-        let hash = calculate_hash(&content);
+    let concurrency = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut loaded = pin!(stream::iter(paths)
+        .map(|path| async move {
+            tokio::task::spawn_blocking(move || {
+                let (content, hash) = load_document(&path)?;
+                Ok::<_, Error>((path, content, hash))
+            })
+            .await
+            .map_err(|e| Error::Processing(format!("Failed to load a document: {}", e)))?
+        })
+        .buffered(concurrency));
+    while let Some((path, content, hash)) = loaded.try_next().await? {
         let path_str = path.to_string_lossy().to_string();
+        if let Some(document) =
+            record_document(storage, path_str, content, hash, force, tags).await?
+        {
+            documents_to_process.push(document);
+        }
+    }
+
+    let stale: Vec<DocumentSummary> = documents_under(storage, documents_path)
+        .await?
+        .into_iter()
+        .filter(|document| !seen.contains(&document.file_path))
+        .collect();
+    if prune {
+        for document in &stale {
+            storage.delete_document(document.id).await?;
+            info!(
+                "Deleted document '{}' and its {} chunks, as its file no longer exists.",
+                document.file_path, document.chunk_count
+            );
+        }
+    } else if !stale.is_empty() {
+        for document in &stale {
+            warn!(
+                "The file of document '{}' no longer exists.",
+                document.file_path
+            );
+        }
+        warn!(
+            "{} documents are stale; run `ingest --prune` to delete them.",
+            stale.len()
+        );
+    }
+
+    info!("Document ingestion complete. Found {} new or updated documents to process.", documents_to_process.len());
+    Ok(documents_to_process)
+}
+
+/// Lists the documents ingested from `path`, or from files within it if it
+/// is a directory. Documents ingested from URLs are never included.
+pub async fn documents_under(storage: &dyn Storage, path: &Path) -> Result<Vec<DocumentSummary>> {
+    Ok(storage
+        .list_documents()
+        .await?
+        .into_iter()
+        .filter(|document| Path::new(&document.file_path).starts_with(path))
+        .collect())
+}
 
-        let existing_doc: Option<DocIdAndHash> = sqlx::query_as(
-            "SELECT id, content_hash FROM documents WHERE file_path = ?",
-        )
-        .bind(&path_str)
-        .fetch_optional(pool)
-        .await?;
+/// Records a document's content in the database, unless it is already
+/// stored unchanged and `force` is not set, and attaches `tags` to it.
+///
+/// # Returns
+///
+/// The document to chunk and index, or `None` if it is unchanged.
+pub async fn ingest_content(
+    storage: &dyn Storage,
+    path: String,
+    content: String,
+    force: bool,
+    tags: &[Tag],
+) -> Result<Option<SourceDocument>> {
+    let hash = calculate_hash(&content);
+    record_document(storage, path, content, hash, force, tags).await
+}
 
-        match existing_doc {
-            Some(doc) if doc.content_hash == hash => continue,
-            Some(doc) => {
-                warn!("Document '{}' has changed and will be re-ingested.", path.display());
-                sqlx::query("UPDATE documents SET content_hash = ? WHERE id = ?")
-                    .bind(&hash)
-                    .bind(doc.id)
-                    .execute(pool)
-                    .await?;
-                sqlx::query("DELETE FROM chunks WHERE document_id = ?")
-                    .bind(doc.id)
-                    .execute(pool)
-                    .await?;
-                documents_to_process.push(SourceDocument {
-                    id: doc.id,
-                    path: path.to_path_buf(),
-                    content,
-                });
+/// Records a document whose content hash has been calculated, as
+/// `ingest_content` does.
+async fn record_document(
+    storage: &dyn Storage,
+    path: String,
+    content: String,
+    hash: String,
+    force: bool,
+    tags: &[Tag],
+) -> Result<Option<SourceDocument>> {
+    match storage.find_document(&path).await? {
+        Some(doc) if doc.content_hash == hash && !force => {
+            if !tags.is_empty() {
+                storage.tag_document(doc.id, tags).await?;
             }
-            None => {
-                info!("Ingesting new document: '{}'", path.display());
-                let result = sqlx::query(
-                    "INSERT INTO documents (file_path, content_hash) VALUES (?, ?)",
-                )
-                .bind(&path_str)
-                .bind(&hash)
-                .execute(pool)
-                .await?;
-                documents_to_process.push(SourceDocument {
-                    id: result.last_insert_rowid(),
-                    path: path.to_path_buf(),
-                    content,
-                });
+            Ok(None)
+        }
+        Some(doc) => {
+            if doc.content_hash == hash {
+                info!("Re-ingesting document: '{}'", path);
+            } else {
+                warn!("Document '{}' has changed and will be re-ingested.", path);
             }
+            storage.reset_document(doc.id, &hash).await?;
+            storage.tag_document(doc.id, tags).await?;
+            Ok(Some(SourceDocument {
+                id: doc.id,
+                path,
+                content,
+            }))
+        }
+        None => {
+            info!("Ingesting new document: '{}'", path);
+            let id = storage.insert_document(&path, &hash).await?;
+            storage.tag_document(id, tags).await?;
+            Ok(Some(SourceDocument { id, path, content }))
         }
     }
+}
 
-    info!("Document ingestion complete. Found {} new or updated documents to process.", documents_to_process.len());
-    Ok(documents_to_process)
+/// Reads a supported file and extracts its text.
+///
+/// # Returns
+///
+/// The text and its content hash.
+fn load_document(path: &Path) -> Result<(String, String)> {
+    let content = match path.extension().and_then(|s| s.to_str()) {
+        Some("pdf") => extract_pdf_text(path)?,
+        _ => fs::read_to_string(path)?,
+    };
+    let hash = calculate_hash(&content);
+    Ok((content, hash))
 }
 
 fn extract_pdf_text(path: &Path) -> Result<String> {
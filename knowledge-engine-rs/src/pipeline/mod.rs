@@ -7,3 +7,94 @@
 pub mod chunking;
 pub mod ingestion;
 pub mod indexing;
+pub mod watch;
+pub mod web;
+
+use crate::config::Config;
+use crate::database::Storage;
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use crate::pipeline::ingestion::SourceDocument;
+use crate::vector_index;
+use colored::Colorize;
+use futures::stream::{self, TryStreamExt};
+use std::sync::Arc;
+use tracing::info;
+
+/// Runs the rest of the pipeline over newly ingested documents, chunking and
+/// indexing them, and updates the search index.
+///
+/// Up to `config.ingest_concurrency` documents are processed at a time. Each
+/// document is chunked on a blocking thread, and its chunks are embedded and
+/// then stored in a single transaction, so an interrupted run leaves no
+/// partially indexed documents behind.
+pub async fn process_documents(
+    storage: &dyn Storage,
+    client: &OpenAIClient,
+    config: &Config,
+    source_docs: &[SourceDocument],
+) -> Result<()> {
+    if source_docs.is_empty() {
+        info!("{}", "No new or updated documents to process.".green());
+        update_search_index(storage, config).await?;
+        return Ok(());
+    }
+    let chunker = Arc::new(chunking::Chunker::new(&config.chunking, client.embedding_model())?);
+    info!(
+        "Chunking and indexing {} documents, up to {} at a time...",
+        source_docs.len(),
+        config.ingest_concurrency
+    );
+    stream::iter(source_docs.iter().map(Ok))
+        .try_for_each_concurrent(config.ingest_concurrency, |doc| {
+            let chunker = chunker.clone();
+            let doc = doc.clone();
+            async move {
+                let chunks = tokio::task::spawn_blocking(move || chunker.chunk_document(&doc))
+                    .await
+                    .map_err(|e| Error::Processing(format!("Failed to chunk a document: {}", e)))?;
+                indexing::index_chunks(storage, client, &chunks).await
+            }
+        })
+        .await?;
+    update_search_index(storage, config).await?;
+    info!(
+        "{}",
+        "Ingestion process completed successfully.".green().bold()
+    );
+    Ok(())
+}
+
+/// Brings the structure used for similarity search up to date with the
+/// chunks in the database: the database's own, if it can search inside the
+/// database, or the approximate nearest-neighbor index otherwise.
+pub async fn update_search_index(storage: &dyn Storage, config: &Config) -> Result<()> {
+    if storage.has_native_search() {
+        storage.sync_vectors().await
+    } else {
+        vector_index::rebuild_if_stale(storage, &config.index_path).await
+    }
+}
+
+/// Discards the structure used for similarity search before embeddings are
+/// replaced in place, so that an interrupted run doesn't leave one built from
+/// the old embeddings behind. Queries search every chunk until it is rebuilt.
+pub async fn invalidate_search_index(storage: &dyn Storage, config: &Config) -> Result<()> {
+    if storage.has_native_search() {
+        storage.reset_vectors().await
+    } else {
+        vector_index::remove(&config.index_path)
+    }
+}
+
+/// Rebuilds the structure used for similarity search from scratch, as
+/// `update_search_index` does not notice embeddings that were replaced in
+/// place.
+pub async fn rebuild_search_index(storage: &dyn Storage, config: &Config) -> Result<()> {
+    if storage.has_native_search() {
+        storage.reset_vectors().await?;
+        storage.sync_vectors().await
+    } else {
+        vector_index::rebuild(storage, &config.index_path).await
+    }
+}
@@ -0,0 +1,131 @@
+//! src/pipeline/watch.rs
+//!
+//! Continuous ingestion for `ingest --watch`. This module watches the
+//! documents directory for changes and runs each created or modified file
+//! through the pipeline as it changes, and removes the documents of deleted
+//! files, so that the knowledge base stays in sync with the directory.
+
+use crate::config::Config;
+use crate::database::{Storage, Tag};
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use crate::pipeline;
+use crate::pipeline::ingestion::{self, SourceDocument};
+use colored::Colorize;
+use notify_debouncer_mini::notify::RecursiveMode;
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// How long the directory must be quiet before a batch of changes is
+/// processed, so that a file being written is ingested once, when complete.
+const DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+
+/// Watches `documents_path` until the process is stopped, ingesting files
+/// as they are created or modified and deleting the documents of files that
+/// are removed. The `tags` are attached to every ingested document.
+///
+/// A batch of changes that fails to process is logged and skipped; the
+/// files are picked up again the next time they change.
+pub async fn watch(
+    storage: &dyn Storage,
+    client: &OpenAIClient,
+    config: &Config,
+    documents_path: &Path,
+    tags: &[Tag],
+) -> Result<()> {
+    // Events name absolute paths, which are mapped back onto
+    // `documents_path` so that documents keep the paths they were first
+    // ingested with.
+    let root = documents_path.canonicalize()?;
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(DEBOUNCE_DELAY, move |result: DebounceEventResult| {
+        let _ = sender.send(result);
+    })
+    .map_err(|e| Error::Watch(e.to_string()))?;
+    debouncer
+        .watcher()
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| Error::Watch(format!("Failed to watch '{}': {}", root.display(), e)))?;
+    info!(
+        "{}",
+        format!(
+            "Watching '{}' for changes. Press Ctrl+C to stop.",
+            documents_path.display()
+        )
+        .green()
+        .bold()
+    );
+
+    while let Some(result) = receiver.recv().await {
+        let events = match result {
+            Ok(events) => events,
+            Err(e) => {
+                warn!("Error while watching for changes: {}", e);
+                continue;
+            }
+        };
+        let changed: BTreeSet<PathBuf> = events
+            .into_iter()
+            .filter_map(|event| {
+                let relative = event.path.strip_prefix(&root).ok()?;
+                Some(documents_path.join(relative))
+            })
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        if let Err(e) = process_changes(storage, client, config, &changed, tags).await {
+            error!(error = ?e, "Failed to process changes; they will be retried when the files change again.");
+        }
+    }
+    Ok(())
+}
+
+/// Brings the knowledge base in line with the current state of the changed
+/// paths: existing files are ingested if their content changed, and the
+/// documents of paths that no longer exist are deleted.
+async fn process_changes(
+    storage: &dyn Storage,
+    client: &OpenAIClient,
+    config: &Config,
+    changed: &BTreeSet<PathBuf>,
+    tags: &[Tag],
+) -> Result<()> {
+    let mut source_docs: Vec<SourceDocument> = Vec::new();
+    let mut deleted = 0;
+    for path in changed {
+        if path.is_file() {
+            let found = ingestion::ingest_documents(storage, path, false, false, tags).await?;
+            source_docs.extend(found);
+        } else if !path.exists() {
+            deleted += delete_documents_under(storage, path).await?;
+        }
+    }
+    if source_docs.is_empty() && deleted == 0 {
+        return Ok(());
+    }
+    pipeline::process_documents(storage, client, config, &source_docs).await
+}
+
+/// Deletes the document ingested from `path`, or every document ingested
+/// from within it if it was a directory.
+///
+/// # Returns
+///
+/// The number of documents deleted.
+async fn delete_documents_under(storage: &dyn Storage, path: &Path) -> Result<usize> {
+    let mut deleted = 0;
+    for document in ingestion::documents_under(storage, path).await? {
+        storage.delete_document(document.id).await?;
+        info!(
+            "Deleted document '{}' and its {} chunks, as its file was removed.",
+            document.file_path, document.chunk_count
+        );
+        deleted += 1;
+    }
+    Ok(deleted)
+}
@@ -0,0 +1,326 @@
+//! src/pipeline/web.rs
+//!
+//! An alternative first stage of the data pipeline for web content. This
+//! module crawls pages from a starting URL or the URLs listed in a sitemap,
+//! respecting each site's `robots.txt`, extracts the main text of every page,
+//! and stores it with the page's URL as the document path.
+
+use crate::database::{Storage, Tag};
+use crate::error::{Error, Result};
+use crate::pipeline::ingestion::{ingest_content, SourceDocument};
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+use texting_robots::Robot;
+use tracing::{info, instrument, warn};
+use url::Url;
+
+/// The user agent sent with every request and matched against `robots.txt`.
+const USER_AGENT: &str = "knowledge-engine";
+
+/// The longest `Crawl-delay` from `robots.txt` that is honored, in seconds.
+const MAX_CRAWL_DELAY_SECS: f32 = 60.0;
+
+/// The elements whose content is taken as a page's main content, in order of
+/// preference. The whole body is used if none of them is found.
+const MAIN_CONTENT_SELECTORS: [&str; 4] = ["main", "article", "[role=main]", "body"];
+
+/// Elements that hold navigation, scripts, or other content that isn't part
+/// of the page's text.
+const SKIPPED_ELEMENTS: [&str; 12] = [
+    "script", "style", "noscript", "template", "svg", "iframe", "form", "nav", "header", "footer",
+    "aside", "button",
+];
+
+/// Limits on how far a crawl goes.
+pub struct CrawlOptions {
+    /// How many links away from the starting page to follow. Zero ingests
+    /// only the starting page, or only the pages listed in a sitemap.
+    pub depth: usize,
+    /// The maximum number of pages to fetch.
+    pub max_pages: usize,
+}
+
+/// A fetched page, after its content has been extracted.
+enum Page {
+    /// An HTML or plain-text page, with its text and the links it contains.
+    Document { text: String, links: Vec<Url> },
+    /// A sitemap or sitemap index, with the URLs it lists.
+    Sitemap(Vec<Url>),
+}
+
+/// Crawls pages starting from `start_url` and ingests new or updated ones.
+///
+/// If `start_url` is a sitemap (an `.xml` URL, or a response with an XML
+/// content type), the pages it lists are crawled. Links are only followed
+/// within the starting host. Pages that fail to load, or that `robots.txt`
+/// disallows, are skipped with a warning.
+#[instrument(skip(storage, options, tags))]
+pub async fn ingest_url(
+    storage: &dyn Storage,
+    start_url: &str,
+    options: &CrawlOptions,
+    tags: &[Tag],
+) -> Result<Vec<SourceDocument>> {
+    let start_url = Url::parse(start_url)
+        .map_err(|e| Error::Web(format!("Invalid URL '{}': {}", start_url, e)))?;
+    if !matches!(start_url.scheme(), "http" | "https") {
+        return Err(Error::Web(format!(
+            "Only http and https URLs can be ingested, got '{}'.",
+            start_url
+        )));
+    }
+    info!("Starting web ingestion from '{}'...", start_url);
+
+    let http_client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut robots: HashMap<String, Option<Robot>> = HashMap::new();
+    let mut queue = VecDeque::from([(start_url.clone(), 0)]);
+    let mut seen = HashSet::from([start_url.to_string()]);
+    let mut fetched = 0;
+    let mut documents_to_process = Vec::new();
+
+    while let Some((url, depth)) = queue.pop_front() {
+        if fetched >= options.max_pages {
+            warn!(
+                "Reached the limit of {} pages; {} queued pages were not fetched.",
+                options.max_pages,
+                queue.len() + 1
+            );
+            break;
+        }
+
+        let origin = url.origin().ascii_serialization();
+        if !robots.contains_key(&origin) {
+            let robot = fetch_robots(&http_client, &url).await;
+            robots.insert(origin.clone(), robot);
+        }
+        let robot = robots[&origin].as_ref();
+        if robot.is_some_and(|robot| !robot.allowed(url.as_str())) {
+            warn!("robots.txt disallows '{}', skipping.", url);
+            continue;
+        }
+        if let Some(delay) = robot
+            .and_then(|robot| robot.delay)
+            .filter(|delay| *delay > 0.0)
+        {
+            tokio::time::sleep(Duration::from_secs_f32(delay.min(MAX_CRAWL_DELAY_SECS))).await;
+        }
+
+        fetched += 1;
+        let page = match fetch_page(&http_client, &url).await {
+            Ok(page) => page,
+            Err(e) => {
+                warn!("Failed to fetch '{}', skipping: {}", url, e);
+                continue;
+            }
+        };
+
+        match page {
+            Page::Sitemap(urls) => {
+                info!("Sitemap '{}' lists {} URLs.", url, urls.len());
+                // The pages of a sitemap are crawled as if they were the
+                // starting page.
+                for listed in urls {
+                    if seen.insert(listed.to_string()) {
+                        queue.push_back((listed, depth));
+                    }
+                }
+            }
+            Page::Document { text, links } => {
+                if depth < options.depth {
+                    for link in links {
+                        if link.host_str() == start_url.host_str() && seen.insert(link.to_string())
+                        {
+                            queue.push_back((link, depth + 1));
+                        }
+                    }
+                }
+                if text.trim().is_empty() {
+                    warn!("No text found on '{}', skipping.", url);
+                    continue;
+                }
+                if let Some(document) =
+                    ingest_content(storage, url.to_string(), text, false, tags).await?
+                {
+                    documents_to_process.push(document);
+                }
+            }
+        }
+    }
+
+    info!(
+        "Web ingestion complete. Fetched {} pages and found {} new or updated documents to process.",
+        fetched,
+        documents_to_process.len()
+    );
+    Ok(documents_to_process)
+}
+
+/// Fetches and parses the `robots.txt` of `url`'s site. Sites without a
+/// readable `robots.txt` allow everything.
+async fn fetch_robots(http_client: &reqwest::Client, url: &Url) -> Option<Robot> {
+    let robots_url = url.join("/robots.txt").ok()?;
+    let response = http_client.get(robots_url.clone()).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let body = response.bytes().await.ok()?;
+    match Robot::new(USER_AGENT, &body) {
+        Ok(robot) => Some(robot),
+        Err(e) => {
+            warn!("Could not parse '{}', ignoring it: {}", robots_url, e);
+            None
+        }
+    }
+}
+
+/// Fetches a page and extracts its content according to its type.
+async fn fetch_page(http_client: &reqwest::Client, url: &Url) -> Result<Page> {
+    let response = http_client
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?;
+    // Redirects may lead elsewhere, so links are resolved against the final URL.
+    let final_url = response.url().clone();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let body = response.text().await?;
+
+    if content_type.contains("xml") || final_url.path().ends_with(".xml") {
+        Ok(Page::Sitemap(sitemap_urls(&body)))
+    } else if content_type.contains("html") || content_type.is_empty() {
+        let (text, links) = extract_html(&body, &final_url);
+        Ok(Page::Document { text, links })
+    } else if content_type.starts_with("text/") {
+        Ok(Page::Document {
+            text: body,
+            links: Vec::new(),
+        })
+    } else {
+        Err(Error::Web(format!(
+            "Unsupported content type '{}'.",
+            content_type
+        )))
+    }
+}
+
+/// Extracts the main text of an HTML page and the links it contains,
+/// resolved against `base_url` and without fragments.
+fn extract_html(html: &str, base_url: &Url) -> (String, Vec<Url>) {
+    let document = Html::parse_document(html);
+
+    let main = MAIN_CONTENT_SELECTORS.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).ok()?;
+        document.select(&selector).next()
+    });
+    let mut text = String::new();
+    if let Some(main) = main {
+        collect_text(main, &mut text);
+    }
+
+    let link_selector = Selector::parse("a[href]").expect("valid selector");
+    let links = document
+        .select(&link_selector)
+        .filter_map(|link| base_url.join(link.value().attr("href")?).ok())
+        .filter(|link| matches!(link.scheme(), "http" | "https"))
+        .map(|mut link| {
+            link.set_fragment(None);
+            link
+        })
+        .collect();
+
+    (normalize_whitespace(&text), links)
+}
+
+/// Appends the text inside `element` to `text`, leaving out skipped elements
+/// and starting a new line for each block element.
+fn collect_text(element: ElementRef, text: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(fragment) => text.push_str(fragment),
+            Node::Element(child_element) => {
+                let name = child_element.name();
+                if SKIPPED_ELEMENTS.contains(&name) {
+                    continue;
+                }
+                let is_block = is_block_element(name);
+                if is_block {
+                    text.push('\n');
+                }
+                if let Some(child) = ElementRef::wrap(child) {
+                    collect_text(child, text);
+                }
+                if is_block {
+                    text.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether an element starts a new line in the extracted text.
+fn is_block_element(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "section"
+            | "article"
+            | "main"
+            | "br"
+            | "hr"
+            | "pre"
+            | "blockquote"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "ul"
+            | "ol"
+            | "li"
+            | "dt"
+            | "dd"
+            | "table"
+            | "tr"
+    )
+}
+
+/// Collapses runs of spaces within lines and keeps at most one blank line
+/// between paragraphs.
+fn normalize_whitespace(text: &str) -> String {
+    let mut normalized = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        normalized.push_str(&line);
+        blank_lines = 0;
+    }
+    normalized
+}
+
+/// Reads the `<loc>` entries of a sitemap or sitemap index.
+fn sitemap_urls(xml: &str) -> Vec<Url> {
+    xml.split("<loc>")
+        .skip(1)
+        .filter_map(|entry| entry.split("</loc>").next())
+        .map(|location| location.trim().replace("&amp;", "&"))
+        .filter_map(|location| Url::parse(&location).ok())
+        .collect()
+}
@@ -1,93 +1,925 @@
 //! src/query_engine.rs
 //!
 //! This module contains the logic for querying the knowledge base. It handles
-//! embedding the user's query, finding relevant chunks from the database
-//! using vector similarity, and synthesizing a final answer using a
-//! generative model.
+//! optionally transforming the user's query or expanding it into several
+//! paraphrases, embedding it, finding relevant chunks from the database by
+//! vector similarity and by keyword, and synthesizing a final answer using a
+//! generative model. The answer cites the chunks it draws on inline, and
+//! each citation maps back to the chunk's source document and position.
+//! The best chunks are packed into the answer prompt up to a token budget,
+//! leaving out near-duplicates.
+//! The model also rates its confidence in the answer, and below a threshold
+//! the engine declines to answer and lists the closest chunks instead.
+//! Answers can also be written as JSON, with the passages they quote, for
+//! services that consume them.
 
+use crate::database::{Storage, Tag, FILTER_OVERSAMPLING};
 use crate::error::{Error, Result};
 use crate::openai_client::OpenAIClient;
-use sqlx::{FromRow, SqlitePool};
-use tracing::{info, instrument};
+use crate::reembed;
+use crate::vector_index::{self, VectorIndex};
+use ai_core::{count_tokens, truncate_to_tokens};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
 
-const SIMILARITY_TOP_K: usize = 5; // The number of most relevant chunks to retrieve.
+pub const DEFAULT_CONTEXT_TOKEN_BUDGET: usize = 3000; // The most tokens of chunks in the answer prompt.
+const CONTEXT_CANDIDATES: usize = 20; // The fused candidates considered for the context, best first.
+const DUPLICATE_OVERLAP: f32 = 0.9; // The word overlap at which a chunk duplicates a better one.
 
-/// A struct to hold a chunk retrieved from the database, including its text
-/// and pre-calculated similarity score to the user's query.
+/// The answer given when no relevant chunks are found.
+const NO_CONTEXT_ANSWER: &str =
+    "I could not find any relevant information in the knowledge base to answer your question.";
+const HYBRID_CANDIDATES: usize = 20; // The candidates taken from each of the vector and keyword searches.
+const RRF_K: f32 = 60.0; // Dampens the weight of the top ranks in reciprocal rank fusion.
+const RERANK_CANDIDATES: usize = 20; // The number of retrieved chunks the reranker scores.
+const RERANK_PASSAGE_TOKENS: usize = 400; // Longer chunks are cut short in the reranking prompt.
+const MAX_EXPANSION: usize = 10; // The most paraphrases a question can be expanded into.
+
+/// A struct to hold a chunk retrieved from the database, including its text,
+/// its source, and its fused relevance score to the user's query.
 #[derive(Debug)]
-struct RelevantChunk {
-    text: String,
-    similarity: f32,
+pub struct RelevantChunk {
+    pub id: i64,
+    pub text: String,
+    pub document_path: String,
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+    pub score: f32,
+    /// The chunk's highest cosine similarity to the embedded query over all
+    /// the searches, or `None` if only the keyword search found it.
+    pub similarity: Option<f32>,
+}
+
+/// A source cited in an answer.
+#[derive(Debug, Serialize)]
+pub struct Citation {
+    /// The number the answer cites the source by, as in `[1]`.
+    pub number: usize,
+    pub chunk_id: i64,
+    pub document_path: String,
+    /// The character offsets of the chunk in its document, if recorded.
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+}
+
+/// How well the retrieved context supports an answer, as judged by the
+/// answering model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Evidence {
+    Sufficient,
+    Partial,
+    Insufficient,
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sufficient => "sufficient",
+            Self::Partial => "partial",
+            Self::Insufficient => "insufficient",
+        })
+    }
+}
+
+impl FromStr for Evidence {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sufficient" => Ok(Self::Sufficient),
+            "partial" => Ok(Self::Partial),
+            "insufficient" => Ok(Self::Insufficient),
+            _ => Err(format!(
+                "unknown evidence '{}'; expected sufficient, partial, or insufficient",
+                s
+            )),
+        }
+    }
+}
+
+/// The assessment the answering model writes on the first line of its
+/// reply, before the answer.
+#[derive(Debug)]
+struct Assessment {
+    /// From 0 to 100.
+    confidence: u8,
+    evidence: Option<Evidence>,
+}
+
+impl Assessment {
+    /// Whether the engine should decline to answer at this threshold.
+    fn abstains(&self, min_confidence: u8) -> bool {
+        self.confidence < min_confidence || self.evidence == Some(Evidence::Insufficient)
+    }
+}
+
+/// An answer to a question, with the sources it cites.
+#[derive(Debug, Serialize)]
+pub struct Answer {
+    pub text: String,
+    /// The cited sources, in the order of their numbers.
+    pub citations: Vec<Citation>,
+    /// The answering model's confidence that the context supports its
+    /// answer, from 0 to 100, if it gave one.
+    pub confidence: Option<u8>,
+    /// Whether the context held enough evidence, if the model judged it.
+    pub evidence: Option<Evidence>,
+    /// Whether the engine declined to answer, because no relevant chunks
+    /// were found or the confidence was below the threshold.
+    pub abstained: bool,
+    /// The most relevant chunks, listed in place of an answer when the
+    /// engine declines to answer.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub closest_chunks: Vec<Citation>,
+    /// The chunks the answer was written from, in the order they were
+    /// numbered in the prompt.
+    #[serde(skip)]
+    pub context: Vec<RelevantChunk>,
+}
+
+/// A source cited by a structured answer, with the passage it quotes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuotedCitation {
+    /// The document the cited chunk comes from.
+    pub doc: String,
+    pub chunk_id: i64,
+    /// A passage of the chunk, verbatim, that supports the answer.
+    pub quote: String,
 }
 
-/// Represents a record from the `chunks` table.
-#[derive(FromRow)]
-struct ChunkRecord {
-    chunk_text: String,
-    embedding: Vec<u8>,
+/// An answer in the machine-readable form of `query --format json`. Every
+/// citation names a retrieved chunk and quotes text found in it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StructuredAnswer {
+    pub answer: String,
+    pub citations: Vec<QuotedCitation>,
+    /// The answering model's confidence, from 0 to 100, if it gave one.
+    pub confidence: Option<u8>,
+    pub evidence: Option<Evidence>,
+    /// Whether the engine declined to answer.
+    pub abstained: bool,
+}
+
+/// The JSON the answering model writes for a structured answer.
+#[derive(Deserialize, Debug)]
+struct StructuredReply {
+    answer: String,
+    #[serde(default)]
+    citations: Vec<ReplyCitation>,
+    #[serde(default)]
+    confidence: Option<f32>,
+    #[serde(default)]
+    evidence: Option<String>,
+}
+
+/// A citation as the answering model writes it, by source number.
+#[derive(Deserialize, Debug)]
+struct ReplyCitation {
+    source: usize,
+    quote: String,
+}
+
+/// How a question is transformed before the knowledge base is searched, to
+/// improve retrieval for terse or vague questions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryTransform {
+    /// Rewrite the question into an explicit, self-contained search query,
+    /// which is used for both the vector and the keyword search.
+    Rewrite,
+    /// Write a hypothetical answer to the question and search for chunks
+    /// similar to it (HyDE), since answers resemble the passages that hold
+    /// them more than questions do. The keyword search uses the question.
+    Hyde,
+}
+
+impl FromStr for QueryTransform {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rewrite" => Ok(Self::Rewrite),
+            "hyde" => Ok(Self::Hyde),
+            _ => Err(format!(
+                "unknown query transform '{}'; expected rewrite or hyde",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for QueryTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rewrite => "rewrite",
+            Self::Hyde => "hyde",
+        })
+    }
+}
+
+/// The texts a question is searched for with, after its transformation.
+struct SearchQuery {
+    /// The text embedded for the vector search.
+    embedded: String,
+    /// The text whose words are looked up in the keyword index.
+    keywords: String,
+}
+
+/// How the engine finds the chunks most similar to a question.
+#[derive(Clone)]
+pub enum Retrieval {
+    /// Score every chunk of the database.
+    Exact,
+    /// Search the approximate nearest-neighbor index.
+    Index(Arc<VectorIndex>),
+    /// Search inside the database, with `sqlite-vec` or pgvector.
+    Native,
+}
+
+impl Retrieval {
+    /// Picks the fastest retrieval method available: search inside the
+    /// database if it supports it, or the index at `index_path` if it is up
+    /// to date, or the exact full scan otherwise. Warns if some chunks were
+    /// embedded with another model than `embedding_model`, as their scores
+    /// can't be trusted.
+    pub async fn select(
+        storage: &dyn Storage,
+        index_path: &Path,
+        embedding_model: &str,
+    ) -> Result<Self> {
+        let outdated = reembed::outdated_chunk_count(storage, embedding_model).await?;
+        if outdated > 0 {
+            warn!(
+                "{} chunks were embedded with another model than '{}', so their similarity scores are meaningless; run `reembed` to fix them.",
+                outdated, embedding_model
+            );
+        }
+        if storage.has_native_search() {
+            storage.sync_vectors().await?;
+            return Ok(Retrieval::Native);
+        }
+        let index = vector_index::load_current(storage, index_path).await?;
+        Ok(match index {
+            Some(index) => Retrieval::Index(Arc::new(index)),
+            None => Retrieval::Exact,
+        })
+    }
 }
 
 /// The main engine for processing user queries against the knowledge base.
 pub struct QueryEngine {
-    pool: SqlitePool,
+    storage: Arc<dyn Storage>,
     client: OpenAIClient,
+    retrieval: Retrieval,
+    /// The tags a chunk's document must have to be retrieved.
+    filters: Vec<Tag>,
+    /// The model that reranks the retrieved chunks, if reranking is enabled.
+    rerank_model: Option<String>,
+    /// How questions are transformed before searching, and the model that
+    /// transforms them, if enabled.
+    query_transform: Option<(QueryTransform, String)>,
+    /// The number of paraphrases each question is expanded into, and the
+    /// model that writes them, if expansion is enabled.
+    expansion: Option<(usize, String)>,
+    /// The confidence below which the engine declines to answer, if set.
+    min_confidence: Option<u8>,
+    /// The most tokens of chunks packed into the answer prompt.
+    context_token_budget: usize,
 }
 
 impl QueryEngine {
-    /// Creates a new instance of the `QueryEngine`.
-    pub fn new(pool: SqlitePool, client: OpenAIClient) -> Self {
-        Self { pool, client }
+    /// Creates a new instance of the `QueryEngine`, which only retrieves
+    /// chunks from documents with all the tags in `filters`.
+    pub fn new(
+        storage: Arc<dyn Storage>,
+        client: OpenAIClient,
+        retrieval: Retrieval,
+        filters: Vec<Tag>,
+    ) -> Self {
+        Self {
+            storage,
+            client,
+            retrieval,
+            filters,
+            rerank_model: None,
+            query_transform: None,
+            expansion: None,
+            min_confidence: None,
+            context_token_budget: DEFAULT_CONTEXT_TOKEN_BUDGET,
+        }
+    }
+
+    /// Enables reranking: more chunks are retrieved, and `model` scores how
+    /// relevant each one is to the question before the best are kept.
+    pub fn with_reranking(mut self, model: String) -> Self {
+        self.rerank_model = Some(model);
+        self
+    }
+
+    /// Enables a query transformation: before searching, `model` rewrites
+    /// each question or writes a hypothetical answer to it. The answer is
+    /// still written for the original question.
+    pub fn with_query_transform(mut self, transform: QueryTransform, model: String) -> Self {
+        self.query_transform = Some((transform, model));
+        self
+    }
+
+    /// Enables multi-query expansion: `model` writes `count` paraphrases of
+    /// each question, up to `MAX_EXPANSION`, and chunks are searched for with
+    /// the question and every paraphrase. The rankings of all the searches
+    /// are fused, so chunks found by several phrasings come first. A count
+    /// of zero disables expansion.
+    pub fn with_expansion(mut self, count: usize, model: String) -> Self {
+        self.expansion = (count > 0).then(|| (count.min(MAX_EXPANSION), model));
+        self
+    }
+
+    /// Enables abstention: if the answering model's confidence is below
+    /// `min_confidence`, from 0 to 100, or it judges the evidence
+    /// insufficient, the engine declines to answer and lists the closest
+    /// chunks instead.
+    pub fn with_abstention(mut self, min_confidence: u8) -> Self {
+        self.min_confidence = Some(min_confidence);
+        self
+    }
+
+    /// Sets the most tokens of retrieved chunks packed into the answer
+    /// prompt. The best chunk is always included, even if it is larger.
+    pub fn with_context_budget(mut self, tokens: usize) -> Self {
+        self.context_token_budget = tokens;
+        self
     }
 
     /// Answers a user's question by querying the knowledge base.
-    #[instrument(skip(self, question))]
-    pub async fn answer_question(&self, question: &str) -> Result<String> {
+    pub async fn answer_question(&self, question: &str) -> Result<Answer> {
+        self.answer(question, None).await
+    }
+
+    /// Answers a user's question like `answer_question`, calling `on_token`
+    /// with each piece of the answer as the model writes it.
+    ///
+    /// If no relevant chunks are found, or the engine declines to answer, no
+    /// answer is written and `on_token` is never called; the returned
+    /// `Answer` holds the explanation.
+    pub async fn answer_question_streamed(
+        &self,
+        question: &str,
+        on_token: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<Answer> {
+        self.answer(question, Some(on_token)).await
+    }
+
+    /// Answers a question, streaming the answer to `on_token` if given.
+    #[instrument(skip(self, question, on_token))]
+    async fn answer(
+        &self,
+        question: &str,
+        on_token: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<Answer> {
         info!("Answering question: '{}'", question);
 
-        let question_embedding = self.client.get_embedding(question).await?;
-        let relevant_chunks = self.find_relevant_chunks(&question_embedding).await?;
+        let relevant_chunks = self.find_relevant_chunks(question).await?;
+        self.answer_from_chunks(question, relevant_chunks, on_token)
+            .await
+    }
 
+    /// Writes the answer to a question from chunks already retrieved with
+    /// `find_relevant_chunks`, streaming it to `on_token` if given. This lets
+    /// callers inspect the context before the answer is written.
+    pub async fn answer_from_chunks(
+        &self,
+        question: &str,
+        relevant_chunks: Vec<RelevantChunk>,
+        on_token: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<Answer> {
         if relevant_chunks.is_empty() {
-            return Ok("I could not find any relevant information in the knowledge base to answer your question.".to_string());
+            return Ok(Answer {
+                text: NO_CONTEXT_ANSWER.to_string(),
+                citations: Vec::new(),
+                confidence: None,
+                evidence: None,
+                abstained: true,
+                closest_chunks: Vec::new(),
+                context: Vec::new(),
+            });
         }
 
-        let system_prompt = "You are a helpful AI assistant. Answer the user's question based *only* on the context provided. If the context does not contain the answer, state that you cannot answer from the given information.";
+        let system_prompt = "You are a helpful AI assistant. Answer the user's question based *only* on the context provided. If the context does not contain the answer, state that you cannot answer from the given information. The context consists of numbered sources. Cite the sources each statement is based on inline with their numbers in square brackets, such as [1] or [2][3], and do not cite sources you did not use. Begin your reply with a line of the form `CONFIDENCE: <0-100>; EVIDENCE: <sufficient|partial|insufficient>`, rating how confident you are that the context supports your answer and whether it holds enough evidence to answer the question, and write the answer on the following lines.";
         let user_prompt = self.build_synthesis_prompt(question, &relevant_chunks);
 
-        self.client.get_completion(system_prompt, &user_prompt).await
+        let reply = match on_token {
+            Some(on_token) => {
+                let mut filter = AssessmentFilter::new(on_token, self.min_confidence);
+                let mut tokens = self
+                    .client
+                    .stream_completion(system_prompt, &user_prompt)
+                    .await?;
+                let mut reply = String::new();
+                while let Some(token) = tokens.try_next().await? {
+                    filter.push(&token);
+                    reply.push_str(&token);
+                }
+                filter.finish();
+                reply
+            }
+            None => {
+                self.client
+                    .get_completion(system_prompt, &user_prompt)
+                    .await?
+            }
+        };
+        let (assessment, text) = split_assessment(&reply);
+        match &assessment {
+            Some(assessment) => info!(
+                "The model's confidence in the answer is {}% (evidence: {}).",
+                assessment.confidence,
+                assessment
+                    .evidence
+                    .map_or("not given".to_string(), |evidence| evidence.to_string())
+            ),
+            None => warn!("The model did not rate its confidence in the answer."),
+        }
+
+        let confidence = assessment.as_ref().map(|assessment| assessment.confidence);
+        let evidence = assessment
+            .as_ref()
+            .and_then(|assessment| assessment.evidence);
+        if let (Some(min_confidence), Some(assessment)) = (self.min_confidence, &assessment) {
+            if assessment.abstains(min_confidence) {
+                info!(
+                    "Declining to answer below the confidence threshold of {}%.",
+                    min_confidence
+                );
+                return Ok(Answer {
+                    text: format!(
+                        "{} The closest chunks found are listed below.",
+                        insufficient_answer(assessment.confidence)
+                    ),
+                    citations: Vec::new(),
+                    confidence,
+                    evidence,
+                    abstained: true,
+                    closest_chunks: number_chunks(&relevant_chunks),
+                    context: relevant_chunks,
+                });
+            }
+        }
+
+        let citations = collect_citations(&text, &relevant_chunks);
+        Ok(Answer {
+            text,
+            citations,
+            confidence,
+            evidence,
+            abstained: false,
+            closest_chunks: Vec::new(),
+            context: relevant_chunks,
+        })
     }
 
-    /// Finds the most relevant text chunks from the database using vector similarity.
-    async fn find_relevant_chunks(&self, question_embedding: &[f32]) -> Result<Vec<RelevantChunk>> {
-        info!("Searching for relevant chunks in the database...");
-        let all_chunks: Vec<ChunkRecord> =
-            sqlx::query_as("SELECT chunk_text, embedding FROM chunks")
-                .fetch_all(&self.pool)
-                .await?;
+    /// Answers a question like `answer_question`, but in structured form.
+    /// The model replies with JSON, which is read with serde and checked
+    /// against the retrieved chunks: citations of sources that weren't
+    /// given, or whose quotes don't appear in the cited chunk, are dropped.
+    #[instrument(skip(self, question))]
+    pub async fn answer_question_structured(&self, question: &str) -> Result<StructuredAnswer> {
+        info!("Answering question: '{}'", question);
 
-        let mut scored_chunks = Vec::new();
+        let relevant_chunks = self.find_relevant_chunks(question).await?;
+        if relevant_chunks.is_empty() {
+            return Ok(StructuredAnswer {
+                answer: NO_CONTEXT_ANSWER.to_string(),
+                citations: Vec::new(),
+                confidence: None,
+                evidence: None,
+                abstained: true,
+            });
+        }
+
+        let system_prompt = r#"You are a helpful AI assistant. Answer the user's question based *only* on the context provided. If the context does not contain the answer, state that you cannot answer from the given information. The context consists of numbered sources. Reply with a single JSON object and nothing else, of the form {"answer": "<the answer>", "citations": [{"source": <source number>, "quote": "<a short passage copied exactly from that source that supports the answer>"}], "confidence": <0-100>, "evidence": "<sufficient|partial|insufficient>"}. Cite every source the answer is based on, and no others. The confidence rates how confident you are that the context supports your answer, and the evidence whether it holds enough to answer the question."#;
+        let user_prompt = self.build_synthesis_prompt(question, &relevant_chunks);
+        let reply = self
+            .client
+            .get_completion(system_prompt, &user_prompt)
+            .await?;
+        let reply: StructuredReply =
+            serde_json::from_str(strip_code_fence(&reply)).map_err(|e| {
+                Error::OpenAI(format!(
+                    "The model's reply is not a valid structured answer: {}",
+                    e
+                ))
+            })?;
+
+        let assessment = reply
+            .confidence
+            .filter(|confidence| (0.0..=100.0).contains(confidence))
+            .map(|confidence| Assessment {
+                confidence: confidence.round() as u8,
+                evidence: reply.evidence.as_deref().and_then(|e| e.parse().ok()),
+            });
+        let confidence = assessment.as_ref().map(|assessment| assessment.confidence);
+        let evidence = assessment
+            .as_ref()
+            .and_then(|assessment| assessment.evidence);
+        if let (Some(min_confidence), Some(assessment)) = (self.min_confidence, &assessment) {
+            if assessment.abstains(min_confidence) {
+                info!(
+                    "Declining to answer below the confidence threshold of {}%.",
+                    min_confidence
+                );
+                return Ok(StructuredAnswer {
+                    answer: insufficient_answer(assessment.confidence),
+                    citations: Vec::new(),
+                    confidence,
+                    evidence,
+                    abstained: true,
+                });
+            }
+        }
 
-        for chunk_record in all_chunks {
-            let chunk_embedding = deserialize_embedding(&chunk_record.embedding)?;
-            let similarity = cosine_similarity(question_embedding, &chunk_embedding);
-            scored_chunks.push(RelevantChunk {
-                text: chunk_record.chunk_text,
-                similarity,
+        let citations = reply
+            .citations
+            .into_iter()
+            .filter_map(|citation| {
+                let chunk = citation
+                    .source
+                    .checked_sub(1)
+                    .and_then(|index| relevant_chunks.get(index));
+                match chunk {
+                    Some(chunk) if contains_quote(&chunk.text, &citation.quote) => {
+                        Some(QuotedCitation {
+                            doc: chunk.document_path.clone(),
+                            chunk_id: chunk.id,
+                            quote: citation.quote,
+                        })
+                    }
+                    Some(_) => {
+                        warn!(
+                            "Dropping a quote not found in source [{}].",
+                            citation.source
+                        );
+                        None
+                    }
+                    None => {
+                        warn!(
+                            "Dropping a citation of the unknown source [{}].",
+                            citation.source
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+        Ok(StructuredAnswer {
+            answer: reply.answer.trim().to_string(),
+            citations,
+            confidence,
+            evidence,
+            abstained: false,
+        })
+    }
+
+    /// Applies the query transformation, if any, to get the texts to search
+    /// for. If the model replies with nothing, the question is used as is.
+    async fn transform_question(&self, question: &str) -> Result<SearchQuery> {
+        let Some((transform, model)) = &self.query_transform else {
+            return Ok(SearchQuery {
+                embedded: question.to_string(),
+                keywords: question.to_string(),
+            });
+        };
+        info!(
+            "Transforming the question with '{}' ({})...",
+            model, transform
+        );
+
+        let system_prompt = match transform {
+            QueryTransform::Rewrite => "You rewrite questions into search queries for a document retrieval system. Make the query self-contained and explicit: expand abbreviations and vague references, and add the specific terms a passage answering the question would likely contain, without changing what is asked. Reply with the search query only.",
+            QueryTransform::Hyde => "Write a short passage, as it might appear in documentation or notes, that answers the question. If you don't know the answer, write a plausible one; the passage is only used to find similar passages. Reply with the passage only.",
+        };
+        let reply = self
+            .client
+            .get_completion_with_model(model, system_prompt, question)
+            .await?;
+        let transformed = reply.trim();
+        if transformed.is_empty() {
+            warn!("The query transformation returned nothing; searching with the question.");
+            return Ok(SearchQuery {
+                embedded: question.to_string(),
+                keywords: question.to_string(),
             });
         }
+        debug!("Transformed the question into: '{}'", transformed);
+
+        Ok(match transform {
+            QueryTransform::Rewrite => SearchQuery {
+                embedded: transformed.to_string(),
+                keywords: transformed.to_string(),
+            },
+            QueryTransform::Hyde => SearchQuery {
+                embedded: transformed.to_string(),
+                keywords: question.to_string(),
+            },
+        })
+    }
+
+    /// Writes paraphrases of the question, if expansion is enabled.
+    ///
+    /// # Returns
+    ///
+    /// The question followed by its paraphrases, without duplicates. If the
+    /// model's reply can't be read, only the question is returned.
+    pub async fn expand_question(&self, question: &str) -> Result<Vec<String>> {
+        let mut questions = vec![question.to_string()];
+        let Some((count, model)) = &self.expansion else {
+            return Ok(questions);
+        };
+        info!(
+            "Expanding the question into {} paraphrases with '{}'...",
+            count, model
+        );
+
+        let system_prompt = format!("You paraphrase questions for a document retrieval system. Write {} different paraphrases of the question, each phrased in its own way, with different words or from a different angle, without changing what is asked. Reply with one paraphrase per line, without numbering, and nothing else.", count);
+        let reply = self
+            .client
+            .get_completion_with_model(model, &system_prompt, question)
+            .await?;
+        for line in reply.lines() {
+            let paraphrase = line
+                .trim()
+                .trim_start_matches(|c: char| {
+                    c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*')
+                })
+                .trim();
+            if questions.len() > *count {
+                break;
+            }
+            if !paraphrase.is_empty() && !questions.iter().any(|q| q == paraphrase) {
+                debug!("Expanded the question into: '{}'", paraphrase);
+                questions.push(paraphrase.to_string());
+            }
+        }
+        if questions.len() == 1 {
+            warn!("Could not read any paraphrases; searching with the question only.");
+        }
+        Ok(questions)
+    }
+
+    /// Finds the most relevant text chunks from the database. The question,
+    /// and each paraphrase of it if expansion is enabled, is transformed if
+    /// a transformation is enabled and searched for, and the rankings of all
+    /// the searches are combined with reciprocal rank fusion. With
+    /// reranking, a larger set of fused candidates is narrowed down by the
+    /// reranking model, which judges them against the original `question`.
+    /// The best chunks are then packed into the context budget.
+    pub async fn find_relevant_chunks(&self, question: &str) -> Result<Vec<RelevantChunk>> {
+        let mut rankings = Vec::new();
+        let mut similarities: HashMap<i64, f32> = HashMap::new();
+        for sub_question in self.expand_question(question).await? {
+            let search_query = self.transform_question(&sub_question).await?;
+            let embedding = self.client.get_embedding(&search_query.embedded).await?;
+            let (vector_matches, keyword_matches) =
+                self.search(&search_query.keywords, &embedding).await?;
+            for (chunk_id, similarity) in &vector_matches {
+                let best = similarities.entry(*chunk_id).or_insert(*similarity);
+                *best = best.max(*similarity);
+            }
+            rankings.push(vector_matches);
+            rankings.push(keyword_matches);
+        }
+
+        let candidates = match &self.rerank_model {
+            Some(rerank_model) => {
+                let matches = reciprocal_rank_fusion(&rankings, RERANK_CANDIDATES);
+                let candidates = self.load_relevant_chunks(matches, &similarities).await?;
+                self.rerank(rerank_model, question, candidates).await?
+            }
+            None => {
+                let matches = reciprocal_rank_fusion(&rankings, CONTEXT_CANDIDATES);
+                self.load_relevant_chunks(matches, &similarities).await?
+            }
+        };
+        Ok(self.pack_context(candidates))
+    }
+
+    /// Keeps the best of the `candidates`, in order, while their sources fit
+    /// in the context budget, leaving out chunks that nearly repeat a better
+    /// one, such as the same passage ingested from two files.
+    fn pack_context(&self, candidates: Vec<RelevantChunk>) -> Vec<RelevantChunk> {
+        let model = self.client.generative_model();
+        let mut packed: Vec<RelevantChunk> = Vec::new();
+        let mut packed_words: Vec<HashSet<String>> = Vec::new();
+        let mut used_tokens = 0;
+        for chunk in candidates {
+            let words = word_set(&chunk.text);
+            if packed_words
+                .iter()
+                .any(|other| word_overlap(&words, other) >= DUPLICATE_OVERLAP)
+            {
+                debug!("Leaving out chunk {} as a near-duplicate.", chunk.id);
+                continue;
+            }
+            let tokens = count_tokens(model, &format_source(packed.len() + 1, &chunk));
+            if !packed.is_empty() && used_tokens + tokens > self.context_token_budget {
+                break;
+            }
+            used_tokens += tokens;
+            packed.push(chunk);
+            packed_words.push(words);
+        }
+        info!(
+            "Packed {} chunks ({} tokens) into the context budget of {} tokens.",
+            packed.len(),
+            used_tokens,
+            self.context_token_budget
+        );
+        packed
+    }
+
+    /// Searches for candidate chunks both by vector similarity to
+    /// `embedding`, with the configured retrieval method, and by the words of
+    /// `keywords`.
+    ///
+    /// # Returns
+    ///
+    /// The rankings of the vector and the keyword search, as pairs of chunk
+    /// ID and score. The vector search's scores are cosine similarities.
+    async fn search(
+        &self,
+        keywords: &str,
+        embedding: &[f32],
+    ) -> Result<(Vec<(i64, f32)>, Vec<(i64, f32)>)> {
+        let vector_matches = match &self.retrieval {
+            Retrieval::Exact => self.search_exact(embedding).await?,
+            Retrieval::Index(index) => self.search_index(index, embedding).await?,
+            Retrieval::Native => self.search_native(embedding).await?,
+        };
+
+        info!("Searching for relevant chunks by keyword...");
+        let keyword_matches = self
+            .storage
+            .search_keywords(keywords, HYBRID_CANDIDATES, &self.filters)
+            .await?;
+        debug!(
+            "Found {} chunks by vector similarity and {} by keyword.",
+            vector_matches.len(),
+            keyword_matches.len()
+        );
+        Ok((vector_matches, keyword_matches))
+    }
+
+    /// Orders chunks by how relevant `model` judges them to the question, in
+    /// a single request. If the model's reply can't be read, the retrieval
+    /// order is kept.
+    async fn rerank(
+        &self,
+        model: &str,
+        question: &str,
+        chunks: Vec<RelevantChunk>,
+    ) -> Result<Vec<RelevantChunk>> {
+        if chunks.len() <= 1 {
+            return Ok(chunks);
+        }
+        info!("Reranking {} chunks with '{}'...", chunks.len(), model);
+
+        let system_prompt = "You judge how relevant passages are to a question. Score each passage from 0 (unrelated) to 10 (directly answers the question). Reply with one line per passage in the form `<passage number>: <score>`, and nothing else.";
+        let passages = chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let text = truncate_to_tokens(model, &chunk.text, RERANK_PASSAGE_TOKENS);
+                format!("[{}]\n{}", index + 1, text)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let user_prompt = format!("QUESTION: {}\n\nPASSAGES:\n{}", question, passages);
+
+        let reply = self
+            .client
+            .get_completion_with_model(model, system_prompt, &user_prompt)
+            .await?;
+        let scores = parse_rerank_scores(&reply, chunks.len());
+        if scores.iter().all(Option::is_none) {
+            warn!("Could not read the reranking scores; keeping the retrieval order.");
+            return Ok(chunks);
+        }
+
+        // Chunks the model didn't score are ranked last; ties keep the
+        // retrieval order, since the sort is stable.
+        let mut scored: Vec<(f32, RelevantChunk)> = scores
+            .into_iter()
+            .map(|score| score.unwrap_or(f32::MIN))
+            .zip(chunks)
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        for (score, chunk) in &scored {
+            debug!("Reranked chunk {} with relevance {}.", chunk.id, score);
+        }
+        Ok(scored.into_iter().map(|(_, chunk)| chunk).collect())
+    }
+
+    /// Finds the chunks most similar to the question by scoring every chunk.
+    async fn search_exact(&self, question_embedding: &[f32]) -> Result<Vec<(i64, f32)>> {
+        info!("Searching for relevant chunks in the database...");
+        let all_chunks = self.storage.load_embeddings(&self.filters).await?;
+
+        let mut scored_chunks = Vec::new();
 
-        scored_chunks.sort_unstable_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap());
-        scored_chunks.truncate(SIMILARITY_TOP_K);
+        for chunk in all_chunks {
+            let similarity = cosine_similarity(question_embedding, &chunk.embedding);
+            scored_chunks.push((chunk.id, similarity));
+        }
 
-        info!("Found {} relevant chunks.", scored_chunks.len());
+        scored_chunks.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        scored_chunks.truncate(HYBRID_CANDIDATES);
         Ok(scored_chunks)
     }
 
+    /// Finds the chunks most similar to the question with the approximate
+    /// nearest-neighbor index.
+    async fn search_index(
+        &self,
+        index: &VectorIndex,
+        question_embedding: &[f32],
+    ) -> Result<Vec<(i64, f32)>> {
+        info!("Searching for relevant chunks in the vector index...");
+        if self.filters.is_empty() {
+            return Ok(index.search(question_embedding, HYBRID_CANDIDATES));
+        }
+
+        // The index holds every chunk, so more candidates are fetched and
+        // the ones outside the filtered documents are dropped.
+        let candidates = index.search(question_embedding, HYBRID_CANDIDATES * FILTER_OVERSAMPLING);
+        let candidate_ids: Vec<i64> = candidates.iter().map(|(chunk_id, _)| *chunk_id).collect();
+        let allowed = self
+            .storage
+            .filter_chunks(&candidate_ids, &self.filters)
+            .await?;
+        Ok(candidates
+            .into_iter()
+            .filter(|(chunk_id, _)| allowed.contains(chunk_id))
+            .take(HYBRID_CANDIDATES)
+            .collect())
+    }
+
+    /// Finds the chunks most similar to the question with a similarity
+    /// search inside the database.
+    async fn search_native(&self, question_embedding: &[f32]) -> Result<Vec<(i64, f32)>> {
+        info!("Searching for relevant chunks inside the database...");
+        self.storage
+            .search_similar(question_embedding, HYBRID_CANDIDATES, &self.filters)
+            .await
+    }
+
+    /// Looks up the text and source of the chunks found by a search, given
+    /// as pairs of chunk ID and score, keeping their order. `similarities`
+    /// holds the vector similarity of the chunks the vector search found.
+    async fn load_relevant_chunks(
+        &self,
+        matches: Vec<(i64, f32)>,
+        similarities: &HashMap<i64, f32>,
+    ) -> Result<Vec<RelevantChunk>> {
+        let chunk_ids: Vec<i64> = matches.iter().map(|(chunk_id, _)| *chunk_id).collect();
+        let mut sources = self.storage.chunk_sources(&chunk_ids).await?;
+
+        let relevant_chunks: Vec<RelevantChunk> = matches
+            .into_iter()
+            .filter_map(|(chunk_id, score)| {
+                let source = sources.remove(&chunk_id)?;
+                Some(RelevantChunk {
+                    id: chunk_id,
+                    text: source.chunk_text,
+                    document_path: source.document_path,
+                    start_offset: source.start_offset,
+                    end_offset: source.end_offset,
+                    score,
+                    similarity: similarities.get(&chunk_id).copied(),
+                })
+            })
+            .collect();
+
+        info!("Found {} relevant chunks.", relevant_chunks.len());
+        for chunk in &relevant_chunks {
+            debug!(
+                "Retrieved chunk {} from '{}' with score {:.4}.",
+                chunk.id, chunk.document_path, chunk.score
+            );
+        }
+        Ok(relevant_chunks)
+    }
+
     /// Builds the final prompt for the generative model to synthesize an answer.
     fn build_synthesis_prompt(&self, question: &str, chunks: &[RelevantChunk]) -> String {
         let context = chunks
             .iter()
-            .map(|c| c.text.as_str())
+            .enumerate()
+            .map(|(index, chunk)| format_source(index + 1, chunk))
             .collect::<Vec<_>>()
             .join("\n---\n");
 
@@ -104,6 +936,264 @@ impl QueryEngine {
     }
 }
 
+/// Collects the sources an answer cites, numbered as in the synthesis prompt.
+fn collect_citations(answer: &str, chunks: &[RelevantChunk]) -> Vec<Citation> {
+    let cited = cited_numbers(answer);
+    number_chunks(chunks)
+        .into_iter()
+        .filter(|citation| cited.contains(&citation.number))
+        .collect()
+}
+
+/// Lists chunks as sources, numbered as in the synthesis prompt.
+fn number_chunks(chunks: &[RelevantChunk]) -> Vec<Citation> {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| Citation {
+            number: index + 1,
+            chunk_id: chunk.id,
+            document_path: chunk.document_path.clone(),
+            start_offset: chunk.start_offset,
+            end_offset: chunk.end_offset,
+        })
+        .collect()
+}
+
+/// Passes a streamed reply on to `on_token` without its first line, which
+/// holds the model's assessment. The rest is held back until the assessment
+/// is read, and dropped if the engine is going to decline to answer. If the
+/// first line is not an assessment, the reply is passed on in full.
+struct AssessmentFilter<'a> {
+    on_token: &'a mut (dyn FnMut(&str) + Send),
+    min_confidence: Option<u8>,
+    /// The start of the reply, while its first line is incomplete.
+    first_line: Option<String>,
+    /// Whether the rest of the reply is dropped.
+    dropped: bool,
+    /// Whether any text has been passed on, until which leading whitespace
+    /// is skipped.
+    started: bool,
+}
+
+impl<'a> AssessmentFilter<'a> {
+    fn new(on_token: &'a mut (dyn FnMut(&str) + Send), min_confidence: Option<u8>) -> Self {
+        Self {
+            on_token,
+            min_confidence,
+            first_line: Some(String::new()),
+            dropped: false,
+            started: false,
+        }
+    }
+
+    fn push(&mut self, token: &str) {
+        let Some(buffer) = &mut self.first_line else {
+            self.emit(token);
+            return;
+        };
+        buffer.push_str(token);
+        let Some(newline) = buffer.find('\n') else {
+            return;
+        };
+        let buffer = self.first_line.take().unwrap_or_default();
+        let (first_line, rest) = buffer.split_at(newline);
+        match parse_assessment(first_line) {
+            Some(assessment) => {
+                self.dropped = self
+                    .min_confidence
+                    .is_some_and(|min_confidence| assessment.abstains(min_confidence));
+                self.emit(rest);
+            }
+            None => self.emit(&buffer),
+        }
+    }
+
+    /// Passes on the start of a reply that ended within its first line.
+    fn finish(&mut self) {
+        if let Some(buffer) = self.first_line.take() {
+            if parse_assessment(&buffer).is_none() {
+                self.emit(&buffer);
+            }
+        }
+    }
+
+    fn emit(&mut self, text: &str) {
+        if self.dropped {
+            return;
+        }
+        let text = if self.started {
+            text
+        } else {
+            text.trim_start()
+        };
+        if !text.is_empty() {
+            self.started = true;
+            (self.on_token)(text);
+        }
+    }
+}
+
+/// The answer given when the engine declines to answer at `confidence`.
+fn insufficient_answer(confidence: u8) -> String {
+    format!("There is insufficient information in the knowledge base to answer this question confidently (confidence {}%).", confidence)
+}
+
+/// Removes the Markdown code fence that models sometimes wrap JSON in.
+fn strip_code_fence(reply: &str) -> &str {
+    let reply = reply.trim();
+    let Some(fenced) = reply.strip_prefix("```") else {
+        return reply;
+    };
+    let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().trim_end_matches("```").trim()
+}
+
+/// Whether `quote` appears in `text`, ignoring case and differences in
+/// whitespace. Empty quotes never do.
+fn contains_quote(text: &str, quote: &str) -> bool {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    let quote = normalize(quote);
+    !quote.is_empty() && normalize(text).contains(&quote)
+}
+
+/// Splits a reply into the assessment on its first line, if there is one,
+/// and the answer after it.
+fn split_assessment(reply: &str) -> (Option<Assessment>, String) {
+    let (first_line, rest) = reply.split_once('\n').unwrap_or((reply, ""));
+    match parse_assessment(first_line) {
+        Some(assessment) => (Some(assessment), rest.trim().to_string()),
+        None => (None, reply.trim().to_string()),
+    }
+}
+
+/// Reads an assessment line such as `CONFIDENCE: 85; EVIDENCE: sufficient`.
+/// The confidence is required; the evidence judgment is optional.
+fn parse_assessment(line: &str) -> Option<Assessment> {
+    let mut confidence = None;
+    let mut evidence = None;
+    for part in line.split([';', ',', '|']) {
+        let Some((key, value)) = part.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches(['*', '`']).to_ascii_lowercase();
+        let value = value
+            .trim()
+            .trim_matches(['*', '`', '.'])
+            .trim_end_matches('%')
+            .to_ascii_lowercase();
+        match key.as_str() {
+            "confidence" => confidence = value.parse::<f32>().ok(),
+            "evidence" => evidence = value.parse().ok(),
+            _ => {}
+        }
+    }
+    let confidence = confidence.filter(|confidence| (0.0..=100.0).contains(confidence))?;
+    Some(Assessment {
+        confidence: confidence.round() as u8,
+        evidence,
+    })
+}
+
+/// The numbers cited in a text as `[n]`, including lists such as `[1, 2]`.
+fn cited_numbers(text: &str) -> Vec<usize> {
+    let mut numbers = Vec::new();
+    for (start, _) in text.match_indices('[') {
+        let Some(length) = text[start + 1..].find(']') else {
+            break;
+        };
+        let inside = &text[start + 1..start + 1 + length];
+        let parsed: Option<Vec<usize>> = inside
+            .split(',')
+            .map(|number| number.trim().parse().ok())
+            .collect();
+        numbers.extend(parsed.unwrap_or_default());
+    }
+    numbers
+}
+
+/// Reads the reranking model's reply, given as lines of `n: score`.
+///
+/// # Returns
+///
+/// The score of each of the `count` passages, in order, or `None` for
+/// passages the reply doesn't score.
+fn parse_rerank_scores(reply: &str, count: usize) -> Vec<Option<f32>> {
+    let mut scores = vec![None; count];
+    for line in reply.lines() {
+        let Some((number, score)) = line.split_once(':') else {
+            continue;
+        };
+        let number = number.trim().trim_matches(|c| c == '[' || c == ']');
+        let score = score
+            .split_whitespace()
+            .next()
+            .and_then(|score| score.parse::<f32>().ok());
+        if let (Ok(number), Some(score)) = (number.parse::<usize>(), score) {
+            if (1..=count).contains(&number) && score.is_finite() {
+                scores[number - 1] = Some(score);
+            }
+        }
+    }
+    scores
+}
+
+/// Combines rankings of chunks with reciprocal rank fusion: each chunk
+/// scores the sum of `1 / (RRF_K + rank)` over the rankings it appears in,
+/// so chunks ranked well by several searches come first. The scores within
+/// each ranking are ignored, which lets rankings with incomparable scores,
+/// such as cosine similarity and BM25, be combined.
+///
+/// # Returns
+///
+/// Up to `limit` pairs of chunk ID and fused score, highest first.
+fn reciprocal_rank_fusion(rankings: &[Vec<(i64, f32)>], limit: usize) -> Vec<(i64, f32)> {
+    let mut scores: HashMap<i64, f32> = HashMap::new();
+    for ranking in rankings {
+        for (rank, (chunk_id, _)) in ranking.iter().enumerate() {
+            *scores.entry(*chunk_id).or_insert(0.0) += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+    let mut fused: Vec<(i64, f32)> = scores.into_iter().collect();
+    fused.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    fused.truncate(limit);
+    fused
+}
+
+//========= Context Packing Helpers =========//
+
+/// Formats a chunk as the numbered source it is given as in the answer
+/// prompt.
+fn format_source(number: usize, chunk: &RelevantChunk) -> String {
+    format!(
+        "[{}] (from {})\n{}",
+        number, chunk.document_path, chunk.text
+    )
+}
+
+/// The distinct lowercased words of a text.
+fn word_set(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The Jaccard similarity of two sets of words: the share of all their
+/// words that they have in common, from 0 to 1.
+fn word_overlap(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
 //========= Vector Math Helpers =========//
 
 fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
@@ -116,14 +1206,3 @@ fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
     dot_product / (norm_v1 * norm_v2)
 }
 
-fn deserialize_embedding(bytes: &[u8]) -> Result<Vec<f32>> {
-    if bytes.len() % 4 != 0 {
-        return Err(Error::Processing(
-            "Invalid embedding data in database: not a multiple of 4 bytes.".to_string(),
-        ));
-    }
-    Ok(bytes
-        .chunks_exact(4)
-        .map(|c| f32::from_ne_bytes(c.try_into().unwrap()))
-        .collect())
-}
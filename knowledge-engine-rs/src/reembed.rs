@@ -0,0 +1,163 @@
+//! src/reembed.rs
+//!
+//! This module implements the `reembed` command. Every chunk records the
+//! model it was embedded with, and embeddings of different models can't be
+//! compared, so after the embedding model is upgraded, the chunks embedded
+//! with an older model would silently get meaningless similarity scores.
+//! `reembed` finds those chunks, embeds them again in batches, and rebuilds
+//! the similarity search structures.
+
+use crate::config::Config;
+use crate::database::{ChunkEmbedding, Storage};
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use crate::pipeline;
+use colored::Colorize;
+use tracing::{info, instrument};
+
+/// The number of chunks embedded per request unless `--batch-size` is
+/// given. The API accepts up to 2048 inputs per request.
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// What a re-embedding run went through.
+#[derive(Debug, Default)]
+pub struct ReembedStats {
+    pub chunks: usize,
+    pub batches: usize,
+}
+
+/// Counts the chunks that were not embedded with `embedding_model`.
+pub async fn outdated_chunk_count(storage: &dyn Storage, embedding_model: &str) -> Result<i64> {
+    Ok(storage
+        .embedding_models()
+        .await?
+        .iter()
+        .filter(|count| count.embedding_model.as_deref() != Some(embedding_model))
+        .map(|count| count.chunk_count)
+        .sum())
+}
+
+/// Prints how many chunks were embedded with each model, marking the ones
+/// that `reembed` would embed again with `embedding_model`.
+pub async fn status(storage: &dyn Storage, embedding_model: &str) -> Result<()> {
+    let counts = storage.embedding_models().await?;
+    if counts.is_empty() {
+        println!("The knowledge base has no chunks.");
+        return Ok(());
+    }
+
+    println!("{}", format!("{:>8}  MODEL", "CHUNKS").bold());
+    for count in &counts {
+        let model = count.embedding_model.as_deref().unwrap_or("(unknown)");
+        if count.embedding_model.as_deref() == Some(embedding_model) {
+            println!("{:>8}  {} (current)", count.chunk_count, model);
+        } else {
+            println!(
+                "{:>8}  {} {}",
+                count.chunk_count,
+                model,
+                "(outdated)".yellow()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Embeds every chunk that was not embedded with the client's embedding
+/// model again,
+/// `batch_size` chunks per request, then rebuilds the similarity search
+/// structures.
+///
+/// Each batch is stored in its own transaction, so an interrupted run loses
+/// at most one batch, and running the command again picks up where it
+/// stopped. The similarity search structures are discarded before each
+/// batch, so an interrupted run never leaves them built from the old
+/// embeddings; a run with nothing left to embed builds them if they are
+/// missing.
+#[instrument(skip(storage, client, config))]
+pub async fn reembed(
+    storage: &dyn Storage,
+    client: &OpenAIClient,
+    config: &Config,
+    batch_size: usize,
+) -> Result<ReembedStats> {
+    let mut stats = ReembedStats::default();
+    let embedding_model = client.embedding_model();
+    let total = outdated_chunk_count(storage, embedding_model).await?;
+    if total == 0 {
+        info!(
+            "Every chunk is already embedded with '{}'.",
+            embedding_model
+        );
+        pipeline::update_search_index(storage, config).await?;
+        return Ok(stats);
+    }
+    check_dimensions(storage, client).await?;
+    info!(
+        "Re-embedding {} chunks with '{}', {} per request...",
+        total, embedding_model, batch_size
+    );
+
+    loop {
+        let chunks = storage.outdated_chunks(embedding_model, batch_size).await?;
+        if chunks.is_empty() {
+            break;
+        }
+        pipeline::invalidate_search_index(storage, config).await?;
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|chunk| chunk.chunk_text.clone())
+            .collect();
+        let embeddings: Vec<ChunkEmbedding> = client
+            .get_embeddings(&texts)
+            .await?
+            .into_iter()
+            .zip(&chunks)
+            .map(|(embedding, chunk)| ChunkEmbedding {
+                id: chunk.id,
+                embedding,
+            })
+            .collect();
+        storage
+            .update_embeddings(&embeddings, embedding_model)
+            .await?;
+        stats.chunks += embeddings.len();
+        stats.batches += 1;
+        info!("Re-embedded {} of {} chunks.", stats.chunks, total);
+    }
+
+    info!("Rebuilding the similarity search structures...");
+    pipeline::rebuild_search_index(storage, config).await?;
+    Ok(stats)
+}
+
+/// Embeds one outdated chunk to check that the new model's embeddings fit
+/// the schema, if it fixes their dimensions, so that a mismatch fails before
+/// a whole batch is paid for.
+async fn check_dimensions(storage: &dyn Storage, client: &OpenAIClient) -> Result<()> {
+    let Some(dimensions) = storage.embedding_dimensions().await? else {
+        return Ok(());
+    };
+    let Some(chunk) = storage
+        .outdated_chunks(client.embedding_model(), 1)
+        .await?
+        .pop()
+    else {
+        return Ok(());
+    };
+    let embedding_dimensions = client
+        .get_embeddings(&[chunk.chunk_text])
+        .await?
+        .first()
+        .map_or(0, Vec::len);
+    if embedding_dimensions != dimensions {
+        return Err(Error::Config(format!(
+            "'{}' makes {}-dimensional embeddings, but the database stores {}-dimensional ones. Choose an embedding model with {} dimensions.",
+            client.embedding_model(),
+            embedding_dimensions,
+            dimensions,
+            dimensions
+        )));
+    }
+    Ok(())
+}
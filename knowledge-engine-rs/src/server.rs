@@ -0,0 +1,396 @@
+//! src/server.rs
+//!
+//! This module implements the `serve` command: a long-running HTTP server
+//! that exposes ingestion, querying, and the document list as a REST API, so
+//! that chat UIs and bots can use the knowledge base. Answers can be streamed
+//! as server-sent events while the model writes them.
+//!
+//! When `SERVER_API_KEY` is set, every endpoint except `GET /health` requires
+//! it as a bearer token.
+
+use crate::config::Config;
+use crate::database::{Storage, Tag};
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use crate::pipeline;
+use crate::pipeline::web::CrawlOptions;
+use crate::query_engine::{QueryEngine, QueryTransform, Retrieval};
+use axum::extract::{Json, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+use tracing::{error, info, warn};
+
+/// The default address the server listens on; only local clients can connect.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+/// The body of a `POST /ingest` request. Exactly one of `path` and `url` must
+/// be given.
+#[derive(Deserialize, Debug)]
+struct IngestRequest {
+    /// A file or directory to ingest, relative to the documents directory.
+    #[serde(default)]
+    path: Option<PathBuf>,
+    /// A web page or sitemap to crawl.
+    #[serde(default)]
+    url: Option<String>,
+    /// How many links away from `url` to follow.
+    #[serde(default)]
+    depth: usize,
+    /// The maximum number of pages to fetch from `url`.
+    #[serde(default = "default_max_pages")]
+    max_pages: usize,
+    /// Tags to attach to the ingested documents, as `KEY=VALUE`.
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn default_max_pages() -> usize {
+    100
+}
+
+/// The response to a `POST /ingest` request.
+#[derive(Serialize)]
+struct IngestResponse {
+    /// The paths of the documents that were new or changed and got indexed.
+    ingested: Vec<String>,
+}
+
+/// The body of a `POST /query` request.
+#[derive(Deserialize, Debug)]
+struct QueryRequest {
+    question: String,
+    /// Only retrieve chunks from documents with these tags, as `KEY=VALUE`.
+    #[serde(default)]
+    filters: Vec<String>,
+    /// Score every chunk instead of using the index.
+    #[serde(default)]
+    exact: bool,
+    /// Rerank the retrieved chunks with the reranking model.
+    #[serde(default)]
+    rerank: bool,
+    /// Transform the question before searching, as `rewrite` or `hyde`.
+    #[serde(default)]
+    transform: Option<String>,
+    /// Also search with this many paraphrases of the question.
+    #[serde(default)]
+    expansion: usize,
+    /// Decline to answer below this confidence, from 0 to 100, instead of
+    /// `MIN_CONFIDENCE`.
+    #[serde(default)]
+    min_confidence: Option<u8>,
+    /// Stream the answer as server-sent events.
+    #[serde(default)]
+    stream: bool,
+}
+
+/// The state shared by all requests.
+struct ServerState {
+    storage: Arc<dyn Storage>,
+    client: OpenAIClient,
+    config: Config,
+    /// The directory that ingested paths are resolved against, as given.
+    documents_dir: PathBuf,
+    /// The canonical form of `documents_dir`, which ingested paths must stay
+    /// within.
+    documents_root: PathBuf,
+    /// The retrieval method for queries, updated after each ingestion so
+    /// that the index is only loaded from disk when it changes.
+    retrieval: RwLock<Retrieval>,
+    /// Serializes ingestions, which would otherwise race on the same
+    /// documents and on rebuilding the index.
+    ingest_lock: Mutex<()>,
+}
+
+/// Serves the knowledge engine over HTTP on `addr` until the process is
+/// stopped.
+///
+/// Endpoints:
+/// - `GET /health` returns `ok`.
+/// - `GET /documents` returns the ingested documents.
+/// - `POST /ingest` takes `{"path": "..."}` or `{"url": "...", "depth": 1}`,
+///   with optional `"tags": ["key=value"]`, and returns the paths of the
+///   documents that were indexed.
+/// - `POST /query` takes `{"question": "..."}`, with optional `filters`,
+///   `exact`, `rerank`, `transform`, `expansion`, `min_confidence`, and
+///   `stream`, and returns `{"text": "...", "citations": [...], "confidence":
+///   85, ...}`. With `"stream": true`, the answer arrives as `token` events
+///   followed by a `done` event holding the whole answer.
+pub async fn serve(
+    storage: Arc<dyn Storage>,
+    client: OpenAIClient,
+    config: Config,
+    documents_dir: &Path,
+    addr: SocketAddr,
+) -> Result<()> {
+    let retrieval = Retrieval::select(
+        storage.as_ref(),
+        &config.index_path,
+        client.embedding_model(),
+    )
+    .await?;
+    let api_key = config.server_api_key.clone();
+    if api_key.is_none() {
+        warn!("SERVER_API_KEY is not set; the API accepts requests without authentication.");
+    }
+    let state = Arc::new(ServerState {
+        storage,
+        client,
+        config,
+        documents_dir: documents_dir.to_path_buf(),
+        documents_root: documents_dir.canonicalize()?,
+        retrieval: RwLock::new(retrieval),
+        ingest_lock: Mutex::new(()),
+    });
+
+    let api = Router::new()
+        .route("/documents", get(list_documents))
+        .route("/ingest", post(ingest))
+        .route("/query", post(query))
+        .route_layer(middleware::from_fn_with_state(api_key, require_api_key))
+        .with_state(state);
+    let app = Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .merge(api);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(
+        "Listening on http://{} (GET /documents, POST /ingest, POST /query).",
+        addr
+    );
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Rejects requests without the API key as a bearer token, if one is set.
+async fn require_api_key(
+    State(api_key): State<Option<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(api_key) = &api_key {
+        let token = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if !token.is_some_and(|token| constant_time_eq(token.as_bytes(), api_key.as_bytes())) {
+            return error_response(
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid API key.".to_string(),
+            );
+        }
+    }
+    next.run(request).await
+}
+
+/// Handles `GET /documents`.
+async fn list_documents(State(state): State<Arc<ServerState>>) -> Response {
+    match state.storage.list_documents().await {
+        Ok(documents) => Json(documents).into_response(),
+        Err(e) => error_from(e),
+    }
+}
+
+/// Handles `POST /ingest`.
+async fn ingest(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<IngestRequest>,
+) -> Response {
+    let tags = match parse_tags(&request.tags) {
+        Ok(tags) => tags,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, message),
+    };
+
+    let _guard = state.ingest_lock.lock().await;
+    let storage = state.storage.as_ref();
+    let source_docs = match (&request.path, &request.url) {
+        (Some(path), None) => {
+            let Some(path) = resolve_path(&state.documents_dir, &state.documents_root, path) else {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "'{}' is not a file or directory within the documents directory",
+                        path.display()
+                    ),
+                );
+            };
+            pipeline::ingestion::ingest_documents(storage, &path, false, false, &tags).await
+        }
+        (None, Some(url)) => {
+            let options = CrawlOptions {
+                depth: request.depth,
+                max_pages: request.max_pages,
+            };
+            pipeline::web::ingest_url(storage, url, &options, &tags).await
+        }
+        _ => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "Give either a 'path' or a 'url' to ingest.".to_string(),
+            )
+        }
+    };
+    let source_docs = match source_docs {
+        Ok(source_docs) => source_docs,
+        Err(e) => return error_from(e),
+    };
+
+    if let Err(e) =
+        pipeline::process_documents(storage, &state.client, &state.config, &source_docs).await
+    {
+        return error_from(e);
+    }
+    match Retrieval::select(
+        storage,
+        &state.config.index_path,
+        state.client.embedding_model(),
+    )
+    .await
+    {
+        Ok(retrieval) => *state.retrieval.write().await = retrieval,
+        Err(e) => return error_from(e),
+    }
+
+    Json(IngestResponse {
+        ingested: source_docs.into_iter().map(|doc| doc.path).collect(),
+    })
+    .into_response()
+}
+
+/// Handles `POST /query`.
+async fn query(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<QueryRequest>,
+) -> Response {
+    let filters = match parse_tags(&request.filters) {
+        Ok(filters) => filters,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, message),
+    };
+    let transform = match request
+        .transform
+        .as_deref()
+        .map(str::parse::<QueryTransform>)
+    {
+        Some(Ok(transform)) => Some(transform),
+        Some(Err(message)) => return error_response(StatusCode::BAD_REQUEST, message),
+        None => None,
+    };
+    if request
+        .min_confidence
+        .is_some_and(|min_confidence| min_confidence > 100)
+    {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "'min_confidence' must be between 0 and 100.".to_string(),
+        );
+    }
+    let retrieval = if request.exact {
+        Retrieval::Exact
+    } else {
+        state.retrieval.read().await.clone()
+    };
+    let mut query_engine = QueryEngine::new(
+        state.storage.clone(),
+        state.client.clone(),
+        retrieval,
+        filters,
+    )
+    .with_context_budget(state.config.context_token_budget);
+    if request.rerank {
+        query_engine = query_engine.with_reranking(state.config.rerank_model.clone());
+    }
+    if let Some(transform) = transform {
+        query_engine = query_engine
+            .with_query_transform(transform, state.config.query_transform_model.clone());
+    }
+    if let Some(min_confidence) = request.min_confidence.or(state.config.min_confidence) {
+        query_engine = query_engine.with_abstention(min_confidence);
+    }
+    if request.expansion > 0 {
+        query_engine = query_engine.with_expansion(
+            request.expansion,
+            state.config.query_transform_model.clone(),
+        );
+    }
+
+    if !request.stream {
+        return match query_engine.answer_question(&request.question).await {
+            Ok(answer) => Json(answer).into_response(),
+            Err(e) => error_from(e),
+        };
+    }
+
+    // The answer is written by a separate task, which sends each token as an
+    // event as soon as it arrives. If the client disconnects, the events are
+    // dropped and the task finishes the answer unobserved.
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let token_sender = sender.clone();
+        let result = query_engine
+            .answer_question_streamed(&request.question, &mut |token| {
+                let _ = token_sender.send(Event::default().event("token").data(token));
+            })
+            .await;
+        let event =
+            match result.and_then(|answer| serde_json::to_string(&answer).map_err(Error::from)) {
+                Ok(answer) => Event::default().event("done").data(answer),
+                Err(e) => {
+                    error!(error = ?e, "Failed to answer a streamed query.");
+                    Event::default().event("error").data(e.to_string())
+                }
+            };
+        let _ = sender.send(event);
+    });
+    Sse::new(UnboundedReceiverStream::new(receiver).map(Ok::<_, Infallible>)).into_response()
+}
+
+/// Parses tags given as `KEY=VALUE` strings.
+pub fn parse_tags(tags: &[String]) -> std::result::Result<Vec<Tag>, String> {
+    tags.iter().map(|tag| tag.parse()).collect()
+}
+
+/// Resolves a requested path against the documents directory, rejecting
+/// paths that escape it. The path is returned relative to the directory as
+/// given, so documents get the same path as when ingested from the command
+/// line.
+pub fn resolve_path(dir: &Path, root: &Path, requested: &Path) -> Option<PathBuf> {
+    let path = dir.join(requested);
+    let canonical = path.canonicalize().ok()?;
+    canonical.starts_with(root).then_some(path)
+}
+
+/// Compares two byte strings in time that depends only on their lengths, so
+/// that response times don't reveal how much of an API key was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Maps an error to a response with a fitting status code.
+fn error_from(e: Error) -> Response {
+    let status = match e {
+        Error::Document(_) => StatusCode::NOT_FOUND,
+        Error::Web(_) | Error::Config(_) => StatusCode::BAD_REQUEST,
+        Error::OpenAI(_) | Error::Client(_) | Error::Reqwest(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    if status == StatusCode::INTERNAL_SERVER_ERROR {
+        error!(error = ?e, "Failed to handle a request.");
+    }
+    error_response(status, e.to_string())
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
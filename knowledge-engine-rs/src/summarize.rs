@@ -0,0 +1,116 @@
+//! src/summarize.rs
+//!
+//! This module implements the `summarize` command, which summarizes a stored
+//! document from its chunks in the database, without reading the file again.
+//! Long documents are summarized with map-reduce: consecutive chunks are
+//! summarized in batches, and the batch summaries are then combined into one.
+
+use crate::database::Storage;
+use crate::docs;
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use ai_core::count_tokens;
+use tracing::{info, instrument};
+
+/// The most text, in tokens, summarized in a single request. Longer
+/// documents, or lists of partial summaries, are split into batches of at
+/// most this size.
+const BATCH_TOKENS: usize = 3_000;
+
+/// The length of each partial summary in the map step, in words. It is
+/// never shorter than the requested summary.
+const PARTIAL_SUMMARY_WORDS: u32 = 250;
+
+/// Summarizes a stored document in at most about `words` words. The target
+/// is a document ID or the file path it was ingested from.
+///
+/// # Returns
+///
+/// The path of the document and its summary.
+#[instrument(skip(storage, client))]
+pub async fn summarize(
+    storage: &dyn Storage,
+    client: &OpenAIClient,
+    target: &str,
+    words: u32,
+) -> Result<(String, String)> {
+    let id = docs::resolve_document(storage, target).await?;
+    let document = storage
+        .get_document(id)
+        .await?
+        .ok_or_else(|| Error::Document(format!("No document with ID {}.", id)))?;
+    let chunks = storage.document_chunks(id).await?;
+    if chunks.is_empty() {
+        return Err(Error::Document(format!(
+            "Document '{}' has no chunks to summarize.",
+            document.file_path
+        )));
+    }
+
+    let texts: Vec<String> = chunks.into_iter().map(|chunk| chunk.chunk_text).collect();
+    let model = client.generative_model();
+    let mut batches = batch(model, &texts);
+    info!(
+        "Summarizing '{}' from {} chunks in {} batches...",
+        document.file_path,
+        texts.len(),
+        batches.len()
+    );
+
+    // Map, then reduce the partial summaries until they fit in one request.
+    let partial_words = PARTIAL_SUMMARY_WORDS.max(words);
+    while batches.len() > 1 {
+        let mut summaries = Vec::new();
+        for (index, text) in batches.iter().enumerate() {
+            info!("Summarizing batch {} of {}...", index + 1, batches.len());
+            let system_prompt = format!("You summarize part of a longer document. Keep the key facts, names, numbers, decisions, and conclusions, in the order they appear, and leave out anything else. Write at most {} words, and nothing but the summary.", partial_words);
+            let user_prompt = format!(
+                "DOCUMENT: {}\nPART {} OF {}:\n{}",
+                document.file_path,
+                index + 1,
+                batches.len(),
+                text
+            );
+            summaries.push(client.get_completion(&system_prompt, &user_prompt).await?);
+        }
+        let reduced = batch(model, &summaries);
+        // Summaries too long to be batched together are combined in the
+        // final request anyway, rather than summarized again without end.
+        batches = if reduced.len() < batches.len() {
+            reduced
+        } else {
+            vec![summaries.join("\n\n")]
+        };
+    }
+
+    info!("Writing the final summary...");
+    let text = batches.pop().unwrap_or_default();
+    let system_prompt = format!("You summarize documents. The text is either the document itself or summaries of its consecutive parts. Write one coherent summary of the whole document that covers its purpose, its main points, and its conclusions, in at most {} words. Write nothing but the summary.", words);
+    let user_prompt = format!("DOCUMENT: {}\n\n{}", document.file_path, text);
+    let summary = client.get_completion(&system_prompt, &user_prompt).await?;
+    Ok((document.file_path, summary.trim().to_string()))
+}
+
+/// Joins consecutive texts into batches of at most `BATCH_TOKENS` tokens of
+/// `model`, except for single texts that are longer.
+fn batch(model: &str, texts: &[String]) -> Vec<String> {
+    let mut batches: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+    for text in texts {
+        let tokens = count_tokens(model, text);
+        if !current.is_empty() && current_tokens + tokens > BATCH_TOKENS {
+            batches.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        current_tokens += tokens;
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(text);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
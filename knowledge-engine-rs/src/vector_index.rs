@@ -0,0 +1,218 @@
+//! src/vector_index.rs
+//!
+//! An approximate nearest-neighbor index over the chunk embeddings. Scoring
+//! every chunk of the database for each query takes seconds once the
+//! knowledge base holds a hundred thousand chunks, so the ingest command
+//! builds an HNSW (Hierarchical Navigable Small World) graph of the
+//! embeddings and saves it next to the database, and queries search the
+//! graph in milliseconds instead.
+//!
+//! The index records how many chunks it covers, the highest chunk ID it has
+//! seen, and how many chunks each model embedded. If the database no longer
+//! matches, the index is stale and
+//! queries fall back to the exact full scan until the next ingest rebuilds it.
+
+use crate::database::{ChunkCoverage, Storage};
+use crate::error::{Error, Result};
+use instant_distance::{Builder, HnswMap, Point, Search};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use tracing::{info, instrument, warn};
+
+/// The default location of the index file, used when `INDEX_PATH` is not set.
+pub const DEFAULT_INDEX_PATH: &str = "data/chunks.hnsw";
+
+/// The size of the candidate list kept while building the graph. Larger
+/// values give a more accurate graph at the cost of a slower build.
+const EF_CONSTRUCTION: usize = 100;
+
+/// The size of the candidate list kept while searching the graph. Larger
+/// values give more accurate results at the cost of slower queries.
+const EF_SEARCH: usize = 100;
+
+/// An embedding, normalized to unit length so that cosine similarity is
+/// just the dot product.
+#[derive(Clone, Serialize, Deserialize)]
+struct Embedding(Vec<f32>);
+
+impl Embedding {
+    fn normalized(mut values: Vec<f32>) -> Self {
+        let norm = values.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            values.iter_mut().for_each(|x| *x /= norm);
+        }
+        Self(values)
+    }
+}
+
+impl Point for Embedding {
+    /// The cosine distance between two embeddings.
+    fn distance(&self, other: &Self) -> f32 {
+        1.0 - self.0.iter().zip(&other.0).map(|(x, y)| x * y).sum::<f32>()
+    }
+}
+
+/// An HNSW graph of the chunk embeddings, mapping each one to its chunk ID.
+#[derive(Serialize, Deserialize)]
+pub struct VectorIndex {
+    coverage: ChunkCoverage,
+    map: HnswMap<Embedding, i64>,
+}
+
+impl VectorIndex {
+    /// Builds an index of every chunk in the database.
+    #[instrument(skip_all)]
+    pub async fn build(storage: &dyn Storage) -> Result<Self> {
+        let coverage = storage.chunk_coverage().await?;
+        info!(
+            "Building the vector index for {} chunks...",
+            coverage.chunk_count
+        );
+
+        let chunks = storage.load_embeddings(&[]).await?;
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        let mut chunk_ids = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            embeddings.push(Embedding::normalized(chunk.embedding));
+            chunk_ids.push(chunk.id);
+        }
+
+        // Building the graph is CPU-bound and takes a while for large
+        // knowledge bases, so it runs off the async runtime.
+        let map = tokio::task::spawn_blocking(move || {
+            Builder::default()
+                .ef_construction(EF_CONSTRUCTION)
+                .ef_search(EF_SEARCH)
+                .build(embeddings, chunk_ids)
+        })
+        .await
+        .map_err(|e| Error::Index(format!("Building the vector index failed: {}", e)))?;
+
+        info!("Successfully built the vector index.");
+        Ok(Self { coverage, map })
+    }
+
+    /// Loads an index from disk.
+    pub fn load(path: &Path) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader).map_err(|e| {
+            Error::Index(format!(
+                "Could not read the vector index '{}': {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Saves the index to disk, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent_dir) = path.parent() {
+            fs::create_dir_all(parent_dir)?;
+        }
+        // Write to a temporary file first so that an interrupted save does
+        // not leave a truncated index behind.
+        let temp_path = path.with_extension("tmp");
+        let writer = BufWriter::new(File::create(&temp_path)?);
+        bincode::serialize_into(writer, self).map_err(|e| {
+            Error::Index(format!(
+                "Could not write the vector index '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Whether the index covers exactly the chunks currently in the database.
+    pub async fn is_current(&self, storage: &dyn Storage) -> Result<bool> {
+        Ok(self.coverage == storage.chunk_coverage().await?)
+    }
+
+    /// Finds the chunks most similar to `embedding`.
+    ///
+    /// # Returns
+    ///
+    /// Up to `limit` pairs of chunk ID and cosine similarity, most similar first.
+    pub fn search(&self, embedding: &[f32], limit: usize) -> Vec<(i64, f32)> {
+        let query = Embedding::normalized(embedding.to_vec());
+        let mut search = Search::default();
+        self.map
+            .search(&query, &mut search)
+            .take(limit)
+            .map(|item| (*item.value, 1.0 - item.distance))
+            .collect()
+    }
+}
+
+/// Loads the index saved at `path`, if there is one and it is up to date.
+///
+/// A missing, unreadable, or stale index is not an error: the caller falls
+/// back to an exact search, and a warning explains how to rebuild the index.
+pub async fn load_current(storage: &dyn Storage, path: &Path) -> Result<Option<VectorIndex>> {
+    if !path.is_file() {
+        warn!(
+            "No vector index found at '{}'; searching every chunk. Run 'ingest' to build it.",
+            path.display()
+        );
+        return Ok(None);
+    }
+    let index = match VectorIndex::load(path) {
+        Ok(index) => index,
+        Err(e) => {
+            warn!("{}. Searching every chunk instead.", e);
+            return Ok(None);
+        }
+    };
+    if !index.is_current(storage).await? {
+        warn!(
+            "The vector index at '{}' is out of date; searching every chunk. Run 'ingest' to rebuild it.",
+            path.display()
+        );
+        return Ok(None);
+    }
+    Ok(Some(index))
+}
+
+/// Rebuilds the index saved at `path` unless it already covers exactly the
+/// chunks in the database.
+#[instrument(skip(storage))]
+pub async fn rebuild_if_stale(storage: &dyn Storage, path: &Path) -> Result<()> {
+    if path.is_file() {
+        match VectorIndex::load(path) {
+            Ok(index) => {
+                if index.is_current(storage).await? {
+                    info!("The vector index is up to date.");
+                    return Ok(());
+                }
+            }
+            Err(e) => warn!("{}. Rebuilding it.", e),
+        }
+    }
+    rebuild(storage, path).await
+}
+
+/// Builds the index of every chunk in the database and saves it at `path`,
+/// replacing any index there, for example after embeddings were replaced.
+/// If there are no chunks, the saved index is removed.
+#[instrument(skip(storage))]
+pub async fn rebuild(storage: &dyn Storage, path: &Path) -> Result<()> {
+    if storage.chunk_coverage().await?.chunk_count == 0 {
+        info!("The knowledge base has no chunks to index.");
+        return remove(path);
+    }
+    let index = VectorIndex::build(storage).await?;
+    index.save(path)?;
+    info!("Saved the vector index to '{}'.", path.display());
+    Ok(())
+}
+
+/// Removes the index saved at `path`, if there is one.
+pub fn remove(path: &Path) -> Result<()> {
+    if path.is_file() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
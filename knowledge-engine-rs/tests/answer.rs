@@ -0,0 +1,109 @@
+//! tests/answer.rs
+//!
+//! The query engine against the mock provider: answers written from a set of
+//! chunks, abstention below the confidence threshold, and question expansion.
+
+use ai_core::{MockClient, ProviderConfig};
+use knowledge_engine_rs::database;
+use knowledge_engine_rs::openai_client::OpenAIClient;
+use knowledge_engine_rs::query_engine::{Evidence, QueryEngine, RelevantChunk, Retrieval};
+use std::sync::Arc;
+
+async fn engine(mock: MockClient) -> QueryEngine {
+    let storage = database::init_db("sqlite::memory:", None).await.unwrap();
+    let client = OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap();
+    QueryEngine::new(Arc::from(storage), client, Retrieval::Exact, Vec::new())
+}
+
+fn chunk(id: i64, document_path: &str, text: &str) -> RelevantChunk {
+    RelevantChunk {
+        id,
+        text: text.to_string(),
+        document_path: document_path.to_string(),
+        start_offset: None,
+        end_offset: None,
+        score: 1.0,
+        similarity: None,
+    }
+}
+
+fn sample_chunks() -> Vec<RelevantChunk> {
+    vec![
+        chunk(
+            1,
+            "docs/chunking.md",
+            "Documents are split into chunks of about 500 tokens, with an overlap of 50 \
+             tokens between neighboring chunks so that no sentence is lost at a boundary.",
+        ),
+        chunk(
+            2,
+            "docs/search.md",
+            "Search combines a vector search over the chunk embeddings with a keyword \
+             search, and fuses the two rankings with reciprocal rank fusion.",
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn answers_from_chunks_with_citations() {
+    let mock = MockClient::fixture(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/answer_chunk_size.json"
+    ))
+    .unwrap();
+    let answer = engine(mock)
+        .await
+        .answer_from_chunks(
+            "How large are the chunks documents are split into?",
+            sample_chunks(),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert!(answer.text.starts_with("Documents are split"));
+    assert_eq!(answer.confidence, Some(92));
+    assert_eq!(answer.evidence, Some(Evidence::Sufficient));
+    assert!(!answer.abstained);
+    assert_eq!(answer.citations.len(), 1);
+    assert_eq!(answer.citations[0].document_path, "docs/chunking.md");
+}
+
+#[tokio::test]
+async fn abstains_below_the_confidence_threshold() {
+    let mock = MockClient::new([
+        "CONFIDENCE: 20; EVIDENCE: insufficient\nThe sources don't say how chunks are stored.",
+    ]);
+    let answer = engine(mock)
+        .await
+        .with_abstention(50)
+        .answer_from_chunks("Where are chunks stored?", sample_chunks(), None)
+        .await
+        .unwrap();
+
+    assert!(answer.abstained);
+    assert!(answer.citations.is_empty());
+    assert_eq!(answer.closest_chunks.len(), 2);
+}
+
+#[tokio::test]
+async fn reads_numbered_paraphrases() {
+    let mock = MockClient::new([
+        "1. What size are document chunks?\n2) How big is each chunk?\n- What size are document chunks?",
+    ]);
+    let questions = engine(mock)
+        .await
+        .with_expansion(2, "gpt-4o-mini".to_string())
+        .expand_question("How large are chunks?")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        questions,
+        [
+            "How large are chunks?",
+            "What size are document chunks?",
+            "How big is each chunk?"
+        ]
+    );
+}
@@ -0,0 +1,48 @@
+//! tests/reembed.rs
+//!
+//! The chunk coverage that tells whether a vector index is stale, when
+//! embeddings are replaced in place by `reembed`.
+
+use knowledge_engine_rs::database::{self, ChunkEmbedding, NewChunk};
+
+#[tokio::test]
+async fn coverage_changes_when_embeddings_are_replaced() {
+    let storage = database::init_db("sqlite::memory:", None).await.unwrap();
+    let document_id = storage.insert_document("notes.md", "hash").await.unwrap();
+    let chunks: Vec<NewChunk> = ["First chunk.", "Second chunk."]
+        .into_iter()
+        .map(|chunk_text| NewChunk {
+            document_id,
+            chunk_text,
+            start_offset: None,
+            end_offset: None,
+            chunking: None,
+            embedding: vec![1.0, 0.0],
+            embedding_model: "text-embedding-3-small",
+        })
+        .collect();
+    storage.insert_chunks(&chunks).await.unwrap();
+    let before = storage.chunk_coverage().await.unwrap();
+
+    let outdated = storage
+        .outdated_chunks("text-embedding-3-large", 10)
+        .await
+        .unwrap();
+    let embeddings: Vec<ChunkEmbedding> = outdated
+        .iter()
+        .map(|chunk| ChunkEmbedding {
+            id: chunk.id,
+            embedding: vec![0.0, 1.0],
+        })
+        .collect();
+    storage
+        .update_embeddings(&embeddings, "text-embedding-3-large")
+        .await
+        .unwrap();
+    let after = storage.chunk_coverage().await.unwrap();
+
+    // The same chunks are stored, but an index built before is stale.
+    assert_eq!(after.chunk_count, before.chunk_count);
+    assert_eq!(after.max_chunk_id, before.max_chunk_id);
+    assert_ne!(after, before);
+}
@@ -0,0 +1,160 @@
+//! src/annotations.rs
+//!
+//! Gives users fine control over individual frames. An annotations file,
+//! passed with `--annotations`, overrides the style profile's modifiers
+//! (style, lighting, palette, and camera) for particular scenes, and adds a
+//! negative prompt to them, listing what their image must not show. A global
+//! negative prompt, given with `--negative-prompt`, applies to every scene.
+//!
+//! ```json
+//! {
+//!   "scenes": [
+//!     { "scene": 3, "camera": "extreme close-up on the hands", "negative_prompt": "extra fingers" },
+//!     { "chapter": 2, "scene": 1, "lighting": "golden hour backlight" }
+//!   ]
+//! }
+//! ```
+
+use crate::config::StyleProfile;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The overrides for a single scene.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SceneAnnotation {
+    /// The chapter of the scene, starting at 1. Required when the narrative
+    /// is split into chapters, and omitted otherwise.
+    #[serde(default)]
+    pub chapter: Option<usize>,
+    /// The scene's position in its chapter (or the narrative), starting at 1.
+    pub scene: usize,
+    #[serde(default)]
+    pub style: Option<String>,
+    #[serde(default)]
+    pub lighting: Option<String>,
+    #[serde(default)]
+    pub palette: Option<String>,
+    #[serde(default)]
+    pub camera: Option<String>,
+    /// What the scene's image must not show, in addition to the global
+    /// negative prompt.
+    #[serde(default)]
+    pub negative_prompt: Option<String>,
+}
+
+/// The global negative prompt and the per-scene overrides of a run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Annotations {
+    /// What no image may show. Set from `--negative-prompt`, not the file.
+    #[serde(skip_deserializing)]
+    pub negative_prompt: Option<String>,
+    #[serde(default)]
+    pub scenes: Vec<SceneAnnotation>,
+}
+
+impl Annotations {
+    /// Loads the per-scene overrides from an annotations file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` if the file cannot be read or parsed, or if
+    /// an annotation refers to scene 0.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file_content = fs::read_to_string(path).map_err(|e| {
+            Error::Config(format!(
+                "Could not read the annotations from '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let annotations: Annotations = serde_json::from_str(&file_content).map_err(|e| {
+            Error::Config(format!(
+                "Could not parse the annotations in '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        if annotations
+            .scenes
+            .iter()
+            .any(|annotation| annotation.scene == 0)
+        {
+            return Err(Error::Config(format!(
+                "The annotations in '{}' must number scenes from 1.",
+                path.display()
+            )));
+        }
+        Ok(annotations)
+    }
+
+    /// Whether there is nothing to apply.
+    pub fn is_empty(&self) -> bool {
+        self.negative_prompt.is_none() && self.scenes.is_empty()
+    }
+
+    /// The annotation of the scene at `index` of a chapter, if any.
+    pub fn scene(&self, chapter_number: Option<usize>, index: usize) -> Option<&SceneAnnotation> {
+        self.scenes.iter().find(|annotation| {
+            annotation.chapter == chapter_number && annotation.scene == index + 1
+        })
+    }
+
+    /// The style profile of the scene at `index` of a chapter: `style`, with
+    /// the scene's overrides applied.
+    pub fn style_for(
+        &self,
+        style: &StyleProfile,
+        chapter_number: Option<usize>,
+        index: usize,
+    ) -> StyleProfile {
+        let mut style = style.clone();
+        if let Some(annotation) = self.scene(chapter_number, index) {
+            let overrides = [
+                (&mut style.style, &annotation.style),
+                (&mut style.lighting, &annotation.lighting),
+                (&mut style.palette, &annotation.palette),
+                (&mut style.camera, &annotation.camera),
+            ];
+            for (field, value) in overrides {
+                if let Some(value) = value {
+                    *field = value.clone();
+                }
+            }
+        }
+        style
+    }
+
+    /// The negative prompt of the scene at `index` of a chapter: the global
+    /// one, followed by the scene's own.
+    pub fn negative_prompt_for(
+        &self,
+        chapter_number: Option<usize>,
+        index: usize,
+    ) -> Option<String> {
+        let scene_negative_prompt = self
+            .scene(chapter_number, index)
+            .and_then(|annotation| annotation.negative_prompt.as_deref());
+        let parts = [self.negative_prompt.as_deref(), scene_negative_prompt]
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+
+    /// The annotations that do not refer to any scene, given the number of
+    /// scenes in each chapter.
+    pub fn unmatched(&self, scene_counts: &[(Option<usize>, usize)]) -> Vec<&SceneAnnotation> {
+        self.scenes
+            .iter()
+            .filter(|annotation| {
+                !scene_counts.iter().any(|&(chapter_number, scene_count)| {
+                    annotation.chapter == chapter_number && annotation.scene <= scene_count
+                })
+            })
+            .collect()
+    }
+}
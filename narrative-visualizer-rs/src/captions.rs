@@ -0,0 +1,224 @@
+//! src/captions.rs
+//!
+//! An optional post-processing step that burns a caption onto each frame's
+//! image before the storyboard is assembled, producing presentation-ready
+//! frames. The caption is the scene description or an excerpt of the
+//! original text, drawn in white on a translucent band across the top or
+//! bottom of the image. The images saved in the project directory are left
+//! untouched, so captions can be changed without regenerating anything.
+
+use crate::error::{Error, Result};
+use crate::output_assembler::StoryboardChapter;
+use ab_glyph::{FontVec, PxScale};
+use clap::ValueEnum;
+use image::{ImageFormat, Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// The fonts tried, in order, when `--caption-font` is not given.
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// The most lines a caption wraps to; longer text is cut off with an ellipsis.
+const MAX_CAPTION_LINES: usize = 4;
+
+/// The caption font size, as a fraction of the image height, when
+/// `--caption-font-size` is not given.
+const DEFAULT_FONT_SIZE_RATIO: f32 = 1.0 / 28.0;
+
+/// What each frame's caption shows.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptionSource {
+    /// The scene description.
+    Description,
+    /// An excerpt of the scene's original text.
+    Text,
+}
+
+/// Where the caption is placed on the image.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptionPosition {
+    Top,
+    Bottom,
+}
+
+/// How the captions are rendered.
+#[derive(Clone, Debug)]
+pub struct CaptionOptions {
+    pub source: CaptionSource,
+    /// A TrueType or OpenType font file, or `None` for a common system font.
+    pub font: Option<PathBuf>,
+    /// The font size in pixels, or `None` to scale it with the image.
+    pub font_size: Option<f32>,
+    pub position: CaptionPosition,
+    /// The opacity of the band behind the caption, from 0 (none) to 1 (solid).
+    pub box_opacity: f32,
+}
+
+/// Burns captions onto images with a loaded font.
+pub struct CaptionRenderer {
+    font: FontVec,
+    options: CaptionOptions,
+}
+
+impl CaptionRenderer {
+    /// Loads the caption font.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` if the font cannot be read or parsed, or if no
+    /// font is given and none of the common system fonts is installed.
+    pub fn new(options: CaptionOptions) -> Result<Self> {
+        let font_path = match &options.font {
+            Some(font_path) => font_path.clone(),
+            None => SYSTEM_FONTS
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.is_file())
+                .ok_or_else(|| {
+                    Error::Config(
+                        "No system font was found for the captions; pass one with --caption-font."
+                            .to_string(),
+                    )
+                })?,
+        };
+        let font = load_font(&font_path)?;
+        info!("Captioning frames with the font '{}'.", font_path.display());
+        Ok(Self { font, options })
+    }
+
+    /// Burns a caption onto the image of every frame of the storyboard.
+    pub fn caption_chapters(&self, chapters: &mut [StoryboardChapter]) -> Result<()> {
+        for chapter in chapters {
+            for frame in &mut chapter.frames {
+                let caption = match self.options.source {
+                    CaptionSource::Description => &frame.scene_description,
+                    CaptionSource::Text => &frame.original_text,
+                };
+                frame.image_data = self.render(&frame.image_data, caption)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Burns `caption` onto an image.
+    ///
+    /// # Returns
+    ///
+    /// The captioned image as PNG data.
+    pub fn render(&self, image_data: &[u8], caption: &str) -> Result<Vec<u8>> {
+        let mut image = image::load_from_memory(image_data)?.to_rgba8();
+        let (width, height) = image.dimensions();
+        let font_size = self
+            .options
+            .font_size
+            .unwrap_or(height as f32 * DEFAULT_FONT_SIZE_RATIO);
+        let scale = PxScale::from(font_size);
+        let margin = (font_size * 0.6).round() as u32;
+        let line_height = (font_size * 1.25).round() as u32;
+
+        let max_line_width = width.saturating_sub(4 * margin);
+        let lines = wrap_lines(caption, MAX_CAPTION_LINES, |line| {
+            text_size(scale, &self.font, line).0 <= max_line_width
+        });
+        if lines.is_empty() {
+            return encode_png(&image);
+        }
+
+        let box_height = (lines.len() as u32 * line_height + 2 * margin).min(height);
+        let box_top = match self.options.position {
+            CaptionPosition::Top => 0,
+            CaptionPosition::Bottom => height - box_height,
+        };
+        darken(&mut image, box_top, box_height, self.options.box_opacity);
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_width = text_size(scale, &self.font, line).0;
+            let x = width.saturating_sub(line_width) / 2;
+            let y = box_top + margin + index as u32 * line_height;
+            draw_text_mut(
+                &mut image,
+                Rgba([255, 255, 255, 255]),
+                x as i32,
+                y as i32,
+                scale,
+                &self.font,
+                line,
+            );
+        }
+        encode_png(&image)
+    }
+}
+
+/// Loads a TrueType or OpenType font file.
+fn load_font(path: &Path) -> Result<FontVec> {
+    let font_data = fs::read(path).map_err(|e| {
+        Error::Config(format!(
+            "Could not read the caption font '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    FontVec::try_from_vec(font_data).map_err(|e| {
+        Error::Config(format!(
+            "Could not parse the caption font '{}': {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Breaks `text` into lines that `fits`, at most `max_lines` of them. If the
+/// text does not fit, the last line ends with an ellipsis.
+fn wrap_lines(text: &str, max_lines: usize, fits: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(line) = lines.last_mut() {
+            if fits(&format!("{} {}", line, word)) {
+                line.push(' ');
+                line.push_str(word);
+                continue;
+            }
+        }
+        if lines.len() == max_lines {
+            let line = lines.last_mut().expect("max_lines is at least one");
+            while !line.is_empty() && !fits(&format!("{}…", line)) {
+                line.pop();
+            }
+            line.push('…');
+            break;
+        }
+        lines.push(word.to_string());
+    }
+    lines
+}
+
+/// Blends a full-width band of the image towards black.
+fn darken(image: &mut RgbaImage, top: u32, height: u32, opacity: f32) {
+    let opacity = opacity.clamp(0.0, 1.0);
+    for y in top..top + height {
+        for x in 0..image.width() {
+            let pixel = image.get_pixel_mut(x, y);
+            for channel in &mut pixel.0[..3] {
+                *channel = (f32::from(*channel) * (1.0 - opacity)).round() as u8;
+            }
+        }
+    }
+}
+
+/// Encodes an image as PNG data.
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>> {
+    let mut png_data = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png)?;
+    Ok(png_data)
+}
@@ -0,0 +1,154 @@
+//! src/chapters.rs
+//!
+//! Splits book-length input into chapters, so that each chapter can be
+//! processed independently. Chapter boundaries are detected from heading
+//! lines (Markdown headings, or lines such as "Chapter 3" or "Prologue"), or
+//! from a user-supplied regex with `--split-on`. Input without any boundary
+//! is treated as a single, untitled chapter.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Matches the heading lines that start a chapter when `--split-on` is not given:
+/// Markdown headings, a keyword with a chapter number ("Chapter 3",
+/// "Part Two", "Book IV"), or "Prologue" and the like, optionally followed
+/// by a separator and a title without sentence punctuation ("Chapter 3: The
+/// Storm"). Prose such as "Part of me wanted to stay." doesn't match.
+static DEFAULT_HEADING: Lazy<Regex> = Lazy::new(|| {
+    let number = format!(
+        r"(?:\d+|[ivxlc]+|{units}|{tens}(?:-(?:{units}))?)",
+        units = "one|two|three|four|five|six|seven|eight|nine|ten|eleven|twelve|thirteen|fourteen|fifteen|sixteen|seventeen|eighteen|nineteen",
+        tens = "twenty|thirty|forty|fifty|sixty|seventy|eighty|ninety",
+    );
+    let title = r#"(?:\s*[.:\-–—]\s*[^.!?"“”]*)?"#;
+    Regex::new(&format!(
+        r"^(?:#{{1,3}}\s+\S.*|(?i:(?:chapter|part|book)\s+{number}{title}|(?:prologue|epilogue|interlude){title}))$"
+    ))
+    .unwrap()
+});
+
+/// Strips the Markdown heading marker from a chapter title.
+static MARKDOWN_HEADING_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^#{1,3}\s+").unwrap());
+
+/// Heading lines are short; longer matching lines are treated as prose.
+const MAX_HEADING_CHARS: usize = 100;
+
+/// The title of the text before the first heading, if there is any.
+const OPENING_TITLE: &str = "Opening";
+
+/// A part of the narrative that is processed on its own.
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    /// The chapter's position in the book, starting at 1, or `None` if the
+    /// narrative was not split.
+    pub number: Option<usize>,
+    pub title: Option<String>,
+    pub text: String,
+}
+
+impl Chapter {
+    /// The chapter's number and title, without its text.
+    pub fn heading(&self) -> ChapterHeading {
+        ChapterHeading {
+            number: self.number,
+            title: self.title.clone(),
+        }
+    }
+}
+
+/// The number and title of a chapter, as saved with the pipeline state.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChapterHeading {
+    pub number: Option<usize>,
+    pub title: Option<String>,
+}
+
+/// Splits a narrative into chapters.
+///
+/// A line matching `split_on` (or, if it is not given, a heading line)
+/// starts a new chapter and becomes its title. Chapters without any text are
+/// dropped. If no boundary is found, the whole narrative is returned as a
+/// single chapter without a number or title.
+pub fn split_chapters(narrative_text: &str, split_on: Option<&Regex>) -> Vec<Chapter> {
+    let is_boundary = |line: &str| {
+        let line = line.trim();
+        match split_on {
+            Some(split_on) => split_on.is_match(line),
+            None => line.len() <= MAX_HEADING_CHARS && DEFAULT_HEADING.is_match(line),
+        }
+    };
+
+    let mut sections: Vec<(Option<String>, String)> = vec![(None, String::new())];
+    for line in narrative_text.lines() {
+        if is_boundary(line) {
+            let title = MARKDOWN_HEADING_MARKER.replace(line.trim(), "").to_string();
+            sections.push((Some(title), String::new()));
+        } else if let Some((_, text)) = sections.last_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    if sections.len() == 1 {
+        return vec![Chapter {
+            number: None,
+            title: None,
+            text: narrative_text.to_string(),
+        }];
+    }
+
+    sections
+        .into_iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .enumerate()
+        .map(|(index, (title, text))| Chapter {
+            number: Some(index + 1),
+            title: Some(title.unwrap_or_else(|| OPENING_TITLE.to_string())),
+            text,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(text: &str) -> Vec<Option<String>> {
+        split_chapters(text, None)
+            .into_iter()
+            .map(|chapter| chapter.title)
+            .collect()
+    }
+
+    #[test]
+    fn splits_at_chapter_headings() {
+        let text = "Prologue\nIt was late.\nChapter 1: The Storm\nRain fell.\n\
+                    Part Two\nThe sun rose.\nBook IV.\nThey left.\nCHAPTER TWENTY-ONE\nThe end.\n\
+                    ## The Return\nThey came back.\nEpilogue - Years Later\nAll was quiet.\n";
+        assert_eq!(
+            titles(text),
+            [
+                "Prologue",
+                "Chapter 1: The Storm",
+                "Part Two",
+                "Book IV.",
+                "CHAPTER TWENTY-ONE",
+                "The Return",
+                "Epilogue - Years Later",
+            ]
+            .map(|title| Some(title.to_string()))
+        );
+    }
+
+    #[test]
+    fn keeps_prose_that_starts_with_a_keyword() {
+        let text = "Part of me wanted to stay.\n\"Book it,\" she said.\n\
+                    Chapter and verse, he knew them all.\nBook two tickets. Then call me.\n\
+                    Epilogue, she called it.\n";
+        let chapters = split_chapters(text, None);
+        assert_eq!(chapters.len(), 1);
+        assert_eq!(chapters[0].title, None);
+        assert_eq!(chapters[0].text, text);
+    }
+}
@@ -0,0 +1,495 @@
+//! src/checkpoint.rs
+//!
+//! Persists the intermediate state of the pipeline to a project directory,
+//! so that a failed or interrupted run can be resumed with `--resume`
+//! instead of repeating (and re-paying for) the completed work. Scenes and
+//! prompts are saved after their stage completes, and each image is saved as
+//! soon as it has been generated.
+//!
+//! The directory layout is:
+//!
+//! ```text
+//! <project-dir>/
+//! ├── manifest.json   # The input and settings the state belongs to
+//! ├── chapters.json   # The number and title of each chapter
+//! ├── characters.json # Character sheet pre-pass output
+//! ├── style_reference.json # Style reference pre-pass output
+//! ├── scenes.json     # Stage 1 output
+//! ├── prompts.json    # Stage 2 output
+//! ├── images/
+//! │   └── scene-001.png ...  # Stage 3 output, one file per scene
+//! └── narration/
+//!     └── scene-001-alloy.mp3 ...  # Narration, one file per scene and voice
+//! ```
+//!
+//! When the narrative is split into chapters, the scenes, prompts, and images
+//! of each chapter are saved in a `chapter-NN/` subdirectory instead, while
+//! the manifest and character sheets, which are shared, stay at the top.
+
+use crate::chapters::ChapterHeading;
+use crate::error::{Error, Result};
+use crate::openai_client::Voice;
+use crate::pipeline::character_sheets::CharacterSheet;
+use crate::pipeline::stage_1_scene_detection::Scene;
+use crate::pipeline::stage_2_prompt_generation::VisualPrompt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// The project directory used when `--project-dir` is not given.
+pub const DEFAULT_PROJECT_DIR: &str = "output/project";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CHAPTERS_FILE: &str = "chapters.json";
+const CHARACTERS_FILE: &str = "characters.json";
+const STYLE_REFERENCE_FILE: &str = "style_reference.json";
+const SCENES_FILE: &str = "scenes.json";
+const PROMPTS_FILE: &str = "prompts.json";
+const IMAGES_DIR: &str = "images";
+const NARRATION_DIR: &str = "narration";
+const CHAPTER_DIR_PREFIX: &str = "chapter-";
+
+/// The input and settings of a run, which decide what saved state is reusable.
+#[derive(Debug)]
+pub struct RunSettings<'a> {
+    pub narrative_text: &'a str,
+    /// The `--split-on` regex, if any.
+    pub split_on: Option<&'a str>,
+    /// A description of the scene limits.
+    pub scene_limits: String,
+    pub style: &'a str,
+    pub character_sheets: bool,
+    /// The negative prompt and per-scene annotations as JSON, if there are any.
+    pub annotations: Option<String>,
+    /// The data of the style reference image, if any.
+    pub style_reference: Option<&'a [u8]>,
+    /// A description of the image size and quality.
+    pub image_options: String,
+}
+
+/// Identifies the run a project directory belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// A fingerprint of the narrative text the scenes were detected from.
+    pub input_fingerprint: String,
+    /// The `--split-on` regex the narrative was split into chapters with.
+    #[serde(default)]
+    pub split_on: Option<String>,
+    /// The limits the scenes were detected with.
+    #[serde(default)]
+    pub scene_limits: String,
+    /// The style profile the prompts and images were generated with.
+    pub style: String,
+    /// Whether the prompts were generated with character sheets.
+    #[serde(default)]
+    pub character_sheets: bool,
+    /// A fingerprint of the annotations the prompts were generated with.
+    #[serde(default)]
+    pub annotations: Option<String>,
+    /// A fingerprint of the style reference image the prompts were generated with.
+    #[serde(default)]
+    pub style_reference: Option<String>,
+    /// The size and quality the images were generated with.
+    #[serde(default)]
+    pub image_options: String,
+}
+
+/// The saved state of one pipeline run.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    dir: PathBuf,
+}
+
+impl Checkpoint {
+    /// Opens the project directory for a run with the given settings.
+    ///
+    /// With `resume`, the saved state is kept, except for scenes detected with
+    /// different limits, prompts and images made in a different style,
+    /// character sheet setting, style reference, or with different
+    /// annotations, and images made with different options.
+    /// Without it, any saved state is discarded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Checkpoint` if `resume` is set and the saved state was
+    /// created from a different input or chapter split, or an I/O error if
+    /// the directory cannot be prepared.
+    pub fn open(dir: &Path, settings: &RunSettings, resume: bool) -> Result<Self> {
+        let checkpoint = Self {
+            dir: dir.to_path_buf(),
+        };
+        let manifest = Manifest {
+            input_fingerprint: fingerprint(settings.narrative_text),
+            split_on: settings.split_on.map(str::to_string),
+            scene_limits: settings.scene_limits.clone(),
+            style: settings.style.to_string(),
+            character_sheets: settings.character_sheets,
+            annotations: settings.annotations.as_deref().map(fingerprint),
+            style_reference: settings.style_reference.map(fingerprint),
+            image_options: settings.image_options.clone(),
+        };
+
+        if resume {
+            match checkpoint.read_json::<Manifest>(MANIFEST_FILE)? {
+                Some(saved) => {
+                    if saved.input_fingerprint != manifest.input_fingerprint
+                        || saved.split_on != manifest.split_on
+                    {
+                        return Err(Error::Checkpoint(format!(
+                            "The project directory '{}' was created from a different input or chapter split. Run without --resume to start over.",
+                            dir.display()
+                        )));
+                    }
+                    // Several settings may have changed at once, so each one
+                    // discards the state that depends on it.
+                    if saved.scene_limits != manifest.scene_limits {
+                        warn!(
+                            "The saved scenes were detected with different limits ({}); they are detected again.",
+                            saved.scene_limits
+                        );
+                        checkpoint.clear_scenes()?;
+                    }
+                    if saved.style != manifest.style {
+                        warn!(
+                            "The saved prompts and images use the '{}' style; they are generated again.",
+                            saved.style
+                        );
+                        checkpoint.clear_prompts()?;
+                    }
+                    if saved.character_sheets != manifest.character_sheets {
+                        warn!("The character sheet setting has changed; the prompts and images are generated again.");
+                        checkpoint.clear_prompts()?;
+                    }
+                    if saved.annotations != manifest.annotations {
+                        warn!("The negative prompt or scene annotations have changed; the prompts and images are generated again.");
+                        checkpoint.clear_prompts()?;
+                    }
+                    if saved.style_reference != manifest.style_reference {
+                        warn!("The style reference image has changed; it is described again, and the prompts and images are generated again.");
+                        remove_file_if_exists(&checkpoint.dir.join(STYLE_REFERENCE_FILE))?;
+                        checkpoint.clear_prompts()?;
+                    }
+                    if saved.image_options != manifest.image_options {
+                        warn!(
+                            "The saved images were generated with different options ({}); they are regenerated.",
+                            saved.image_options
+                        );
+                        checkpoint.clear_images()?;
+                    }
+                    info!("Resuming from '{}'.", dir.display());
+                }
+                None => info!(
+                    "No saved state found in '{}'; starting from the beginning.",
+                    dir.display()
+                ),
+            }
+        } else {
+            checkpoint.clear_all()?;
+        }
+
+        checkpoint.write_json(MANIFEST_FILE, &manifest)?;
+        Ok(checkpoint)
+    }
+
+    /// Opens a project directory left by a previous run, without changing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Checkpoint` if the directory holds no saved state.
+    pub fn open_existing(dir: &Path) -> Result<(Self, Manifest)> {
+        let checkpoint = Self {
+            dir: dir.to_path_buf(),
+        };
+        let manifest = checkpoint
+            .read_json::<Manifest>(MANIFEST_FILE)?
+            .ok_or_else(|| {
+                Error::Checkpoint(format!(
+                    "'{}' is not a project directory; it has no {}.",
+                    dir.display(),
+                    MANIFEST_FILE
+                ))
+            })?;
+        Ok((checkpoint, manifest))
+    }
+
+    /// The chapters the narrative was split into, if saved.
+    pub fn load_chapters(&self) -> Result<Option<Vec<ChapterHeading>>> {
+        self.read_json(CHAPTERS_FILE)
+    }
+
+    /// Saves the chapters the narrative was split into.
+    pub fn save_chapters(&self, chapters: &[ChapterHeading]) -> Result<()> {
+        self.write_json(CHAPTERS_FILE, chapters)
+    }
+
+    /// The saved state of a chapter, or the whole run if the narrative was
+    /// not split (`number` is `None`).
+    pub fn chapter(&self, number: Option<usize>) -> Checkpoint {
+        match number {
+            Some(number) => Checkpoint {
+                dir: self
+                    .dir
+                    .join(format!("{}{:02}", CHAPTER_DIR_PREFIX, number)),
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// The character sheets saved by a previous run, if any.
+    pub fn load_characters(&self) -> Result<Option<Vec<CharacterSheet>>> {
+        self.read_json(CHARACTERS_FILE)
+    }
+
+    /// Saves the character sheets, discarding the prompts and images derived
+    /// from earlier ones.
+    pub fn save_characters(&self, characters: &[CharacterSheet]) -> Result<()> {
+        self.clear_prompts()?;
+        self.write_json(CHARACTERS_FILE, characters)
+    }
+
+    /// The description of the style reference saved by a previous run, if any.
+    pub fn load_style_reference(&self) -> Result<Option<String>> {
+        self.read_json(STYLE_REFERENCE_FILE)
+    }
+
+    /// Saves the description of the style reference, discarding the prompts
+    /// and images derived from an earlier one.
+    pub fn save_style_reference(&self, description: &str) -> Result<()> {
+        self.clear_prompts()?;
+        self.write_json(STYLE_REFERENCE_FILE, description)
+    }
+
+    /// The scenes saved by a previous run, if any.
+    pub fn load_scenes(&self) -> Result<Option<Vec<Scene>>> {
+        self.read_json(SCENES_FILE)
+    }
+
+    /// Saves the scenes, discarding the prompts, images, and narration derived
+    /// from earlier ones.
+    pub fn save_scenes(&self, scenes: &[Scene]) -> Result<()> {
+        self.clear_prompts()?;
+        self.clear_narration()?;
+        self.write_json(SCENES_FILE, scenes)
+    }
+
+    /// The visual prompts saved by a previous run, if any.
+    pub fn load_prompts(&self) -> Result<Option<Vec<VisualPrompt>>> {
+        self.read_json(PROMPTS_FILE)
+    }
+
+    /// Saves the visual prompts, discarding the images derived from earlier ones.
+    pub fn save_prompts(&self, prompts: &[VisualPrompt]) -> Result<()> {
+        self.clear_images()?;
+        self.write_json(PROMPTS_FILE, prompts)
+    }
+
+    /// Saves visual prompts revised by hand, keeping the saved images and
+    /// narration of the scenes that can reuse them. `image_sources[i]` and
+    /// `narration_sources[i]` are the position of the scene whose image and
+    /// narration the scene at `i` takes over, or `None` if it needs new ones.
+    pub fn save_revised_prompts(
+        &self,
+        prompts: &[VisualPrompt],
+        image_sources: &[Option<usize>],
+        narration_sources: &[Option<usize>],
+    ) -> Result<()> {
+        self.remap_scene_files(IMAGES_DIR, image_sources)?;
+        self.remap_scene_files(NARRATION_DIR, narration_sources)?;
+        self.write_json(PROMPTS_FILE, prompts)
+    }
+
+    /// The image saved for the scene at `index`, if any.
+    pub fn load_image(&self, index: usize) -> Result<Option<Vec<u8>>> {
+        let path = self.image_path(index);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read(path)?))
+    }
+
+    /// Saves the image generated for the scene at `index`.
+    pub fn save_image(&self, index: usize, image_data: &[u8]) -> Result<()> {
+        fs::create_dir_all(self.dir.join(IMAGES_DIR))?;
+        write_atomically(&self.image_path(index), image_data)
+    }
+
+    /// The path the image of the scene at `index` is saved at.
+    pub fn image_path(&self, index: usize) -> PathBuf {
+        self.dir
+            .join(IMAGES_DIR)
+            .join(format!("scene-{:03}.png", index + 1))
+    }
+
+    /// The narration saved for the scene at `index` in `voice`, if any.
+    pub fn load_narration(&self, index: usize, voice: Voice) -> Result<Option<Vec<u8>>> {
+        let path = self.narration_path(index, voice);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read(path)?))
+    }
+
+    /// Saves the narration synthesized for the scene at `index` in `voice`.
+    pub fn save_narration(&self, index: usize, voice: Voice, audio_data: &[u8]) -> Result<()> {
+        fs::create_dir_all(self.dir.join(NARRATION_DIR))?;
+        write_atomically(&self.narration_path(index, voice), audio_data)
+    }
+
+    /// The narration of each voice is saved separately, so switching voices
+    /// does not discard the narration already paid for.
+    fn narration_path(&self, index: usize, voice: Voice) -> PathBuf {
+        self.dir
+            .join(NARRATION_DIR)
+            .join(format!("scene-{:03}-{}.mp3", index + 1, voice.as_str()))
+    }
+
+    /// Renumbers the `scene-NNN*` files in a subdirectory to follow their
+    /// scenes to new positions, removing those no scene takes over.
+    fn remap_scene_files(&self, dir_name: &str, sources: &[Option<usize>]) -> Result<()> {
+        let dir = self.dir.join(dir_name);
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let mut remapped = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(rest) = name.strip_prefix("scene-") else {
+                continue;
+            };
+            let (Some(number), Some(suffix)) = (rest.get(..3), rest.get(3..)) else {
+                continue;
+            };
+            let Ok(number @ 1..) = number.parse::<usize>() else {
+                continue;
+            };
+            for (index, source) in sources.iter().enumerate() {
+                if *source == Some(number - 1) {
+                    let new_name = format!("scene-{:03}{}", index + 1, suffix);
+                    remapped.push((new_name, fs::read(&path)?));
+                }
+            }
+        }
+        fs::remove_dir_all(&dir)?;
+        fs::create_dir_all(&dir)?;
+        for (name, data) in remapped {
+            write_atomically(&dir.join(name), &data)?;
+        }
+        Ok(())
+    }
+
+    fn clear_all(&self) -> Result<()> {
+        remove_file_if_exists(&self.dir.join(CHARACTERS_FILE))?;
+        remove_file_if_exists(&self.dir.join(STYLE_REFERENCE_FILE))?;
+        self.clear_scenes()
+    }
+
+    fn clear_scenes(&self) -> Result<()> {
+        remove_file_if_exists(&self.dir.join(SCENES_FILE))?;
+        for chapter in self.chapters()? {
+            fs::remove_dir_all(chapter.dir)?;
+        }
+        self.clear_narration()?;
+        self.clear_prompts()
+    }
+
+    fn clear_prompts(&self) -> Result<()> {
+        remove_file_if_exists(&self.dir.join(PROMPTS_FILE))?;
+        for chapter in self.chapters()? {
+            chapter.clear_prompts()?;
+        }
+        self.clear_images()
+    }
+
+    fn clear_images(&self) -> Result<()> {
+        let images_dir = self.dir.join(IMAGES_DIR);
+        if images_dir.is_dir() {
+            fs::remove_dir_all(images_dir)?;
+        }
+        for chapter in self.chapters()? {
+            chapter.clear_images()?;
+        }
+        Ok(())
+    }
+
+    fn clear_narration(&self) -> Result<()> {
+        let narration_dir = self.dir.join(NARRATION_DIR);
+        if narration_dir.is_dir() {
+            fs::remove_dir_all(narration_dir)?;
+        }
+        for chapter in self.chapters()? {
+            chapter.clear_narration()?;
+        }
+        Ok(())
+    }
+
+    /// The saved chapter subdirectories.
+    fn chapters(&self) -> Result<Vec<Checkpoint>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut chapters = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_chapter = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(CHAPTER_DIR_PREFIX));
+            if is_chapter && path.is_dir() {
+                chapters.push(Checkpoint { dir: path });
+            }
+        }
+        Ok(chapters)
+    }
+
+    fn read_json<T: DeserializeOwned>(&self, file_name: &str) -> Result<Option<T>> {
+        let path = self.dir.join(file_name);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).map(Some).map_err(|e| {
+            Error::Checkpoint(format!(
+                "Could not parse '{}': {}. Run without --resume to start over.",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    fn write_json<T: Serialize + ?Sized>(&self, file_name: &str, value: &T) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let content = serde_json::to_string_pretty(value)?;
+        write_atomically(&self.dir.join(file_name), content.as_bytes())
+    }
+}
+
+/// Writes to a temporary file first, so an interrupted write never leaves a
+/// truncated file behind to be picked up on resume.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+fn remove_file_if_exists(path: &Path) -> Result<()> {
+    if path.is_file() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// A stable FNV-1a fingerprint of the input text or image.
+fn fingerprint(data: impl AsRef<[u8]>) -> String {
+    let hash = data
+        .as_ref()
+        .iter()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        });
+    format!("{:016x}", hash)
+}
@@ -1,28 +1,92 @@
 //! src/config.rs
 //!
 //! Manages the application's configuration, primarily loading the
-//! OpenAI API key from the environment.
+//! model provider settings from the environment and the visual style profiles
+//! from their JSON file.
 
 use crate::error::{Error, Result};
+use ai_core::ProviderConfig;
+use colored::Colorize;
 use dotenvy::dotenv;
-use std::env;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
 
-/// Loads the OpenAI API key from the environment.
-///
-/// # Returns
-///
-/// A `Result` containing the API key as a `String` on success.
+/// The default path of the visual style profiles file.
+pub const STYLE_PROFILES_PATH: &str = "style_profiles.json";
+
+/// The style profile used when `--style` is not given.
+pub const DEFAULT_STYLE: &str = "film-noir";
+
+/// The default address of a local Stable Diffusion WebUI.
+pub const DEFAULT_STABLE_DIFFUSION_URL: &str = "http://127.0.0.1:7860";
+
+/// Loads the model provider settings from the environment: `AI_PROVIDER`,
+/// `AI_MODEL`, and the keys, such as `OPENAI_API_KEY`.
 ///
 /// # Errors
 ///
-/// Returns `Error::Config` if the `OPENAI_API_KEY` environment variable is not set.
-pub fn get_api_key() -> Result<String> {
+/// Returns an `Error` if `AI_PROVIDER` names an unknown provider.
+pub fn get_provider_config() -> Result<ProviderConfig> {
     // Load environment variables from a .env file if it exists.
     dotenv().ok();
 
-    env::var("OPENAI_API_KEY").map_err(|_| {
-        Error::Config(
-            "The OPENAI_API_KEY environment variable must be set.".to_string(),
-        )
-    })
+    Ok(ProviderConfig::from_env()?)
+}
+
+/// A named visual style, with the defaults injected into every image prompt.
+#[derive(Deserialize, Debug, Clone)]
+pub struct StyleProfile {
+    pub name: String,
+    pub description: String,
+    /// The artistic style, e.g. "cinematic film noir".
+    pub style: String,
+    pub lighting: String,
+    /// The color palette.
+    pub palette: String,
+    /// The camera angle and framing.
+    pub camera: String,
+}
+
+/// Represents the top-level structure of the style profiles file.
+#[derive(Deserialize, Debug, Clone)]
+pub struct StyleProfileConfig {
+    pub profiles: Vec<StyleProfile>,
+}
+
+/// Loads the style profiles from `path` and returns the one named `name`.
+///
+/// # Errors
+///
+/// Returns an `Error` if the file cannot be read or parsed, or
+/// `Error::Config`, listing the available profiles, if none is named `name`.
+pub fn load_style_profile(path: &Path, name: &str) -> Result<StyleProfile> {
+    let file_content = fs::read_to_string(path).map_err(|e| {
+        Error::Config(format!(
+            "Could not read the style profiles from '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let config: StyleProfileConfig = serde_json::from_str(&file_content)?;
+
+    config
+        .profiles
+        .iter()
+        .find(|profile| profile.name == name)
+        .cloned()
+        .ok_or_else(|| {
+            let available_profiles_info = config
+                .profiles
+                .iter()
+                .map(|profile| format!("  - {}: {}", profile.name.cyan(), profile.description))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Error::Config(format!(
+                "Style '{}' not found in '{}'.\n\nAvailable styles:\n{}",
+                name,
+                path.display(),
+                available_profiles_info
+            ))
+        })
 }
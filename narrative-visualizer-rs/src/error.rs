@@ -17,9 +17,10 @@ pub enum Error {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
-    /// Wraps errors from the `reqwest` HTTP client.
-    #[error("HTTP request error")]
-    Reqwest(#[from] reqwest::Error),
+    /// Wraps errors from requests to the OpenAI API and local servers, made
+    /// with the shared `ai-core` client.
+    #[error(transparent)]
+    Client(#[from] ai_core::Error),
 
     /// For errors returned specifically by the OpenAI API.
     #[error("OpenAI API error: {0}")]
@@ -29,13 +30,37 @@ pub enum Error {
     #[error("JSON processing error: {0}")]
     SerdeJson(#[from] serde_json::Error),
 
+    /// For errors returned by a local Stable Diffusion server.
+    #[error("Stable Diffusion error: {0}")]
+    StableDiffusion(String),
+
     /// For errors related to decoding base64 image data.
     #[error("Base64 decoding error: {0}")]
     Base64Decode(#[from] base64::DecodeError),
 
+    /// Wraps errors from decoding and encoding images, e.g. for captions.
+    #[error("Image processing error: {0}")]
+    Image(#[from] image::ImageError),
+
+    /// Wraps errors from writing EPUB archives.
+    #[error("EPUB packaging error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
     /// For when a required step in the pipeline produces no output.
     #[error("Pipeline error: {0}")]
     Pipeline(String),
+
+    /// For saved pipeline state that cannot be resumed.
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(String),
+
+    /// For an interactive scene review that cannot be completed.
+    #[error("Scene review error: {0}")]
+    Review(String),
+
+    /// For storyboard project files that cannot be exported or imported.
+    #[error("Project file error: {0}")]
+    Project(String),
 }
 
 /// A convenient type alias for `Result<T, E>` using our custom `Error` type.
@@ -0,0 +1,20 @@
+//! src/lib.rs
+//!
+//! The library behind the Narrative Visualization Engine: the pipeline
+//! stages, the clients for the text and image models, checkpoints, and the
+//! storyboard writers. `main.rs` drives them from the command line, and the
+//! tests in `tests/` run the stages against the mock provider.
+
+pub mod annotations;
+pub mod captions;
+pub mod chapters;
+pub mod checkpoint;
+pub mod config;
+pub mod error;
+pub mod openai_client;
+pub mod output_assembler;
+pub mod pipeline;
+pub mod plan;
+pub mod progress;
+pub mod project_file;
+pub mod review;
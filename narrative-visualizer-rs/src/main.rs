@@ -4,19 +4,20 @@
 //! This module orchestrates the entire multi-stage pipeline, from parsing
 //! command-line arguments to generating the final storyboard file.
 
-// Declare the module hierarchy for the compiler.
-mod config;
-mod error;
-mod openai_client;
-mod output_assembler;
-mod pipeline;
-
-use crate::error::Result;
-use clap::Parser;
+use ai_core::RetryPolicy;
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
+use narrative_visualizer_rs::error::Result;
+use narrative_visualizer_rs::{
+    annotations, captions, chapters, checkpoint, config, error, openai_client, output_assembler,
+    pipeline, plan, progress, project_file, review,
+};
+use regex::Regex;
 use std::fs;
-use std::path::PathBuf;
-use tracing::{error, info};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, EnvFilter};
 
 /// Defines the command-line arguments for the application.
@@ -24,16 +25,227 @@ use tracing_subscriber::{fmt, EnvFilter};
 #[command(
     author = "Your Name",
     version,
-    about = "An AI-powered engine to generate visual storyboards from narrative text."
+    about = "An AI-powered engine to generate visual storyboards from narrative text.",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The path to the input narrative text file.
+    #[arg(short, long, required_unless_present = "input_dir")]
+    input_file: Option<PathBuf>,
+
+    /// A directory of narrative files (.txt or .md) to generate a storyboard
+    /// for each, in one run with the same settings.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["input_file", "output_file"])]
+    input_dir: Option<PathBuf>,
+
+    /// With --input-dir, the directory the storyboard of each narrative is
+    /// saved in, under a folder named after the narrative.
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "output",
+        requires = "input_dir"
+    )]
+    output_dir: PathBuf,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// A regex matching the lines that start a new chapter. By default,
+    /// Markdown headings and lines such as "Chapter 3" or "Prologue" do.
+    #[arg(long, value_name = "REGEX")]
+    split_on: Option<String>,
+
+    /// The most scenes the narrative (or each chapter) is divided into;
+    /// adjacent scenes are merged to keep to it.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_scenes: Option<u32>,
+
+    /// The fewest characters of text a scene spans; shorter scenes are
+    /// merged with a neighbor. 0 disables merging.
+    #[arg(long, default_value_t = 0)]
+    min_scene_length: usize,
+
+    /// The maximum number of images generated at the same time.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: u32,
+
+    /// The service that generates the images: DALL-E, or a local Stable
+    /// Diffusion WebUI, which costs nothing per image.
+    #[arg(long, value_enum, default_value_t = openai_client::ImageBackendKind::DallE)]
+    image_backend: openai_client::ImageBackendKind,
+
+    /// The URL of the Stable Diffusion WebUI, started with `--api`.
+    #[arg(long, default_value = config::DEFAULT_STABLE_DIFFUSION_URL)]
+    sd_url: String,
+
+    /// What happens to image prompts flagged by the OpenAI moderation
+    /// endpoint before they are submitted to DALL-E.
+    #[arg(long, value_enum, default_value_t = pipeline::moderation::ModerationPolicy::Rewrite)]
+    moderation: pipeline::moderation::ModerationPolicy,
+
+    /// How many times a request that failed with a rate limit, timeout, or
+    /// server error is retried, with jittered exponential backoff.
+    #[arg(long, default_value_t = 3)]
+    retries: u32,
+
+    /// Stop sending requests once the run has cost this many US dollars
+    /// (default: AI_MAX_COST). Frames whose images can no longer be
+    /// generated get placeholders, as for other failures.
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Reuse the cached replies to the same requests for scenes and prompts
+    /// instead of asking the model again. `AI_CACHE=1` does the same.
+    /// Images are never cached.
+    #[arg(long)]
+    cache: bool,
+
+    /// The size of the generated images.
+    #[arg(long, value_enum, default_value_t = openai_client::ImageSize::Square)]
+    size: openai_client::ImageSize,
+
+    /// The quality of the generated images; `hd` has finer detail and costs more.
+    #[arg(long, value_enum, default_value_t = openai_client::ImageQuality::Standard)]
+    quality: openai_client::ImageQuality,
+
+    /// Size each image by the orientation suggested for its scene (landscape,
+    /// portrait, or square), using --size only for scenes without a suggestion.
+    #[arg(long)]
+    orientation_hints: bool,
+
+    /// Narrate each scene's original text with text-to-speech, adding an audio
+    /// player to every frame.
+    #[arg(long)]
+    narration: bool,
+
+    /// The voice of the narration.
+    #[arg(long, value_enum, default_value_t = openai_client::Voice::Alloy)]
+    voice: openai_client::Voice,
+
+    /// The visual style profile used for every image (e.g., 'watercolor', 'anime').
+    #[arg(long, default_value = config::DEFAULT_STYLE)]
+    style: String,
+
+    /// An image whose illustration style every frame should match; its style
+    /// is described by a vision model and blended into every prompt.
+    #[arg(long, value_name = "IMAGE")]
+    style_reference: Option<PathBuf>,
+
+    /// Skip the character sheet pre-pass, which keeps recurring characters
+    /// looking the same across frames.
+    #[arg(long)]
+    no_character_sheets: bool,
+
+    /// Things no image may show, e.g. "extra limbs, text, watermark".
+    #[arg(long, value_name = "TEXT")]
+    negative_prompt: Option<String>,
+
+    /// A JSON file of per-scene style overrides and negative prompts.
+    #[arg(long, value_name = "FILE")]
+    annotations: Option<PathBuf>,
+
+    /// The path to the JSON file defining the style profiles.
+    #[arg(long, default_value = config::STYLE_PROFILES_PATH)]
+    style_profiles: PathBuf,
+
+    /// The directory where the state of each pipeline stage is saved.
+    #[arg(long, default_value = checkpoint::DEFAULT_PROJECT_DIR)]
+    project_dir: PathBuf,
+
+    /// Open the prompts of each chapter in $EDITOR after they are generated,
+    /// to edit, delete, or reorder scenes before any image is generated.
+    #[arg(long)]
+    review: bool,
+
+    /// Detect scenes and generate prompts only, then print the plan with the
+    /// estimated cost of the images and stop, without generating any images.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Resume from the state saved in the project directory, skipping completed work.
+    #[arg(long)]
+    resume: bool,
+}
+
+/// The commands that work on a project without running the pipeline.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export the scenes, prompts, and images of a project directory as a
+    /// storyboard.json project file, for editing outside the engine.
+    Export {
+        /// The project directory to export.
+        #[arg(long, default_value = checkpoint::DEFAULT_PROJECT_DIR)]
+        project_dir: PathBuf,
+
+        /// The path for the project file.
+        #[arg(short, long, default_value = project_file::DEFAULT_PROJECT_FILE)]
+        output_file: PathBuf,
+    },
+    /// Assemble a storyboard from a (possibly hand-edited) project file,
+    /// without calling any API.
+    Import {
+        /// The path to the storyboard.json project file.
+        project_file: PathBuf,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+/// The arguments that control how the storyboard is assembled.
+#[derive(clap::Args, Clone, Debug)]
+struct OutputArgs {
+    /// The path for the output storyboard file [default: output/storyboard.<format>].
     #[arg(short, long)]
-    input_file: PathBuf,
+    output_file: Option<PathBuf>,
+
+    /// The format of the storyboard.
+    #[arg(long, value_enum, default_value_t = output_assembler::OutputFormat::Html)]
+    format: output_assembler::OutputFormat,
+
+    /// How images are included in HTML: embedded in the page, or saved as files next to it.
+    #[arg(long, value_enum, default_value_t = output_assembler::ImageMode::Embedded)]
+    image_mode: output_assembler::ImageMode,
+
+    /// Whether the chapters are assembled into one storyboard, or into a
+    /// separate storyboard each.
+    #[arg(long, value_enum, default_value_t = ChapterOutput::Combined)]
+    chapter_output: ChapterOutput,
 
-    /// The path for the output HTML storyboard file.
-    #[arg(short, long, default_value = "output/storyboard.html")]
-    output_file: PathBuf,
+    /// Burn a caption onto every image: the scene description, or an excerpt
+    /// of the original text.
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    captions: Option<captions::CaptionSource>,
+
+    /// The TrueType or OpenType font of the captions [default: a common system font].
+    #[arg(long, value_name = "FILE")]
+    caption_font: Option<PathBuf>,
+
+    /// The font size of the captions in pixels [default: 1/28 of the image height].
+    #[arg(long, value_name = "PIXELS")]
+    caption_font_size: Option<f32>,
+
+    /// Where the captions are placed on the images.
+    #[arg(long, value_enum, default_value_t = captions::CaptionPosition::Bottom)]
+    caption_position: captions::CaptionPosition,
+
+    /// The opacity of the band behind the captions, from 0 (none) to 1 (solid).
+    #[arg(long, default_value_t = 0.6, value_parser = parse_opacity)]
+    caption_opacity: f32,
+}
+
+/// How the storyboards of the chapters are assembled.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ChapterOutput {
+    /// A single storyboard, with a heading for each chapter.
+    Combined,
+    /// One storyboard per chapter, named `<output>-chapter-NN.<format>`.
+    Separate,
 }
 
 /// The main asynchronous function that orchestrates the application.
@@ -52,56 +264,632 @@ async fn main() {
 async fn run() -> Result<()> {
     // Parse the command-line arguments.
     let args = Args::parse();
+
+    // The project file commands need no API key.
+    match &args.command {
+        Some(Command::Export {
+            project_dir,
+            output_file,
+        }) => {
+            let scene_count = project_file::export_project(project_dir, output_file)?;
+            println!(
+                "{}",
+                format!(
+                    "Exported {} scenes to '{}'",
+                    scene_count,
+                    output_file.display()
+                )
+                .green()
+                .bold()
+            );
+            return Ok(());
+        }
+        Some(Command::Import {
+            project_file,
+            output,
+        }) => {
+            let storyboard_chapters = project_file::import_project(project_file)?;
+            return write_storyboard(storyboard_chapters, output);
+        }
+        None => {}
+    }
+
+    // --- Initialization ---
+    let retry = RetryPolicy::default().with_max_retries(args.retries);
+    let provider_config = config::get_provider_config()?
+        .with_retry_policy(retry)
+        .track_usage(env!("CARGO_PKG_NAME"), args.max_cost)
+        .cache_responses(args.cache);
+    let client = openai_client::OpenAIClient::new(&provider_config)?;
+    let style = config::load_style_profile(&args.style_profiles, &args.style)?;
+    info!("Using the '{}' visual style.", style.name);
+    let mut annotations = match &args.annotations {
+        Some(path) => annotations::Annotations::load(path)?,
+        None => annotations::Annotations::default(),
+    };
+    annotations.negative_prompt = args.negative_prompt.clone();
+    let reference_image = args
+        .style_reference
+        .as_deref()
+        .map(pipeline::style_reference::ReferenceImage::load)
+        .transpose()?;
+    let shared = SharedSettings {
+        client,
+        style,
+        annotations,
+        reference_image,
+    };
+
+    match &args.input_dir {
+        Some(input_dir) => run_batch(&args, &shared, input_dir).await,
+        None => {
+            let input_file = args
+                .input_file
+                .as_deref()
+                .expect("clap requires --input-file without a subcommand or --input-dir");
+            visualize(&args, &shared, input_file, &args.output, &args.project_dir).await?;
+            Ok(())
+        }
+    }
+}
+
+/// The settings shared by every narrative of a run.
+struct SharedSettings {
+    client: openai_client::OpenAIClient,
+    style: config::StyleProfile,
+    annotations: annotations::Annotations,
+    reference_image: Option<pipeline::style_reference::ReferenceImage>,
+}
+
+/// Runs the pipeline on one narrative and assembles its storyboard.
+///
+/// # Returns
+///
+/// The number of scenes in the storyboard (or, in a dry run, the plan).
+async fn visualize(
+    args: &Args,
+    shared: &SharedSettings,
+    input_file: &Path,
+    output: &OutputArgs,
+    project_dir: &Path,
+) -> Result<usize> {
+    let SharedSettings {
+        client,
+        style,
+        annotations,
+        reference_image,
+    } = shared;
     info!(
         "Starting narrative visualization for '{}'.",
-        args.input_file.display()
+        input_file.display()
     );
 
-    // --- Initialization ---
-    let api_key = config::get_api_key()?;
-    let client = openai_client::OpenAIClient::new(api_key);
-
     // Load the source narrative text from the input file.
-    let narrative_text = fs::read_to_string(&args.input_file)?;
+    let narrative_text = fs::read_to_string(input_file)?;
     if narrative_text.trim().is_empty() {
         return Err(error::Error::Pipeline(
             "Input file is empty.".to_string(),
         ));
     }
 
+    let split_on = args
+        .split_on
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| error::Error::Config(format!("Invalid --split-on regex: {}", e)))?;
+    let chapters = chapters::split_chapters(&narrative_text, split_on.as_ref());
+    if chapters.is_empty() {
+        return Err(error::Error::Pipeline(
+            "No chapter of the input file contains any text.".to_string(),
+        ));
+    }
+    if chapters.len() > 1 {
+        info!("Split the narrative into {} chapters.", chapters.len());
+    }
+
+    let scene_limits = pipeline::stage_1_scene_detection::SceneLimits {
+        max_scenes: args.max_scenes.map(|max_scenes| max_scenes as usize),
+        min_scene_length: args.min_scene_length,
+    };
+    let image_options = pipeline::stage_3_image_generation::ImageOptions {
+        size: args.size,
+        quality: args.quality,
+        orientation_hints: args.orientation_hints,
+        backend: args.image_backend,
+    };
+    let checkpoint = checkpoint::Checkpoint::open(
+        project_dir,
+        &checkpoint::RunSettings {
+            narrative_text: &narrative_text,
+            split_on: args.split_on.as_deref(),
+            scene_limits: scene_limits.label(),
+            style: &style.name,
+            character_sheets: !args.no_character_sheets,
+            annotations: if annotations.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&annotations)?)
+            },
+            style_reference: reference_image.as_ref().map(|image| image.data.as_slice()),
+            image_options: image_options.label(),
+        },
+        args.resume,
+    )?;
+    checkpoint.save_chapters(
+        &chapters
+            .iter()
+            .map(chapters::Chapter::heading)
+            .collect::<Vec<_>>(),
+    )?;
+
     // --- Execute Pipeline ---
-    // Stage 1: Decompose the narrative into distinct scenes.
-    let scenes = pipeline::stage_1_scene_detection::detect_scenes(&client, &narrative_text).await?;
+    // Each stage is skipped if its output was saved by a previous run.
+    let progress = progress::Progress::new(client.cost().clone());
+    let scenes_bar = progress.add_bar("Scenes", 0);
+
+    // Pre-pass: Fix the look of the recurring characters, across all chapters.
+    let characters = if args.no_character_sheets {
+        Vec::new()
+    } else {
+        scenes_bar.set_message("extracting character sheets");
+        match checkpoint.load_characters()? {
+            Some(characters) => {
+                info!("Reusing {} saved character sheets.", characters.len());
+                characters
+            }
+            None => {
+                let characters =
+                    pipeline::character_sheets::extract_characters(client, &narrative_text).await?;
+                checkpoint.save_characters(&characters)?;
+                characters
+            }
+        }
+    };
+
+    // Pre-pass: Describe the style of the reference image, if one is given.
+    let style_reference = match reference_image {
+        Some(reference_image) => {
+            scenes_bar.set_message("describing the style reference");
+            match checkpoint.load_style_reference()? {
+                Some(description) => {
+                    info!("Reusing the saved style reference description.");
+                    Some(description)
+                }
+                None => {
+                    let description =
+                        pipeline::style_reference::describe_style(client, reference_image).await?;
+                    checkpoint.save_style_reference(&description)?;
+                    Some(description)
+                }
+            }
+        }
+        None => None,
+    };
+    if let Some(description) = &style_reference {
+        info!("Matching the reference style: {}", description);
+    }
+
+    let prompt_context = pipeline::stage_2_prompt_generation::PromptContext {
+        style,
+        characters: &characters,
+        style_reference: style_reference.as_deref(),
+        annotations,
+    };
+
+    // Stages 1 and 2 run on each chapter on its own, so that book-length
+    // input does not have to fit in a single request.
+    let mut chapter_prompts = Vec::with_capacity(chapters.len());
+    for chapter in &chapters {
+        if let Some(title) = &chapter.title {
+            info!("Processing '{}'.", title);
+        }
+        let chapter_checkpoint = checkpoint.chapter(chapter.number);
+        scenes_bar.set_message(match &chapter.title {
+            Some(title) => format!("detecting scenes in '{}'", title),
+            None => "detecting scenes".to_string(),
+        });
+
+        // Stage 1: Decompose the chapter into distinct scenes.
+        let scenes = match chapter_checkpoint.load_scenes()? {
+            Some(scenes) => {
+                info!("Reusing {} saved scenes.", scenes.len());
+                scenes
+            }
+            None => {
+                let scenes = pipeline::stage_1_scene_detection::detect_scenes(
+                    client,
+                    &chapter.text,
+                    scene_limits,
+                )
+                .await?;
+                chapter_checkpoint.save_scenes(&scenes)?;
+                scenes
+            }
+        };
+        scenes_bar.inc_length(scenes.len() as u64);
+        scenes_bar.set_message("writing image prompts");
+
+        // Stage 2: Generate rich visual prompts for each scene.
+        let visual_prompts = match chapter_checkpoint.load_prompts()? {
+            Some(visual_prompts) => {
+                info!("Reusing {} saved visual prompts.", visual_prompts.len());
+                scenes_bar.inc(visual_prompts.len() as u64);
+                visual_prompts
+            }
+            None => {
+                let visual_prompts = pipeline::stage_2_prompt_generation::generate_visual_prompts(
+                    client,
+                    &scenes,
+                    &prompt_context,
+                    chapter.number,
+                    &scenes_bar,
+                )
+                .await?;
+                chapter_checkpoint.save_prompts(&visual_prompts)?;
+                visual_prompts
+            }
+        };
+        chapter_prompts.push(visual_prompts);
+    }
+    scenes_bar.finish_with_message("done");
+
+    let scene_counts = chapters
+        .iter()
+        .zip(&chapter_prompts)
+        .map(|(chapter, visual_prompts)| (chapter.number, visual_prompts.len()))
+        .collect::<Vec<_>>();
+    for annotation in annotations.unmatched(&scene_counts) {
+        warn!(
+            "The annotation of scene {}{} does not match any scene and is ignored.",
+            annotation.scene,
+            annotation
+                .chapter
+                .map(|chapter| format!(" of chapter {}", chapter))
+                .unwrap_or_default()
+        );
+    }
+
+    // Let the user revise the scenes before the images are paid for.
+    if args.review {
+        for (chapter, visual_prompts) in chapters.iter().zip(&mut chapter_prompts) {
+            review::review_prompts(
+                visual_prompts,
+                chapter.title.as_deref(),
+                &checkpoint.chapter(chapter.number),
+            )?;
+        }
+    }
 
-    // Stage 2: Generate rich visual prompts for each scene.
-    let visual_prompts =
-        pipeline::stage_2_prompt_generation::generate_visual_prompts(&client, &scenes).await?;
+    // Screen the prompts before they are submitted to DALL-E, which rejects
+    // prompts that violate its content policy. Local backends are not screened.
+    if args.image_backend == openai_client::ImageBackendKind::DallE {
+        for (chapter, visual_prompts) in chapters.iter().zip(&mut chapter_prompts) {
+            pipeline::moderation::moderate_prompts(
+                client,
+                visual_prompts,
+                args.moderation,
+                chapter.title.as_deref(),
+                &checkpoint.chapter(chapter.number),
+            )
+            .await?;
+        }
+    }
+
+    let scene_count = chapter_prompts.iter().map(Vec::len).sum::<usize>();
+
+    // In a dry run, the plan is printed instead of paying for the images.
+    // The scenes and prompts are saved, so `--resume` continues from the plan.
+    if args.dry_run {
+        let mut plans = Vec::with_capacity(chapters.len());
+        for (chapter, visual_prompts) in chapters.iter().zip(&chapter_prompts) {
+            let chapter_checkpoint = checkpoint.chapter(chapter.number);
+            let saved = (0..visual_prompts.len())
+                .map(|index| Ok(chapter_checkpoint.load_image(index)?.is_some()))
+                .collect::<Result<Vec<_>>>()?;
+            let narrated = (0..visual_prompts.len())
+                .map(|index| {
+                    Ok(chapter_checkpoint
+                        .load_narration(index, args.voice)?
+                        .is_some())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            plans.push(plan::ChapterPlan {
+                title: chapter.title.as_deref(),
+                prompts: visual_prompts,
+                saved,
+                narrated,
+            });
+        }
+        plan::print_plan(&plans, image_options, args.narration);
+        println!("\n{}", progress.summary().bold());
+        return Ok(scene_count);
+    }
 
     // Stage 3: Synthesize an image for each visual prompt.
-    let storyboard_frames =
-        pipeline::stage_3_image_generation::generate_images(&client, &visual_prompts).await?;
+    let retry = RetryPolicy::default().with_max_retries(args.retries);
+    let image_backend =
+        openai_client::ImageBackend::new(args.image_backend, client, &args.sd_url, retry);
+    let images_bar = progress.add_bar("Images", scene_count as u64);
+    let narration_bar = args
+        .narration
+        .then(|| progress.add_bar("Audio", scene_count as u64));
+    let mut storyboard_chapters = Vec::with_capacity(chapters.len());
+    for (chapter, visual_prompts) in chapters.into_iter().zip(&chapter_prompts) {
+        let chapter_checkpoint = checkpoint.chapter(chapter.number);
+        let mut frames = pipeline::stage_3_image_generation::generate_images(
+            &image_backend,
+            visual_prompts,
+            args.concurrency as usize,
+            image_options,
+            &chapter_checkpoint,
+            &images_bar,
+        )
+        .await?;
+
+        // Optional stage: Narrate the original text of each scene.
+        if let Some(narration_bar) = &narration_bar {
+            let tracks = pipeline::narration::generate_narration(
+                client,
+                visual_prompts,
+                args.voice,
+                args.concurrency as usize,
+                &chapter_checkpoint,
+                narration_bar,
+            )
+            .await?;
+            for (frame, track) in frames.iter_mut().zip(tracks) {
+                frame.narration = Some(track);
+            }
+        }
+        storyboard_chapters.push(output_assembler::StoryboardChapter {
+            number: chapter.number,
+            title: chapter.title,
+            frames,
+        });
+    }
+    images_bar.finish_with_message("done");
+    let failed_images = storyboard_chapters
+        .iter()
+        .flat_map(|chapter| &chapter.frames)
+        .filter(|frame| frame.error.is_some())
+        .count();
+    if failed_images > 0 {
+        warn!(
+            "{} images could not be generated and were replaced by placeholders; rerun with --resume to retry them.",
+            failed_images
+        );
+    }
+    if let Some(narration_bar) = &narration_bar {
+        narration_bar.finish_with_message("done");
+    }
 
     // --- Assemble Output ---
-    // Combine the text and generated images into a final HTML storyboard.
-    output_assembler::assemble_storyboard_html(&storyboard_frames, &args.output_file)?;
+    // Combine the text and generated images into the final storyboard.
+    write_storyboard(storyboard_chapters, output)?;
+
+    println!("\n{}", progress.summary().bold());
+
+    Ok(scene_count)
+}
+
+/// Runs the pipeline on every narrative in `input_dir`, saving each
+/// storyboard and its project state in a folder of its own, and prints a
+/// summary of the batch. A failed narrative does not stop the others.
+///
+/// # Errors
+///
+/// Returns `Error::Config` if the directory holds no narratives, and
+/// `Error::Pipeline` if any of them failed.
+async fn run_batch(args: &Args, shared: &SharedSettings, input_dir: &Path) -> Result<()> {
+    let mut input_files = Vec::new();
+    for entry in fs::read_dir(input_dir)? {
+        let path = entry?.path();
+        let is_narrative = path
+            .extension()
+            .is_some_and(|extension| extension == "txt" || extension == "md");
+        if is_narrative && path.is_file() {
+            input_files.push(path);
+        }
+    }
+    input_files.sort();
+    if input_files.is_empty() {
+        return Err(error::Error::Config(format!(
+            "No .txt or .md narratives were found in '{}'.",
+            input_dir.display()
+        )));
+    }
+    info!(
+        "Generating storyboards for {} narratives in '{}'.",
+        input_files.len(),
+        input_dir.display()
+    );
+
+    let started = Instant::now();
+    let mut results = Vec::with_capacity(input_files.len());
+    for (index, input_file) in input_files.iter().enumerate() {
+        let name = input_file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        println!(
+            "\n{}",
+            format!("[{}/{}] {}", index + 1, input_files.len(), name).bold()
+        );
+
+        let output = OutputArgs {
+            output_file: Some(
+                args.output_dir
+                    .join(&name)
+                    .join(format!("storyboard.{}", args.output.format.extension())),
+            ),
+            ..args.output.clone()
+        };
+        let project_dir = args.project_dir.join(&name);
+        let cost_at_start = shared.client.cost().total_usd();
+        let result = visualize(args, shared, input_file, &output, &project_dir).await;
+        if let Err(e) = &result {
+            error!("{} {}", "Error:".red().bold(), e.to_string().red());
+        }
+        let cost = shared.client.cost().total_usd() - cost_at_start;
+        results.push((name, output.output_file, result, cost));
+    }
+
+    println!("\n{}", "Batch summary".bold());
+    let mut failures = 0;
+    for (name, output_file, result, cost) in &results {
+        match result {
+            Ok(scene_count) if args.dry_run => println!(
+                "  {} {}: {} scenes planned, ${:.2}",
+                "✓".green(),
+                name,
+                scene_count,
+                cost
+            ),
+            Ok(scene_count) => println!(
+                "  {} {}: {} scenes, ${:.2}, {}",
+                "✓".green(),
+                name,
+                scene_count,
+                cost,
+                output_file
+                    .as_deref()
+                    .map(|output_file| output_file.display().to_string())
+                    .unwrap_or_default()
+            ),
+            Err(e) => {
+                failures += 1;
+                println!("  {} {}: {}, ${:.2}", "✗".red(), name, e, cost);
+            }
+        }
+    }
+    println!(
+        "{}",
+        format!(
+            "{} succeeded, {} failed. Finished in {:.1}s, total API cost ${:.2}",
+            results.len() - failures,
+            failures,
+            started.elapsed().as_secs_f64(),
+            shared.client.cost().total_usd()
+        )
+        .bold()
+    );
+
+    if failures > 0 {
+        return Err(error::Error::Pipeline(format!(
+            "{} of {} narratives failed.",
+            failures,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Assembles the storyboard of the given chapters into one output file, or
+/// one per chapter.
+fn write_storyboard(
+    mut storyboard_chapters: Vec<output_assembler::StoryboardChapter>,
+    output: &OutputArgs,
+) -> Result<()> {
+    if let Some(source) = output.captions {
+        let renderer = captions::CaptionRenderer::new(captions::CaptionOptions {
+            source,
+            font: output.caption_font.clone(),
+            font_size: output.caption_font_size,
+            position: output.caption_position,
+            box_opacity: output.caption_opacity,
+        })?;
+        renderer.caption_chapters(&mut storyboard_chapters)?;
+    }
+
+    let output_file = match &output.output_file {
+        Some(output_file) => output_file.clone(),
+        None => PathBuf::from(format!("output/storyboard.{}", output.format.extension())),
+    };
+    match output.chapter_output {
+        ChapterOutput::Combined => assemble(&storyboard_chapters, &output_file, output)?,
+        ChapterOutput::Separate => {
+            for storyboard_chapter in storyboard_chapters {
+                let chapter_file = match storyboard_chapter.number {
+                    Some(number) => chapter_output_file(&output_file, number),
+                    None => output_file.clone(),
+                };
+                assemble(&[storyboard_chapter], &chapter_file, output)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Assembles the storyboard of the given chapters into `output_file`.
+fn assemble(
+    storyboard_chapters: &[output_assembler::StoryboardChapter],
+    output_file: &Path,
+    output: &OutputArgs,
+) -> Result<()> {
+    output_assembler::assemble_storyboard(
+        storyboard_chapters,
+        output_file,
+        output.format,
+        output.image_mode,
+    )?;
 
     info!(
         "{}",
         format!(
             "Successfully generated storyboard at '{}'",
-            args.output_file.display()
+            output_file.display()
         )
         .green()
         .bold()
     );
-
     Ok(())
 }
 
+/// Parses an opacity between 0 and 1.
+fn parse_opacity(value: &str) -> std::result::Result<f32, String> {
+    let opacity: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if (0.0..=1.0).contains(&opacity) {
+        Ok(opacity)
+    } else {
+        Err("the opacity must be between 0 and 1".to_string())
+    }
+}
+
+/// The output file of a separately assembled chapter, e.g.
+/// `storyboard-chapter-02.html` for `storyboard.html`.
+fn chapter_output_file(output_file: &Path, number: usize) -> PathBuf {
+    let stem = output_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "storyboard".to_string());
+    let file_name = match output_file.extension() {
+        Some(extension) => format!(
+            "{}-chapter-{:02}.{}",
+            stem,
+            number,
+            extension.to_string_lossy()
+        ),
+        None => format!("{}-chapter-{:02}", stem, number),
+    };
+    output_file.with_file_name(file_name)
+}
+
 /// Initializes the global logger for the application.
+///
+/// When stderr is a terminal, the progress bars replace the informational
+/// log lines, so only warnings and errors are logged unless `RUST_LOG` says
+/// otherwise.
 fn init_logger() {
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let default_level = if std::io::stderr().is_terminal() {
+        "warn"
+    } else {
+        "info"
+    };
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
 
     fmt::Subscriber::builder()
         .with_env_filter(filter)
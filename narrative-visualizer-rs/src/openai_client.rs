@@ -1,91 +1,361 @@
 //! src/openai_client.rs
 //!
 //! This module provides a dedicated, multi-modal client for interacting with
-//! the OpenAI API. It supports both text generation via the Chat Completions
-//! endpoint and image generation via the DALL-E 3 endpoint.
+//! the OpenAI API. It supports text generation via the Chat Completions
+//! endpoint (including descriptions of images), image generation via the
+//! DALL-E 3 endpoint, speech synthesis via the text-to-speech endpoint, and
+//! content screening via the moderation endpoint. The requests are sent with
+//! the `Client` of the shared `ai-core` crate.
+//! Text generation can be moved to another provider with `AI_PROVIDER`; the
+//! other endpoints are only offered by OpenAI. The cost of every
+//! request is added up as it completes, for the live cost display, and
+//! recorded in the usage ledger when the provider settings track usage.
+//!
+//! Images can also be generated by a local Stable Diffusion server instead of
+//! DALL-E, through the `ImageBackend` abstraction, which stage 3 uses.
 
 use crate::error::{Error, Result};
+use ai_core::{
+    count_tokens, ChatProvider, ChatRequest, ChatResponse, Middleware, ProviderConfig,
+    ProviderKind, RateLimits, RetryPolicy, Usage, UsageTracker,
+};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tracing::{info, instrument};
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1";
 const TEXT_MODEL: &str = "gpt-4o";
 const IMAGE_MODEL: &str = "dall-e-3";
+const SPEECH_MODEL: &str = "tts-1";
+const MODERATION_MODEL: &str = "omni-moderation-latest";
+
+/// The price in US dollars of one million input tokens of the text model.
+const TEXT_INPUT_PRICE_PER_MILLION_USD: f64 = 2.50;
+/// The price in US dollars of one million output tokens of the text model.
+const TEXT_OUTPUT_PRICE_PER_MILLION_USD: f64 = 10.00;
+/// The price in US dollars of one million characters of synthesized speech.
+const SPEECH_PRICE_PER_MILLION_CHARS_USD: f64 = 15.00;
+
+/// The dimensions of a generated image, as supported by DALL-E 3.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageSize {
+    /// A square image.
+    #[value(name = "1024x1024")]
+    Square,
+    /// A wide image, for landscapes and establishing shots.
+    #[value(name = "1792x1024")]
+    Landscape,
+    /// A tall image, for portraits and vertical compositions.
+    #[value(name = "1024x1792")]
+    Portrait,
+}
+
+impl ImageSize {
+    /// The size as sent to the API.
+    pub fn dimensions(&self) -> &'static str {
+        match self {
+            ImageSize::Square => "1024x1024",
+            ImageSize::Landscape => "1792x1024",
+            ImageSize::Portrait => "1024x1792",
+        }
+    }
+}
+
+/// The quality of a generated image. `hd` has finer detail and costs more.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageQuality {
+    Standard,
+    Hd,
+}
+
+impl ImageQuality {
+    /// The quality as sent to the API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageQuality::Standard => "standard",
+            ImageQuality::Hd => "hd",
+        }
+    }
+}
+
+/// The voice narration is synthesized in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Voice {
+    Alloy,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Shimmer,
+}
+
+impl Voice {
+    /// The voice as sent to the API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Voice::Alloy => "alloy",
+            Voice::Echo => "echo",
+            Voice::Fable => "fable",
+            Voice::Onyx => "onyx",
+            Voice::Nova => "nova",
+            Voice::Shimmer => "shimmer",
+        }
+    }
+}
+
+/// The verdict of the moderation endpoint on a text.
+#[derive(Debug, Clone)]
+pub struct Moderation {
+    pub flagged: bool,
+    /// The content policy categories the text was flagged for, e.g. `violence/graphic`.
+    pub categories: Vec<String>,
+}
+
+/// The price in US dollars of synthesizing speech for `text`.
+pub fn speech_price_usd(text: &str) -> f64 {
+    text.chars().count() as f64 * SPEECH_PRICE_PER_MILLION_CHARS_USD / 1_000_000.0
+}
+
+/// The number of tokens in an image prompt for DALL-E 3.
+pub fn image_prompt_tokens(prompt: &str) -> usize {
+    count_tokens(IMAGE_MODEL, prompt)
+}
+
+/// The price in US dollars of one DALL-E 3 image of the given size and quality.
+pub fn image_price_usd(size: ImageSize, quality: ImageQuality) -> f64 {
+    match (quality, size) {
+        (ImageQuality::Standard, ImageSize::Square) => 0.040,
+        (ImageQuality::Standard, _) => 0.080,
+        (ImageQuality::Hd, ImageSize::Square) => 0.080,
+        (ImageQuality::Hd, _) => 0.120,
+    }
+}
 
-/// A client for making requests to the OpenAI API.
+/// The service that generates the images.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageBackendKind {
+    /// DALL-E 3, through the OpenAI API.
+    #[value(name = "dall-e")]
+    DallE,
+    /// A local Stable Diffusion WebUI (AUTOMATIC1111, Forge, or SD.Next).
+    StableDiffusion,
+}
+
+impl ImageBackendKind {
+    /// The name of the backend, as given on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageBackendKind::DallE => "dall-e",
+            ImageBackendKind::StableDiffusion => "stable-diffusion",
+        }
+    }
+
+    /// The price in US dollars of one image of the given size and quality.
+    /// Local generation is free.
+    pub fn image_price_usd(&self, size: ImageSize, quality: ImageQuality) -> f64 {
+        match self {
+            ImageBackendKind::DallE => image_price_usd(size, quality),
+            ImageBackendKind::StableDiffusion => 0.0,
+        }
+    }
+}
+
+/// The price in US dollars of a text completion with the given token usage.
+fn completion_price_usd(usage: &Usage) -> f64 {
+    (usage.prompt_tokens as f64 * TEXT_INPUT_PRICE_PER_MILLION_USD
+        + usage.completion_tokens as f64 * TEXT_OUTPUT_PRICE_PER_MILLION_USD)
+        / 1_000_000.0
+}
+
+/// The accumulated cost of the requests made by a client and its clones.
+#[derive(Clone, Debug, Default)]
+pub struct CostTracker {
+    /// The cost in millionths of a US dollar, so it can be updated atomically.
+    micro_usd: Arc<AtomicU64>,
+}
+
+impl CostTracker {
+    fn add(&self, usd: f64) {
+        self.micro_usd
+            .fetch_add((usd * 1_000_000.0).round() as u64, Ordering::Relaxed);
+    }
+
+    /// The total cost so far, in US dollars.
+    pub fn total_usd(&self) -> f64 {
+        self.micro_usd.load(Ordering::Relaxed) as f64 / 1_000_000.0
+    }
+}
+
+/// A client for making requests to the OpenAI API, with the `Client` of the
+/// shared `ai-core` crate, and text completions to the configured provider.
 #[derive(Clone)]
 pub struct OpenAIClient {
-    http_client: reqwest::Client,
-    api_key: String,
+    /// The OpenAI API, if `OPENAI_API_KEY` is set.
+    client: Option<ai_core::Client>,
+    provider: Arc<dyn ChatProvider>,
+    /// The model text completions are requested from.
+    text_model: String,
+    /// Whether text completions are billed at OpenAI's prices. Other
+    /// providers are not included in the cost display.
+    text_billed: bool,
+    cost: CostTracker,
+    /// The usage ledger and maximum cost of the run, if tracked. Text
+    /// completions are recorded by the provider; images, speech, and image
+    /// descriptions are recorded here.
+    usage: Option<Arc<UsageTracker>>,
 }
 
 impl OpenAIClient {
-    /// Creates a new instance of the `OpenAIClient`.
-    pub fn new(api_key: String) -> Self {
-        Self {
-            http_client: reqwest::Client::new(),
-            api_key,
-        }
+    /// Creates a new instance of the `OpenAIClient` from the provider settings.
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            client: config.openai().ok(),
+            provider: config.chat_provider()?,
+            text_model: config.chat_model(TEXT_MODEL),
+            text_billed: matches!(config.provider, ProviderKind::OpenAI | ProviderKind::Azure),
+            cost: CostTracker::default(),
+            usage: config.usage.clone(),
+        })
+    }
+
+    /// The OpenAI API, for the endpoints no other provider offers.
+    fn openai(&self) -> Result<&ai_core::Client> {
+        self.client.as_ref().ok_or_else(|| {
+            Error::Config(
+                "The OPENAI_API_KEY environment variable must be set for images, speech, and moderation."
+                    .to_string(),
+            )
+        })
+    }
+
+    /// The accumulated cost of the requests made by this client and its clones.
+    pub fn cost(&self) -> &CostTracker {
+        &self.cost
     }
 
     /// Sends a text-based request to the Chat Completions API.
     #[instrument(skip(self, system_prompt, user_prompt))]
     pub async fn get_completion(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
-        info!("Requesting text completion from OpenAI API.");
+        info!("Requesting text completion from {}.", self.text_model);
 
+        let request = ChatRequest::from_prompts(&self.text_model, system_prompt, user_prompt);
+        let response = self.provider.chat(&request).await?;
+        if self.text_billed {
+            self.add_completion_cost(&response);
+        }
+        first_choice_text(response)
+    }
+
+    /// Sends a text-based request like `get_completion`, with the JSON schema
+    /// of `T`, and reads the reply into a `T`.
+    #[instrument(skip(self, system_prompt, user_prompt))]
+    pub async fn get_structured_completion<T: DeserializeOwned + JsonSchema>(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<T> {
+        info!("Requesting structured completion from {}.", self.text_model);
+
+        let request = ChatRequest::from_prompts(&self.text_model, system_prompt, user_prompt)
+            .json_schema::<T>();
+        let response = self.provider.chat(&request).await?;
+        if self.text_billed {
+            self.add_completion_cost(&response);
+        }
+        Ok(ai_core::parse_structured(&first_choice_text(response)?)?)
+    }
+
+    /// Sends a request with an image to the Chat Completions API, for the
+    /// model to describe it as `user_prompt` asks.
+    #[instrument(skip(self, system_prompt, user_prompt, image_data))]
+    pub async fn describe_image(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        image_data: &[u8],
+        media_type: &str,
+    ) -> Result<String> {
+        info!("Requesting image description from OpenAI API.");
+
+        let image_url = format!("data:{};base64,{}", media_type, STANDARD.encode(image_data));
         let messages = vec![
-            Message { role: "system".to_string(), content: system_prompt.to_string() },
-            Message { role: "user".to_string(), content: user_prompt.to_string() },
+            VisionMessage {
+                role: "system".to_string(),
+                content: vec![ContentPart::Text {
+                    text: system_prompt.to_string(),
+                }],
+            },
+            VisionMessage {
+                role: "user".to_string(),
+                content: vec![
+                    ContentPart::Text {
+                        text: user_prompt.to_string(),
+                    },
+                    ContentPart::ImageUrl {
+                        image_url: ImageUrl { url: image_url },
+                    },
+                ],
+            },
         ];
 
-        let body = ChatCompletionRequest {
+        let body = VisionCompletionRequest {
             model: TEXT_MODEL.to_string(),
             messages,
         };
 
-        let response: ChatCompletionResponse = self
-            .http_client
-            .post(format!("{}/chat/completions", OPENAI_API_URL))
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
+        let response: ChatResponse = self.openai()?.post_json("chat/completions", &body).await?;
+        self.add_completion_cost(&response);
+        if let (Some(tracker), Some(usage)) = (&self.usage, &response.usage) {
+            tracker.record(TEXT_MODEL, usage);
+        }
+        first_choice_text(response)
+    }
 
-        if let Some(choice) = response.choices.into_iter().next() {
-            info!("Successfully received text completion.");
-            Ok(choice.message.content)
-        } else {
-            Err(Error::OpenAI("API response did not contain any text choices.".to_string()))
+    /// Adds the cost of a text completion, from its token usage.
+    fn add_completion_cost(&self, response: &ChatResponse) {
+        if let Some(usage) = &response.usage {
+            self.cost.add(completion_price_usd(usage));
+        }
+    }
+
+    /// Adds the cost of a request priced per image or character rather than
+    /// per token, and records it in the usage ledger.
+    fn add_priced_cost(&self, model: &str, usd: f64) {
+        self.cost.add(usd);
+        if let Some(tracker) = &self.usage {
+            tracker.record_cost(model, &Usage::default(), usd);
         }
     }
 
     /// Sends a request to the Image Generation API to synthesize an image.
     #[instrument(skip(self, prompt))]
-    pub async fn generate_image(&self, prompt: &str) -> Result<Vec<u8>> {
+    pub async fn generate_image(
+        &self,
+        prompt: &str,
+        size: ImageSize,
+        quality: ImageQuality,
+    ) -> Result<Vec<u8>> {
         info!("Requesting image generation from OpenAI API.");
 
         let body = ImageGenerationRequest {
             model: IMAGE_MODEL.to_string(),
             prompt: prompt.to_string(),
             n: 1,
-            size: "1024x1024".to_string(),
+            size: size.dimensions().to_string(),
+            quality: quality.as_str().to_string(),
             response_format: "b64_json".to_string(),
         };
 
         let response: ImageGenerationResponse = self
-            .http_client
-            .post(format!("{}/images/generations", OPENAI_API_URL))
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
+            .openai()?
+            .post_json("images/generations", &body)
             .await?;
+        self.add_priced_cost(IMAGE_MODEL, image_price_usd(size, quality));
 
         if let Some(image_data) = response.data.into_iter().next() {
             info!("Successfully received image data.");
@@ -96,30 +366,240 @@ impl OpenAIClient {
             Err(Error::OpenAI("API response did not contain any image data.".to_string()))
         }
     }
+
+    /// Sends a request to the Moderation API to check `text` against the
+    /// content policy. Moderation is free of charge.
+    #[instrument(skip(self, text))]
+    pub async fn moderate(&self, text: &str) -> Result<Moderation> {
+        info!("Requesting moderation from OpenAI API.");
+
+        let body = ModerationRequest {
+            model: MODERATION_MODEL.to_string(),
+            input: text.to_string(),
+        };
+
+        let response: ModerationResponse = self.openai()?.post_json("moderations", &body).await?;
+
+        let result = response.results.into_iter().next().ok_or_else(|| {
+            Error::OpenAI("API response did not contain any moderation results.".to_string())
+        })?;
+        Ok(Moderation {
+            flagged: result.flagged,
+            categories: result
+                .categories
+                .into_iter()
+                .filter(|&(_, flagged)| flagged)
+                .map(|(category, _)| category)
+                .collect(),
+        })
+    }
+
+    /// Sends a request to the Speech API to read `text` aloud.
+    ///
+    /// # Returns
+    ///
+    /// The synthesized speech as MP3 data.
+    #[instrument(skip(self, text))]
+    pub async fn generate_speech(&self, text: &str, voice: Voice) -> Result<Vec<u8>> {
+        info!("Requesting speech synthesis from OpenAI API.");
+
+        let body = SpeechRequest {
+            model: SPEECH_MODEL.to_string(),
+            input: text.to_string(),
+            voice: voice.as_str().to_string(),
+            response_format: "mp3".to_string(),
+        };
+
+        let audio_data = self.openai()?.post_bytes("audio/speech", &body).await?;
+        self.add_priced_cost(SPEECH_MODEL, speech_price_usd(text));
+
+        if audio_data.is_empty() {
+            return Err(Error::OpenAI(
+                "API response did not contain any audio data.".to_string(),
+            ));
+        }
+        info!("Successfully received audio data.");
+        Ok(audio_data)
+    }
+}
+
+/// Returns the text of the first choice of a chat completion.
+fn first_choice_text(response: ChatResponse) -> Result<String> {
+    if let Some(choice) = response.choices.into_iter().next() {
+        info!("Successfully received text completion.");
+        Ok(choice.message.content)
+    } else {
+        Err(Error::OpenAI("API response did not contain any text choices.".to_string()))
+    }
+}
+
+/// What Stable Diffusion images are steered away from. DALL-E has no
+/// negative prompt.
+const STABLE_DIFFUSION_NEGATIVE_PROMPT: &str =
+    "blurry, low quality, deformed, disfigured, extra limbs, text, watermark, signature";
+
+/// A client for the txt2img endpoint of a local Stable Diffusion WebUI,
+/// started with the `--api` flag, with the `Client` of the shared `ai-core`
+/// crate.
+#[derive(Clone)]
+pub struct StableDiffusionClient {
+    client: ai_core::Client,
+    base_url: String,
+}
+
+impl StableDiffusionClient {
+    /// Creates a client for the WebUI at `base_url`, e.g. `http://127.0.0.1:7860`,
+    /// that retries failed requests with `retry`.
+    pub fn new(base_url: &str, retry: RetryPolicy) -> Self {
+        Self {
+            client: ai_core::Client::local(base_url)
+                .with_middleware(Middleware::new(retry, RateLimits::default())),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Sends a request to the txt2img endpoint to synthesize an image.
+    ///
+    /// DALL-E sizes are mapped to the closest resolutions Stable Diffusion XL
+    /// is trained on, and `hd` quality to more sampling steps. The
+    /// `negative_prompt` is added to the default one.
+    #[instrument(skip(self, prompt, negative_prompt))]
+    pub async fn generate_image(
+        &self,
+        prompt: &str,
+        negative_prompt: Option<&str>,
+        size: ImageSize,
+        quality: ImageQuality,
+    ) -> Result<Vec<u8>> {
+        info!("Requesting image generation from Stable Diffusion.");
+
+        let (width, height) = match size {
+            ImageSize::Square => (1024, 1024),
+            ImageSize::Landscape => (1344, 768),
+            ImageSize::Portrait => (768, 1344),
+        };
+        let body = Txt2ImgRequest {
+            prompt: prompt.to_string(),
+            negative_prompt: match negative_prompt {
+                Some(negative_prompt) => {
+                    format!("{}, {}", negative_prompt, STABLE_DIFFUSION_NEGATIVE_PROMPT)
+                }
+                None => STABLE_DIFFUSION_NEGATIVE_PROMPT.to_string(),
+            },
+            width,
+            height,
+            steps: match quality {
+                ImageQuality::Standard => 25,
+                ImageQuality::Hd => 40,
+            },
+            batch_size: 1,
+        };
+
+        let response: Txt2ImgResponse = self
+            .client
+            .post_json("sdapi/v1/txt2img", &body)
+            .await
+            .map_err(|e| match e {
+                ai_core::Error::Reqwest(e) => Error::StableDiffusion(format!(
+                    "Could not reach the Stable Diffusion WebUI at '{}' ({}). Is it running with --api?",
+                    self.base_url, e
+                )),
+                e => e.into(),
+            })?;
+
+        if let Some(image_base64) = response.images.into_iter().next() {
+            info!("Successfully received image data.");
+            Ok(STANDARD.decode(image_base64)?)
+        } else {
+            Err(Error::StableDiffusion(
+                "API response did not contain any image data.".to_string(),
+            ))
+        }
+    }
+}
+
+/// The service stage 3 generates images with.
+#[derive(Clone)]
+pub enum ImageBackend {
+    DallE(OpenAIClient),
+    StableDiffusion(StableDiffusionClient),
+}
+
+impl ImageBackend {
+    /// Creates a backend of the given kind. DALL-E images are requested with
+    /// `client`, and Stable Diffusion images from the WebUI at `sd_url`,
+    /// retried with `retry` like the client's requests.
+    pub fn new(
+        kind: ImageBackendKind,
+        client: &OpenAIClient,
+        sd_url: &str,
+        retry: RetryPolicy,
+    ) -> Self {
+        match kind {
+            ImageBackendKind::DallE => ImageBackend::DallE(client.clone()),
+            ImageBackendKind::StableDiffusion => {
+                ImageBackend::StableDiffusion(StableDiffusionClient::new(sd_url, retry))
+            }
+        }
+    }
+
+    /// Synthesizes an image for `prompt` with the backend, avoiding what
+    /// `negative_prompt` lists.
+    ///
+    /// DALL-E has no negative prompt parameter, so the negative prompt is
+    /// appended to the prompt as an instruction instead.
+    pub async fn generate_image(
+        &self,
+        prompt: &str,
+        negative_prompt: Option<&str>,
+        size: ImageSize,
+        quality: ImageQuality,
+    ) -> Result<Vec<u8>> {
+        match self {
+            ImageBackend::DallE(client) => {
+                let prompt = match negative_prompt {
+                    Some(negative_prompt) => format!(
+                        "{}\n\nDo not include: {}.",
+                        prompt.trim_end(),
+                        negative_prompt
+                    ),
+                    None => prompt.to_string(),
+                };
+                client.generate_image(&prompt, size, quality).await
+            }
+            ImageBackend::StableDiffusion(client) => {
+                client
+                    .generate_image(prompt, negative_prompt, size, quality)
+                    .await
+            }
+        }
+    }
 }
 
 //========= API Data Structures =========//
 
 #[derive(Serialize)]
-struct ChatCompletionRequest {
+struct VisionCompletionRequest {
     model: String,
-    messages: Vec<Message>,
+    messages: Vec<VisionMessage>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Message {
+#[derive(Serialize)]
+struct VisionMessage {
     role: String,
-    content: String,
+    content: Vec<ContentPart>,
 }
 
-#[derive(Deserialize)]
-struct ChatCompletionResponse {
-    choices: Vec<Choice>,
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
 }
 
-#[derive(Deserialize)]
-struct Choice {
-    message: Message,
+#[derive(Serialize)]
+struct ImageUrl {
+    url: String,
 }
 
 #[derive(Serialize)]
@@ -128,6 +608,7 @@ struct ImageGenerationRequest {
     prompt: String,
     n: u32,
     size: String,
+    quality: String,
     response_format: String,
 }
 
@@ -140,3 +621,43 @@ struct ImageGenerationResponse {
 struct ImageData {
     b64_json: String,
 }
+
+#[derive(Serialize)]
+struct SpeechRequest {
+    model: String,
+    input: String,
+    voice: String,
+    response_format: String,
+}
+
+#[derive(Serialize)]
+struct ModerationRequest {
+    model: String,
+    input: String,
+}
+
+#[derive(Deserialize)]
+struct ModerationResponse {
+    results: Vec<ModerationResultData>,
+}
+
+#[derive(Deserialize)]
+struct ModerationResultData {
+    flagged: bool,
+    categories: BTreeMap<String, bool>,
+}
+
+#[derive(Serialize)]
+struct Txt2ImgRequest {
+    prompt: String,
+    negative_prompt: String,
+    width: u32,
+    height: u32,
+    steps: u32,
+    batch_size: u32,
+}
+
+#[derive(Deserialize)]
+struct Txt2ImgResponse {
+    images: Vec<String>,
+}
@@ -0,0 +1,253 @@
+//! src/output_assembler/epub.rs
+//!
+//! Assembles the storyboard into an EPUB 3 e-book, for reading on e-readers.
+//! Each scene becomes its own page, with its image, description, and text,
+//! and the images are packaged inside the book. Chapters are listed in the
+//! table of contents with their scenes nested below them.
+
+use super::{image_file_name, StoryboardChapter, STORYBOARD_TITLE};
+use crate::error::Result;
+use crate::pipeline::stage_3_image_generation::StoryboardFrame;
+use chrono::Utc;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Assembles a storyboard from its chapters and saves it as an EPUB file.
+pub fn assemble_storyboard_epub(chapters: &[StoryboardChapter], output_path: &Path) -> Result<()> {
+    info!(
+        "Assembling final storyboard EPUB at '{}'...",
+        output_path.display()
+    );
+
+    if let Some(parent_dir) = output_path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+
+    let mut zip = ZipWriter::new(File::create(output_path)?);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype must be the first entry of the archive, and uncompressed.
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(build_package_document(chapters).as_bytes())?;
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(build_navigation_document(chapters).as_bytes())?;
+
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(STYLESHEET.as_bytes())?;
+
+    for chapter in chapters {
+        for (index, frame) in chapter.frames.iter().enumerate() {
+            zip.start_file(
+                format!("OEBPS/{}", page_file_name(chapter.number, index)),
+                deflated,
+            )?;
+            zip.write_all(build_scene_page(chapter, index, frame).as_bytes())?;
+
+            // PNG data is already compressed.
+            zip.start_file(
+                format!("OEBPS/images/{}", image_file_name(chapter.number, index)),
+                SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+            )?;
+            zip.write_all(&frame.image_data)?;
+        }
+    }
+
+    zip.finish()?;
+
+    info!("Successfully assembled and saved storyboard.");
+    Ok(())
+}
+
+/// The file name of the page of the scene at `index` of a chapter.
+fn page_file_name(chapter_number: Option<usize>, index: usize) -> String {
+    image_file_name(chapter_number, index).replace(".png", ".xhtml")
+}
+
+/// The manifest ID of the page of the scene at `index` of a chapter.
+fn page_id(chapter_number: Option<usize>, index: usize) -> String {
+    image_file_name(chapter_number, index).replace(".png", "")
+}
+
+/// Builds the OPF package document, listing every file and the reading order.
+fn build_package_document(chapters: &[StoryboardChapter]) -> String {
+    let now = Utc::now();
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    for chapter in chapters {
+        for index in 0..chapter.frames.len() {
+            let id = page_id(chapter.number, index);
+            manifest.push_str(&format!(
+                "    <item id=\"{0}\" href=\"{1}\" media-type=\"application/xhtml+xml\"/>\n    <item id=\"{0}-image\" href=\"images/{2}\" media-type=\"image/png\"/>\n",
+                id,
+                page_file_name(chapter.number, index),
+                image_file_name(chapter.number, index)
+            ));
+            spine.push_str(&format!("    <itemref idref=\"{}\"/>\n", id));
+        }
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:narrative-visualizer-rs:{}</dc:identifier>
+    <dc:title>{}</dc:title>
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+{}  </manifest>
+  <spine>
+{}  </spine>
+</package>
+"#,
+        now.timestamp(),
+        escape_xml(STORYBOARD_TITLE),
+        now.format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest,
+        spine
+    )
+}
+
+/// Builds the navigation document, the book's table of contents.
+fn build_navigation_document(chapters: &[StoryboardChapter]) -> String {
+    let scene_entries = |chapter: &StoryboardChapter, indent: &str| {
+        chapter
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                format!(
+                    "{}<li><a href=\"{}\">Scene {}: {}</a></li>",
+                    indent,
+                    page_file_name(chapter.number, index),
+                    index + 1,
+                    escape_xml(frame.scene_description.trim())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let entries = chapters
+        .iter()
+        .filter(|chapter| !chapter.frames.is_empty())
+        .map(|chapter| match &chapter.title {
+            Some(title) => format!(
+                "        <li>\n          <a href=\"{}\">{}</a>\n          <ol>\n{}\n          </ol>\n        </li>",
+                page_file_name(chapter.number, 0),
+                escape_xml(title),
+                scene_entries(chapter, "            ")
+            ),
+            None => scene_entries(chapter, "        "),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    xhtml_page(
+        STORYBOARD_TITLE,
+        &format!(
+            "    <nav epub:type=\"toc\" id=\"toc\">\n      <h1>{}</h1>\n      <ol>\n{}\n      </ol>\n    </nav>",
+            escape_xml(STORYBOARD_TITLE),
+            entries
+        ),
+    )
+}
+
+/// Builds the page of a single scene. The first page of a titled chapter
+/// also carries the chapter's title.
+fn build_scene_page(chapter: &StoryboardChapter, index: usize, frame: &StoryboardFrame) -> String {
+    let chapter_heading = match &chapter.title {
+        Some(title) if index == 0 => format!("    <h1>{}</h1>\n", escape_xml(title)),
+        _ => String::new(),
+    };
+    let error_note = match &frame.error {
+        Some(error) => format!(
+            "    <p class=\"error\">Image generation failed: {}</p>\n",
+            escape_xml(error)
+        ),
+        None => String::new(),
+    };
+    let paragraphs = frame
+        .original_text
+        .trim()
+        .split("\n\n")
+        .map(|paragraph| format!("    <p>{}</p>", escape_xml(paragraph.trim())))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    xhtml_page(
+        &format!("Scene {}", index + 1),
+        &format!(
+            "{}    <h2>Scene {}</h2>\n    <div class=\"image-container\">\n      <img src=\"images/{}\" alt=\"{}\"/>\n    </div>\n{}    <h4>{}</h4>\n{}",
+            chapter_heading,
+            index + 1,
+            image_file_name(chapter.number, index),
+            escape_xml(frame.scene_description.trim()),
+            error_note,
+            escape_xml(frame.scene_description.trim()),
+            paragraphs
+        ),
+    )
+}
+
+/// Wraps a body in an XHTML document that links the stylesheet.
+fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="en" lang="en">
+  <head>
+    <title>{}</title>
+    <link rel="stylesheet" type="text/css" href="style.css"/>
+  </head>
+  <body>
+{}
+  </body>
+</html>
+"#,
+        escape_xml(title),
+        body
+    )
+}
+
+/// Escapes the characters that are special in XML text and attributes.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLESHEET: &str = r#"body { font-family: serif; line-height: 1.6; margin: 1em; }
+h1, h2 { text-align: center; }
+.image-container { text-align: center; margin: 1em 0; }
+img { max-width: 100%; height: auto; }
+h4 { margin-bottom: 0.5em; color: #555; }
+p { font-style: italic; }
+p.error { font-style: normal; color: #b00020; }
+"#;
@@ -0,0 +1,340 @@
+//! src/output_assembler/html.rs
+//!
+//! Assembles the storyboard into a single HTML page. Images are either
+//! embedded in the HTML or saved as separate files next to it, and so is the
+//! narration of each frame, which gets its own audio player.
+//!
+//! The page doubles as a review artifact: a thumbnail strip links to every
+//! frame, the arrow keys step through the frames, clicking an image opens it
+//! in a lightbox with its full prompt, and a print stylesheet lays out one
+//! frame per block with its prompt for marking up on paper.
+
+use super::{
+    needs_table_of_contents, save_audio_file, save_image_file, ImageMode, StoryboardChapter,
+};
+use crate::error::Result;
+use crate::pipeline::stage_3_image_generation::StoryboardFrame;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Assembles a storyboard from its chapters and saves it as an HTML file.
+pub fn assemble_storyboard_html(
+    chapters: &[StoryboardChapter],
+    output_path: &Path,
+    image_mode: ImageMode,
+) -> Result<()> {
+    info!("Assembling final storyboard HTML at '{}'...", output_path.display());
+
+    let output_dir = output_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut html_content = String::new();
+    html_content.push_str(HTML_HEADER);
+
+    if needs_table_of_contents(chapters) {
+        html_content.push_str(&build_table_of_contents(chapters));
+    }
+
+    html_content.push_str(THUMBNAIL_STRIP);
+
+    let mut frame_number = 0;
+    for chapter in chapters {
+        if let Some(title) = &chapter.title {
+            html_content.push_str(&format!(
+                "\n        <h2 class=\"chapter\" id=\"{}\">{}</h2>\n",
+                chapter.anchor(),
+                title
+            ));
+        }
+        for (index, frame) in chapter.frames.iter().enumerate() {
+            frame_number += 1;
+            html_content.push_str(&build_frame_html(
+                output_dir,
+                chapter.number,
+                index,
+                frame_number,
+                frame,
+                image_mode,
+            )?);
+        }
+    }
+
+    html_content.push_str(HTML_FOOTER);
+
+    fs::create_dir_all(output_dir)?;
+
+    fs::write(output_path, html_content)?;
+
+    info!("Successfully assembled and saved storyboard.");
+    Ok(())
+}
+
+/// Builds the HTML of a single frame, saving its image and narration first
+/// in `Files` mode. `frame_number` counts the frames across all chapters and
+/// gives the frame its anchor.
+fn build_frame_html(
+    output_dir: &Path,
+    chapter_number: Option<usize>,
+    index: usize,
+    frame_number: usize,
+    frame: &StoryboardFrame,
+    image_mode: ImageMode,
+) -> Result<String> {
+    let image_src = match image_mode {
+        ImageMode::Embedded => {
+            // Corrected: Use the modern Engine API for encoding.
+            let image_base64 = STANDARD.encode(&frame.image_data);
+            format!("data:image/png;base64,{}", image_base64)
+        }
+        ImageMode::Files => save_image_file(output_dir, chapter_number, index, &frame.image_data)?,
+    };
+    let audio_player = match &frame.narration {
+        Some(audio_data) => {
+            let audio_src = match image_mode {
+                ImageMode::Embedded => {
+                    format!("data:audio/mpeg;base64,{}", STANDARD.encode(audio_data))
+                }
+                ImageMode::Files => save_audio_file(output_dir, chapter_number, index, audio_data)?,
+            };
+            format!(
+                "\n                    <audio controls preload=\"none\" src=\"{}\"></audio>",
+                audio_src
+            )
+        }
+        None => String::new(),
+    };
+    let error_note = match &frame.error {
+        Some(error) => format!(
+            "\n                    <p class=\"error\">Image generation failed: {}</p>",
+            error
+        ),
+        None => String::new(),
+    };
+
+    // Corrected: Use all fields from the frame to create a richer output.
+    let frame_html = format!(
+        r#"
+            <div class="frame" id="frame-{}">
+                <div class="image-container">
+                    <img src="{}" alt="{}" title="Visual Prompt: {}" data-prompt="{}">
+                </div>
+                <div class="text-container">
+                    <h4>{}</h4>
+                    <p>{}</p>
+                    <p class="prompt">Visual Prompt: {}</p>{}{}
+                </div>
+            </div>
+            "#,
+        frame_number,
+        image_src,
+        escape_attribute(&frame.scene_description), // Use description for alt text
+        escape_attribute(&frame.image_prompt),      // Use full prompt for tooltip
+        escape_attribute(&frame.image_prompt),      // And for the lightbox
+        frame.scene_description,                    // Display description as a sub-header
+        frame.original_text.trim(),
+        frame.image_prompt, // Shown in print only
+        audio_player,
+        error_note
+    );
+    Ok(frame_html)
+}
+
+/// Builds the table of contents linking to each chapter's heading.
+fn build_table_of_contents(chapters: &[StoryboardChapter]) -> String {
+    let entries = chapters
+        .iter()
+        .filter_map(|chapter| {
+            let title = chapter.title.as_deref()?;
+            Some(format!(
+                "                <li><a href=\"#{}\">{}</a></li>",
+                chapter.anchor(),
+                title
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "\n        <nav class=\"toc\">\n            <h3>Contents</h3>\n            <ol>\n{}\n            </ol>\n        </nav>\n",
+        entries
+    )
+}
+
+/// Escapes text for use inside a double-quoted HTML attribute.
+fn escape_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The thumbnail strip, filled in by the script in `HTML_FOOTER` from the
+/// frame images, so embedded images are not stored in the page twice.
+const THUMBNAIL_STRIP: &str = r#"
+        <nav class="thumbnails" aria-label="Frames"></nav>
+"#;
+
+// Corrected: Added styling for the new h4 element.
+const HTML_HEADER: &str = r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>AI Generated Storyboard</title>
+    <style>
+        body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, Helvetica, Arial, sans-serif; line-height: 1.6; background-color: #f4f4f9; color: #333; margin: 0; padding: 2rem; }
+        .container { max-width: 900px; margin: auto; background: #fff; padding: 2rem; box-shadow: 0 4px 12px rgba(0,0,0,0.1); border-radius: 8px; }
+        h1 { text-align: center; color: #1a1a1a; }
+        .frame { display: flex; flex-wrap: wrap; gap: 2rem; margin-bottom: 3rem; padding-bottom: 2rem; border-bottom: 1px solid #e0e0e0; align-items: center; }
+        .frame:last-child { border-bottom: none; }
+        .image-container { flex: 1; min-width: 300px; }
+        .text-container { flex: 1.5; min-width: 300px; }
+        img { max-width: 100%; height: auto; border-radius: 6px; box-shadow: 0 2px 8px rgba(0,0,0,0.1); }
+        h4 { margin-top: 0; margin-bottom: 0.5rem; color: #555; font-weight: bold; }
+        p { font-size: 1.1rem; color: #444; font-style: italic; }
+        .toc { margin-bottom: 3rem; padding: 1rem 2rem; background: #f9f9fb; border-radius: 6px; }
+        .toc a { color: #1a1a1a; }
+        audio { width: 100%; }
+        p.error { font-size: 0.9rem; font-style: normal; color: #b00020; }
+        h2.chapter { margin-top: 3rem; padding-bottom: 0.5rem; border-bottom: 2px solid #1a1a1a; color: #1a1a1a; }
+        .image-container img { cursor: zoom-in; }
+        p.prompt { display: none; }
+        .thumbnails { position: sticky; top: 0; z-index: 1; display: flex; gap: 0.5rem; overflow-x: auto; margin: 0 -2rem 2rem; padding: 0.75rem 2rem; background: rgba(255,255,255,0.95); border-bottom: 1px solid #e0e0e0; }
+        .thumbnails:empty { display: none; }
+        .thumbnails a { flex: none; border: 2px solid transparent; border-radius: 4px; line-height: 0; }
+        .thumbnails a.current { border-color: #1a1a1a; }
+        .thumbnails img { width: 72px; height: 72px; object-fit: cover; border-radius: 2px; box-shadow: none; }
+        .lightbox { position: fixed; inset: 0; z-index: 10; display: none; flex-direction: column; align-items: center; justify-content: center; gap: 1rem; padding: 2rem; background: rgba(0,0,0,0.9); color: #eee; }
+        .lightbox.open { display: flex; }
+        .lightbox img { max-width: 100%; max-height: 70vh; box-shadow: none; }
+        .lightbox h4 { color: #fff; margin: 0; }
+        .lightbox p { max-width: 900px; max-height: 15vh; overflow-y: auto; margin: 0; font-size: 0.95rem; font-style: normal; color: #ccc; }
+        .lightbox button { position: absolute; border: none; background: none; color: #fff; font-size: 2.5rem; cursor: pointer; }
+        .lightbox .close { top: 1rem; right: 1.5rem; }
+        .lightbox .previous { left: 1rem; top: 50%; }
+        .lightbox .next { right: 1rem; top: 50%; }
+        .lightbox .counter { font-size: 0.9rem; color: #aaa; }
+        @media print {
+            body { background: #fff; padding: 0; }
+            .container { max-width: none; padding: 0; box-shadow: none; }
+            .thumbnails, .lightbox, audio { display: none !important; }
+            .frame { break-inside: avoid; page-break-inside: avoid; margin-bottom: 1.5rem; padding-bottom: 1rem; }
+            img { box-shadow: none; }
+            h2.chapter { break-before: page; page-break-before: always; }
+            h2.chapter:first-of-type { break-before: auto; page-break-before: auto; }
+            p.prompt { display: block; font-size: 0.8rem; font-style: normal; color: #666; }
+        }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>AI Generated Storyboard</h1>
+"#;
+
+const HTML_FOOTER: &str = r##"
+    </div>
+    <div class="lightbox" role="dialog" aria-modal="true" aria-label="Frame">
+        <button class="close" aria-label="Close">&times;</button>
+        <button class="previous" aria-label="Previous frame">&lsaquo;</button>
+        <img alt="">
+        <h4></h4>
+        <p></p>
+        <span class="counter"></span>
+        <button class="next" aria-label="Next frame">&rsaquo;</button>
+    </div>
+    <script>
+    (function () {
+        var frames = Array.prototype.slice.call(document.querySelectorAll(".frame"));
+        var strip = document.querySelector(".thumbnails");
+        var thumbnails = frames.map(function (frame, index) {
+            var image = frame.querySelector(".image-container img");
+            var link = document.createElement("a");
+            link.href = "#" + frame.id;
+            link.title = image.alt;
+            var thumbnail = document.createElement("img");
+            thumbnail.src = image.src;
+            thumbnail.alt = "Frame " + (index + 1);
+            thumbnail.loading = "lazy";
+            link.appendChild(thumbnail);
+            strip.appendChild(link);
+            image.addEventListener("click", function () { openLightbox(index); });
+            return link;
+        });
+
+        var current = 0;
+        function setCurrent(index) {
+            thumbnails[current].classList.remove("current");
+            current = index;
+            thumbnails[current].classList.add("current");
+            thumbnails[current].scrollIntoView({ block: "nearest", inline: "nearest" });
+        }
+        function goTo(index) {
+            if (index < 0 || index >= frames.length) { return; }
+            setCurrent(index);
+            if (lightbox.classList.contains("open")) {
+                showInLightbox(index);
+            } else {
+                frames[index].scrollIntoView({ behavior: "smooth", block: "start" });
+                history.replaceState(null, "", "#" + frames[index].id);
+            }
+        }
+
+        var lightbox = document.querySelector(".lightbox");
+        function showInLightbox(index) {
+            var image = frames[index].querySelector(".image-container img");
+            lightbox.querySelector("img").src = image.src;
+            lightbox.querySelector("img").alt = image.alt;
+            lightbox.querySelector("h4").textContent = image.alt;
+            lightbox.querySelector("p").textContent = image.dataset.prompt;
+            lightbox.querySelector(".counter").textContent = (index + 1) + " / " + frames.length;
+        }
+        function openLightbox(index) {
+            setCurrent(index);
+            showInLightbox(index);
+            lightbox.classList.add("open");
+        }
+        function closeLightbox() {
+            lightbox.classList.remove("open");
+            frames[current].scrollIntoView({ block: "start" });
+        }
+        lightbox.querySelector(".close").addEventListener("click", closeLightbox);
+        lightbox.querySelector(".previous").addEventListener("click", function () { goTo(current - 1); });
+        lightbox.querySelector(".next").addEventListener("click", function () { goTo(current + 1); });
+        lightbox.addEventListener("click", function (event) {
+            if (event.target === lightbox) { closeLightbox(); }
+        });
+
+        // The frame nearest the top of the window is the current one.
+        var observer = new IntersectionObserver(function (entries) {
+            if (lightbox.classList.contains("open")) { return; }
+            entries.forEach(function (entry) {
+                if (entry.isIntersecting) { setCurrent(frames.indexOf(entry.target)); }
+            });
+        }, { rootMargin: "0px 0px -70% 0px" });
+        frames.forEach(function (frame) { observer.observe(frame); });
+
+        document.addEventListener("keydown", function (event) {
+            if (event.altKey || event.ctrlKey || event.metaKey || frames.length === 0) { return; }
+            if (event.target.closest && event.target.closest("audio, input, textarea")) { return; }
+            if (event.key === "ArrowRight" || event.key === "j") {
+                goTo(current + 1);
+            } else if (event.key === "ArrowLeft" || event.key === "k") {
+                goTo(current - 1);
+            } else if (event.key === "Escape" && lightbox.classList.contains("open")) {
+                closeLightbox();
+            } else {
+                return;
+            }
+            event.preventDefault();
+        });
+
+        if (frames.length > 0) {
+            var start = frames.findIndex(function (frame) { return "#" + frame.id === location.hash; });
+            setCurrent(Math.max(start, 0));
+        }
+    })();
+    </script>
+</body>
+</html>
+"##;
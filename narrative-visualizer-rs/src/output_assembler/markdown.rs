@@ -0,0 +1,125 @@
+//! src/output_assembler/markdown.rs
+//!
+//! Assembles the storyboard into a Markdown document, for publishing with
+//! static-site generators. Images are saved as files next to the document
+//! and referenced by relative path, as is the narration of each scene, which
+//! is played with an HTML audio element. Chapters become second-level
+//! sections, linked from a table of contents.
+
+use super::{
+    needs_table_of_contents, save_audio_file, save_image_file, StoryboardChapter, STORYBOARD_TITLE,
+};
+use crate::error::Result;
+use crate::pipeline::stage_3_image_generation::StoryboardFrame;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Assembles a storyboard from its chapters and saves it as a Markdown file.
+pub fn assemble_storyboard_markdown(
+    chapters: &[StoryboardChapter],
+    output_path: &Path,
+) -> Result<()> {
+    info!(
+        "Assembling final storyboard Markdown at '{}'...",
+        output_path.display()
+    );
+
+    let output_dir = output_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut markdown = format!("# {}\n", STORYBOARD_TITLE);
+
+    if needs_table_of_contents(chapters) {
+        markdown.push_str("\n## Contents\n\n");
+        for chapter in chapters {
+            if let Some(title) = &chapter.title {
+                markdown.push_str(&format!("1. [{}](#{})\n", title, chapter.anchor()));
+            }
+        }
+    }
+
+    for chapter in chapters {
+        // Scenes are nested one level below their chapter.
+        let scene_level = if chapter.title.is_some() { "###" } else { "##" };
+        if let Some(title) = &chapter.title {
+            // An explicit anchor, since generators differ in how they derive heading IDs.
+            markdown.push_str(&format!(
+                "\n<a id=\"{}\"></a>\n\n## {}\n",
+                chapter.anchor(),
+                title
+            ));
+        }
+        for (index, frame) in chapter.frames.iter().enumerate() {
+            let image_src = save_image_file(output_dir, chapter.number, index, &frame.image_data)?;
+            let audio_src = frame
+                .narration
+                .as_deref()
+                .map(|audio_data| save_audio_file(output_dir, chapter.number, index, audio_data))
+                .transpose()?;
+            markdown.push_str(&build_scene_markdown(
+                scene_level,
+                index,
+                frame,
+                &image_src,
+                audio_src.as_deref(),
+            ));
+        }
+    }
+
+    fs::create_dir_all(output_dir)?;
+    fs::write(output_path, markdown)?;
+
+    info!("Successfully assembled and saved storyboard.");
+    Ok(())
+}
+
+/// Builds the section of a single scene.
+fn build_scene_markdown(
+    heading_level: &str,
+    index: usize,
+    frame: &StoryboardFrame,
+    image_src: &str,
+    audio_src: Option<&str>,
+) -> String {
+    // Markdown has no syntax for audio, but generators pass HTML through.
+    let audio_player = match audio_src {
+        Some(audio_src) => format!("\n<audio controls src=\"{}\"></audio>\n", audio_src),
+        None => String::new(),
+    };
+    let quoted_text = frame
+        .original_text
+        .trim()
+        .lines()
+        .map(|line| format!("> {}", line).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let error_note = match &frame.error {
+        Some(error) => format!("\n*Image generation failed: {}*\n", error.trim()),
+        None => String::new(),
+    };
+
+    format!(
+        "\n{} Scene {}\n\n![{}]({} \"Visual Prompt: {}\")\n{}{}\n**{}**\n\n{}\n",
+        heading_level,
+        index + 1,
+        escape_alt_text(&frame.scene_description),
+        image_src,
+        frame
+            .image_prompt
+            .trim()
+            .replace('"', "'")
+            .replace('\n', " "),
+        audio_player,
+        error_note,
+        frame.scene_description.trim(),
+        quoted_text
+    )
+}
+
+/// Makes a text safe to use as the alt text of a Markdown image.
+fn escape_alt_text(text: &str) -> String {
+    text.trim()
+        .replace('\n', " ")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
@@ -0,0 +1,155 @@
+//! src/output_assembler/mod.rs
+//!
+//! This module is responsible for taking the final processed storyboard frames
+//! and assembling them into a user-viewable output file. Each output format
+//! (HTML, Markdown, and EPUB) has its own submodule, and all of them are
+//! built from the same `StoryboardFrame` data, grouped into chapters. Titled
+//! chapters get a heading, and a storyboard with several of them also gets a
+//! table of contents.
+
+pub mod epub;
+pub mod html;
+pub mod markdown;
+
+use crate::error::Result;
+use crate::pipeline::stage_3_image_generation::StoryboardFrame;
+use clap::ValueEnum;
+use std::fs;
+use std::path::Path;
+
+/// The title of the generated storyboard.
+pub const STORYBOARD_TITLE: &str = "AI Generated Storyboard";
+
+/// The directory, relative to the output file, that image files are saved in.
+const IMAGES_DIR: &str = "images";
+
+/// The frames of one chapter of the storyboard.
+#[derive(Debug)]
+pub struct StoryboardChapter {
+    /// The chapter's position in the book, or `None` if the narrative was not split.
+    pub number: Option<usize>,
+    /// The chapter's heading in the narrative, e.g. `Chapter 2: The Harbor`.
+    pub title: Option<String>,
+    pub frames: Vec<StoryboardFrame>,
+}
+
+impl StoryboardChapter {
+    /// The anchor of the chapter's heading, for links from the table of contents.
+    fn anchor(&self) -> String {
+        format!("chapter-{}", self.number.unwrap_or(1))
+    }
+}
+
+/// Whether the storyboard has several titled chapters, which are linked from
+/// a table of contents.
+fn needs_table_of_contents(chapters: &[StoryboardChapter]) -> bool {
+    chapters
+        .iter()
+        .filter(|chapter| chapter.title.is_some())
+        .count()
+        > 1
+}
+
+/// The format of the assembled storyboard.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A single HTML page.
+    Html,
+    /// A Markdown document, with the images saved next to it.
+    Md,
+    /// An EPUB 3 e-book, with the images packaged inside it.
+    Epub,
+}
+
+impl OutputFormat {
+    /// The file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Html => "html",
+            OutputFormat::Md => "md",
+            OutputFormat::Epub => "epub",
+        }
+    }
+}
+
+/// How the images are included in an HTML storyboard.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageMode {
+    /// Embed each image (and narration track) as base64 data, producing a
+    /// single self-contained file.
+    Embedded,
+    /// Save each image as `images/scene_NN.png` (and narration track as
+    /// `images/scene_NN.mp3`) next to the HTML and reference it.
+    Files,
+}
+
+/// Assembles a storyboard from its chapters in the given format.
+///
+/// `image_mode` only applies to HTML: Markdown always references image
+/// files, and EPUB always packages the images inside the book. Narration is
+/// included in HTML and Markdown, but not in EPUB.
+pub fn assemble_storyboard(
+    chapters: &[StoryboardChapter],
+    output_path: &Path,
+    format: OutputFormat,
+    image_mode: ImageMode,
+) -> Result<()> {
+    match format {
+        OutputFormat::Html => html::assemble_storyboard_html(chapters, output_path, image_mode),
+        OutputFormat::Md => markdown::assemble_storyboard_markdown(chapters, output_path),
+        OutputFormat::Epub => epub::assemble_storyboard_epub(chapters, output_path),
+    }
+}
+
+/// The file name of the image of the scene at `index` of a chapter.
+///
+/// The chapter number is part of the name, so that the images of separately
+/// assembled chapters do not overwrite each other.
+fn image_file_name(chapter_number: Option<usize>, index: usize) -> String {
+    match chapter_number {
+        Some(chapter_number) => format!("chapter_{:02}_scene_{:02}.png", chapter_number, index + 1),
+        None => format!("scene_{:02}.png", index + 1),
+    }
+}
+
+/// The file name of the narration of the scene at `index` of a chapter,
+/// saved next to its image.
+fn audio_file_name(chapter_number: Option<usize>, index: usize) -> String {
+    image_file_name(chapter_number, index).replace(".png", ".mp3")
+}
+
+/// Saves the image of the scene at `index` of a chapter under `output_dir`.
+///
+/// # Returns
+///
+/// The image's path relative to `output_dir`, so the output file and its
+/// images can be moved together.
+fn save_image_file(
+    output_dir: &Path,
+    chapter_number: Option<usize>,
+    index: usize,
+    image_data: &[u8],
+) -> Result<String> {
+    fs::create_dir_all(output_dir.join(IMAGES_DIR))?;
+    let image_src = format!("{}/{}", IMAGES_DIR, image_file_name(chapter_number, index));
+    fs::write(output_dir.join(&image_src), image_data)?;
+    Ok(image_src)
+}
+
+/// Saves the narration of the scene at `index` of a chapter under
+/// `output_dir`, next to its image.
+///
+/// # Returns
+///
+/// The narration's path relative to `output_dir`.
+fn save_audio_file(
+    output_dir: &Path,
+    chapter_number: Option<usize>,
+    index: usize,
+    audio_data: &[u8],
+) -> Result<String> {
+    fs::create_dir_all(output_dir.join(IMAGES_DIR))?;
+    let audio_src = format!("{}/{}", IMAGES_DIR, audio_file_name(chapter_number, index));
+    fs::write(output_dir.join(&audio_src), audio_data)?;
+    Ok(audio_src)
+}
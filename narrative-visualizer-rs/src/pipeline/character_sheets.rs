@@ -0,0 +1,123 @@
+//! src/pipeline/character_sheets.rs
+//!
+//! A pre-pass of the narrative visualization pipeline that keeps characters
+//! consistent across frames. Each image is generated independently, so
+//! without guidance the same protagonist can look different in every scene.
+//! This module asks an AI model to extract the recurring characters from the
+//! whole narrative and to fix a canonical visual description for each (a
+//! "character sheet"), which stage 2 then writes into every image prompt.
+
+use crate::error::Result;
+use crate::openai_client::OpenAIClient;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+/// The canonical visual description of a recurring character.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct CharacterSheet {
+    pub name: String,
+    /// Other ways the narrative refers to the character (e.g., "the detective").
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// The fixed physical appearance and clothing of the character.
+    pub appearance: String,
+}
+
+/// The top-level structure that the AI is instructed to return. Its JSON
+/// schema is sent with the request.
+#[derive(Deserialize, JsonSchema, Debug)]
+struct CharacterExtractionResponse {
+    characters: Vec<CharacterSheet>,
+}
+
+/// Extracts the recurring characters of a narrative and writes a character
+/// sheet for each.
+///
+/// # Returns
+///
+/// The character sheets, which may be empty if the narrative has no
+/// recurring characters.
+#[instrument(skip_all)]
+pub async fn extract_characters(
+    client: &OpenAIClient,
+    narrative_text: &str,
+) -> Result<Vec<CharacterSheet>> {
+    info!("Starting character sheet extraction.");
+
+    let system_prompt = "You are a character designer for film and animation. Your task is to read a narrative, identify its recurring characters, and design a single, canonical look for each one so that every illustrator draws them the same way.";
+    let user_prompt = build_user_prompt(narrative_text);
+
+    let parsed_response: CharacterExtractionResponse = client
+        .get_structured_completion(system_prompt, &user_prompt)
+        .await?;
+
+    info!(
+        "Successfully extracted {} character sheets.",
+        parsed_response.characters.len()
+    );
+    Ok(parsed_response.characters)
+}
+
+/// Renders the character sheets as a section of the stage 2 prompt, or an
+/// empty string if there are none.
+pub fn prompt_section(characters: &[CharacterSheet]) -> String {
+    if characters.is_empty() {
+        return String::new();
+    }
+    let sheets = characters
+        .iter()
+        .map(|character| {
+            let aliases = if character.aliases.is_empty() {
+                String::new()
+            } else {
+                format!(" (also: {})", character.aliases.join(", "))
+            };
+            format!(
+                "- {}{}: {}",
+                character.name,
+                aliases,
+                character.appearance.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Recurring characters. If any of these characters appear in the scene, even when referred to only by a pronoun or a description, describe them using their character sheet word for word, so they look the same in every image:\n{}",
+        sheets
+    )
+}
+
+/// Constructs the detailed user prompt for the character extraction task.
+fn build_user_prompt(narrative_text: &str) -> String {
+    let output_schema = serde_json::json!({
+      "characters": [
+        {
+          "name": "The character's name, or a short label such as \"The Old Detective\" if unnamed.",
+          "aliases": ["Other names, titles, or descriptions the narrative uses for this character."],
+          "appearance": "One or two sentences fixing the character's age, build, face, hair, clothing, and one distinctive feature."
+        }
+      ]
+    });
+
+    format!(
+        "Identify the characters who appear in more than one moment of the following narrative, or who are central to it. For each, write a character sheet that fixes their visual appearance.
+
+        The appearance must agree with everything the narrative says about the character, and invent concrete, specific details where it says nothing. Describe only permanent physical traits and clothing, not actions, emotions, or surroundings. Do not mention any artistic style.
+
+        Your final output must be a single, valid JSON object with a single key, \"characters\", which contains an array of objects strictly adhering to the following schema:
+        ```json
+        {}
+        ```
+
+        If the narrative has no recurring characters, return an empty array. Do not include any text or explanations outside of the JSON object.
+
+        Narrative Text to Analyze:
+        \"\"\"
+        {}
+        \"\"\"
+        ",
+        serde_json::to_string_pretty(&output_schema).unwrap(),
+        narrative_text
+    )
+}
@@ -2,8 +2,15 @@
 //!
 //! This module defines the stages of the narrative visualization pipeline.
 //! Each submodule represents a distinct step in transforming raw text into
-//! a visual storyboard.
+//! a visual storyboard. The `character_sheets` and optional `style_reference`
+//! pre-passes feed stage 2, the `moderation` check screens the prompts before
+//! stage 3 submits them, and the optional `narration` stage reads each scene
+//! aloud.
 
+pub mod character_sheets;
+pub mod moderation;
+pub mod narration;
 pub mod stage_1_scene_detection;
 pub mod stage_2_prompt_generation;
 pub mod stage_3_image_generation;
+pub mod style_reference;
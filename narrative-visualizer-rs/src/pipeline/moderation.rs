@@ -0,0 +1,146 @@
+//! src/pipeline/moderation.rs
+//!
+//! A pre-flight check between stage 2 and stage 3 that screens the image
+//! prompts with the OpenAI moderation endpoint before they are submitted to
+//! DALL-E. DALL-E rejects prompts that violate its content policy with an
+//! opaque 400 error, which would otherwise stop the pipeline halfway through
+//! the images. Flagged prompts are rewritten into a safe variant, or skipped,
+//! and every flagged scene is reported.
+
+use crate::checkpoint::Checkpoint;
+use crate::error::Result;
+use crate::openai_client::OpenAIClient;
+use crate::pipeline::stage_2_prompt_generation::VisualPrompt;
+use clap::ValueEnum;
+use tracing::{info, instrument, warn};
+
+/// What happens to a prompt that the moderation endpoint flags.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModerationPolicy {
+    /// Rewrite the prompt into a safe variant, and skip the scene if the
+    /// rewrite is flagged too.
+    Rewrite,
+    /// Skip the scene.
+    Skip,
+    /// Do not screen the prompts.
+    Off,
+}
+
+/// Screens the visual prompts of a chapter, rewriting or removing the ones
+/// that are flagged.
+///
+/// # Arguments
+///
+/// * `client` - An instance of the `OpenAIClient`.
+/// * `prompts` - The visual prompts from stage 2, replaced by the screened list.
+/// * `policy` - What happens to flagged prompts.
+/// * `title` - The chapter's title, if the narrative was split into chapters.
+/// * `checkpoint` - Where the screened prompts are saved. Prompts whose image
+///   is already saved have been submitted before, and are not screened again.
+///
+/// # Returns
+///
+/// The number of prompts that were flagged.
+#[instrument(skip_all)]
+pub async fn moderate_prompts(
+    client: &OpenAIClient,
+    prompts: &mut Vec<VisualPrompt>,
+    policy: ModerationPolicy,
+    title: Option<&str>,
+    checkpoint: &Checkpoint,
+) -> Result<usize> {
+    if policy == ModerationPolicy::Off {
+        return Ok(0);
+    }
+    info!("Screening {} image prompts.", prompts.len());
+
+    let mut screened = Vec::with_capacity(prompts.len());
+    let mut image_sources = Vec::with_capacity(prompts.len());
+    let mut narration_sources = Vec::with_capacity(prompts.len());
+    let mut flagged_count = 0;
+    for (index, prompt) in prompts.iter().enumerate() {
+        let scene_label = match title {
+            Some(title) => format!("Scene {} of '{}'", index + 1, title),
+            None => format!("Scene {}", index + 1),
+        };
+        let moderation = if checkpoint.image_path(index).is_file() {
+            None
+        } else {
+            Some(client.moderate(&prompt.image_prompt).await?).filter(|m| m.flagged)
+        };
+        let Some(moderation) = moderation else {
+            screened.push(prompt.clone());
+            image_sources.push(Some(index));
+            narration_sources.push(Some(index));
+            continue;
+        };
+        flagged_count += 1;
+
+        let rewritten = match policy {
+            ModerationPolicy::Rewrite => {
+                rewrite_prompt(client, prompt, &moderation.categories).await?
+            }
+            _ => None,
+        };
+        match rewritten {
+            Some(image_prompt) => {
+                warn!(
+                    "{} was flagged for {}; its prompt was rewritten.",
+                    scene_label,
+                    moderation.categories.join(", ")
+                );
+                screened.push(VisualPrompt {
+                    image_prompt,
+                    ..prompt.clone()
+                });
+                image_sources.push(None);
+                narration_sources.push(Some(index));
+            }
+            None => {
+                warn!(
+                    "{} was flagged for {} and is skipped.",
+                    scene_label,
+                    moderation.categories.join(", ")
+                );
+            }
+        }
+    }
+
+    if flagged_count > 0 {
+        checkpoint.save_revised_prompts(&screened, &image_sources, &narration_sources)?;
+        *prompts = screened;
+    }
+    info!("Screening flagged {} prompts.", flagged_count);
+    Ok(flagged_count)
+}
+
+/// Asks the text model for a variant of a flagged prompt that complies with
+/// the content policy.
+///
+/// # Returns
+///
+/// The rewritten prompt, or `None` if the rewrite is flagged as well.
+async fn rewrite_prompt(
+    client: &OpenAIClient,
+    prompt: &VisualPrompt,
+    categories: &[String],
+) -> Result<Option<String>> {
+    let system_prompt = "You are an art director who adapts image generation prompts to a strict content policy. You keep the scene, composition, characters, mood, and artistic style of a prompt, but remove or soften anything graphic, explicit, hateful, or otherwise disallowed, implying it instead of showing it.";
+    let user_prompt = format!(
+        "The following image prompt was flagged by a content filter for: {}. Rewrite it as a single descriptive paragraph that conveys the same scene safely. Reply with the rewritten prompt only.
+
+        Flagged Prompt:
+        \"\"\"
+        {}
+        \"\"\"",
+        categories.join(", "),
+        prompt.image_prompt
+    );
+
+    let image_prompt = client.get_completion(system_prompt, &user_prompt).await?;
+    let image_prompt = image_prompt.trim().to_string();
+    if client.moderate(&image_prompt).await?.flagged {
+        return Ok(None);
+    }
+    Ok(Some(image_prompt))
+}
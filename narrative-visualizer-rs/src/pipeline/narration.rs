@@ -0,0 +1,139 @@
+//! src/pipeline/narration.rs
+//!
+//! An optional stage of the narrative visualization pipeline that reads each
+//! scene's original text aloud. The narration is synthesized with the OpenAI
+//! text-to-speech endpoint, one MP3 track per scene, so that every frame of
+//! the storyboard can be played back with its own audio. Like the images,
+//! tracks are requested concurrently, saved to the checkpoint as soon as they
+//! arrive, and reused by a resumed run.
+
+use crate::checkpoint::Checkpoint;
+use crate::error::{Error, Result};
+use crate::openai_client::{OpenAIClient, Voice};
+use crate::pipeline::stage_2_prompt_generation::VisualPrompt;
+use indicatif::ProgressBar;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{info, instrument};
+
+/// The longest text, in characters, that the speech endpoint accepts in one request.
+const MAX_SPEECH_CHARS: usize = 4096;
+
+/// Synthesizes the narration of each scene.
+///
+/// # Arguments
+///
+/// * `client` - An instance of the `OpenAIClient`.
+/// * `prompts` - The visual prompts from stage 2, whose original text is read.
+/// * `voice` - The voice the narration is read in.
+/// * `concurrency` - The maximum number of speech requests in flight at once.
+/// * `checkpoint` - Where the narration is saved and reused from.
+/// * `progress` - Advanced by one for each track synthesized or reused.
+///
+/// # Returns
+///
+/// The MP3 data of each scene's narration, in the same order as `prompts`.
+/// If a request fails, the requests already in flight are still completed
+/// and saved before the first error is returned.
+#[instrument(skip_all)]
+pub async fn generate_narration(
+    client: &OpenAIClient,
+    prompts: &[VisualPrompt],
+    voice: Voice,
+    concurrency: usize,
+    checkpoint: &Checkpoint,
+    progress: &ProgressBar,
+) -> Result<Vec<Vec<u8>>> {
+    info!(
+        "Starting narration for {} scenes in the '{}' voice.",
+        prompts.len(),
+        voice.as_str()
+    );
+
+    let total = prompts.len();
+    let mut tracks: Vec<Option<Vec<u8>>> = vec![None; total];
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+
+    for (index, prompt) in prompts.iter().enumerate() {
+        if let Some(audio_data) = checkpoint.load_narration(index, voice)? {
+            tracks[index] = Some(audio_data);
+            progress.inc(1);
+            continue;
+        }
+
+        let client = client.clone();
+        let checkpoint = checkpoint.clone();
+        let semaphore = Arc::clone(&semaphore);
+        let text = prompt.original_text.trim().to_string();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            info!("Narrating scene {}/{}...", index + 1, total);
+
+            let audio_data = narrate(&client, &text, voice).await.and_then(|audio_data| {
+                checkpoint.save_narration(index, voice, &audio_data)?;
+                Ok(audio_data)
+            });
+            (index, audio_data)
+        });
+    }
+
+    let mut first_error = None;
+    while let Some(joined) = tasks.join_next().await {
+        let result = joined
+            .map_err(|e| Error::Pipeline(format!("Narration task failed: {}", e)))
+            .and_then(|(index, audio_data)| Ok((index, audio_data?)));
+        match result {
+            Ok((index, audio_data)) => {
+                tracks[index] = Some(audio_data);
+                progress.inc(1);
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+
+    info!("Successfully narrated {} scenes.", total);
+    Ok(tracks.into_iter().map(Option::unwrap_or_default).collect())
+}
+
+/// Synthesizes the narration of a text, in several requests if it is too
+/// long for one. MP3 streams can be joined by concatenation.
+async fn narrate(client: &OpenAIClient, text: &str, voice: Voice) -> Result<Vec<u8>> {
+    let mut audio_data = Vec::new();
+    for chunk in split_text(text, MAX_SPEECH_CHARS) {
+        audio_data.extend(client.generate_speech(&chunk, voice).await?);
+    }
+    Ok(audio_data)
+}
+
+/// Splits a text into chunks of at most `max_chars` characters, breaking
+/// after a sentence or, failing that, a word where possible.
+fn split_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(i, _)| i);
+        let head = &rest[..limit];
+        let end = head
+            .rfind(['.', '!', '?', '\n'])
+            .map(|i| i + 1)
+            .or_else(|| head.rfind(' '))
+            .filter(|&end| end > 0)
+            .unwrap_or(limit);
+        chunks.push(rest[..end].trim().to_string());
+        rest = rest[end..].trim_start();
+    }
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
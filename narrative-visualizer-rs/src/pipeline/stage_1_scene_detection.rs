@@ -2,83 +2,203 @@
 //!
 //! The first stage of the narrative visualization pipeline. This module is
 //! responsible for taking a block of source text and using an AI model to
-//! decompose it into a structured list of distinct scenes.
+//! decompose it into a structured list of distinct scenes. The model tends to
+//! over-segment dialogue, so the number and length of scenes can be limited,
+//! and adjacent scenes are merged when the model does not keep to the limits.
 
 use crate::error::{Error, Result};
 use crate::openai_client::OpenAIClient;
-use once_cell::sync::Lazy;
-use regex::Regex;
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use tracing::{info, instrument};
 
-/// A lazily-compiled regular expression to robustly extract a JSON object
-/// from within the AI's response, which might include markdown code fences.
-static JSON_EXTRACTOR: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?s)\s*\{.*\}\s*").unwrap());
+/// The framing the AI suggests for a scene's image.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
 
 /// Represents a single, distinct scene identified by the AI.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct Scene {
     pub description: String,
     #[serde(rename = "originalText")]
     pub original_text: String,
+    /// The suggested framing. Missing or unrecognized hints are ignored.
+    #[serde(default, deserialize_with = "deserialize_orientation")]
+    pub orientation: Option<Orientation>,
+}
+
+/// Reads an orientation hint leniently, so that an unexpected value from the
+/// AI does not fail the whole response.
+fn deserialize_orientation<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Orientation>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| serde_json::from_value(value).ok()))
 }
 
-/// The top-level structure that the AI is instructed to return.
-#[derive(Deserialize, Debug)]
+/// Limits on how finely the narrative is divided into scenes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SceneLimits {
+    /// The most scenes the narrative (or each chapter) is divided into.
+    pub max_scenes: Option<usize>,
+    /// The fewest characters of original text a scene should span; shorter
+    /// scenes are merged with a neighbor. Zero disables merging.
+    pub min_scene_length: usize,
+}
+
+impl SceneLimits {
+    /// A short description of the limits, e.g.
+    /// `at most 12 scenes, at least 300 characters each`.
+    pub fn label(&self) -> String {
+        let max_scenes = match self.max_scenes {
+            Some(max_scenes) => format!("at most {} scenes", max_scenes),
+            None => "any number of scenes".to_string(),
+        };
+        format!(
+            "{}, at least {} characters each",
+            max_scenes, self.min_scene_length
+        )
+    }
+}
+
+/// The top-level structure that the AI is instructed to return. Its JSON
+/// schema is sent with the request.
+#[derive(Deserialize, JsonSchema, Debug)]
 struct SceneDetectionResponse {
     scenes: Vec<Scene>,
 }
 
 /// Analyzes a narrative text and breaks it down into distinct scenes.
+///
+/// The model is asked to keep to `limits`, and the scenes it returns are
+/// merged afterwards if it does not.
 #[instrument(skip_all)]
-pub async fn detect_scenes(client: &OpenAIClient, narrative_text: &str) -> Result<Vec<Scene>> {
+pub async fn detect_scenes(
+    client: &OpenAIClient,
+    narrative_text: &str,
+    limits: SceneLimits,
+) -> Result<Vec<Scene>> {
     info!("Starting scene detection.");
 
     let system_prompt = "You are an expert film director and script analyst. Your task is to read the provided narrative text and break it down into distinct, visually coherent scenes or 'shots'. Each scene should represent a single, continuous moment or a specific visual focus.";
-    let user_prompt = build_user_prompt(narrative_text);
-
-    let response_text = client.get_completion(system_prompt, &user_prompt).await?;
-    info!(raw_response = %response_text, "Received raw response from API.");
-
-    // Corrected: Robustly extract the JSON part of the response using regex.
-    let json_text = JSON_EXTRACTOR
-        .find(&response_text)
-        .map(|m| m.as_str())
-        .ok_or_else(|| {
-            Error::Pipeline(
-                "Could not find a valid JSON object in the AI's response.".to_string(),
-            )
-        })?;
-
-    let parsed_response: SceneDetectionResponse = serde_json::from_str(json_text).map_err(|e| {
-        Error::Pipeline(format!(
-            "Failed to parse scene detection response: {}. Extracted text: '{}'",
-            e, json_text
-        ))
-    })?;
+    let user_prompt = build_user_prompt(narrative_text, limits);
+
+    let parsed_response: SceneDetectionResponse = client
+        .get_structured_completion(system_prompt, &user_prompt)
+        .await?;
 
     if parsed_response.scenes.is_empty() {
         Err(Error::Pipeline("Scene detection returned no scenes.".to_string()))
     } else {
-        info!("Successfully detected {} scenes.", parsed_response.scenes.len());
-        Ok(parsed_response.scenes)
+        let detected = parsed_response.scenes.len();
+        let scenes = merge_scenes(parsed_response.scenes, limits);
+        if scenes.len() < detected {
+            info!(
+                "Merged {} detected scenes into {} to keep to the scene limits.",
+                detected,
+                scenes.len()
+            );
+        }
+        info!("Successfully detected {} scenes.", scenes.len());
+        Ok(scenes)
     }
 }
 
+/// Merges adjacent scenes until every scene is at least
+/// `limits.min_scene_length` characters long and there are no more than
+/// `limits.max_scenes`.
+///
+/// Short scenes are merged into their shorter neighbor, and surplus scenes
+/// are removed by merging the adjacent pair with the least text, so that
+/// long scenes are left alone as far as possible.
+fn merge_scenes(mut scenes: Vec<Scene>, limits: SceneLimits) -> Vec<Scene> {
+    let length = |scene: &Scene| scene.original_text.trim().chars().count();
+
+    while scenes.len() > 1 {
+        let Some(short) = (0..scenes.len())
+            .filter(|&i| length(&scenes[i]) < limits.min_scene_length)
+            .min_by_key(|&i| length(&scenes[i]))
+        else {
+            break;
+        };
+        let merge_with_previous = match (short.checked_sub(1), scenes.get(short + 1)) {
+            (Some(previous), Some(next)) => length(&scenes[previous]) <= length(next),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        let first = if merge_with_previous {
+            short - 1
+        } else {
+            short
+        };
+        merge_adjacent(&mut scenes, first);
+    }
+
+    if let Some(max_scenes) = limits.max_scenes {
+        while scenes.len() > max_scenes.max(1) {
+            let first = (0..scenes.len() - 1)
+                .min_by_key(|&i| length(&scenes[i]) + length(&scenes[i + 1]))
+                .unwrap_or(0);
+            merge_adjacent(&mut scenes, first);
+        }
+    }
+
+    scenes
+}
+
+/// Merges the scene at `first` with the one after it. The merged scene keeps
+/// the description and framing of the scene with more text, which is the one
+/// most likely to hold the moment worth illustrating.
+fn merge_adjacent(scenes: &mut Vec<Scene>, first: usize) {
+    let second = scenes.remove(first + 1);
+    let merged = &mut scenes[first];
+    if second.original_text.trim().len() > merged.original_text.trim().len() {
+        merged.description = second.description;
+        merged.orientation = second.orientation;
+    }
+    merged.original_text = format!(
+        "{}\n\n{}",
+        merged.original_text.trim_end(),
+        second.original_text.trim_start()
+    );
+}
+
 /// Constructs the detailed user prompt for the scene detection task.
-fn build_user_prompt(narrative_text: &str) -> String {
+fn build_user_prompt(narrative_text: &str, limits: SceneLimits) -> String {
     let output_schema = serde_json::json!({
       "scenes": [
         {
           "description": "A concise, one-sentence description of the key visual elements and action in this specific scene.",
-          "originalText": "The exact, unmodified segment of the original text that corresponds to this scene."
+          "originalText": "The exact, unmodified segment of the original text that corresponds to this scene.",
+          "orientation": "The image framing that best suits this scene: \"landscape\" for wide vistas and establishing shots, \"portrait\" for single figures and tall subjects, or \"square\" otherwise."
         }
       ]
     });
 
+    let mut constraints = Vec::new();
+    if let Some(max_scenes) = limits.max_scenes {
+        constraints.push(format!("Use no more than {} scenes.", max_scenes));
+    }
+    if limits.min_scene_length > 0 {
+        constraints.push(format!(
+            "Each scene should span at least {} characters of the original text.",
+            limits.min_scene_length
+        ));
+    }
+    if !constraints.is_empty() {
+        constraints.push("Keep a run of dialogue that happens in one place within a single scene rather than giving each line its own scene.".to_string());
+    }
+
     format!(
-        "Analyze the following narrative text. Decompose it into a sequence of scenes.
+        "Analyze the following narrative text. Decompose it into a sequence of scenes. {}
 
         Your final output must be a single, valid JSON object. The root object should have a single key, \"scenes\", which contains an array of scene objects. Each scene object must strictly adhere to the following schema:
         ```json
@@ -92,6 +212,7 @@ fn build_user_prompt(narrative_text: &str) -> String {
         {}
         \"\"\"
         ",
+        constraints.join(" "),
         serde_json::to_string_pretty(&output_schema).unwrap(),
         narrative_text
     )
@@ -2,20 +2,48 @@
 //!
 //! The second stage of the narrative visualization pipeline. This module takes
 //! the scenes identified in stage 1 and uses an AI model to generate rich,
-//! descriptive visual prompts suitable for an image generation API.
+//! descriptive visual prompts suitable for an image generation API. The
+//! artistic style, lighting, palette, and camera defaults come from the
+//! selected style profile, and recurring characters are described with their
+//! character sheets so they look the same in every image. Scenes can override
+//! the style profile and carry a negative prompt through their annotations.
 
+use crate::annotations::Annotations;
+use crate::config::StyleProfile;
 use crate::error::Result;
 use crate::openai_client::OpenAIClient;
-use crate::pipeline::stage_1_scene_detection::Scene;
+use crate::pipeline::character_sheets::{self, CharacterSheet};
+use crate::pipeline::stage_1_scene_detection::{Orientation, Scene};
+use crate::pipeline::style_reference;
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
 use tracing::{info, instrument};
 
 /// Represents a scene that has been enriched with a detailed visual prompt
 /// ready for image generation.
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VisualPrompt {
     pub scene_description: String,
     pub original_text: String,
     pub image_prompt: String,
+    /// The framing suggested for the scene, carried over from stage 1.
+    #[serde(default)]
+    pub orientation: Option<Orientation>,
+    /// What the image must not show, from the annotations.
+    #[serde(default)]
+    pub negative_prompt: Option<String>,
+}
+
+/// The settings every visual prompt of a run is written with.
+pub struct PromptContext<'a> {
+    /// The visual style profile the prompts are written in.
+    pub style: &'a StyleProfile,
+    /// The character sheets of the narrative's recurring characters.
+    pub characters: &'a [CharacterSheet],
+    /// The description of the reference image's style, if any.
+    pub style_reference: Option<&'a str>,
+    /// The negative prompts and per-scene style overrides.
+    pub annotations: &'a Annotations,
 }
 
 /// Takes a list of scenes and generates a detailed visual prompt for each one.
@@ -24,6 +52,9 @@ pub struct VisualPrompt {
 ///
 /// * `client` - An instance of the `OpenAIClient`.
 /// * `scenes` - A slice of `Scene` structs from the previous pipeline stage.
+/// * `context` - The style, character sheets, and annotations of the run.
+/// * `chapter_number` - The chapter of the scenes, for looking up their annotations.
+/// * `progress` - Advanced by one for each prompt generated.
 ///
 /// # Returns
 ///
@@ -32,15 +63,34 @@ pub struct VisualPrompt {
 pub async fn generate_visual_prompts(
     client: &OpenAIClient,
     scenes: &[Scene],
+    context: &PromptContext<'_>,
+    chapter_number: Option<usize>,
+    progress: &ProgressBar,
 ) -> Result<Vec<VisualPrompt>> {
-    info!("Starting visual prompt generation for {} scenes.", scenes.len());
+    let PromptContext {
+        style,
+        characters,
+        style_reference,
+        annotations,
+    } = *context;
+    info!(
+        "Starting visual prompt generation for {} scenes in the '{}' style.",
+        scenes.len(),
+        style.name
+    );
 
     let mut visual_prompts = Vec::new();
     let system_prompt = "You are a creative visual artist and art director. Your task is to take a simple scene description and expand it into a rich, detailed, and evocative prompt for an AI image generation model like DALL-E 3. The prompt must be a single, descriptive paragraph and should specify the mood, lighting, color palette, camera angle, and artistic style.";
 
     for (index, scene) in scenes.iter().enumerate() {
         info!("Generating prompt for scene {}/{}...", index + 1, scenes.len());
-        let user_prompt = build_user_prompt(&scene.description);
+        let scene_style = annotations.style_for(style, chapter_number, index);
+        let user_prompt = build_user_prompt(
+            &scene.description,
+            &scene_style,
+            characters,
+            style_reference,
+        );
 
         // Call the AI to transform the simple description into a rich prompt.
         let image_prompt = client.get_completion(system_prompt, &user_prompt).await?;
@@ -49,7 +99,10 @@ pub async fn generate_visual_prompts(
             scene_description: scene.description.clone(),
             original_text: scene.original_text.clone(),
             image_prompt,
+            orientation: scene.orientation,
+            negative_prompt: annotations.negative_prompt_for(chapter_number, index),
         });
+        progress.inc(1);
     }
 
     info!("Successfully generated {} visual prompts.", visual_prompts.len());
@@ -57,11 +110,20 @@ pub async fn generate_visual_prompts(
 }
 
 /// Constructs the detailed user prompt for the visual prompt generation task.
-fn build_user_prompt(scene_description: &str) -> String {
+fn build_user_prompt(
+    scene_description: &str,
+    style: &StyleProfile,
+    characters: &[CharacterSheet],
+    style_reference: Option<&str>,
+) -> String {
     format!(
         "Based on the following scene description, generate a detailed visual prompt for an image generation model.
 
-        The prompt should be a single, descriptive paragraph. Emphasize a 'cinematic film noir' artistic style. Include details about lighting (e.g., 'dramatic chiaroscuro lighting'), color (e.g., 'monochromatic with a single splash of color'), and camera angle (e.g., 'low-angle shot').
+        The prompt should be a single, descriptive paragraph. Emphasize a '{}' artistic style. Include details about lighting (default: '{}'), color (default: '{}'), and camera angle (default: '{}'), adapting them only where the scene clearly calls for it.
+
+        {}
+
+        {}
 
         Scene Description:
         \"\"\"
@@ -69,6 +131,12 @@ fn build_user_prompt(scene_description: &str) -> String {
         \"\"\"
 
         Generated Visual Prompt:",
+        style.style,
+        style.lighting,
+        style.palette,
+        style.camera,
+        style_reference::prompt_section(style_reference),
+        character_sheets::prompt_section(characters),
         scene_description
     )
 }
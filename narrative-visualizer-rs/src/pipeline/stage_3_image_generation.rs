@@ -2,12 +2,32 @@
 //!
 //! The third and final stage of the narrative visualization pipeline. This
 //! module takes the visually descriptive prompts and uses an AI image
-//! generation model to synthesize an image for each scene.
+//! generation model (DALL-E or a local Stable Diffusion server) to synthesize
+//! an image for each scene. Images are
+//! requested concurrently, up to a configurable limit, and collected back in
+//! scene order. Each image is saved to the checkpoint as soon as it arrives,
+//! and images already saved by a previous run are reused.
+//!
+//! Transient failures, such as rate limits and server errors, are retried
+//! by the client. A frame whose image still cannot be generated
+//! gets a placeholder image and records the error, so that one bad prompt
+//! does not discard the rest of the storyboard; a resumed run retries it.
 
-use crate::error::Result;
-use crate::openai_client::OpenAIClient;
+use crate::checkpoint::Checkpoint;
+use crate::error::{Error, Result};
+use crate::openai_client::{ImageBackend, ImageBackendKind, ImageQuality, ImageSize};
+use crate::pipeline::stage_1_scene_detection::Orientation;
 use crate::pipeline::stage_2_prompt_generation::VisualPrompt;
-use tracing::{info, instrument};
+use image::{ImageFormat, Rgba, RgbaImage};
+use indicatif::ProgressBar;
+use std::io::Cursor;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{info, instrument, warn};
+
+/// The color of the placeholder images of failed frames.
+const PLACEHOLDER_COLOR: Rgba<u8> = Rgba([64, 64, 64, 255]);
 
 /// Represents a fully processed scene, containing the original text, its
 /// description, the prompt used for image generation, and the raw byte data
@@ -18,41 +38,188 @@ pub struct StoryboardFrame {
     pub scene_description: String,
     pub image_prompt: String,
     pub image_data: Vec<u8>, // The raw PNG/JPEG data of the generated image.
+    /// The MP3 narration of the original text, if narration is enabled.
+    pub narration: Option<Vec<u8>>,
+    /// Why the image could not be generated, if `image_data` is a placeholder.
+    pub error: Option<String>,
+}
+
+/// The image generation parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageOptions {
+    /// The size of every image, or of the images without an orientation hint.
+    pub size: ImageSize,
+    pub quality: ImageQuality,
+    /// Whether each image is sized by its scene's orientation hint.
+    pub orientation_hints: bool,
+    pub backend: ImageBackendKind,
+}
+
+impl ImageOptions {
+    /// The size of the image for a prompt.
+    pub fn size_for(&self, prompt: &VisualPrompt) -> ImageSize {
+        match prompt.orientation.filter(|_| self.orientation_hints) {
+            Some(Orientation::Landscape) => ImageSize::Landscape,
+            Some(Orientation::Portrait) => ImageSize::Portrait,
+            Some(Orientation::Square) => ImageSize::Square,
+            None => self.size,
+        }
+    }
+
+    /// The price in US dollars of an image of the given size.
+    pub fn price_usd(&self, size: ImageSize) -> f64 {
+        self.backend.image_price_usd(size, self.quality)
+    }
+
+    /// A short description of the options, e.g. `1792x1024, hd`. The backend
+    /// is only named if it is not DALL-E.
+    pub fn label(&self) -> String {
+        let size = if self.orientation_hints {
+            format!("orientation hints or {}", self.size.dimensions())
+        } else {
+            self.size.dimensions().to_string()
+        };
+        match self.backend {
+            ImageBackendKind::DallE => format!("{}, {}", size, self.quality.as_str()),
+            backend => format!("{}, {}, {}", size, self.quality.as_str(), backend.as_str()),
+        }
+    }
 }
 
 /// Takes a list of visual prompts and generates an image for each one.
 ///
 /// # Arguments
 ///
-/// * `client` - An instance of the `OpenAIClient`.
+/// * `backend` - The service the images are generated with.
 /// * `prompts` - A slice of `VisualPrompt` structs from the previous pipeline stage.
+/// * `concurrency` - The maximum number of image requests in flight at once.
+/// * `options` - The size and quality of the images.
+/// * `checkpoint` - Where generated images are saved and reused from.
+/// * `progress` - Advanced by one for each image generated or reused.
 ///
 /// # Returns
 ///
-/// A `Result` containing a `Vec<StoryboardFrame>` on success.
+/// A `Result` containing a `Vec<StoryboardFrame>` on success, in the same
+/// order as `prompts`. The frames whose image failed have a placeholder image
+/// and their `error` set. Only if every requested image failed is the first
+/// error returned instead.
 #[instrument(skip_all)]
 pub async fn generate_images(
-    client: &OpenAIClient,
+    backend: &ImageBackend,
     prompts: &[VisualPrompt],
+    concurrency: usize,
+    options: ImageOptions,
+    checkpoint: &Checkpoint,
+    progress: &ProgressBar,
 ) -> Result<Vec<StoryboardFrame>> {
-    info!("Starting image generation for {} prompts.", prompts.len());
+    info!(
+        "Starting image generation for {} prompts ({} at a time, {}).",
+        prompts.len(),
+        concurrency,
+        options.label()
+    );
 
-    let mut storyboard_frames = Vec::new();
+    let total = prompts.len();
+    let mut images: Vec<Option<Result<Vec<u8>>>> = (0..total).map(|_| None).collect();
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut reused = 0;
 
     for (index, prompt) in prompts.iter().enumerate() {
-        info!("Generating image for scene {}/{}...", index + 1, prompts.len());
+        if let Some(image_data) = checkpoint.load_image(index)? {
+            images[index] = Some(Ok(image_data));
+            reused += 1;
+            progress.inc(1);
+            continue;
+        }
+
+        let backend = backend.clone();
+        let checkpoint = checkpoint.clone();
+        let semaphore = Arc::clone(&semaphore);
+        let image_prompt = prompt.image_prompt.clone();
+        let negative_prompt = prompt.negative_prompt.clone();
+        let size = options.size_for(prompt);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            info!("Generating image for scene {}/{}...", index + 1, total);
+
+            // Call the AI to synthesize an image based on the detailed prompt,
+            // and save it right away so it survives a later failure.
+            let image_data = backend
+                .generate_image(
+                    &image_prompt,
+                    negative_prompt.as_deref(),
+                    size,
+                    options.quality,
+                )
+                .await
+                .and_then(|image_data| {
+                    checkpoint.save_image(index, &image_data)?;
+                    Ok(image_data)
+                });
+            (index, image_data)
+        });
+    }
+    if reused > 0 {
+        info!("Reused {} of {} images from the checkpoint.", reused, total);
+    }
 
-        // Call the AI to synthesize an image based on the detailed prompt.
-        let image_data = client.generate_image(&prompt.image_prompt).await?;
+    // Tasks finish in any order, so each image is slotted back by scene index.
+    // A failed image does not stop the others, so that every image already
+    // paid for is saved and assembled.
+    let requested = total - reused;
+    let mut failed = 0;
+    while let Some(joined) = tasks.join_next().await {
+        let (index, image_data) =
+            joined.map_err(|e| Error::Pipeline(format!("Image generation task failed: {}", e)))?;
+        if let Err(e) = &image_data {
+            warn!("The image of scene {}/{} failed: {}", index + 1, total, e);
+            failed += 1;
+        }
+        images[index] = Some(image_data);
+        progress.inc(1);
+    }
+    if failed > 0 && failed == requested {
+        let first_error = images.into_iter().flatten().find_map(Result::err);
+        return Err(first_error.expect("a failed image has an error"));
+    }
 
+    let mut storyboard_frames = Vec::with_capacity(total);
+    for (prompt, image_data) in prompts.iter().zip(images) {
+        let (image_data, error) = match image_data.expect("every scene has a result") {
+            Ok(image_data) => (image_data, None),
+            Err(e) => (
+                placeholder_image(options.size_for(prompt))?,
+                Some(e.to_string()),
+            ),
+        };
         storyboard_frames.push(StoryboardFrame {
             original_text: prompt.original_text.clone(),
             scene_description: prompt.scene_description.clone(),
             image_prompt: prompt.image_prompt.clone(),
             image_data,
+            narration: None,
+            error,
         });
     }
 
-    info!("Successfully generated {} images.", storyboard_frames.len());
+    info!(
+        "Generated {} of {} images.",
+        total - failed,
+        storyboard_frames.len()
+    );
     Ok(storyboard_frames)
 }
+
+/// A plain image of the given size, standing in for an image that failed.
+fn placeholder_image(size: ImageSize) -> Result<Vec<u8>> {
+    let (width, height) = match size {
+        ImageSize::Square => (1024, 1024),
+        ImageSize::Landscape => (1792, 1024),
+        ImageSize::Portrait => (1024, 1792),
+    };
+    let image = RgbaImage::from_pixel(width, height, PLACEHOLDER_COLOR);
+    let mut png_data = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png)?;
+    Ok(png_data)
+}
@@ -0,0 +1,98 @@
+//! src/pipeline/style_reference.rs
+//!
+//! An optional pre-pass of the narrative visualization pipeline that matches
+//! the storyboard to an existing illustration style or brand look. The image
+//! given with `--style-reference` is described by a vision model, in terms of
+//! its medium, line work, palette, lighting, and texture but not its content,
+//! and stage 2 blends that description into every image prompt.
+
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use std::fs;
+use std::path::Path;
+use tracing::{info, instrument};
+
+/// A reference image, as loaded from disk.
+pub struct ReferenceImage {
+    pub data: Vec<u8>,
+    /// The MIME type of the image, e.g. `image/png`.
+    pub media_type: &'static str,
+}
+
+impl ReferenceImage {
+    /// Loads a reference image in one of the formats the vision model
+    /// accepts: PNG, JPEG, WebP, or GIF.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` if the file has another extension or cannot be read.
+    pub fn load(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        let media_type = match extension.as_deref() {
+            Some("png") => "image/png",
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            Some("gif") => "image/gif",
+            _ => {
+                return Err(Error::Config(format!(
+                    "The style reference '{}' must be a PNG, JPEG, WebP, or GIF image.",
+                    path.display()
+                )))
+            }
+        };
+        let data = fs::read(path).map_err(|e| {
+            Error::Config(format!(
+                "Could not read the style reference '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(Self { data, media_type })
+    }
+}
+
+/// Describes the visual style of a reference image.
+///
+/// # Returns
+///
+/// A short paragraph describing the style, for stage 2 to blend into every
+/// image prompt.
+#[instrument(skip_all)]
+pub async fn describe_style(client: &OpenAIClient, reference: &ReferenceImage) -> Result<String> {
+    info!("Describing the style of the reference image.");
+
+    let system_prompt = "You are an art director who briefs illustrators. Your task is to look at a reference illustration and describe its visual style precisely enough that other artists can reproduce that style in entirely different pictures.";
+    let user_prompt = "Describe the visual style of this image in one paragraph of at most 80 words: the medium and rendering technique, line work, color palette, lighting, texture, level of detail, and overall mood. Describe only the style. Do not mention the subjects, characters, objects, text, or setting shown in the image. Reply with the description only.";
+
+    let description = client
+        .describe_image(
+            system_prompt,
+            user_prompt,
+            &reference.data,
+            reference.media_type,
+        )
+        .await?;
+    let description = description.trim().to_string();
+    if description.is_empty() {
+        return Err(Error::Pipeline(
+            "The style reference description is empty.".to_string(),
+        ));
+    }
+
+    info!("Successfully described the reference style.");
+    Ok(description)
+}
+
+/// Renders the reference style as a section of the stage 2 prompt, or an
+/// empty string if there is none.
+pub fn prompt_section(description: Option<&str>) -> String {
+    match description {
+        Some(description) => format!(
+            "Reference style. Match the look of an existing illustration, described below, blending it with the artistic style above; where they conflict, the reference style wins:\n{}",
+            description.trim()
+        ),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,149 @@
+//! src/plan.rs
+//!
+//! Prints the scene plan for `--dry-run`: every scene with the image prompt
+//! generated for it, and the estimated cost of the image and narration
+//! stages, so that the plan can be vetted before any of it is paid for. The scenes of a book
+//! split into chapters are listed under their chapter's title.
+
+use crate::openai_client::{self, ImageBackendKind, ImageQuality};
+use crate::pipeline::stage_2_prompt_generation::VisualPrompt;
+use crate::pipeline::stage_3_image_generation::ImageOptions;
+use colored::Colorize;
+
+/// The longest prompt, in characters, that DALL-E 3 accepts.
+const MAX_PROMPT_CHARS: usize = 4000;
+
+/// The scene plan of one chapter.
+pub struct ChapterPlan<'a> {
+    /// The chapter's heading, or `None` if the narrative was not split.
+    pub title: Option<&'a str>,
+    /// The visual prompts from stage 2.
+    pub prompts: &'a [VisualPrompt],
+    /// Whether an image is already saved for each prompt, in which case it
+    /// would be reused instead of paid for.
+    pub saved: Vec<bool>,
+    /// Whether the narration of each scene is already saved in the chosen voice.
+    pub narrated: Vec<bool>,
+}
+
+/// Prints the plan for generating an image for each prompt of each chapter.
+///
+/// # Arguments
+///
+/// * `chapters` - The prompts of each chapter, and which images are saved.
+/// * `options` - The size and quality the images would be generated with.
+/// * `narration` - Whether each scene would also be narrated.
+pub fn print_plan(chapters: &[ChapterPlan], options: ImageOptions, narration: bool) {
+    println!("\n{}", "Storyboard Plan".bold().underline());
+
+    let mut total_tokens = 0;
+    let mut total_cost = 0.0;
+    let mut to_generate = 0;
+    let mut total_prompts = 0;
+    for chapter in chapters {
+        if let Some(title) = chapter.title {
+            println!("\n{}", title.magenta().bold());
+        }
+        total_prompts += chapter.prompts.len();
+
+        for (index, prompt) in chapter.prompts.iter().enumerate() {
+            let size = options.size_for(prompt);
+            let tokens = openai_client::image_prompt_tokens(&prompt.image_prompt);
+            let price = options.price_usd(size);
+            let cost = if chapter.saved.get(index).copied().unwrap_or(false) {
+                "already saved".to_string()
+            } else {
+                total_tokens += tokens;
+                total_cost += price;
+                to_generate += 1;
+                format!("${:.3}", price)
+            };
+
+            print_scene(
+                index,
+                chapter.prompts.len(),
+                prompt,
+                size.dimensions(),
+                options,
+                tokens,
+                &cost,
+            );
+
+            if narration {
+                let characters = prompt.original_text.trim().chars().count();
+                let price = openai_client::speech_price_usd(prompt.original_text.trim());
+                let cost = if chapter.narrated.get(index).copied().unwrap_or(false) {
+                    "already saved".to_string()
+                } else {
+                    total_cost += price;
+                    format!("${:.3}", price)
+                };
+                println!(
+                    "  {} {} characters, {}",
+                    "Narration:".bold(),
+                    characters,
+                    cost
+                );
+            }
+        }
+    }
+
+    println!(
+        "\n{} {} of {} images to generate, {} prompt tokens, estimated cost {}",
+        "Total:".bold(),
+        to_generate,
+        total_prompts,
+        total_tokens,
+        format!("${:.2}", total_cost).green().bold()
+    );
+    println!("Run again with --resume to generate the images from this plan.");
+}
+
+/// Prints the plan of a single scene.
+fn print_scene(
+    index: usize,
+    scene_count: usize,
+    prompt: &VisualPrompt,
+    dimensions: &str,
+    options: ImageOptions,
+    tokens: usize,
+    cost: &str,
+) {
+    println!(
+        "\n{} {}",
+        format!("Scene {}/{}:", index + 1, scene_count)
+            .cyan()
+            .bold(),
+        prompt.scene_description.trim()
+    );
+    println!(
+        "  {} {}, {}, {} prompt tokens, {}",
+        "Image:".bold(),
+        dimensions,
+        quality_name(options.quality),
+        tokens,
+        cost
+    );
+    println!("  {} {}", "Prompt:".bold(), prompt.image_prompt.trim());
+    if let Some(negative_prompt) = &prompt.negative_prompt {
+        println!("  {} {}", "Negative:".bold(), negative_prompt);
+    }
+    if options.backend == ImageBackendKind::DallE && prompt.image_prompt.len() > MAX_PROMPT_CHARS {
+        println!(
+            "  {}",
+            format!(
+                "Warning: the prompt is {} characters long, over the {}-character limit of the image model.",
+                prompt.image_prompt.len(),
+                MAX_PROMPT_CHARS
+            )
+            .yellow()
+        );
+    }
+}
+
+fn quality_name(quality: ImageQuality) -> &'static str {
+    match quality {
+        ImageQuality::Standard => "standard quality",
+        ImageQuality::Hd => "HD quality",
+    }
+}
@@ -0,0 +1,72 @@
+//! src/progress.rs
+//!
+//! The live progress display of the pipeline. Each stage gets a progress bar
+//! showing how much of its work is done, the elapsed time, and the API cost
+//! accumulated so far. The display is drawn on stderr only when it is a
+//! terminal; otherwise the log lines are the only feedback. The cost is
+//! counted from when the display is created, so each narrative of a batch
+//! shows its own.
+
+use crate::openai_client::CostTracker;
+use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+const BAR_TEMPLATE: &str =
+    "{spinner:.green} {prefix:>7.bold} [{bar:30.cyan/blue}] {pos}/{len} [{elapsed_precise}] {cost:.yellow} {msg}";
+
+/// How often the spinner, elapsed time, and cost are redrawn.
+const TICK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The progress bars of a pipeline run.
+pub struct Progress {
+    multi: MultiProgress,
+    cost: CostTracker,
+    /// The cost already tracked when the display was created.
+    cost_at_start: f64,
+    started: Instant,
+}
+
+impl Progress {
+    /// Creates the display, showing the cost tracked by `cost`.
+    pub fn new(cost: CostTracker) -> Self {
+        Self {
+            multi: MultiProgress::new(),
+            cost_at_start: cost.total_usd(),
+            cost,
+            started: Instant::now(),
+        }
+    }
+
+    /// Adds a progress bar labelled `prefix` with `len` steps. The length can
+    /// grow later, e.g. as the scenes of each chapter are detected.
+    pub fn add_bar(&self, prefix: &'static str, len: u64) -> ProgressBar {
+        let cost = self.cost.clone();
+        let cost_at_start = self.cost_at_start;
+        let style = ProgressStyle::with_template(BAR_TEMPLATE)
+            .expect("the progress bar template is valid")
+            .with_key("cost", move |_: &ProgressState, w: &mut dyn Write| {
+                let _ = write!(w, "${:.2}", cost.total_usd() - cost_at_start);
+            })
+            .progress_chars("=> ");
+        let bar = self
+            .multi
+            .add(ProgressBar::new(len).with_style(style).with_prefix(prefix));
+        bar.enable_steady_tick(TICK_INTERVAL);
+        bar
+    }
+
+    /// The API cost of the run so far, in US dollars.
+    pub fn cost_usd(&self) -> f64 {
+        self.cost.total_usd() - self.cost_at_start
+    }
+
+    /// A one-line summary of the run's duration and API cost.
+    pub fn summary(&self) -> String {
+        format!(
+            "Finished in {:.1}s, API cost ${:.2}",
+            self.started.elapsed().as_secs_f64(),
+            self.cost_usd()
+        )
+    }
+}
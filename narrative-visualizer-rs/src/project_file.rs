@@ -0,0 +1,255 @@
+//! src/project_file.rs
+//!
+//! The storyboard project file, `storyboard.json`. It captures the scenes,
+//! prompts, and image paths of a run together with the settings it was made
+//! with, in a form meant to be edited by hand or by other tools. `export`
+//! writes it from a project directory, and `import` assembles a storyboard
+//! from it again, so hand-tuned text, reordered scenes, and replaced images
+//! make it into the output without running the pipeline.
+//!
+//! Image paths are relative to the project file, so the file and its images
+//! can be moved together. Narration is not part of the project file.
+
+use crate::chapters::ChapterHeading;
+use crate::checkpoint::{Checkpoint, Manifest};
+use crate::error::{Error, Result};
+use crate::output_assembler::StoryboardChapter;
+use crate::pipeline::stage_1_scene_detection::Orientation;
+use crate::pipeline::stage_3_image_generation::StoryboardFrame;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// The default path of an exported project file.
+pub const DEFAULT_PROJECT_FILE: &str = "output/storyboard.json";
+
+/// The version of the project file format written by `export`.
+const PROJECT_FILE_VERSION: u32 = 1;
+
+/// A storyboard, as saved in a project file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectFile {
+    /// The version of the format, for future changes to it.
+    pub version: u32,
+    /// The settings of the run the storyboard was exported from. Informative
+    /// only; `import` does not use them.
+    #[serde(default)]
+    pub settings: Option<Manifest>,
+    pub chapters: Vec<ProjectChapter>,
+}
+
+/// A chapter of the storyboard, as saved in a project file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectChapter {
+    /// The chapter's position in the book, or `None` if the narrative was not split.
+    #[serde(default)]
+    pub number: Option<usize>,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub scenes: Vec<ProjectScene>,
+}
+
+/// A scene of the storyboard, as saved in a project file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectScene {
+    pub description: String,
+    pub original_text: String,
+    pub image_prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<Orientation>,
+    /// The scene's image, relative to the project file, or `None` if it has
+    /// not been generated yet.
+    #[serde(default)]
+    pub image: Option<PathBuf>,
+}
+
+/// Exports the state saved in a project directory as a project file.
+///
+/// # Returns
+///
+/// The number of scenes exported.
+///
+/// # Errors
+///
+/// Returns `Error::Checkpoint` if the directory holds no saved state, and
+/// `Error::Project` if it holds no scenes with prompts.
+pub fn export_project(project_dir: &Path, output_path: &Path) -> Result<usize> {
+    info!(
+        "Exporting the project in '{}' to '{}'...",
+        project_dir.display(),
+        output_path.display()
+    );
+
+    let (checkpoint, manifest) = Checkpoint::open_existing(project_dir)?;
+    // Runs that did not save their chapters were never split into any.
+    let headings = checkpoint.load_chapters()?.unwrap_or_else(|| {
+        vec![ChapterHeading {
+            number: None,
+            title: None,
+        }]
+    });
+
+    let output_dir = match output_path.parent() {
+        Some(parent_dir) if !parent_dir.as_os_str().is_empty() => parent_dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    fs::create_dir_all(&output_dir)?;
+
+    let mut chapters = Vec::with_capacity(headings.len());
+    let mut scene_count = 0;
+    let mut missing_images = 0;
+    for heading in headings {
+        let chapter_checkpoint = checkpoint.chapter(heading.number);
+        let Some(visual_prompts) = chapter_checkpoint.load_prompts()? else {
+            warn!(
+                "Skipping {}, which has no saved prompts.",
+                heading.title.as_deref().unwrap_or("the narrative")
+            );
+            continue;
+        };
+
+        let mut scenes = Vec::with_capacity(visual_prompts.len());
+        for (index, prompt) in visual_prompts.into_iter().enumerate() {
+            let image_path = chapter_checkpoint.image_path(index);
+            let image = if image_path.is_file() {
+                Some(relative_path(&image_path, &output_dir))
+            } else {
+                missing_images += 1;
+                None
+            };
+            scenes.push(ProjectScene {
+                description: prompt.scene_description,
+                original_text: prompt.original_text,
+                image_prompt: prompt.image_prompt,
+                negative_prompt: prompt.negative_prompt,
+                orientation: prompt.orientation,
+                image,
+            });
+        }
+        scene_count += scenes.len();
+        chapters.push(ProjectChapter {
+            number: heading.number,
+            title: heading.title,
+            scenes,
+        });
+    }
+    if scene_count == 0 {
+        return Err(Error::Project(format!(
+            "'{}' holds no scenes with prompts to export.",
+            project_dir.display()
+        )));
+    }
+    if missing_images > 0 {
+        warn!(
+            "{} scenes have no image yet; add their paths before importing.",
+            missing_images
+        );
+    }
+
+    let project_file = ProjectFile {
+        version: PROJECT_FILE_VERSION,
+        settings: Some(manifest),
+        chapters,
+    };
+    fs::write(output_path, serde_json::to_string_pretty(&project_file)?)?;
+
+    info!("Successfully exported {} scenes.", scene_count);
+    Ok(scene_count)
+}
+
+/// Loads a project file and the images it refers to, ready to be assembled.
+///
+/// # Errors
+///
+/// Returns `Error::Project` if the file cannot be read or parsed, was written
+/// by a newer version, or refers to images that are missing.
+pub fn import_project(path: &Path) -> Result<Vec<StoryboardChapter>> {
+    info!("Importing the storyboard from '{}'...", path.display());
+
+    let file_content = fs::read_to_string(path)
+        .map_err(|e| Error::Project(format!("Could not read '{}': {}", path.display(), e)))?;
+    let project_file: ProjectFile = serde_json::from_str(&file_content)
+        .map_err(|e| Error::Project(format!("Could not parse '{}': {}", path.display(), e)))?;
+    if project_file.version > PROJECT_FILE_VERSION {
+        return Err(Error::Project(format!(
+            "'{}' has format version {}, but only versions up to {} are supported.",
+            path.display(),
+            project_file.version,
+            PROJECT_FILE_VERSION
+        )));
+    }
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut chapters = Vec::with_capacity(project_file.chapters.len());
+    for chapter in project_file.chapters {
+        let mut frames = Vec::with_capacity(chapter.scenes.len());
+        for (index, scene) in chapter.scenes.into_iter().enumerate() {
+            let scene_label = match &chapter.title {
+                Some(title) => format!("scene {} of '{}'", index + 1, title),
+                None => format!("scene {}", index + 1),
+            };
+            let image = scene
+                .image
+                .ok_or_else(|| Error::Project(format!("No image is given for {}.", scene_label)))?;
+            let image_path = base_dir.join(image);
+            let image_data = fs::read(&image_path).map_err(|e| {
+                Error::Project(format!(
+                    "Could not read the image of {} from '{}': {}",
+                    scene_label,
+                    image_path.display(),
+                    e
+                ))
+            })?;
+            frames.push(StoryboardFrame {
+                original_text: scene.original_text,
+                scene_description: scene.description,
+                image_prompt: scene.image_prompt,
+                image_data,
+                narration: None,
+                error: None,
+            });
+        }
+        chapters.push(StoryboardChapter {
+            number: chapter.number,
+            title: chapter.title,
+            frames,
+        });
+    }
+    if chapters.iter().all(|chapter| chapter.frames.is_empty()) {
+        return Err(Error::Project(format!(
+            "'{}' contains no scenes.",
+            path.display()
+        )));
+    }
+
+    info!(
+        "Imported {} scenes.",
+        chapters
+            .iter()
+            .map(|chapter| chapter.frames.len())
+            .sum::<usize>()
+    );
+    Ok(chapters)
+}
+
+/// The path of `path` relative to `base_dir`, or `path` itself if either
+/// cannot be resolved.
+fn relative_path(path: &Path, base_dir: &Path) -> PathBuf {
+    let (Ok(path), Ok(base_dir)) = (path.canonicalize(), base_dir.canonicalize()) else {
+        return path.to_path_buf();
+    };
+    let common = path
+        .components()
+        .zip(base_dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in base_dir.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(path.components().skip(common));
+    relative
+}
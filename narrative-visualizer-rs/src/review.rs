@@ -0,0 +1,174 @@
+//! src/review.rs
+//!
+//! The interactive review of the visual prompts between stage 2 and stage 3,
+//! enabled with `--review`. The prompts of each chapter are opened as JSON in
+//! the user's editor (`$VISUAL`, `$EDITOR`, or `vi`), where scenes can be
+//! edited, deleted, or reordered before any image is paid for. The revised
+//! list is saved to the checkpoint, keeping the images and narration already
+//! generated for the scenes that did not change.
+
+use crate::checkpoint::Checkpoint;
+use crate::error::{Error, Result};
+use crate::pipeline::stage_2_prompt_generation::VisualPrompt;
+use colored::Colorize;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::{self, Command};
+use tracing::info;
+
+/// The editor used when neither `$VISUAL` nor `$EDITOR` is set.
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Lets the user revise the visual prompts of a chapter in their editor.
+///
+/// # Arguments
+///
+/// * `prompts` - The visual prompts from stage 2, replaced by the revised list.
+/// * `title` - The chapter's title, if the narrative was split into chapters.
+/// * `checkpoint` - Where the revised prompts are saved.
+///
+/// # Errors
+///
+/// Returns `Error::Review` if there is no terminal to review in, the editor
+/// cannot be run, or the user gives up on a list that does not parse.
+pub fn review_prompts(
+    prompts: &mut Vec<VisualPrompt>,
+    title: Option<&str>,
+    checkpoint: &Checkpoint,
+) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(Error::Review(
+            "--review needs an interactive terminal.".to_string(),
+        ));
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+    let review_file = env::temp_dir().join(format!("storyboard-review-{}.json", process::id()));
+    fs::write(&review_file, serde_json::to_string_pretty(prompts)?)?;
+
+    println!(
+        "\n{} {} scenes{} in '{}'. Edit, delete, or reorder them, then save and close the editor.",
+        "Reviewing".bold(),
+        prompts.len(),
+        title
+            .map(|title| format!(" of '{}'", title))
+            .unwrap_or_default(),
+        editor
+    );
+    let revised = loop {
+        let revised = open_editor(&editor, &review_file).and_then(|()| {
+            let content = fs::read_to_string(&review_file)?;
+            let revised: Vec<VisualPrompt> = serde_json::from_str(&content)?;
+            if revised.is_empty() {
+                return Err(Error::Review(
+                    "At least one scene must be kept.".to_string(),
+                ));
+            }
+            Ok(revised)
+        });
+        match revised {
+            Ok(revised) => break revised,
+            Err(e) => {
+                println!("{} {}", "The revised scenes are invalid:".red(), e);
+                if !confirm("Edit them again?")? {
+                    fs::remove_file(&review_file)?;
+                    return Err(Error::Review("The scene review was abandoned.".to_string()));
+                }
+            }
+        }
+    };
+    fs::remove_file(&review_file)?;
+
+    if revised_matches(prompts, &revised) {
+        info!("The scenes were not changed.");
+        return Ok(());
+    }
+
+    // Images and narration are saved by position, so they follow their
+    // scene to its new position, and are discarded if it changed.
+    let image_sources = match_sources(prompts, &revised, |old, new| {
+        old.image_prompt == new.image_prompt
+            && old.negative_prompt == new.negative_prompt
+            && old.orientation == new.orientation
+    });
+    let narration_sources = match_sources(prompts, &revised, |old, new| {
+        old.original_text.trim() == new.original_text.trim()
+    });
+    checkpoint.save_revised_prompts(&revised, &image_sources, &narration_sources)?;
+
+    info!(
+        "Continuing with {} revised scenes (previously {}).",
+        revised.len(),
+        prompts.len()
+    );
+    *prompts = revised;
+    Ok(())
+}
+
+/// Opens `path` in the editor and waits for it to close. The editor may be
+/// given with arguments, e.g. `code --wait`.
+fn open_editor(editor: &str, path: &Path) -> Result<()> {
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| Error::Review("The editor command is empty.".to_string()))?;
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|e| Error::Review(format!("Could not run the editor '{}': {}", editor, e)))?;
+    if !status.success() {
+        return Err(Error::Review(format!(
+            "The editor '{}' exited with {}.",
+            editor, status
+        )));
+    }
+    Ok(())
+}
+
+/// Asks a yes-or-no question on the terminal, defaulting to yes.
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [Y/n] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(!answer.trim().to_lowercase().starts_with('n'))
+}
+
+/// Whether the revised list is the same as the original one.
+fn revised_matches(prompts: &[VisualPrompt], revised: &[VisualPrompt]) -> bool {
+    prompts.len() == revised.len()
+        && prompts.iter().zip(revised).all(|(old, new)| {
+            old.scene_description == new.scene_description
+                && old.original_text == new.original_text
+                && old.image_prompt == new.image_prompt
+                && old.negative_prompt == new.negative_prompt
+                && old.orientation == new.orientation
+        })
+}
+
+/// For each revised scene, the position of an original scene it can reuse
+/// the saved output of, as decided by `reusable`. Each original scene is
+/// reused at most once.
+fn match_sources(
+    prompts: &[VisualPrompt],
+    revised: &[VisualPrompt],
+    reusable: impl Fn(&VisualPrompt, &VisualPrompt) -> bool,
+) -> Vec<Option<usize>> {
+    let mut used = vec![false; prompts.len()];
+    revised
+        .iter()
+        .map(|new| {
+            let source = prompts
+                .iter()
+                .enumerate()
+                .position(|(index, old)| !used[index] && reusable(old, new))?;
+            used[source] = true;
+            Some(source)
+        })
+        .collect()
+}
@@ -0,0 +1,32 @@
+//! tests/character_sheets.rs
+//!
+//! The character sheet pre-pass against the mock provider: the schema the
+//! sheets are requested with, and the sheets read from a scripted reply.
+
+use ai_core::{MockClient, ProviderConfig, ResponseFormat};
+use narrative_visualizer_rs::openai_client::OpenAIClient;
+use narrative_visualizer_rs::pipeline::character_sheets::extract_characters;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn reads_the_character_sheets_requested_with_their_schema() {
+    let mock = Arc::new(MockClient::new([r#"{"characters": [
+        {"name": "Elara", "aliases": ["the detective"], "appearance": "A tall woman in a grey trench coat."},
+        {"name": "The Cat", "appearance": "A thin black cat with one white paw."}
+    ]}"#]));
+    let client = OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap();
+
+    let characters = extract_characters(&client, "Elara followed the cat.")
+        .await
+        .unwrap();
+
+    assert_eq!(characters.len(), 2);
+    assert_eq!(characters[0].aliases, ["the detective"]);
+    // Aliases are optional in the reply.
+    assert!(characters[1].aliases.is_empty());
+    let requests = mock.requests();
+    let Some(ResponseFormat::JsonSchema { json_schema }) = &requests[0].response_format else {
+        panic!("the character sheets are not requested with a JSON schema");
+    };
+    assert_eq!(json_schema.name, "CharacterExtractionResponse");
+}
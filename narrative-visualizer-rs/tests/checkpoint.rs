@@ -0,0 +1,35 @@
+//! tests/checkpoint.rs
+//!
+//! Resuming from a project directory after several settings changed at once.
+
+use narrative_visualizer_rs::checkpoint::{Checkpoint, RunSettings};
+use std::fs;
+
+fn settings<'a>(style: &'a str, style_reference: &'a [u8]) -> RunSettings<'a> {
+    RunSettings {
+        narrative_text: "Elara followed the cat.",
+        split_on: None,
+        scene_limits: "at most 10 scenes".to_string(),
+        style,
+        character_sheets: false,
+        annotations: None,
+        style_reference: Some(style_reference),
+        image_options: "1024x1024, standard".to_string(),
+    }
+}
+
+#[test]
+fn discards_the_style_reference_when_the_style_changes_too() {
+    let dir = std::env::temp_dir().join(format!("checkpoint-test-{}", std::process::id()));
+    let checkpoint = Checkpoint::open(&dir, &settings("noir", b"first image"), false).unwrap();
+    checkpoint
+        .save_style_reference("Muted greys and hard shadows.")
+        .unwrap();
+
+    let checkpoint =
+        Checkpoint::open(&dir, &settings("watercolor", b"second image"), true).unwrap();
+    let style_reference = checkpoint.load_style_reference().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(style_reference, None);
+}
@@ -0,0 +1,125 @@
+//! tests/detect_scenes.rs
+//!
+//! Stage 1 against the mock provider: the recorded scenes of the sample
+//! story, the scene limits as the prompt states them, and the merging of
+//! scenes the model makes too short.
+
+use ai_core::{MockClient, ProviderConfig, ResponseFormat};
+use narrative_visualizer_rs::error::Error;
+use narrative_visualizer_rs::openai_client::OpenAIClient;
+use narrative_visualizer_rs::pipeline::stage_1_scene_detection::{
+    detect_scenes, Orientation, SceneLimits,
+};
+use std::sync::Arc;
+
+const SAMPLE_STORY: &str = include_str!("../input/sample_story.txt");
+
+#[tokio::test]
+async fn splits_the_sample_story_into_the_recorded_scenes() {
+    let mock = MockClient::fixture(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/detect_scenes_sample_story.json"
+    ))
+    .unwrap();
+    let client = OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap();
+    let limits = SceneLimits {
+        max_scenes: Some(3),
+        min_scene_length: 0,
+    };
+
+    let scenes = detect_scenes(&client, SAMPLE_STORY, limits).await.unwrap();
+
+    let orientations: Vec<_> = scenes.iter().map(|scene| scene.orientation).collect();
+    assert_eq!(
+        orientations,
+        [Some(Orientation::Portrait), Some(Orientation::Landscape)]
+    );
+    // The original texts reconstruct the story, as the prompt demands.
+    let original_text: String = scenes
+        .iter()
+        .map(|scene| scene.original_text.as_str())
+        .collect();
+    assert_eq!(original_text, SAMPLE_STORY);
+}
+
+#[tokio::test]
+async fn states_the_scene_limits_in_the_prompt() {
+    let mock = Arc::new(MockClient::new([
+        r#"{"scenes": [{"description": "A street.", "originalText": "Rain fell."}]}"#,
+    ]));
+    let client = OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap();
+    let limits = SceneLimits {
+        max_scenes: Some(12),
+        min_scene_length: 300,
+    };
+    detect_scenes(&client, "Rain fell.", limits).await.unwrap();
+
+    let request = &mock.requests()[0];
+    let user_prompt = &request.messages[1].content;
+    assert!(user_prompt.contains(
+        "Use no more than 12 scenes. Each scene should span at least 300 characters of the original text. \
+         Keep a run of dialogue that happens in one place within a single scene"
+    ));
+    assert!(user_prompt.contains("\"\"\"\n        Rain fell.\n        \"\"\""));
+    assert!(request.messages[0].content.contains("film director"));
+    let Some(ResponseFormat::JsonSchema { json_schema }) = &request.response_format else {
+        panic!("the scenes are not requested with a JSON schema");
+    };
+    assert_eq!(json_schema.name, "SceneDetectionResponse");
+
+    // Without limits, the prompt leaves the number of scenes to the model.
+    let mock = Arc::new(MockClient::new([
+        r#"{"scenes": [{"description": "A street.", "originalText": "Rain fell."}]}"#,
+    ]));
+    let client = OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap();
+    detect_scenes(&client, "Rain fell.", SceneLimits::default())
+        .await
+        .unwrap();
+    assert!(!mock.requests()[0].messages[1]
+        .content
+        .contains("Use no more than"));
+}
+
+#[tokio::test]
+async fn merges_a_short_scene_into_its_neighbor() {
+    let mock = Arc::new(MockClient::new([r#"{"scenes": [
+        {"description": "A door.", "originalText": "The door creaked.", "orientation": "portrait"},
+        {"description": "A hallway.", "originalText": "A long, dark hallway stretched ahead of her.", "orientation": "diagonal"}
+    ]}"#]));
+    let client = OpenAIClient::new(&ProviderConfig::mock(mock)).unwrap();
+    let limits = SceneLimits {
+        max_scenes: None,
+        min_scene_length: 30,
+    };
+
+    let scenes = detect_scenes(
+        &client,
+        "The door creaked. A long, dark hallway stretched ahead of her.",
+        limits,
+    )
+    .await
+    .unwrap();
+
+    let [scene] = scenes.as_slice() else {
+        panic!("expected the scenes to be merged, got {}", scenes.len());
+    };
+    // The longer scene's description wins, and its unknown framing is dropped.
+    assert_eq!(scene.description, "A hallway.");
+    assert_eq!(scene.orientation, None);
+    assert_eq!(
+        scene.original_text,
+        "The door creaked.\n\nA long, dark hallway stretched ahead of her."
+    );
+}
+
+#[tokio::test]
+async fn fails_when_the_model_finds_no_scenes() {
+    let client = OpenAIClient::new(&ProviderConfig::mock(Arc::new(MockClient::new([
+        r#"{"scenes": []}"#,
+    ]))))
+    .unwrap();
+
+    let result = detect_scenes(&client, SAMPLE_STORY, SceneLimits::default()).await;
+
+    assert!(matches!(result, Err(Error::Pipeline(_))));
+}
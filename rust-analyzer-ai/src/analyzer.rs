@@ -2,51 +2,433 @@
 //!
 //! This module contains the core logic for the code analysis process.
 //! It orchestrates reading files, sending them to the OpenAI client for
-//! review, and displaying the results.
+//! review, and parsing the reply into typed findings.
 
-use crate::error::Result;
+use crate::cache::AnalysisCache;
+use crate::diagnostics::{self, DiagnosticIndex};
+use crate::error::{Error, Result};
+use crate::files;
+use crate::findings::{FileAnalysis, Finding};
+use crate::focus::{self, FocusArea};
 use crate::openai;
-use colored::Colorize;
+use crate::rules::{self, RulePack};
+use crate::suppression;
+use crate::symbols::{self, Definition, FunctionTarget};
+use crate::workspace::{CrateInfo, Workspace};
+use serde::Deserialize;
 use std::fs;
+use std::ops::RangeInclusive;
 use std::path::Path;
 
-/// Analyzes a single Rust source file using the OpenAI API.
-///
-/// This function performs the following steps:
-/// 1. Prints the name of the file being analyzed.
-/// 2. Reads the file's content into a string.
-/// 3. Passes the content to the provided OpenAI client.
-/// 4. Prints the AI-generated analysis in a formatted block.
-///
-/// # Arguments
-///
-/// * `client` - An instance of `openai::Client` to communicate with the API.
-/// * `file_path` - A reference to the path of the file to be analyzed.
-///
-/// # Returns
+/// The base instructions given to the model for every review.
+const SYSTEM_PROMPT: &str = "You are an expert Rust programmer with over 20 years of experience. \
+    You are acting as a code reviewer. Your goal is to provide concise, actionable feedback \
+    to help a developer improve their code. Focus on identifying anti-patterns, suggesting \
+    idiomatic Rust, improving clarity, and pointing out potential performance improvements. \
+    Do not comment on code style like formatting, as that is handled by rustfmt.";
+
+/// The output contract appended to the system prompt. Line numbers refer to the
+/// numbered listing sent in the user prompt.
+const OUTPUT_INSTRUCTIONS: &str = "Report each issue as a separate finding. The code is shown \
+    with line numbers; use them to give the exact location of each finding. \
+    Your final output must be a single, valid JSON object of the form \
+    {\"findings\": [{\"category\": \"kebab-case category, e.g. error-handling, performance, \
+    idiomatic-rust, api-design, concurrency, security, clarity\", \
+    \"severity\": \"one of: info, low, medium, high, critical\", \
+    \"title\": \"one-line summary\", \"message\": \"full explanation\", \
+    \"line\": 1, \"endLine\": 1, \"suggestion\": \"how to fix it\"}]}. \
+    Return an empty findings array if the code has no issues.";
+
+/// Extra instructions for a targeted review of a single function.
+const FUNCTION_REVIEW_INSTRUCTIONS: &str = "You are reviewing a single function in depth rather \
+    than a whole file. Examine its correctness, edge cases, error handling, panics, ownership \
+    and borrowing, allocations, and how it uses the types it depends on. Only report findings \
+    located in the function itself.";
+
+/// The shape of the JSON object the model is instructed to return.
+#[derive(Deserialize, Debug)]
+struct AnalysisResponse {
+    #[serde(default)]
+    findings: Vec<Finding>,
+}
+
+/// The request `Analyzer::analyze_file` would send for a file.
+#[derive(Debug)]
+pub struct PlannedRequest {
+    pub system_prompt: String,
+    pub user_prompt: String,
+    /// Whether the result is already cached, so no request would be sent.
+    pub cached: bool,
+}
+
+/// The engine that reviews source files and turns the replies into findings.
+pub struct Analyzer {
+    client: openai::Client,
+    focus: Vec<FocusArea>,
+    cache: Option<AnalysisCache>,
+    diagnostics: DiagnosticIndex,
+    workspace: Option<Workspace>,
+    rules: Vec<RulePack>,
+}
+
+impl Analyzer {
+    /// Creates a new `Analyzer` performing a general-purpose review.
+    pub fn new(client: openai::Client) -> Self {
+        Self {
+            client,
+            focus: Vec::new(),
+            cache: None,
+            diagnostics: DiagnosticIndex::default(),
+            workspace: None,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Restricts the review to the given focus areas.
+    ///
+    /// The specialized prompts replace the general reviewer persona, and any
+    /// finding outside the selected categories is discarded.
+    pub fn with_focus(mut self, focus: Vec<FocusArea>) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// Reuses results for unchanged files from `cache` instead of calling the API.
+    pub fn with_cache(mut self, cache: AnalysisCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Includes each file's existing compiler and Clippy diagnostics in its prompt.
+    pub fn with_diagnostics(mut self, diagnostics: DiagnosticIndex) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Adds the house rules of the given rule packs to every review.
+    pub fn with_rules(mut self, rules: Vec<RulePack>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Includes each file's crate and its dependency edges in the prompt, and
+    /// attributes every result to its crate.
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Analyzes a single Rust source file using the OpenAI API.
+    ///
+    /// This function performs the following steps:
+    /// 1. Reads the file's content into a string.
+    /// 2. Sends a line-numbered listing of the content to the OpenAI client,
+    ///    unless an identical request is already in the cache.
+    /// 3. Parses the reply into typed findings with file/line locations.
+    /// 4. Fingerprints each finding and drops those silenced by `ai-allow` comments.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - A reference to the path of the file to be analyzed.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `FileAnalysis` for the file, or an `Err` if any
+    /// step (file reading, API communication, response parsing) fails.
+    pub async fn analyze_file(&self, file_path: &Path) -> Result<FileAnalysis> {
+        // Read the file content into a string. The `?` operator will propagate
+        // any I/O errors, which our main function will handle.
+        let file_content = fs::read_to_string(file_path)?;
+        self.analyze_source(file_path, &file_content).await
+    }
+
+    /// Analyzes `file_content` as the content of `file_path`.
+    ///
+    /// This is used for unsaved editor buffers, whose content differs from
+    /// the file on disk. The path is still used for locations and context.
+    pub async fn analyze_source(
+        &self,
+        file_path: &Path,
+        file_content: &str,
+    ) -> Result<FileAnalysis> {
+        let system_prompt = self.system_prompt();
+        let user_prompt = self.user_prompt(file_path, file_content);
+        let (raw_findings, from_cache) = self.findings_for(&system_prompt, &user_prompt).await?;
+
+        let line_count = file_content.lines().count();
+        Ok(self.finish(
+            file_path,
+            file_content,
+            raw_findings,
+            1..=line_count,
+            from_cache,
+        ))
+    }
+
+    /// Performs a deep review of a single function located by `--function`.
+    ///
+    /// Only the function's lines are sent for review, together with the
+    /// crate-local definitions it depends on. Findings are reported against
+    /// the function's file and limited to its line range.
+    pub async fn analyze_function(&self, target: &FunctionTarget) -> Result<FileAnalysis> {
+        let file_content = fs::read_to_string(&target.file)?;
+
+        let system_prompt = format!(
+            "{}\n\n{}\n\n{}",
+            self.persona(),
+            FUNCTION_REVIEW_INSTRUCTIONS,
+            OUTPUT_INSTRUCTIONS
+        );
+        let user_prompt = self.function_prompt(target, &file_content);
+        let (raw_findings, from_cache) = self.findings_for(&system_prompt, &user_prompt).await?;
+
+        Ok(self.finish(
+            &target.file,
+            &file_content,
+            raw_findings,
+            target.start_line..=target.end_line,
+            from_cache,
+        ))
+    }
+
+    /// Returns the findings for a request, from the cache when possible.
+    ///
+    /// The second value is `true` if the findings were served from the cache.
+    async fn findings_for(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<(Vec<Finding>, bool)> {
+        let cache_key = AnalysisCache::key(user_prompt, self.client.model(), system_prompt);
+        if let Some(findings) = self.cache.as_ref().and_then(|cache| cache.get(&cache_key)) {
+            return Ok((findings, true));
+        }
+
+        let findings = self.request_findings(system_prompt, user_prompt).await?;
+        if let Some(cache) = &self.cache {
+            cache.put(&cache_key, &findings)?;
+        }
+        Ok((findings, false))
+    }
+
+    /// Turns the model's findings into the file's final result: applies the focus
+    /// filter, clamps locations to `reviewed_lines`, drops findings silenced by
+    /// `ai-allow` comments, and fingerprints the rest.
+    fn finish(
+        &self,
+        file_path: &Path,
+        file_content: &str,
+        raw_findings: Vec<Finding>,
+        reviewed_lines: RangeInclusive<usize>,
+        from_cache: bool,
+    ) -> FileAnalysis {
+        let uri = files::relative_uri(file_path);
+        let lines: Vec<&str> = file_content.lines().collect();
+        let findings = raw_findings
+            .into_iter()
+            .filter(|finding| self.is_in_focus(finding))
+            .map(|finding| clamp_location(finding, &reviewed_lines))
+            .filter(|finding| !suppression::is_allowed_inline(finding, &lines))
+            .map(|mut finding| {
+                finding.fingerprint = suppression::fingerprint(&uri, &finding, &lines);
+                finding
+            })
+            .collect();
+
+        FileAnalysis {
+            path: file_path.to_path_buf(),
+            findings,
+            from_cache,
+            crate_name: self.crate_for(file_path).map(|info| info.name.clone()),
+        }
+    }
+
+    /// Builds the request that `analyze_file` would send, without sending it.
+    pub fn plan_file(&self, file_path: &Path) -> Result<PlannedRequest> {
+        let file_content = fs::read_to_string(file_path)?;
+        let system_prompt = self.system_prompt();
+        let user_prompt = self.user_prompt(file_path, &file_content);
+        let cache_key = AnalysisCache::key(&user_prompt, self.client.model(), &system_prompt);
+        let cached = self
+            .cache
+            .as_ref()
+            .is_some_and(|cache| cache.contains(&cache_key));
+
+        Ok(PlannedRequest {
+            system_prompt,
+            user_prompt,
+            cached,
+        })
+    }
+
+    /// Sends the prompts to the model and parses the findings from its reply.
+    async fn request_findings(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<Vec<Finding>> {
+        // Use the client to send the code for analysis. This is an async operation.
+        let response_text = self
+            .client
+            .complete_json(system_prompt, user_prompt)
+            .await?;
+        let response: AnalysisResponse = serde_json::from_str(&response_text).map_err(|e| {
+            Error::OpenAI(format!(
+                "Failed to parse analysis response: {}. Response text: '{}'",
+                e, response_text
+            ))
+        })?;
+        Ok(response.findings)
+    }
+
+    /// Builds the user prompt: the numbered source plus any supporting context.
+    fn user_prompt(&self, file_path: &Path, file_content: &str) -> String {
+        let mut prompt = format!(
+            "Please review the following Rust code from `{}`:\n\n```rust\n{}\n```",
+            file_path.display(),
+            number_lines(file_content, 1)
+        );
+        if let Some(info) = self.crate_for(file_path) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&info.format_for_prompt());
+        }
+        let imports = symbols::imported_signatures(file_path, file_content);
+        if let Some(section) = format_definitions(
+            "The file imports these items from other modules of the same crate. Their \
+             signatures are provided for context only; do not report findings on them.",
+            &imports,
+        ) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&section);
+        }
+        let existing = self.diagnostics.for_file(file_path);
+        if let Some(section) = diagnostics::format_for_prompt(existing) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&section);
+        }
+        prompt
+    }
+
+    /// Builds the prompt for a single function: its numbered lines, the
+    /// definitions it depends on, and the diagnostics within its range.
+    fn function_prompt(&self, target: &FunctionTarget, file_content: &str) -> String {
+        let function_source = file_content
+            .lines()
+            .skip(target.start_line - 1)
+            .take(target.end_line + 1 - target.start_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut prompt = format!(
+            "Please perform a deep review of the function `{}` from `{}`:\n\n```rust\n{}\n```",
+            target.qualified_name,
+            target.file.display(),
+            number_lines(&function_source, target.start_line)
+        );
+
+        if let Some(section) = format_definitions(
+            "The function uses these definitions from the same crate. They are provided \
+             for context only; do not report findings on them.",
+            &target.dependencies,
+        ) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&section);
+        }
+
+        let existing: Vec<_> = self
+            .diagnostics
+            .for_file(&target.file)
+            .iter()
+            .filter(|diagnostic| (target.start_line..=target.end_line).contains(&diagnostic.line))
+            .cloned()
+            .collect();
+        if let Some(section) = diagnostics::format_for_prompt(&existing) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&section);
+        }
+        prompt
+    }
+
+    /// Builds the full system prompt: the reviewer persona plus the output contract.
+    fn system_prompt(&self) -> String {
+        format!("{}\n\n{}", self.persona(), OUTPUT_INSTRUCTIONS)
+    }
+
+    /// Returns the reviewer persona: the general one, or the focus-area prompts,
+    /// followed by the selected rule packs.
+    fn persona(&self) -> String {
+        let persona = if self.focus.is_empty() {
+            SYSTEM_PROMPT.to_string()
+        } else {
+            focus::build_focus_prompt(&self.focus)
+        };
+        if self.rules.is_empty() {
+            persona
+        } else {
+            format!("{}\n\n{}", persona, rules::build_rules_prompt(&self.rules))
+        }
+    }
+
+    /// Returns the workspace crate that owns `file_path`, if a workspace is set.
+    fn crate_for(&self, file_path: &Path) -> Option<&CrateInfo> {
+        self.workspace
+            .as_ref()
+            .and_then(|workspace| workspace.crate_for(file_path))
+    }
+
+    /// Returns `true` if the finding belongs to one of the selected focus areas.
+    ///
+    /// Violations of the selected rule packs are always kept.
+    fn is_in_focus(&self, finding: &Finding) -> bool {
+        self.focus.is_empty()
+            || self
+                .focus
+                .iter()
+                .any(|area| area.category() == finding.category)
+            || self
+                .rules
+                .iter()
+                .any(|pack| pack.category() == finding.category)
+    }
+}
+
+/// Prefixes every line of the source with its line number, starting at `first_line`.
+fn number_lines(content: &str, first_line: usize) -> String {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| format!("{:>4} | {}", first_line + index, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats supporting definitions as a prompt section under `heading`.
 ///
-/// A `Result<()>` which will be `Ok(())` on success, or an `Err` if any
-/// step (file reading, API communication) fails.
-pub async fn analyze_file(client: &openai::Client, file_path: &Path) -> Result<()> {
-    // Print a header for the file being analyzed.
-    println!("\n{}", "==================================================".blue());
-    println!(
-        "{} {}",
-        "Analyzing:".blue().bold(),
-        file_path.display().to_string().bright_white()
-    );
-    println!("{}", "==================================================".blue());
-
-    // Read the file content into a string. The `?` operator will propagate
-    // any I/O errors, which our main function will handle.
-    let file_content = fs::read_to_string(file_path)?;
-
-    // Use the client to send the code for analysis. This is an async operation.
-    let analysis_result = client.analyze_code(&file_content).await?;
-
-    // Print the analysis received from the AI.
-    println!("{}", "Analysis:".green().bold());
-    println!("{}", analysis_result.trim());
-
-    Ok(())
+/// Returns `None` if there are no definitions.
+fn format_definitions(heading: &str, definitions: &[Definition]) -> Option<String> {
+    if definitions.is_empty() {
+        return None;
+    }
+    let mut section = heading.to_string();
+    for definition in definitions {
+        section.push_str(&format!(
+            "\n\n`{}` from `{}`:\n```rust\n{}\n```",
+            definition.name,
+            files::relative_uri(&definition.file),
+            definition.source
+        ));
+    }
+    Some(section)
+}
+
+/// Discards line numbers the model reported outside of the reviewed lines.
+fn clamp_location(mut finding: Finding, reviewed_lines: &RangeInclusive<usize>) -> Finding {
+    let in_range = |line: &usize| reviewed_lines.contains(line);
+    finding.line = finding.line.filter(in_range);
+    finding.end_line = match finding.line {
+        Some(start) => finding
+            .end_line
+            .filter(|end| in_range(end) && *end >= start),
+        None => None,
+    };
+    finding
 }
@@ -0,0 +1,165 @@
+//! src/benches.rs
+//!
+//! This module implements benchmark suggestions for performance audits. For
+//! each file with performance findings, the model proposes criterion
+//! benchmarks for the functions it suspects are hot paths. Each proposal is
+//! written as a ready-to-compile file under the crate's `benches/` directory.
+
+use crate::error::{Error, Result};
+use crate::files;
+use crate::findings::FileAnalysis;
+use crate::openai;
+use colored::Colorize;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// The directory, relative to the crate's manifest, that receives the benchmarks.
+const BENCH_DIR: &str = "benches";
+
+const BENCH_SYSTEM_PROMPT: &str = "You are an expert in Rust performance engineering. You will \
+    be given a Rust source file and the performance findings reported for it. Identify the \
+    functions that are likely hot paths and write a criterion benchmark for each. Every \
+    benchmark must be a complete, ready-to-compile file for the crate's `benches/` directory: \
+    import the crate's items by the crate name given, use `criterion::{criterion_group, \
+    criterion_main, Criterion}` and `std::hint::black_box`, build realistic inputs, and end with \
+    `criterion_group!` and `criterion_main!`. Only benchmark items reachable from the crate's \
+    library target; if there is none, return no benchmarks. Your final output must be a \
+    single, valid JSON object of the form {\"benchmarks\": [{\"function\": \"path::to::function\", \
+    \"reason\": \"why it is likely hot\", \"name\": \"snake_case_benchmark_name\", \
+    \"code\": \"the complete benchmark file\"}]}.";
+
+/// A benchmark proposed for a suspected hot path.
+#[derive(Deserialize, Debug)]
+pub struct BenchSkeleton {
+    pub function: String,
+    pub reason: String,
+    pub name: String,
+    pub code: String,
+}
+
+/// The shape of the JSON object the model is instructed to return.
+#[derive(Deserialize, Debug)]
+struct BenchResponse {
+    #[serde(default)]
+    benchmarks: Vec<BenchSkeleton>,
+}
+
+/// Asks the model for benchmarks of the hot paths in an analyzed file and
+/// writes them under the crate's `benches/` directory.
+///
+/// Existing benchmark files are never overwritten. Returns the names of the
+/// benchmarks that were written, for the `[[bench]]` entries in `Cargo.toml`.
+pub async fn suggest_benchmarks(
+    client: &openai::Client,
+    analysis: &FileAnalysis,
+) -> Result<Vec<String>> {
+    let manifest_dir = files::find_manifest_dir(&analysis.path).ok_or_else(|| {
+        Error::Config(format!(
+            "No Cargo.toml found for '{}'",
+            analysis.path.display()
+        ))
+    })?;
+    let uri = files::relative_uri(&analysis.path);
+    println!("{} {}", "Suggesting benchmarks for:".magenta().bold(), uri);
+
+    let content = fs::read_to_string(&analysis.path)?;
+    let findings = analysis
+        .findings
+        .iter()
+        .map(|finding| {
+            let location = finding
+                .line
+                .map_or_else(String::new, |line| format!(" (line {})", line));
+            format!("- {}{}: {}", finding.title, location, finding.message)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let user_prompt = format!(
+        "Crate name: `{}`. Library target: {}.\n\nFile `{}`:\n\n```rust\n{}\n```\n\n\
+        Performance findings:\n{}",
+        crate_name(&manifest_dir).unwrap_or_else(|| "unknown".to_string()),
+        if manifest_dir.join("src/lib.rs").is_file() {
+            "yes"
+        } else {
+            "no"
+        },
+        uri,
+        content,
+        findings
+    );
+
+    let response_text = client
+        .complete_json(BENCH_SYSTEM_PROMPT, &user_prompt)
+        .await?;
+    let response: BenchResponse = serde_json::from_str(&response_text).map_err(|e| {
+        Error::OpenAI(format!(
+            "Failed to parse benchmark response: {}. Response text: '{}'",
+            e, response_text
+        ))
+    })?;
+
+    let bench_dir = manifest_dir.join(BENCH_DIR);
+    let mut written = Vec::new();
+    for skeleton in response.benchmarks {
+        let name = sanitize_name(&skeleton.name);
+        let bench_path = bench_dir.join(format!("{}.rs", name));
+        if name.is_empty() || bench_path.exists() {
+            println!(
+                "  {} {}: {} already exists or has an invalid name",
+                "Skipped:".yellow(),
+                skeleton.function,
+                bench_path.display()
+            );
+            continue;
+        }
+        fs::create_dir_all(&bench_dir)?;
+        fs::write(&bench_path, skeleton.code.trim().to_string() + "\n")?;
+        println!(
+            "  {} {} -> {} ({})",
+            "Benchmark:".green(),
+            skeleton.function,
+            files::relative_uri(&bench_path),
+            skeleton.reason.trim()
+        );
+        written.push(name);
+    }
+    Ok(written)
+}
+
+/// Prints the `Cargo.toml` entries needed to compile the written benchmarks.
+pub fn print_manifest_entries(names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+    println!(
+        "\n{}",
+        "Add these entries to Cargo.toml to run the benchmarks with `cargo bench`:".bold()
+    );
+    println!("\n[dev-dependencies]\ncriterion = \"0.5\"");
+    for name in names {
+        println!("\n[[bench]]\nname = \"{}\"\nharness = false", name);
+    }
+}
+
+/// Reads the package name from the crate's `Cargo.toml`.
+fn crate_name(manifest_dir: &Path) -> Option<String> {
+    let manifest: toml::Table = fs::read_to_string(manifest_dir.join("Cargo.toml"))
+        .ok()?
+        .parse()
+        .ok()?;
+    let name = manifest.get("package")?.get("name")?.as_str()?;
+    // Crate names are imported with underscores in place of hyphens.
+    Some(name.replace('-', "_"))
+}
+
+/// Restricts a benchmark name to the characters valid in a file and target name.
+fn sanitize_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
@@ -0,0 +1,80 @@
+//! src/cache.rs
+//!
+//! This module implements a local, content-addressed cache of analysis
+//! results. Entries are keyed by the SHA-256 of the exact prompts sent (which
+//! embed the file content) together with the model, so any change to the
+//! code, its context, the model, or the review instructions produces a fresh
+//! analysis while unchanged files are skipped on reruns.
+
+use crate::error::Result;
+use crate::findings::Finding;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+/// The cache directory used when no other path is given.
+pub const DEFAULT_CACHE_DIR: &str = ".ai-cache/analysis";
+
+/// Bumped whenever the way findings are produced changes in a way that is
+/// not visible in the prompt text, invalidating every existing entry.
+const PROMPT_VERSION: u32 = 1;
+
+/// The on-disk representation of a cached analysis.
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    findings: Vec<Finding>,
+}
+
+/// A directory of cached analysis results.
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+impl AnalysisCache {
+    /// Creates a cache rooted at `dir`. The directory is created on first write.
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Computes the cache key for a request from its prompts and model.
+    pub fn key(user_prompt: &str, model: &str, system_prompt: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(PROMPT_VERSION.to_le_bytes());
+        for part in [model, system_prompt, user_prompt] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Returns the cached findings for `key`, if present.
+    ///
+    /// Unreadable or corrupt entries are treated as cache misses.
+    pub fn get(&self, key: &str) -> Option<Vec<Finding>> {
+        let content = fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str::<CacheEntry>(&content)
+            .ok()
+            .map(|entry| entry.findings)
+    }
+
+    /// Returns `true` if an entry exists for `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.entry_path(key).is_file()
+    }
+
+    /// Stores the findings for `key`.
+    pub fn put(&self, key: &str, findings: &[Finding]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = CacheEntry {
+            findings: findings.to_vec(),
+        };
+        fs::write(self.entry_path(key), serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
@@ -2,32 +2,25 @@
 //!
 //! This module handles the application's configuration. It is responsible
 //! for loading secrets and settings from the environment, primarily the
-//! OpenAI API key from a `.env` file.
+//! model provider and its API key from a `.env` file.
 
-use crate::error::{Error, Result};
+use crate::error::Result;
+use ai_core::ProviderConfig;
 use dotenvy::dotenv;
-use std::env;
 
-const OPENAI_API_KEY: &str = "OPENAI_API_KEY";
-
-/// Retrieves the OpenAI API key from the environment.
+/// Retrieves the model provider settings from the environment.
 ///
 /// This function loads the `.env` file from the project directory and then
-/// attempts to read the `OPENAI_API_KEY` environment variable.
-///
-/// # Returns
-///
-/// A `Result` containing the API key as a `String` if successful.
+/// reads `AI_PROVIDER`, `AI_MODEL`, and the selected provider's settings,
+/// such as `OPENAI_API_KEY` or `OLLAMA_HOST`.
 ///
 /// # Errors
 ///
-/// Returns `Error::Config` if the `OPENAI_API_KEY` is not set.
-pub fn api_key() -> Result<String> {
+/// Returns an error if `AI_PROVIDER` names an unknown provider. A missing
+/// API key is only reported when the client is created.
+pub fn provider_config() -> Result<ProviderConfig> {
     // Attempt to load the .env file. Fails silently if not present.
     dotenv().ok();
 
-    // Read the variable, mapping the `VarError` to our custom `Error::Config`.
-    env::var(OPENAI_API_KEY).map_err(|_| {
-        Error::Config(format!("{} is not set in the .env file", OPENAI_API_KEY))
-    })
+    Ok(ProviderConfig::from_env()?)
 }
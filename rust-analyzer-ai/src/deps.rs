@@ -0,0 +1,397 @@
+//! src/deps.rs
+//!
+//! This module implements the `deps` subcommand. It reads a crate's
+//! `Cargo.toml` and the `Cargo.lock` of its workspace, summarizes each
+//! direct dependency (kind, features, transitive footprint, and how often the
+//! sources reference it), detects crates locked at several versions, and asks
+//! the model to flag unnecessary, heavyweight, or overlapping dependencies.
+
+use crate::error::{Error, Result};
+use crate::files;
+use crate::findings::Severity;
+use crate::openai;
+use colored::Colorize;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const DEPS_SYSTEM_PROMPT: &str = "You are an expert Rust programmer reviewing a crate's \
+    dependencies. You will be given its Cargo.toml and a summary of each direct dependency: \
+    its kind, enabled features, the number of packages it pulls in, and how many source files \
+    reference it, plus any crates locked at several versions. Explain the purpose of each \
+    direct dependency in one sentence. Then flag dependencies that look unnecessary (unused or \
+    trivially replaceable by the standard library), heavyweight (a large footprint for what \
+    the crate uses), or overlapping (several crates serving the same purpose), and duplicate \
+    versions worth consolidating. Your final output must be a single, valid JSON object of the \
+    form {\"purposes\": [{\"name\": \"crate\", \"purpose\": \"one sentence\"}], \
+    \"issues\": [{\"dependencies\": [\"crate\"], \"kind\": \"one of: unnecessary, heavyweight, \
+    overlapping, duplicate\", \"severity\": \"one of: info, low, medium, high, critical\", \
+    \"message\": \"full explanation\", \"suggestion\": \"how to consolidate or replace it\"}]}.";
+
+/// The dependency tables read from a manifest, with the kind they declare.
+const DEPENDENCY_TABLES: [(&str, &str); 3] = [
+    ("dependencies", "normal"),
+    ("dev-dependencies", "dev"),
+    ("build-dependencies", "build"),
+];
+
+/// A dependency declared directly in `Cargo.toml`.
+#[derive(Debug)]
+pub struct DirectDependency {
+    pub name: String,
+    /// `normal`, `dev`, or `build`.
+    pub kind: &'static str,
+    /// The version requirement, or the path/git source it comes from.
+    pub source: String,
+    pub features: Vec<String>,
+    pub optional: bool,
+    /// The number of packages in the lock file reachable from it, itself excluded.
+    pub transitive: Option<usize>,
+    /// The number of source files that mention the crate.
+    pub referencing_files: usize,
+}
+
+/// The local facts about a crate's dependencies, gathered without the model.
+#[derive(Debug)]
+pub struct DependencySummary {
+    pub manifest_path: PathBuf,
+    pub manifest: String,
+    pub dependencies: Vec<DirectDependency>,
+    /// The total number of packages in the lock file, if one was found.
+    pub locked_packages: Option<usize>,
+    /// Crates locked at more than one version, with their versions.
+    pub duplicates: BTreeMap<String, Vec<String>>,
+}
+
+/// The model's review of the dependencies.
+#[derive(Deserialize, Debug, Default)]
+pub struct DependencyReview {
+    #[serde(default)]
+    pub purposes: Vec<DependencyPurpose>,
+    #[serde(default)]
+    pub issues: Vec<DependencyIssue>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DependencyPurpose {
+    pub name: String,
+    pub purpose: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DependencyIssue {
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    pub kind: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+/// Summarizes the dependencies of the crate containing `path`, asks the model
+/// to review them, and prints the result.
+pub async fn review_dependencies(client: &openai::Client, path: &Path) -> Result<()> {
+    let summary = DependencySummary::collect(path)?;
+    summary.print();
+
+    eprintln!("-> Asking the model to review the dependencies...");
+    let response_text = client
+        .complete_json(DEPS_SYSTEM_PROMPT, &summary.to_prompt())
+        .await?;
+    let review: DependencyReview = serde_json::from_str(&response_text).map_err(|e| {
+        Error::OpenAI(format!(
+            "Failed to parse dependency review: {}. Response text: '{}'",
+            e, response_text
+        ))
+    })?;
+    review.print();
+    Ok(())
+}
+
+impl DependencySummary {
+    /// Reads the manifest of the crate containing `path` and its workspace's lock file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no `Cargo.toml` is found or a file cannot be parsed.
+    pub fn collect(path: &Path) -> Result<Self> {
+        let manifest_dir = files::find_manifest_dir(path).ok_or_else(|| {
+            Error::Config(format!("No Cargo.toml found for '{}'", path.display()))
+        })?;
+        let manifest_path = manifest_dir.join("Cargo.toml");
+        let manifest = fs::read_to_string(&manifest_path)?;
+        let mut dependencies = parse_manifest(&manifest)?;
+
+        // The lock file lives at the workspace root, which may be above the crate.
+        let lock = match manifest_dir
+            .ancestors()
+            .map(|dir| dir.join("Cargo.lock"))
+            .find(|candidate| candidate.is_file())
+        {
+            Some(lock_path) => Some(toml::from_str::<CargoLock>(&fs::read_to_string(
+                lock_path,
+            )?)?),
+            None => None,
+        };
+
+        let sources = read_sources(&manifest_dir);
+        for dependency in &mut dependencies {
+            dependency.transitive = lock
+                .as_ref()
+                .map(|lock| lock.transitive_count(&dependency.name));
+            let ident = dependency.name.replace('-', "_");
+            dependency.referencing_files = sources
+                .iter()
+                .filter(|source| mentions(source, &ident))
+                .count();
+        }
+
+        Ok(Self {
+            manifest_path,
+            manifest,
+            dependencies,
+            locked_packages: lock.as_ref().map(|lock| lock.package.len()),
+            duplicates: lock.map(|lock| lock.duplicates()).unwrap_or_default(),
+        })
+    }
+
+    /// Prints the locally gathered dependency facts.
+    pub fn print(&self) {
+        println!(
+            "\n{} {}",
+            "Dependencies of".blue().bold(),
+            files::relative_uri(&self.manifest_path).bright_white()
+        );
+        println!("Crate                        Kind    Transitive   Referenced  Source");
+        for dependency in &self.dependencies {
+            let transitive = dependency
+                .transitive
+                .map_or_else(|| "-".to_string(), |count| count.to_string());
+            println!(
+                "{:<28} {:<7} {:>10} {:>7} file(s)  {}{}",
+                dependency.name,
+                dependency.kind,
+                transitive,
+                dependency.referencing_files,
+                dependency.source,
+                if dependency.optional {
+                    " (optional)"
+                } else {
+                    ""
+                }
+            );
+        }
+
+        if let Some(total) = self.locked_packages {
+            println!("\n{} packages in Cargo.lock.", total);
+        }
+        for (name, versions) in &self.duplicates {
+            println!(
+                "{} {} is locked at {} versions: {}",
+                "Duplicate:".yellow(),
+                name,
+                versions.len(),
+                versions.join(", ")
+            );
+        }
+    }
+
+    /// Renders the manifest and the gathered facts as the review prompt.
+    fn to_prompt(&self) -> String {
+        let mut prompt = format!(
+            "Cargo.toml:\n\n```toml\n{}\n```\n\nDirect dependencies:",
+            self.manifest
+        );
+        for dependency in &self.dependencies {
+            prompt.push_str(&format!(
+                "\n- {} ({}, {}{}): features [{}]; pulls in {} package(s); referenced in {} source file(s)",
+                dependency.name,
+                dependency.kind,
+                dependency.source,
+                if dependency.optional { ", optional" } else { "" },
+                dependency.features.join(", "),
+                dependency
+                    .transitive
+                    .map_or_else(|| "an unknown number of".to_string(), |count| count.to_string()),
+                dependency.referencing_files
+            ));
+        }
+        if !self.duplicates.is_empty() {
+            prompt.push_str("\n\nCrates locked at several versions:");
+            for (name, versions) in &self.duplicates {
+                prompt.push_str(&format!("\n- {}: {}", name, versions.join(", ")));
+            }
+        }
+        prompt
+    }
+}
+
+impl DependencyReview {
+    /// Prints each dependency's purpose and the flagged issues.
+    pub fn print(&self) {
+        if !self.purposes.is_empty() {
+            println!("\n{}", "Purpose:".green().bold());
+            for purpose in &self.purposes {
+                println!("- {}: {}", purpose.name.bold(), purpose.purpose.trim());
+            }
+        }
+
+        if self.issues.is_empty() {
+            println!("\n{}", "No dependency issues found.".green());
+            return;
+        }
+        println!("\n{}", "Issues:".green().bold());
+        for issue in &self.issues {
+            println!(
+                "- [{}] {} ({})",
+                issue.severity.to_string().to_uppercase(),
+                issue.dependencies.join(", ").bold(),
+                issue.kind
+            );
+            println!("  {}", issue.message.trim());
+            if let Some(suggestion) = &issue.suggestion {
+                println!("  {} {}", "Suggestion:".cyan(), suggestion.trim());
+            }
+        }
+    }
+}
+
+/// Extracts the direct dependencies from the manifest's dependency tables.
+fn parse_manifest(manifest: &str) -> Result<Vec<DirectDependency>> {
+    let table: toml::Table = manifest.parse()?;
+    let mut dependencies = Vec::new();
+
+    for (table_name, kind) in DEPENDENCY_TABLES {
+        let Some(entries) = table.get(table_name).and_then(toml::Value::as_table) else {
+            continue;
+        };
+        for (name, spec) in entries {
+            let (source, features, optional) = match spec {
+                toml::Value::String(version) => (version.clone(), Vec::new(), false),
+                toml::Value::Table(spec) => {
+                    let source = ["version", "path", "git"]
+                        .iter()
+                        .find_map(|key| spec.get(*key).and_then(toml::Value::as_str))
+                        .map(str::to_string)
+                        .or_else(|| {
+                            spec.get("workspace")
+                                .and_then(toml::Value::as_bool)
+                                .filter(|inherited| *inherited)
+                                .map(|_| "workspace".to_string())
+                        })
+                        .unwrap_or_else(|| "*".to_string());
+                    let features = spec
+                        .get("features")
+                        .and_then(toml::Value::as_array)
+                        .map(|features| {
+                            features
+                                .iter()
+                                .filter_map(toml::Value::as_str)
+                                .map(str::to_string)
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    let optional = spec
+                        .get("optional")
+                        .and_then(toml::Value::as_bool)
+                        .unwrap_or(false);
+                    (source, features, optional)
+                }
+                _ => continue,
+            };
+            dependencies.push(DirectDependency {
+                name: name.clone(),
+                kind,
+                source,
+                features,
+                optional,
+                transitive: None,
+                referencing_files: 0,
+            });
+        }
+    }
+    Ok(dependencies)
+}
+
+/// Reads the crate's Rust sources, skipping the `target` directory.
+fn read_sources(manifest_dir: &Path) -> Vec<String> {
+    WalkDir::new(manifest_dir)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "target")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "rs"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .collect()
+}
+
+/// Returns `true` if the source refers to the crate `ident` by path or `use`.
+fn mentions(source: &str, ident: &str) -> bool {
+    source.contains(&format!("{}::", ident))
+        || source.contains(&format!("use {}", ident))
+        || source.contains(&format!("extern crate {}", ident))
+        || source.contains(&format!("#[{}", ident))
+}
+
+//========= Cargo.lock Structures =========//
+
+#[derive(Deserialize, Debug)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockPackage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LockPackage {
+    name: String,
+    version: String,
+    /// Entries are `name` or, when ambiguous, `name version` or `name version (source)`.
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+impl CargoLock {
+    /// Returns the index of the package an entry refers to.
+    fn find(&self, entry: &str) -> Option<usize> {
+        let mut parts = entry.split_whitespace();
+        let name = parts.next()?;
+        let version = parts.next();
+        self.package.iter().position(|package| {
+            package.name == name && version.is_none_or(|version| package.version == version)
+        })
+    }
+
+    /// Counts the packages reachable from `name`, itself excluded.
+    fn transitive_count(&self, name: &str) -> usize {
+        let Some(start) = self.find(name) else {
+            return 0;
+        };
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(index) = queue.pop_front() {
+            for entry in &self.package[index].dependencies {
+                if let Some(next) = self.find(entry) {
+                    if seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        seen.len() - 1
+    }
+
+    /// Returns the crates locked at more than one version.
+    fn duplicates(&self) -> BTreeMap<String, Vec<String>> {
+        let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for package in &self.package {
+            versions
+                .entry(package.name.clone())
+                .or_default()
+                .push(package.version.clone());
+        }
+        versions.retain(|_, versions| versions.len() > 1);
+        versions
+    }
+}
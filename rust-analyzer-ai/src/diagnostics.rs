@@ -0,0 +1,178 @@
+//! src/diagnostics.rs
+//!
+//! This module runs `cargo clippy` with JSON output and indexes the compiler
+//! and lint diagnostics by source file. They are included in each file's
+//! review prompt so the AI builds on existing tooling instead of repeating it.
+
+use crate::error::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// A single compiler or Clippy diagnostic attached to a line of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub line: usize,
+}
+
+/// Diagnostics grouped by the canonical path of the file they point at.
+#[derive(Debug, Default)]
+pub struct DiagnosticIndex {
+    by_file: HashMap<PathBuf, Vec<Diagnostic>>,
+}
+
+impl DiagnosticIndex {
+    /// Runs `cargo clippy --message-format=json` in `manifest_dir` and indexes the output.
+    ///
+    /// Clippy exits with a non-zero status when the crate has errors, but the
+    /// diagnostics it printed are still useful, so only a failure to launch
+    /// cargo is treated as an error.
+    pub async fn collect(manifest_dir: &Path) -> Result<Self> {
+        eprintln!(
+            "-> Collecting Clippy diagnostics in '{}'...",
+            manifest_dir.display()
+        );
+        let output = Command::new("cargo")
+            .args([
+                "clippy",
+                "--all-targets",
+                "--quiet",
+                "--message-format=json",
+            ])
+            .current_dir(manifest_dir)
+            .output()
+            .await?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let index = Self::from_json_lines(manifest_dir, &stdout);
+        eprintln!(
+            "-> Found {} diagnostic(s) across {} file(s).",
+            index.by_file.values().map(Vec::len).sum::<usize>(),
+            index.by_file.len()
+        );
+        Ok(index)
+    }
+
+    /// Builds the index from cargo's line-delimited JSON messages.
+    fn from_json_lines(manifest_dir: &Path, output: &str) -> Self {
+        let mut by_file: HashMap<PathBuf, Vec<Diagnostic>> = HashMap::new();
+
+        let messages = output
+            .lines()
+            .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+            .filter(|message| message.reason == "compiler-message")
+            .filter_map(|message| message.message);
+
+        for message in messages {
+            if !matches!(message.level.as_str(), "error" | "warning") {
+                continue;
+            }
+            let Some(span) = message.spans.iter().find(|span| span.is_primary) else {
+                continue;
+            };
+            let Some(path) = resolve_span_path(manifest_dir, &span.file_name) else {
+                continue;
+            };
+
+            let diagnostic = Diagnostic {
+                level: message.level.clone(),
+                code: message.code.as_ref().map(|code| code.code.clone()),
+                message: message.message.clone(),
+                line: span.line_start,
+            };
+            let entries = by_file.entry(path).or_default();
+            if !entries.contains(&diagnostic) {
+                entries.push(diagnostic);
+            }
+        }
+
+        for entries in by_file.values_mut() {
+            entries.sort_by_key(|diagnostic| diagnostic.line);
+        }
+        Self { by_file }
+    }
+
+    /// Returns the diagnostics reported for `path`, in line order.
+    pub fn for_file(&self, path: &Path) -> &[Diagnostic] {
+        path.canonicalize()
+            .ok()
+            .and_then(|canonical| self.by_file.get(&canonical))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+}
+
+/// Formats diagnostics as a prompt section, or `None` if there are none.
+pub fn format_for_prompt(diagnostics: &[Diagnostic]) -> Option<String> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+    let lines = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let code = diagnostic
+                .code
+                .as_deref()
+                .map_or_else(String::new, |code| format!(" [{}]", code));
+            format!(
+                "- line {} {}{}: {}",
+                diagnostic.line, diagnostic.level, code, diagnostic.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(format!(
+        "The compiler and Clippy already report the following diagnostics for this file. \
+        Do not repeat them as findings; build on them where relevant, for example by explaining \
+        a deeper design issue behind a lint:\n{}",
+        lines
+    ))
+}
+
+/// Resolves a span's file name, which cargo reports relative to the workspace root.
+fn resolve_span_path(manifest_dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let file = Path::new(file_name);
+    if file.is_absolute() {
+        return file.canonicalize().ok();
+    }
+    manifest_dir
+        .ancestors()
+        .map(|dir| dir.join(file))
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| candidate.canonicalize().ok())
+}
+
+//========= Cargo JSON Message Structures =========//
+
+#[derive(Deserialize, Debug)]
+struct CargoMessage {
+    reason: String,
+    #[serde(default)]
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CompilerMessage {
+    message: String,
+    level: String,
+    #[serde(default)]
+    code: Option<DiagnosticCode>,
+    #[serde(default)]
+    spans: Vec<DiagnosticSpan>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct DiagnosticSpan {
+    file_name: String,
+    line_start: usize,
+    is_primary: bool,
+}
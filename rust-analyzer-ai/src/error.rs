@@ -26,18 +26,47 @@ pub enum Error {
     #[error("Directory traversal error: {0}")]
     Walkdir(#[from] walkdir::Error),
 
+    /// Wraps errors from invalid include/exclude glob patterns.
+    #[error("Invalid glob pattern: {0}")]
+    Glob(#[from] globset::Error),
+
     /// Wraps errors from the `syn` crate during Rust code parsing.
     #[error("Code parsing error: {0}")]
     Syn(#[from] syn::Error),
 
-    /// Wraps errors from the `reqwest` HTTP client.
-    #[error("HTTP request error")]
-    Reqwest(#[from] reqwest::Error),
+    /// For item paths (e.g., `--function`) that do not resolve to an item in the crate.
+    #[error("Symbol resolution error: {0}")]
+    Resolve(String),
+
+    /// Wraps errors from requests to the model's API, made with the shared
+    /// `ai-core` client.
+    #[error(transparent)]
+    Client(#[from] ai_core::Error),
 
     /// For errors returned specifically by the OpenAI API.
     #[error("OpenAI API error: {0}")]
     OpenAI(String),
 
+    /// Wraps errors from the interactive review prompts.
+    #[error("Interactive prompt error: {0}")]
+    Prompt(#[from] dialoguer::Error),
+
+    /// Wraps errors from parsing `Cargo.toml` and `Cargo.lock` files.
+    #[error("TOML parsing error: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    /// Wraps errors from parsing YAML rule packs.
+    #[error("YAML parsing error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    /// For failures of cargo commands whose output is required, like `cargo metadata`.
+    #[error("Cargo error: {0}")]
+    Cargo(String),
+
+    /// For fixes whose diff is malformed, does not apply, or fails verification.
+    #[error("Patch error: {0}")]
+    Patch(String),
+
     /// For errors during JSON serialization or deserialization.
     #[error("JSON serialization/deserialization error")]
     SerdeJson(#[from] serde_json::Error),
@@ -0,0 +1,126 @@
+//! src/estimate.rs
+//!
+//! This module implements the dry-run mode. It counts the tokens of every
+//! request an analysis would send and prints the number of requests and the
+//! approximate cost per model, without contacting the API.
+
+use crate::files;
+use ai_core::count_tokens;
+use ai_core::usage::model_price;
+use colored::Colorize;
+use std::path::PathBuf;
+
+/// The share of the context window available to the prompt; the rest is
+/// reserved for the model's reply. Files are never split, so a larger
+/// prompt makes its request fail.
+const PROMPT_CONTEXT_TOKENS: usize = 120_000;
+
+/// A rough allowance for the JSON findings returned by each request.
+const ESTIMATED_OUTPUT_TOKENS_PER_REQUEST: usize = 1_000;
+
+/// The models the cost is estimated for, at their list prices, besides the
+/// current model.
+const PRICED_MODELS: [&str; 3] = ["gpt-4o", "gpt-4o-mini", "gpt-4.1"];
+
+/// The estimated cost of analyzing a single file.
+#[derive(Debug)]
+pub struct FileEstimate {
+    pub path: PathBuf,
+    pub input_tokens: usize,
+    pub requests: usize,
+    pub cached: bool,
+    /// Whether the prompt fits the context window.
+    pub fits: bool,
+}
+
+/// Estimates a file's cost from the prompts its analysis would send,
+/// counting their tokens with the tokenizer of `model`.
+pub fn estimate_file(
+    path: PathBuf,
+    model: &str,
+    system_prompt: &str,
+    user_prompt: &str,
+    cached: bool,
+) -> FileEstimate {
+    let input_tokens = count_tokens(model, system_prompt) + count_tokens(model, user_prompt);
+    // Each file is analyzed with a single request.
+    let requests = if cached { 0 } else { 1 };
+    FileEstimate {
+        path,
+        input_tokens,
+        requests,
+        cached,
+        fits: input_tokens <= PROMPT_CONTEXT_TOKENS,
+    }
+}
+
+/// Prints the per-file plan and the estimated cost of the whole run per model.
+pub fn print_plan(estimates: &[FileEstimate], current_model: &str) {
+    println!("\n{}", "Dry run: no requests will be sent.".bold().cyan());
+    println!("{:<60} {:>10} {:>9}", "File", "Tokens", "Requests");
+
+    for estimate in estimates {
+        let mut label = files::relative_uri(&estimate.path);
+        if estimate.cached {
+            label.push_str(" (cached)");
+        } else if !estimate.fits {
+            label.push_str(" (won't fit)");
+        }
+        println!(
+            "{:<60} {:>10} {:>9}",
+            label, estimate.input_tokens, estimate.requests
+        );
+    }
+
+    let requests: usize = estimates.iter().map(|e| e.requests).sum();
+    let input_tokens: usize = estimates
+        .iter()
+        .filter(|e| !e.cached)
+        .map(|e| e.input_tokens)
+        .sum();
+    let output_tokens = requests * ESTIMATED_OUTPUT_TOKENS_PER_REQUEST;
+    let cached = estimates.iter().filter(|e| e.cached).count();
+
+    println!(
+        "\n{} {} file(s), {} request(s), {} input token(s), ~{} output token(s).",
+        "Total:".bold(),
+        estimates.len(),
+        requests,
+        input_tokens,
+        output_tokens
+    );
+    if cached > 0 {
+        println!("{} file(s) would be served from the cache.", cached);
+    }
+    let oversized = estimates.iter().filter(|e| !e.cached && !e.fits).count();
+    if oversized > 0 {
+        println!(
+            "{} {} file(s) exceed the {}-token prompt limit; their requests would fail.",
+            "Warning:".yellow().bold(),
+            oversized,
+            PROMPT_CONTEXT_TOKENS
+        );
+    }
+
+    println!("\n{}", "Estimated cost:".bold());
+    if model_price(current_model).is_none() {
+        println!("  {:<12} no list price (current model)", current_model);
+    }
+    let mut models = PRICED_MODELS.to_vec();
+    if !models.contains(&current_model) {
+        models.insert(0, current_model);
+    }
+    for name in models {
+        let Some(pricing) = model_price(name) else {
+            continue;
+        };
+        let cost = input_tokens as f64 / 1_000_000.0 * pricing.input_per_million
+            + output_tokens as f64 / 1_000_000.0 * pricing.output_per_million;
+        let marker = if name == current_model {
+            " (current model)"
+        } else {
+            ""
+        };
+        println!("  {:<12} ${:>9.4}{}", name, cost, marker);
+    }
+}
@@ -1,21 +1,99 @@
 //! src/files.rs
 //!
 //! This module is responsible for file system operations, specifically
-//! discovering all Rust source files within a given directory path.
+//! discovering the Rust source files within a given directory path and
+//! applying the user's include/exclude rules to them.
 
 use crate::error::Result;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir; // Corrected: Changed to use WalkDir with a capital 'D'.
+use walkdir::WalkDir;
+
+/// The name of the ignore file read from the root of an analyzed directory.
+const IGNORE_FILE_NAME: &str = ".aiignore";
+
+/// Decides which discovered files are analyzed.
+///
+/// Exclusions come from `--exclude` flags and from the `.aiignore` file at the
+/// root of the analyzed directory. Both use gitignore-like patterns: a pattern
+/// without a `/` matches at any depth, a leading `/` anchors it to the root,
+/// and a trailing `/` matches only directories. Inclusions come from
+/// `--include` flags and are plain globs relative to the root; when any are
+/// given, a file must match at least one of them.
+#[derive(Debug)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl FileFilter {
+    /// Builds the filter for `root_path`, reading its `.aiignore` file if present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ignore file cannot be read or any pattern is invalid.
+    pub fn new(root_path: &Path, include: &[String], exclude: &[String]) -> Result<Self> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in include {
+                builder.add(glob(pattern)?);
+            }
+            Some(builder.build()?)
+        };
+
+        let mut ignore_patterns = exclude.to_vec();
+        let ignore_file = root_path.join(IGNORE_FILE_NAME);
+        if ignore_file.is_file() {
+            let content = fs::read_to_string(&ignore_file)?;
+            ignore_patterns.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &ignore_patterns {
+            for expanded in expand_ignore_pattern(pattern) {
+                builder.add(glob(&expanded)?);
+            }
+        }
+
+        Ok(Self {
+            include,
+            exclude: builder.build()?,
+        })
+    }
+
+    /// Returns `true` if the path (relative to the root) is excluded.
+    fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude.is_match(relative)
+    }
+
+    /// Returns `true` if the file (relative to the root) passes the include rules.
+    fn is_included(&self, relative: &Path) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.is_match(relative))
+    }
+}
 
 /// Recursively finds all Rust source files (`.rs`) in a given directory.
 ///
 /// This function walks the directory tree starting from the provided `root_path`,
-/// filtering for files that have the `.rs` extension. It ignores directories
-/// and files that are not Rust source files.
+/// filtering for files that have the `.rs` extension. Excluded directories are
+/// not descended into, and every file must pass the include rules.
 ///
 /// # Arguments
 ///
 /// * `root_path` - A reference to the path of the directory to search.
+/// * `filter` - The include/exclude rules to apply.
 ///
 /// # Returns
 ///
@@ -27,18 +105,17 @@ use walkdir::WalkDir; // Corrected: Changed to use WalkDir with a capital 'D'.
 /// This function can return an error if the directory traversal fails
 /// (e.g., due to permissions issues), which will be wrapped in our
 /// custom `Error::Walkdir` variant.
-pub fn find_rust_files(root_path: &Path) -> Result<Vec<PathBuf>> {
-    println!("-> Discovering Rust files in '{}'...", root_path.display());
+pub fn find_rust_files(root_path: &Path, filter: &FileFilter) -> Result<Vec<PathBuf>> {
+    eprintln!("-> Discovering Rust files in '{}'...", root_path.display());
 
-    // Corrected: Changed to use WalkDir with a capital 'D'.
-    let walker = WalkDir::new(root_path);
+    let relative = |path: &Path| path.strip_prefix(root_path).unwrap_or(path).to_path_buf();
 
     // Process the iterator to collect valid Rust file paths.
-    // `filter_map` is used to both filter and map the entries.
-    // `e.ok()` converts a `Result<DirEntry>` into an `Option<DirEntry>`,
-    // effectively skipping any entries that result in an error during traversal.
-    let rust_files: Vec<PathBuf> = walker
+    // `filter_entry` prunes excluded directories before they are walked, and
+    // `e.ok()` skips any entries that result in an error during traversal.
+    let rust_files: Vec<PathBuf> = WalkDir::new(root_path)
         .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !filter.is_excluded(&relative(e.path())))
         .filter_map(|e| e.ok()) // Ignore any errors during iteration.
         .filter(|e| {
             // We are only interested in files that have the ".rs" extension.
@@ -46,11 +123,62 @@ pub fn find_rust_files(root_path: &Path) -> Result<Vec<PathBuf>> {
                 && e.path()
                     .extension()
                     .map_or(false, |ext| ext == "rs")
+                && filter.is_included(&relative(e.path()))
         })
         .map(|e| e.into_path()) // Convert the DirEntry into a PathBuf.
         .collect();
 
-    println!("-> Found {} Rust file(s).", rust_files.len());
+    eprintln!("-> Found {} Rust file(s).", rust_files.len());
 
     Ok(rust_files)
 }
+
+/// Compiles a glob in which `*` does not cross directory separators.
+fn glob(pattern: &str) -> Result<Glob> {
+    Ok(GlobBuilder::new(pattern).literal_separator(true).build()?)
+}
+
+/// Expands a gitignore-like pattern into the globs that implement it.
+fn expand_ignore_pattern(pattern: &str) -> Vec<String> {
+    let anchored = pattern.starts_with('/');
+    let directory_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_matches('/');
+
+    let base = if anchored || trimmed.contains('/') {
+        trimmed.to_string()
+    } else {
+        format!("**/{}", trimmed)
+    };
+
+    // A directory pattern matches everything beneath it; a plain pattern
+    // matches the path itself and, if it names a directory, its contents.
+    if directory_only {
+        vec![base.clone(), format!("{}/**", base)]
+    } else {
+        vec![format!("{}/**", base), base]
+    }
+}
+
+/// Converts a file path into a forward-slash URI relative to the working directory.
+///
+/// This is the form used when referring to files in exported results and
+/// patches (e.g., `src/main.rs` rather than `./src/main.rs`).
+pub fn relative_uri(path: &Path) -> String {
+    let relative = env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path);
+    let uri = relative.to_string_lossy().replace('\\', "/");
+    uri.trim_start_matches("./").to_string()
+}
+
+/// Finds the directory of the nearest `Cargo.toml` at or above the given path.
+///
+/// Returns `None` if the path is not inside a Cargo package.
+pub fn find_manifest_dir(path: &Path) -> Option<PathBuf> {
+    let absolute = path.canonicalize().ok()?;
+    absolute
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
@@ -0,0 +1,116 @@
+//! src/findings.rs
+//!
+//! This module defines the structured results of an analysis. Instead of
+//! free-form text, the model is asked to return typed findings with a
+//! category, a severity, and a file location, so they can be filtered,
+//! exported, and consumed by other tools.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+/// How serious a finding is, ordered from least to most severe.
+#[derive(
+    Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[serde(alias = "note")]
+    Info,
+    Low,
+    #[serde(alias = "warning")]
+    Medium,
+    #[serde(alias = "error")]
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Maps the severity onto the three SARIF result levels.
+    pub fn sarif_level(self) -> &'static str {
+        match self {
+            Severity::Info => "note",
+            Severity::Low | Severity::Medium => "warning",
+            Severity::High | Severity::Critical => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.write_str(label)
+    }
+}
+
+/// A single issue or suggestion reported for a source file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    /// A short, kebab-case category such as `error-handling` or `performance`.
+    pub category: String,
+    pub severity: Severity,
+    /// A one-line summary of the finding.
+    pub title: String,
+    /// A full explanation of the problem and why it matters.
+    pub message: String,
+    /// The 1-based line where the finding starts, if it can be pinpointed.
+    #[serde(default)]
+    pub line: Option<usize>,
+    /// The 1-based line where the finding ends, if it spans several lines.
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    /// A concrete suggestion for how to resolve the finding.
+    #[serde(default)]
+    pub suggestion: Option<String>,
+    /// A stable identifier used to match the finding across runs. Computed
+    /// locally after the reply is parsed, never supplied by the model.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub fingerprint: String,
+}
+
+impl Finding {
+    /// The rule identifier used when exporting this finding (e.g., `ai/error-handling`).
+    pub fn rule_id(&self) -> String {
+        format!("ai/{}", self.category)
+    }
+
+    /// Returns the numbered source lines around the finding's location.
+    ///
+    /// `context` lines are included above and below the reported range.
+    /// Returns `None` if the finding has no location within `lines`.
+    pub fn excerpt(&self, lines: &[String], context: usize) -> Option<Vec<(usize, String)>> {
+        let start = self.line?;
+        let end = self.end_line.unwrap_or(start);
+        let first = start.saturating_sub(context).max(1);
+        let last = (end + context).min(lines.len());
+        if first > last {
+            return None;
+        }
+        Some(
+            (first..=last)
+                .map(|number| (number, lines[number - 1].clone()))
+                .collect(),
+        )
+    }
+}
+
+/// All findings produced for a single source file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileAnalysis {
+    pub path: PathBuf,
+    pub findings: Vec<Finding>,
+    /// Whether the findings were served from the local cache.
+    #[serde(skip)]
+    pub from_cache: bool,
+    /// The workspace crate that owns the file, when analyzing a multi-crate workspace.
+    #[serde(skip)]
+    pub crate_name: Option<String>,
+}
@@ -0,0 +1,226 @@
+//! src/fixes.rs
+//!
+//! This module implements the auto-fix mode. For each finding, the model is
+//! asked for a unified diff. The diff is validated against the current file
+//! content, optionally verified with `cargo check`, and then either written
+//! to a patch directory or applied to the file in place.
+
+use crate::error::{Error, Result};
+use crate::files;
+use crate::findings::{FileAnalysis, Finding};
+use crate::openai;
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// The marker the model returns when a finding cannot be fixed within the file.
+const NO_FIX_MARKER: &str = "NO_FIX";
+
+const FIX_SYSTEM_PROMPT: &str = "You are an expert Rust programmer. You will be given a Rust \
+    source file and a single review finding about it. Produce the smallest change that resolves \
+    the finding without altering unrelated behavior. Respond with only a unified diff against the \
+    file exactly as shown, with `---`/`+++` headers, `@@` hunk headers, and three lines of \
+    context, inside a ```diff code block. If the finding cannot be fixed safely by editing this \
+    file alone, respond with only the word NO_FIX.";
+
+/// Controls what happens to a validated fix.
+#[derive(Debug, Clone)]
+pub struct FixOptions {
+    /// Directory that receives one `.patch` file per fix when not applying.
+    pub patch_dir: PathBuf,
+    /// Apply each fix to the source file instead of writing a patch.
+    pub apply: bool,
+    /// Require the patched file to pass `cargo check` before it is accepted.
+    pub verify: bool,
+}
+
+/// Generates, validates, and saves or applies a fix for every finding in a file.
+///
+/// Fixes are requested one finding at a time. When applying, each request is
+/// made against the file as already modified by the previous fixes, so the
+/// diffs stay consistent with one another. A fix that fails validation is
+/// reported and skipped without affecting the others.
+pub async fn fix_file(
+    client: &openai::Client,
+    analysis: &FileAnalysis,
+    options: &FixOptions,
+) -> Result<()> {
+    let uri = files::relative_uri(&analysis.path);
+    println!("{} {}", "Generating fixes for:".magenta().bold(), uri);
+
+    for (index, finding) in analysis.findings.iter().enumerate() {
+        let patch_name = format!("{}-{:02}.patch", uri.replace('/', "_"), index + 1);
+        fix_finding(client, &analysis.path, finding, &patch_name, options).await?;
+    }
+
+    Ok(())
+}
+
+/// Generates, validates, and saves or applies a fix for a single finding.
+///
+/// The file is read fresh from disk, so fixes applied earlier are taken into
+/// account. Returns `Ok(true)` if a fix was written or applied, and `Ok(false)`
+/// if it was skipped; the reason is printed either way.
+pub async fn fix_finding(
+    client: &openai::Client,
+    path: &Path,
+    finding: &Finding,
+    patch_name: &str,
+    options: &FixOptions,
+) -> Result<bool> {
+    let uri = files::relative_uri(path);
+    let current = fs::read_to_string(path)?;
+
+    let patched = match request_patch(client, &uri, &current, finding).await {
+        Ok(Some(patched)) => patched,
+        Ok(None) => {
+            report_skip(finding, "no safe fix proposed");
+            return Ok(false);
+        }
+        Err(e) => {
+            report_skip(finding, &e.to_string());
+            return Ok(false);
+        }
+    };
+
+    if options.verify {
+        if let Err(e) = verify_compiles(path, &current, &patched).await {
+            report_skip(finding, &e.to_string());
+            return Ok(false);
+        }
+    }
+
+    if options.apply {
+        fs::write(path, &patched)?;
+        println!("  {} {}", "Applied:".green(), finding.title);
+    } else {
+        let patch_path = options.patch_dir.join(patch_name);
+        fs::create_dir_all(&options.patch_dir)?;
+        fs::write(&patch_path, render_patch(&uri, &current, &patched))?;
+        println!(
+            "  {} {} -> {}",
+            "Patch:".green(),
+            finding.title,
+            patch_path.display()
+        );
+    }
+
+    Ok(true)
+}
+
+/// Asks the model for a diff resolving `finding` and applies it in memory.
+///
+/// Returns `Ok(None)` if the model declined or the diff is a no-op, and an
+/// `Error::Patch` if the diff is malformed or does not apply cleanly.
+async fn request_patch(
+    client: &openai::Client,
+    uri: &str,
+    content: &str,
+    finding: &Finding,
+) -> Result<Option<String>> {
+    let location = finding.line.map_or_else(
+        || "unknown line".to_string(),
+        |line| format!("line {}", line),
+    );
+    let user_prompt = format!(
+        "File `{}`:\n\n```rust\n{}\n```\n\nFinding [{}] {} ({}):\n{}\n\nSuggested resolution: {}",
+        uri,
+        content,
+        finding.severity,
+        finding.title,
+        location,
+        finding.message,
+        finding.suggestion.as_deref().unwrap_or("none given")
+    );
+
+    let response = client.complete(FIX_SYSTEM_PROMPT, &user_prompt).await?;
+    if response.trim() == NO_FIX_MARKER {
+        return Ok(None);
+    }
+
+    let diff_text = extract_diff(&response);
+    let patch = diffy::Patch::from_str(&diff_text)
+        .map_err(|e| Error::Patch(format!("model returned a malformed diff: {}", e)))?;
+    let patched = diffy::apply(content, &patch)
+        .map_err(|e| Error::Patch(format!("diff does not apply cleanly: {}", e)))?;
+
+    Ok(Some(patched).filter(|patched| patched != content))
+}
+
+/// Temporarily writes the patched content and runs `cargo check` on its crate.
+///
+/// The original content is always restored; the caller decides whether the
+/// patched version is kept.
+async fn verify_compiles(path: &Path, original: &str, patched: &str) -> Result<()> {
+    verify_with_cargo(path, original, patched, &["check"]).await
+}
+
+/// Temporarily writes `candidate` to `path` and runs `cargo <cargo_args>` in
+/// the file's crate, restoring the original content afterwards.
+///
+/// Returns an `Error::Patch` with the first compiler errors if cargo fails.
+pub async fn verify_with_cargo(
+    path: &Path,
+    original: &str,
+    candidate: &str,
+    cargo_args: &[&str],
+) -> Result<()> {
+    let command = format!("cargo {}", cargo_args.join(" "));
+    let manifest_dir = files::find_manifest_dir(path).ok_or_else(|| {
+        Error::Patch(format!(
+            "cannot run `{}`: no Cargo.toml found above the file",
+            command
+        ))
+    })?;
+
+    fs::write(path, candidate)?;
+    let output = Command::new("cargo")
+        .args(cargo_args)
+        .args(["--quiet", "--message-format", "short"])
+        .current_dir(&manifest_dir)
+        .output()
+        .await;
+    fs::write(path, original)?;
+
+    let output = output?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let first_errors = stderr.lines().take(10).collect::<Vec<_>>().join("\n");
+        Err(Error::Patch(format!(
+            "`{}` failed:\n{}",
+            command, first_errors
+        )))
+    }
+}
+
+/// Extracts the diff from the model's reply, stripping any Markdown code fence.
+fn extract_diff(response: &str) -> String {
+    let body = response
+        .split_once("```diff")
+        .or_else(|| response.split_once("```"))
+        .map(|(_, rest)| rest.split_once("```").map_or(rest, |(inner, _)| inner))
+        .unwrap_or(response);
+
+    // Only strip line breaks: a trailing context line may legitimately be a single space.
+    let mut diff = body
+        .trim_start_matches(['\n', '\r'])
+        .trim_end_matches(['\n', '\r'])
+        .to_string();
+    diff.push('\n');
+    diff
+}
+
+/// Renders a canonical, `git apply`-compatible patch between two versions of a file.
+fn render_patch(uri: &str, original: &str, patched: &str) -> String {
+    let patch = diffy::create_patch(original, patched).to_string();
+    let hunks = patch.find("@@").map_or("", |start| &patch[start..]);
+    format!("--- a/{}\n+++ b/{}\n{}", uri, uri, hunks)
+}
+
+/// Prints a one-line notice that a finding could not be fixed.
+fn report_skip(finding: &Finding, reason: &str) {
+    println!("  {} {}: {}", "Skipped:".yellow(), finding.title, reason);
+}
@@ -0,0 +1,87 @@
+//! src/focus.rs
+//!
+//! This module defines the focus areas that narrow an analysis to a single
+//! concern. Each focus area carries a specialized reviewer prompt and the
+//! finding category it reports under, so targeted audits only surface the
+//! findings that matter to them.
+
+use clap::ValueEnum;
+
+/// A concern that an analysis can be restricted to.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusArea {
+    Security,
+    Performance,
+    ErrorHandling,
+    ApiDesign,
+    Concurrency,
+}
+
+impl FocusArea {
+    /// The finding category reported for this focus area.
+    pub fn category(self) -> &'static str {
+        match self {
+            FocusArea::Security => "security",
+            FocusArea::Performance => "performance",
+            FocusArea::ErrorHandling => "error-handling",
+            FocusArea::ApiDesign => "api-design",
+            FocusArea::Concurrency => "concurrency",
+        }
+    }
+
+    /// The specialized review instructions for this focus area.
+    pub fn prompt(self) -> &'static str {
+        match self {
+            FocusArea::Security => {
+                "Security: look for unsound or unjustified `unsafe` blocks, \
+                unchecked indexing and integer overflow on untrusted input, path traversal, \
+                command or SQL injection, secrets in source, insecure randomness or cryptography, \
+                and panics reachable from external input."
+            }
+            FocusArea::Performance => {
+                "Performance: look for unnecessary allocations and clones, \
+                repeated work inside loops, quadratic algorithms, blocking calls in async code, \
+                missed opportunities for iterators, borrowing, or capacity preallocation, and \
+                inefficient data structure choices."
+            }
+            FocusArea::ErrorHandling => {
+                "Error handling: look for `unwrap`/`expect` on fallible \
+                operations, swallowed or silently ignored errors, lost error context, overly broad \
+                error types, panics used for control flow, and errors that should be propagated \
+                with `?`."
+            }
+            FocusArea::ApiDesign => {
+                "API design: look for public items that leak implementation \
+                details, missing or misleading documentation, non-idiomatic naming, overly \
+                restrictive or permissive signatures (e.g., `&String` instead of `&str`), missing \
+                standard trait implementations, and breaking-change hazards."
+            }
+            FocusArea::Concurrency => {
+                "Concurrency: look for data races, deadlock risks and lock \
+                ordering issues, holding locks across `.await`, unbounded task spawning or \
+                channels, missing `Send`/`Sync` considerations, and misuse of atomics."
+            }
+        }
+    }
+}
+
+/// Builds the reviewer persona for a targeted audit across the given focus areas.
+pub fn build_focus_prompt(focus: &[FocusArea]) -> String {
+    let areas = focus
+        .iter()
+        .map(|area| format!("- {}", area.prompt()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let categories = focus
+        .iter()
+        .map(|area| format!("\"{}\"", area.category()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "You are an expert Rust programmer with over 20 years of experience, performing a \
+        targeted audit. Review the code only for the following concerns and ignore everything \
+        else:\n{}\n\nEvery finding's category must be exactly one of: {}.",
+        areas, categories
+    )
+}
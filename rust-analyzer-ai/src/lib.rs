@@ -0,0 +1,32 @@
+//! src/lib.rs
+//!
+//! The library behind the AI-Powered Rust Code Analyzer: file discovery, the
+//! analyzer and its cache, the findings and their output formats, and the
+//! fix, review, and test generation workflows. `main.rs` parses the command
+//! line and drives them, and the tests in `tests/` run the analyzer against
+//! the mock provider.
+
+pub mod analyzer;
+pub mod benches;
+pub mod cache;
+pub mod config;
+pub mod deps;
+pub mod diagnostics;
+pub mod error;
+pub mod estimate;
+pub mod files;
+pub mod findings;
+pub mod fixes;
+pub mod focus;
+pub mod openai;
+pub mod output;
+pub mod report;
+pub mod review;
+pub mod rules;
+pub mod sarif;
+pub mod server;
+pub mod summary;
+pub mod suppression;
+pub mod symbols;
+pub mod testgen;
+pub mod workspace;
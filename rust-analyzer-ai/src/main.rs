@@ -7,18 +7,30 @@
 //! 2. Initializing configuration and the OpenAI client.
 //! 3. Discovering target Rust files.
 //! 4. Iterating through the files and triggering the analysis for each one.
-//! 5. Handling and reporting any errors that occur during the process.
+//! 5. Reporting the findings in the requested output format.
+//! 6. Handling and reporting any errors that occur during the process.
 
-// Declare the module hierarchy for the compiler.
-mod analyzer;
-mod config;
-mod error;
-mod files;
-mod openai;
-
-use crate::error::Result;
-use clap::Parser;
+use ai_core::{ProviderKind, RateLimits};
+use clap::{ArgGroup, Parser, Subcommand};
 use colored::Colorize;
+use futures::stream::{self, StreamExt};
+use rust_analyzer_ai::analyzer::Analyzer;
+use rust_analyzer_ai::cache::AnalysisCache;
+use rust_analyzer_ai::diagnostics::DiagnosticIndex;
+use rust_analyzer_ai::error::{Error, Result};
+use rust_analyzer_ai::findings::Severity;
+use rust_analyzer_ai::fixes::FixOptions;
+use rust_analyzer_ai::focus::FocusArea;
+use rust_analyzer_ai::output::OutputFormat;
+use rust_analyzer_ai::summary::RunSummary;
+use rust_analyzer_ai::suppression::Baseline;
+use rust_analyzer_ai::testgen::TestGenOptions;
+use rust_analyzer_ai::workspace::Workspace;
+use rust_analyzer_ai::{
+    benches, cache, config, deps, estimate, files, fixes, openai, output, report, review, rules,
+    server, summary, suppression, symbols, testgen,
+};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 /// Defines the command-line arguments for our application.
@@ -30,10 +42,235 @@ use std::path::PathBuf;
     version,
     about = "An AI-powered assistant to analyze and suggest improvements for Rust code."
 )]
+#[command(group(ArgGroup::new("fixing").args(["fix", "review"]).multiple(true)))]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The path to the Rust source file or project directory to analyze.
     #[arg(required = true)]
+    path: Option<PathBuf>,
+
+    /// The format in which findings are reported.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Write machine-readable results to this file instead of standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Only analyze files matching this glob, relative to the path. Can be repeated.
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Skip files and directories matching this pattern. Can be repeated.
+    /// Patterns from a `.aiignore` file in the analyzed directory are added to these.
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Run as a CI quality gate: disable colors and exit non-zero when findings
+    /// at or above the `--fail-on` severity are present (exit code 1), or when
+    /// the analysis itself fails (exit code 2).
+    #[arg(long)]
+    ci: bool,
+
+    /// The lowest severity that fails the CI gate.
+    #[arg(long, value_enum, default_value_t = Severity::High, requires = "ci")]
+    fail_on: Severity,
+
+    /// Write a machine-readable summary of the run (counts and gate result) to this file.
+    #[arg(long)]
+    summary_json: Option<PathBuf>,
+
+    /// Hide findings below this severity from the output, reports, and summary.
+    #[arg(long, value_enum)]
+    min_severity: Option<Severity>,
+
+    /// The baseline file of accepted findings; findings recorded in it are not reported.
+    #[arg(long, default_value = suppression::DEFAULT_BASELINE_PATH)]
+    baseline: PathBuf,
+
+    /// Record every current finding in the baseline file instead of filtering by it.
+    #[arg(long)]
+    update_baseline: bool,
+
+    /// The directory where analysis results are cached by file content hash.
+    #[arg(long, default_value = cache::DEFAULT_CACHE_DIR)]
+    cache_dir: PathBuf,
+
+    /// Always call the API, ignoring and not updating the result cache, and
+    /// bypassing the shared response cache even if `AI_CACHE` is set.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Count tokens and estimate the cost of the run without calling the API.
+    #[arg(long, conflicts_with_all = ["fix", "review", "ci"])]
+    dry_run: bool,
+
+    /// The model provider: `openai`, `azure`, `anthropic`, or `ollama` (a local
+    /// server, for confidential codebases). Defaults to `AI_PROVIDER`, or `openai`.
+    #[arg(long)]
+    provider: Option<ProviderKind>,
+
+    /// The model to use instead of `AI_MODEL` or the provider's default (`gpt-4o`
+    /// for OpenAI, `qwen2.5-coder` for Ollama).
+    #[arg(long)]
+    model: Option<String>,
+
+    /// The maximum number of files analyzed at the same time.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: u32,
+
+    /// The maximum number of API requests started per minute, across all files.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    requests_per_minute: Option<u32>,
+
+    /// Stop sending requests once the run has cost this many US dollars
+    /// (default: `AI_MAX_COST`).
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Run `cargo clippy` first and include each file's compiler and lint diagnostics
+    /// in its prompt, so the review builds on them instead of repeating them.
+    #[arg(long)]
+    clippy: bool,
+
+    /// Restrict the review to a specific concern. Can be repeated.
+    #[arg(long, value_enum)]
+    focus: Vec<FocusArea>,
+
+    /// Add the house rules of a rule pack (e.g., `company-style` for
+    /// `rulepacks/company-style.yaml`) to the review. Can be repeated.
+    #[arg(long = "rules", value_name = "NAME")]
+    rules: Vec<String>,
+
+    /// The directory containing the rule packs selected with `--rules`.
+    #[arg(long, default_value = rules::DEFAULT_RULEPACK_DIR)]
+    rules_dir: PathBuf,
+
+    /// With `--focus performance`, also propose criterion benchmarks for suspected
+    /// hot paths and write them under the crate's `benches/` directory.
+    #[arg(long, requires = "focus")]
+    benches: bool,
+
+    /// Deeply review a single function, given by its path within the crate
+    /// (e.g., `analyzer::Analyzer::analyze_file`), instead of whole files.
+    #[arg(long, value_name = "PATH", conflicts_with = "dry_run")]
+    function: Option<String>,
+
+    /// Render all findings into a navigable report (`.html` or `.md`) at this path.
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Ask the model for a unified diff fixing each finding.
+    #[arg(long)]
+    fix: bool,
+
+    /// Step through each finding interactively: accept it (write a patch), dismiss it
+    /// (add it to the baseline), or ask the model a follow-up question.
+    #[arg(long, conflicts_with_all = ["fix", "ci"])]
+    review: bool,
+
+    /// Apply validated fixes to the source files instead of writing patch files.
+    #[arg(long, requires = "fixing")]
+    apply: bool,
+
+    /// Only accept a fix if the crate still passes `cargo check` with it applied.
+    #[arg(long, requires = "fixing")]
+    verify: bool,
+
+    /// The directory that receives generated patch files.
+    #[arg(long, default_value = ".patches")]
+    patch_dir: PathBuf,
+}
+
+/// Modes other than the default analysis.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate `#[cfg(test)]` unit tests for a module or function, and save them
+    /// only if they compile with `cargo test --no-run`.
+    GenerateTests(GenerateTestsArgs),
+
+    /// Review the dependencies in `Cargo.toml`/`Cargo.lock` for unnecessary,
+    /// heavyweight, overlapping, or duplicated crates.
+    Deps(DepsArgs),
+
+    /// Run an HTTP server that analyzes files on request, for editor integrations.
+    Serve(ServeArgs),
+}
+
+/// Arguments of the `generate-tests` subcommand.
+#[derive(clap::Args, Debug)]
+struct GenerateTestsArgs {
+    /// The module or function to test, by its path within the crate
+    /// (e.g., `analyzer` or `analyzer::Analyzer::analyze_file`).
+    target: String,
+
+    /// A path inside the crate that contains the target.
+    #[arg(long, default_value = ".")]
     path: PathBuf,
+
+    /// The directory that receives the candidate test module of each attempt.
+    #[arg(long, default_value = testgen::DEFAULT_CANDIDATE_DIR)]
+    candidate_dir: PathBuf,
+
+    /// The maximum number of attempts; compiler errors are fed back after each failure.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..))]
+    attempts: u32,
+}
+
+/// Arguments of the `deps` subcommand.
+#[derive(clap::Args, Debug)]
+struct DepsArgs {
+    /// A path inside the crate whose dependencies are reviewed.
+    #[arg(default_value = ".")]
+    path: PathBuf,
+}
+
+/// Arguments of the `serve` subcommand.
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// The address to listen on.
+    #[arg(long, default_value = server::DEFAULT_ADDR)]
+    addr: SocketAddr,
+
+    /// The directory that requested paths are resolved against and must stay within.
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+
+    /// Restrict every review to a specific concern. Can be repeated.
+    #[arg(long, value_enum)]
+    focus: Vec<FocusArea>,
+
+    /// Add the house rules of a rule pack to every review. Can be repeated.
+    #[arg(long = "rules", value_name = "NAME")]
+    rules: Vec<String>,
+
+    /// The directory containing the rule packs selected with `--rules`.
+    #[arg(long, default_value = rules::DEFAULT_RULEPACK_DIR)]
+    rules_dir: PathBuf,
+
+    /// The directory where analysis results are cached by file content hash.
+    #[arg(long, default_value = cache::DEFAULT_CACHE_DIR)]
+    cache_dir: PathBuf,
+
+    /// Always call the API, ignoring and not updating the result cache, and
+    /// bypassing the shared response cache even if `AI_CACHE` is set.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// The maximum number of API requests started per minute.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    requests_per_minute: Option<u32>,
+
+    /// The model provider: `openai`, `azure`, `anthropic`, or `ollama`.
+    #[arg(long)]
+    provider: Option<ProviderKind>,
+
+    /// The model to use instead of the provider's default.
+    #[arg(long)]
+    model: Option<String>,
 }
 
 /// The main asynchronous function that runs our application.
@@ -41,70 +278,424 @@ struct Args {
 async fn main() -> Result<()> {
     // Parse the command-line arguments. `clap` will handle errors and exit
     // if the arguments are invalid.
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let ci = args.ci;
+    if ci {
+        colored::control::set_override(false);
+    }
 
     // Use a single, top-level try block to catch and handle any errors
     // that bubble up from our application's logic.
-    if let Err(e) = run_analyzer(args).await {
-        eprintln!("{} {}", "Error:".red().bold(), e);
-        // Ensure the process exits with a non-zero status code on error.
-        std::process::exit(1);
+    let result = match args.command.take() {
+        Some(Command::GenerateTests(test_args)) => run_generate_tests(test_args).await,
+        Some(Command::Serve(serve_args)) => run_server(serve_args).await,
+        Some(Command::Deps(deps_args)) => {
+            let client = build_client(None, None, None, None, false, false)?;
+            deps::review_dependencies(&client, &deps_args.path)
+                .await
+                .map(|()| 0)
+        }
+        None => run_analyzer(args).await,
+    };
+    match result {
+        Ok(0) => {}
+        Ok(exit_code) => std::process::exit(exit_code),
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            // Ensure the process exits with a non-zero status code on error.
+            std::process::exit(if ci { summary::EXIT_CI_ERROR } else { 1 });
+        }
     }
 
     Ok(())
 }
 
+/// Runs the `generate-tests` subcommand.
+///
+/// Returns the process exit code: `0` if tests were saved, `1` otherwise.
+async fn run_generate_tests(args: GenerateTestsArgs) -> Result<i32> {
+    let client = build_client(None, None, None, None, false, false)?;
+    let options = TestGenOptions {
+        candidate_dir: args.candidate_dir,
+        attempts: args.attempts,
+    };
+    let saved = testgen::generate_tests(&client, &args.path, &args.target, &options).await?;
+    Ok(if saved { 0 } else { 1 })
+}
+
+/// Builds the client for the selected provider, model, request budget, and
+/// maximum cost, which override `AI_PROVIDER`, `AI_MODEL`,
+/// `AI_REQUESTS_PER_MINUTE`, and `AI_MAX_COST`. The usage of every request
+/// is recorded in the shared usage ledger. The shared response cache is
+/// used if `AI_CACHE` is set, unless `no_cache` bypasses it.
+fn build_client(
+    provider: Option<ProviderKind>,
+    model: Option<String>,
+    requests_per_minute: Option<u32>,
+    max_cost: Option<f64>,
+    no_cache: bool,
+    dry_run: bool,
+) -> Result<openai::Client> {
+    let mut provider_config =
+        config::provider_config()?.track_usage(env!("CARGO_PKG_NAME"), max_cost);
+    if no_cache {
+        provider_config = provider_config.without_cache();
+    }
+    if let Some(provider) = provider {
+        provider_config = provider_config.with_provider(provider);
+    }
+    if let Some(model) = model {
+        provider_config = provider_config.with_model(model);
+    }
+    if let Some(requests_per_minute) = requests_per_minute {
+        let rate_limits = RateLimits {
+            requests_per_minute: Some(requests_per_minute),
+            ..provider_config.rate_limits
+        };
+        provider_config = provider_config.with_rate_limits(rate_limits);
+    }
+
+    // A dry run never contacts the API, so it does not require a key.
+    if dry_run {
+        return Ok(openai::Client::new(&provider_config)
+            .unwrap_or_else(|_| openai::Client::offline(&provider_config)));
+    }
+    openai::Client::new(&provider_config)
+}
+
+/// Runs the `serve` subcommand until the process is stopped.
+async fn run_server(args: ServeArgs) -> Result<i32> {
+    let client = build_client(
+        args.provider,
+        args.model,
+        args.requests_per_minute,
+        None,
+        args.no_cache,
+        false,
+    )?;
+    let mut analyzer = Analyzer::new(client)
+        .with_focus(args.focus)
+        .with_rules(rules::load_all(&args.rules_dir, &args.rules)?);
+    if !args.no_cache {
+        analyzer = analyzer.with_cache(AnalysisCache::new(args.cache_dir));
+    }
+    server::serve(analyzer, &args.root, args.addr).await?;
+    Ok(0)
+}
+
 /// The core logic runner for the analyzer.
 ///
 /// This function is separated from `main` to allow for clean error handling
-/// using the `?` operator.
-async fn run_analyzer(args: Args) -> Result<()> {
+/// using the `?` operator. Progress messages are written to standard error so
+/// that machine-readable output on standard output stays clean.
+///
+/// Returns the process exit code: `0` unless the CI gate failed.
+async fn run_analyzer(args: Args) -> Result<i32> {
+    // `path` is required by clap whenever no subcommand is given.
+    let path = args
+        .path
+        .clone()
+        .ok_or_else(|| Error::Config("A path to analyze is required.".to_string()))?;
+
+    if args.benches && !args.focus.contains(&FocusArea::Performance) {
+        return Err(Error::Config(
+            "--benches is only available with --focus performance.".to_string(),
+        ));
+    }
+
     // --- Initialization ---
-    println!("{}", "Initializing analyzer...".cyan());
-    let api_key = config::api_key()?;
-    let client = openai::Client::new(api_key);
+    eprintln!("{}", "Initializing analyzer...".cyan());
+    let client = build_client(
+        args.provider,
+        args.model.clone(),
+        args.requests_per_minute,
+        args.max_cost,
+        args.no_cache,
+        args.dry_run,
+    )?;
+    let mut analyzer = Analyzer::new(client.clone())
+        .with_focus(args.focus.clone())
+        .with_rules(rules::load_all(&args.rules_dir, &args.rules)?);
+    if !args.no_cache {
+        analyzer = analyzer.with_cache(AnalysisCache::new(args.cache_dir.clone()));
+    }
 
     // --- File Discovery ---
     let mut files_to_analyze = Vec::new();
+    let mut function_target = None;
 
-    if args.path.is_dir() {
+    if let Some(function_path) = &args.function {
+        // A targeted review analyzes only the file that defines the function.
+        let target = symbols::resolve_function(&path, function_path)?;
+        eprintln!(
+            "-> Resolved '{}' to {}:{}-{} with {} dependent definition(s).",
+            function_path,
+            files::relative_uri(&target.file),
+            target.start_line,
+            target.end_line,
+            target.dependencies.len()
+        );
+        files_to_analyze.push(target.file.clone());
+        function_target = Some(target);
+    } else if path.is_dir() {
         // If the path is a directory, find all `.rs` files within it.
-        files_to_analyze = files::find_rust_files(&args.path)?;
-    } else if args.path.is_file() {
+        let filter = files::FileFilter::new(&path, &args.include, &args.exclude)?;
+        files_to_analyze = files::find_rust_files(&path, &filter)?;
+    } else if path.is_file() {
         // If it's a single file, just add it to the list.
-        files_to_analyze.push(args.path);
+        files_to_analyze.push(path.clone());
     } else {
         // If the path doesn't exist, print an error.
         eprintln!(
             "{} Path '{}' is not a valid file or directory.",
             "Error:".red().bold(),
-            args.path.display()
+            path.display()
         );
         std::process::exit(1);
     }
 
     if files_to_analyze.is_empty() {
-        println!("{}", "No Rust files to analyze. Exiting.".yellow());
-        return Ok(());
+        eprintln!("{}", "No Rust files to analyze. Exiting.".yellow());
+        return Ok(0);
     }
 
-    // --- Analysis Loop ---
-    for file_path in files_to_analyze {
-        // Analyze each file. If an error occurs for a single file,
-        // we print it and continue to the next one.
-        if let Err(e) = analyzer::analyze_file(&client, &file_path).await {
+    // When updating the baseline, every finding is kept so it can be recorded.
+    let baseline = if args.update_baseline {
+        None
+    } else {
+        Baseline::load(&args.baseline)?
+    };
+    let mut suppressed = 0;
+    let mut below_min_severity = 0;
+    let mut cache_hits = 0;
+    let mut files_failed = 0;
+
+    if args.clippy {
+        match files::find_manifest_dir(&path) {
+            Some(manifest_dir) => match DiagnosticIndex::collect(&manifest_dir).await {
+                Ok(index) => analyzer = analyzer.with_diagnostics(index),
+                Err(e) => eprintln!(
+                    "{} Could not run cargo clippy: {}",
+                    "Warning:".yellow().bold(),
+                    e
+                ),
+            },
+            None => eprintln!(
+                "{} No Cargo.toml found for '{}'; skipping Clippy diagnostics.",
+                "Warning:".yellow().bold(),
+                path.display()
+            ),
+        }
+    }
+
+    // --- Workspace Layout ---
+    // In a multi-crate workspace, files are analyzed crate by crate with the
+    // crate's dependency edges in their prompts.
+    let workspace = match Workspace::detect(&path).await {
+        Ok(workspace) => workspace.filter(Workspace::is_multi_crate),
+        Err(e) => {
             eprintln!(
-                "{} Could not analyze file '{}': {}",
+                "{} Could not read the Cargo workspace layout: {}",
                 "Warning:".yellow().bold(),
-                file_path.display(),
                 e
             );
+            None
         }
+    };
+    if let Some(workspace) = &workspace {
+        files_to_analyze.sort_by_key(|file_path| workspace.crate_index(file_path));
+        eprintln!(
+            "-> Detected a Cargo workspace with {} crates.",
+            workspace.crates.len()
+        );
+        analyzer = analyzer.with_workspace(workspace.clone());
     }
 
-    println!(
-        "\n{}",
-        "Analysis complete. All files have been processed.".green().bold()
-    );
-    Ok(())
+    // --- Dry Run ---
+    if args.dry_run {
+        let mut estimates = Vec::with_capacity(files_to_analyze.len());
+        for file_path in files_to_analyze {
+            let plan = analyzer.plan_file(&file_path)?;
+            estimates.push(estimate::estimate_file(
+                file_path,
+                client.model(),
+                &plan.system_prompt,
+                &plan.user_prompt,
+                plan.cached,
+            ));
+        }
+        estimate::print_plan(&estimates, client.model());
+        return Ok(0);
+    }
+
+    let fix_options = FixOptions {
+        patch_dir: args.patch_dir.clone(),
+        apply: args.apply,
+        verify: args.verify,
+    };
+
+    // --- Analysis Loop ---
+    // Files are analyzed concurrently, up to the configured limit. `buffered`
+    // yields results in the original file order, so output stays deterministic.
+    let mut analyses = Vec::with_capacity(files_to_analyze.len());
+    let analyzer = &analyzer;
+    let function_target = function_target.as_ref();
+    let mut results = stream::iter(files_to_analyze)
+        .map(|file_path| async move {
+            let result = match function_target {
+                Some(target) => analyzer.analyze_function(target).await,
+                None => analyzer.analyze_file(&file_path).await,
+            };
+            (file_path, result)
+        })
+        .buffered(args.concurrency as usize);
+
+    let mut current_crate = None;
+    let mut benchmarks = Vec::new();
+    while let Some((file_path, result)) = results.next().await {
+        if let Some(workspace) = &workspace {
+            let crate_index = workspace.crate_index(&file_path);
+            if crate_index != current_crate && args.format == OutputFormat::Text {
+                if let Some(index) = crate_index {
+                    output::print_crate_header(&workspace.crates[index]);
+                }
+            }
+            current_crate = crate_index;
+        }
+
+        // If an error occurs for a single file, we print it and continue
+        // to the next one.
+        match result {
+            Ok(mut analysis) => {
+                if analysis.from_cache {
+                    cache_hits += 1;
+                }
+                if let Some(baseline) = &baseline {
+                    suppressed += baseline.filter(&mut analysis);
+                }
+                if let Some(min_severity) = args.min_severity {
+                    let before = analysis.findings.len();
+                    analysis
+                        .findings
+                        .retain(|finding| finding.severity >= min_severity);
+                    below_min_severity += before - analysis.findings.len();
+                }
+                if args.format == OutputFormat::Text {
+                    output::print_file_analysis(&analysis);
+                }
+                if args.fix && !analysis.findings.is_empty() {
+                    if let Err(e) = fixes::fix_file(&client, &analysis, &fix_options).await {
+                        eprintln!(
+                            "{} Could not generate fixes for '{}': {}",
+                            "Warning:".yellow().bold(),
+                            file_path.display(),
+                            e
+                        );
+                    }
+                }
+                if args.benches && !analysis.findings.is_empty() {
+                    match benches::suggest_benchmarks(&client, &analysis).await {
+                        Ok(names) => benchmarks.extend(names),
+                        Err(e) => eprintln!(
+                            "{} Could not suggest benchmarks for '{}': {}",
+                            "Warning:".yellow().bold(),
+                            file_path.display(),
+                            e
+                        ),
+                    }
+                }
+                analyses.push(analysis);
+            }
+            Err(e) => {
+                files_failed += 1;
+                eprintln!(
+                    "{} Could not analyze file '{}': {}",
+                    "Warning:".yellow().bold(),
+                    file_path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    benches::print_manifest_entries(&benchmarks);
+
+    if below_min_severity > 0 {
+        eprintln!(
+            "-> {} finding(s) below '{}' severity hidden.",
+            below_min_severity,
+            args.min_severity.unwrap_or(Severity::Info)
+        );
+    }
+
+    if cache_hits > 0 {
+        eprintln!(
+            "-> {} unchanged file(s) served from the cache in '{}'.",
+            cache_hits,
+            args.cache_dir.display()
+        );
+    }
+
+    // --- Interactive Review ---
+    if args.review {
+        let outcome =
+            review::run_review(&client, &mut analyses, &fix_options, &args.baseline).await?;
+        eprintln!(
+            "-> Review complete: {} accepted, {} dismissed.",
+            outcome.accepted, outcome.dismissed
+        );
+    }
+
+    // --- Baseline ---
+    if args.update_baseline {
+        let baseline = Baseline::from_analyses(&analyses);
+        baseline.save(&args.baseline)?;
+        eprintln!(
+            "-> Recorded {} finding(s) in baseline '{}'.",
+            baseline.entry_count(),
+            args.baseline.display()
+        );
+    } else if suppressed > 0 {
+        eprintln!(
+            "-> {} known finding(s) suppressed by the baseline.",
+            suppressed
+        );
+    }
+
+    // --- Reporting ---
+    output::write_results(args.format, &analyses, args.output.as_deref())?;
+    if let Some(report_path) = &args.report {
+        report::write_report(report_path, &analyses)?;
+    }
+
+    // --- Summary and Quality Gate ---
+    let run_summary = RunSummary::new(&analyses, files_failed, args.ci.then_some(args.fail_on));
+    run_summary.print_table();
+    run_summary.print_by_crate();
+    if let Some(path) = &args.summary_json {
+        run_summary.write_json(path)?;
+    }
+
+    if !args.ci {
+        return Ok(0);
+    }
+    if files_failed > 0 {
+        eprintln!(
+            "{} {} file(s) could not be analyzed.",
+            "CI gate:".red().bold(),
+            files_failed
+        );
+        return Ok(summary::EXIT_CI_ERROR);
+    }
+    if !run_summary.passed {
+        eprintln!(
+            "{} {} finding(s) at or above '{}' severity.",
+            "CI gate failed:".red().bold(),
+            run_summary.gate_findings,
+            args.fail_on
+        );
+        return Ok(summary::EXIT_GATE_FAILED);
+    }
+    eprintln!("{}", "CI gate passed.".green().bold());
+    Ok(0)
 }
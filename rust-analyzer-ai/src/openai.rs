@@ -1,113 +1,108 @@
 //! src/openai.rs
 //!
-//! This module serves as the client for the OpenAI API. It is specifically
-//! tailored to send Rust source code for analysis and retrieve actionable
-//! feedback.
+//! This module serves as the client for the model provider. It is responsible
+//! only for transport: building chat completion requests, sending them to the
+//! provider chosen with `--provider` or `AI_PROVIDER` (OpenAI by default, or
+//! Azure OpenAI, Anthropic, or a local Ollama server), and returning the
+//! model's reply. Prompt construction lives with the callers.
 
 use crate::error::{Error, Result};
-use serde::{Deserialize, Serialize};
+use ai_core::{ChatProvider, ChatRequest, Message, ProviderConfig, ProviderKind};
+use std::sync::Arc;
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
+const AI_MODEL_NAME: &str = "gpt-4o"; // Using a more advanced model for better code analysis.
 
-// Defines the client responsible for making requests to OpenAI.
+/// The model used with Ollama when neither `--model` nor `AI_MODEL` is given.
+const OLLAMA_MODEL_NAME: &str = "qwen2.5-coder";
+
+/// The maximum number of follow-up requests made to complete a truncated reply.
+const MAX_CONTINUATIONS: usize = 3;
+
+/// The instruction sent after a reply was cut off by the output token limit.
+const CONTINUATION_PROMPT: &str = "Your previous reply was cut off. Continue exactly where it \
+    stopped, without repeating anything and without any preamble, so that the two parts can be \
+    joined directly.";
+
+// Defines the client responsible for making requests to the model provider,
+// on top of the shared `ai-core` providers.
 #[derive(Clone)]
 pub struct Client {
-    http_client: reqwest::Client,
-    api_key: String,
+    provider: Arc<dyn ChatProvider>,
+    model: String,
 }
 
 impl Client {
-    /// Creates a new OpenAI client.
-    pub fn new(api_key: String) -> Self {
+    /// Creates a client for the configured provider and model.
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            provider: config.chat_provider()?,
+            model: model_name(config),
+        })
+    }
+
+    /// Creates a client that knows the configured model but has no
+    /// credentials, for dry runs, which never send a request.
+    pub fn offline(config: &ProviderConfig) -> Self {
         Self {
-            http_client: reqwest::Client::new(),
-            api_key,
+            provider: Arc::new(ai_core::Client::new(String::new())),
+            model: model_name(config),
         }
     }
 
-    /// Sends a file's content to the OpenAI API for analysis.
-    ///
-    /// This function constructs a specialized prompt, asking the AI model to act as
-    /// a senior Rust developer and provide refactoring suggestions.
-    ///
-    /// # Arguments
-    ///
-    /// * `file_content` - A string slice containing the Rust source code to analyze.
-    ///
-    /// # Returns
+    /// The name of the model this client sends requests to.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Sends a system and user prompt and returns the model's reply as free text.
+    pub async fn complete(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+        let request = ChatRequest::from_prompts(&self.model, system_prompt, user_prompt);
+        self.send(request).await
+    }
+
+    /// Sends a system and user prompt with JSON mode enabled.
     ///
-    /// A `Result` containing the AI-generated analysis as a `String`.
-    pub async fn analyze_code(&self, file_content: &str) -> Result<String> {
-        let system_prompt = "You are an expert Rust programmer with over 20 years of experience. \
-            You are acting as a code reviewer. Your goal is to provide concise, actionable feedback \
-            to help a developer improve their code. Focus on identifying anti-patterns, suggesting \
-            idiomatic Rust, improving clarity, and pointing out potential performance improvements. \
-            Do not comment on code style like formatting, as that is handled by rustfmt. \
-            Provide your feedback in a clear, bulleted list.";
-
-        let user_prompt = format!(
-            "Please review the following Rust code and provide refactoring suggestions:\n\n```rust\n{}\n```",
-            file_content
-        );
-
-        let messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: user_prompt,
-            },
-        ];
-
-        let body = ChatCompletionRequest {
-            model: "gpt-4o".to_string(), // Using a more advanced model for better code analysis.
-            messages,
-        };
-
-        // Make the API request.
-        let response: ChatCompletionResponse = self
-            .http_client
-            .post(OPENAI_API_URL)
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-
-        // Extract the content from the API response.
-        if let Some(choice) = response.choices.get(0) {
-            Ok(choice.message.content.clone())
-        } else {
-            Err(Error::OpenAI("No analysis received from API".to_string()))
-        }
+    /// The reply is guaranteed to be a single JSON object; the system prompt
+    /// must still describe the schema the caller expects.
+    pub async fn complete_json(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+        let request = ChatRequest::from_prompts(&self.model, system_prompt, user_prompt).json();
+        self.send(request).await
     }
-}
 
-//========= API Data Structures =========//
-// These structs map to the JSON format of the OpenAI Chat Completions API.
+    /// Sends the chat completion request and extracts the first choice.
+    ///
+    /// If the reply is cut off by the output token limit (`finish_reason:
+    /// length`), the partial reply is sent back with a request to continue,
+    /// and the parts are stitched together, up to `MAX_CONTINUATIONS` times.
+    async fn send(&self, mut request: ChatRequest) -> Result<String> {
+        let mut reply = String::new();
 
-#[derive(Serialize, Debug)]
-struct ChatCompletionRequest {
-    model: String,
-    messages: Vec<Message>,
-}
+        for _ in 0..=MAX_CONTINUATIONS {
+            let choice = self.provider.chat_choice(&request).await?;
+            reply.push_str(&choice.message.content);
+            if choice.finish_reason.as_deref() != Some("length") {
+                return Ok(reply);
+            }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Message {
-    role: String,
-    content: String,
-}
+            // Continuations are plain text: in JSON mode the model would start a
+            // new object instead of resuming the truncated one.
+            request.response_format = None;
+            request.messages.push(choice.message);
+            request.messages.push(Message::user(CONTINUATION_PROMPT));
+        }
 
-#[derive(Deserialize, Debug)]
-struct ChatCompletionResponse {
-    choices: Vec<Choice>,
+        Err(Error::OpenAI(format!(
+            "Response was still truncated after {} continuation requests",
+            MAX_CONTINUATIONS
+        )))
+    }
 }
 
-#[derive(Deserialize, Debug)]
-struct Choice {
-    message: Message,
+/// The configured model, or else the default of the configured provider.
+/// Ollama defaults to a code model rather than `ai-core`'s general one.
+fn model_name(config: &ProviderConfig) -> String {
+    match (config.provider, &config.model) {
+        (ProviderKind::Ollama, None) => OLLAMA_MODEL_NAME.to_string(),
+        _ => config.chat_model(AI_MODEL_NAME),
+    }
 }
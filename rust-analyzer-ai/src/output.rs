@@ -0,0 +1,120 @@
+//! src/output.rs
+//!
+//! This module is responsible for presenting analysis results, either as
+//! human-readable terminal output or in a machine-readable format.
+
+use crate::error::Result;
+use crate::findings::{FileAnalysis, Severity};
+use crate::sarif;
+use crate::workspace::CrateInfo;
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
+use std::fs;
+use std::path::Path;
+
+/// The supported output formats for analysis results.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored, human-readable findings printed per file.
+    Text,
+    /// A SARIF 2.1.0 log, suitable for GitHub code scanning and CI dashboards.
+    Sarif,
+    /// A JSON array of per-file results, each with its list of findings.
+    Json,
+}
+
+/// Prints a banner introducing the files of a workspace crate.
+pub fn print_crate_header(info: &CrateInfo) {
+    println!(
+        "\n{} {}",
+        "### Crate:".magenta().bold(),
+        info.name.bright_white().bold()
+    );
+    if !info.dependencies.is_empty() {
+        println!("    depends on: {}", info.dependencies.join(", "));
+    }
+}
+
+/// Prints the findings for a single file in a formatted block.
+///
+/// Files without findings are not printed; they are counted in the final summary.
+pub fn print_file_analysis(analysis: &FileAnalysis) {
+    if analysis.findings.is_empty() {
+        return;
+    }
+
+    // Print a header for the file being analyzed.
+    println!(
+        "\n{}",
+        "==================================================".blue()
+    );
+    println!(
+        "{} {}",
+        "Analyzing:".blue().bold(),
+        analysis.path.display().to_string().bright_white()
+    );
+    println!(
+        "{}",
+        "==================================================".blue()
+    );
+
+    println!("{}", "Analysis:".green().bold());
+    for finding in &analysis.findings {
+        let location = match (finding.line, finding.end_line) {
+            (Some(start), Some(end)) if end > start => format!("L{}-{}", start, end),
+            (Some(start), _) => format!("L{}", start),
+            (None, _) => "-".to_string(),
+        };
+        println!(
+            "- [{}] {} ({}, {})",
+            colorize_severity(finding.severity),
+            finding.title.bold(),
+            finding.category,
+            location
+        );
+        println!("  {}", finding.message.trim());
+        if let Some(suggestion) = &finding.suggestion {
+            println!("  {} {}", "Suggestion:".cyan(), suggestion.trim());
+        }
+    }
+}
+
+/// Writes the complete set of results in a machine-readable format.
+///
+/// If `output_path` is `None`, the document is written to standard output.
+pub fn write_results(
+    format: OutputFormat,
+    analyses: &[FileAnalysis],
+    output_path: Option<&Path>,
+) -> Result<()> {
+    let document = match format {
+        // Text output is printed incrementally as each file completes.
+        OutputFormat::Text => return Ok(()),
+        OutputFormat::Sarif => serde_json::to_string_pretty(&sarif::build_log(analyses))?,
+        OutputFormat::Json => serde_json::to_string_pretty(analyses)?,
+    };
+
+    match output_path {
+        Some(path) => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, document)?;
+            eprintln!("-> Results written to '{}'.", path.display());
+        }
+        None => println!("{}", document),
+    }
+    Ok(())
+}
+
+/// Applies a severity-specific color to the severity label.
+fn colorize_severity(severity: Severity) -> ColoredString {
+    let label = severity.to_string().to_uppercase();
+    match severity {
+        Severity::Info => label.normal(),
+        Severity::Low => label.cyan(),
+        Severity::Medium => label.yellow(),
+        Severity::High => label.red(),
+        Severity::Critical => label.red().bold(),
+    }
+}
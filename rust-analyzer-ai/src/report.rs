@@ -0,0 +1,295 @@
+//! src/report.rs
+//!
+//! This module renders a standalone report of all findings, grouped by file
+//! and ordered by severity, with an excerpt of the code each finding refers
+//! to. The format is chosen from the report file's extension: `.html`/`.htm`
+//! produces a navigable HTML page and `.md` produces Markdown.
+
+use crate::error::{Error, Result};
+use crate::files;
+use crate::findings::{FileAnalysis, Finding, Severity};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Lines of context shown above and below a finding's location.
+const EXCERPT_CONTEXT: usize = 2;
+
+/// Renders the report for `analyses` and writes it to `path`.
+///
+/// # Errors
+///
+/// Returns an error if the extension is not supported or the file cannot be written.
+pub fn write_report(path: &Path, analyses: &[FileAnalysis]) -> Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+
+    let document = match extension.as_deref() {
+        Some("html") | Some("htm") => render_html(analyses),
+        Some("md") | Some("markdown") => render_markdown(analyses),
+        _ => {
+            return Err(Error::Config(format!(
+                "Unsupported report format for '{}': use a .html or .md file.",
+                path.display()
+            )))
+        }
+    };
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, document)?;
+    eprintln!("-> Report written to '{}'.", path.display());
+    Ok(())
+}
+
+/// A file's findings sorted from most to least severe, with its source lines.
+struct ReportSection<'a> {
+    uri: String,
+    findings: Vec<&'a Finding>,
+    lines: Vec<String>,
+}
+
+/// Prepares the per-file sections, skipping files without findings.
+fn sections(analyses: &[FileAnalysis]) -> Vec<ReportSection<'_>> {
+    analyses
+        .iter()
+        .filter(|analysis| !analysis.findings.is_empty())
+        .map(|analysis| {
+            let mut findings: Vec<&Finding> = analysis.findings.iter().collect();
+            findings.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.line.cmp(&b.line)));
+            // The source is re-read for excerpts; if it has since disappeared,
+            // the report is still produced, just without code.
+            let lines = fs::read_to_string(&analysis.path)
+                .map(|content| content.lines().map(str::to_string).collect())
+                .unwrap_or_default();
+            ReportSection {
+                uri: files::relative_uri(&analysis.path),
+                findings,
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Counts findings per severity across all sections, most severe first.
+fn severity_counts(sections: &[ReportSection]) -> Vec<(Severity, usize)> {
+    let all = [
+        Severity::Critical,
+        Severity::High,
+        Severity::Medium,
+        Severity::Low,
+        Severity::Info,
+    ];
+    all.into_iter()
+        .map(|severity| {
+            let count = sections
+                .iter()
+                .flat_map(|section| &section.findings)
+                .filter(|finding| finding.severity == severity)
+                .count();
+            (severity, count)
+        })
+        .collect()
+}
+
+fn location_label(finding: &Finding) -> String {
+    match (finding.line, finding.end_line) {
+        (Some(start), Some(end)) if end > start => format!("lines {}-{}", start, end),
+        (Some(start), _) => format!("line {}", start),
+        (None, _) => "no location".to_string(),
+    }
+}
+
+//========= Markdown =========//
+
+fn render_markdown(analyses: &[FileAnalysis]) -> String {
+    let sections = sections(analyses);
+    let mut out = String::from("# Rust Code Analysis Report\n\n");
+
+    let _ = writeln!(out, "| Severity | Findings |\n|----------|----------|");
+    for (severity, count) in severity_counts(&sections) {
+        let _ = writeln!(out, "| {} | {} |", severity, count);
+    }
+    let _ = writeln!(out, "\n**Files analyzed:** {}\n", analyses.len());
+
+    if sections.is_empty() {
+        out.push_str("No findings.\n");
+        return out;
+    }
+
+    out.push_str("## Files\n\n");
+    for section in &sections {
+        let _ = writeln!(
+            out,
+            "- [{}](#{}) ({} finding(s))",
+            section.uri,
+            anchor(&section.uri),
+            section.findings.len()
+        );
+    }
+
+    // Renderers slug headings differently (GitHub drops the `/` and `.` of
+    // a path), so each file's heading gets an explicit anchor for the links.
+    for section in &sections {
+        let _ = writeln!(
+            out,
+            "\n<a id=\"{}\"></a>\n\n## {}\n",
+            anchor(&section.uri),
+            section.uri
+        );
+        for finding in &section.findings {
+            let _ = writeln!(
+                out,
+                "### [{}] {}\n\n*{} · {}*\n\n{}\n",
+                finding.severity.to_string().to_uppercase(),
+                finding.title,
+                finding.category,
+                location_label(finding),
+                finding.message.trim()
+            );
+            if let Some(lines) = finding.excerpt(&section.lines, EXCERPT_CONTEXT) {
+                out.push_str("```rust\n");
+                for (number, line) in lines {
+                    let _ = writeln!(out, "{:>4} | {}", number, line);
+                }
+                out.push_str("```\n\n");
+            }
+            if let Some(suggestion) = &finding.suggestion {
+                let _ = writeln!(out, "**Suggestion:** {}\n", suggestion.trim());
+            }
+        }
+    }
+    out
+}
+
+//========= HTML =========//
+
+const HTML_STYLE: &str =
+    "body{font-family:system-ui,sans-serif;margin:0;display:flex;color:#1f2328}\
+nav{width:280px;min-height:100vh;background:#f6f8fa;padding:1rem;box-sizing:border-box;\
+position:sticky;top:0;align-self:flex-start;overflow-y:auto;max-height:100vh}\
+nav a{display:block;padding:.2rem 0;color:#0969da;text-decoration:none;word-break:break-all}\
+main{flex:1;padding:1rem 2rem;max-width:1000px}\
+table{border-collapse:collapse}td,th{border:1px solid #d0d7de;padding:.3rem .8rem}\
+.finding{border:1px solid #d0d7de;border-radius:6px;padding:.8rem 1rem;margin:1rem 0}\
+.badge{display:inline-block;border-radius:4px;padding:0 .4rem;color:#fff;font-size:.8rem;\
+font-weight:bold;text-transform:uppercase;margin-right:.5rem}\
+.critical{background:#8b0000}.high{background:#cf222e}.medium{background:#bf8700}\
+.low{background:#0969da}.info{background:#6e7781}\
+.meta{color:#57606a;font-size:.9rem}\
+pre{background:#f6f8fa;padding:.6rem;overflow-x:auto;border-radius:6px}\
+.hl{background:#fff8c5;display:block}";
+
+fn render_html(analyses: &[FileAnalysis]) -> String {
+    let sections = sections(analyses);
+    let mut nav = String::new();
+    let mut body = String::new();
+
+    let _ = write!(
+        body,
+        "<h1>Rust Code Analysis Report</h1><p>Files analyzed: {}</p>\
+        <table><tr><th>Severity</th><th>Findings</th></tr>",
+        analyses.len()
+    );
+    for (severity, count) in severity_counts(&sections) {
+        let _ = write!(
+            body,
+            "<tr><td><span class=\"badge {0}\">{0}</span></td><td>{1}</td></tr>",
+            severity, count
+        );
+    }
+    body.push_str("</table>");
+
+    if sections.is_empty() {
+        body.push_str("<p>No findings.</p>");
+    }
+
+    for section in &sections {
+        let id = anchor(&section.uri);
+        let uri = escape_html(&section.uri);
+        let _ = write!(
+            nav,
+            "<a href=\"#{}\">{} ({})</a>",
+            id,
+            uri,
+            section.findings.len()
+        );
+        let _ = write!(body, "<h2 id=\"{}\">{}</h2>", id, uri);
+
+        for finding in &section.findings {
+            let _ = write!(
+                body,
+                "<div class=\"finding\"><h3><span class=\"badge {}\">{}</span>{}</h3>\
+                <div class=\"meta\">{} · {}</div><p>{}</p>",
+                finding.severity,
+                finding.severity,
+                escape_html(&finding.title),
+                escape_html(&finding.category),
+                location_label(finding),
+                escape_html(finding.message.trim())
+            );
+            if let Some(lines) = finding.excerpt(&section.lines, EXCERPT_CONTEXT) {
+                let start = finding.line.unwrap_or(0);
+                let end = finding.end_line.unwrap_or(start);
+                body.push_str("<pre><code>");
+                for (number, line) in lines {
+                    let text = format!("{:>4} | {}", number, escape_html(&line));
+                    if (start..=end).contains(&number) {
+                        let _ = write!(body, "<span class=\"hl\">{}</span>", text);
+                    } else {
+                        let _ = writeln!(body, "{}", text);
+                    }
+                }
+                body.push_str("</code></pre>");
+            }
+            if let Some(suggestion) = &finding.suggestion {
+                let _ = write!(
+                    body,
+                    "<p><strong>Suggestion:</strong> {}</p>",
+                    escape_html(suggestion.trim())
+                );
+            }
+            body.push_str("</div>");
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\">\
+        <title>Rust Code Analysis Report</title><style>{}</style></head>\
+        <body><nav><strong>Files</strong>{}</nav><main>{}</main></body></html>\n",
+        HTML_STYLE, nav, body
+    )
+}
+
+/// Builds a stable anchor identifier from a file path, used as the id of
+/// its section in both formats.
+fn anchor(uri: &str) -> String {
+    uri.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Escapes the characters that are significant in HTML text and attributes.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,203 @@
+//! src/review.rs
+//!
+//! This module implements the interactive review mode. Each finding is shown
+//! with the code around it, and the user decides what to do with it: accept
+//! it (generate a patch), dismiss it (record it in the baseline), or ask the
+//! model a follow-up question about it before deciding.
+
+use crate::error::Result;
+use crate::files;
+use crate::findings::{FileAnalysis, Finding};
+use crate::fixes::{self, FixOptions};
+use crate::openai;
+use crate::suppression::Baseline;
+use colored::Colorize;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Input, Select};
+use std::fs;
+use std::path::Path;
+
+/// Lines of context shown above and below a finding during review.
+const REVIEW_CONTEXT: usize = 4;
+
+const FOLLOW_UP_SYSTEM_PROMPT: &str = "You are an expert Rust programmer who reviewed a file \
+    and reported the finding shown below. The developer has a follow-up question about it. \
+    Answer concisely and concretely, referring to the code shown. If the finding turns out to be \
+    mistaken, say so plainly.";
+
+const ACTIONS: [&str; 5] = [
+    "Accept (write patch)",
+    "Dismiss (add to baseline)",
+    "Ask a follow-up question",
+    "Skip",
+    "Quit review",
+];
+
+/// The outcome of reviewing all findings.
+#[derive(Debug, Default)]
+pub struct ReviewOutcome {
+    pub accepted: usize,
+    pub dismissed: usize,
+}
+
+/// Walks the user through every finding, one at a time.
+///
+/// Dismissed findings are removed from `analyses` and recorded in the
+/// baseline at `baseline_path`, which is saved once the review ends.
+pub async fn run_review(
+    client: &openai::Client,
+    analyses: &mut [FileAnalysis],
+    fix_options: &FixOptions,
+    baseline_path: &Path,
+) -> Result<ReviewOutcome> {
+    let theme = ColorfulTheme::default();
+    let mut baseline = Baseline::load_or_empty(baseline_path)?;
+    let mut outcome = ReviewOutcome::default();
+    let total: usize = analyses
+        .iter()
+        .map(|analysis| analysis.findings.len())
+        .sum();
+    let mut position = 0;
+    let mut quit = false;
+
+    for analysis in analyses.iter_mut() {
+        let uri = files::relative_uri(&analysis.path);
+        let lines: Vec<String> = fs::read_to_string(&analysis.path)
+            .map(|content| content.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        let mut dismissed = Vec::new();
+
+        for (index, finding) in analysis.findings.iter().enumerate() {
+            if quit {
+                break;
+            }
+            position += 1;
+            show_finding(&uri, finding, &lines, position, total);
+
+            loop {
+                let choice = Select::with_theme(&theme)
+                    .with_prompt("Action")
+                    .items(&ACTIONS)
+                    .default(0)
+                    .interact()?;
+
+                match choice {
+                    0 => {
+                        let patch_name = format!(
+                            "{}-{}.patch",
+                            uri.replace('/', "_"),
+                            &finding.fingerprint[..finding.fingerprint.len().min(12)]
+                        );
+                        if fixes::fix_finding(
+                            client,
+                            &analysis.path,
+                            finding,
+                            &patch_name,
+                            fix_options,
+                        )
+                        .await?
+                        {
+                            outcome.accepted += 1;
+                        }
+                        break;
+                    }
+                    1 => {
+                        baseline.insert(&uri, finding);
+                        dismissed.push(index);
+                        outcome.dismissed += 1;
+                        break;
+                    }
+                    2 => {
+                        let question: String = Input::with_theme(&theme)
+                            .with_prompt("Question")
+                            .interact_text()?;
+                        let answer =
+                            ask_follow_up(client, &uri, finding, &lines, &question).await?;
+                        println!("\n{}\n{}\n", "Reviewer:".cyan().bold(), answer.trim());
+                    }
+                    3 => break,
+                    _ => {
+                        quit = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        remove_indices(&mut analysis.findings, &dismissed);
+        if quit {
+            break;
+        }
+    }
+
+    if outcome.dismissed > 0 {
+        baseline.save(baseline_path)?;
+    }
+    Ok(outcome)
+}
+
+/// Prints a finding together with the code it refers to.
+fn show_finding(uri: &str, finding: &Finding, lines: &[String], position: usize, total: usize) {
+    println!(
+        "\n{} {}",
+        format!("[{}/{}]", position, total).dimmed(),
+        uri.bright_white().bold()
+    );
+    println!(
+        "{} {} ({})",
+        format!("[{}]", finding.severity.to_string().to_uppercase()).red(),
+        finding.title.bold(),
+        finding.category
+    );
+
+    if let Some(excerpt) = finding.excerpt(lines, REVIEW_CONTEXT) {
+        let start = finding.line.unwrap_or(0);
+        let end = finding.end_line.unwrap_or(start);
+        for (number, line) in excerpt {
+            let text = format!("{:>4} | {}", number, line);
+            if (start..=end).contains(&number) {
+                println!("{}", text.yellow());
+            } else {
+                println!("{}", text.dimmed());
+            }
+        }
+    }
+
+    println!("{}", finding.message.trim());
+    if let Some(suggestion) = &finding.suggestion {
+        println!("{} {}", "Suggestion:".cyan(), suggestion.trim());
+    }
+}
+
+/// Asks the model a follow-up question about a single finding.
+async fn ask_follow_up(
+    client: &openai::Client,
+    uri: &str,
+    finding: &Finding,
+    lines: &[String],
+    question: &str,
+) -> Result<String> {
+    let code = finding
+        .excerpt(lines, REVIEW_CONTEXT * 4)
+        .map(|excerpt| {
+            excerpt
+                .into_iter()
+                .map(|(number, line)| format!("{:>4} | {}", number, line))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_else(|| lines.join("\n"));
+
+    let user_prompt = format!(
+        "File `{}`:\n```rust\n{}\n```\n\nFinding [{}] {}:\n{}\n\nQuestion: {}",
+        uri, code, finding.severity, finding.title, finding.message, question
+    );
+    client.complete(FOLLOW_UP_SYSTEM_PROMPT, &user_prompt).await
+}
+
+/// Removes the findings at the given (ascending) indices.
+fn remove_indices(findings: &mut Vec<Finding>, indices: &[usize]) {
+    for &index in indices.iter().rev() {
+        findings.remove(index);
+    }
+}
@@ -0,0 +1,133 @@
+//! src/rules.rs
+//!
+//! This module loads custom rule packs. A rule pack is a YAML or JSON file in
+//! the rule pack directory (`rulepacks/` by default) that encodes an
+//! organization's house conventions: a description, instructions added to
+//! the review prompt, and examples of code that follows or breaks them.
+//! Packs are selected by file name with `--rules <name>`.
+
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// The default directory searched for rule packs.
+pub const DEFAULT_RULEPACK_DIR: &str = "rulepacks";
+
+/// The file extensions a rule pack may use, in lookup order.
+const RULEPACK_EXTENSIONS: [&str; 3] = ["yaml", "yml", "json"];
+
+/// A named set of house conventions added to the review prompt.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RulePack {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Instructions appended to the reviewer prompt.
+    pub prompt: String,
+    #[serde(default)]
+    pub examples: Vec<RuleExample>,
+}
+
+/// An illustration of a convention: code that breaks it and code that follows it.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuleExample {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub bad: Option<String>,
+    #[serde(default)]
+    pub good: Option<String>,
+}
+
+impl RulePack {
+    /// Loads the pack `name` from `dir`, trying `.yaml`, `.yml`, then `.json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no file exists for the pack or it cannot be parsed.
+    pub fn load(dir: &Path, name: &str) -> Result<Self> {
+        let path = RULEPACK_EXTENSIONS
+            .iter()
+            .map(|extension| dir.join(format!("{}.{}", name, extension)))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "Rule pack '{}' not found in '{}' (expected {}.yaml, {}.yml, or {}.json)",
+                    name,
+                    dir.display(),
+                    name,
+                    name,
+                    name
+                ))
+            })?;
+
+        let content = fs::read_to_string(&path)?;
+        let pack = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::from_str(&content)?
+        } else {
+            serde_yaml::from_str(&content)?
+        };
+        Ok(pack)
+    }
+
+    /// The finding category used for violations of this pack.
+    pub fn category(&self) -> String {
+        format!("rules/{}", self.name)
+    }
+
+    /// Renders the pack as a section of the reviewer prompt.
+    fn prompt_section(&self) -> String {
+        let mut section = format!(
+            "House rules \"{}\"{}: {}\nReport violations of these rules under the category \"{}\".",
+            self.name,
+            if self.description.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", self.description.trim())
+            },
+            self.prompt.trim(),
+            self.category()
+        );
+        for example in &self.examples {
+            section.push_str("\n\nExample");
+            if !example.description.is_empty() {
+                section.push_str(&format!(": {}", example.description.trim()));
+            }
+            if let Some(bad) = &example.bad {
+                section.push_str(&format!(
+                    "\nViolates the rules:\n```rust\n{}\n```",
+                    bad.trim()
+                ));
+            }
+            if let Some(good) = &example.good {
+                section.push_str(&format!(
+                    "\nFollows the rules:\n```rust\n{}\n```",
+                    good.trim()
+                ));
+            }
+        }
+        section
+    }
+}
+
+/// Loads every pack in `names` from `dir`.
+pub fn load_all(dir: &Path, names: &[String]) -> Result<Vec<RulePack>> {
+    names.iter().map(|name| RulePack::load(dir, name)).collect()
+}
+
+/// Combines the selected rule packs into a single prompt section.
+pub fn build_rules_prompt(packs: &[RulePack]) -> String {
+    let sections = packs
+        .iter()
+        .map(RulePack::prompt_section)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        "In addition to your review, check the code against the organization's house rules below.\n\n{}",
+        sections
+    )
+}
@@ -0,0 +1,86 @@
+//! src/sarif.rs
+//!
+//! This module converts analysis results into a SARIF 2.1.0 log, the static
+//! analysis interchange format understood by GitHub code scanning and most
+//! CI dashboards.
+
+use crate::files;
+use crate::findings::FileAnalysis;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+const TOOL_NAME: &str = "rust-analyzer-ai";
+const TOOL_INFORMATION_URI: &str = "https://github.com/dfbustosus/ai-rs";
+
+/// Builds a single-run SARIF log from the results of an analysis.
+pub fn build_log(analyses: &[FileAnalysis]) -> Value {
+    // Every distinct category becomes a rule in the tool's driver.
+    let mut rules: BTreeMap<String, String> = BTreeMap::new();
+    let mut results = Vec::new();
+
+    for analysis in analyses {
+        let uri = files::relative_uri(&analysis.path);
+        for finding in &analysis.findings {
+            let rule_id = finding.rule_id();
+            rules
+                .entry(rule_id.clone())
+                .or_insert_with(|| format!("AI review: {}", finding.category));
+
+            let mut message = finding.message.trim().to_string();
+            if let Some(suggestion) = &finding.suggestion {
+                message.push_str("\n\nSuggestion: ");
+                message.push_str(suggestion.trim());
+            }
+
+            let mut physical_location = json!({
+                "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" }
+            });
+            if let Some(start_line) = finding.line {
+                physical_location["region"] = json!({
+                    "startLine": start_line,
+                    "endLine": finding.end_line.unwrap_or(start_line),
+                });
+            }
+
+            let mut result = json!({
+                "ruleId": rule_id,
+                "level": finding.severity.sarif_level(),
+                "message": { "text": format!("{}: {}", finding.title, message) },
+                "locations": [{ "physicalLocation": physical_location }],
+                "properties": { "severity": finding.severity.to_string() },
+            });
+            if !finding.fingerprint.is_empty() {
+                result["partialFingerprints"] = json!({ "aiFindingHash/v1": finding.fingerprint });
+            }
+            results.push(result);
+        }
+    }
+
+    let rules: Vec<Value> = rules
+        .into_iter()
+        .map(|(id, description)| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": description },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": TOOL_INFORMATION_URI,
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
@@ -0,0 +1,114 @@
+//! src/server.rs
+//!
+//! This module implements the `serve` mode: a long-running HTTP server for
+//! editor integrations. Plugins post a file path, and optionally the unsaved
+//! contents of its buffer, and receive the findings as JSON, without
+//! spawning a process per request. Results are cached like in a normal run.
+
+use crate::analyzer::Analyzer;
+use crate::error::{Error, Result};
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// The default address the server listens on; only local clients can connect.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8787";
+
+/// The body of a `POST /analyze` request.
+#[derive(Deserialize, Debug)]
+struct AnalyzeRequest {
+    /// The file to analyze, relative to the server's root or absolute within it.
+    path: PathBuf,
+    /// The current contents of the file, if they differ from the file on disk.
+    #[serde(default)]
+    contents: Option<String>,
+}
+
+/// The state shared by all requests.
+struct ServerState {
+    analyzer: Analyzer,
+    /// The canonical directory that requested paths must stay within.
+    root: PathBuf,
+}
+
+/// Serves the analyzer over HTTP on `addr` until the process is stopped.
+///
+/// Endpoints:
+/// - `GET /health` returns `ok`.
+/// - `POST /analyze` takes `{"path": "...", "contents": "..."}` and returns
+///   the file's result as `{"path": "...", "findings": [...]}`.
+pub async fn serve(analyzer: Analyzer, root: &Path, addr: SocketAddr) -> Result<()> {
+    let state = Arc::new(ServerState {
+        analyzer,
+        root: root.canonicalize()?,
+    });
+    let app = Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/analyze", post(analyze))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!("-> Listening on http://{} (POST /analyze).", addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Handles `POST /analyze`.
+async fn analyze(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<AnalyzeRequest>,
+) -> Response {
+    let Some(path) = resolve_path(&state.root, &request.path, request.contents.is_some()) else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "'{}' is not a file within the server root",
+                request.path.display()
+            ),
+        );
+    };
+
+    let result = match &request.contents {
+        Some(contents) => state.analyzer.analyze_source(&path, contents).await,
+        None => state.analyzer.analyze_file(&path).await,
+    };
+    match result {
+        Ok(analysis) => Json(analysis).into_response(),
+        Err(e) => {
+            let status = match e {
+                Error::Io(_) => StatusCode::BAD_REQUEST,
+                Error::OpenAI(_) | Error::Client(_) => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            error_response(status, e.to_string())
+        }
+    }
+}
+
+/// Resolves a requested path against the root, rejecting paths that escape it.
+///
+/// A file that does not exist yet is accepted when its contents are supplied,
+/// as long as its path does not climb out of the root.
+fn resolve_path(root: &Path, requested: &Path, has_contents: bool) -> Option<PathBuf> {
+    let path = root.join(requested);
+    match path.canonicalize() {
+        Ok(canonical) => Some(canonical).filter(|canonical| canonical.starts_with(root)),
+        Err(_) if has_contents => Some(path).filter(|path| {
+            path.starts_with(root)
+                && !path
+                    .components()
+                    .any(|component| component == Component::ParentDir)
+        }),
+        Err(_) => None,
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
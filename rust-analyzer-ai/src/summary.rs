@@ -0,0 +1,157 @@
+//! src/summary.rs
+//!
+//! This module aggregates the results of a run into a summary that can be
+//! written as machine-readable JSON and evaluated as a CI quality gate.
+
+use crate::error::Result;
+use crate::findings::{FileAnalysis, Severity};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// The number of categories listed in the summary table.
+const TOP_CATEGORIES: usize = 5;
+
+/// The process exit code used when the quality gate fails.
+pub const EXIT_GATE_FAILED: i32 = 1;
+
+/// The process exit code used in CI mode when the tool itself fails, so a
+/// broken run can be told apart from a failed gate.
+pub const EXIT_CI_ERROR: i32 = 2;
+
+/// Aggregate statistics and the gate verdict for a complete run.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub files_analyzed: usize,
+    pub files_failed: usize,
+    pub total_findings: usize,
+    pub by_severity: BTreeMap<Severity, usize>,
+    pub by_category: BTreeMap<String, usize>,
+    /// Per-crate totals, when a multi-crate workspace was analyzed.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub by_crate: BTreeMap<String, CrateSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_on: Option<Severity>,
+    /// Findings at or above the `fail_on` threshold.
+    pub gate_findings: usize,
+    /// Whether the gate passed: every file was analyzed and no finding
+    /// reached the threshold.
+    pub passed: bool,
+}
+
+/// The findings rolled up for a single workspace crate.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CrateSummary {
+    pub files_analyzed: usize,
+    pub total_findings: usize,
+    pub by_severity: BTreeMap<Severity, usize>,
+}
+
+impl RunSummary {
+    /// Builds the summary for a run, evaluating the gate against `fail_on`.
+    pub fn new(analyses: &[FileAnalysis], files_failed: usize, fail_on: Option<Severity>) -> Self {
+        let mut by_severity = BTreeMap::new();
+        let mut by_category: BTreeMap<String, usize> = BTreeMap::new();
+        let mut by_crate: BTreeMap<String, CrateSummary> = BTreeMap::new();
+        let mut gate_findings = 0;
+
+        for analysis in analyses {
+            let Some(crate_name) = &analysis.crate_name else {
+                continue;
+            };
+            let rollup = by_crate.entry(crate_name.clone()).or_default();
+            rollup.files_analyzed += 1;
+            rollup.total_findings += analysis.findings.len();
+            for finding in &analysis.findings {
+                *rollup.by_severity.entry(finding.severity).or_insert(0) += 1;
+            }
+        }
+
+        for finding in analyses.iter().flat_map(|analysis| &analysis.findings) {
+            *by_severity.entry(finding.severity).or_insert(0) += 1;
+            *by_category.entry(finding.category.clone()).or_insert(0) += 1;
+            if fail_on.is_some_and(|threshold| finding.severity >= threshold) {
+                gate_findings += 1;
+            }
+        }
+
+        Self {
+            files_analyzed: analyses.len(),
+            files_failed,
+            total_findings: by_severity.values().sum(),
+            by_severity,
+            by_category,
+            by_crate,
+            fail_on,
+            gate_findings,
+            passed: files_failed == 0 && gate_findings == 0,
+        }
+    }
+
+    /// Prints the final summary table: files, findings by severity, and the
+    /// most frequent categories.
+    pub fn print_table(&self) {
+        eprintln!("\n{}", "Summary".bold());
+        eprintln!("  {:<22} {:>6}", "Files analyzed", self.files_analyzed);
+        if self.files_failed > 0 {
+            eprintln!("  {:<22} {:>6}", "Files failed", self.files_failed);
+        }
+        eprintln!("  {:<22} {:>6}", "Findings", self.total_findings);
+
+        if self.total_findings == 0 {
+            return;
+        }
+        eprintln!("\n  {}", "By severity".bold());
+        for (severity, count) in self.by_severity.iter().rev() {
+            eprintln!("  {:<22} {:>6}", severity, count);
+        }
+
+        let mut categories: Vec<(&String, &usize)> = self.by_category.iter().collect();
+        categories.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        eprintln!("\n  {}", "Top categories".bold());
+        for (category, count) in categories.into_iter().take(TOP_CATEGORIES) {
+            eprintln!("  {:<22} {:>6}", category, count);
+        }
+    }
+
+    /// Prints the per-crate roll-up, highest-severity counts first.
+    pub fn print_by_crate(&self) {
+        if self.by_crate.is_empty() {
+            return;
+        }
+        eprintln!("\n{}", "Findings by crate:".bold());
+        for (name, rollup) in &self.by_crate {
+            let severities = rollup
+                .by_severity
+                .iter()
+                .rev()
+                .map(|(severity, count)| format!("{} {}", count, severity))
+                .collect::<Vec<_>>()
+                .join(", ");
+            eprintln!(
+                "  {:<30} {:>4} file(s) {:>5} finding(s){}",
+                name,
+                rollup.files_analyzed,
+                rollup.total_findings,
+                if severities.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", severities)
+                }
+            );
+        }
+    }
+
+    /// Writes the summary to `path` as pretty-printed JSON.
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
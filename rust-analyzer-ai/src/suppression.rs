@@ -0,0 +1,179 @@
+//! src/suppression.rs
+//!
+//! This module lets teams adopt the analyzer on existing codebases by hiding
+//! findings they have already accepted. Two mechanisms are supported:
+//! a baseline file that records existing findings by fingerprint, and inline
+//! `// ai-allow: <category>` comments placed on or directly above a line.
+
+use crate::error::{Error, Result};
+use crate::files;
+use crate::findings::{FileAnalysis, Finding};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// The baseline file used when no other path is given.
+pub const DEFAULT_BASELINE_PATH: &str = "analyzer-baseline.json";
+
+const BASELINE_VERSION: u32 = 1;
+const ALLOW_MARKER: &str = "ai-allow:";
+
+/// Computes a stable fingerprint for a finding.
+///
+/// The fingerprint combines the file, the category, and the trimmed source
+/// text of the finding's first line rather than the line number, so it
+/// survives unrelated edits that shift code up or down. Findings without a
+/// location fall back to their title.
+pub fn fingerprint(uri: &str, finding: &Finding, lines: &[&str]) -> String {
+    let anchor = finding
+        .line
+        .and_then(|line| line.checked_sub(1))
+        .and_then(|index| lines.get(index))
+        .map(|source| source.trim())
+        .filter(|source| !source.is_empty())
+        .map_or_else(|| finding.title.trim().to_lowercase(), str::to_string);
+
+    let mut hasher = Sha256::new();
+    for part in [uri, finding.category.as_str(), anchor.as_str()] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Returns `true` if an `ai-allow` comment suppresses the finding.
+///
+/// The comment may be on the finding's first line or the line directly above
+/// it, and lists one or more categories separated by commas; `all` matches
+/// every category (e.g., `// ai-allow: performance, clarity`).
+pub fn is_allowed_inline(finding: &Finding, lines: &[&str]) -> bool {
+    let Some(line) = finding.line else {
+        return false;
+    };
+    [line.checked_sub(1), line.checked_sub(2)]
+        .into_iter()
+        .flatten()
+        .filter_map(|index| lines.get(index))
+        .any(|source| allows_category(source, &finding.category))
+}
+
+/// Checks a single source line for an `ai-allow` comment naming `category`.
+fn allows_category(source: &str, category: &str) -> bool {
+    let Some(comment_start) = source.find("//") else {
+        return false;
+    };
+    let comment = &source[comment_start..];
+    let Some(marker) = comment.find(ALLOW_MARKER) else {
+        return false;
+    };
+    comment[marker + ALLOW_MARKER.len()..]
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .any(|token| token == category || token == "all")
+}
+
+/// A previously accepted finding recorded in the baseline file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BaselineEntry {
+    fingerprint: String,
+    path: String,
+    category: String,
+    title: String,
+}
+
+/// The set of findings that existed when the baseline was captured.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Baseline {
+    version: u32,
+    entries: Vec<BaselineEntry>,
+    #[serde(skip)]
+    known: HashSet<String>,
+}
+
+impl Baseline {
+    /// Loads a baseline file, returning `None` if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        let mut baseline: Baseline = serde_json::from_str(&content)?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(Error::Config(format!(
+                "Unsupported baseline version {} in '{}'.",
+                baseline.version,
+                path.display()
+            )));
+        }
+        baseline.known = baseline
+            .entries
+            .iter()
+            .map(|entry| entry.fingerprint.clone())
+            .collect();
+        Ok(Some(baseline))
+    }
+
+    /// Loads a baseline file, or starts an empty baseline if it does not exist.
+    pub fn load_or_empty(path: &Path) -> Result<Self> {
+        Ok(Self::load(path)?.unwrap_or_else(Self::empty))
+    }
+
+    /// Creates a baseline with no recorded findings.
+    pub fn empty() -> Self {
+        Self {
+            version: BASELINE_VERSION,
+            entries: Vec::new(),
+            known: HashSet::new(),
+        }
+    }
+
+    /// Captures every finding in the given results as a new baseline.
+    pub fn from_analyses(analyses: &[FileAnalysis]) -> Self {
+        let mut baseline = Self::empty();
+        for analysis in analyses {
+            let path = files::relative_uri(&analysis.path);
+            for finding in &analysis.findings {
+                baseline.insert(&path, finding);
+            }
+        }
+        baseline
+    }
+
+    /// Records a finding from the file at `path` (a relative URI) as accepted.
+    pub fn insert(&mut self, path: &str, finding: &Finding) {
+        if self.known.insert(finding.fingerprint.clone()) {
+            self.entries.push(BaselineEntry {
+                fingerprint: finding.fingerprint.clone(),
+                path: path.to_string(),
+                category: finding.category.clone(),
+                title: finding.title.clone(),
+            });
+        }
+    }
+
+    /// Writes the baseline to disk as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The number of findings recorded in the baseline.
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Removes findings already recorded in the baseline, returning how many were removed.
+    pub fn filter(&self, analysis: &mut FileAnalysis) -> usize {
+        let before = analysis.findings.len();
+        analysis
+            .findings
+            .retain(|finding| !self.known.contains(&finding.fingerprint));
+        before - analysis.findings.len()
+    }
+}
@@ -0,0 +1,637 @@
+//! src/symbols.rs
+//!
+//! This module resolves Rust items by path using `syn`. It locates a single
+//! function (e.g., `analyzer::number_lines` or `analyzer::Analyzer::new`)
+//! within its crate and gathers the definitions of the crate-local types it
+//! uses, so the function can be reviewed on its own with enough context.
+//! It also extracts the signatures of the items a file imports from sibling
+//! modules, which are included as context when the file is analyzed.
+
+use crate::error::{Error, Result};
+use proc_macro2::LineColumn;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{ImplItem, Item, TraitItem, Type, UseTree, Visibility};
+use walkdir::WalkDir;
+
+/// The candidate crate root files, tried in order.
+const CRATE_ROOTS: [&str; 2] = ["src/lib.rs", "src/main.rs"];
+
+/// The most imported definitions included in a single prompt.
+const MAX_IMPORTED_DEFINITIONS: usize = 30;
+
+/// A function located in the source tree, ready for a targeted review.
+#[derive(Debug)]
+pub struct FunctionTarget {
+    /// The path the function was requested by, e.g. `analyzer::Analyzer::new`.
+    pub qualified_name: String,
+    /// The file that contains the function.
+    pub file: PathBuf,
+    /// The first line of the function (1-based), including its attributes.
+    pub start_line: usize,
+    /// The last line of the function (1-based).
+    pub end_line: usize,
+    /// The crate-local types the function refers to.
+    pub dependencies: Vec<Definition>,
+}
+
+/// The source of a type, trait, or alias definition used by a target function.
+#[derive(Debug)]
+pub struct Definition {
+    pub name: String,
+    pub file: PathBuf,
+    pub source: String,
+}
+
+/// A parsed module: its items and the directory holding its child module files.
+struct Module {
+    file: PathBuf,
+    items: Vec<Item>,
+    child_dir: PathBuf,
+}
+
+/// Resolves `function_path` within the crate that contains `path`.
+///
+/// The function path is relative to the crate root and may start with
+/// `crate::`. Its last segment names a free function, or a method when the
+/// segment before it names a type with an `impl` block (or a trait with a
+/// default method body) in the same module.
+///
+/// # Errors
+///
+/// Returns an error if `path` is not inside a Cargo package, a module file
+/// cannot be read or parsed, or no function matches the path.
+pub fn resolve_function(path: &Path, function_path: &str) -> Result<FunctionTarget> {
+    let manifest_dir = crate::files::find_manifest_dir(path)
+        .ok_or_else(|| Error::Resolve(format!("No Cargo.toml found for '{}'", path.display())))?;
+    let segments: Vec<&str> = function_path
+        .trim_start_matches("crate::")
+        .split("::")
+        .collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err(Error::Resolve(format!(
+            "'{}' is not a valid item path",
+            function_path
+        )));
+    }
+
+    for root in CRATE_ROOTS.iter().map(|root| manifest_dir.join(root)) {
+        if !root.is_file() {
+            continue;
+        }
+        if let Some((file, function, owner)) = locate(load_module(&root)?, &segments)? {
+            let line_count = fs::read_to_string(&file)?.lines().count();
+            let mut used = used_type_names(&function.item);
+            used.extend(owner);
+            return Ok(FunctionTarget {
+                qualified_name: function_path.to_string(),
+                file,
+                start_line: function.start_line,
+                end_line: function.end_line.min(line_count),
+                dependencies: find_definitions(&manifest_dir.join("src"), &used),
+            });
+        }
+    }
+
+    Err(Error::Resolve(format!(
+        "No function '{}' found in the crate at '{}'",
+        function_path,
+        manifest_dir.display()
+    )))
+}
+
+/// Resolves `module_path` (e.g., `analyzer` or `crate::a::b`) within the crate
+/// that contains `path`, returning the file that holds the module.
+///
+/// # Errors
+///
+/// Returns an error if `path` is not inside a Cargo package or no module
+/// matches the path.
+pub fn resolve_module(path: &Path, module_path: &str) -> Result<PathBuf> {
+    let manifest_dir = crate::files::find_manifest_dir(path)
+        .ok_or_else(|| Error::Resolve(format!("No Cargo.toml found for '{}'", path.display())))?;
+    let segments: Vec<String> = module_path
+        .split("::")
+        .filter(|segment| !segment.is_empty() && *segment != "crate")
+        .map(str::to_string)
+        .collect();
+
+    find_module(&manifest_dir, &segments)
+        .map(|module| module.file)
+        .ok_or_else(|| {
+            Error::Resolve(format!(
+                "No module '{}' found in the crate at '{}'",
+                module_path,
+                manifest_dir.display()
+            ))
+        })
+}
+
+/// The syntax tree and line range of a located function.
+struct LocatedFunction {
+    item: FunctionItem,
+    start_line: usize,
+    end_line: usize,
+}
+
+/// The syntax of a free function, method, or default trait method.
+enum FunctionItem {
+    Free(syn::ItemFn),
+    Method(syn::ImplItemFn),
+    TraitMethod(syn::TraitItemFn),
+}
+
+impl LocatedFunction {
+    fn new(item: FunctionItem) -> Self {
+        let span = match &item {
+            FunctionItem::Free(item) => item.span(),
+            FunctionItem::Method(item) => item.span(),
+            FunctionItem::TraitMethod(item) => item.span(),
+        };
+        Self {
+            start_line: span.start().line,
+            end_line: span.end().line,
+            item,
+        }
+    }
+}
+
+/// Walks the module tree along `segments`. Returns the file, the function, and
+/// the name of the type or trait that owns it, if it is a method.
+fn locate(
+    module: Module,
+    segments: &[&str],
+) -> Result<Option<(PathBuf, LocatedFunction, Option<String>)>> {
+    match segments {
+        [] => Ok(None),
+        [name] => {
+            Ok(find_free_function(&module.items, name)
+                .map(|function| (module.file, function, None)))
+        }
+        [owner, name] => {
+            if let Some(function) = find_method(&module.items, owner, name) {
+                return Ok(Some((module.file, function, Some(owner.to_string()))));
+            }
+            match child_module(module, owner)? {
+                Some(child) => locate(child, &segments[1..]),
+                None => Ok(None),
+            }
+        }
+        [first, ..] => match child_module(module, first)? {
+            Some(child) => locate(child, &segments[1..]),
+            None => Ok(None),
+        },
+    }
+}
+
+/// Parses a module file. Files named `lib.rs`, `main.rs`, or `mod.rs` keep
+/// their child modules next to them; any other `name.rs` keeps them in `name/`.
+fn load_module(file: &Path) -> Result<Module> {
+    let content = fs::read_to_string(file)?;
+    let items = syn::parse_file(&content)?.items;
+    let parent = file.parent().unwrap_or(Path::new("."));
+    let stem = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("");
+    let child_dir = match stem {
+        "lib" | "main" | "mod" => parent.to_path_buf(),
+        _ => parent.join(stem),
+    };
+    Ok(Module {
+        file: file.to_path_buf(),
+        items,
+        child_dir,
+    })
+}
+
+/// Finds the child module `name`, whether it is declared inline or in its own file.
+fn child_module(module: Module, name: &str) -> Result<Option<Module>> {
+    let declaration = module.items.into_iter().find_map(|item| match item {
+        Item::Mod(item_mod) if item_mod.ident == name => Some(item_mod),
+        _ => None,
+    });
+    let Some(declaration) = declaration else {
+        return Ok(None);
+    };
+
+    if let Some((_, items)) = declaration.content {
+        return Ok(Some(Module {
+            file: module.file,
+            items,
+            child_dir: module.child_dir.join(name),
+        }));
+    }
+
+    let candidates = [
+        module.child_dir.join(format!("{}.rs", name)),
+        module.child_dir.join(name).join("mod.rs"),
+    ];
+    match candidates.iter().find(|candidate| candidate.is_file()) {
+        Some(file) => load_module(file).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn find_free_function(items: &[Item], name: &str) -> Option<LocatedFunction> {
+    items.iter().find_map(|item| match item {
+        Item::Fn(item_fn) if item_fn.sig.ident == name => {
+            Some(LocatedFunction::new(FunctionItem::Free(item_fn.clone())))
+        }
+        _ => None,
+    })
+}
+
+/// Finds method `name` in an `impl` block for `owner`, or a default method of trait `owner`.
+fn find_method(items: &[Item], owner: &str, name: &str) -> Option<LocatedFunction> {
+    items.iter().find_map(|item| match item {
+        Item::Impl(item_impl) if type_name(&item_impl.self_ty).as_deref() == Some(owner) => {
+            item_impl
+                .items
+                .iter()
+                .find_map(|impl_item| match impl_item {
+                    ImplItem::Fn(method) if method.sig.ident == name => {
+                        Some(LocatedFunction::new(FunctionItem::Method(method.clone())))
+                    }
+                    _ => None,
+                })
+        }
+        Item::Trait(item_trait) if item_trait.ident == owner => {
+            item_trait
+                .items
+                .iter()
+                .find_map(|trait_item| match trait_item {
+                    TraitItem::Fn(method)
+                        if method.sig.ident == name && method.default.is_some() =>
+                    {
+                        Some(LocatedFunction::new(FunctionItem::TraitMethod(
+                            method.clone(),
+                        )))
+                    }
+                    _ => None,
+                })
+        }
+        _ => None,
+    })
+}
+
+/// Returns the name of the type an `impl` block is for (e.g., `Foo` for `impl<T> Foo<T>`).
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Collects the capitalized path segments in a function: the candidate type,
+/// trait, and enum names it refers to.
+#[derive(Default)]
+struct TypeNameCollector {
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for TypeNameCollector {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        for segment in &path.segments {
+            let name = segment.ident.to_string();
+            if name != "Self" && name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                self.names.insert(name);
+            }
+        }
+        visit::visit_path(self, path);
+    }
+}
+
+fn used_type_names(function: &FunctionItem) -> BTreeSet<String> {
+    let mut collector = TypeNameCollector::default();
+    match function {
+        FunctionItem::Free(item) => collector.visit_item_fn(item),
+        FunctionItem::Method(item) => collector.visit_impl_item_fn(item),
+        FunctionItem::TraitMethod(item) => collector.visit_trait_item_fn(item),
+    }
+    collector.names
+}
+
+/// Finds the definitions of `names` among the crate's source files.
+///
+/// Names without a definition in the crate (standard library or dependency
+/// types) are skipped, as are files that fail to parse.
+fn find_definitions(source_dir: &Path, names: &BTreeSet<String>) -> Vec<Definition> {
+    let mut definitions = Vec::new();
+    let files = WalkDir::new(source_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "rs"));
+
+    for entry in files {
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let Ok(file) = syn::parse_file(&content) else {
+            continue;
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        collect_definitions(&file.items, names, entry.path(), &lines, &mut definitions);
+    }
+    definitions
+}
+
+fn collect_definitions(
+    items: &[Item],
+    names: &BTreeSet<String>,
+    file: &Path,
+    lines: &[&str],
+    definitions: &mut Vec<Definition>,
+) {
+    for item in items {
+        let ident = match item {
+            Item::Struct(item) => &item.ident,
+            Item::Enum(item) => &item.ident,
+            Item::Union(item) => &item.ident,
+            Item::Trait(item) => &item.ident,
+            Item::Type(item) => &item.ident,
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    collect_definitions(items, names, file, lines, definitions);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if !names.contains(&ident.to_string()) {
+            continue;
+        }
+        let span = item.span();
+        let start = span.start().line.saturating_sub(1);
+        let end = span.end().line.min(lines.len());
+        definitions.push(Definition {
+            name: ident.to_string(),
+            file: file.to_path_buf(),
+            source: lines.get(start..end).unwrap_or_default().join("\n"),
+        });
+    }
+}
+
+/// Returns the signatures of the crate-local items that a file imports.
+///
+/// Every `use` of an item from another module of the same crate (through
+/// `crate::`, `super::`, `self::`, or a child module) is resolved to its
+/// definition. Functions contribute their signature, types their definition
+/// plus the signatures of their visible inherent methods. Imports that do not
+/// resolve inside the crate, such as those of dependencies, are skipped.
+pub fn imported_signatures(file: &Path, content: &str) -> Vec<Definition> {
+    let Ok(syntax) = syn::parse_file(content) else {
+        return Vec::new();
+    };
+    let Some(manifest_dir) = crate::files::find_manifest_dir(file) else {
+        return Vec::new();
+    };
+    let Some(current_module) = file
+        .canonicalize()
+        .ok()
+        .and_then(|file| module_path(&manifest_dir.join("src"), &file))
+    else {
+        return Vec::new();
+    };
+
+    let mut definitions: Vec<Definition> = Vec::new();
+    for import in imported_paths(&syntax.items) {
+        let Some(absolute) = absolute_path(&current_module, import) else {
+            continue;
+        };
+        let Some((name, module_segments)) = absolute.split_last() else {
+            continue;
+        };
+        if module_segments == current_module.as_slice()
+            || definitions
+                .iter()
+                .any(|definition| &definition.name == name)
+        {
+            continue;
+        }
+        if let Some(module) = find_module(&manifest_dir, module_segments) {
+            definitions.extend(signatures(&module, name));
+        }
+        if definitions.len() >= MAX_IMPORTED_DEFINITIONS {
+            definitions.truncate(MAX_IMPORTED_DEFINITIONS);
+            break;
+        }
+    }
+    definitions
+}
+
+/// Returns the module path of a source file (e.g., `["a", "b"]` for
+/// `src/a/b.rs` or `src/a/b/mod.rs`, and `[]` for `src/lib.rs`).
+fn module_path(source_dir: &Path, file: &Path) -> Option<Vec<String>> {
+    let relative = file.strip_prefix(source_dir).ok()?.with_extension("");
+    let mut segments: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if segments.len() == 1 && (segments[0] == "lib" || segments[0] == "main") {
+        segments.clear();
+    } else if segments.last().is_some_and(|last| last == "mod") {
+        segments.pop();
+    }
+    Some(segments)
+}
+
+/// Flattens the top-level `use` declarations of a file into item paths,
+/// following renames and groups. Glob and `self` imports are skipped.
+fn imported_paths(items: &[Item]) -> Vec<Vec<String>> {
+    fn flatten(tree: &UseTree, prefix: &mut Vec<String>, paths: &mut Vec<Vec<String>>) {
+        match tree {
+            UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                flatten(&path.tree, prefix, paths);
+                prefix.pop();
+            }
+            UseTree::Name(name) if name.ident != "self" => {
+                let mut path = prefix.clone();
+                path.push(name.ident.to_string());
+                paths.push(path);
+            }
+            UseTree::Rename(rename) if rename.ident != "self" => {
+                let mut path = prefix.clone();
+                path.push(rename.ident.to_string());
+                paths.push(path);
+            }
+            UseTree::Group(group) => {
+                for tree in &group.items {
+                    flatten(tree, prefix, paths);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut paths = Vec::new();
+    for item in items {
+        if let Item::Use(item_use) = item {
+            flatten(&item_use.tree, &mut Vec::new(), &mut paths);
+        }
+    }
+    paths
+}
+
+/// Resolves an imported path against the module that imports it.
+///
+/// Returns `None` for `super::` paths that climb above the crate root.
+fn absolute_path(current_module: &[String], import: Vec<String>) -> Option<Vec<String>> {
+    let mut segments = import.into_iter().peekable();
+    let mut absolute = match segments.peek().map(String::as_str) {
+        Some("crate") => {
+            segments.next();
+            Vec::new()
+        }
+        Some("self") => {
+            segments.next();
+            current_module.to_vec()
+        }
+        _ => current_module.to_vec(),
+    };
+    while segments.peek().is_some_and(|segment| segment == "super") {
+        segments.next();
+        absolute.pop()?;
+    }
+    absolute.extend(segments);
+    Some(absolute)
+}
+
+/// Loads the module at `segments`, starting from the first crate root that has it.
+fn find_module(manifest_dir: &Path, segments: &[String]) -> Option<Module> {
+    CRATE_ROOTS
+        .iter()
+        .map(|root| manifest_dir.join(root))
+        .filter(|root| root.is_file())
+        .find_map(|root| {
+            let mut module = load_module(&root).ok()?;
+            for segment in segments {
+                module = child_module(module, segment).ok()??;
+            }
+            Some(module)
+        })
+}
+
+/// Renders the API surface of the item `name` defined in `module`.
+fn signatures(module: &Module, name: &str) -> Vec<Definition> {
+    let Ok(content) = fs::read_to_string(&module.file) else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let mut sources = Vec::new();
+
+    for item in &module.items {
+        match item {
+            Item::Fn(item_fn) if item_fn.sig.ident == name => {
+                let open_brace = item_fn.block.brace_token.span.open().start();
+                sources.push(format!(
+                    "{};",
+                    source_between(&lines, item.span().start(), open_brace)
+                ));
+            }
+            Item::Struct(item_struct) if item_struct.ident == name => {
+                sources.push(source_of(&lines, item));
+            }
+            Item::Enum(item_enum) if item_enum.ident == name => {
+                sources.push(source_of(&lines, item));
+            }
+            Item::Union(item_union) if item_union.ident == name => {
+                sources.push(source_of(&lines, item));
+            }
+            Item::Trait(item_trait) if item_trait.ident == name => {
+                sources.push(source_of(&lines, item));
+            }
+            Item::Type(item_type) if item_type.ident == name => {
+                sources.push(source_of(&lines, item));
+            }
+            Item::Const(item_const) if item_const.ident == name => {
+                sources.push(source_of(&lines, item));
+            }
+            Item::Static(item_static) if item_static.ident == name => {
+                sources.push(source_of(&lines, item));
+            }
+            Item::Impl(item_impl)
+                if item_impl.trait_.is_none()
+                    && type_name(&item_impl.self_ty).as_deref() == Some(name) =>
+            {
+                if let Some(source) = inherent_methods(&lines, item_impl) {
+                    sources.push(source);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if sources.is_empty() {
+        return Vec::new();
+    }
+    vec![Definition {
+        name: name.to_string(),
+        file: module.file.clone(),
+        source: sources.join("\n\n"),
+    }]
+}
+
+/// Renders an `impl` block as the signatures of its non-private methods.
+fn inherent_methods(lines: &[&str], item_impl: &syn::ItemImpl) -> Option<String> {
+    let methods: Vec<String> = item_impl
+        .items
+        .iter()
+        .filter_map(|impl_item| match impl_item {
+            ImplItem::Fn(method) if !matches!(method.vis, Visibility::Inherited) => {
+                let open_brace = method.block.brace_token.span.open().start();
+                let signature = source_between(lines, method.span().start(), open_brace);
+                Some(format!("    {};", signature))
+            }
+            _ => None,
+        })
+        .collect();
+    if methods.is_empty() {
+        return None;
+    }
+    let header = source_between(
+        lines,
+        item_impl.span().start(),
+        item_impl.brace_token.span.open().start(),
+    );
+    Some(format!("{} {{\n{}\n}}", header, methods.join("\n")))
+}
+
+/// Returns the full source text of an item, including its attributes.
+fn source_of(lines: &[&str], item: &Item) -> String {
+    let span = item.span();
+    source_between(lines, span.start(), span.end())
+}
+
+/// Returns the source text between two span positions, trimmed at the end.
+fn source_between(lines: &[&str], start: LineColumn, end: LineColumn) -> String {
+    let mut text = String::new();
+    for line_number in start.line..=end.line {
+        let Some(line) = lines.get(line_number - 1) else {
+            break;
+        };
+        let from = if line_number == start.line {
+            start.column
+        } else {
+            0
+        };
+        let to = if line_number == end.line {
+            end.column
+        } else {
+            line.chars().count()
+        };
+        text.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+        if line_number != end.line {
+            text.push('\n');
+        }
+    }
+    text.trim_end().to_string()
+}
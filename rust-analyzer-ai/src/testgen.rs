@@ -0,0 +1,273 @@
+//! src/testgen.rs
+//!
+//! This module implements the `generate-tests` subcommand. For a module or a
+//! single function, the model is asked for a `#[cfg(test)]` module of unit
+//! tests. Each attempt is written to a candidate file, and the tests are only
+//! saved into the source file once `cargo test --no-run` accepts them. When
+//! compilation fails, the failing tests and the errors are fed back to the
+//! model for another attempt. Tests generated by an earlier run are replaced.
+
+use crate::error::{Error, Result};
+use crate::files;
+use crate::fixes;
+use crate::openai;
+use crate::symbols::{self, FunctionTarget};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::Item;
+
+/// The default directory that receives candidate test modules.
+pub const DEFAULT_CANDIDATE_DIR: &str = ".ai-tests";
+
+/// The name of the generated test module, chosen to avoid clashing with an
+/// existing `mod tests`.
+const TEST_MODULE_NAME: &str = "ai_generated_tests";
+
+const TESTGEN_SYSTEM_PROMPT: &str = "You are an expert Rust programmer writing unit tests. You \
+    will be given a Rust source file and the module or function to test. Write focused unit \
+    tests covering normal behavior, edge cases, and error paths. The tests will be appended to \
+    the end of the file, so bring items into scope with `use super::*;` or explicit paths. \
+    Only use the standard library and the crates the file already uses; do not add \
+    dependencies, and do not perform network or file system access outside a temporary \
+    directory. Respond with only one `#[cfg(test)]` module inside a ```rust code block.";
+
+/// Controls how tests are generated and verified.
+#[derive(Debug, Clone)]
+pub struct TestGenOptions {
+    /// Directory that receives the candidate test module of each attempt.
+    pub candidate_dir: PathBuf,
+    /// The maximum number of generation attempts.
+    pub attempts: u32,
+}
+
+/// Tests that did not compile, and the compiler's errors.
+struct FailedAttempt {
+    tests: String,
+    errors: String,
+}
+
+/// What the tests are generated for.
+enum TestTarget {
+    /// A whole module, given by its source file.
+    Module(PathBuf),
+    /// A single function and the definitions it depends on.
+    Function(FunctionTarget),
+}
+
+impl TestTarget {
+    fn file(&self) -> &Path {
+        match self {
+            TestTarget::Module(file) => file,
+            TestTarget::Function(target) => &target.file,
+        }
+    }
+}
+
+/// Generates unit tests for `target`, a module or function path within the
+/// crate that contains `crate_path`, and saves them once they compile.
+///
+/// Returns `Ok(true)` if tests were saved into the source file, and
+/// `Ok(false)` if every attempt failed to compile; the last candidate is
+/// left in the candidate directory for manual review.
+pub async fn generate_tests(
+    client: &openai::Client,
+    crate_path: &Path,
+    target: &str,
+    options: &TestGenOptions,
+) -> Result<bool> {
+    let target = resolve_target(crate_path, target)?;
+    let path = target.file().to_path_buf();
+    let uri = files::relative_uri(&path);
+    let original = fs::read_to_string(&path)?;
+    // The tests of an earlier run are replaced rather than followed by a
+    // second module of the same name, which would not compile.
+    let source = without_generated_tests(&original);
+    let candidate_path = options
+        .candidate_dir
+        .join(format!("{}.tests.rs", uri.replace('/', "_")));
+    fs::create_dir_all(&options.candidate_dir)?;
+
+    println!("{} {}", "Generating tests for:".magenta().bold(), uri);
+    let mut failed_attempt: Option<FailedAttempt> = None;
+
+    for attempt in 1..=options.attempts {
+        let user_prompt = build_user_prompt(&target, &uri, &source, failed_attempt.as_ref());
+        let response = client.complete(TESTGEN_SYSTEM_PROMPT, &user_prompt).await?;
+        let tests = extract_rust_block(&response);
+        if !tests.contains("#[cfg(test)]") {
+            return Err(Error::OpenAI(
+                "the model did not return a #[cfg(test)] module".to_string(),
+            ));
+        }
+        fs::write(&candidate_path, &tests)?;
+
+        let candidate = format!("{}\n\n{}\n", source.trim_end(), tests);
+        match fixes::verify_with_cargo(&path, &original, &candidate, &["test", "--no-run"]).await {
+            Ok(()) => {
+                fs::write(&path, &candidate)?;
+                println!(
+                    "  {} tests added to {} (attempt {}).",
+                    "Saved:".green(),
+                    uri,
+                    attempt
+                );
+                return Ok(true);
+            }
+            Err(e) => {
+                println!(
+                    "  {} attempt {} of {}: {}",
+                    "Rejected:".yellow(),
+                    attempt,
+                    options.attempts,
+                    e
+                );
+                failed_attempt = Some(FailedAttempt {
+                    tests,
+                    errors: e.to_string(),
+                });
+            }
+        }
+    }
+
+    println!(
+        "  {} the last candidate is in {} for manual review.",
+        "Not saved:".yellow(),
+        candidate_path.display()
+    );
+    Ok(false)
+}
+
+/// Resolves `target` as a function first, then as a module.
+fn resolve_target(crate_path: &Path, target: &str) -> Result<TestTarget> {
+    match symbols::resolve_function(crate_path, target) {
+        Ok(function) => Ok(TestTarget::Function(function)),
+        Err(Error::Resolve(_)) => symbols::resolve_module(crate_path, target)
+            .map(TestTarget::Module)
+            .map_err(|_| {
+                Error::Resolve(format!(
+                    "'{}' is neither a function nor a module of the crate",
+                    target
+                ))
+            }),
+        Err(e) => Err(e),
+    }
+}
+
+/// Builds the request for tests, including the previous attempt's tests and
+/// compiler errors.
+fn build_user_prompt(
+    target: &TestTarget,
+    uri: &str,
+    content: &str,
+    failed_attempt: Option<&FailedAttempt>,
+) -> String {
+    let subject = match target {
+        TestTarget::Module(_) => "the public and crate-visible items of this module".to_string(),
+        TestTarget::Function(function) => format!(
+            "the function `{}` (lines {}-{})",
+            function.qualified_name, function.start_line, function.end_line
+        ),
+    };
+    let mut prompt = format!(
+        "File `{}`:\n\n```rust\n{}\n```\n\nWrite unit tests for {}. Name the test module `{}`.",
+        uri, content, subject, TEST_MODULE_NAME
+    );
+
+    if let TestTarget::Function(function) = target {
+        for definition in &function.dependencies {
+            prompt.push_str(&format!(
+                "\n\nThe function uses `{}` from `{}`:\n```rust\n{}\n```",
+                definition.name,
+                files::relative_uri(&definition.file),
+                definition.source
+            ));
+        }
+    }
+    if let Some(failed) = failed_attempt {
+        prompt.push_str(&format!(
+            "\n\nYour previous tests did not compile:\n```rust\n{}\n```\n\nFix these errors:\n{}",
+            failed.tests, failed.errors
+        ));
+    }
+    prompt
+}
+
+/// Removes the test module generated by an earlier run, with its attributes,
+/// from `content`. Content that doesn't parse is returned unchanged.
+fn without_generated_tests(content: &str) -> String {
+    let Ok(file) = syn::parse_file(content) else {
+        return content.to_string();
+    };
+    let Some(module) = file.items.iter().find_map(|item| match item {
+        Item::Mod(module) if module.ident == TEST_MODULE_NAME => Some(module),
+        _ => None,
+    }) else {
+        return content.to_string();
+    };
+    let span = module.span();
+    let (start, end) = (span.start().line, span.end().line);
+    let mut kept: Vec<&str> = content
+        .lines()
+        .enumerate()
+        .filter(|(index, _)| !(start..=end).contains(&(index + 1)))
+        .map(|(_, line)| line)
+        .collect();
+    while kept.last().is_some_and(|line| line.trim().is_empty()) {
+        kept.pop();
+    }
+    kept.join("\n") + "\n"
+}
+
+/// Extracts the Rust code from the model's reply, stripping any Markdown code fence.
+fn extract_rust_block(response: &str) -> String {
+    response
+        .split_once("```rust")
+        .or_else(|| response.split_once("```"))
+        .map(|(_, rest)| rest.split_once("```").map_or(rest, |(inner, _)| inner))
+        .unwrap_or(response)
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_the_tests_of_an_earlier_run() {
+        let content = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\n\
+                       #[cfg(test)]\nmod ai_generated_tests {\n    use super::*;\n\n    \
+                       #[test]\n    fn adds() {\n        assert_eq!(add(1, 2), 3);\n    }\n}\n";
+
+        assert_eq!(
+            without_generated_tests(content),
+            "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_other_test_modules() {
+        let content = "pub fn one() -> i32 {\n    1\n}\n\n#[cfg(test)]\nmod tests {}\n";
+
+        assert_eq!(without_generated_tests(content), content);
+    }
+
+    #[test]
+    fn retries_with_the_failing_tests_and_errors() {
+        let failed = FailedAttempt {
+            tests: "#[cfg(test)]\nmod ai_generated_tests {}".to_string(),
+            errors: "error[E0425]: cannot find function `ad`".to_string(),
+        };
+        let prompt = build_user_prompt(
+            &TestTarget::Module(PathBuf::from("src/lib.rs")),
+            "src/lib.rs",
+            "pub fn add() {}",
+            Some(&failed),
+        );
+
+        assert!(prompt.contains(&failed.tests));
+        assert!(prompt.contains(&failed.errors));
+    }
+}
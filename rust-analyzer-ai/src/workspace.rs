@@ -0,0 +1,171 @@
+//! src/workspace.rs
+//!
+//! This module detects the Cargo workspace layout around the analyzed path
+//! using `cargo metadata`. It maps each file to the crate that owns it and
+//! records the dependency edges between the workspace's crates, so files can
+//! be analyzed crate by crate with that context in their prompts.
+
+use crate::error::{Error, Result};
+use crate::files;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// A member crate of the workspace and its edges to other members.
+#[derive(Debug, Clone)]
+pub struct CrateInfo {
+    pub name: String,
+    /// The canonical directory containing the crate's `Cargo.toml`.
+    pub manifest_dir: PathBuf,
+    /// Workspace crates this crate depends on.
+    pub dependencies: Vec<String>,
+    /// Workspace crates that depend on this crate.
+    pub dependents: Vec<String>,
+}
+
+/// The member crates of a Cargo workspace, in `cargo metadata` order.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub crates: Vec<CrateInfo>,
+}
+
+impl Workspace {
+    /// Runs `cargo metadata` for the package containing `path`.
+    ///
+    /// Returns `Ok(None)` if the path is not inside a Cargo package. For a
+    /// standalone crate, the workspace has a single member.
+    pub async fn detect(path: &Path) -> Result<Option<Self>> {
+        let Some(manifest_dir) = files::find_manifest_dir(path) else {
+            return Ok(None);
+        };
+        let output = Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .current_dir(&manifest_dir)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(Error::Cargo(format!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let metadata: Metadata = serde_json::from_slice(&output.stdout)?;
+        Ok(Some(Self::from_metadata(metadata)))
+    }
+
+    /// Builds the crate list and dependency edges from `cargo metadata` output.
+    fn from_metadata(metadata: Metadata) -> Self {
+        let names: Vec<String> = metadata
+            .packages
+            .iter()
+            .map(|package| package.name.clone())
+            .collect();
+
+        let mut crates: Vec<CrateInfo> = metadata
+            .packages
+            .into_iter()
+            .map(|package| {
+                let manifest_dir = package
+                    .manifest_path
+                    .parent()
+                    .map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()))
+                    .unwrap_or_default();
+                let dependencies = package
+                    .dependencies
+                    .into_iter()
+                    .map(|dependency| dependency.name)
+                    .filter(|name| names.contains(name) && *name != package.name)
+                    .collect();
+                CrateInfo {
+                    name: package.name,
+                    manifest_dir,
+                    dependencies,
+                    dependents: Vec::new(),
+                }
+            })
+            .collect();
+
+        let dependents: Vec<Vec<String>> = crates
+            .iter()
+            .map(|info| {
+                crates
+                    .iter()
+                    .filter(|other| other.dependencies.contains(&info.name))
+                    .map(|other| other.name.clone())
+                    .collect()
+            })
+            .collect();
+        for (info, dependents) in crates.iter_mut().zip(dependents) {
+            info.dependents = dependents;
+        }
+        Self { crates }
+    }
+
+    /// Returns `true` if the workspace has more than one member crate.
+    pub fn is_multi_crate(&self) -> bool {
+        self.crates.len() > 1
+    }
+
+    /// Returns the index of the crate that owns `file`: the member whose
+    /// manifest directory is the closest ancestor of the file.
+    pub fn crate_index(&self, file: &Path) -> Option<usize> {
+        let file = file.canonicalize().ok()?;
+        self.crates
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| file.starts_with(&info.manifest_dir))
+            .max_by_key(|(_, info)| info.manifest_dir.components().count())
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the crate that owns `file`, if any.
+    pub fn crate_for(&self, file: &Path) -> Option<&CrateInfo> {
+        self.crate_index(file).map(|index| &self.crates[index])
+    }
+}
+
+impl CrateInfo {
+    /// Formats the crate's name and dependency edges as a prompt section.
+    pub fn format_for_prompt(&self) -> String {
+        let list = |names: &[String]| {
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        };
+        format!(
+            "This file belongs to the crate `{}` in a Cargo workspace. It depends on these \
+            workspace crates: {}. These workspace crates depend on it: {}. Take the crate's \
+            role into account, for example when judging public API changes.",
+            self.name,
+            list(&self.dependencies),
+            list(&self.dependents)
+        )
+    }
+}
+
+//========= cargo metadata Structures =========//
+
+#[derive(Deserialize, Debug)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Dependency {
+    name: String,
+}
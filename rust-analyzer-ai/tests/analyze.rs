@@ -0,0 +1,75 @@
+//! tests/analyze.rs
+//!
+//! Analyzes a small config loader against the mock provider: the recorded
+//! findings and where they are located, and scripted replies for the focus
+//! filter and for replies that are not findings.
+
+use ai_core::{MockClient, ProviderConfig};
+use rust_analyzer_ai::analyzer::Analyzer;
+use rust_analyzer_ai::error::Error;
+use rust_analyzer_ai::findings::Severity;
+use rust_analyzer_ai::focus::FocusArea;
+use rust_analyzer_ai::openai;
+use std::path::Path;
+use std::sync::Arc;
+
+const CONFIG_LOADER: &str = r#"use std::fs;
+
+pub fn load_config(path: &str) -> String {
+    let content = fs::read_to_string(path).unwrap();
+    content.trim().to_string()
+}
+"#;
+
+fn analyzer(mock: MockClient) -> Analyzer {
+    Analyzer::new(openai::Client::new(&ProviderConfig::mock(Arc::new(mock))).unwrap())
+}
+
+#[tokio::test]
+async fn turns_the_reply_into_located_findings() {
+    let mock = MockClient::fixture(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/analyze_config_loader.json"
+    ))
+    .unwrap();
+    let analysis = analyzer(mock)
+        .analyze_source(Path::new("src/config_loader.rs"), CONFIG_LOADER)
+        .await
+        .unwrap();
+
+    assert_eq!(analysis.findings.len(), 2);
+    let unwrap = &analysis.findings[0];
+    assert_eq!(unwrap.category, "error-handling");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.line, Some(4));
+    assert!(!unwrap.fingerprint.is_empty());
+    // The second finding is reported past the end of the file.
+    assert_eq!(analysis.findings[1].line, None);
+    assert!(!analysis.from_cache);
+}
+
+#[tokio::test]
+async fn keeps_only_findings_in_focus() {
+    let mock = MockClient::new([r#"{"findings": [
+        {"category": "performance", "severity": "low", "title": "Extra allocation", "message": "..."},
+        {"category": "security", "severity": "medium", "title": "Path traversal", "message": "..."}
+    ]}"#]);
+    let analysis = analyzer(mock)
+        .with_focus(vec![FocusArea::Security])
+        .analyze_source(Path::new("src/config_loader.rs"), CONFIG_LOADER)
+        .await
+        .unwrap();
+
+    assert_eq!(analysis.findings.len(), 1);
+    assert_eq!(analysis.findings[0].category, "security");
+}
+
+#[tokio::test]
+async fn rejects_replies_that_are_not_findings() {
+    let mock = MockClient::new([r#"{"findings": "none"}"#]);
+    let result = analyzer(mock)
+        .analyze_source(Path::new("src/config_loader.rs"), CONFIG_LOADER)
+        .await;
+
+    assert!(matches!(result, Err(Error::OpenAI(_))));
+}
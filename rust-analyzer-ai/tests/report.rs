@@ -0,0 +1,32 @@
+//! tests/report.rs
+//!
+//! The links between the table of contents and the file sections of the
+//! Markdown report.
+
+use rust_analyzer_ai::findings::FileAnalysis;
+use rust_analyzer_ai::report;
+use serde_json::json;
+use std::fs;
+
+#[test]
+fn links_the_markdown_contents_to_explicit_anchors() {
+    let analysis: FileAnalysis = serde_json::from_value(json!({
+        "path": "src/main.rs",
+        "findings": [{
+            "severity": "low",
+            "category": "style",
+            "title": "Long function",
+            "message": "The function does too much.",
+            "line": 1
+        }]
+    }))
+    .unwrap();
+    let path = std::env::temp_dir().join(format!("report-test-{}.md", std::process::id()));
+
+    report::write_report(&path, &[analysis]).unwrap();
+    let markdown = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(markdown.contains("- [src/main.rs](#src-main-rs)"));
+    assert!(markdown.contains("<a id=\"src-main-rs\"></a>\n\n## src/main.rs"));
+}
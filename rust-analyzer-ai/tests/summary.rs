@@ -0,0 +1,14 @@
+//! tests/summary.rs
+//!
+//! The gate result reported in the run summary.
+
+use rust_analyzer_ai::findings::Severity;
+use rust_analyzer_ai::summary::RunSummary;
+
+#[test]
+fn fails_the_gate_when_files_could_not_be_analyzed() {
+    let summary = RunSummary::new(&[], 1, Some(Severity::High));
+
+    assert_eq!(summary.gate_findings, 0);
+    assert!(!summary.passed);
+}
@@ -0,0 +1,161 @@
+//! src/c4.rs
+//!
+//! Support for C4 model diagrams. A C4 diagram shows the system at one of the
+//! model's zoom levels (context, container, or component), drawn with
+//! Mermaid's C4 syntax. Facts the code cannot reveal, such as the system's
+//! name, the people who use it, the external systems it talks to, and how
+//! its modules are grouped, come from a TOML config file and are injected
+//! into the prompt.
+
+use crate::error::{Error, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// The config file read when `--c4-config` is not given, if it exists.
+pub const DEFAULT_C4_CONFIG: &str = "c4.toml";
+
+/// The zoom level of a C4 diagram.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum C4Level {
+    /// The system as a single box, with its users and external systems.
+    Context,
+    /// The deployable and runnable parts of the system (binaries, crates, data stores).
+    Container,
+    /// The modules and main types inside the system.
+    Component,
+}
+
+impl C4Level {
+    /// The Mermaid keyword that starts a diagram of this level.
+    pub fn mermaid_keyword(&self) -> &'static str {
+        match self {
+            C4Level::Context => "C4Context",
+            C4Level::Container => "C4Container",
+            C4Level::Component => "C4Component",
+        }
+    }
+
+    /// What the diagram of this level shows, used in prompts.
+    fn instructions(&self) -> &'static str {
+        match self {
+            C4Level::Context => "Show the system as a single `System`, the people who use it as `Person`, and the external systems it depends on as `System_Ext`, connected with `Rel` statements describing each interaction. Do not show the system's internals.",
+            C4Level::Container => "Show the system's containers (binaries, libraries, services, and data stores) inside a `System_Boundary`, as `Container` and `ContainerDb` elements with their technology, plus the people and external systems they interact with, connected with `Rel` statements.",
+            C4Level::Component => "Show the main components (modules and their key types) inside a `Container_Boundary` as `Component` elements with their technology, and the external systems they call, connected with `Rel` statements describing each interaction.",
+        }
+    }
+}
+
+/// Facts about the system that are not visible in its code.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct C4Config {
+    /// The name of the system being diagrammed.
+    #[serde(default)]
+    pub system_name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The people who use the system.
+    #[serde(default)]
+    pub actors: Vec<C4Element>,
+    /// The systems outside of this codebase that it interacts with.
+    #[serde(default)]
+    pub external_systems: Vec<C4Element>,
+    /// Named groups of modules, drawn as boundaries.
+    #[serde(default)]
+    pub boundaries: Vec<C4Boundary>,
+}
+
+/// A person or external system in a C4 diagram.
+#[derive(Deserialize, Debug, Clone)]
+pub struct C4Element {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// A group of modules drawn inside one boundary.
+#[derive(Deserialize, Debug, Clone)]
+pub struct C4Boundary {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The modules or crates inside the boundary.
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+impl C4Config {
+    /// Loads the config from `path`, or from `c4.toml` in the current
+    /// directory if no path is given. A missing default file yields an empty config.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the given file does not exist or cannot be parsed.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) if !path.is_file() => {
+                return Err(Error::Config(format!(
+                    "C4 config '{}' not found.",
+                    path.display()
+                )));
+            }
+            Some(path) => path,
+            None if Path::new(DEFAULT_C4_CONFIG).is_file() => Path::new(DEFAULT_C4_CONFIG),
+            None => return Ok(Self::default()),
+        };
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Renders the config as a section of the diagram prompt, or an empty
+    /// string if it has no content.
+    fn prompt_section(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(system_name) = &self.system_name {
+            lines.push(format!("- The system is named \"{}\".", system_name));
+        }
+        if let Some(description) = &self.description {
+            lines.push(format!("- System description: {}", description.trim()));
+        }
+        for actor in &self.actors {
+            lines.push(format!(
+                "- Person \"{}\": {}",
+                actor.name,
+                actor.description.trim()
+            ));
+        }
+        for system in &self.external_systems {
+            lines.push(format!(
+                "- External system \"{}\": {}",
+                system.name,
+                system.description.trim()
+            ));
+        }
+        for boundary in &self.boundaries {
+            lines.push(format!(
+                "- Boundary \"{}\" ({}) contains: {}",
+                boundary.name,
+                boundary.description.trim(),
+                boundary.members.join(", ")
+            ));
+        }
+        if lines.is_empty() {
+            return String::new();
+        }
+        format!(
+            " Use these facts about the system, which take precedence over what you infer from the code:\n{}",
+            lines.join("\n")
+        )
+    }
+}
+
+/// Builds the diagram instructions for a C4 diagram of the given level.
+pub fn build_c4_instructions(level: C4Level, config: &C4Config) -> String {
+    format!(
+        "Generate a C4 model diagram at the {:?} level using Mermaid's C4 syntax, starting with `{}`. {} Give every element and relationship a short description.{}",
+        level,
+        level.mermaid_keyword(),
+        level.instructions(),
+        config.prompt_section()
+    )
+}
@@ -0,0 +1,66 @@
+//! src/cache.rs
+//!
+//! A content-addressed cache for intermediate AI results, such as per-file
+//! summaries. Entries are keyed by a SHA-256 hash of the model and the full
+//! prompt, which includes the file's content, so editing one module only
+//! invalidates the summaries derived from it.
+
+use crate::error::Result;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// The default directory for cached results.
+pub const DEFAULT_CACHE_DIR: &str = ".architect-cache";
+
+/// Stores one text file per cached response.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    /// Creates a cache rooted at `dir`. The directory is created on the first write.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Computes the cache key for a prompt sent to `model`.
+    pub fn key(model: &str, prompt: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0]);
+        hasher.update(prompt.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Returns the cached response for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.entry_path(key)).ok()
+    }
+
+    /// Stores a response under `key`.
+    pub fn put(&self, key: &str, response: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry_path(key), response)?;
+        Ok(())
+    }
+
+    /// Stores a response, logging instead of failing if the cache is not writable.
+    pub fn put_or_warn(&self, key: &str, response: &str) {
+        if let Err(e) = self.put(key, response) {
+            warn!(
+                "Failed to write to the cache at '{}': {}",
+                self.dir.display(),
+                e
+            );
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.txt", key))
+    }
+}
@@ -1,31 +1,113 @@
 //! src/config.rs
 //!
-//! Manages the application's configuration, primarily loading the
-//! OpenAI API key from the environment.
+//! Manages the application's configuration, primarily loading the model
+//! provider and its settings, such as the OpenAI API key, from the
+//! environment and the diagram style profiles from their JSON file.
 
+use crate::diagram_generator::OutputFormat;
 use crate::error::{Error, Result};
+use ai_core::ProviderConfig;
+use colored::Colorize;
 use dotenvy::dotenv;
-use std::env;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
 
-/// Loads the OpenAI API key from the environment.
+/// The default path of the diagram style profiles file.
+pub const DIAGRAM_PROFILES_PATH: &str = "diagram_profiles.json";
+
+/// Loads the model provider settings from the environment.
 ///
 /// This function first loads the `.env` file from the current directory,
-/// then attempts to read the `OPENAI_API_KEY` environment variable.
+/// then reads `AI_PROVIDER`, `AI_MODEL`, and the provider's settings, such
+/// as `OPENAI_API_KEY`.
 ///
 /// # Returns
 ///
-/// A `Result` containing the API key as a `String` on success.
+/// A `Result` containing the `ProviderConfig` on success. The provider's
+/// settings are checked when the client is created.
 ///
 /// # Errors
 ///
-/// Returns `Error::Config` if the `OPENAI_API_KEY` environment variable is not set.
-pub fn get_api_key() -> Result<String> {
+/// Returns an error if `AI_PROVIDER` names an unknown provider.
+pub fn get_provider_config() -> Result<ProviderConfig> {
     // Load environment variables from a .env file if it exists.
     dotenv().ok();
 
-    env::var("OPENAI_API_KEY").map_err(|_| {
-        Error::Config(
-            "The OPENAI_API_KEY environment variable must be set.".to_string(),
-        )
-    })
+    Ok(ProviderConfig::from_env()?)
+}
+
+/// A named diagram style: extra prompt instructions and Mermaid theming.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DiagramProfile {
+    pub name: String,
+    pub description: String,
+    /// Instructions appended to the diagram prompt.
+    #[serde(default)]
+    pub prompt: String,
+    /// The Mermaid configuration (e.g., `{"theme": "forest"}`) written as an
+    /// `%%{init: ...}%%` directive at the top of Mermaid diagrams.
+    #[serde(default)]
+    pub mermaid_init: Option<serde_json::Value>,
+}
+
+impl DiagramProfile {
+    /// Adds the profile's Mermaid init directive to a Mermaid diagram.
+    ///
+    /// Diagrams in other formats, and diagrams that already start with an
+    /// init directive, are returned unchanged.
+    pub fn apply_theme(&self, diagram: String, output_format: OutputFormat) -> String {
+        match &self.mermaid_init {
+            Some(init)
+                if output_format == OutputFormat::Mermaid
+                    && !diagram.trim_start().starts_with("%%{init") =>
+            {
+                format!("%%{{init: {}}}%%\n{}", init, diagram)
+            }
+            _ => diagram,
+        }
+    }
+}
+
+/// Represents the top-level structure of the diagram profiles file.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DiagramProfileConfig {
+    pub profiles: Vec<DiagramProfile>,
+}
+
+/// Loads the diagram profiles from `path` and returns the one named `name`.
+///
+/// # Errors
+///
+/// Returns an `Error` if the file cannot be read or parsed, or
+/// `Error::Config`, listing the available profiles, if none is named `name`.
+pub fn load_diagram_profile(path: &Path, name: &str) -> Result<DiagramProfile> {
+    let file_content = fs::read_to_string(path).map_err(|e| {
+        Error::Config(format!(
+            "Could not read the diagram profiles from '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let config: DiagramProfileConfig = serde_json::from_str(&file_content)?;
+
+    config
+        .profiles
+        .iter()
+        .find(|profile| profile.name == name)
+        .cloned()
+        .ok_or_else(|| {
+            let available_profiles_info = config
+                .profiles
+                .iter()
+                .map(|profile| format!("  - {}: {}", profile.name.cyan(), profile.description))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Error::Config(format!(
+                "Style '{}' not found in '{}'.\n\nAvailable styles:\n{}",
+                name,
+                path.display(),
+                available_profiles_info
+            ))
+        })
 }
@@ -0,0 +1,637 @@
+//! src/dependency_graph.rs
+//!
+//! Builds the module and type dependency graph of a Rust project statically,
+//! by parsing every source file with `syn`. Modules are linked by their `use`
+//! statements and path references, types by `impl Trait for Type` blocks and
+//! the types of their fields. Because the graph is derived from the code, it
+//! is reproducible and complete; the AI is only asked to label and prune it.
+//!
+//! The graph is also the typed intermediate architecture model: the AI can
+//! produce one from the project context, diagrams are rendered from it, and
+//! `--emit-model` exports it as JSON for other tools.
+
+use crate::error::{Error, Result};
+use crate::project_scanner::{self, ScanFilter, WorkspaceMember};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::visit::{self, Visit};
+use tracing::{info, warn};
+
+/// The kind of a node in the dependency graph.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Crate,
+    /// A crate from outside the workspace, e.g. from crates.io.
+    External,
+    Module,
+    Struct,
+    Enum,
+    Trait,
+}
+
+/// The kind of a relationship between two nodes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// A module defines a type.
+    Contains,
+    /// A module imports or references items of another module.
+    Uses,
+    /// A type implements a trait.
+    Implements,
+    /// A type holds a field of another type.
+    References,
+    /// A crate depends on another crate.
+    DependsOn,
+}
+
+/// A module, type, or crate in the graph.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Node {
+    /// A unique identifier, e.g. `diagram_generator::DiagramGenerator`.
+    pub id: String,
+    /// The display name.
+    pub name: String,
+    pub kind: NodeKind,
+    /// The file that defines the node, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// The first line of the node's doc comment, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// A short description of the node's role, added by the AI annotation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// A directed relationship between two nodes, referenced by their ids.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+    /// A description of the interaction, added by the AI annotation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// A directed graph of the architectural elements of a project.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DependencyGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+/// The subset of `cargo metadata` output used to build crate graphs.
+#[derive(Deserialize, Debug)]
+struct CargoMetadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Package {
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    manifest_path: PathBuf,
+    dependencies: Vec<PackageDependency>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PackageDependency {
+    name: String,
+    req: String,
+    /// `None` for normal dependencies, `"dev"` or `"build"` otherwise.
+    kind: Option<String>,
+}
+
+/// The items and references found in one source file.
+struct ParsedModule {
+    path: Vec<String>,
+    file: PathBuf,
+    doc: Option<String>,
+    collector: ItemCollector,
+}
+
+impl DependencyGraph {
+    /// Builds the module and type graph of the files under `root_path` that pass `filter`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error` if directory traversal or file reading fails. Files
+    /// that `syn` cannot parse are skipped with a warning.
+    pub fn from_sources(root_path: &Path, filter: &ScanFilter) -> Result<Self> {
+        info!("Building the static dependency graph...");
+        let mut modules = Vec::new();
+        for file in project_scanner::find_rust_files(root_path, filter)? {
+            let content = fs::read_to_string(&file)?;
+            let syntax = match syn::parse_file(&content) {
+                Ok(syntax) => syntax,
+                Err(e) => {
+                    warn!("Skipping '{}': {}", file.display(), e);
+                    continue;
+                }
+            };
+            let mut collector = ItemCollector::default();
+            collector.visit_file(&syntax);
+            modules.push(ParsedModule {
+                path: module_path(root_path, &file),
+                doc: first_doc_line(&syntax.attrs),
+                file,
+                collector,
+            });
+        }
+
+        let mut graph = DependencyGraph::default();
+        let known_modules: BTreeSet<Vec<String>> =
+            modules.iter().map(|module| module.path.clone()).collect();
+
+        // Index every type by its name, so references can be resolved.
+        let mut types_by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for module in &modules {
+            let module_node = module_id(&module.path);
+            graph.add_node(Node {
+                id: module_node.clone(),
+                name: module_node.clone(),
+                kind: NodeKind::Module,
+                file: Some(module.file.clone()),
+                doc: module.doc.clone(),
+                label: None,
+            });
+            for item in &module.collector.items {
+                let id = type_id(&module.path, &item.name);
+                let added = graph.add_node(Node {
+                    id: id.clone(),
+                    name: item.name.clone(),
+                    kind: item.kind,
+                    file: Some(module.file.clone()),
+                    doc: item.doc.clone(),
+                    label: None,
+                });
+                if added {
+                    types_by_name
+                        .entry(item.name.clone())
+                        .or_default()
+                        .push(id.clone());
+                    graph.add_edge(&module_node, &id, EdgeKind::Contains);
+                }
+            }
+        }
+
+        for module in &modules {
+            let module_node = module_id(&module.path);
+            for used in &module.collector.paths {
+                if let Some(target) = resolve_module(&module.path, used, &known_modules) {
+                    if target != module.path {
+                        graph.add_edge(&module_node, &module_id(&target), EdgeKind::Uses);
+                    }
+                }
+            }
+
+            let resolve = |name: &str| resolve_type(&module.path, name, &types_by_name);
+            for (self_ty, trait_name) in &module.collector.impls {
+                if let (Some(from), Some(to)) = (resolve(self_ty), resolve(trait_name)) {
+                    graph.add_edge(&from, &to, EdgeKind::Implements);
+                }
+            }
+            for item in &module.collector.items {
+                let from = type_id(&module.path, &item.name);
+                for referenced in &item.references {
+                    if let Some(to) = resolve(referenced) {
+                        if to != from {
+                            graph.add_edge(&from, &to, EdgeKind::References);
+                        }
+                    }
+                }
+            }
+        }
+
+        info!(
+            "Static graph has {} nodes and {} edges.",
+            graph.nodes.len(),
+            graph.edges.len()
+        );
+        Ok(graph)
+    }
+
+    /// Builds a graph whose nodes are the crates of a workspace and whose edges
+    /// are their dependencies on each other.
+    pub fn from_workspace(members: &[WorkspaceMember]) -> Self {
+        let mut graph = DependencyGraph::default();
+        for member in members {
+            graph.add_node(Node {
+                id: member.name.clone(),
+                name: member.name.clone(),
+                kind: NodeKind::Crate,
+                file: Some(member.path.join("Cargo.toml")),
+                doc: None,
+                label: None,
+            });
+        }
+        for member in members {
+            for dependency in &member.dependencies {
+                graph.add_edge(&member.name, dependency, EdgeKind::DependsOn);
+            }
+        }
+        graph
+    }
+
+    /// Builds the graph of the workspace crates and their direct dependencies
+    /// from `cargo metadata`, without scanning any source.
+    ///
+    /// Internal crates are the workspace members; every other dependency is an
+    /// external crate. Dev-dependencies are left out, as they are not part of
+    /// the architecture; build dependencies are labeled as such.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Cargo` if `cargo metadata` cannot be run or fails.
+    pub async fn from_cargo_metadata(project_path: &Path) -> Result<Self> {
+        info!("Reading crate dependencies with `cargo metadata`...");
+        let output = tokio::process::Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .current_dir(project_path)
+            .output()
+            .await
+            .map_err(|e| Error::Cargo(format!("failed to run `cargo metadata`: {}", e)))?;
+        if !output.status.success() {
+            return Err(Error::Cargo(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        let metadata: CargoMetadata = serde_json::from_slice(&output.stdout)?;
+
+        let members: Vec<&Package> = metadata
+            .packages
+            .iter()
+            .filter(|package| metadata.workspace_members.contains(&package.id))
+            .collect();
+        let member_names: BTreeSet<&str> = members
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect();
+
+        let mut graph = DependencyGraph::default();
+        for member in &members {
+            graph.add_node(Node {
+                id: member.name.clone(),
+                name: member.name.clone(),
+                kind: NodeKind::Crate,
+                file: Some(member.manifest_path.clone()),
+                doc: member.description.clone(),
+                label: None,
+            });
+        }
+        for member in &members {
+            for dependency in &member.dependencies {
+                if dependency.kind.as_deref() == Some("dev") {
+                    continue;
+                }
+                if !member_names.contains(dependency.name.as_str()) {
+                    graph.add_node(Node {
+                        id: dependency.name.clone(),
+                        name: dependency.name.clone(),
+                        kind: NodeKind::External,
+                        file: None,
+                        doc: Some(format!("version {}", dependency.req)),
+                        label: None,
+                    });
+                }
+                graph.add_edge(&member.name, &dependency.name, EdgeKind::DependsOn);
+                if dependency.kind.as_deref() == Some("build") {
+                    if let Some(edge) = graph.edges.last_mut() {
+                        edge.label = Some("build".to_string());
+                    }
+                }
+            }
+        }
+
+        info!(
+            "Crate graph has {} crates and {} dependency edges.",
+            graph.nodes.len(),
+            graph.edges.len()
+        );
+        Ok(graph)
+    }
+
+    /// Removes the given nodes and every edge that touches them.
+    pub fn remove_nodes(&mut self, ids: &BTreeSet<String>) {
+        self.nodes.retain(|node| !ids.contains(&node.id));
+        self.edges
+            .retain(|edge| !ids.contains(&edge.from) && !ids.contains(&edge.to));
+    }
+
+    /// Drops duplicate nodes and edges, and edges whose endpoints are not
+    /// nodes of the graph, e.g. after the graph was produced by the AI.
+    pub fn normalize(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        for node in nodes {
+            self.add_node(node);
+        }
+        let ids: BTreeSet<&str> = self.nodes.iter().map(|node| node.id.as_str()).collect();
+        let mut edges: Vec<Edge> = Vec::new();
+        for edge in std::mem::take(&mut self.edges) {
+            if ids.contains(edge.from.as_str())
+                && ids.contains(edge.to.as_str())
+                && !edges.contains(&edge)
+            {
+                edges.push(edge);
+            }
+        }
+        self.edges = edges;
+    }
+
+    /// Adds a node unless a node with the same id already exists.
+    ///
+    /// Returns whether the node was added.
+    fn add_node(&mut self, node: Node) -> bool {
+        if self.nodes.iter().any(|existing| existing.id == node.id) {
+            return false;
+        }
+        self.nodes.push(node);
+        true
+    }
+
+    /// Adds an edge unless the same edge already exists.
+    fn add_edge(&mut self, from: &str, to: &str, kind: EdgeKind) {
+        let edge = Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            kind,
+            label: None,
+        };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+}
+
+/// A struct, enum, or trait defined in a module.
+struct TypeItem {
+    name: String,
+    kind: NodeKind,
+    doc: Option<String>,
+    /// The names of the types used by the item's fields.
+    references: BTreeSet<String>,
+}
+
+/// Collects the items, `impl` blocks, and paths of a source file.
+#[derive(Default)]
+struct ItemCollector {
+    items: Vec<TypeItem>,
+    /// `(self type, trait)` name pairs of trait implementations.
+    impls: Vec<(String, String)>,
+    /// The segments of every `use` path and path expression.
+    paths: BTreeSet<Vec<String>>,
+}
+
+impl<'ast> Visit<'ast> for ItemCollector {
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        // Test modules are not part of the architecture.
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        let mut references = TypeNameCollector::default();
+        references.visit_fields(&item.fields);
+        self.items.push(TypeItem {
+            name: item.ident.to_string(),
+            kind: NodeKind::Struct,
+            doc: first_doc_line(&item.attrs),
+            references: references.names,
+        });
+        visit::visit_item_struct(self, item);
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast syn::ItemEnum) {
+        let mut references = TypeNameCollector::default();
+        for variant in &item.variants {
+            references.visit_fields(&variant.fields);
+        }
+        self.items.push(TypeItem {
+            name: item.ident.to_string(),
+            kind: NodeKind::Enum,
+            doc: first_doc_line(&item.attrs),
+            references: references.names,
+        });
+        visit::visit_item_enum(self, item);
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        self.items.push(TypeItem {
+            name: item.ident.to_string(),
+            kind: NodeKind::Trait,
+            doc: first_doc_line(&item.attrs),
+            references: BTreeSet::new(),
+        });
+        visit::visit_item_trait(self, item);
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        if let (Some((_, trait_path, _)), syn::Type::Path(self_ty)) =
+            (&item.trait_, item.self_ty.as_ref())
+        {
+            if let (Some(trait_name), Some(self_name)) =
+                (trait_path.segments.last(), self_ty.path.segments.last())
+            {
+                self.impls
+                    .push((self_name.ident.to_string(), trait_name.ident.to_string()));
+            }
+        }
+        visit::visit_item_impl(self, item);
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        collect_use_paths(&item.tree, &mut Vec::new(), &mut self.paths);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.segments.len() > 1 {
+            self.paths.insert(
+                path.segments
+                    .iter()
+                    .map(|segment| segment.ident.to_string())
+                    .collect(),
+            );
+        }
+        visit::visit_path(self, path);
+    }
+}
+
+/// Collects the type names mentioned in field types, including generic arguments.
+#[derive(Default)]
+struct TypeNameCollector {
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for TypeNameCollector {
+    fn visit_type_path(&mut self, type_path: &'ast syn::TypePath) {
+        if let Some(segment) = type_path.path.segments.last() {
+            self.names.insert(segment.ident.to_string());
+        }
+        visit::visit_type_path(self, type_path);
+    }
+}
+
+/// Expands a `use` tree into the full path of each imported item.
+fn collect_use_paths(
+    tree: &syn::UseTree,
+    prefix: &mut Vec<String>,
+    paths: &mut BTreeSet<Vec<String>>,
+) {
+    match tree {
+        syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            collect_use_paths(&path.tree, prefix, paths);
+            prefix.pop();
+        }
+        syn::UseTree::Name(name) => {
+            let mut path = prefix.clone();
+            path.push(name.ident.to_string());
+            paths.insert(path);
+        }
+        syn::UseTree::Rename(rename) => {
+            let mut path = prefix.clone();
+            path.push(rename.ident.to_string());
+            paths.insert(path);
+        }
+        syn::UseTree::Glob(_) => {
+            paths.insert(prefix.clone());
+        }
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_use_paths(tree, prefix, paths);
+            }
+        }
+    }
+}
+
+/// Resolves a path to the longest known module it refers to.
+///
+/// Paths may start with `crate`, `super`, or `self`; other paths are tried
+/// relative to the current module first, then to the crate root.
+fn resolve_module(
+    current: &[String],
+    segments: &[String],
+    known_modules: &BTreeSet<Vec<String>>,
+) -> Option<Vec<String>> {
+    let mut bases = Vec::new();
+    let mut rest = segments;
+    match rest.first().map(String::as_str) {
+        Some("crate") => {
+            bases.push(Vec::new());
+            rest = &rest[1..];
+        }
+        Some("self") => {
+            bases.push(current.to_vec());
+            rest = &rest[1..];
+        }
+        Some("super") => {
+            let mut base = current.to_vec();
+            while rest.first().map(String::as_str) == Some("super") {
+                base.pop();
+                rest = &rest[1..];
+            }
+            bases.push(base);
+        }
+        _ => {
+            bases.push(current.to_vec());
+            bases.push(Vec::new());
+        }
+    }
+
+    bases.into_iter().find_map(|base| {
+        (1..=rest.len()).rev().find_map(|len| {
+            let mut candidate = base.clone();
+            candidate.extend_from_slice(&rest[..len]);
+            known_modules.contains(&candidate).then_some(candidate)
+        })
+    })
+}
+
+/// Resolves a type name, preferring a type defined in the current module.
+///
+/// Names defined in several other modules are ambiguous and left unresolved.
+fn resolve_type(
+    current: &[String],
+    name: &str,
+    types_by_name: &BTreeMap<String, Vec<String>>,
+) -> Option<String> {
+    let candidates = types_by_name.get(name)?;
+    let local = type_id(current, name);
+    if candidates.contains(&local) {
+        return Some(local);
+    }
+    match candidates.as_slice() {
+        [only] => Some(only.clone()),
+        _ => None,
+    }
+}
+
+/// Derives a module path from a file path, e.g. `src/a/mod.rs` -> `a`.
+fn module_path(root_path: &Path, file: &Path) -> Vec<String> {
+    let relative = file.strip_prefix(root_path).unwrap_or(file);
+    let mut segments: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if segments.first().map(String::as_str) == Some("src") {
+        segments.remove(0);
+    }
+    let is_crate_root =
+        segments.len() == 1 && matches!(segments.first().map(String::as_str), Some("main" | "lib"));
+    if is_crate_root || segments.last().map(String::as_str) == Some("mod") {
+        segments.pop();
+    }
+    segments
+}
+
+fn module_id(path: &[String]) -> String {
+    if path.is_empty() {
+        "crate".to_string()
+    } else {
+        path.join("::")
+    }
+}
+
+fn type_id(module: &[String], name: &str) -> String {
+    format!("{}::{}", module_id(module), name)
+}
+
+/// Returns the first non-empty line of the doc comments in `attrs`.
+fn first_doc_line(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(text),
+                        ..
+                    }),
+                ..
+            }) => Some(text.value().trim().to_string()),
+            _ => None,
+        })
+        // Skip the `src/x.rs` header line that opens many module docs.
+        .find(|line| !line.is_empty() && !line.ends_with(".rs"))
+}
+
+/// Whether the attributes contain `#[cfg(test)]`.
+pub(crate) fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|ident| ident == "test")
+    })
+}
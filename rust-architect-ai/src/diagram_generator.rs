@@ -5,34 +5,219 @@
 //! architectural diagram, and parses the AI's response to extract the
 //! final diagram syntax.
 
+use crate::c4::{self, C4Config, C4Level};
+use crate::config::DiagramProfile;
+use crate::dependency_graph::{DependencyGraph, Edge, EdgeKind, Node, NodeKind};
 use crate::error::{Error, Result};
+use crate::mermaid;
+use crate::mermaid_style::MermaidStyle;
 use crate::openai_client::OpenAIClient;
 use clap::ValueEnum;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use tracing::info;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use tracing::{info, warn};
 
-/// A lazily-compiled regular expression to robustly extract diagram syntax
+/// A lazily-compiled regular expression to robustly extract Mermaid syntax
 /// from within a Markdown code block (e.g., ```mermaid ... ```).
-static DIAGRAM_EXTRACTOR: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?s)```(?:mermaid|plantuml)?\s*(.*)\s*```").unwrap());
+static MERMAID_EXTRACTOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)```(?:mermaid)?\s*(.*)\s*```").unwrap());
+
+/// Extracts a PlantUML diagram, including its `@startuml`/`@enduml` markers,
+/// whether or not the model wrapped it in a code block.
+static PLANTUML_EXTRACTOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)(@startuml.*?@enduml)").unwrap());
+
+/// Extracts a Graphviz DOT graph, from its `graph`/`digraph` keyword up to
+/// the last closing brace.
+static DOT_EXTRACTOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)((?:strict\s+)?(?:di)?graph\b.*\})").unwrap());
 
 /// Defines the types of diagrams the application can generate.
-#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DiagramType {
     Component,
     Sequence,
+    /// The key structs, enums, and traits with their fields, methods, and relationships.
+    Class,
+    /// The workspace crates and their external and internal dependencies.
+    Crates,
+}
+
+impl DiagramType {
+    /// The name used on the command line and for the diagram's file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiagramType::Component => "component",
+            DiagramType::Sequence => "sequence",
+            DiagramType::Class => "class",
+            DiagramType::Crates => "crates",
+        }
+    }
+
+    /// A human-readable title, used as a heading in the index page.
+    pub fn title(&self) -> &'static str {
+        match self {
+            DiagramType::Component => "Component Diagram",
+            DiagramType::Sequence => "Sequence Diagram",
+            DiagramType::Class => "Class Diagram",
+            DiagramType::Crates => "Crate Dependency Diagram",
+        }
+    }
+}
+
+/// Extracts a JSON object from a response, whether or not it is in a code block.
+static JSON_EXTRACTOR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)(\{.*\})").unwrap());
+
+/// The default number of repair rounds for an invalid Mermaid diagram.
+pub const DEFAULT_REPAIR_ATTEMPTS: u32 = 2;
+
+/// The number of nodes the annotated graph should be pruned down to.
+const MAX_ANNOTATED_NODES: usize = 40;
+
+/// The AI's labels and pruning decisions for a static dependency graph.
+#[derive(Deserialize, Debug, Default)]
+struct GraphAnnotation {
+    /// A short role description per node id.
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    /// The ids of nodes that are not architecturally significant.
+    #[serde(default)]
+    remove: Vec<String>,
+    /// Descriptions of the most important edges.
+    #[serde(default)]
+    edges: Vec<EdgeAnnotation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EdgeAnnotation {
+    from: String,
+    to: String,
+    label: String,
+}
+
+/// Defines how a Cargo workspace is diagrammed.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkspaceMode {
+    /// One diagram per member crate, each generated from that crate's sources.
+    PerCrate,
+    /// A single top-level diagram whose nodes are the member crates.
+    Overview,
+}
+
+/// Defines the diagram languages the application can produce.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Mermaid,
+    Plantuml,
+    Dot,
+}
+
+impl OutputFormat {
+    /// The human-readable name of the diagram language, used in prompts.
+    pub fn language_name(&self) -> &'static str {
+        match self {
+            OutputFormat::Mermaid => "Mermaid",
+            OutputFormat::Plantuml => "PlantUML",
+            OutputFormat::Dot => "Graphviz DOT",
+        }
+    }
+
+    /// The Markdown code block tag the model is asked to use.
+    pub fn code_block_tag(&self) -> &'static str {
+        match self {
+            OutputFormat::Mermaid => "mermaid",
+            OutputFormat::Plantuml => "plantuml",
+            OutputFormat::Dot => "dot",
+        }
+    }
+
+    /// The regular expression that extracts this format's syntax from a response.
+    fn extractor(&self) -> &'static Regex {
+        match self {
+            OutputFormat::Mermaid => &MERMAID_EXTRACTOR,
+            OutputFormat::Plantuml => &PLANTUML_EXTRACTOR,
+            OutputFormat::Dot => &DOT_EXTRACTOR,
+        }
+    }
+
+    /// The conventional file extension for diagrams in this format.
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Mermaid => "md",
+            OutputFormat::Plantuml => "puml",
+            OutputFormat::Dot => "dot",
+        }
+    }
 }
 
 /// The primary struct responsible for generating diagrams.
 pub struct DiagramGenerator {
     client: OpenAIClient,
+    repair_attempts: u32,
+    /// When set, component diagrams are drawn as C4 diagrams of this level.
+    c4: Option<(C4Level, C4Config)>,
+    /// The style profile whose instructions and theme are applied to every diagram.
+    profile: Option<DiagramProfile>,
+    /// The theme, layout, and node classes enforced on Mermaid diagrams.
+    mermaid_style: Option<MermaidStyle>,
 }
 
 impl DiagramGenerator {
     /// Creates a new instance of the `DiagramGenerator`.
     pub fn new(client: OpenAIClient) -> Self {
-        Self { client }
+        Self {
+            client,
+            repair_attempts: DEFAULT_REPAIR_ATTEMPTS,
+            c4: None,
+            profile: None,
+            mermaid_style: None,
+        }
+    }
+
+    /// Sets how many times an invalid Mermaid diagram is sent back to the
+    /// model for repair. Zero disables repairs.
+    pub fn with_repair_attempts(mut self, repair_attempts: u32) -> Self {
+        self.repair_attempts = repair_attempts;
+        self
+    }
+
+    /// Draws component diagrams as Mermaid C4 diagrams of the given level,
+    /// using the system facts in `config`.
+    pub fn with_c4(mut self, level: C4Level, config: C4Config) -> Self {
+        self.c4 = Some((level, config));
+        self
+    }
+
+    /// Applies a style profile: its instructions are added to every diagram
+    /// prompt, and its Mermaid theme to every diagram, see `apply_theme`.
+    pub fn with_profile(mut self, profile: DiagramProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Sets the Mermaid theme, direction, and node classes: they are added
+    /// to the prompts of Mermaid diagrams and enforced on the output.
+    pub fn with_mermaid_style(mut self, style: MermaidStyle) -> Self {
+        self.mermaid_style = Some(style);
+        self
+    }
+
+    /// Adds the style profile's Mermaid theme, if any, to a diagram, then
+    /// enforces the Mermaid style on it.
+    ///
+    /// Diagrams built without the AI, such as static graphs, are styled with
+    /// this too, so every diagram of a run looks the same.
+    pub fn apply_theme(&self, diagram: String, output_format: OutputFormat) -> String {
+        let diagram = match &self.profile {
+            Some(profile) => profile.apply_theme(diagram, output_format),
+            None => diagram,
+        };
+        match &self.mermaid_style {
+            Some(style) if output_format == OutputFormat::Mermaid => style.apply(diagram),
+            _ => diagram,
+        }
     }
 
     /// Generates an architectural diagram from the provided source code context.
@@ -41,8 +226,13 @@ impl DiagramGenerator {
         project_context: &str,
         diagram_type: DiagramType,
         entry_function: Option<String>,
+        output_format: OutputFormat,
     ) -> Result<String> {
-        info!("Generating '{:?}' diagram...", diagram_type);
+        info!(
+            "Generating '{:?}' diagram as {}...",
+            diagram_type,
+            output_format.language_name()
+        );
 
         if project_context.is_empty() {
             return Err(Error::Config(
@@ -50,22 +240,167 @@ impl DiagramGenerator {
             ));
         }
 
-        let prompt = self.build_prompt(project_context, diagram_type, entry_function);
+        let prompt =
+            self.build_prompt(project_context, diagram_type, entry_function, output_format);
 
         let response_text = self.client.send_request(prompt).await?;
         info!("Received diagram response from AI.");
 
-        let diagram_syntax = DIAGRAM_EXTRACTOR
+        let diagram_syntax = extract_diagram(&response_text, output_format)?;
+        info!("Successfully extracted diagram syntax.");
+
+        if output_format == OutputFormat::Mermaid {
+            return self.validate_and_repair(diagram_syntax).await;
+        }
+        Ok(diagram_syntax)
+    }
+
+    /// Validates a Mermaid diagram and, while it is invalid, sends the syntax
+    /// error back to the model for a repair round.
+    ///
+    /// If the diagram is still invalid after the last attempt, it is returned
+    /// with a warning, so the output can be fixed by hand.
+    async fn validate_and_repair(&self, mut diagram: String) -> Result<String> {
+        let mut attempt = 0;
+        loop {
+            let error = match mermaid::validate(&diagram) {
+                Ok(()) => {
+                    info!("The Mermaid syntax is valid.");
+                    return Ok(diagram);
+                }
+                Err(error) => error,
+            };
+            if attempt == self.repair_attempts {
+                warn!(
+                    "The diagram is still invalid after {} repair attempts ({}).",
+                    attempt, error
+                );
+                return Ok(diagram);
+            }
+            attempt += 1;
+            warn!(
+                "Invalid Mermaid syntax ({}); repair attempt {} of {}...",
+                error, attempt, self.repair_attempts
+            );
+
+            let prompt = format!(
+                "The following Mermaid diagram has a syntax error at {error}.\n\n```mermaid\n{diagram}\n```\n\nFix the error without changing the diagram's content. Your final output must ONLY be the corrected Mermaid syntax, enclosed in a ```mermaid code block."
+            );
+            let response_text = self.client.send_request(prompt).await?;
+            diagram = extract_diagram(&response_text, OutputFormat::Mermaid)?;
+        }
+    }
+
+    /// Labels and prunes a statically built dependency graph.
+    ///
+    /// The AI may only describe and remove existing nodes and edges; it can
+    /// never add relationships, so the diagram stays faithful to the code.
+    pub async fn annotate_graph(&self, graph: &mut DependencyGraph) -> Result<()> {
+        info!("Annotating the static graph...");
+        let nodes = graph
+            .nodes
+            .iter()
+            .map(|node| match &node.doc {
+                Some(doc) => format!("- `{}` ({}): {}", node.id, kind_name(node.kind), doc),
+                None => format!("- `{}` ({})", node.id, kind_name(node.kind)),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let edges = graph
+            .edges
+            .iter()
+            .map(|edge| format!("- `{}` -[{:?}]-> `{}`", edge.from, edge.kind, edge.to))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "You are an expert software architect with deep knowledge of Rust. Below is the dependency graph of a Rust project, extracted statically from its source code. Your task is to annotate it for an architecture diagram.\n\nInstructions:\n1. Give every node you keep a short label (at most 8 words) describing its architectural role.\n2. List the ids of nodes that are not architecturally significant (small helper types, error plumbing, test utilities) so they can be removed. Keep at most {max} nodes.\n3. Label the most important edges with the interaction they represent (e.g., \"sends requests to\").\n4. Only use the node ids given below. Never invent nodes or edges.\n5. Your final output must ONLY be a JSON object of the form {{\"labels\": {{\"node id\": \"label\"}}, \"remove\": [\"node id\"], \"edges\": [{{\"from\": \"node id\", \"to\": \"node id\", \"label\": \"label\"}}]}}.\n\nNODES\n{nodes}\n\nEDGES\n{edges}",
+            max = MAX_ANNOTATED_NODES
+        );
+
+        let response_text = self.client.send_request(prompt).await?;
+        let json = JSON_EXTRACTOR
             .captures(&response_text)
-            .and_then(|caps| caps.get(1).map(|m| m.as_str().trim().to_string()))
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str())
             .ok_or_else(|| {
-                Error::OpenAI(
-                    "AI response did not contain a valid diagram code block.".to_string(),
-                )
+                Error::OpenAI("AI response did not contain a JSON annotation.".to_string())
             })?;
+        let annotation: GraphAnnotation = serde_json::from_str(json)?;
 
-        info!("Successfully extracted diagram syntax.");
-        Ok(diagram_syntax)
+        let removed: BTreeSet<String> = annotation.remove.into_iter().collect();
+        // Never let the pruning empty the diagram.
+        if removed.len() < graph.nodes.len() {
+            graph.remove_nodes(&removed);
+        }
+        for node in &mut graph.nodes {
+            if let Some(label) = annotation.labels.get(&node.id) {
+                node.label = Some(label.trim().to_string());
+            }
+        }
+        for labeled in annotation.edges {
+            if let Some(edge) = graph
+                .edges
+                .iter_mut()
+                .find(|edge| edge.from == labeled.from && edge.to == labeled.to)
+            {
+                edge.label = Some(labeled.label.trim().to_string());
+            }
+        }
+        info!(
+            "Annotated graph has {} nodes and {} edges.",
+            graph.nodes.len(),
+            graph.edges.len()
+        );
+        Ok(())
+    }
+
+    /// Asks the AI for the architecture model of the project: its components
+    /// and their relationships as a typed graph, from which the component
+    /// diagram is then rendered.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::OpenAI` if the response contains no valid model.
+    pub async fn generate_model(&self, project_context: &str) -> Result<DependencyGraph> {
+        info!("Generating the architecture model...");
+        let mut instructions = format!(
+            "Identify the main architectural components (crates, modules, structs, enums, and traits) and their primary relationships. Focus on the most significant architectural interactions; keep at most {max} nodes.",
+            max = MAX_ANNOTATED_NODES
+        );
+        if let Some(profile) = self
+            .profile
+            .as_ref()
+            .filter(|profile| !profile.prompt.is_empty())
+        {
+            instructions.push(' ');
+            instructions.push_str(profile.prompt.trim());
+        }
+        let prompt = format!(
+            "You are an expert software architect with deep knowledge of Rust. Your task is to analyze the entire provided codebase and describe its architecture as a graph.\n\nInstructions:\n1. {instructions}\n2. Give every node a unique `id` (its module path, e.g. `diagram_generator::DiagramGenerator`), a display `name`, a `kind` (one of \"crate\", \"external\", \"module\", \"struct\", \"enum\", \"trait\"), the `file` that defines it as given in the FILE headers, and a short `label` describing its role.\n3. Give every edge the `from` and `to` node ids, a `kind` (one of \"contains\", \"uses\", \"implements\", \"references\", \"depends_on\"), and a short `label` describing the interaction.\n4. Your final output must ONLY be a JSON object of the form {{\"nodes\": [{{\"id\": \"...\", \"name\": \"...\", \"kind\": \"...\", \"file\": \"...\", \"label\": \"...\"}}], \"edges\": [{{\"from\": \"...\", \"to\": \"...\", \"kind\": \"...\", \"label\": \"...\"}}]}}.\n\nSTART OF CODEBASE CONTEXT\n---\n{project_context}\n---\nEND OF CODEBASE CONTEXT"
+        );
+
+        let response_text = self.client.send_request(prompt).await?;
+        let json = JSON_EXTRACTOR
+            .captures(&response_text)
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str())
+            .ok_or_else(|| {
+                Error::OpenAI("AI response did not contain a JSON architecture model.".to_string())
+            })?;
+        let mut model: DependencyGraph = serde_json::from_str(json)
+            .map_err(|e| Error::OpenAI(format!("Failed to parse the architecture model: {}", e)))?;
+        model.normalize();
+        if model.nodes.is_empty() {
+            return Err(Error::OpenAI(
+                "The architecture model has no nodes.".to_string(),
+            ));
+        }
+        info!(
+            "Architecture model has {} nodes and {} edges.",
+            model.nodes.len(),
+            model.edges.len()
+        );
+        Ok(model)
     }
 
     /// Constructs a specialized prompt based on the desired diagram type.
@@ -74,24 +409,271 @@ impl DiagramGenerator {
         project_context: &str,
         diagram_type: DiagramType,
         entry_function: Option<String>,
+        output_format: OutputFormat,
     ) -> String {
         let base_prompt = "You are an expert software architect with deep knowledge of Rust. Your task is to analyze the entire provided codebase and generate a diagram.";
-        let output_format = "Your final output must ONLY be the Mermaid syntax, enclosed in a ```mermaid code block. Do not include any other text, explanations, or introductory sentences.";
+        let format_instructions = format!(
+            "Your final output must ONLY be the {} syntax, enclosed in a ```{} code block. Do not include any other text, explanations, or introductory sentences.",
+            output_format.language_name(),
+            output_format.code_block_tag()
+        );
 
         let specific_instructions: String = match diagram_type {
+            DiagramType::Component if self.c4.is_some() => {
+                let (level, config) = self.c4.as_ref().unwrap();
+                c4::build_c4_instructions(*level, config)
+            }
             DiagramType::Component => {
                 // Corrected: Convert the string literal to an owned String
                 // so that both match arms have the same type.
-                "Generate a high-level component diagram showing the main modules, structs, and their primary relationships. Focus on the most significant architectural interactions, not every single function call. The output must be a Mermaid `graph TD`.".to_string()
+                let syntax = match output_format {
+                    OutputFormat::Mermaid => "a Mermaid `graph TD`",
+                    OutputFormat::Plantuml => {
+                        "a PlantUML component diagram between `@startuml` and `@enduml`"
+                    }
+                    OutputFormat::Dot => {
+                        "a Graphviz DOT `digraph` with one node per component and labeled edges"
+                    }
+                };
+                format!("Generate a high-level component diagram showing the main modules, structs, and their primary relationships. Focus on the most significant architectural interactions, not every single function call. The output must be {syntax}.")
             }
             DiagramType::Sequence => {
                 let func_name = entry_function.as_deref().unwrap_or("[unspecified function]");
-                format!("Generate a sequence diagram illustrating the flow of calls starting from the public function `{func_name}`. Trace the interactions between different modules and structs. The output must be a Mermaid `sequenceDiagram`.", func_name = func_name)
+                // DOT has no sequence diagrams, so the call order is encoded in the edge labels.
+                let syntax = match output_format {
+                    OutputFormat::Mermaid => "a Mermaid `sequenceDiagram`",
+                    OutputFormat::Plantuml => "a PlantUML sequence diagram between `@startuml` and `@enduml`",
+                    OutputFormat::Dot => "a Graphviz DOT `digraph` whose edges are labeled with the call order (1, 2, 3, ...) and the function called",
+                };
+                format!("Generate a sequence diagram illustrating the flow of calls starting from the public function `{func_name}`. Trace the interactions between different modules and structs. The output must be {syntax}.", func_name = func_name)
+            }
+            DiagramType::Class => {
+                let syntax = match output_format {
+                    OutputFormat::Mermaid => "a Mermaid `classDiagram`",
+                    OutputFormat::Plantuml => {
+                        "a PlantUML class diagram between `@startuml` and `@enduml`"
+                    }
+                    OutputFormat::Dot => {
+                        "a Graphviz DOT `digraph` with `record`-shaped nodes listing each type's fields and methods"
+                    }
+                };
+                format!("Generate a class diagram of the most important structs, enums, and traits, showing their key fields and public methods, trait implementations, and composition relationships. Omit private helpers and trivial types. The output must be {syntax}.")
+            }
+            DiagramType::Crates => {
+                let syntax = match output_format {
+                    OutputFormat::Mermaid => "a Mermaid `graph TD`",
+                    OutputFormat::Plantuml => {
+                        "a PlantUML component diagram between `@startuml` and `@enduml`"
+                    }
+                    OutputFormat::Dot => "a Graphviz DOT `digraph`",
+                };
+                format!("Generate a diagram of the crates in the codebase and the external crates they depend on, labeling each dependency with its role. The output must be {syntax}.")
+            }
+        };
+
+        let specific_instructions = match &self.profile {
+            Some(profile) if !profile.prompt.is_empty() => {
+                format!("{} {}", specific_instructions, profile.prompt.trim())
             }
+            _ => specific_instructions,
+        };
+        let specific_instructions = match &self.mermaid_style {
+            Some(style) if output_format == OutputFormat::Mermaid => {
+                let section = style.prompt_section();
+                if section.is_empty() {
+                    specific_instructions
+                } else {
+                    format!("{} {}", specific_instructions, section)
+                }
+            }
+            _ => specific_instructions,
         };
 
         format!(
-            "{base_prompt}\n\nInstructions:\n1. Analyze the entire codebase provided below.\n2. {specific_instructions}\n3. {output_format}\n\nSTART OF CODEBASE CONTEXT\n---\n{project_context}\n---\nEND OF CODEBASE CONTEXT"
+            "{base_prompt}\n\nInstructions:\n1. Analyze the entire codebase provided below.\n2. {specific_instructions}\n3. {format_instructions}\n\nSTART OF CODEBASE CONTEXT\n---\n{project_context}\n---\nEND OF CODEBASE CONTEXT"
         )
     }
 }
+
+/// Extracts the diagram syntax of the given format from a response.
+fn extract_diagram(response_text: &str, output_format: OutputFormat) -> Result<String> {
+    output_format
+        .extractor()
+        .captures(response_text)
+        .and_then(|caps| caps.get(1).map(|m| m.as_str().trim().to_string()))
+        .ok_or_else(|| {
+            Error::OpenAI(format!(
+                "AI response did not contain a valid {} diagram.",
+                output_format.language_name()
+            ))
+        })
+}
+
+/// Renders a dependency graph in the requested diagram language.
+///
+/// The diagram is derived directly from the graph, so it needs no AI request.
+/// Node labels and edge labels from the AI annotation are included when present.
+pub fn graph_diagram(graph: &DependencyGraph, output_format: OutputFormat) -> String {
+    // Node ids like `a::B` are not valid identifiers in every language, so
+    // nodes are referred to by their position.
+    let index: HashMap<&str, String> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id.as_str(), format!("n{}", i)))
+        .collect();
+    let edges: Vec<(&String, &String, &Edge)> = graph
+        .edges
+        .iter()
+        .filter_map(|edge| {
+            Some((
+                index.get(edge.from.as_str())?,
+                index.get(edge.to.as_str())?,
+                edge,
+            ))
+        })
+        .collect();
+
+    let mut lines = Vec::new();
+    match output_format {
+        OutputFormat::Mermaid => {
+            lines.push("graph TD".to_string());
+            for node in &graph.nodes {
+                let text = node_text(node, "<br/>").replace('"', "#quot;");
+                let (open, close) = match node.kind {
+                    NodeKind::Crate | NodeKind::Struct => ("[", "]"),
+                    NodeKind::External => ("(", ")"),
+                    NodeKind::Module => ("[[", "]]"),
+                    NodeKind::Enum => ("([", "])"),
+                    NodeKind::Trait => ("{{", "}}"),
+                };
+                lines.push(format!(
+                    "    {}{}\"{}\"{}",
+                    index[node.id.as_str()],
+                    open,
+                    text,
+                    close
+                ));
+            }
+            for (from, to, edge) in edges {
+                let arrow = if edge_is_dashed(edge.kind) {
+                    "-.->"
+                } else {
+                    "-->"
+                };
+                match edge_text(edge) {
+                    Some(text) => lines.push(format!(
+                        "    {} {}|\"{}\"| {}",
+                        from,
+                        arrow,
+                        text.replace('"', "#quot;"),
+                        to
+                    )),
+                    None => lines.push(format!("    {} {} {}", from, arrow, to)),
+                }
+            }
+        }
+        OutputFormat::Plantuml => {
+            lines.push("@startuml".to_string());
+            for node in &graph.nodes {
+                let element = match node.kind {
+                    NodeKind::Crate | NodeKind::External | NodeKind::Module => "component",
+                    NodeKind::Struct | NodeKind::Enum | NodeKind::Trait => "rectangle",
+                };
+                lines.push(format!(
+                    "{} \"{}\" as {} <<{}>>",
+                    element,
+                    node_text(node, "\\n").replace('"', "'"),
+                    index[node.id.as_str()],
+                    kind_name(node.kind)
+                ));
+            }
+            for (from, to, edge) in edges {
+                let arrow = if edge_is_dashed(edge.kind) {
+                    "..>"
+                } else {
+                    "-->"
+                };
+                match edge_text(edge) {
+                    Some(text) => lines.push(format!("{} {} {} : {}", from, arrow, to, text)),
+                    None => lines.push(format!("{} {} {}", from, arrow, to)),
+                }
+            }
+            lines.push("@enduml".to_string());
+        }
+        OutputFormat::Dot => {
+            lines.push("digraph architecture {".to_string());
+            lines.push("    node [shape=box];".to_string());
+            for node in &graph.nodes {
+                let shape = match node.kind {
+                    NodeKind::Crate => "box3d",
+                    NodeKind::External => "ellipse",
+                    NodeKind::Module => "folder",
+                    NodeKind::Struct => "box",
+                    NodeKind::Enum => "box",
+                    NodeKind::Trait => "hexagon",
+                };
+                lines.push(format!(
+                    "    {} [label=\"{}\", shape={}];",
+                    index[node.id.as_str()],
+                    node_text(node, "\\n").replace('"', "\\\""),
+                    shape
+                ));
+            }
+            for (from, to, edge) in edges {
+                let mut attributes = Vec::new();
+                if let Some(text) = edge_text(edge) {
+                    attributes.push(format!("label=\"{}\"", text.replace('"', "\\\"")));
+                }
+                if edge_is_dashed(edge.kind) {
+                    attributes.push("style=dashed".to_string());
+                }
+                if attributes.is_empty() {
+                    lines.push(format!("    {} -> {};", from, to));
+                } else {
+                    lines.push(format!(
+                        "    {} -> {} [{}];",
+                        from,
+                        to,
+                        attributes.join(", ")
+                    ));
+                }
+            }
+            lines.push("}".to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// The text shown in a node: its name and, if annotated, its role.
+fn node_text(node: &Node, line_break: &str) -> String {
+    match &node.label {
+        Some(label) => format!("{}{}{}", node.name, line_break, label),
+        None => node.name.clone(),
+    }
+}
+
+/// The text shown on an edge: the annotation, or a description of its kind.
+fn edge_text(edge: &Edge) -> Option<String> {
+    edge.label.clone().or_else(|| match edge.kind {
+        EdgeKind::Implements => Some("implements".to_string()),
+        EdgeKind::References => Some("has".to_string()),
+        EdgeKind::Contains | EdgeKind::Uses | EdgeKind::DependsOn => None,
+    })
+}
+
+/// Structural relationships are drawn dashed to set them apart from dependencies.
+fn edge_is_dashed(kind: EdgeKind) -> bool {
+    matches!(kind, EdgeKind::Contains | EdgeKind::Implements)
+}
+
+fn kind_name(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Crate => "crate",
+        NodeKind::External => "external crate",
+        NodeKind::Module => "module",
+        NodeKind::Struct => "struct",
+        NodeKind::Enum => "enum",
+        NodeKind::Trait => "trait",
+    }
+}
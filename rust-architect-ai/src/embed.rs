@@ -0,0 +1,79 @@
+//! src/embed.rs
+//!
+//! Implements the `embed` subcommand, which keeps a diagram inside an existing
+//! document (usually `README.md`) up to date. The diagram is written between
+//! marker comments, so regenerating it replaces the previous version instead
+//! of requiring a manual copy-paste.
+
+use crate::diagram_generator::OutputFormat;
+use crate::error::{Error, Result};
+use std::fs;
+use std::path::Path;
+
+/// The comment that opens the embedded diagram.
+pub const START_MARKER: &str = "<!-- arch:start -->";
+
+/// The comment that closes the embedded diagram.
+pub const END_MARKER: &str = "<!-- arch:end -->";
+
+/// Inserts or updates the diagram between the markers in `target`.
+///
+/// If the document has no markers, they are appended with the diagram at the
+/// end of it; a missing document is created.
+///
+/// # Returns
+///
+/// `true` if an existing embedded diagram was replaced, `false` if it was inserted.
+///
+/// # Errors
+///
+/// Returns `Error::Config` if only one of the markers is present, or they
+/// are out of order.
+pub fn embed_diagram(target: &Path, diagram: &str, output_format: OutputFormat) -> Result<bool> {
+    let document = if target.exists() {
+        fs::read_to_string(target)?
+    } else {
+        String::new()
+    };
+    let block = format!(
+        "{}\n```{}\n{}\n```\n{}",
+        START_MARKER,
+        output_format.code_block_tag(),
+        diagram.trim(),
+        END_MARKER
+    );
+
+    let (updated, replaced) = match (document.find(START_MARKER), document.find(END_MARKER)) {
+        (Some(start), Some(end)) if start < end => {
+            let after = end + END_MARKER.len();
+            (
+                format!("{}{}{}", &document[..start], block, &document[after..]),
+                true,
+            )
+        }
+        (None, None) => {
+            let separator = match document.trim_end() {
+                "" => "",
+                _ => "\n\n",
+            };
+            (
+                format!("{}{}{}\n", document.trim_end(), separator, block),
+                false,
+            )
+        }
+        _ => {
+            return Err(Error::Config(format!(
+                "'{}' must contain '{}' followed by '{}', or neither.",
+                target.display(),
+                START_MARKER,
+                END_MARKER
+            )))
+        }
+    };
+
+    if let Some(parent_dir) = target.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    fs::write(target, updated)?;
+    Ok(replaced)
+}
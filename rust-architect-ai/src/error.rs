@@ -17,22 +17,40 @@ pub enum Error {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
-    /// Wraps errors from the `walkdir` crate during file system traversal.
+    /// Wraps errors from the `ignore` crate during file system traversal
+    /// or while parsing include/exclude patterns.
     #[error("Directory traversal error: {0}")]
-    Walkdir(#[from] walkdir::Error),
+    Ignore(#[from] ignore::Error),
 
     /// Wraps errors from the `handlebars` templating engine.
     #[error("Template rendering error: {0}")]
     Template(#[from] handlebars::RenderError),
 
-    /// Wraps errors from the `reqwest` HTTP client.
-    #[error("HTTP request error")]
-    Reqwest(#[from] reqwest::Error),
+    /// Wraps errors from requests to the OpenAI API, made with the shared
+    /// `ai-core` client.
+    #[error(transparent)]
+    Client(#[from] ai_core::Error),
 
     /// For errors returned specifically by the OpenAI API.
     #[error("OpenAI API error: {0}")]
     OpenAI(String),
 
+    /// For failures while rendering a diagram to an image.
+    #[error("Rendering error: {0}")]
+    Render(String),
+
+    /// Wraps errors from parsing `Cargo.toml` manifests.
+    #[error("TOML parsing error: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    /// For failures of cargo commands, such as `cargo metadata`.
+    #[error("Cargo error: {0}")]
+    Cargo(String),
+
+    /// Wraps errors from the file system watcher used by `--watch`.
+    #[error("File watcher error: {0}")]
+    Watch(#[from] notify_debouncer_mini::notify::Error),
+
     /// For errors during JSON serialization or deserialization.
     #[error("JSON processing error: {0}")]
     SerdeJson(#[from] serde_json::Error),
@@ -0,0 +1,54 @@
+//! src/index_page.rs
+//!
+//! Writes the index page for a run that generates several diagram types. The
+//! page links every generated diagram file and, for Mermaid, also embeds the
+//! diagram itself so that the whole set can be browsed in one place.
+
+use crate::diagram_generator::OutputFormat;
+use crate::error::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file name of the index page within the output directory.
+pub const INDEX_FILE_NAME: &str = "index.md";
+
+/// A diagram generated in a batch run.
+pub struct IndexEntry {
+    /// The heading of the diagram's section.
+    pub title: String,
+    /// Where the diagram was saved.
+    pub path: PathBuf,
+    pub diagram: String,
+}
+
+/// Writes `index.md` into `output_dir`, with one section per entry.
+///
+/// # Returns
+///
+/// The path of the written index page.
+pub fn write_index(
+    output_dir: &Path,
+    entries: &[IndexEntry],
+    output_format: OutputFormat,
+) -> Result<PathBuf> {
+    let mut page = String::from("# Architecture Diagrams\n");
+    for entry in entries {
+        // Links are relative to the index page whenever the diagram is beside it.
+        let link = entry
+            .path
+            .strip_prefix(output_dir)
+            .unwrap_or(&entry.path)
+            .display()
+            .to_string();
+        page.push_str(&format!("\n## {}\n\n[{}]({})\n", entry.title, link, link));
+        // Only Mermaid diagrams are rendered inline by common Markdown viewers.
+        if output_format == OutputFormat::Mermaid {
+            page.push_str(&format!("\n```mermaid\n{}\n```\n", entry.diagram.trim()));
+        }
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let path = output_dir.join(INDEX_FILE_NAME);
+    fs::write(&path, page)?;
+    Ok(path)
+}
@@ -0,0 +1,24 @@
+//! src/lib.rs
+//!
+//! The library behind the AI Rust Architect: the project scanner, the
+//! dependency graph, the summarizer, and the diagram generators and
+//! renderers. `main.rs` is the command-line interface over it, and the
+//! tests in `tests/` call the generators directly.
+
+pub mod c4;
+pub mod cache;
+pub mod config;
+pub mod dependency_graph;
+pub mod diagram_generator;
+pub mod embed;
+pub mod error;
+pub mod index_page;
+pub mod mermaid;
+pub mod mermaid_style;
+pub mod narrative;
+pub mod openai_client;
+pub mod project_scanner;
+pub mod renderer;
+pub mod summarizer;
+pub mod trimmer;
+pub mod watcher;
@@ -4,20 +4,35 @@
 //! This module orchestrates the entire workflow, from parsing command-line
 //! arguments to generating and saving the final architectural diagram.
 
-// Declare the module hierarchy for the compiler.
-mod config;
-mod diagram_generator;
-mod error;
+// The logger is set up by the binary alone; the rest is in the library.
 mod logger;
-mod openai_client;
-mod project_scanner;
 
-use crate::error::Result;
-use clap::Parser;
+use ai_core::count_tokens;
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use diagram_generator::DiagramType;
+use rust_architect_ai::c4::{C4Config, C4Level};
+use rust_architect_ai::cache::ResponseCache;
+use rust_architect_ai::dependency_graph::DependencyGraph;
+use rust_architect_ai::diagram_generator::{
+    DiagramGenerator, DiagramType, OutputFormat, WorkspaceMode,
+};
+use rust_architect_ai::error::{Error, Result};
+use rust_architect_ai::index_page::IndexEntry;
+use rust_architect_ai::mermaid_style::{Direction, MermaidStyle};
+use rust_architect_ai::narrative::NarrativeGenerator;
+use rust_architect_ai::project_scanner::ScanFilter;
+use rust_architect_ai::renderer::ImageFormat;
+use rust_architect_ai::summarizer::{SummarizeMode, Summarizer};
+use rust_architect_ai::trimmer::TrimStrategyKind;
+use rust_architect_ai::watcher::ChangeWatcher;
+use rust_architect_ai::{
+    cache, config, diagram_generator, embed, index_page, openai_client, project_scanner, renderer,
+    trimmer, watcher,
+};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::OnceCell;
 use tracing::{error, info};
 
 /// Defines the command-line arguments for the application.
@@ -28,21 +43,175 @@ use tracing::{error, info};
     about = "An AI-powered tool to automatically generate architectural diagrams from Rust code."
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The path to the Rust project directory to analyze.
     #[arg(short, long, default_value = ".")]
     project_path: PathBuf,
 
+    /// Only scan files matching this gitignore-style pattern, relative to the
+    /// project path (e.g., `src/**`). Can be repeated.
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Skip files and directories matching this gitignore-style pattern
+    /// (e.g., `tests/`, `examples/`, `*_generated.rs`). Can be repeated.
+    #[arg(long)]
+    exclude: Vec<String>,
+
     /// The path to the output file for the generated diagram.
-    #[arg(short, long, default_value = "output/architecture.md")]
-    output: PathBuf,
+    /// Defaults to `output/architecture.md`, `.puml`, or `.dot`, depending on the output format.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
 
-    /// The type of diagram to generate.
-    #[arg(long, value_enum, default_value_t = DiagramType::Component)]
-    diagram_type: DiagramType,
+    /// The type of diagram to generate. Several types can be given, separated
+    /// by commas (e.g., `component,sequence,class`).
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "component")]
+    diagram_type: Vec<DiagramType>,
+
+    /// Generate every diagram type in one run. The sequence diagram is only
+    /// included when `--function-name` is given.
+    #[arg(long, conflicts_with = "diagram_type")]
+    all: bool,
+
+    /// The directory for the diagrams and the index page when several diagram
+    /// types are generated. Each diagram is saved as `<type>.<extension>`.
+    #[arg(long, default_value = "output")]
+    output_dir: PathBuf,
 
     /// (For Sequence Diagrams) The name of the public function to use as the entry point.
     #[arg(long)]
     function_name: Option<String>,
+
+    /// The diagram language to generate.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Mermaid)]
+    output_format: OutputFormat,
+
+    /// The style profile from the profiles file to apply (e.g., `detailed`,
+    /// `minimal`, or `onboarding`), adding its prompt instructions and Mermaid theme.
+    #[arg(long)]
+    style: Option<String>,
+
+    /// The JSON file that defines the style profiles.
+    #[arg(long, default_value = config::DIAGRAM_PROFILES_PATH, requires = "style")]
+    profiles: PathBuf,
+
+    /// A TOML file with the Mermaid theme, flowchart direction, and node
+    /// classes to apply. Defaults to `mermaid_style.toml`, if it exists.
+    #[arg(long)]
+    mermaid_style: Option<PathBuf>,
+
+    /// The Mermaid theme (e.g., `default`, `neutral`, `dark`, `forest`),
+    /// overriding the style file.
+    #[arg(long)]
+    mermaid_theme: Option<String>,
+
+    /// The flowchart direction of Mermaid diagrams, overriding the style file.
+    #[arg(long, value_enum)]
+    direction: Option<Direction>,
+
+    /// (For Component Diagrams) Draw a Mermaid C4 diagram at this level instead.
+    #[arg(long, value_enum, conflicts_with = "static_graph")]
+    c4_level: Option<C4Level>,
+
+    /// A TOML file with the system name, external actors and systems, and
+    /// boundaries for C4 diagrams. Defaults to `c4.toml`, if it exists.
+    #[arg(long, requires = "c4_level")]
+    c4_config: Option<PathBuf>,
+
+    /// Also render the diagram to an image next to the output file.
+    /// Requires `mmdc`, `plantuml`, or `dot` (for the respective format) on the PATH.
+    #[arg(long, value_enum)]
+    render: Option<ImageFormat>,
+
+    /// For Cargo workspaces, generate one diagram per member crate (`per-crate`),
+    /// or a top-level diagram of the crates and their dependencies (`overview`).
+    #[arg(long, value_enum)]
+    workspace: Option<WorkspaceMode>,
+
+    /// (For Component Diagrams) Build the graph statically from `use` statements,
+    /// `impl` blocks, and field types, and only use the AI to label and prune it.
+    #[arg(long)]
+    static_graph: bool,
+
+    /// Write the intermediate architecture model (nodes, edges, kinds, and file
+    /// references) of the component or crates diagram to this JSON file. The
+    /// component diagram is then rendered from the model.
+    #[arg(long, conflicts_with = "c4_level")]
+    emit_model: Option<PathBuf>,
+
+    /// Skip the AI annotation of the static graph (with `--static-graph` or
+    /// `--diagram-type crates`), for a fully reproducible diagram.
+    #[arg(long)]
+    no_annotate: bool,
+
+    /// When to summarize each file first and generate the diagram from the summaries:
+    /// only when the source is too large for one request (`auto`), `always`, or `never`.
+    #[arg(long, value_enum, default_value_t = SummarizeMode::Auto)]
+    summarize: SummarizeMode,
+
+    /// Trim the source to about this many tokens before sending it to the model,
+    /// applying the `--trim-strategy` steps in order until it fits.
+    #[arg(long)]
+    max_context_tokens: Option<usize>,
+
+    /// The trimming strategies to apply, in order, with `--max-context-tokens`.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        requires = "max_context_tokens",
+        default_values_t = TrimStrategyKind::DEFAULT
+    )]
+    trim_strategy: Vec<TrimStrategyKind>,
+
+    /// How many times an invalid Mermaid diagram is sent back to the model for repair.
+    #[arg(long, default_value_t = diagram_generator::DEFAULT_REPAIR_ATTEMPTS)]
+    repair_attempts: u32,
+
+    /// The directory that caches per-file summaries between runs.
+    #[arg(long, default_value = cache::DEFAULT_CACHE_DIR)]
+    cache_dir: PathBuf,
+
+    /// Summarize every file again instead of reusing cached summaries, and
+    /// bypass the shared response cache even if `AI_CACHE` is set.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Stop once the requests have cost this many US dollars, over the whole
+    /// session with `--watch` (default: AI_MAX_COST).
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Keep running after the first generation, and regenerate the outputs
+    /// whenever the project's Rust files or manifests change.
+    #[arg(long)]
+    watch: bool,
+
+    /// With `--watch`, how long the project must be quiet, in milliseconds,
+    /// before the outputs are regenerated.
+    #[arg(long, default_value_t = watcher::DEFAULT_DEBOUNCE_MS, requires = "watch")]
+    debounce_ms: u64,
+
+    /// Also write an architecture document (overview, module responsibilities,
+    /// data flow, and extension points) in Markdown, next to the diagram as
+    /// `<output>-narrative.md`, or as `narrative.md` in the output directory
+    /// when several diagram types are generated.
+    #[arg(long)]
+    narrative: bool,
+}
+
+/// Defines the subcommands. Without one, the diagram is written to `--output`.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Insert or update the diagram in a document, between the
+    /// `<!-- arch:start -->` and `<!-- arch:end -->` marker comments.
+    Embed {
+        /// The document to embed the diagram in.
+        #[arg(long, default_value = "README.md")]
+        target: PathBuf,
+    },
 }
 
 /// The main asynchronous function that runs the application.
@@ -64,46 +233,578 @@ async fn run() -> Result<()> {
     let args = Args::parse();
     info!("Starting architectural analysis for project at '{}'.", args.project_path.display());
 
+    let diagram_types = selected_diagram_types(&args);
+
     // --- Validation for Sequence Diagram ---
-    if args.diagram_type == DiagramType::Sequence && args.function_name.is_none() {
+    if diagram_types.contains(&DiagramType::Sequence) && args.function_name.is_none() {
         error!("The '--function-name' argument is required when generating a sequence diagram.");
         std::process::exit(1);
     }
+    if args.static_graph && !diagram_types.contains(&DiagramType::Component) {
+        return Err(Error::Config(
+            "'--static-graph' can only be used with component diagrams.".to_string(),
+        ));
+    }
+    if args.c4_level.is_some() {
+        if args.output_format != OutputFormat::Mermaid {
+            return Err(Error::Config(
+                "'--c4-level' is only supported with Mermaid output.".to_string(),
+            ));
+        }
+        if !diagram_types.contains(&DiagramType::Component) {
+            return Err(Error::Config(
+                "'--c4-level' can only be used with component diagrams.".to_string(),
+            ));
+        }
+    }
+    let mermaid_options =
+        args.mermaid_style.is_some() || args.mermaid_theme.is_some() || args.direction.is_some();
+    if mermaid_options && args.output_format != OutputFormat::Mermaid {
+        return Err(Error::Config(
+            "'--mermaid-style', '--mermaid-theme', and '--direction' require Mermaid output."
+                .to_string(),
+        ));
+    }
+    if args.emit_model.is_some()
+        && !diagram_types.iter().any(|diagram_type| {
+            matches!(diagram_type, DiagramType::Component | DiagramType::Crates)
+        })
+    {
+        return Err(Error::Config(
+            "'--emit-model' needs a component or crates diagram.".to_string(),
+        ));
+    }
+    let batch = diagram_types.len() > 1;
+    if batch && args.command.is_some() {
+        return Err(Error::Config(
+            "'embed' needs a single diagram; select one '--diagram-type'.".to_string(),
+        ));
+    }
 
     // --- Initialization ---
-    let api_key = config::get_api_key()?;
-    let client = openai_client::OpenAIClient::new(api_key);
-    let generator = diagram_generator::DiagramGenerator::new(client);
+    let mut provider_config =
+        config::get_provider_config()?.track_usage(env!("CARGO_PKG_NAME"), args.max_cost);
+    if args.no_cache {
+        provider_config = provider_config.without_cache();
+    }
+    let client = openai_client::OpenAIClient::new(&provider_config)?;
+    let mut summarizer = Summarizer::new(client.clone());
+    if !args.no_cache {
+        summarizer = summarizer.with_cache(ResponseCache::new(&args.cache_dir));
+    }
+    let narrator = args
+        .narrative
+        .then(|| NarrativeGenerator::new(client.clone()));
+    let mut generator = DiagramGenerator::new(client).with_repair_attempts(args.repair_attempts);
+    if let Some(level) = args.c4_level {
+        generator = generator.with_c4(level, C4Config::load(args.c4_config.as_deref())?);
+    }
+    if let Some(style) = &args.style {
+        let profile = config::load_diagram_profile(&args.profiles, style)?;
+        info!("Using diagram style '{}'.", profile.name);
+        generator = generator.with_profile(profile);
+    }
+    if args.output_format == OutputFormat::Mermaid {
+        let mut style = MermaidStyle::load(args.mermaid_style.as_deref())?;
+        if let Some(theme) = &args.mermaid_theme {
+            style.theme = Some(theme.clone());
+        }
+        if let Some(direction) = args.direction {
+            style.direction = Some(direction);
+        }
+        if !style.is_empty() {
+            generator = generator.with_mermaid_style(style);
+        }
+    }
 
-    // --- Project Scanning ---
-    let project_context = project_scanner::scan_project(&args.project_path)?;
+    // --- Generation ---
+    generate_outputs(
+        &generator,
+        &summarizer,
+        narrator.as_ref(),
+        &diagram_types,
+        &args,
+    )
+    .await?;
 
-    if project_context.is_empty() {
-        info!("{}", "No Rust files were found in the specified directory. Exiting.".yellow());
+    // --- Watch Mode ---
+    if args.watch {
+        let mut changes =
+            ChangeWatcher::new(&args.project_path, Duration::from_millis(args.debounce_ms))?;
+        info!(
+            "{}",
+            format!(
+                "Watching '{}' for changes. Press Ctrl+C to stop.",
+                args.project_path.display()
+            )
+            .cyan()
+        );
+        while let Some(changed) = changes.next_change().await {
+            info!("{} file(s) changed; regenerating.", changed.len());
+            // A failed regeneration, e.g. while a file is half-edited, must not stop watching.
+            if let Err(e) = generate_outputs(
+                &generator,
+                &summarizer,
+                narrator.as_ref(),
+                &diagram_types,
+                &args,
+            )
+            .await
+            {
+                error!(error = ?e, "Regeneration failed; waiting for the next change.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates every requested diagram (and the narrative and index page, if
+/// requested) and writes them to their outputs.
+async fn generate_outputs(
+    generator: &DiagramGenerator,
+    summarizer: &Summarizer,
+    narrator: Option<&NarrativeGenerator>,
+    diagram_types: &[DiagramType],
+    args: &Args,
+) -> Result<()> {
+    let batch = diagram_types.len() > 1;
+    let mut index = Vec::new();
+
+    // --- Workspace Diagrams ---
+    if let Some(mode) = args.workspace {
+        let members = project_scanner::find_workspace_members(&args.project_path)?;
+        if members.is_empty() {
+            return Err(Error::Config(format!(
+                "'{}' is not a Cargo workspace with members.",
+                args.project_path.display()
+            )));
+        }
+
+        match mode {
+            WorkspaceMode::Overview => {
+                let graph = DependencyGraph::from_workspace(&members);
+                let diagram = generator.apply_theme(
+                    diagram_generator::graph_diagram(&graph, args.output_format),
+                    args.output_format,
+                );
+                publish_diagram(&diagram, &output_path(args, None), args).await?;
+                if let Some(narrator) = narrator {
+                    let project = Project::new(&args.project_path);
+                    let output = narrative_path(args, batch);
+                    write_narrative(
+                        narrator,
+                        summarizer,
+                        &project,
+                        Some(&diagram),
+                        &output,
+                        args,
+                    )
+                    .await?;
+                }
+            }
+            WorkspaceMode::PerCrate if args.command.is_some() => {
+                return Err(Error::Config(
+                    "'embed' needs a single diagram; use '--workspace overview' instead."
+                        .to_string(),
+                ));
+            }
+            WorkspaceMode::PerCrate => {
+                for member in &members {
+                    info!("Scanning workspace member '{}'.", member.name);
+                    let project = Project::new(&member.path);
+                    let first_entry = index.len();
+                    for &diagram_type in diagram_types {
+                        let model_output = model_path(args, diagram_type, batch)
+                            .map(|path| suffixed_output(&path, &member.name));
+                        let Some(diagram) = generate(
+                            generator,
+                            summarizer,
+                            &project,
+                            diagram_type,
+                            model_output.as_deref(),
+                            args,
+                        )
+                        .await?
+                        else {
+                            info!(
+                                "{}",
+                                format!("No Rust files found for '{}'. Skipping.", member.name)
+                                    .yellow()
+                            );
+                            break;
+                        };
+                        let output = suffixed_output(
+                            &output_path(args, batch.then_some(diagram_type)),
+                            &member.name,
+                        );
+                        save_diagram(&diagram, &output, args).await?;
+                        index.push(IndexEntry {
+                            title: format!("{} ({})", diagram_type.title(), member.name),
+                            path: output,
+                            diagram,
+                        });
+                    }
+                    if let Some(narrator) = narrator {
+                        let diagram = index.get(first_entry).map(|entry| entry.diagram.as_str());
+                        let output = suffixed_output(&narrative_path(args, batch), &member.name);
+                        write_narrative(narrator, summarizer, &project, diagram, &output, args)
+                            .await?;
+                    }
+                }
+            }
+        }
+        if batch {
+            write_index(&index, args)?;
+        }
         return Ok(());
     }
 
-    // --- Diagram Generation ---
+    // --- Project Scanning and Diagram Generation ---
+    // The project is scanned once and shared by all requested diagram types.
+    let project = Project::new(&args.project_path);
+    for &diagram_type in diagram_types {
+        let model_output = model_path(args, diagram_type, batch);
+        let Some(diagram) = generate(
+            generator,
+            summarizer,
+            &project,
+            diagram_type,
+            model_output.as_deref(),
+            args,
+        )
+        .await?
+        else {
+            info!("{}", "No Rust files were found in the specified directory. Exiting.".yellow());
+            return Ok(());
+        };
+
+        // --- Output ---
+        let output = output_path(args, batch.then_some(diagram_type));
+        publish_diagram(&diagram, &output, args).await?;
+        index.push(IndexEntry {
+            title: diagram_type.title().to_string(),
+            path: output,
+            diagram,
+        });
+    }
+    if let Some(narrator) = narrator {
+        let diagram = index.first().map(|entry| entry.diagram.as_str());
+        let output = narrative_path(args, batch);
+        write_narrative(narrator, summarizer, &project, diagram, &output, args).await?;
+    }
+    if batch {
+        write_index(&index, args)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the diagram types selected with `--diagram-type` or `--all`.
+fn selected_diagram_types(args: &Args) -> Vec<DiagramType> {
+    if !args.all {
+        return args.diagram_type.clone();
+    }
+    DiagramType::value_variants()
+        .iter()
+        .copied()
+        .filter(|diagram_type| {
+            let included = *diagram_type != DiagramType::Sequence || args.function_name.is_some();
+            if !included {
+                info!("Skipping the sequence diagram; pass '--function-name' to include it.");
+            }
+            included
+        })
+        .collect()
+}
+
+/// A project to diagram. Its source is scanned (and summarized, if needed)
+/// at most once, however many diagrams are generated from it.
+struct Project<'a> {
+    path: &'a Path,
+    context: OnceCell<Option<String>>,
+}
+
+impl<'a> Project<'a> {
+    fn new(path: &'a Path) -> Self {
+        Self {
+            path,
+            context: OnceCell::new(),
+        }
+    }
+
+    /// Returns the context sent to the model: the combined source, or the
+    /// summaries if the source is too large. `None` if there are no Rust files.
+    async fn context(&self, summarizer: &Summarizer, args: &Args) -> Result<Option<&str>> {
+        let context = self
+            .context
+            .get_or_try_init(|| async {
+                let mut sources = project_scanner::read_sources(self.path, &scan_filter(args))?;
+                if sources.is_empty() {
+                    return Ok::<_, Error>(None);
+                }
+                if let Some(budget) = args.max_context_tokens {
+                    let strategies: Vec<_> = args
+                        .trim_strategy
+                        .iter()
+                        .map(TrimStrategyKind::strategy)
+                        .collect();
+                    sources =
+                        trimmer::trim_to_budget(sources, summarizer.model(), budget, &strategies);
+                }
+                let project_context = project_scanner::combine_sources(&sources);
+                let context_tokens = count_tokens(summarizer.model(), &project_context);
+                if !args.summarize.applies_to(context_tokens) {
+                    return Ok(Some(project_context));
+                }
+                if args.summarize == SummarizeMode::Auto {
+                    info!(
+                        "The source ({} tokens) exceeds the context limit; falling back to summaries.",
+                        context_tokens
+                    );
+                }
+                Ok(Some(summarizer.summarize_project(&sources).await?))
+            })
+            .await?;
+        Ok(context.as_deref())
+    }
+}
+
+fn scan_filter(args: &Args) -> ScanFilter {
+    ScanFilter {
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+    }
+}
+
+/// Generates a diagram of the given type for `project`.
+///
+/// Component and crates diagrams are rendered from an architecture model;
+/// if `model_output` is given, the model is also written there as JSON.
+/// Returns `None` if the project contains no Rust files.
+async fn generate(
+    generator: &DiagramGenerator,
+    summarizer: &Summarizer,
+    project: &Project<'_>,
+    diagram_type: DiagramType,
+    model_output: Option<&Path>,
+    args: &Args,
+) -> Result<Option<String>> {
+    let model = match diagram_type {
+        DiagramType::Crates => {
+            let mut graph = DependencyGraph::from_cargo_metadata(project.path).await?;
+            if !args.no_annotate {
+                generator.annotate_graph(&mut graph).await?;
+            }
+            Some(graph)
+        }
+        DiagramType::Component if args.static_graph => {
+            let mut graph = DependencyGraph::from_sources(project.path, &scan_filter(args))?;
+            if graph.nodes.is_empty() {
+                return Ok(None);
+            }
+            if !args.no_annotate {
+                generator.annotate_graph(&mut graph).await?;
+            }
+            Some(graph)
+        }
+        // With `--emit-model`, the AI produces the model instead of the diagram syntax.
+        DiagramType::Component if args.emit_model.is_some() => {
+            let Some(project_context) = project.context(summarizer, args).await? else {
+                return Ok(None);
+            };
+            Some(generator.generate_model(project_context).await?)
+        }
+        _ => None,
+    };
+    if let Some(model) = model {
+        if let Some(path) = model_output {
+            write_model(&model, path)?;
+        }
+        let diagram = diagram_generator::graph_diagram(&model, args.output_format);
+        return Ok(Some(generator.apply_theme(diagram, args.output_format)));
+    }
+
+    let Some(project_context) = project.context(summarizer, args).await? else {
+        return Ok(None);
+    };
     let diagram = generator
-        .generate_diagram(&project_context, args.diagram_type, args.function_name)
+        .generate_diagram(
+            project_context,
+            diagram_type,
+            args.function_name.clone(),
+            args.output_format,
+        )
         .await?;
+    Ok(Some(generator.apply_theme(diagram, args.output_format)))
+}
 
-    // --- Output ---
+/// Returns where the architecture model of a diagram is written: `--emit-model`,
+/// or `<stem>-<type>.json` in a batch run. `None` if no model is requested.
+fn model_path(args: &Args, diagram_type: DiagramType, batch: bool) -> Option<PathBuf> {
+    let path = args.emit_model.as_ref()?;
+    Some(if batch {
+        suffixed_output(path, diagram_type.name())
+    } else {
+        path.clone()
+    })
+}
+
+/// Writes an architecture model as pretty-printed JSON.
+fn write_model(model: &DependencyGraph, path: &Path) -> Result<()> {
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(model)?)?;
+    info!(
+        "{}",
+        format!("Wrote the architecture model to '{}'.", path.display())
+            .green()
+            .bold()
+    );
+    Ok(())
+}
+
+/// Returns where a diagram is saved: `--output` (or its default) for a single
+/// diagram, or `<output-dir>/<type>.<extension>` when several types are generated.
+fn output_path(args: &Args, batch_type: Option<DiagramType>) -> PathBuf {
+    let extension = args.output_format.file_extension();
+    match batch_type {
+        Some(diagram_type) => {
+            args.output_dir
+                .join(format!("{}.{}", diagram_type.name(), extension))
+        }
+        None => args
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("output/architecture.{}", extension))),
+    }
+}
+
+/// Returns where the architecture document is saved: beside the diagram as
+/// `<stem>-narrative.md`, or as `narrative.md` in the output directory in a batch run.
+fn narrative_path(args: &Args, batch: bool) -> PathBuf {
+    if batch {
+        return args.output_dir.join("narrative.md");
+    }
+    let output = output_path(args, None);
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    output.with_file_name(format!("{}-narrative.md", stem))
+}
+
+/// Generates the architecture document for `project` and writes it to `output`.
+///
+/// Nothing is written if the project contains no Rust files.
+async fn write_narrative(
+    narrator: &NarrativeGenerator,
+    summarizer: &Summarizer,
+    project: &Project<'_>,
+    diagram: Option<&str>,
+    output: &Path,
+    args: &Args,
+) -> Result<()> {
+    let Some(project_context) = project.context(summarizer, args).await? else {
+        return Ok(());
+    };
+    let narrative = narrator
+        .generate_narrative(project_context, diagram, args.output_format)
+        .await?;
+
+    if let Some(parent_dir) = output.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    fs::write(output, narrative)?;
+    info!(
+        "{}",
+        format!(
+            "Wrote the architecture narrative to '{}'.",
+            output.display()
+        )
+        .green()
+        .bold()
+    );
+    Ok(())
+}
+
+/// Writes the index page of a batch run and logs its location.
+fn write_index(entries: &[IndexEntry], args: &Args) -> Result<()> {
+    let path = index_page::write_index(&args.output_dir, entries, args.output_format)?;
+    info!(
+        "{}",
+        format!("Wrote the diagram index to '{}'.", path.display())
+            .green()
+            .bold()
+    );
+    Ok(())
+}
+
+/// Embeds the diagram in a document for the `embed` subcommand, or saves it
+/// to `output` otherwise.
+async fn publish_diagram(diagram: &str, output: &Path, args: &Args) -> Result<()> {
+    let Some(Command::Embed { target }) = &args.command else {
+        return save_diagram(diagram, output, args).await;
+    };
+
+    let replaced = embed::embed_diagram(target, diagram, args.output_format)?;
+    info!(
+        "{}",
+        format!(
+            "Successfully {} the diagram in '{}'.",
+            if replaced { "updated" } else { "inserted" },
+            target.display()
+        )
+        .green()
+        .bold()
+    );
+    Ok(())
+}
+
+/// Writes a diagram to `output` and, if requested, renders it to an image.
+async fn save_diagram(diagram: &str, output: &Path, args: &Args) -> Result<()> {
     // Ensure the output directory exists before writing the file.
-    if let Some(parent_dir) = args.output.parent() {
+    if let Some(parent_dir) = output.parent() {
         fs::create_dir_all(parent_dir)?;
     }
-    fs::write(&args.output, &diagram)?;
+    fs::write(output, diagram)?;
 
     info!(
         "{}",
         format!(
             "Successfully generated diagram and saved it to '{}'.",
-            args.output.display()
+            output.display()
         )
         .green()
         .bold()
     );
 
+    // --- Rendering ---
+    if let Some(image_format) = args.render {
+        let image_path =
+            renderer::render(diagram, args.output_format, image_format, output).await?;
+        info!(
+            "{}",
+            format!("Rendered diagram to '{}'.", image_path.display())
+                .green()
+                .bold()
+        );
+    }
+
     Ok(())
 }
+
+/// Appends a suffix to the file name of an output path, e.g. a member
+/// crate's `output/architecture-core.md`.
+fn suffixed_output(output: &Path, suffix: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "architecture".to_string());
+    let file_name = match output.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, suffix),
+    };
+    output.with_file_name(file_name)
+}
@@ -0,0 +1,350 @@
+//! src/mermaid.rs
+//!
+//! A lightweight Mermaid syntax checker, used to catch broken diagrams before
+//! they are written. It recognizes the diagram header and, for the flowchart
+//! and sequence diagrams this tool generates, checks block structure, node
+//! labels, and message syntax, reporting the first error with its line number
+//! so the model can repair it.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt;
+
+/// The diagram types Mermaid accepts as the first statement.
+const DIAGRAM_KEYWORDS: [&str; 25] = [
+    "graph",
+    "flowchart",
+    "sequenceDiagram",
+    "classDiagram",
+    "stateDiagram",
+    "stateDiagram-v2",
+    "erDiagram",
+    "gantt",
+    "pie",
+    "journey",
+    "gitGraph",
+    "mindmap",
+    "timeline",
+    "quadrantChart",
+    "requirementDiagram",
+    "C4Context",
+    "C4Container",
+    "C4Component",
+    "C4Dynamic",
+    "C4Deployment",
+    "block-beta",
+    "architecture-beta",
+    "sankey-beta",
+    "xychart-beta",
+    "packet-beta",
+];
+
+/// The directions a flowchart may declare after its keyword.
+const FLOWCHART_DIRECTIONS: [&str; 5] = ["TD", "TB", "BT", "RL", "LR"];
+
+/// Sequence diagram statements that open a block closed by `end`.
+const SEQUENCE_BLOCKS: [&str; 8] = [
+    "alt", "opt", "loop", "par", "critical", "break", "rect", "box",
+];
+
+/// Sequence diagram statements that continue an open block.
+const SEQUENCE_BLOCK_BRANCHES: [&str; 3] = ["else", "and", "option"];
+
+/// Sequence diagram statements other than messages and blocks.
+const SEQUENCE_STATEMENTS: [&str; 9] = [
+    "participant",
+    "actor",
+    "Note",
+    "note",
+    "activate",
+    "deactivate",
+    "autonumber",
+    "title",
+    "create",
+];
+
+/// Flowchart statements that are not node or edge definitions.
+const FLOWCHART_STATEMENTS: [&str; 6] = [
+    "classDef",
+    "class",
+    "style",
+    "linkStyle",
+    "click",
+    "direction",
+];
+
+/// A sequence diagram message: `Alice->>Bob: text`, with optional activation markers.
+static SEQUENCE_MESSAGE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[^:]+?\s*(<<-->>|<<->>|-->>|->>|-->|->|--x|-x|--\)|-\))\s*[+-]?[^:]+:.*$")
+        .unwrap()
+});
+
+/// The first syntax error found in a diagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The 1-based line number of the error.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// The family of a diagram, which determines the checks applied to its body.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Family {
+    Flowchart,
+    Sequence,
+    Other,
+}
+
+/// Checks the syntax of a Mermaid diagram.
+///
+/// # Errors
+///
+/// Returns the first `SyntaxError` found.
+pub fn validate(diagram: &str) -> std::result::Result<(), SyntaxError> {
+    let error = |line: usize, message: String| Err(SyntaxError { line, message });
+    let mut family = None;
+    let mut open_blocks: Vec<(usize, &str)> = Vec::new();
+    let mut in_front_matter = false;
+
+    for (index, raw_line) in diagram.lines().enumerate() {
+        let number = index + 1;
+        let line = raw_line.trim();
+
+        // Front matter (`---` ... `---`), comments, and directives are skipped.
+        if line == "---" && family.is_none() {
+            in_front_matter = !in_front_matter;
+            continue;
+        }
+        if in_front_matter || line.is_empty() || line.starts_with("%%") {
+            continue;
+        }
+
+        let Some(current) = family else {
+            family = Some(parse_header(line).map_err(|message| SyntaxError {
+                line: number,
+                message,
+            })?);
+            continue;
+        };
+
+        let first_word = line.split_whitespace().next().unwrap_or_default();
+        if first_word == "end" {
+            if open_blocks.pop().is_none() {
+                return error(number, "`end` without an open block".to_string());
+            }
+            continue;
+        }
+
+        match current {
+            Family::Flowchart => {
+                if first_word == "subgraph" {
+                    open_blocks.push((number, "subgraph"));
+                    continue;
+                }
+                if FLOWCHART_STATEMENTS.contains(&first_word) {
+                    continue;
+                }
+                check_flowchart_line(line).map_err(|message| SyntaxError {
+                    line: number,
+                    message,
+                })?;
+            }
+            Family::Sequence => {
+                if SEQUENCE_BLOCKS.contains(&first_word) {
+                    open_blocks.push((number, first_word));
+                } else if SEQUENCE_BLOCK_BRANCHES.contains(&first_word) {
+                    if open_blocks.is_empty() {
+                        return error(number, format!("`{}` outside of a block", first_word));
+                    }
+                } else if !SEQUENCE_STATEMENTS.contains(&first_word)
+                    && !SEQUENCE_MESSAGE.is_match(line)
+                {
+                    return error(
+                        number,
+                        format!(
+                            "`{}` is not a valid statement; messages must have the form `A->>B: text`",
+                            line
+                        ),
+                    );
+                }
+            }
+            Family::Other => {}
+        }
+    }
+
+    if family.is_none() {
+        return error(1, "the diagram is empty".to_string());
+    }
+    if let Some((line, block)) = open_blocks.pop() {
+        return error(line, format!("`{}` is never closed with `end`", block));
+    }
+    if family == Some(Family::Other) {
+        check_balanced(diagram).map_err(|message| SyntaxError { line: 1, message })?;
+    }
+    Ok(())
+}
+
+/// Parses the diagram's first statement.
+fn parse_header(line: &str) -> std::result::Result<Family, String> {
+    let mut words = line.split_whitespace();
+    let keyword = words.next().unwrap_or_default();
+    if !DIAGRAM_KEYWORDS.contains(&keyword) {
+        return Err(format!(
+            "`{}` is not a diagram type; the diagram must start with one such as `graph TD` or `sequenceDiagram`",
+            keyword
+        ));
+    }
+    match keyword {
+        "graph" | "flowchart" => match words.next() {
+            Some(direction) if !FLOWCHART_DIRECTIONS.contains(&direction) => Err(format!(
+                "`{}` is not a flowchart direction; use one of {}",
+                direction,
+                FLOWCHART_DIRECTIONS.join(", ")
+            )),
+            _ => Ok(Family::Flowchart),
+        },
+        "sequenceDiagram" => Ok(Family::Sequence),
+        _ => Ok(Family::Other),
+    }
+}
+
+/// Checks the node labels and brackets of a flowchart node or edge statement.
+///
+/// Unquoted labels may not contain brackets or parentheses, e.g.
+/// `A[run (async)]` must be written as `A["run (async)"]`.
+fn check_flowchart_line(line: &str) -> std::result::Result<(), String> {
+    let mut chars = line.chars().peekable();
+    let mut previous = ' ';
+    while let Some(c) = chars.next() {
+        // `A>label]` opens an asymmetric shape; in arrows, `>` follows `-`, `=`, or `.`.
+        let opens_asymmetric = c == '>' && (previous.is_alphanumeric() || previous == '_');
+        match c {
+            // Quoted text and edge labels (`-->|text|`) may contain anything.
+            '"' | '|' if !chars.by_ref().any(|next| next == c) => {
+                return Err(format!("unclosed `{}` in `{}`", c, line));
+            }
+            '[' | '(' | '{' | '>' if c != '>' || opens_asymmetric => {
+                // A shape may open with several delimiters, e.g. `[(`, `((`, or `{{`.
+                let mut depth = 1;
+                let mut slanted = false;
+                while chars
+                    .peek()
+                    .is_some_and(|next| matches!(next, '[' | '(' | '{' | '/' | '\\'))
+                {
+                    slanted |= matches!(chars.next(), Some('/' | '\\'));
+                    depth += 1;
+                }
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    if !chars.by_ref().any(|next| next == '"') {
+                        return Err(format!("unclosed `\"` in `{}`", line));
+                    }
+                } else {
+                    // In `[/label/]` and the other slanted shapes, the label
+                    // ends at the closing `/` or `\`.
+                    let ends_label = |next: &char| {
+                        matches!(next, ']' | ')' | '}') || (slanted && matches!(next, '/' | '\\'))
+                    };
+                    while let Some(next) = chars.next_if(|next| !ends_label(next)) {
+                        if matches!(next, '[' | '(' | '{') {
+                            return Err(format!(
+                                "the label in `{}` contains `{}`; wrap the label in double quotes",
+                                line, next
+                            ));
+                        }
+                    }
+                }
+                for _ in 0..depth {
+                    match chars.next() {
+                        Some(']' | ')' | '}' | '/' | '\\') => {}
+                        _ => return Err(format!("unbalanced brackets in `{}`", line)),
+                    }
+                }
+            }
+            ']' | ')' | '}' => return Err(format!("unexpected `{}` in `{}`", c, line)),
+            _ => {}
+        }
+        previous = c;
+    }
+    Ok(())
+}
+
+/// Checks that braces and double quotes are balanced across the whole diagram.
+fn check_balanced(diagram: &str) -> std::result::Result<(), String> {
+    if !diagram.matches('"').count().is_multiple_of(2) {
+        return Err("unbalanced double quotes".to_string());
+    }
+    let mut depth: i64 = 0;
+    for c in diagram.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return Err("`}` without a matching `{`".to_string());
+        }
+    }
+    if depth != 0 {
+        return Err("unclosed `{`".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_every_flowchart_shape() {
+        let shapes = [
+            "A[Rectangle]",
+            "A(Rounded)",
+            "A([Stadium])",
+            "A[[Subroutine]]",
+            "A[(Database)]",
+            "A((Circle))",
+            "A(((Double circle)))",
+            "A>Asymmetric]",
+            "A{Rhombus}",
+            "A{{Hexagon}}",
+            "A[/Parallelogram/]",
+            "A[\\Parallelogram\\]",
+            "A[/Trapezoid\\]",
+            "A[\\Trapezoid/]",
+            "A[\"Quoted (label)\"]",
+        ];
+        for shape in shapes {
+            assert_eq!(check_flowchart_line(shape), Ok(()), "{}", shape);
+            let edge = format!("{} -->|yes| B[Next]", shape);
+            assert_eq!(check_flowchart_line(&edge), Ok(()), "{}", edge);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_flowchart_lines() {
+        for line in [
+            "A[run (async)]",
+            "A[Unclosed",
+            "A[/Unclosed/",
+            "A(Mismatched]]",
+            "A[\"Unclosed quote]",
+            "A -->|unclosed B",
+            "A --> B]",
+        ] {
+            assert!(check_flowchart_line(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn validates_a_flowchart_with_slanted_shapes() {
+        let diagram = "graph TD\n    A[/Input/] --> B[\\Process\\]\n    B --> C[/Output\\]";
+        assert_eq!(validate(diagram), Ok(()));
+    }
+}
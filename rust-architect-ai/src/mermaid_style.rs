@@ -0,0 +1,229 @@
+//! src/mermaid_style.rs
+//!
+//! Mermaid theming and layout configuration, so that generated diagrams match
+//! the styling of existing documentation. The style sets the Mermaid theme,
+//! the flowchart direction, and named node classes (`classDef`). It is added
+//! to the prompt, so the model lays out and classifies the nodes accordingly,
+//! and then enforced on the output, so the result is consistent even when the
+//! model ignores part of it.
+
+use crate::error::{Error, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+/// The style file read when `--mermaid-style` is not given, if it exists.
+pub const DEFAULT_MERMAID_STYLE: &str = "mermaid_style.toml";
+
+/// The direction of a flowchart.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Direction {
+    /// Top to bottom.
+    Td,
+    /// Top to bottom (same as `td`).
+    Tb,
+    /// Bottom to top.
+    Bt,
+    /// Left to right.
+    Lr,
+    /// Right to left.
+    Rl,
+}
+
+impl Direction {
+    /// The Mermaid keyword for the direction.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Direction::Td => "TD",
+            Direction::Tb => "TB",
+            Direction::Bt => "BT",
+            Direction::Lr => "LR",
+            Direction::Rl => "RL",
+        }
+    }
+}
+
+/// A named node style, written as a `classDef` statement.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ClassDef {
+    /// The CSS-like style, e.g. `fill:#e8f0fe,stroke:#1a73e8`.
+    pub style: String,
+    /// Which nodes the class is for, used to tell the model when to apply it.
+    #[serde(default)]
+    pub description: String,
+}
+
+/// The theme, layout, and node classes of generated Mermaid diagrams.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MermaidStyle {
+    /// A Mermaid theme, e.g. `default`, `neutral`, `dark`, `forest`, or `base`.
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// Mermaid `themeVariables`, e.g. `primaryColor`.
+    #[serde(default)]
+    pub theme_variables: BTreeMap<String, String>,
+    #[serde(default)]
+    pub direction: Option<Direction>,
+    /// Node classes by name.
+    #[serde(default)]
+    pub class_defs: BTreeMap<String, ClassDef>,
+}
+
+impl MermaidStyle {
+    /// Loads the style from `path`, or from `mermaid_style.toml` in the
+    /// current directory if no path is given. A missing default file yields
+    /// an empty style.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the given file does not exist or cannot be parsed.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) if !path.is_file() => {
+                return Err(Error::Config(format!(
+                    "Mermaid style '{}' not found.",
+                    path.display()
+                )));
+            }
+            Some(path) => path,
+            None if Path::new(DEFAULT_MERMAID_STYLE).is_file() => Path::new(DEFAULT_MERMAID_STYLE),
+            None => return Ok(Self::default()),
+        };
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Whether the style sets anything.
+    pub fn is_empty(&self) -> bool {
+        self.theme.is_none()
+            && self.theme_variables.is_empty()
+            && self.direction.is_none()
+            && self.class_defs.is_empty()
+    }
+
+    /// Renders the layout and class instructions as a section of the diagram
+    /// prompt, or an empty string if there are none. The theme is not
+    /// mentioned, since it is added to the output afterwards.
+    pub fn prompt_section(&self) -> String {
+        let mut instructions = Vec::new();
+        if let Some(direction) = self.direction {
+            instructions.push(format!(
+                "If the diagram is a flowchart, use the direction `{}` (e.g., `graph {}`).",
+                direction.keyword(),
+                direction.keyword()
+            ));
+        }
+        if !self.class_defs.is_empty() {
+            let classes = self
+                .class_defs
+                .iter()
+                .map(|(name, class)| format!("- `{}`: {}", name, class.description.trim()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            instructions.push(format!(
+                "Assign nodes to these style classes with `class` statements (e.g., `class A,B name`), and do not define any `classDef` or `style` statements yourself:\n{}",
+                classes
+            ));
+        }
+        instructions.join(" ")
+    }
+
+    /// Enforces the style on a generated Mermaid diagram: merges the theme
+    /// into its init directive, sets the flowchart direction, and replaces any
+    /// `classDef` statements for the configured classes with their styles.
+    pub fn apply(&self, diagram: String) -> String {
+        if self.is_empty() {
+            return diagram;
+        }
+        let mut lines: Vec<String> = diagram.lines().map(str::to_string).collect();
+
+        // The existing init directive, e.g. from a style profile, is merged with the theme.
+        let mut init = serde_json::Map::new();
+        let mut apply_theme = true;
+        if let Some(index) = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with("%%{init"))
+        {
+            match parse_init(&lines[index]) {
+                Some(existing) => {
+                    lines.remove(index);
+                    init = existing;
+                }
+                None => {
+                    warn!(
+                        "Could not parse the diagram's init directive; the theme is not applied."
+                    );
+                    apply_theme = false;
+                }
+            }
+        }
+
+        let mut is_flowchart = false;
+        if let Some(header) = lines
+            .iter_mut()
+            .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with("%%"))
+        {
+            let keyword = header
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            is_flowchart = keyword == "graph" || keyword == "flowchart";
+            if let Some(direction) = self.direction.filter(|_| is_flowchart) {
+                *header = format!("{} {}", keyword, direction.keyword());
+            }
+        }
+
+        if is_flowchart && !self.class_defs.is_empty() {
+            lines.retain(|line| {
+                let mut words = line.split_whitespace();
+                !(words.next() == Some("classDef")
+                    && words
+                        .next()
+                        .is_some_and(|name| self.class_defs.contains_key(name)))
+            });
+            for (name, class) in &self.class_defs {
+                lines.push(format!("    classDef {} {}", name, class.style.trim()));
+            }
+        }
+
+        if apply_theme {
+            if let Some(theme) = &self.theme {
+                init.insert("theme".to_string(), serde_json::Value::from(theme.clone()));
+            }
+            if !self.theme_variables.is_empty() {
+                let variables = init
+                    .entry("themeVariables")
+                    .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+                if let Some(variables) = variables.as_object_mut() {
+                    for (key, value) in &self.theme_variables {
+                        variables.insert(key.clone(), serde_json::Value::from(value.clone()));
+                    }
+                }
+            }
+            if !init.is_empty() {
+                lines.insert(
+                    0,
+                    format!("%%{{init: {}}}%%", serde_json::Value::Object(init)),
+                );
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Parses the JSON object of an `%%{init: {...}}%%` directive.
+fn parse_init(line: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let json = line
+        .trim()
+        .strip_prefix("%%{init:")?
+        .strip_suffix("}%%")?
+        .trim();
+    match serde_json::from_str(json).ok()? {
+        serde_json::Value::Object(init) => Some(init),
+        _ => None,
+    }
+}
@@ -0,0 +1,107 @@
+//! src/narrative.rs
+//!
+//! Generates a written architecture document to accompany the diagrams. From
+//! the same project context used for the diagram, the model describes the
+//! system's overview, module responsibilities, data flow, and extension
+//! points as structured Markdown.
+
+use crate::diagram_generator::OutputFormat;
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::info;
+
+/// The sections the document must contain, in order.
+const SECTIONS: [&str; 4] = [
+    "Overview",
+    "Module Responsibilities",
+    "Data Flow",
+    "Extension Points",
+];
+
+/// Strips a Markdown code fence the model may wrap the whole document in.
+static MARKDOWN_FENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)^\s*```(?:markdown|md)?\s*\n(.*)\n\s*```\s*$").unwrap());
+
+/// Writes architecture documents with the AI model.
+pub struct NarrativeGenerator {
+    client: OpenAIClient,
+}
+
+impl NarrativeGenerator {
+    /// Creates a new instance of the `NarrativeGenerator`.
+    pub fn new(client: OpenAIClient) -> Self {
+        Self { client }
+    }
+
+    /// Generates the architecture document for a project.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_context` - The combined source or summaries of the project.
+    /// * `diagram` - The generated diagram, if any. The document is kept
+    ///   consistent with it and includes it in a final section.
+    /// * `output_format` - The language of `diagram`.
+    ///
+    /// # Returns
+    ///
+    /// The document as Markdown.
+    pub async fn generate_narrative(
+        &self,
+        project_context: &str,
+        diagram: Option<&str>,
+        output_format: OutputFormat,
+    ) -> Result<String> {
+        info!("Requesting the architecture narrative from the AI model...");
+        let prompt = build_prompt(project_context, diagram, output_format);
+        let response_text = self.client.send_request(prompt).await?;
+
+        let document = MARKDOWN_FENCE
+            .captures(&response_text)
+            .and_then(|caps| caps.get(1))
+            .map_or(response_text.as_str(), |m| m.as_str())
+            .trim();
+        if document.is_empty() {
+            return Err(Error::OpenAI(
+                "AI response did not contain an architecture document.".to_string(),
+            ));
+        }
+
+        let mut narrative = document.to_string();
+        if let Some(diagram) = diagram {
+            narrative.push_str(&format!(
+                "\n\n## Architecture Diagram\n\n```{}\n{}\n```\n",
+                output_format.code_block_tag(),
+                diagram.trim()
+            ));
+        } else {
+            narrative.push('\n');
+        }
+        Ok(narrative)
+    }
+}
+
+/// Constructs the prompt for the architecture document.
+fn build_prompt(
+    project_context: &str,
+    diagram: Option<&str>,
+    output_format: OutputFormat,
+) -> String {
+    let sections = SECTIONS
+        .iter()
+        .map(|section| format!("`## {}`", section))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let diagram_instructions = diagram.map_or_else(String::new, |diagram| {
+        format!(
+            "\n\nThe following {} diagram was generated for this codebase. Use the same component names so the document and the diagram match:\n\n{}",
+            output_format.language_name(),
+            diagram.trim()
+        )
+    });
+
+    format!(
+        "You are an expert software architect with deep knowledge of Rust. Your task is to write an architecture document for the codebase provided below, for a developer who is new to it.\n\nInstructions:\n1. Start with a `# ` title naming the project, followed by the sections {sections}, in that order.\n2. Overview: the purpose of the system and its main building blocks. Module Responsibilities: one entry per significant module, with its responsibility and key types. Data Flow: how data moves through the system for its main use cases. Extension Points: the traits, configuration, and modules to change when extending the system.\n3. Refer to real module, type, and function names from the code. Do not invent components.\n4. Your final output must ONLY be the Markdown document. Do not include any other text, explanations, or introductory sentences.{diagram_instructions}\n\nSTART OF CODEBASE CONTEXT\n---\n{project_context}\n---\nEND OF CODEBASE CONTEXT"
+    )
+}
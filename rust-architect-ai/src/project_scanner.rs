@@ -2,46 +2,54 @@
 //!
 //! This module is responsible for scanning a project directory, discovering
 //! all relevant Rust source files, and aggregating their content into a
-//! single context for analysis.
+//! single context for analysis. Files ignored by `.gitignore` or rejected by
+//! the include/exclude patterns are skipped. It also reads Cargo workspace membership, so
+//! each member crate can be scanned separately.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use std::collections::BTreeSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tracing::info;
-use walkdir::WalkDir;
 
-/// Scans the given project path, finds all Rust files, and consolidates
-/// their content into a single string.
-///
-/// Each file's content is prefixed with a clear header indicating its path,
-/// providing essential context for the AI model's analysis.
-///
-/// # Arguments
-///
-/// * `root_path` - A reference to the root directory of the project to scan.
-///
-/// # Returns
-///
-/// A `Result` containing a single `String` with the combined content of all
-/// found `.rs` files.
+/// The dependency tables of a manifest that can reference other workspace members.
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A member crate of a Cargo workspace.
+#[derive(Debug, Clone)]
+pub struct WorkspaceMember {
+    /// The package name from the member's `Cargo.toml`.
+    pub name: String,
+    /// The member's directory.
+    pub path: PathBuf,
+    /// The names of the other workspace members this crate depends on.
+    pub dependencies: Vec<String>,
+}
+
+/// A Rust source file read during a scan.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// The path relative to the scanned root.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Finds all Rust files under `root_path` and reads their contents.
 ///
 /// # Errors
 ///
 /// This function can return an `Error` if directory traversal or file
 /// reading fails.
-pub fn scan_project(root_path: &Path) -> Result<String> {
+pub fn read_sources(root_path: &Path, filter: &ScanFilter) -> Result<Vec<SourceFile>> {
     info!("Starting project scan at '{}'...", root_path.display());
 
-    let rust_files = find_rust_files(root_path)?;
+    let rust_files = find_rust_files(root_path, filter)?;
     let total_files = rust_files.len();
     info!("Found {} Rust source files to process.", total_files);
 
-    if total_files == 0 {
-        return Ok(String::new());
-    }
-
-    let mut combined_context = String::new();
-
+    let mut sources = Vec::with_capacity(total_files);
     for (index, file_path) in rust_files.iter().enumerate() {
         info!(
             "Processing file {}/{}: {}",
@@ -49,31 +57,75 @@ pub fn scan_project(root_path: &Path) -> Result<String> {
             total_files,
             file_path.display()
         );
-        let file_content = fs::read_to_string(file_path)?;
+        sources.push(SourceFile {
+            path: file_path
+                .strip_prefix(root_path)
+                .unwrap_or(file_path)
+                .to_path_buf(),
+            content: fs::read_to_string(file_path)?,
+        });
+    }
 
-        // Create a structured header for each file to provide clear context to the AI.
-        let file_header = format!(
-            "\n\n======================================\n// FILE: {}\n======================================\n\n",
-            file_path.strip_prefix(root_path).unwrap_or(file_path).display()
-        );
+    info!("Project scan complete. All file contents have been read.");
+    Ok(sources)
+}
 
-        combined_context.push_str(&file_header);
-        combined_context.push_str(&file_content);
+/// Consolidates the content of the scanned files into a single string.
+///
+/// Each file's content is prefixed with a clear header indicating its path,
+/// providing essential context for the AI model's analysis.
+pub fn combine_sources(sources: &[SourceFile]) -> String {
+    let mut combined_context = String::new();
+    for source in sources {
+        combined_context.push_str(&file_header(&source.path));
+        combined_context.push_str(&source.content);
     }
+    combined_context
+}
 
-    info!("Project scan complete. All file contents have been aggregated.");
-    Ok(combined_context)
+/// Creates a structured header for a file to provide clear context to the AI.
+pub fn file_header(path: &Path) -> String {
+    format!(
+        "\n\n======================================\n// FILE: {}\n======================================\n\n",
+        path.display()
+    )
+}
+
+/// Restricts which files a scan includes.
+///
+/// Patterns use gitignore syntax and are matched against paths relative to
+/// the scanned root, e.g. `src/**` or `tests/`.
+#[derive(Debug, Clone, Default)]
+pub struct ScanFilter {
+    /// If non-empty, only files matching one of these patterns are scanned.
+    pub include: Vec<String>,
+    /// Files and directories matching these patterns are skipped.
+    pub exclude: Vec<String>,
 }
 
 /// Discovers all Rust files (`.rs`) within a given directory, ignoring common
-/// target and git directories.
-fn find_rust_files(root_path: &Path) -> Result<Vec<PathBuf>> {
+/// target and git directories, files ignored by `.gitignore`, and files
+/// rejected by the `filter`.
+pub fn find_rust_files(root_path: &Path, filter: &ScanFilter) -> Result<Vec<PathBuf>> {
+    let mut overrides = OverrideBuilder::new(root_path);
+    for pattern in &filter.include {
+        overrides.add(pattern)?;
+    }
+    for pattern in &filter.exclude {
+        overrides.add(&format!("!{}", pattern))?;
+    }
+
     let mut files = Vec::new();
 
-    let walker = WalkDir::new(root_path)
-        .into_iter()
+    let walker = WalkBuilder::new(root_path)
+        .overrides(overrides.build()?)
+        // Keep hidden files, but honor .gitignore even outside a git repository.
+        .hidden(false)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
         // Use filter_entry to efficiently prune entire directories.
-        .filter_entry(|e| !is_ignored_dir(e.path()));
+        .filter_entry(|e| !is_ignored_dir(e.path()))
+        .build();
 
     for entry in walker {
         let entry = entry?; // Propagate errors from walking the directory.
@@ -94,3 +146,111 @@ fn is_ignored_dir(path: &Path) -> bool {
         .map(|name| name == "target" || name == ".git")
         .unwrap_or(false)
 }
+
+/// Reads the workspace members declared in the `Cargo.toml` at `root_path`.
+///
+/// Member entries may use glob patterns (e.g., `crates/*`), and entries in
+/// `exclude` are skipped. The dependency edges only include other members.
+///
+/// # Returns
+///
+/// The members sorted by name, or an empty `Vec` if the manifest does not
+/// declare a `[workspace]` with members.
+///
+/// # Errors
+///
+/// Returns an `Error` if a manifest cannot be read or parsed.
+pub fn find_workspace_members(root_path: &Path) -> Result<Vec<WorkspaceMember>> {
+    let manifest = read_manifest(&root_path.join("Cargo.toml"))?;
+    let Some(workspace) = manifest.get("workspace") else {
+        return Ok(Vec::new());
+    };
+
+    let excluded: BTreeSet<PathBuf> = string_array(workspace.get("exclude"))
+        .iter()
+        .map(|entry| root_path.join(entry))
+        .collect();
+
+    let mut member_dirs = BTreeSet::new();
+    for entry in string_array(workspace.get("members")) {
+        let pattern = root_path.join(&entry).display().to_string();
+        let paths = glob::glob(&pattern).map_err(|e| {
+            Error::Config(format!(
+                "Invalid workspace member pattern '{}': {}",
+                entry, e
+            ))
+        })?;
+        for path in paths.flatten() {
+            if path.join("Cargo.toml").is_file() && !excluded.contains(&path) {
+                member_dirs.insert(path);
+            }
+        }
+    }
+
+    // Read each member's name and the names of everything it depends on.
+    let mut members = Vec::new();
+    for dir in member_dirs {
+        let manifest = read_manifest(&dir.join("Cargo.toml"))?;
+        let Some(name) = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+        else {
+            continue;
+        };
+        members.push((name.to_string(), dir, dependency_names(&manifest)));
+    }
+
+    let member_names: BTreeSet<String> = members.iter().map(|(name, _, _)| name.clone()).collect();
+    let mut members: Vec<WorkspaceMember> = members
+        .into_iter()
+        .map(|(name, path, dependencies)| WorkspaceMember {
+            dependencies: dependencies
+                .into_iter()
+                .filter(|dependency| *dependency != name && member_names.contains(dependency))
+                .collect(),
+            name,
+            path,
+        })
+        .collect();
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+
+    info!("Found {} workspace member crates.", members.len());
+    Ok(members)
+}
+
+/// Reads and parses a `Cargo.toml` file.
+fn read_manifest(path: &Path) -> Result<toml::Table> {
+    Ok(fs::read_to_string(path)?.parse()?)
+}
+
+/// Collects the package names of all dependencies, honoring `package = "..."` renames.
+fn dependency_names(manifest: &toml::Table) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for table in DEPENDENCY_TABLES {
+        let Some(dependencies) = manifest.get(table).and_then(|table| table.as_table()) else {
+            continue;
+        };
+        for (key, value) in dependencies {
+            let name = value
+                .get("package")
+                .and_then(|package| package.as_str())
+                .unwrap_or(key);
+            names.insert(name.to_string());
+        }
+    }
+    names
+}
+
+/// Reads an optional array of strings from a manifest value.
+fn string_array(value: Option<&toml::Value>) -> Vec<String> {
+    value
+        .and_then(|value| value.as_array())
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| entry.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
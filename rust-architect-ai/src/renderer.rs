@@ -1 +1,138 @@
-// TODO: Implement the rendering logic for the graphical user interface (GUI) after integrating the layout module. Ensure compatibility with the event handling system.
\ No newline at end of file
+//! src/renderer.rs
+//!
+//! Converts generated diagram syntax into an image (SVG or PNG), so the output
+//! can be viewed without extra tooling. Rendering is delegated to the standard
+//! command-line renderer of each format when it is installed: `mmdc` for
+//! Mermaid, `plantuml` for PlantUML, and `dot` for Graphviz.
+
+use crate::diagram_generator::OutputFormat;
+use crate::error::{Error, Result};
+use clap::ValueEnum;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::info;
+
+/// Defines the image formats a diagram can be rendered to.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    /// The file extension, which is also the format name the renderers accept.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "svg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// Renders `diagram` to an image next to `output`, with the image's extension.
+///
+/// # Returns
+///
+/// The path of the rendered image.
+///
+/// # Errors
+///
+/// Returns `Error::Render` if the renderer for the format is not installed or
+/// rejects the diagram.
+pub async fn render(
+    diagram: &str,
+    diagram_format: OutputFormat,
+    image_format: ImageFormat,
+    output: &Path,
+) -> Result<PathBuf> {
+    let image_path = output.with_extension(image_format.extension());
+    let (program, args) = renderer_command(diagram_format, image_format, &image_path);
+    info!(
+        "Rendering diagram to '{}' with `{}`...",
+        image_path.display(),
+        program
+    );
+
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => Error::Render(format!(
+                "`{}` was not found on the PATH. {}",
+                program,
+                install_hint(diagram_format)
+            )),
+            _ => Error::Io(e),
+        })?;
+
+    // The diagram is passed on stdin, so no intermediate file is needed.
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(diagram.as_bytes()).await?;
+    }
+    let result = child.wait_with_output().await?;
+    if !result.status.success() {
+        return Err(Error::Render(format!(
+            "`{}` failed: {}",
+            program,
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+
+    // PlantUML writes the image to stdout in pipe mode.
+    if diagram_format == OutputFormat::Plantuml {
+        tokio::fs::write(&image_path, &result.stdout).await?;
+    }
+    Ok(image_path)
+}
+
+/// Returns the program and arguments that render a diagram read from stdin.
+fn renderer_command(
+    diagram_format: OutputFormat,
+    image_format: ImageFormat,
+    image_path: &Path,
+) -> (&'static str, Vec<String>) {
+    let extension = image_format.extension();
+    let image_path = image_path.display().to_string();
+    match diagram_format {
+        OutputFormat::Mermaid => (
+            "mmdc",
+            vec![
+                "--input".to_string(),
+                "-".to_string(),
+                "--output".to_string(),
+                image_path,
+                "--outputFormat".to_string(),
+                extension.to_string(),
+            ],
+        ),
+        OutputFormat::Plantuml => (
+            "plantuml",
+            vec![format!("-t{}", extension), "-pipe".to_string()],
+        ),
+        OutputFormat::Dot => (
+            "dot",
+            vec![format!("-T{}", extension), "-o".to_string(), image_path],
+        ),
+    }
+}
+
+/// Explains how to install the renderer for a format.
+fn install_hint(diagram_format: OutputFormat) -> &'static str {
+    match diagram_format {
+        OutputFormat::Mermaid => {
+            "Install the Mermaid CLI with `npm install -g @mermaid-js/mermaid-cli`."
+        }
+        OutputFormat::Plantuml => {
+            "Install PlantUML (e.g., `brew install plantuml` or `apt install plantuml`)."
+        }
+        OutputFormat::Dot => {
+            "Install Graphviz (e.g., `brew install graphviz` or `apt install graphviz`)."
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! src/summarizer.rs
+//!
+//! Implements hierarchical summarization for codebases too large to send to
+//! the model in one request. Each file is first summarized on its own; if the
+//! file summaries are still too large, the summaries of each directory are
+//! condensed into module summaries. The diagram is then generated from the
+//! summaries instead of the raw source.
+
+use crate::cache::ResponseCache;
+use crate::error::{Error, Result};
+use crate::openai_client::OpenAIClient;
+use crate::project_scanner::{self, SourceFile};
+use ai_core::{count_tokens, truncate_to_tokens};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::info;
+
+/// The largest context, in tokens, sent to the model in one request,
+/// leaving room for the instructions and the response.
+pub const MAX_CONTEXT_TOKENS: usize = 100_000;
+
+/// The number of summary requests in flight at once.
+const MAX_CONCURRENT_SUMMARIES: usize = 4;
+
+/// Defines when the project is summarized before generating a diagram.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummarizeMode {
+    /// Summarize only when the raw source exceeds the context limit.
+    Auto,
+    /// Always generate the diagram from summaries.
+    Always,
+    /// Always send the raw source.
+    Never,
+}
+
+impl SummarizeMode {
+    /// Whether a project whose raw context has `context_tokens` tokens is summarized.
+    pub fn applies_to(&self, context_tokens: usize) -> bool {
+        match self {
+            SummarizeMode::Auto => context_tokens > MAX_CONTEXT_TOKENS,
+            SummarizeMode::Always => true,
+            SummarizeMode::Never => false,
+        }
+    }
+}
+
+/// Condenses source files into summaries that fit in the model's context.
+pub struct Summarizer {
+    client: OpenAIClient,
+    cache: Option<ResponseCache>,
+}
+
+impl Summarizer {
+    /// Creates a new instance of the `Summarizer`.
+    pub fn new(client: OpenAIClient) -> Self {
+        Self {
+            client,
+            cache: None,
+        }
+    }
+
+    /// The model the summaries are requested from, whose tokenizer sizes the
+    /// context.
+    pub fn model(&self) -> &str {
+        self.client.model()
+    }
+
+    /// Reuses summaries from `cache` for unchanged files and stores new ones in it.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Builds the project context from summaries instead of raw source.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error` if any summary request fails.
+    pub async fn summarize_project(&self, sources: &[SourceFile]) -> Result<String> {
+        info!("Phase 1: summarizing {} files...", sources.len());
+        let requests = sources
+            .iter()
+            .map(|source| {
+                (
+                    source.path.clone(),
+                    file_summary_prompt(self.model(), &source.path, &source.content),
+                )
+            })
+            .collect();
+        let mut summaries = self.run_all(requests).await?;
+
+        let total_tokens: usize = summaries
+            .iter()
+            .map(|(_, summary)| count_tokens(self.model(), summary))
+            .sum();
+        if total_tokens > MAX_CONTEXT_TOKENS {
+            info!("File summaries are still too large; summarizing each module...");
+            summaries = self.summarize_modules(summaries).await?;
+        }
+
+        info!("Phase 2: generating the diagram from the summaries.");
+        let mut context = "NOTE: The project is too large to include in full. Each entry below is a summary of a file or module, not its source code.".to_string();
+        for (path, summary) in &summaries {
+            context.push_str(&project_scanner::file_header(path));
+            context.push_str(summary);
+        }
+        Ok(context)
+    }
+
+    /// Condenses the file summaries of each directory into one module summary.
+    async fn summarize_modules(
+        &self,
+        summaries: Vec<(PathBuf, String)>,
+    ) -> Result<Vec<(PathBuf, String)>> {
+        let mut modules: BTreeMap<PathBuf, Vec<(PathBuf, String)>> = BTreeMap::new();
+        for (path, summary) in summaries {
+            let directory = path.parent().map(PathBuf::from).unwrap_or_default();
+            modules.entry(directory).or_default().push((path, summary));
+        }
+        let requests = modules
+            .into_iter()
+            .map(|(directory, files)| {
+                let prompt = module_summary_prompt(&directory, &files);
+                (directory, prompt)
+            })
+            .collect();
+        self.run_all(requests).await
+    }
+
+    /// Sends the summary requests concurrently, keeping their order.
+    ///
+    /// Requests whose prompt is unchanged since a previous run are answered
+    /// from the cache.
+    async fn run_all(&self, requests: Vec<(PathBuf, String)>) -> Result<Vec<(PathBuf, String)>> {
+        let total = requests.len();
+        let mut results: Vec<Option<String>> = vec![None; total];
+        let mut paths = Vec::with_capacity(total);
+        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_SUMMARIES));
+        let mut tasks = JoinSet::new();
+        let mut cached = 0;
+
+        for (index, (path, prompt)) in requests.into_iter().enumerate() {
+            paths.push(path.clone());
+            let key = ResponseCache::key(self.client.model(), &prompt);
+            if let Some(summary) = self.cache.as_ref().and_then(|cache| cache.get(&key)) {
+                results[index] = Some(summary);
+                cached += 1;
+                continue;
+            }
+
+            let client = self.client.clone();
+            let semaphore = Arc::clone(&semaphore);
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                info!("Summarizing {}/{}: {}", index + 1, total, path.display());
+                let summary = client.send_request(prompt).await;
+                (index, key, summary)
+            });
+        }
+        if cached > 0 {
+            info!("Reused {} of {} summaries from the cache.", cached, total);
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            let (index, key, summary) =
+                joined.map_err(|e| Error::OpenAI(format!("Summary task failed: {}", e)))?;
+            let summary = summary?.trim().to_string();
+            if let Some(cache) = &self.cache {
+                cache.put_or_warn(&key, &summary);
+            }
+            results[index] = Some(summary);
+        }
+
+        Ok(paths
+            .into_iter()
+            .zip(results)
+            .map(|(path, summary)| (path, summary.unwrap_or_default()))
+            .collect())
+    }
+}
+
+/// Asks for the architectural summary of one file.
+fn file_summary_prompt(model: &str, path: &Path, content: &str) -> String {
+    // A single file larger than the context is cut; its beginning usually
+    // holds the declarations that matter most for the architecture.
+    let content = truncate_to_tokens(model, content, MAX_CONTEXT_TOKENS);
+    format!(
+        "You are an expert software architect with deep knowledge of Rust. Summarize the Rust file `{}` for someone drawing an architectural diagram of the project. In at most 150 words, state: the file's responsibility; its main structs, enums, traits, and public functions and what they do; which other modules and crates it uses; and how data flows through it. Output only the summary.\n\n```rust\n{}\n```",
+        path.display(),
+        content
+    )
+}
+
+/// Asks for one summary of a directory, given the summaries of its files.
+fn module_summary_prompt(directory: &Path, files: &[(PathBuf, String)]) -> String {
+    let summaries = files
+        .iter()
+        .map(|(path, summary)| format!("### {}\n{}", path.display(), summary))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        "You are an expert software architect with deep knowledge of Rust. Below are summaries of the files in the directory `{}`. Condense them into a single summary of the module in at most 250 words, keeping the main types, their responsibilities, and the dependencies on other modules. Output only the summary.\n\n{}",
+        directory.display(),
+        summaries
+    )
+}
@@ -0,0 +1,309 @@
+//! src/trimmer.rs
+//!
+//! Fits the project's source into a token budget before it is sent to the
+//! model. Trimming strategies are applied in order, each removing content of
+//! less architectural value than the next (tests, comments, private items,
+//! function bodies, and finally whole non-root files), and trimming stops as
+//! soon as the source fits. Each strategy implements `TrimStrategy`, so new
+//! ones can be added without changing the pipeline.
+
+use crate::dependency_graph::is_cfg_test;
+use crate::project_scanner::{self, SourceFile};
+use ai_core::count_tokens;
+use clap::ValueEnum;
+use std::collections::BTreeSet;
+use std::path::Path;
+use syn::visit_mut::{self, VisitMut};
+use tracing::{info, warn};
+
+/// The file names that declare a module tree and are kept by `module-roots`.
+const MODULE_ROOT_FILES: [&str; 3] = ["main.rs", "lib.rs", "mod.rs"];
+
+/// The directories whose files are dropped by `drop-tests`.
+const TEST_DIRS: [&str; 3] = ["tests", "benches", "examples"];
+
+/// Removes content from the source to fit it into a token budget.
+pub trait TrimStrategy {
+    /// The name logged when the strategy is applied.
+    fn name(&self) -> &'static str;
+
+    /// Trims `sources`. `budget` is the token budget the whole source must
+    /// fit in, counted with the tokenizer of `model`.
+    fn trim(&self, sources: Vec<SourceFile>, model: &str, budget: usize) -> Vec<SourceFile>;
+}
+
+/// The built-in trimming strategies, selectable with `--trim-strategy`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrimStrategyKind {
+    /// Drop test, benchmark, and example files and `#[cfg(test)]` modules.
+    DropTests,
+    /// Remove comments, keeping doc comments.
+    StripComments,
+    /// Remove private functions, constants, statics, and macros.
+    PublicItems,
+    /// Replace function bodies with empty blocks, keeping signatures.
+    StripBodies,
+    /// Drop whole files, largest first, keeping `main.rs`, `lib.rs`, and `mod.rs`.
+    ModuleRoots,
+}
+
+impl TrimStrategyKind {
+    /// The default pipeline, from the least to the most architecturally relevant content.
+    pub const DEFAULT: [TrimStrategyKind; 5] = [
+        TrimStrategyKind::DropTests,
+        TrimStrategyKind::StripComments,
+        TrimStrategyKind::PublicItems,
+        TrimStrategyKind::StripBodies,
+        TrimStrategyKind::ModuleRoots,
+    ];
+
+    /// Creates the strategy.
+    pub fn strategy(&self) -> Box<dyn TrimStrategy> {
+        match self {
+            TrimStrategyKind::DropTests => Box::new(DropTests),
+            TrimStrategyKind::StripComments => Box::new(StripComments),
+            TrimStrategyKind::PublicItems => Box::new(PublicItems),
+            TrimStrategyKind::StripBodies => Box::new(StripBodies),
+            TrimStrategyKind::ModuleRoots => Box::new(ModuleRoots),
+        }
+    }
+}
+
+/// Counts the tokens the combined sources take up for `model`.
+fn total_tokens(sources: &[SourceFile], model: &str) -> usize {
+    count_tokens(model, &project_scanner::combine_sources(sources))
+}
+
+/// Applies `strategies` in order until the sources fit in `budget` tokens of
+/// `model`.
+///
+/// If every strategy has been applied and the sources are still too large,
+/// they are returned as trimmed as possible and a warning is logged.
+pub fn trim_to_budget(
+    mut sources: Vec<SourceFile>,
+    model: &str,
+    budget: usize,
+    strategies: &[Box<dyn TrimStrategy>],
+) -> Vec<SourceFile> {
+    let mut tokens = total_tokens(&sources, model);
+    for strategy in strategies {
+        if tokens <= budget {
+            break;
+        }
+        sources = strategy.trim(sources, model, budget);
+        let trimmed = total_tokens(&sources, model);
+        info!(
+            "Trimming with '{}': {} -> {} tokens (budget {}).",
+            strategy.name(),
+            tokens,
+            trimmed,
+            budget
+        );
+        tokens = trimmed;
+    }
+    if tokens > budget {
+        warn!(
+            "The source still takes {} tokens after trimming, over the budget of {}.",
+            tokens, budget
+        );
+    }
+    sources
+}
+
+/// Rewrites every file that parses with `rewrite`, then prints it back.
+///
+/// Printing drops all regular comments. Files that do not parse are kept as they are.
+fn rewrite_files(sources: Vec<SourceFile>, rewrite: impl Fn(&mut syn::File)) -> Vec<SourceFile> {
+    sources
+        .into_iter()
+        .map(|source| rewrite_file(source, &rewrite))
+        .collect()
+}
+
+fn rewrite_file(source: SourceFile, rewrite: &impl Fn(&mut syn::File)) -> SourceFile {
+    match syn::parse_file(&source.content) {
+        Ok(mut file) => {
+            rewrite(&mut file);
+            SourceFile {
+                content: prettyplease::unparse(&file),
+                ..source
+            }
+        }
+        Err(_) => source,
+    }
+}
+
+struct DropTests;
+
+impl TrimStrategy for DropTests {
+    fn name(&self) -> &'static str {
+        "drop-tests"
+    }
+
+    fn trim(&self, sources: Vec<SourceFile>, _model: &str, _budget: usize) -> Vec<SourceFile> {
+        let remove_test_modules = |file: &mut syn::File| {
+            file.items.retain(|item| !is_test_module(item));
+        };
+        sources
+            .into_iter()
+            .filter(|source| !is_test_file(&source.path))
+            // Only files with test modules are rewritten, so the others keep their comments.
+            .map(|source| {
+                if source.content.contains("cfg(test)") {
+                    rewrite_file(source, &remove_test_modules)
+                } else {
+                    source
+                }
+            })
+            .collect()
+    }
+}
+
+/// Whether a file holds tests, benchmarks, or examples rather than library code.
+fn is_test_file(path: &Path) -> bool {
+    path.components()
+        .any(|component| TEST_DIRS.iter().any(|dir| component.as_os_str() == *dir))
+        || path.file_stem().is_some_and(|stem| {
+            let stem = stem.to_string_lossy();
+            stem == "tests" || stem.ends_with("_test") || stem.ends_with("_tests")
+        })
+}
+
+fn is_test_module(item: &syn::Item) -> bool {
+    matches!(item, syn::Item::Mod(module) if is_cfg_test(&module.attrs))
+}
+
+struct StripComments;
+
+impl TrimStrategy for StripComments {
+    fn name(&self) -> &'static str {
+        "strip-comments"
+    }
+
+    fn trim(&self, sources: Vec<SourceFile>, _model: &str, _budget: usize) -> Vec<SourceFile> {
+        // Parsing and printing the file is enough to drop its comments.
+        rewrite_files(sources, |_| {})
+    }
+}
+
+struct PublicItems;
+
+impl TrimStrategy for PublicItems {
+    fn name(&self) -> &'static str {
+        "public-items"
+    }
+
+    fn trim(&self, sources: Vec<SourceFile>, _model: &str, _budget: usize) -> Vec<SourceFile> {
+        rewrite_files(sources, |file| PrivateItemRemover.visit_file_mut(file))
+    }
+}
+
+/// Removes private functions, constants, statics, and macros, including
+/// private methods of inherent impls. Types, traits, and `use` declarations
+/// are kept, since they carry the relationships between components.
+struct PrivateItemRemover;
+
+impl VisitMut for PrivateItemRemover {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        file.items.retain(is_kept_item);
+        visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut module.content {
+            items.retain(is_kept_item);
+        }
+        visit_mut::visit_item_mod_mut(self, module);
+    }
+
+    fn visit_item_impl_mut(&mut self, item: &mut syn::ItemImpl) {
+        // Trait impls have no visibility; their methods are part of the trait.
+        if item.trait_.is_none() {
+            item.items.retain(|item| match item {
+                syn::ImplItem::Fn(method) => is_public(&method.vis),
+                syn::ImplItem::Const(constant) => is_public(&constant.vis),
+                _ => true,
+            });
+        }
+    }
+}
+
+fn is_kept_item(item: &syn::Item) -> bool {
+    match item {
+        syn::Item::Fn(function) => is_public(&function.vis) || function.sig.ident == "main",
+        syn::Item::Const(constant) => is_public(&constant.vis),
+        syn::Item::Static(item_static) => is_public(&item_static.vis),
+        syn::Item::Macro(_) => false,
+        _ => true,
+    }
+}
+
+fn is_public(vis: &syn::Visibility) -> bool {
+    !matches!(vis, syn::Visibility::Inherited)
+}
+
+struct StripBodies;
+
+impl TrimStrategy for StripBodies {
+    fn name(&self) -> &'static str {
+        "strip-bodies"
+    }
+
+    fn trim(&self, sources: Vec<SourceFile>, _model: &str, _budget: usize) -> Vec<SourceFile> {
+        rewrite_files(sources, |file| BodyStripper.visit_file_mut(file))
+    }
+}
+
+/// Replaces the bodies of functions and methods with empty blocks.
+struct BodyStripper;
+
+impl VisitMut for BodyStripper {
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block.stmts.clear();
+    }
+}
+
+struct ModuleRoots;
+
+impl TrimStrategy for ModuleRoots {
+    fn name(&self) -> &'static str {
+        "module-roots"
+    }
+
+    fn trim(&self, sources: Vec<SourceFile>, model: &str, budget: usize) -> Vec<SourceFile> {
+        // Drop the largest leaf files first, as they cost the most budget.
+        let mut leaves: Vec<(usize, usize)> = sources
+            .iter()
+            .enumerate()
+            .filter(|(_, source)| !is_module_root(&source.path))
+            .map(|(index, source)| {
+                let header = project_scanner::file_header(&source.path);
+                let tokens = count_tokens(model, &header) + count_tokens(model, &source.content);
+                (index, tokens)
+            })
+            .collect();
+        leaves.sort_by_key(|leaf| std::cmp::Reverse(leaf.1));
+
+        let mut tokens = total_tokens(&sources, model);
+        let mut dropped = BTreeSet::new();
+        for (index, file_tokens) in leaves {
+            if tokens <= budget {
+                break;
+            }
+            tokens = tokens.saturating_sub(file_tokens);
+            dropped.insert(index);
+        }
+
+        sources
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !dropped.contains(index))
+            .map(|(_, source)| source)
+            .collect()
+    }
+}
+
+fn is_module_root(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| MODULE_ROOT_FILES.iter().any(|root| name == *root))
+}
@@ -0,0 +1,84 @@
+//! src/watcher.rs
+//!
+//! Implements the file watching behind `--watch`. Changes under the project
+//! are debounced, so that saving several files or a burst of editor writes
+//! triggers a single regeneration, and only changes to Rust sources and
+//! manifests are reported. Generated outputs are therefore never picked up
+//! as changes, even when they are written inside the project.
+
+use crate::error::Result;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// The default time, in milliseconds, that the project must be quiet before
+/// a regeneration starts.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+/// Directories whose changes never affect the diagrams.
+const IGNORED_DIRS: [&str; 2] = ["target", ".git"];
+
+/// Reports debounced changes to the Rust sources and manifests of a project.
+pub struct ChangeWatcher {
+    /// Kept alive for as long as changes are watched.
+    _debouncer: Debouncer<RecommendedWatcher>,
+    events: mpsc::UnboundedReceiver<DebounceEventResult>,
+}
+
+impl ChangeWatcher {
+    /// Starts watching `root` recursively, reporting changes once no new ones
+    /// have arrived for `debounce`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file system watcher cannot be started.
+    pub fn new(root: &Path, debounce: Duration) -> Result<Self> {
+        let (sender, events) = mpsc::unbounded_channel();
+        let mut debouncer = new_debouncer(debounce, move |result: DebounceEventResult| {
+            // The receiver is only gone once watching has stopped.
+            let _ = sender.send(result);
+        })?;
+        debouncer.watcher().watch(root, RecursiveMode::Recursive)?;
+        Ok(Self {
+            _debouncer: debouncer,
+            events,
+        })
+    }
+
+    /// Waits for the next batch of relevant changes.
+    ///
+    /// # Returns
+    ///
+    /// The changed files, or `None` if the watcher has stopped.
+    pub async fn next_change(&mut self) -> Option<Vec<PathBuf>> {
+        loop {
+            match self.events.recv().await? {
+                Ok(events) => {
+                    let changed: BTreeSet<PathBuf> = events
+                        .into_iter()
+                        .map(|event| event.path)
+                        .filter(|path| is_relevant(path))
+                        .collect();
+                    if !changed.is_empty() {
+                        return Some(changed.into_iter().collect());
+                    }
+                }
+                Err(e) => warn!("File watcher error: {}", e),
+            }
+        }
+    }
+}
+
+/// Whether a change to `path` can affect the generated diagrams.
+fn is_relevant(path: &Path) -> bool {
+    let in_ignored_dir = path
+        .components()
+        .any(|component| IGNORED_DIRS.iter().any(|dir| component.as_os_str() == *dir));
+    let is_source = path.extension().is_some_and(|extension| extension == "rs")
+        || path.file_name().is_some_and(|name| name == "Cargo.toml");
+    !in_ignored_dir && is_source
+}
@@ -0,0 +1,133 @@
+//! tests/generate_diagram.rs
+//!
+//! Generates diagrams of a one-file project through the mock provider: the
+//! recorded component diagram and its repair round, the prompt of a
+//! sequence diagram, and the extraction of PlantUML and DOT replies.
+
+use ai_core::{MockClient, ProviderConfig};
+use rust_architect_ai::diagram_generator::{DiagramGenerator, DiagramType, OutputFormat};
+use rust_architect_ai::error::Error;
+use rust_architect_ai::mermaid;
+use rust_architect_ai::openai_client::OpenAIClient;
+use rust_architect_ai::project_scanner::{self, SourceFile};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const MAIN_RS: &str = r#"mod diagram_generator;
+mod project_scanner;
+
+use diagram_generator::DiagramGenerator;
+
+#[tokio::main]
+async fn main() {
+    let sources = project_scanner::scan("src").unwrap();
+    let diagram = DiagramGenerator::new().generate(&sources).await.unwrap();
+    println!("{}", diagram);
+}
+"#;
+
+/// The context of a project whose only file is `MAIN_RS`.
+fn one_file_project() -> String {
+    project_scanner::combine_sources(&[SourceFile {
+        path: PathBuf::from("src/main.rs"),
+        content: MAIN_RS.to_string(),
+    }])
+}
+
+#[tokio::test]
+async fn repairs_the_recorded_component_diagram() {
+    let mock = Arc::new(
+        MockClient::fixture(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/component_diagram.json"
+        ))
+        .unwrap(),
+    );
+    let generator =
+        DiagramGenerator::new(OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap());
+
+    let diagram = generator
+        .generate_diagram(
+            &one_file_project(),
+            DiagramType::Component,
+            None,
+            OutputFormat::Mermaid,
+        )
+        .await
+        .unwrap();
+
+    assert!(mermaid::validate(&diagram).is_ok());
+    assert!(diagram.contains(r#"Generator["DiagramGenerator::generate (async)"]"#));
+    // The first reply put parentheses in an unquoted label, which the repair
+    // round is told about by line.
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    let repair_prompt = &requests[1].messages[0].content;
+    assert!(repair_prompt.starts_with("The following Mermaid diagram has a syntax error at line 3"));
+    assert!(repair_prompt.contains("Generator[DiagramGenerator::generate (async)]"));
+}
+
+#[tokio::test]
+async fn asks_for_a_sequence_diagram_from_the_entry_function() {
+    let mock = Arc::new(MockClient::new([
+        "```mermaid\nsequenceDiagram\n    main->>project_scanner: scan(\"src\")\n```",
+    ]));
+    let generator =
+        DiagramGenerator::new(OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap());
+
+    let diagram = generator
+        .generate_diagram(
+            &one_file_project(),
+            DiagramType::Sequence,
+            Some("main".to_string()),
+            OutputFormat::Mermaid,
+        )
+        .await
+        .unwrap();
+    assert!(diagram.starts_with("sequenceDiagram"));
+
+    let prompt = &mock.requests()[0].messages[0].content;
+    assert!(prompt.contains("starting from the public function `main`"));
+    assert!(prompt.contains("The output must be a Mermaid `sequenceDiagram`."));
+    assert!(prompt.contains("enclosed in a ```mermaid code block"));
+    assert!(prompt.contains("START OF CODEBASE CONTEXT\n---\n"));
+    assert!(prompt.contains("let sources = project_scanner::scan(\"src\").unwrap();"));
+    assert!(prompt.ends_with("---\nEND OF CODEBASE CONTEXT"));
+}
+
+#[tokio::test]
+async fn finds_plantuml_between_its_markers_in_prose() {
+    let mock = MockClient::new(["Sure!\n@startuml\n[main] --> [scanner]\n@enduml\nDone."]);
+    let generator =
+        DiagramGenerator::new(OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap());
+
+    let diagram = generator
+        .generate_diagram(
+            &one_file_project(),
+            DiagramType::Component,
+            None,
+            OutputFormat::Plantuml,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(diagram, "@startuml\n[main] --> [scanner]\n@enduml");
+}
+
+#[tokio::test]
+async fn fails_when_the_reply_has_no_dot_graph() {
+    let mock = MockClient::new(["I can't draw this codebase."]);
+    let generator =
+        DiagramGenerator::new(OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap());
+
+    let result = generator
+        .generate_diagram(
+            &one_file_project(),
+            DiagramType::Class,
+            None,
+            OutputFormat::Dot,
+        )
+        .await;
+
+    assert!(matches!(result, Err(Error::OpenAI(_))));
+}
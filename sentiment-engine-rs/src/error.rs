@@ -17,21 +17,14 @@ pub enum Error {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
-    /// Wraps errors from the `reqwest` HTTP client.
-    #[error("HTTP request error")]
-    Reqwest(#[from] reqwest::Error),
-
-    /// For errors returned specifically by the OpenAI API.
-    #[error("OpenAI API error: {0}")]
-    OpenAI(String),
+    /// Wraps errors from requests to the OpenAI API, made with the shared
+    /// `ai-core` client.
+    #[error(transparent)]
+    Client(#[from] ai_core::Error),
 
     /// For errors during JSON serialization or deserialization.
     #[error("JSON processing error: {0}")]
     SerdeJson(#[from] serde_json::Error),
-
-    /// For when the AI's response is not in the expected format.
-    #[error("Invalid response format from AI: {0}")]
-    InvalidResponseFormat(String),
 }
 
 /// A convenient type alias for `Result<T, E>` using our custom `Error` type.
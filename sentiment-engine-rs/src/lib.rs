@@ -0,0 +1,12 @@
+//! src/lib.rs
+//!
+//! The library behind the Explainable Sentiment Analysis Engine. The binary
+//! in `main.rs` parses the command line and prints the results; the modules
+//! here load the labels, talk to the model, and read its analysis, and are
+//! exercised directly by the tests in `tests/`.
+
+pub mod config;
+pub mod constants;
+pub mod error;
+pub mod openai_client;
+pub mod sentiment_analyzer;
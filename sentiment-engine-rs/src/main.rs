@@ -4,18 +4,15 @@
 //! This module ties all other components together to form a cohesive
 //! command-line application.
 
-// Declare the module hierarchy for the compiler.
-mod config;
-mod constants;
-mod error;
+// The logger is only set up by the binary; everything else is in the library.
 mod logger;
-mod openai_client;
-mod sentiment_analyzer;
 
-use crate::error::Result;
+use ai_core::ProviderConfig;
 use clap::Parser;
 use colored::Colorize;
-use sentiment_analyzer::{AnalysisResult, SentimentAnalyzer};
+use sentiment_engine_rs::error::Result;
+use sentiment_engine_rs::sentiment_analyzer::{AnalysisResult, SentimentAnalyzer};
+use sentiment_engine_rs::{config, openai_client};
 use tracing::{error, info};
 
 /// Defines the command-line arguments accepted by the application.
@@ -30,6 +27,15 @@ struct Args {
     /// The text to analyze for sentiment.
     #[arg(required = true)]
     text: String,
+
+    /// Abort once the run has cost this many US dollars (default: AI_MAX_COST).
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Reuse the cached analysis of the same text, if there is one, instead
+    /// of asking the model again. `AI_CACHE=1` does the same.
+    #[arg(long)]
+    cache: bool,
 }
 
 /// The main asynchronous function that orchestrates the application.
@@ -60,9 +66,12 @@ async fn run() -> Result<()> {
     let sentiment_config = config::load()?;
     info!("Successfully loaded {} sentiment labels.", sentiment_config.labels.len());
 
-    // Load the OpenAI API key from the environment.
-    let api_key = load_api_key()?;
-    let openai_client = openai_client::OpenAIClient::new(api_key);
+    // Load the model provider settings from the environment, recording the
+    // usage of the run in the shared ledger and caching the replies if asked.
+    let provider_config = load_provider_config()?
+        .track_usage(env!("CARGO_PKG_NAME"), args.max_cost)
+        .cache_responses(args.cache);
+    let openai_client = openai_client::OpenAIClient::new(&provider_config)?;
 
     // Create the analyzer instance.
     let analyzer = SentimentAnalyzer::new(openai_client, sentiment_config);
@@ -78,11 +87,11 @@ async fn run() -> Result<()> {
     Ok(())
 }
 
-/// Loads the OpenAI API key from the environment variables.
-fn load_api_key() -> Result<String> {
+/// Loads the model provider and its settings, such as the OpenAI API key,
+/// from the environment variables.
+fn load_provider_config() -> Result<ProviderConfig> {
     dotenvy::dotenv().ok();
-    std::env::var("OPENAI_API_KEY")
-        .map_err(|_| crate::error::Error::Config("OPENAI_API_KEY not found in environment.".to_string()))
+    Ok(ProviderConfig::from_env()?)
 }
 
 /// Prints the final analysis results to the console.
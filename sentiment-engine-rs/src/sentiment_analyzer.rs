@@ -4,30 +4,26 @@
 //! interacts with the OpenAI client, and parses the structured response.
 
 use crate::config::SentimentConfig;
-use crate::error::{Error, Result};
+use crate::error::Result;
 use crate::openai_client::OpenAIClient;
-use once_cell::sync::Lazy;
-use regex::Regex;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use tracing::info;
 
-/// A lazily-compiled regular expression to robustly extract a JSON object
-/// from within a Markdown code block.
-/// The `(?s)` flag allows `.` to match newlines.
-static JSON_EXTRACTOR: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?s)```(?:json)?\s*(\{.*\})\s*```").unwrap());
-
 /// The main analyzer struct, holding the necessary components for analysis.
 pub struct SentimentAnalyzer {
     client: OpenAIClient,
     config: SentimentConfig,
 }
 
-/// The structure of the JSON object we expect to receive from the AI.
-#[derive(Deserialize, Debug)]
+/// The structure of the JSON object we expect to receive from the AI. Its
+/// JSON schema is sent with the request.
+#[derive(Deserialize, JsonSchema, Debug)]
 pub struct AnalysisResult {
+    /// The step-by-step reasoning behind the classification.
     #[serde(rename = "chainOfThought")]
     pub chain_of_thought: String,
+    /// The name of the sentiment label that best describes the text.
     pub sentiment: String,
 }
 
@@ -39,8 +35,8 @@ impl SentimentAnalyzer {
 
     /// Analyzes the provided text to determine its sentiment.
     ///
-    /// This function builds a detailed prompt, sends it to the OpenAI API,
-    /// and parses the resulting JSON object into an `AnalysisResult`.
+    /// This function builds a detailed prompt, sends it to the OpenAI API
+    /// with the JSON schema of `AnalysisResult`, and reads the reply into one.
     ///
     /// # Arguments
     ///
@@ -56,24 +52,11 @@ impl SentimentAnalyzer {
         let prompt = self.build_prompt(text_to_analyze);
         info!(prompt = %prompt, "Constructed analysis prompt.");
 
-        // Send the request to the OpenAI client.
-        let response_text = self.client.send_request(prompt).await?;
-        info!(response = %response_text, "Received response from API.");
-
-        // Use the robust regex-based method to extract the JSON payload.
-        // If the regex does not find a match, or if the AI simply returns raw JSON,
-        // we fall back to parsing the raw text.
-        let json_text = JSON_EXTRACTOR
-            .captures(&response_text)
-            .and_then(|caps| caps.get(1).map(|m| m.as_str()))
-            .unwrap_or(&response_text);
+        // Send the request to the OpenAI client, which reads the reply.
+        let result: AnalysisResult = self.client.send_request(prompt).await?;
+        info!(sentiment = %result.sentiment, "Received analysis from API.");
 
-        serde_json::from_str(json_text.trim()).map_err(|e| {
-            Error::InvalidResponseFormat(format!(
-                "Failed to parse JSON response: {}. Response text: '{}'",
-                e, response_text
-            ))
-        })
+        Ok(result)
     }
 
     /// Constructs the detailed prompt for the AI model.
@@ -0,0 +1,87 @@
+//! tests/analyze.rs
+//!
+//! Runs the sentiment analyzer against the mock provider: the recorded
+//! analysis of an urgent support message, and scripted replies for the
+//! prompt built from a custom label set and for malformed analyses.
+
+use ai_core::{MockClient, ProviderConfig, ResponseFormat};
+use sentiment_engine_rs::config::{self, SentimentConfig, SentimentLabel};
+use sentiment_engine_rs::error::Error;
+use sentiment_engine_rs::openai_client::OpenAIClient;
+use sentiment_engine_rs::sentiment_analyzer::SentimentAnalyzer;
+use std::sync::Arc;
+
+const URGENT_MESSAGE: &str = "The update broke my export again and support hasn't answered in \
+                              three days. I need this fixed before Friday's board meeting!";
+
+#[tokio::test]
+async fn classifies_the_urgent_message_as_recorded() {
+    let mock = MockClient::fixture(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/analyze_urgent.json"
+    ))
+    .unwrap();
+    let client = OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap();
+    let analyzer = SentimentAnalyzer::new(client, config::load().unwrap());
+
+    let result = analyzer.analyze(URGENT_MESSAGE).await.unwrap();
+
+    assert_eq!(result.sentiment, "Urgent");
+    assert!(result.chain_of_thought.contains("deadline"));
+}
+
+#[tokio::test]
+async fn prompts_with_the_configured_labels_and_the_quoted_text() {
+    let mock = Arc::new(MockClient::new([
+        r#"{"chainOfThought": "Praise that means the opposite.", "sentiment": "Sarcastic"}"#,
+    ]));
+    let labels = SentimentConfig {
+        labels: vec![
+            SentimentLabel {
+                name: "Sarcastic".to_string(),
+                description: "Says the opposite of what it means.".to_string(),
+            },
+            SentimentLabel {
+                name: "Sincere".to_string(),
+                description: "Means what it says.".to_string(),
+            },
+        ],
+    };
+    let client = OpenAIClient::new(&ProviderConfig::mock(mock.clone())).unwrap();
+    let result = SentimentAnalyzer::new(client, labels)
+        .analyze("Oh great, another Monday.")
+        .await
+        .unwrap();
+    assert_eq!(result.sentiment, "Sarcastic");
+
+    let requests = mock.requests();
+    let [request] = requests.as_slice() else {
+        panic!("expected a single request, got {}", requests.len());
+    };
+    let prompt = &request.messages[0].content;
+    assert!(prompt.contains(
+        "- \"Sarcastic\": Says the opposite of what it means.\n- \"Sincere\": Means what it says."
+    ));
+    assert!(!prompt.contains("\"Positive\""));
+    assert!(prompt.contains("\"\"\"\n            Oh great, another Monday.\n            \"\"\""));
+
+    let Some(ResponseFormat::JsonSchema { json_schema }) = &request.response_format else {
+        panic!("the analysis is not requested with a JSON schema");
+    };
+    assert_eq!(json_schema.name, "AnalysisResult");
+    assert_eq!(
+        json_schema.schema["required"],
+        serde_json::json!(["chainOfThought", "sentiment"])
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_analysis_without_reasoning() {
+    let mock = MockClient::new([r#"{"sentiment": "Positive"}"#]);
+    let client = OpenAIClient::new(&ProviderConfig::mock(Arc::new(mock))).unwrap();
+    let analyzer = SentimentAnalyzer::new(client, config::load().unwrap());
+
+    let result = analyzer.analyze("I love it.").await;
+
+    assert!(matches!(result, Err(Error::Client(ai_core::Error::Api(_)))));
+}